@@ -0,0 +1,35 @@
+use std::io;
+
+use bytes::BytesMut;
+
+/// Decodes frames from the bytes read by [`Framed`](crate::Framed).
+pub trait Decoder {
+    /// The type of decoded frames.
+    type Item;
+
+    /// Decodes one frame from `src`, returns `None` if more bytes are required.
+    ///
+    /// The consumed bytes of the decoded frame should be removed from `src`.
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>>;
+
+    /// Decodes one frame when the underlying io reaches EOF, returns `None` if there is no more frame.
+    ///
+    /// The default implementation calls [`decode`](Self::decode), and returns
+    /// [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error if there are remaining bytes.
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bytes remaining on stream",
+            )),
+        }
+    }
+}
+
+/// Encodes frames into the bytes written by [`Framed`](crate::Framed).
+pub trait Encoder<Item> {
+    /// Encodes one frame `item` and appends to `dst`.
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> io::Result<()>;
+}
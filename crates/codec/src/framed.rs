@@ -0,0 +1,250 @@
+use std::{
+    fmt::Debug,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, BytesMut};
+use futures::{AsyncRead, AsyncWrite, Sink, Stream};
+
+use crate::{Decoder, Encoder};
+
+/// The number of bytes read from the underlying io at once.
+const READ_SIZE: usize = 8 * 1024;
+
+/// The size of buffered frames to flush before accepting new frames.
+const BACKPRESSURE_BOUNDARY: usize = 128 * 1024;
+
+/// The [`Stream`] of frames decoded from the underlying io by codec `C`,
+/// which is also the [`Sink`] of frames encoded into the underlying io.
+pub struct Framed<T, C> {
+    io: T,
+    codec: C,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+    eof: bool,
+}
+
+impl<T, C> Debug for Framed<T, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Framed(read_buf={}, write_buf={}, eof={})",
+            self.read_buf.len(),
+            self.write_buf.len(),
+            self.eof
+        )
+    }
+}
+
+impl<T, C> Framed<T, C> {
+    /// Create new framed io with `codec`.
+    pub fn new(io: T, codec: C) -> Self {
+        Self {
+            io,
+            codec,
+            read_buf: BytesMut::new(),
+            write_buf: BytesMut::new(),
+            eof: false,
+        }
+    }
+
+    /// Returns the reference of the underlying io.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns the mutable reference of the underlying io.
+    ///
+    /// Reading / writing the underlying io directly may corrupt the frames.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns the reference of the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns the mutable reference of the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Returns the bytes read from the underlying io but not yet decoded.
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.read_buf
+    }
+
+    /// Consumes this framed io and returns the underlying io, the buffered bytes are dropped.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T, C> Stream for Framed<T, C>
+where
+    T: AsyncRead + Unpin,
+    C: Decoder + Unpin,
+{
+    type Item = io::Result<C::Item>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.eof {
+                return Poll::Ready(this.codec.decode_eof(&mut this.read_buf).transpose());
+            }
+
+            if let Some(frame) = this.codec.decode(&mut this.read_buf)? {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+
+            let len = this.read_buf.len();
+
+            this.read_buf.resize(len + READ_SIZE, 0);
+
+            let read_size = match Pin::new(&mut this.io).poll_read(cx, &mut this.read_buf[len..]) {
+                Poll::Ready(Ok(read_size)) => read_size,
+                Poll::Ready(Err(err)) => {
+                    this.read_buf.truncate(len);
+                    return Poll::Ready(Some(Err(err)));
+                }
+                Poll::Pending => {
+                    this.read_buf.truncate(len);
+                    return Poll::Pending;
+                }
+            };
+
+            this.read_buf.truncate(len + read_size);
+
+            if read_size == 0 {
+                this.eof = true;
+            }
+        }
+    }
+}
+
+impl<T, C, I> Sink<I> for Framed<T, C>
+where
+    T: AsyncWrite + Unpin,
+    C: Encoder<I> + Unpin,
+{
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.write_buf.len() >= BACKPRESSURE_BOUNDARY {
+            return Sink::<I>::poll_flush(self, cx);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: I) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+
+        this.codec.encode(item, &mut this.write_buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        while !this.write_buf.is_empty() {
+            let write_size = match Pin::new(&mut this.io).poll_write(cx, &this.write_buf) {
+                Poll::Ready(r) => r?,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if write_size == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write frame to the underlying io",
+                )));
+            }
+
+            this.write_buf.advance(write_size);
+        }
+
+        Pin::new(&mut this.io).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match Sink::<I>::poll_flush(self.as_mut(), cx) {
+            Poll::Ready(Ok(())) => {}
+            r => return r,
+        }
+
+        Pin::new(&mut self.io).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures::{executor::block_on, io::Cursor, SinkExt, StreamExt};
+
+    use crate::{LengthDelimitedCodec, LinesCodec};
+
+    use super::*;
+
+    #[test]
+    fn test_length_delimited() {
+        block_on(async {
+            let mut framed = Framed::new(Cursor::new(vec![]), LengthDelimitedCodec::new());
+
+            framed.send(Bytes::from_static(b"hello")).await.unwrap();
+            framed.send(Bytes::new()).await.unwrap();
+            framed.send(Bytes::from(vec![1; 100000])).await.unwrap();
+
+            let mut io = framed.into_inner();
+
+            io.set_position(0);
+
+            let frames = Framed::new(io, LengthDelimitedCodec::new())
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+
+            assert_eq!(
+                frames,
+                vec![
+                    Bytes::from_static(b"hello"),
+                    Bytes::new(),
+                    Bytes::from(vec![1; 100000])
+                ]
+            );
+        })
+    }
+
+    #[test]
+    fn test_truncated_frame() {
+        block_on(async {
+            let mut framed = Framed::new(
+                Cursor::new(vec![0, 0, 0, 5, b'h', b'e']),
+                LengthDelimitedCodec::new(),
+            );
+
+            let err = framed.next().await.unwrap().unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        })
+    }
+
+    #[test]
+    fn test_lines() {
+        block_on(async {
+            let framed = Framed::new(
+                Cursor::new(b"hello\r\nworld\n\nlast".to_vec()),
+                LinesCodec::new(),
+            );
+
+            let lines = framed.map(|line| line.unwrap()).collect::<Vec<_>>().await;
+
+            assert_eq!(lines, vec!["hello", "world", "", "last"]);
+        })
+    }
+}
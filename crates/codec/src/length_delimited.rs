@@ -0,0 +1,145 @@
+use std::io;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{Decoder, Encoder};
+
+/// The length of frame header.
+const HEADER_LEN: usize = 4;
+
+/// The default max length of frame payload, 8MB.
+const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// The codec of frames which are prefixed by the big-endian `u32` length of payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthDelimitedCodec {
+    max_frame_length: usize,
+}
+
+impl Default for LengthDelimitedCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LengthDelimitedCodec {
+    /// Create new codec with the default max frame length 8MB.
+    pub fn new() -> Self {
+        Self::with_max_frame_length(DEFAULT_MAX_FRAME_LENGTH)
+    }
+
+    /// Create new codec with `max_frame_length`, the longer frame is rejected
+    /// with [`InvalidData`](io::ErrorKind::InvalidData) error.
+    pub fn with_max_frame_length(max_frame_length: usize) -> Self {
+        Self {
+            max_frame_length: max_frame_length.min(u32::MAX as usize),
+        }
+    }
+
+    /// Returns the max length of frame payload.
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    fn check_frame_length(&self, len: usize) -> io::Result<()> {
+        if len > self.max_frame_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "frame length {} exceeds the max length {}",
+                    len, self.max_frame_length
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = Bytes;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        let len = u32::from_be_bytes(src[..HEADER_LEN].try_into().unwrap()) as usize;
+
+        self.check_frame_length(len)?;
+
+        if src.len() < HEADER_LEN + len {
+            src.reserve(HEADER_LEN + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(HEADER_LEN);
+
+        Ok(Some(src.split_to(len).freeze()))
+    }
+}
+
+impl Encoder<Bytes> for LengthDelimitedCodec {
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        self.encode(&item[..], dst)
+    }
+}
+
+impl Encoder<&[u8]> for LengthDelimitedCodec {
+    fn encode(&mut self, item: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+        self.check_frame_length(item.len())?;
+
+        dst.reserve(HEADER_LEN + item.len());
+
+        dst.put_u32(item.len() as u32);
+        dst.put_slice(item);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_frame() {
+        let mut codec = LengthDelimitedCodec::new();
+
+        let mut buf = BytesMut::new();
+
+        codec.encode(&b"hello"[..], &mut buf).unwrap();
+
+        let mut src = BytesMut::new();
+
+        for b in &buf[..buf.len() - 1] {
+            src.put_u8(*b);
+
+            assert_eq!(codec.decode(&mut src).unwrap(), None);
+        }
+
+        src.put_u8(buf[buf.len() - 1]);
+
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap(), &b"hello"[..]);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_max_frame_length() {
+        let mut codec = LengthDelimitedCodec::with_max_frame_length(4);
+
+        let mut buf = BytesMut::new();
+
+        assert_eq!(
+            codec.encode(&b"hello"[..], &mut buf).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let mut src = BytesMut::from(&[0u8, 0, 0, 5][..]);
+
+        assert_eq!(
+            codec.decode(&mut src).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
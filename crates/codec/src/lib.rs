@@ -0,0 +1,14 @@
+//! Message framing over asynchronous io, which turns [`AsyncRead`](futures::AsyncRead) /
+//! [`AsyncWrite`](futures::AsyncWrite) types into [`Stream`](futures::Stream) / [`Sink`](futures::Sink) of frames.
+
+mod codec;
+pub use codec::*;
+
+mod framed;
+pub use framed::*;
+
+mod length_delimited;
+pub use length_delimited::*;
+
+mod lines;
+pub use lines::*;
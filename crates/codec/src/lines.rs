@@ -0,0 +1,157 @@
+use std::io;
+
+use bytes::{BufMut, BytesMut};
+
+use crate::{Decoder, Encoder};
+
+/// The codec of lines which are delimited by `\n`, the trailing `\r` is also removed when decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinesCodec {
+    max_length: usize,
+    /// The offset of `src` from which to search the next `\n`.
+    next_index: usize,
+}
+
+impl Default for LinesCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinesCodec {
+    /// Create new codec without limiting the line length.
+    pub fn new() -> Self {
+        Self::with_max_length(usize::MAX)
+    }
+
+    /// Create new codec with `max_length`, the longer line is rejected
+    /// with [`InvalidData`](io::ErrorKind::InvalidData) error.
+    pub fn with_max_length(max_length: usize) -> Self {
+        Self {
+            max_length,
+            next_index: 0,
+        }
+    }
+
+    /// Returns the max length of line, excluding the delimiter.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    fn parse_line(&self, mut line: BytesMut) -> io::Result<String> {
+        if line.last() == Some(&b'\r') {
+            line.truncate(line.len() - 1);
+        }
+
+        if line.len() > self.max_length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "line length {} exceeds the max length {}",
+                    line.len(),
+                    self.max_length
+                ),
+            ));
+        }
+
+        String::from_utf8(line.to_vec())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        match src[self.next_index..].iter().position(|b| *b == b'\n') {
+            Some(offset) => {
+                let mut line = src.split_to(self.next_index + offset + 1);
+
+                self.next_index = 0;
+
+                // Removes the delimiter `\n`.
+                line.truncate(line.len() - 1);
+
+                self.parse_line(line).map(Some)
+            }
+            None => {
+                self.next_index = src.len();
+
+                // One more byte for the trailing `\r`.
+                if src.len() > self.max_length.saturating_add(1) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line length exceeds the max length {}", self.max_length),
+                    ));
+                }
+
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        if let Some(line) = self.decode(src)? {
+            return Ok(Some(line));
+        }
+
+        self.next_index = 0;
+
+        if src.is_empty() {
+            return Ok(None);
+        }
+
+        // The last line without delimiter.
+        self.parse_line(src.split()).map(Some)
+    }
+}
+
+impl Encoder<String> for LinesCodec {
+    fn encode(&mut self, item: String, dst: &mut BytesMut) -> io::Result<()> {
+        self.encode(item.as_str(), dst)
+    }
+}
+
+impl Encoder<&str> for LinesCodec {
+    fn encode(&mut self, item: &str, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(item.len() + 1);
+
+        dst.put_slice(item.as_bytes());
+        dst.put_u8(b'\n');
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_line() {
+        let mut codec = LinesCodec::new();
+
+        let mut src = BytesMut::from(&b"hel"[..]);
+
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+
+        src.put_slice(b"lo\r\nworld");
+
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap(), "hello");
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert_eq!(codec.decode_eof(&mut src).unwrap().unwrap(), "world");
+        assert_eq!(codec.decode_eof(&mut src).unwrap(), None);
+    }
+
+    #[test]
+    fn test_max_length() {
+        let mut codec = LinesCodec::with_max_length(3);
+
+        let mut src = BytesMut::from(&b"hello"[..]);
+
+        assert_eq!(
+            codec.decode(&mut src).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
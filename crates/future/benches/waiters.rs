@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+
+use divan::Bencher;
+use hala_future::waiters::WaiterList;
+
+fn main() {
+    divan::main();
+}
+
+#[divan::bench(args = [16, 256, 4096])]
+fn list_push_pop(bencher: Bencher, len: usize) {
+    let mut list = WaiterList::new();
+
+    for i in 0..len {
+        list.push_back(i);
+    }
+
+    bencher.bench_local(|| {
+        let value = list.pop_front().unwrap();
+        list.push_back(value);
+    })
+}
+
+#[divan::bench(args = [16, 256, 4096])]
+fn vec_deque_push_pop(bencher: Bencher, len: usize) {
+    let mut list = (0..len).collect::<VecDeque<_>>();
+
+    bencher.bench_local(|| {
+        let value = list.pop_front().unwrap();
+        list.push_back(value);
+    })
+}
+
+/// Cancels the waiter in the middle of list, and registers it again.
+#[divan::bench(args = [16, 256, 4096])]
+fn list_cancel(bencher: Bencher, len: usize) {
+    let mut list = WaiterList::new();
+
+    let mut keys = (0..len).map(|i| list.push_back(i)).collect::<Vec<_>>();
+
+    let middle = len / 2;
+
+    bencher.bench_local(|| {
+        let value = list.remove(keys[middle]).unwrap();
+        keys[middle] = list.push_back(value);
+    })
+}
+
+/// Cancels the waiter in the middle of list by scanning, and registers it again.
+#[divan::bench(args = [16, 256, 4096])]
+fn vec_deque_cancel(bencher: Bencher, len: usize) {
+    let mut list = (0..len).collect::<VecDeque<_>>();
+
+    let middle = len / 2;
+
+    bencher.bench_local(|| {
+        let index = list.iter().position(|value| *value == middle).unwrap();
+        let value = list.remove(index).unwrap();
+        list.push_back(value);
+    })
+}
@@ -217,6 +217,29 @@ impl<R> FutureBatcher<R> {
             batch: self.clone(),
         }
     }
+
+    /// Polls the woken futures without waiting, returns `None` if none of them is ready.
+    ///
+    /// Unlike polling [`wait`](Self::wait) once, the caller's task is not registered to be woken.
+    pub fn try_wait(&self) -> Option<R> {
+        while let Some(ready) = self.ready_futures.pop() {
+            let mut future = self
+                .pending_futures
+                .remove(ready)
+                .expect("Calling BatchFuture.await in multi-threads is not allowed");
+
+            let waker = new_batcher_waker(ready, self.clone());
+
+            match future.poll_unpin(&mut Context::from_waker(&waker)) {
+                std::task::Poll::Pending => {
+                    self.pending_futures.insert(ready, future);
+                }
+                std::task::Poll::Ready(r) => return Some(r),
+            }
+        }
+
+        None
+    }
 }
 
 pub struct Wait<R> {
@@ -292,6 +315,27 @@ mod tests {
         }
     }
 
+    #[futures_test::test]
+    async fn test_try_wait() {
+        let batch_future = FutureBatcher::<usize>::new();
+
+        assert_eq!(batch_future.try_wait(), None);
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+
+        batch_future.push(async move { receiver.await.unwrap() });
+
+        assert_eq!(batch_future.try_wait(), None);
+
+        sender.send(1).unwrap();
+
+        assert_eq!(batch_future.try_wait(), Some(1));
+
+        batch_future.push(async { 2 });
+
+        assert_eq!(batch_future.wait().await, 2);
+    }
+
     #[futures_test::test]
     async fn test_push_wakeup() {
         let pool = ThreadPool::builder().pool_size(10).create().unwrap();
@@ -1,7 +1,11 @@
 use std::{
+    any::Any,
     borrow::Borrow,
+    cell::RefCell,
+    collections::HashMap,
     fmt::Debug,
     hash::Hash,
+    marker::PhantomData,
     sync::{
         atomic::{AtomicU8, Ordering},
         Arc,
@@ -12,6 +16,8 @@ use std::{
 use dashmap::DashMap;
 use hala_sync::{AsyncGuardMut, AsyncLockable};
 
+use crate::waiters::{WaiterKey, WaiterList};
+
 #[derive(Debug, thiserror::Error, PartialEq)]
 pub enum EventMapError {
     #[error("Waiting operation canceled by user")]
@@ -62,13 +68,45 @@ impl WakerWithReason {
     }
 }
 
-/// The mediator of event notify for futures-aware enviroment.
+thread_local! {
+    /// The [`defer`](EventMap::defer) scopes entered on current thread, keyed by the address of `EventMap`.
+    static DEFERRED: RefCell<HashMap<usize, Box<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// The deferred notifications raised in [`defer`](EventMap::defer) scope.
 #[derive(Debug)]
+struct Deferred<E> {
+    /// The nesting depth of defer scopes.
+    depth: usize,
+    /// Coalesced notifications, tuple (event, reason of the last notification, count of `notify_one` calls).
+    events: Vec<(E, Reason, usize)>,
+}
+
+impl<E> Default for Deferred<E> {
+    fn default() -> Self {
+        Self {
+            depth: 0,
+            events: vec![],
+        }
+    }
+}
+
+/// The mediator of event notify for futures-aware enviroment.
 pub struct EventMap<E>
 where
     E: Send + Eq + Hash,
 {
-    wakers: DashMap<E, WakerWithReason>,
+    /// The waiters of events, in the order of waiting.
+    wakers: DashMap<E, WaiterList<WakerWithReason>>,
+}
+
+impl<E> Debug for EventMap<E>
+where
+    E: Send + Eq + Hash + Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EventMap({:?})", self.wakers)
+    }
 }
 
 impl<E> Drop for EventMap<E>
@@ -77,7 +115,9 @@ where
 {
     fn drop(&mut self) {
         for entry in self.wakers.iter() {
-            entry.value().wake_by_ref(Reason::Destroy);
+            for waker in entry.value().iter() {
+                waker.wake_by_ref(Reason::Destroy);
+            }
         }
     }
 }
@@ -93,10 +133,67 @@ where
     }
 }
 
+/// RAII scope guard created by [`defer`](EventMap::defer) function,
+/// the deferred notifications are flushed when the outermost guard of current thread is dropped.
+///
+/// The guard is `!Send`, the scope belongs to the thread entering it.
+pub struct DeferGuard<'a, E>
+where
+    E: Send + Eq + Hash + Debug + Clone + 'static,
+{
+    event_map: &'a EventMap<E>,
+    _thread_bound: PhantomData<*const ()>,
+}
+
+impl<'a, E> Drop for DeferGuard<'a, E>
+where
+    E: Send + Eq + Hash + Debug + Clone + 'static,
+{
+    fn drop(&mut self) {
+        let events = self.event_map.with_deferred(|deferred| {
+            let deferred = deferred.expect("defer scope entered");
+
+            deferred.depth -= 1;
+
+            if deferred.depth > 0 {
+                None
+            } else {
+                Some(std::mem::take(&mut deferred.events))
+            }
+        });
+
+        let Some(events) = events else {
+            return;
+        };
+
+        DEFERRED.with_borrow_mut(|scopes| scopes.remove(&self.event_map.id()));
+
+        for (event, reason, count) in events {
+            self.event_map.notify_now(&event, reason, count);
+        }
+    }
+}
+
 impl<E> EventMap<E>
 where
-    E: Send + Eq + Hash + Debug + Clone,
+    E: Send + Eq + Hash + Debug + Clone + 'static,
 {
+    fn id(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Calls `f` with the deferred notifications of current thread, `None` if not in defer scope.
+    fn with_deferred<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(Option<&mut Deferred<E>>) -> R,
+    {
+        DEFERRED.with_borrow_mut(|scopes| {
+            f(scopes
+                .get_mut(&self.id())
+                .and_then(|deferred| deferred.downcast_mut::<Deferred<E>>()))
+        })
+    }
+
     /// Only remove event waker, without wakeup it.
     pub fn wait_cancel<Q>(&self, event: Q)
     where
@@ -105,18 +202,88 @@ where
         self.wakers.remove(event.borrow());
     }
 
-    /// Notify one event `E` on.
+    /// Enters the deferred-notification scope of current thread.
+    ///
+    /// The [`notify_one`](Self::notify_one) calls of current thread before the returned guard is dropped
+    /// are coalesced by event and flushed when the outermost guard is dropped, each call still wakes one waiter.
+    /// The notifications raised by other threads are not deferred.
+    pub fn defer(&self) -> DeferGuard<'_, E> {
+        DEFERRED.with_borrow_mut(|scopes| {
+            let deferred = scopes
+                .entry(self.id())
+                .or_insert_with(|| Box::<Deferred<E>>::default());
+
+            deferred
+                .downcast_mut::<Deferred<E>>()
+                .expect("deferred notifications of the same event map")
+                .depth += 1;
+        });
+
+        DeferGuard {
+            event_map: self,
+            _thread_bound: PhantomData,
+        }
+    }
+
+    /// Notify one event `E` on, only the earliest waiter of the event is woken up.
+    ///
+    /// In the deferred-notification scope, returns true if there are more waiters of the event than
+    /// the deferred notifications.
     pub fn notify_one<Q>(&self, event: Q, reason: Reason) -> bool
     where
         Q: Borrow<E>,
     {
-        if let Some((_, waker)) = self.wakers.remove(event.borrow()) {
-            log::trace!("{:?} wakeup", event.borrow());
+        let event = event.borrow();
+
+        let deferred = self.with_deferred(|deferred| {
+            let deferred = deferred?;
+
+            let pending = match deferred.events.iter_mut().find(|(e, _, _)| e == event) {
+                Some((_, last_reason, count)) => {
+                    *last_reason = reason;
+                    *count += 1;
+                    *count - 1
+                }
+                None => {
+                    deferred.events.push((event.clone(), reason, 1));
+                    0
+                }
+            };
+
+            Some(pending)
+        });
+
+        match deferred {
+            Some(pending) => self
+                .wakers
+                .get(event)
+                .is_some_and(|wakers| wakers.len() > pending),
+            None => self.notify_now(event, reason, 1),
+        }
+    }
+
+    /// Wakes up at most `count` earliest waiters of `event`, returns true if any waiter is woken.
+    fn notify_now(&self, event: &E, reason: Reason, count: usize) -> bool {
+        let wakers = match self.wakers.get_mut(event) {
+            Some(mut wakers) => (0..count)
+                .map_while(|_| wakers.pop_front())
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        self.wakers.remove_if(event, |_, wakers| wakers.is_empty());
+
+        if wakers.is_empty() {
+            return false;
+        }
+
+        log::trace!("{:?} wakeup {} waiters", event, wakers.len());
+
+        for waker in wakers {
             waker.wake(reason);
-            true
-        } else {
-            false
         }
+
+        true
     }
 
     /// Notify all event on in the providing `events` list
@@ -126,7 +293,7 @@ where
         }
     }
 
-    /// Notify all event on in the providing `events` list
+    /// Notify all waiters of all events on.
     pub fn notify_any(&self, reason: Reason) {
         let events = self
             .wakers
@@ -134,7 +301,37 @@ where
             .map(|pair| pair.key().clone())
             .collect::<Vec<_>>();
 
-        self.notify_all(&events, reason);
+        for event in events {
+            if let Some((_, mut wakers)) = self.wakers.remove(&event) {
+                while let Some(waker) = wakers.pop_front() {
+                    waker.wake(reason);
+                }
+            }
+        }
+    }
+
+    /// Registers `waker` as the waiter of `event`, which is used by the `poll_*` style functions.
+    ///
+    /// The waker is queued after the other waiters of `event`, including the [`wait`](Self::wait) ones,
+    /// unless the same task has been registered. Unlike [`wait`](Self::wait), the registration can't be
+    /// removed by the caller, it's removed once woken up.
+    pub fn register<Q>(&self, event: Q, waker: &Waker)
+    where
+        Q: Borrow<E>,
+    {
+        let mut wakers = self.wakers.entry(event.borrow().clone()).or_default();
+
+        if wakers
+            .iter()
+            .any(|registered| registered.waker.will_wake(waker))
+        {
+            return;
+        }
+
+        wakers.push_back(WakerWithReason {
+            waker: waker.clone(),
+            reason: Arc::new(AtomicU8::new(Reason::None.into())),
+        });
     }
 
     pub fn wait<'a, Q, G>(&'a self, event: Q, guard: G) -> Wait<'a, E, G>
@@ -147,6 +344,7 @@ where
             guard: Some(guard),
             event_map: self,
             reason: Arc::new(AtomicU8::new(Reason::None.into())),
+            key: None,
         }
     }
 }
@@ -160,6 +358,8 @@ where
     guard: Option<G>,
     event_map: &'a EventMap<E>,
     reason: Arc<AtomicU8>,
+    /// The key of registered waker, used to remove the waker if this future is dropped before wakeup.
+    key: Option<WaiterKey>,
 }
 
 impl<'a, E, G> std::future::Future for Wait<'a, E, G>
@@ -175,13 +375,17 @@ where
     ) -> std::task::Poll<Self::Output> {
         if let Some(guard) = self.guard.take() {
             // insert waker into waiting map.
-            self.event_map.wakers.insert(
-                self.event.clone(),
-                WakerWithReason {
+            let key = self
+                .event_map
+                .wakers
+                .entry(self.event.clone())
+                .or_default()
+                .push_back(WakerWithReason {
                     waker: cx.waker().clone(),
                     reason: self.reason.clone(),
-                },
-            );
+                });
+
+            self.key = Some(key);
 
             G::Locker::unlock(guard);
 
@@ -204,6 +408,31 @@ where
     }
 }
 
+impl<'a, E, G> Drop for Wait<'a, E, G>
+where
+    E: Send + Eq + Hash,
+    G: AsyncGuardMut<'a> + 'a,
+{
+    fn drop(&mut self) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+
+        if self.reason.load(Ordering::SeqCst) != Reason::None.into() {
+            return;
+        }
+
+        // Removes the waker of cancelled waiting, so the later notification isn't consumed by it.
+        if let Some(mut wakers) = self.event_map.wakers.get_mut(&self.event) {
+            wakers.remove(key);
+        }
+
+        self.event_map
+            .wakers
+            .remove_if(&self.event, |_, wakers| wakers.is_empty());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,4 +462,155 @@ mod tests {
 
         handle.await;
     }
+
+    #[futures_test::test]
+    async fn test_multiple_waiters() {
+        let local_pool = ThreadPool::builder().pool_size(10).create().unwrap();
+
+        let mediator = Arc::new(EventMap::<i32>::default());
+
+        let shared = Arc::new(AsyncSpinMutex::new(0));
+
+        let mut handles = vec![];
+
+        for _ in 0..2 {
+            let mediator = mediator.clone();
+            let shared = shared.clone();
+
+            handles.push(
+                local_pool
+                    .spawn_with_handle(async move {
+                        let mut guard = shared.lock().await;
+
+                        *guard += 1;
+
+                        mediator.wait(1, guard).await.unwrap();
+                    })
+                    .unwrap(),
+            );
+        }
+
+        while *shared.lock().await != 2 {}
+
+        // each notification wakes only one waiter.
+        assert!(mediator.notify_one(1, Reason::On));
+        assert!(mediator.notify_one(1, Reason::On));
+        assert!(!mediator.notify_one(1, Reason::On));
+
+        for handle in handles {
+            handle.await;
+        }
+    }
+
+    #[test]
+    fn test_defer() {
+        let mediator = EventMap::<i32>::default();
+
+        let (first, first_count) = futures_test::task::new_count_waker();
+        let (second, second_count) = futures_test::task::new_count_waker();
+
+        mediator.register(1, &first);
+        mediator.register(1, &second);
+
+        {
+            let _guard = mediator.defer();
+
+            let nested = mediator.defer();
+
+            assert!(mediator.notify_one(1, Reason::On));
+            assert!(mediator.notify_one(1, Reason::On));
+            assert!(!mediator.notify_one(1, Reason::On));
+
+            drop(nested);
+
+            // Flushed by the outermost guard.
+            assert_eq!((first_count.get(), second_count.get()), (0, 0));
+        }
+
+        // Each deferred `notify_one` wakes one waiter.
+        assert_eq!((first_count.get(), second_count.get()), (1, 1));
+
+        assert!(!mediator.notify_one(1, Reason::On));
+    }
+
+    #[test]
+    fn test_defer_per_thread() {
+        let mediator = Arc::new(EventMap::<i32>::default());
+
+        let (waker, count) = futures_test::task::new_count_waker();
+
+        mediator.register(1, &waker);
+
+        let _guard = mediator.defer();
+
+        // The scope of current thread doesn't defer the notifications of other threads.
+        std::thread::spawn({
+            let mediator = mediator.clone();
+
+            move || assert!(mediator.notify_one(1, Reason::On))
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(count.get(), 1);
+    }
+
+    #[futures_test::test]
+    async fn test_cancel_wait() {
+        let mediator = EventMap::<i32>::default();
+
+        let shared = AsyncSpinMutex::new(());
+
+        let mut wait = mediator.wait(1, shared.lock().await);
+
+        assert!(futures::poll!(&mut wait).is_pending());
+
+        drop(wait);
+
+        // The waker of dropped future is removed.
+        assert!(!mediator.notify_one(1, Reason::On));
+    }
+
+    #[test]
+    fn test_register() {
+        let mediator = EventMap::<i32>::default();
+
+        let (first, first_count) = futures_test::task::new_count_waker();
+        let (second, second_count) = futures_test::task::new_count_waker();
+
+        mediator.register(1, &first);
+        mediator.register(1, &second);
+
+        // The same task is registered once.
+        mediator.register(1, &first);
+
+        assert!(mediator.notify_one(1, Reason::On));
+        assert_eq!((first_count.get(), second_count.get()), (1, 0));
+
+        assert!(mediator.notify_one(1, Reason::On));
+        assert_eq!((first_count.get(), second_count.get()), (1, 1));
+
+        assert!(!mediator.notify_one(1, Reason::On));
+    }
+
+    #[futures_test::test]
+    async fn test_register_with_wait() {
+        let mediator = EventMap::<i32>::default();
+
+        let shared = AsyncSpinMutex::new(());
+
+        let mut wait = mediator.wait(1, shared.lock().await);
+
+        assert!(futures::poll!(&mut wait).is_pending());
+
+        let (waker, count) = futures_test::task::new_count_waker();
+
+        // The registration doesn't clobber the waiting future.
+        mediator.register(1, &waker);
+
+        drop(wait);
+
+        assert!(mediator.notify_one(1, Reason::On));
+        assert_eq!(count.get(), 1);
+    }
 }
@@ -0,0 +1,355 @@
+//! Thread-per-core runtime.
+//!
+//! Each worker thread owns a single-thread executor, tasks never migrate between workers
+//! and therefore don't need to be [`Send`]. Use [`LocalMutex`](hala_sync::LocalMutex) to
+//! share state between tasks of the same worker, and [`ThreadPerCore::spawn_on`] or the
+//! [`mpsc`](crate::mpsc) channels to communicate with other workers.
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle, Thread},
+};
+
+use futures::{
+    executor::{LocalPool, LocalSpawner},
+    future::LocalBoxFuture,
+    task::LocalSpawnExt,
+    FutureExt,
+};
+use hala_lockfree::queue::Queue;
+
+use crate::metrics::instrument;
+
+/// The job sent to worker thread, which creates the `!Send` task on the target worker.
+type Job = Box<dyn FnOnce() -> LocalBoxFuture<'static, ()> + Send>;
+
+thread_local! {
+    /// The index and spawner of the worker running on current thread.
+    static CURRENT: RefCell<Option<(usize, LocalSpawner)>> = const { RefCell::new(None) };
+}
+
+/// Spawns a `!Send` task onto the worker running on current thread.
+///
+/// Returns error if the current thread is not a worker of [`ThreadPerCore`].
+pub fn spawn_local<Fut>(fut: Fut) -> io::Result<()>
+where
+    Fut: Future<Output = ()> + 'static,
+{
+    CURRENT.with_borrow(|current| match current {
+        Some((_, spawner)) => spawner
+            .spawn_local(instrument(fut))
+            .map_err(io::Error::other),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "current thread is not a thread-per-core worker",
+        )),
+    })
+}
+
+/// Returns the index of the worker running on current thread.
+pub fn current_worker() -> Option<usize> {
+    CURRENT.with_borrow(|current| current.as_ref().map(|(index, _)| *index))
+}
+
+struct Worker {
+    jobs: Arc<Queue<Job>>,
+    thread: Thread,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Builder for [`ThreadPerCore`] runtime.
+#[derive(Debug, Clone)]
+pub struct ThreadPerCoreBuilder {
+    workers: usize,
+    pin_cpus: bool,
+    thread_name: String,
+}
+
+impl Default for ThreadPerCoreBuilder {
+    fn default() -> Self {
+        Self {
+            workers: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            pin_cpus: false,
+            thread_name: "hala-worker".into(),
+        }
+    }
+}
+
+impl ThreadPerCoreBuilder {
+    /// Sets the number of worker threads, the default value is the number of available cpus.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Pins the worker `n` to the cpu `n % available cpus`, only supported on linux.
+    pub fn pin_cpus(mut self, pin_cpus: bool) -> Self {
+        self.pin_cpus = pin_cpus;
+        self
+    }
+
+    /// Sets the name prefix of worker threads.
+    pub fn thread_name(mut self, name: &str) -> Self {
+        self.thread_name = name.to_owned();
+        self
+    }
+
+    /// Starts the worker threads and creates the runtime.
+    pub fn build(self) -> io::Result<ThreadPerCore> {
+        if self.workers == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "workers must be greater than zero",
+            ));
+        }
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let cpus = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        let mut workers = vec![];
+
+        for index in 0..self.workers {
+            let jobs = Arc::new(Queue::<Job>::new());
+
+            let cpu = if self.pin_cpus {
+                Some(index % cpus)
+            } else {
+                None
+            };
+
+            let handle = thread::Builder::new()
+                .name(format!("{}-{}", self.thread_name, index))
+                .spawn({
+                    let jobs = jobs.clone();
+                    let shutdown = shutdown.clone();
+
+                    move || run_worker(index, cpu, jobs, shutdown)
+                })?;
+
+            workers.push(Worker {
+                jobs,
+                thread: handle.thread().clone(),
+                handle: Some(handle),
+            });
+        }
+
+        Ok(ThreadPerCore {
+            workers,
+            shutdown,
+            next: Default::default(),
+        })
+    }
+}
+
+fn run_worker(index: usize, cpu: Option<usize>, jobs: Arc<Queue<Job>>, shutdown: Arc<AtomicBool>) {
+    if let Some(cpu) = cpu {
+        if let Err(err) = pin_current_thread(cpu) {
+            log::error!("pin worker {} to cpu {} failed, err={}", index, cpu, err);
+        }
+    }
+
+    let mut pool = LocalPool::new();
+
+    let spawner = pool.spawner();
+
+    CURRENT.with_borrow_mut(|current| *current = Some((index, spawner.clone())));
+
+    loop {
+        while let Some(job) = jobs.pop() {
+            if let Err(err) = spawner.spawn_local(instrument(job())) {
+                log::error!("worker {} spawn task failed, err={}", index, err);
+            }
+        }
+
+        pool.run_until_stalled();
+
+        if shutdown.load(Ordering::Acquire) {
+            break;
+        }
+
+        // Unparked by `spawn_on` or the wakers of local tasks.
+        thread::park();
+    }
+
+    CURRENT.with_borrow_mut(|current| *current = None);
+}
+
+#[cfg(target_os = "linux")]
+fn pin_current_thread(cpu: usize) -> io::Result<()> {
+    // Safety: `cpu_set_t` is a plain bitmask, and the pointer is valid during the call.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+
+        libc::CPU_SET(cpu, &mut set);
+
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_current_thread(_cpu: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "cpu pinning is only supported on linux",
+    ))
+}
+
+/// The thread-per-core runtime, the worker threads stop when this runtime is dropped.
+pub struct ThreadPerCore {
+    workers: Vec<Worker>,
+    shutdown: Arc<AtomicBool>,
+    next: AtomicUsize,
+}
+
+impl ThreadPerCore {
+    /// Creates a runtime with `workers` threads, see [`ThreadPerCoreBuilder`] for more options.
+    pub fn new(workers: usize) -> io::Result<Self> {
+        Self::builder().workers(workers).build()
+    }
+
+    /// Creates a [`ThreadPerCoreBuilder`] with default options.
+    pub fn builder() -> ThreadPerCoreBuilder {
+        ThreadPerCoreBuilder::default()
+    }
+
+    /// Returns the number of worker threads.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Spawns the task created by `f` onto the worker `index`.
+    ///
+    /// The closure `f` is sent to the target worker, so the created task doesn't need to be [`Send`].
+    pub fn spawn_on<F, Fut>(&self, index: usize, f: F) -> io::Result<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let worker = self.workers.get(index).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("worker {} out of range", index),
+            )
+        })?;
+
+        worker.jobs.push(Box::new(move || f().boxed_local()));
+
+        worker.thread.unpark();
+
+        Ok(())
+    }
+
+    /// Spawns the task created by `f` onto the workers in a round-robin manner.
+    pub fn spawn<F, Fut>(&self, f: F) -> io::Result<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.workers.len();
+
+        self.spawn_on(index, f)
+    }
+}
+
+impl Drop for ThreadPerCore {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Release);
+
+        for worker in &mut self.workers {
+            worker.thread.unpark();
+
+            if let Some(handle) = worker.handle.take() {
+                _ = handle.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, sync::mpsc::channel, thread};
+
+    use hala_sync::{LocalMutex, Lockable, LockableNew};
+
+    use super::*;
+
+    #[test]
+    fn test_spawn_on() {
+        let runtime = ThreadPerCore::new(2).unwrap();
+
+        let (sender, receiver) = channel();
+
+        for index in 0..runtime.workers() {
+            let sender = sender.clone();
+
+            runtime
+                .spawn_on(index, move || async move {
+                    sender
+                        .send((current_worker(), thread::current().id()))
+                        .unwrap();
+                })
+                .unwrap();
+        }
+
+        let mut workers = [receiver.recv().unwrap(), receiver.recv().unwrap()];
+
+        workers.sort_by_key(|(index, _)| *index);
+
+        assert_eq!(workers[0].0, Some(0));
+        assert_eq!(workers[1].0, Some(1));
+        assert_ne!(workers[0].1, workers[1].1);
+
+        assert!(runtime.spawn_on(2, || async {}).is_err());
+    }
+
+    #[test]
+    fn test_spawn_local() {
+        let runtime = ThreadPerCore::builder()
+            .workers(1)
+            .pin_cpus(true)
+            .build()
+            .unwrap();
+
+        let (sender, receiver) = channel();
+
+        runtime
+            .spawn(move || async move {
+                // `Rc` is `!Send`.
+                let counter = Rc::new(LocalMutex::new(0));
+
+                for _ in 0..10 {
+                    let counter = counter.clone();
+
+                    spawn_local(async move {
+                        *counter.lock() += 1;
+                    })
+                    .unwrap();
+                }
+
+                spawn_local(async move {
+                    sender.send(*counter.lock()).unwrap();
+                })
+                .unwrap();
+            })
+            .unwrap();
+
+        assert_eq!(receiver.recv().unwrap(), 10);
+
+        assert!(spawn_local(async {}).is_err());
+    }
+}
@@ -1,3 +1,14 @@
 pub mod batching;
 pub mod event_map;
+pub mod executor;
+pub mod local_set;
+pub use local_set::LocalSet;
+pub mod lock;
+pub mod metrics;
+pub mod mpsc;
+pub mod oneshot;
 pub mod poll;
+pub mod scheduler;
+pub mod scope;
+pub use scope::scope;
+pub mod waiters;
@@ -0,0 +1,329 @@
+//! Single-thread executor of `!Send` tasks.
+//!
+//! The [`LocalSet`] runs the spawned tasks on the thread calling [`run_until`](LocalSet::run_until)
+//! or [`run`](LocalSet::run). With [`LocalSet::io_poller`], the io events are polled on the same
+//! thread while all tasks are pending, so no dedicated event loop thread is needed.
+
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    io,
+    pin::pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    thread::{self, Thread},
+};
+
+use futures::{
+    executor::{LocalPool, LocalSpawner},
+    task::{waker, ArcWake, LocalSpawnExt},
+};
+
+use crate::metrics::instrument;
+
+/// The function polling the io events once, see [`LocalSet::io_poller`].
+type IoPoller = Box<dyn Fn() -> io::Result<()>>;
+
+thread_local! {
+    /// The spawner of the [`LocalSet`] running on current thread.
+    static CURRENT: RefCell<Option<LocalSetSpawner>> = const { RefCell::new(None) };
+}
+
+/// Spawns a `!Send` task onto the [`LocalSet`] running on current thread.
+///
+/// Returns error if no [`LocalSet`] is running on current thread.
+pub fn spawn_local<Fut>(fut: Fut) -> io::Result<()>
+where
+    Fut: Future<Output = ()> + 'static,
+{
+    CURRENT.with_borrow(|current| match current {
+        Some(spawner) => spawner.spawn_local(fut),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no local set is running on current thread",
+        )),
+    })
+}
+
+/// The handle to spawn tasks onto [`LocalSet`], which can be cloned and used by the running tasks.
+#[derive(Clone)]
+pub struct LocalSetSpawner {
+    spawner: LocalSpawner,
+    /// The number of alive tasks.
+    tasks: Rc<Cell<usize>>,
+}
+
+impl LocalSetSpawner {
+    /// Spawns a `!Send` task, which is run by the next [`run_until`](LocalSet::run_until) or [`run`](LocalSet::run).
+    pub fn spawn_local<Fut>(&self, fut: Fut) -> io::Result<()>
+    where
+        Fut: Future<Output = ()> + 'static,
+    {
+        let guard = TaskGuard::new(self.tasks.clone());
+
+        self.spawner
+            .spawn_local(instrument(async move {
+                fut.await;
+
+                drop(guard);
+            }))
+            .map_err(io::Error::other)
+    }
+}
+
+/// Decreases the number of alive tasks when the task completes or is dropped.
+struct TaskGuard(Rc<Cell<usize>>);
+
+impl TaskGuard {
+    fn new(tasks: Rc<Cell<usize>>) -> Self {
+        tasks.set(tasks.get() + 1);
+        Self(tasks)
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+/// Sets the spawner of current thread, and restores the previous one on drop.
+struct EnterGuard(Option<LocalSetSpawner>);
+
+impl EnterGuard {
+    fn new(spawner: LocalSetSpawner) -> Self {
+        Self(CURRENT.replace(Some(spawner)))
+    }
+}
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        CURRENT.set(self.0.take());
+    }
+}
+
+/// Wakes up the future passed to [`run_until`](LocalSet::run_until).
+struct MainWaker {
+    woken: AtomicBool,
+    thread: Thread,
+}
+
+impl ArcWake for MainWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.woken.store(true, Ordering::Release);
+        arc_self.thread.unpark();
+    }
+}
+
+/// The set of `!Send` tasks run on the same thread.
+pub struct LocalSet {
+    pool: LocalPool,
+    spawner: LocalSetSpawner,
+    io_poller: Option<IoPoller>,
+}
+
+impl Default for LocalSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalSet {
+    /// Creates an empty task set.
+    pub fn new() -> Self {
+        let pool = LocalPool::new();
+
+        let spawner = LocalSetSpawner {
+            spawner: pool.spawner(),
+            tasks: Default::default(),
+        };
+
+        Self {
+            pool,
+            spawner,
+            io_poller: None,
+        }
+    }
+
+    /// Sets the function polling the io events once, which is called while all tasks are pending
+    /// instead of parking the thread, e.g. `move || driver.fd_cntl(poller, Cmd::PollOnce(Some(timeout)))`.
+    ///
+    /// The thread can't be unparked while polling, so `f` should return in a bounded time,
+    /// the tasks woken by other threads meanwhile are run after `f` returns.
+    pub fn io_poller<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> io::Result<()> + 'static,
+    {
+        self.io_poller = Some(Box::new(f));
+        self
+    }
+
+    /// Returns the handle to spawn tasks onto this set.
+    pub fn spawner(&self) -> LocalSetSpawner {
+        self.spawner.clone()
+    }
+
+    /// Spawns a `!Send` task onto this set, see [`LocalSetSpawner::spawn_local`].
+    pub fn spawn_local<Fut>(&self, fut: Fut) -> io::Result<()>
+    where
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.spawner.spawn_local(fut)
+    }
+
+    /// Returns the number of tasks that have not completed.
+    pub fn tasks(&self) -> usize {
+        self.spawner.tasks.get()
+    }
+
+    /// Runs the spawned tasks until `fut` completes, and returns its output.
+    ///
+    /// The uncompleted tasks are kept, and continue to run by the next `run_until` or [`run`](Self::run).
+    pub fn run_until<Fut: Future>(&mut self, fut: Fut) -> Fut::Output {
+        let _guard = EnterGuard::new(self.spawner.clone());
+
+        let Some(io_poller) = &self.io_poller else {
+            return self.pool.run_until(fut);
+        };
+
+        let main_waker = Arc::new(MainWaker {
+            woken: AtomicBool::new(true),
+            thread: thread::current(),
+        });
+
+        let waker = waker(main_waker.clone());
+
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = pin!(fut);
+
+        loop {
+            if main_waker.woken.swap(false, Ordering::AcqRel) {
+                if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+                    return output;
+                }
+            }
+
+            self.pool.run_until_stalled();
+
+            if main_waker.woken.load(Ordering::Acquire) {
+                continue;
+            }
+
+            if let Err(err) = io_poller() {
+                log::error!("local set poll io events failed, err={}", err);
+            }
+        }
+    }
+
+    /// Runs the spawned tasks until all of them complete.
+    pub fn run(&mut self) {
+        let _guard = EnterGuard::new(self.spawner.clone());
+
+        let Some(io_poller) = &self.io_poller else {
+            return self.pool.run();
+        };
+
+        loop {
+            self.pool.run_until_stalled();
+
+            if self.spawner.tasks.get() == 0 {
+                return;
+            }
+
+            if let Err(err) = io_poller() {
+                log::error!("local set poll io events failed, err={}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::channel::oneshot;
+
+    use super::*;
+
+    #[test]
+    fn test_local_set() {
+        let mut local_set = LocalSet::new();
+
+        let value = Rc::new(Cell::new(0));
+
+        let (sender, receiver) = oneshot::channel();
+
+        local_set
+            .spawn_local({
+                let value = value.clone();
+
+                async move {
+                    // Spawns from the running task.
+                    spawn_local(async move {
+                        value.set(1);
+
+                        sender.send(()).unwrap();
+                    })
+                    .unwrap();
+                }
+            })
+            .unwrap();
+
+        local_set.run_until(receiver).unwrap();
+
+        assert_eq!(value.get(), 1);
+        assert_eq!(local_set.tasks(), 0);
+
+        // No local set is running.
+        spawn_local(async {}).unwrap_err();
+    }
+
+    #[test]
+    fn test_local_set_io_poller() {
+        let polls = Rc::new(Cell::new(0));
+
+        let (sender, receiver) = oneshot::channel();
+
+        // The io poller completes the pending task, like the io event does.
+        let sender = RefCell::new(Some(sender));
+
+        let mut local_set = LocalSet::new().io_poller({
+            let polls = polls.clone();
+
+            move || {
+                polls.set(polls.get() + 1);
+
+                if let Some(sender) = sender.take() {
+                    sender.send(1).unwrap();
+                }
+
+                Ok(())
+            }
+        });
+
+        let value = Rc::new(Cell::new(0));
+
+        local_set
+            .spawn_local({
+                let value = value.clone();
+
+                async move {
+                    value.set(receiver.await.unwrap());
+                }
+            })
+            .unwrap();
+
+        assert_eq!(local_set.tasks(), 1);
+
+        local_set.run();
+
+        assert_eq!(value.get(), 1);
+        assert_eq!(polls.get(), 1);
+        assert_eq!(local_set.tasks(), 0);
+
+        assert_eq!(local_set.run_until(async { 2 }), 2);
+    }
+}
@@ -0,0 +1,49 @@
+//! Lock types that protect the shared state of channels.
+
+use std::ops::DerefMut;
+
+use hala_sync::{LocalMutex, Lockable, LockableNew, SpinMutex};
+
+/// A lock type that protects the shared state `S`.
+///
+/// Implemented for [`SpinMutex`] (thread-safe) and [`LocalMutex`] (single thread mode).
+pub trait StateLock<S> {
+    /// RAII guard type of locked state.
+    type Guard<'a>: DerefMut<Target = S>
+    where
+        Self: 'a;
+
+    /// Create new lock with the initial `state`.
+    fn new(state: S) -> Self;
+
+    /// Lock the state and returns RAII guard object.
+    fn lock(&self) -> Self::Guard<'_>;
+}
+
+impl<S> StateLock<S> for SpinMutex<S> {
+    type Guard<'a> = <Self as Lockable>::GuardMut<'a>
+    where
+        Self: 'a;
+
+    fn new(state: S) -> Self {
+        LockableNew::new(state)
+    }
+
+    fn lock(&self) -> Self::Guard<'_> {
+        Lockable::lock(self)
+    }
+}
+
+impl<S> StateLock<S> for LocalMutex<S> {
+    type Guard<'a> = <Self as Lockable>::GuardMut<'a>
+    where
+        Self: 'a;
+
+    fn new(state: S) -> Self {
+        LockableNew::new(state)
+    }
+
+    fn lock(&self) -> Self::Guard<'_> {
+        Lockable::lock(self)
+    }
+}
@@ -0,0 +1,405 @@
+//! Task-level instrumentation of the runtimes.
+//!
+//! The tasks spawned by [`WorkStealing`](crate::scheduler::WorkStealing),
+//! [`ThreadPerCore`](crate::executor::ThreadPerCore) and [`LocalSet`](crate::LocalSet) are wrapped by
+//! [`Instrumented`], which records the poll count, the poll duration and the wake-to-poll latency of
+//! each task. Call [`runtime_metrics`] to take a snapshot, and [`set_slow_poll_threshold`] to log a
+//! warning for the polls blocking the worker thread too long:
+//!
+//! ```ignore
+//! hala_future::metrics::set_slow_poll_threshold(Some(Duration::from_millis(10)));
+//!
+//! for task in hala_future::metrics::runtime_metrics().tasks {
+//!     println!("task {}: {:?}", task.id, task);
+//! }
+//! ```
+
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use futures::task::{waker, ArcWake};
+
+thread_local! {
+    /// The id of the task being polled on current thread.
+    static CURRENT: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// The counters of one task, which are shared with its wakers.
+#[derive(Default)]
+struct TaskStats {
+    polls: AtomicU64,
+    wakes: AtomicU64,
+    /// The sum of poll durations in nanoseconds.
+    busy: AtomicU64,
+    max_poll: AtomicU64,
+    max_wake_latency: AtomicU64,
+    /// The time of the first wakeup since the last poll, see [`Registry::now`], zero if not woken.
+    woken_at: AtomicU64,
+}
+
+impl TaskStats {
+    fn snapshot(&self, id: u64) -> TaskMetrics {
+        TaskMetrics {
+            id,
+            polls: self.polls.load(Ordering::Relaxed),
+            wakes: self.wakes.load(Ordering::Relaxed),
+            poll_duration: Duration::from_nanos(self.busy.load(Ordering::Relaxed)),
+            max_poll_duration: Duration::from_nanos(self.max_poll.load(Ordering::Relaxed)),
+            max_wake_latency: Duration::from_nanos(self.max_wake_latency.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+struct Registry {
+    epoch: Instant,
+    next_id: AtomicU64,
+    /// The alive tasks.
+    tasks: DashMap<u64, Arc<TaskStats>>,
+    polls: AtomicU64,
+    slow_polls: AtomicU64,
+    max_poll: AtomicU64,
+    max_wake_latency: AtomicU64,
+    /// The slow poll threshold in nanoseconds, zero if disabled.
+    slow_poll_threshold: AtomicU64,
+}
+
+impl Registry {
+    fn get() -> &'static Registry {
+        static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+        REGISTRY.get_or_init(|| Registry {
+            epoch: Instant::now(),
+            next_id: AtomicU64::new(1),
+            tasks: DashMap::new(),
+            polls: AtomicU64::new(0),
+            slow_polls: AtomicU64::new(0),
+            max_poll: AtomicU64::new(0),
+            max_wake_latency: AtomicU64::new(0),
+            slow_poll_threshold: AtomicU64::new(0),
+        })
+    }
+
+    /// Returns the nanoseconds since the registry creation, which is never zero.
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_nanos() as u64 + 1
+    }
+}
+
+/// Records the wakeup time and forwards to the waker of the executor.
+struct TaskWaker {
+    inner: Waker,
+    stats: Arc<TaskStats>,
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        let stats = &arc_self.stats;
+
+        stats.wakes.fetch_add(1, Ordering::Relaxed);
+
+        // Keeps the first wakeup, the latency is measured from it.
+        _ = stats.woken_at.compare_exchange(
+            0,
+            Registry::get().now(),
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+
+        arc_self.inner.wake_by_ref();
+    }
+}
+
+/// The future wrapper recording the metrics of a task, created by [`instrument`].
+///
+/// The task is listed in [`RuntimeMetrics::tasks`] until this wrapper is dropped.
+pub struct Instrumented<Fut> {
+    fut: Fut,
+    id: u64,
+    stats: Arc<TaskStats>,
+    /// The waker of the executor and the wrapping waker passed to `fut`.
+    waker: Option<(Waker, Waker)>,
+}
+
+/// Wraps `fut` to record its metrics, the spawn APIs of this crate call it for every task.
+///
+/// The spawn time is treated as the first wakeup, so the latency of the first poll is the
+/// scheduling delay of the task.
+pub fn instrument<Fut: Future>(fut: Fut) -> Instrumented<Fut> {
+    let registry = Registry::get();
+
+    let id = registry.next_id.fetch_add(1, Ordering::Relaxed);
+
+    let stats = Arc::new(TaskStats {
+        woken_at: AtomicU64::new(registry.now()),
+        ..Default::default()
+    });
+
+    registry.tasks.insert(id, stats.clone());
+
+    Instrumented {
+        fut,
+        id,
+        stats,
+        waker: None,
+    }
+}
+
+impl<Fut> Instrumented<Fut> {
+    /// Returns the task id, which is unique in the process.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<Fut: Future> Future for Instrumented<Fut> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Safety: `fut` is never moved out, the other fields are not structurally pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        let registry = Registry::get();
+
+        let stats = &this.stats;
+
+        let woken_at = stats.woken_at.swap(0, Ordering::AcqRel);
+
+        if woken_at != 0 {
+            let latency = registry.now().saturating_sub(woken_at);
+
+            stats.max_wake_latency.fetch_max(latency, Ordering::Relaxed);
+            registry
+                .max_wake_latency
+                .fetch_max(latency, Ordering::Relaxed);
+        }
+
+        // The wrapping waker is recreated only if the executor passes another waker.
+        if !matches!(&this.waker, Some((inner, _)) if inner.will_wake(cx.waker())) {
+            let wrapped = waker(Arc::new(TaskWaker {
+                inner: cx.waker().clone(),
+                stats: stats.clone(),
+            }));
+
+            this.waker = Some((cx.waker().clone(), wrapped));
+        }
+
+        let Some((_, wrapped)) = &this.waker else {
+            unreachable!("the waker is set above");
+        };
+
+        let mut wrapped_cx = Context::from_waker(wrapped);
+
+        let prev = CURRENT.replace(Some(this.id));
+
+        let start = Instant::now();
+
+        // Safety: `this` is pinned, see above.
+        let poll = unsafe { Pin::new_unchecked(&mut this.fut) }.poll(&mut wrapped_cx);
+
+        let elapsed = start.elapsed();
+
+        CURRENT.set(prev);
+
+        let nanos = elapsed.as_nanos() as u64;
+
+        stats.polls.fetch_add(1, Ordering::Relaxed);
+        stats.busy.fetch_add(nanos, Ordering::Relaxed);
+        stats.max_poll.fetch_max(nanos, Ordering::Relaxed);
+
+        registry.polls.fetch_add(1, Ordering::Relaxed);
+        registry.max_poll.fetch_max(nanos, Ordering::Relaxed);
+
+        let threshold = registry.slow_poll_threshold.load(Ordering::Relaxed);
+
+        if threshold != 0 && nanos >= threshold {
+            registry.slow_polls.fetch_add(1, Ordering::Relaxed);
+
+            log::warn!(
+                "task {} slow poll, elapsed={:?}, threshold={:?}",
+                this.id,
+                elapsed,
+                Duration::from_nanos(threshold)
+            );
+        }
+
+        poll
+    }
+}
+
+impl<Fut> Drop for Instrumented<Fut> {
+    fn drop(&mut self) {
+        Registry::get().tasks.remove(&self.id);
+    }
+}
+
+/// Returns the id of the task being polled on current thread, or `None` if not polled by the runtimes.
+pub fn current_task_id() -> Option<u64> {
+    CURRENT.get()
+}
+
+/// Sets the threshold of the slow poll warning, or disables the warning with `None`, the default.
+pub fn set_slow_poll_threshold(threshold: Option<Duration>) {
+    let nanos = threshold.map_or(0, |threshold| (threshold.as_nanos() as u64).max(1));
+
+    Registry::get()
+        .slow_poll_threshold
+        .store(nanos, Ordering::Relaxed);
+}
+
+/// Returns the threshold of the slow poll warning.
+pub fn slow_poll_threshold() -> Option<Duration> {
+    match Registry::get().slow_poll_threshold.load(Ordering::Relaxed) {
+        0 => None,
+        nanos => Some(Duration::from_nanos(nanos)),
+    }
+}
+
+/// The metrics of one alive task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskMetrics {
+    /// The task id, see [`current_task_id`].
+    pub id: u64,
+    /// The number of times the task has been polled.
+    pub polls: u64,
+    /// The number of times the task has been woken.
+    pub wakes: u64,
+    /// The total duration of the polls.
+    pub poll_duration: Duration,
+    /// The longest duration of one poll.
+    pub max_poll_duration: Duration,
+    /// The longest delay from a wakeup to the next poll.
+    pub max_wake_latency: Duration,
+}
+
+/// The snapshot returned by [`runtime_metrics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeMetrics {
+    /// The number of tasks spawned since the process started.
+    pub tasks_spawned: u64,
+    /// The number of polls of all tasks, including the completed ones.
+    pub polls: u64,
+    /// The number of polls exceeding the [slow poll threshold](set_slow_poll_threshold).
+    pub slow_polls: u64,
+    /// The longest duration of one poll of all tasks.
+    pub max_poll_duration: Duration,
+    /// The longest delay from a wakeup to the next poll of all tasks.
+    pub max_wake_latency: Duration,
+    /// The alive tasks, sorted by id.
+    pub tasks: Vec<TaskMetrics>,
+}
+
+/// Takes a snapshot of the metrics of all tasks spawned onto the runtimes of this crate.
+pub fn runtime_metrics() -> RuntimeMetrics {
+    let registry = Registry::get();
+
+    let mut tasks = registry
+        .tasks
+        .iter()
+        .map(|entry| entry.value().snapshot(*entry.key()))
+        .collect::<Vec<_>>();
+
+    tasks.sort_by_key(|task| task.id);
+
+    RuntimeMetrics {
+        tasks_spawned: registry.next_id.load(Ordering::Relaxed) - 1,
+        polls: registry.polls.load(Ordering::Relaxed),
+        slow_polls: registry.slow_polls.load(Ordering::Relaxed),
+        max_poll_duration: Duration::from_nanos(registry.max_poll.load(Ordering::Relaxed)),
+        max_wake_latency: Duration::from_nanos(registry.max_wake_latency.load(Ordering::Relaxed)),
+        tasks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use futures::{executor::block_on, future::poll_fn};
+
+    use crate::scheduler::WorkStealing;
+
+    use super::*;
+
+    /// Yields once, and wakes the task immediately.
+    async fn yield_now() {
+        let mut yielded = false;
+
+        poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+
+            yielded = true;
+            cx.waker().wake_by_ref();
+
+            Poll::Pending
+        })
+        .await
+    }
+
+    #[test]
+    fn test_task_metrics() {
+        set_slow_poll_threshold(Some(Duration::from_millis(10)));
+
+        assert_eq!(slow_poll_threshold(), Some(Duration::from_millis(10)));
+
+        let slow_polls = runtime_metrics().slow_polls;
+
+        let task = instrument(async {
+            for _ in 0..3 {
+                yield_now().await;
+            }
+
+            thread::sleep(Duration::from_millis(20));
+
+            yield_now().await;
+
+            let id = current_task_id().unwrap();
+
+            let metrics = runtime_metrics();
+
+            let task = metrics.tasks.iter().find(|task| task.id == id).unwrap();
+
+            assert_eq!(task.polls, 4);
+            assert_eq!(task.wakes, 4);
+            assert!(task.max_poll_duration >= Duration::from_millis(20));
+            assert!(task.poll_duration >= task.max_poll_duration);
+
+            assert!(metrics.slow_polls > slow_polls);
+            assert!(metrics.max_poll_duration >= Duration::from_millis(20));
+
+            id
+        });
+
+        let id = task.id();
+
+        assert_eq!(block_on(task), id);
+
+        assert!(current_task_id().is_none());
+
+        assert!(runtime_metrics().tasks.iter().all(|task| task.id != id));
+
+        set_slow_poll_threshold(None);
+    }
+
+    #[test]
+    fn test_spawn_instrumented() {
+        let runtime = WorkStealing::new(2).unwrap();
+
+        let handle = runtime
+            .spawn_with_handle(async { current_task_id() })
+            .unwrap();
+
+        assert!(block_on(handle).is_some());
+    }
+}
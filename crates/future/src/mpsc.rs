@@ -0,0 +1,554 @@
+//! A multi-producer, single-consumer queue for sending values between asynchronous tasks.
+//!
+//! Channels created by [`channel`] / [`unbounded`] can be shared between threads,
+//! the [`local`] module provides `!Send` variants for single thread mode.
+
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use futures::{future::poll_fn, Stream};
+use hala_sync::SpinMutex;
+
+use crate::lock::StateLock;
+
+/// Error returned by [`send`](Sender::send) function, the channel's receiver had been dropped.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Sending on a closed channel")]
+pub struct SendError<T>(pub T);
+
+/// Error returned by [`try_send`](Sender::try_send) function.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    #[error("Sending on a full channel")]
+    Full(T),
+    #[error("Sending on a closed channel")]
+    Disconnected(T),
+}
+
+/// Error returned by [`try_recv`](Receiver::try_recv) function.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TryRecvError {
+    #[error("Receiving on an empty channel")]
+    Empty,
+    #[error("Receiving on a closed channel")]
+    Disconnected,
+}
+
+/// The shared state of one channel.
+pub struct RawChannel<T> {
+    /// Buffered messages.
+    buf: VecDeque<T>,
+    /// The capacity of message buffer, `None` means unbounded.
+    capacity: Option<usize>,
+    /// Alive senders.
+    senders: usize,
+    /// True if the receiver had been dropped.
+    closed: bool,
+    /// The waker of the pending receiving task.
+    recv_waker: Option<Waker>,
+    /// FIFO queue of the pending sending tasks, tuple (id, waker).
+    send_wakers: VecDeque<(usize, Waker)>,
+    /// The id of next pending sending task.
+    next_send_id: usize,
+}
+
+impl<T> RawChannel<T> {
+    fn new(capacity: Option<usize>) -> Self {
+        Self {
+            buf: Default::default(),
+            capacity,
+            senders: 1,
+            closed: false,
+            recv_waker: None,
+            send_wakers: Default::default(),
+            next_send_id: 0,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        match self.capacity {
+            Some(capacity) => self.buf.len() >= capacity,
+            None => false,
+        }
+    }
+
+    fn wake_receiver(&mut self) {
+        if let Some(waker) = self.recv_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_sender(&mut self) {
+        if let Some((_, waker)) = self.send_wakers.pop_front() {
+            waker.wake();
+        }
+    }
+
+    /// Registers the waker of pending sending task `id`, allocates a new id if `id` is `None`.
+    fn register_sender(&mut self, id: &mut Option<usize>, waker: &Waker) {
+        let key = match *id {
+            Some(key) => key,
+            None => {
+                let key = self.next_send_id;
+
+                self.next_send_id = self.next_send_id.wrapping_add(1);
+
+                *id = Some(key);
+
+                key
+            }
+        };
+
+        // Update the waker if this task is still queued, otherwise it was woken and queues again.
+        match self.send_wakers.iter_mut().find(|(i, _)| *i == key) {
+            Some(sender) => sender.1 = waker.clone(),
+            None => self.send_wakers.push_back((key, waker.clone())),
+        }
+    }
+
+    /// Removes the pending sending task `id`, returns false if it was not queued.
+    fn remove_sender(&mut self, id: usize) -> bool {
+        let len = self.send_wakers.len();
+
+        self.send_wakers.retain(|(i, _)| *i != id);
+
+        self.send_wakers.len() != len
+    }
+}
+
+/// Removes the pending sending task from the queue when the [`send`](Sender::send) future is dropped.
+struct SendWaiter<'a, T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    raw: &'a L,
+    /// The id of pending sending task, `None` if not yet pending.
+    id: Option<usize>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<'a, T, L> Drop for SendWaiter<'a, T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            let mut raw = self.raw.lock();
+
+            // This task was woken but cancelled before sending, passes the wakeup on.
+            if !raw.remove_sender(id) && !raw.is_full() {
+                raw.wake_sender();
+            }
+        }
+    }
+}
+
+/// The sending-half of channel, which can be cloned to send to the same channel from multiple code locations.
+pub struct Sender<T, L = SpinMutex<RawChannel<T>>>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    raw: Arc<L>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+/// The receiving-half of channel.
+pub struct Receiver<T, L = SpinMutex<RawChannel<T>>>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    raw: Arc<L>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T, L> Debug for Sender<T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "mpsc::Sender")
+    }
+}
+
+impl<T, L> Debug for Receiver<T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "mpsc::Receiver")
+    }
+}
+
+fn new_channel<T, L>(capacity: Option<usize>) -> (Sender<T, L>, Receiver<T, L>)
+where
+    L: StateLock<RawChannel<T>>,
+{
+    let raw = Arc::new(L::new(RawChannel::new(capacity)));
+
+    (
+        Sender {
+            raw: raw.clone(),
+            _marker: Default::default(),
+        },
+        Receiver {
+            raw,
+            _marker: Default::default(),
+        },
+    )
+}
+
+/// Creates a bounded channel, the [`send`](Sender::send) function will suspend the current task
+/// when the channel's buffer holds `capacity` messages.
+///
+/// #Panic
+///
+/// Passing zero `capacity` will cause panic.
+pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "create bounded channel with zero capacity");
+
+    new_channel(Some(capacity))
+}
+
+/// Creates an unbounded channel, the [`send`](Sender::send) function will never suspend the current task.
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    new_channel(None)
+}
+
+impl<T, L> Sender<T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    /// Attempts to send a message on this channel without suspending the current task.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut raw = self.raw.lock();
+
+        if raw.closed {
+            return Err(TrySendError::Disconnected(value));
+        }
+
+        if raw.is_full() {
+            return Err(TrySendError::Full(value));
+        }
+
+        raw.buf.push_back(value);
+
+        raw.wake_receiver();
+
+        Ok(())
+    }
+
+    /// Sends a message on this channel, if the channel is full, suspends the current task
+    /// until there is space in the buffer.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut value = Some(value);
+
+        let mut waiter = SendWaiter {
+            raw: &*self.raw,
+            id: None,
+            _marker: Default::default(),
+        };
+
+        poll_fn(|cx| {
+            let mut raw = waiter.raw.lock();
+
+            if !raw.closed && raw.is_full() {
+                raw.register_sender(&mut waiter.id, cx.waker());
+
+                return Poll::Pending;
+            }
+
+            if let Some(id) = waiter.id.take() {
+                raw.remove_sender(id);
+            }
+
+            if raw.closed {
+                return Poll::Ready(Err(SendError(value.take().unwrap())));
+            }
+
+            raw.buf.push_back(value.take().unwrap());
+
+            raw.wake_receiver();
+
+            Poll::Ready(Ok(()))
+        })
+        .await
+    }
+
+    /// Returns true if the receiver of this channel had been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.raw.lock().closed
+    }
+}
+
+impl<T, L> Clone for Sender<T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    fn clone(&self) -> Self {
+        self.raw.lock().senders += 1;
+
+        Self {
+            raw: self.raw.clone(),
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<T, L> Drop for Sender<T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    fn drop(&mut self) {
+        let mut raw = self.raw.lock();
+
+        raw.senders -= 1;
+
+        if raw.senders == 0 {
+            raw.wake_receiver();
+        }
+    }
+}
+
+impl<T, L> Receiver<T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    /// Attempts to receive a message without suspending the current task.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut raw = self.raw.lock();
+
+        if let Some(value) = raw.buf.pop_front() {
+            raw.wake_sender();
+
+            return Ok(value);
+        }
+
+        if raw.senders == 0 {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Poll version of [`recv`](Self::recv) function.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut raw = self.raw.lock();
+
+        if let Some(value) = raw.buf.pop_front() {
+            raw.wake_sender();
+
+            return Poll::Ready(Some(value));
+        }
+
+        if raw.senders == 0 {
+            return Poll::Ready(None);
+        }
+
+        raw.recv_waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+
+    /// Receives the next message, or returns `None` if all senders had been dropped
+    /// and there are no more buffered messages.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Closes the receiving half of a channel, without dropping it.
+    ///
+    /// The buffered messages can still be received.
+    pub fn close(&mut self) {
+        let mut raw = self.raw.lock();
+
+        raw.closed = true;
+
+        while let Some((_, waker)) = raw.send_wakers.pop_front() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T, L> Drop for Receiver<T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl<T, L> Stream for Receiver<T, L>
+where
+    L: StateLock<RawChannel<T>>,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T, L> Unpin for Receiver<T, L> where L: StateLock<RawChannel<T>> {}
+
+/// `!Send` channel variants for single thread mode.
+pub mod local {
+    use hala_sync::LocalMutex;
+
+    use super::RawChannel;
+
+    /// The sending-half of local channel.
+    pub type Sender<T> = super::Sender<T, LocalMutex<RawChannel<T>>>;
+
+    /// The receiving-half of local channel.
+    pub type Receiver<T> = super::Receiver<T, LocalMutex<RawChannel<T>>>;
+
+    /// Creates a bounded `!Send` channel, see [`channel`](super::channel) for more information.
+    pub fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+        assert!(capacity > 0, "create bounded channel with zero capacity");
+
+        super::new_channel(Some(capacity))
+    }
+
+    /// Creates an unbounded `!Send` channel, see [`unbounded`](super::unbounded) for more information.
+    pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+        super::new_channel(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+
+    use futures::{executor::ThreadPool, task::SpawnExt, StreamExt};
+    use futures_test::task::new_count_waker;
+
+    use super::*;
+
+    #[futures_test::test]
+    async fn test_bounded_channel() {
+        let pool = ThreadPool::builder().pool_size(10).create().unwrap();
+
+        let (sender, mut receiver) = channel(4);
+
+        let loops = 1000;
+        let senders = 10;
+
+        for _ in 0..senders {
+            let sender = sender.clone();
+
+            pool.spawn(async move {
+                for i in 0..loops {
+                    sender.send(i).await.unwrap();
+                }
+            })
+            .unwrap();
+        }
+
+        drop(sender);
+
+        let mut count = 0;
+
+        while receiver.recv().await.is_some() {
+            count += 1;
+        }
+
+        assert_eq!(count, loops * senders);
+    }
+
+    #[futures_test::test]
+    async fn test_try_send_recv() {
+        let (sender, mut receiver) = channel(1);
+
+        sender.try_send(1).unwrap();
+
+        assert_eq!(sender.try_send(2), Err(TrySendError::Full(2)));
+
+        assert_eq!(receiver.try_recv(), Ok(1));
+
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        drop(receiver);
+
+        assert_eq!(sender.send(3).await, Err(SendError(3)));
+    }
+
+    #[futures_test::test]
+    async fn test_local_unbounded() {
+        let (sender, receiver) = local::unbounded();
+
+        for i in 0..100 {
+            sender.send(i).await.unwrap();
+        }
+
+        drop(sender);
+
+        assert_eq!(
+            receiver.collect::<Vec<_>>().await,
+            (0..100).collect::<Vec<_>>()
+        );
+    }
+
+    fn check_cancelled_sender<L>(sender: Sender<u32, L>, mut receiver: Receiver<u32, L>)
+    where
+        L: StateLock<RawChannel<u32>>,
+    {
+        sender.try_send(0).unwrap();
+
+        let (cancelled_waker, _) = new_count_waker();
+        let (waker, count) = new_count_waker();
+
+        let mut cancelled_cx = Context::from_waker(&cancelled_waker);
+        let mut cx = Context::from_waker(&waker);
+
+        // The pending sender is cancelled before the channel has space.
+        let mut cancelled = Box::pin(sender.send(1));
+        let mut send = Box::pin(sender.send(2));
+
+        assert!(cancelled.as_mut().poll(&mut cancelled_cx).is_pending());
+        assert!(send.as_mut().poll(&mut cx).is_pending());
+
+        drop(cancelled);
+
+        assert_eq!(receiver.try_recv(), Ok(0));
+        assert_eq!(count.get(), 1);
+        assert!(send.as_mut().poll(&mut cx).is_ready());
+
+        // The pending sender is woken and then cancelled before sending.
+        let mut cancelled = Box::pin(sender.send(3));
+        let mut send = Box::pin(sender.send(4));
+
+        assert!(cancelled.as_mut().poll(&mut cancelled_cx).is_pending());
+        assert!(send.as_mut().poll(&mut cx).is_pending());
+
+        assert_eq!(receiver.try_recv(), Ok(2));
+        assert_eq!(count.get(), 1);
+
+        drop(cancelled);
+
+        assert_eq!(count.get(), 2);
+        assert!(send.as_mut().poll(&mut cx).is_ready());
+
+        assert_eq!(receiver.try_recv(), Ok(4));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_cancelled_sender() {
+        let (sender, receiver) = channel(1);
+
+        check_cancelled_sender(sender, receiver);
+    }
+
+    #[test]
+    fn test_local_cancelled_sender() {
+        let (sender, receiver) = local::channel(1);
+
+        check_cancelled_sender(sender, receiver);
+    }
+}
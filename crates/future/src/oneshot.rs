@@ -0,0 +1,252 @@
+//! A channel for sending a single message between asynchronous tasks.
+//!
+//! Channels created by [`channel`] can be shared between threads,
+//! the [`local`] module provides a `!Send` variant for single thread mode.
+
+use std::{
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use hala_sync::SpinMutex;
+
+use crate::lock::StateLock;
+
+/// Error returned by [`Receiver`] future, the [`Sender`] had been dropped without sending a message.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("Oneshot sender canceled")]
+pub struct Canceled;
+
+/// The shared state of one oneshot channel.
+pub struct RawOneshot<T> {
+    /// The sent message.
+    value: Option<T>,
+    /// True if either half of this channel had been dropped.
+    closed: bool,
+    /// The waker of the pending receiving task.
+    recv_waker: Option<Waker>,
+}
+
+impl<T> Default for RawOneshot<T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            closed: false,
+            recv_waker: None,
+        }
+    }
+}
+
+/// The sending-half of oneshot channel.
+pub struct Sender<T, L = SpinMutex<RawOneshot<T>>>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    raw: Arc<L>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+/// The receiving-half of oneshot channel, which is a future resolves to the sent message.
+pub struct Receiver<T, L = SpinMutex<RawOneshot<T>>>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    raw: Arc<L>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T, L> Debug for Sender<T, L>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "oneshot::Sender")
+    }
+}
+
+impl<T, L> Debug for Receiver<T, L>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "oneshot::Receiver")
+    }
+}
+
+fn new_channel<T, L>() -> (Sender<T, L>, Receiver<T, L>)
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    let raw = Arc::new(L::new(Default::default()));
+
+    (
+        Sender {
+            raw: raw.clone(),
+            _marker: Default::default(),
+        },
+        Receiver {
+            raw,
+            _marker: Default::default(),
+        },
+    )
+}
+
+/// Creates a new oneshot channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    new_channel()
+}
+
+impl<T, L> Sender<T, L>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    /// Completes this oneshot with a successful result.
+    ///
+    /// Returns `Err(value)` if the [`Receiver`] had been dropped.
+    pub fn send(self, value: T) -> Result<(), T> {
+        let mut raw = self.raw.lock();
+
+        if raw.closed {
+            return Err(value);
+        }
+
+        raw.value = Some(value);
+
+        Ok(())
+    }
+
+    /// Returns true if the [`Receiver`] had been dropped.
+    pub fn is_canceled(&self) -> bool {
+        self.raw.lock().closed
+    }
+}
+
+impl<T, L> Drop for Sender<T, L>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    fn drop(&mut self) {
+        let mut raw = self.raw.lock();
+
+        raw.closed = true;
+
+        if let Some(waker) = raw.recv_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T, L> Receiver<T, L>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    /// Attempts to receive the message without suspending the current task.
+    ///
+    /// Returns `Ok(None)` if the message is not ready yet.
+    pub fn try_recv(&mut self) -> Result<Option<T>, Canceled> {
+        let mut raw = self.raw.lock();
+
+        if let Some(value) = raw.value.take() {
+            return Ok(Some(value));
+        }
+
+        if raw.closed {
+            Err(Canceled)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T, L> Drop for Receiver<T, L>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    fn drop(&mut self) {
+        self.raw.lock().closed = true;
+    }
+}
+
+impl<T, L> Future for Receiver<T, L>
+where
+    L: StateLock<RawOneshot<T>>,
+{
+    type Output = Result<T, Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut raw = self.raw.lock();
+
+        if let Some(value) = raw.value.take() {
+            return Poll::Ready(Ok(value));
+        }
+
+        if raw.closed {
+            return Poll::Ready(Err(Canceled));
+        }
+
+        raw.recv_waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
+
+impl<T, L> Unpin for Receiver<T, L> where L: StateLock<RawOneshot<T>> {}
+
+/// `!Send` oneshot channel variant for single thread mode.
+pub mod local {
+    use hala_sync::LocalMutex;
+
+    use super::RawOneshot;
+
+    /// The sending-half of local oneshot channel.
+    pub type Sender<T> = super::Sender<T, LocalMutex<RawOneshot<T>>>;
+
+    /// The receiving-half of local oneshot channel.
+    pub type Receiver<T> = super::Receiver<T, LocalMutex<RawOneshot<T>>>;
+
+    /// Creates a new `!Send` oneshot channel.
+    pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+        super::new_channel()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::ThreadPool, task::SpawnExt};
+
+    use super::*;
+
+    #[futures_test::test]
+    async fn test_oneshot() {
+        let pool = ThreadPool::builder().pool_size(10).create().unwrap();
+
+        for i in 0..1000 {
+            let (sender, receiver) = channel();
+
+            pool.spawn(async move {
+                sender.send(i).unwrap();
+            })
+            .unwrap();
+
+            assert_eq!(receiver.await, Ok(i));
+        }
+    }
+
+    #[futures_test::test]
+    async fn test_canceled() {
+        let (sender, receiver) = local::channel::<i32>();
+
+        drop(sender);
+
+        assert_eq!(receiver.await, Err(Canceled));
+
+        let (sender, receiver) = channel();
+
+        drop(receiver);
+
+        assert_eq!(sender.send(1), Err(1));
+    }
+}
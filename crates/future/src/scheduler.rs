@@ -0,0 +1,492 @@
+//! Work-stealing multi-thread runtime.
+//!
+//! Each worker owns a run queue, the tasks woken on a worker thread are pushed into its own queue,
+//! and the tasks spawned or woken by other threads are pushed into the shared injector queue.
+//! An idle worker takes tasks from the injector and steals from the other workers before parking.
+//!
+//! The io events can be polled by the idle workers too: with [`WorkStealingBuilder::io_poller`],
+//! one idle worker at a time calls the poller instead of parking, so no dedicated event loop
+//! thread is needed.
+
+use std::{
+    cell::Cell,
+    future::Future,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock, Weak,
+    },
+    task::Context,
+    thread::{self, JoinHandle, Thread},
+};
+
+use futures::{
+    future::{BoxFuture, FutureObj, RemoteHandle},
+    task::{waker_ref, ArcWake, Spawn, SpawnError, SpawnExt},
+    FutureExt,
+};
+use hala_lockfree::queue::Queue;
+use hala_sync::{Lockable, LockableNew, SpinMutex};
+
+use crate::metrics::instrument;
+
+/// The function polling the io events once, see [`WorkStealingBuilder::io_poller`].
+type IoPoller = Arc<dyn Fn() -> io::Result<()> + Send + Sync>;
+
+thread_local! {
+    /// The address of the shared state and the index of the worker running on current thread.
+    static CURRENT: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+struct Task {
+    future: SpinMutex<Option<BoxFuture<'static, ()>>>,
+    /// The task is in a run queue.
+    scheduled: AtomicBool,
+    /// The wakers may outlive the runtime, e.g. registered in the io driver.
+    shared: Weak<Shared>,
+}
+
+impl ArcWake for Task {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if arc_self.scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        if let Some(shared) = arc_self.shared.upgrade() {
+            shared.schedule(arc_self.clone());
+        }
+    }
+}
+
+impl Task {
+    fn run(self: Arc<Self>) {
+        // Clear the flag before polling, so the wakeup during polling schedules this task again.
+        self.scheduled.store(false, Ordering::Release);
+
+        let mut future = self.future.lock();
+
+        let Some(fut) = future.as_mut() else {
+            return;
+        };
+
+        let waker = waker_ref(&self);
+
+        let mut cx = Context::from_waker(&waker);
+
+        if fut.as_mut().poll(&mut cx).is_ready() {
+            *future = None;
+        }
+    }
+}
+
+struct WorkerState {
+    queue: Queue<Arc<Task>>,
+    /// Set by the worker thread itself before it parks.
+    thread: OnceLock<Thread>,
+}
+
+struct Shared {
+    injector: Queue<Arc<Task>>,
+    workers: Vec<WorkerState>,
+    /// The indexes of parked workers.
+    parked: SpinMutex<Vec<usize>>,
+    /// One idle worker is calling `io_poller`.
+    polling: AtomicBool,
+    shutdown: AtomicBool,
+    io_poller: Option<IoPoller>,
+}
+
+impl Shared {
+    fn id(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    fn schedule(&self, task: Arc<Task>) {
+        match CURRENT.get() {
+            Some((id, index)) if id == self.id() => self.workers[index].queue.push(task),
+            _ => self.injector.push(task),
+        }
+
+        self.notify_one();
+    }
+
+    fn notify_one(&self) {
+        let index = self.parked.lock().pop();
+
+        if let Some(thread) = index.and_then(|index| self.workers[index].thread.get()) {
+            thread.unpark();
+        }
+    }
+
+    fn next_task(&self, index: usize) -> Option<Arc<Task>> {
+        if let Some(task) = self.workers[index].queue.pop() {
+            return Some(task);
+        }
+
+        if let Some(task) = self.injector.pop() {
+            return Some(task);
+        }
+
+        let len = self.workers.len();
+
+        (1..len)
+            .map(|offset| (index + offset) % len)
+            .find_map(|victim| self.workers[victim].queue.pop())
+    }
+
+    /// Polls the io events once if no other worker is polling, returns false if not polled.
+    fn poll_io(&self) -> bool {
+        let Some(io_poller) = &self.io_poller else {
+            return false;
+        };
+
+        if self.polling.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        if let Err(err) = io_poller() {
+            log::error!("work-stealing runtime poll io events failed, err={}", err);
+        }
+
+        self.polling.store(false, Ordering::Release);
+
+        true
+    }
+
+    fn park(&self, index: usize) {
+        self.parked.lock().push(index);
+
+        // Check again after registration, the task scheduled before it would not unpark this worker.
+        if let Some(task) = self.next_task(index) {
+            self.unregister_parked(index);
+
+            task.run();
+
+            return;
+        }
+
+        if !self.shutdown.load(Ordering::Acquire) {
+            thread::park();
+        }
+
+        // Also removes the index after a spurious wakeup.
+        self.unregister_parked(index);
+    }
+
+    fn unregister_parked(&self, index: usize) {
+        self.parked.lock().retain(|parked| *parked != index);
+    }
+}
+
+fn run_worker(shared: Arc<Shared>, index: usize) {
+    _ = shared.workers[index].thread.set(thread::current());
+
+    CURRENT.set(Some((shared.id(), index)));
+
+    while !shared.shutdown.load(Ordering::Acquire) {
+        if let Some(task) = shared.next_task(index) {
+            task.run();
+            continue;
+        }
+
+        if shared.poll_io() {
+            continue;
+        }
+
+        shared.park(index);
+    }
+
+    CURRENT.set(None);
+}
+
+/// Builder for [`WorkStealing`] runtime.
+pub struct WorkStealingBuilder {
+    workers: usize,
+    thread_name: String,
+    io_poller: Option<IoPoller>,
+}
+
+impl Default for WorkStealingBuilder {
+    fn default() -> Self {
+        Self {
+            workers: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            thread_name: "hala-stealing".into(),
+            io_poller: None,
+        }
+    }
+}
+
+impl WorkStealingBuilder {
+    /// Sets the number of worker threads, the default value is the number of available cpus.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Sets the name prefix of worker threads.
+    pub fn thread_name(mut self, name: &str) -> Self {
+        self.thread_name = name.to_owned();
+        self
+    }
+
+    /// Sets the function polling the io events once, which is called by one idle worker at a time
+    /// instead of parking the thread, e.g. `move || driver.fd_cntl(poller, Cmd::PollOnce(Some(timeout)))`.
+    ///
+    /// The worker can't be unparked while polling, so `f` should return in a bounded time,
+    /// the tasks spawned meanwhile are run by the other workers or after `f` returns.
+    pub fn io_poller<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.io_poller = Some(Arc::new(f));
+        self
+    }
+
+    /// Starts the worker threads and creates the runtime.
+    pub fn build(self) -> io::Result<WorkStealing> {
+        if self.workers == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "workers must be greater than zero",
+            ));
+        }
+
+        let shared = Arc::new(Shared {
+            injector: Queue::new(),
+            workers: (0..self.workers)
+                .map(|_| WorkerState {
+                    queue: Queue::new(),
+                    thread: OnceLock::new(),
+                })
+                .collect(),
+            parked: SpinMutex::new(vec![]),
+            polling: AtomicBool::new(false),
+            shutdown: AtomicBool::new(false),
+            io_poller: self.io_poller,
+        });
+
+        let mut runtime = WorkStealing {
+            spawner: WorkStealingSpawner {
+                shared: shared.clone(),
+            },
+            handles: vec![],
+        };
+
+        for index in 0..self.workers {
+            let handle = thread::Builder::new()
+                .name(format!("{}-{}", self.thread_name, index))
+                .spawn({
+                    let shared = shared.clone();
+
+                    move || run_worker(shared, index)
+                })?;
+
+            runtime.handles.push(handle);
+        }
+
+        Ok(runtime)
+    }
+}
+
+/// The spawner of [`WorkStealing`] runtime, which can be cloned and sent to other threads.
+#[derive(Clone)]
+pub struct WorkStealingSpawner {
+    shared: Arc<Shared>,
+}
+
+impl Spawn for WorkStealingSpawner {
+    fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        if self.shared.shutdown.load(Ordering::Acquire) {
+            return Err(SpawnError::shutdown());
+        }
+
+        let task = Arc::new(Task {
+            future: SpinMutex::new(Some(instrument(future).boxed())),
+            scheduled: AtomicBool::new(true),
+            shared: Arc::downgrade(&self.shared),
+        });
+
+        self.shared.schedule(task);
+
+        Ok(())
+    }
+}
+
+/// The work-stealing runtime, the worker threads stop when this runtime is dropped.
+pub struct WorkStealing {
+    spawner: WorkStealingSpawner,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl WorkStealing {
+    /// Creates a runtime with `workers` threads, see [`WorkStealingBuilder`] for more options.
+    pub fn new(workers: usize) -> io::Result<Self> {
+        Self::builder().workers(workers).build()
+    }
+
+    /// Creates a [`WorkStealingBuilder`] with default options.
+    pub fn builder() -> WorkStealingBuilder {
+        WorkStealingBuilder::default()
+    }
+
+    /// Returns the number of worker threads.
+    pub fn workers(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Returns a spawner of this runtime.
+    pub fn spawner(&self) -> WorkStealingSpawner {
+        self.spawner.clone()
+    }
+
+    /// Spawns a task onto this runtime.
+    pub fn spawn<Fut>(&self, fut: Fut) -> io::Result<()>
+    where
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.spawner.spawn(fut).map_err(io::Error::other)
+    }
+
+    /// Spawns a task onto this runtime, and returns a handle to get the output of the task.
+    ///
+    /// The task is cancelled if the handle is dropped, see [`RemoteHandle`] for more information.
+    pub fn spawn_with_handle<Fut>(&self, fut: Fut) -> io::Result<RemoteHandle<Fut::Output>>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send,
+    {
+        self.spawner
+            .spawn_with_handle(fut)
+            .map_err(io::Error::other)
+    }
+}
+
+impl Drop for WorkStealing {
+    fn drop(&mut self) {
+        let shared = &self.spawner.shared;
+
+        shared.shutdown.store(true, Ordering::Release);
+
+        for worker in &shared.workers {
+            if let Some(thread) = worker.thread.get() {
+                thread.unpark();
+            }
+        }
+
+        for handle in self.handles.drain(..) {
+            _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{atomic::AtomicUsize, mpsc::channel},
+        time::Duration,
+    };
+
+    use futures::{channel::oneshot, executor::block_on};
+
+    use super::*;
+
+    #[test]
+    fn test_spawn() {
+        let runtime = WorkStealing::new(4).unwrap();
+
+        let handles = (0..100)
+            .map(|i| runtime.spawn_with_handle(async move { i * 2 }).unwrap())
+            .collect::<Vec<_>>();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(block_on(handle), i * 2);
+        }
+    }
+
+    #[test]
+    fn test_steal() {
+        let runtime = WorkStealing::new(4).unwrap();
+
+        let (sender, receiver) = channel();
+
+        // All tasks are spawned into the queue of one worker, and stolen by the others.
+        runtime
+            .spawn({
+                let spawner = runtime.spawner();
+
+                async move {
+                    for _ in 0..16 {
+                        let sender = sender.clone();
+
+                        spawner
+                            .spawn(async move {
+                                thread::sleep(Duration::from_millis(20));
+
+                                sender.send(thread::current().id()).unwrap();
+                            })
+                            .unwrap();
+                    }
+                }
+            })
+            .unwrap();
+
+        let threads = (0..16)
+            .map(|_| receiver.recv().unwrap())
+            .collect::<HashSet<_>>();
+
+        assert!(threads.len() > 1);
+    }
+
+    #[test]
+    fn test_io_poller() {
+        let polls = Arc::new(AtomicUsize::new(0));
+
+        let (sender, receiver) = oneshot::channel::<()>();
+
+        let sender = Arc::new(SpinMutex::new(Some(sender)));
+
+        let runtime = WorkStealing::builder()
+            .workers(2)
+            .io_poller({
+                let polls = polls.clone();
+
+                move || {
+                    // Simulates the io event, which wakes the task waiting on `receiver`.
+                    if polls.fetch_add(1, Ordering::Relaxed) == 10 {
+                        if let Some(sender) = sender.lock().take() {
+                            _ = sender.send(());
+                        }
+                    }
+
+                    thread::sleep(Duration::from_millis(1));
+
+                    Ok(())
+                }
+            })
+            .build()
+            .unwrap();
+
+        let handle = runtime.spawn_with_handle(receiver).unwrap();
+
+        block_on(handle).unwrap();
+
+        assert!(polls.load(Ordering::Relaxed) > 10);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let runtime = WorkStealing::new(2).unwrap();
+
+        let spawner = runtime.spawner();
+
+        // The pending task doesn't block the shutdown.
+        runtime.spawn(futures::future::pending()).unwrap();
+
+        drop(runtime);
+
+        assert!(spawner.spawn(async {}).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+//! Structured concurrency for the tasks of one owner.
+//!
+//! The tasks spawned by [`Scope::spawn`] are polled by the future returned from [`scope`] instead of
+//! an executor, so they can borrow the caller's stack, and never outlive the scope:
+//!
+//! ```ignore
+//! hala_future::scope(|s| async move {
+//!     s.spawn(send_loop(&socket));
+//!     s.spawn(recv_loop(&socket));
+//!
+//!     Ok(())
+//! })
+//! .await?;
+//! ```
+//!
+//! The scope future completes after the body and all the spawned tasks have completed. The first
+//! error or panic of the body or any task is propagated by the scope future, and the other tasks
+//! are dropped.
+
+use std::{
+    future::Future,
+    mem::take,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+use hala_sync::*;
+
+type Task<'env, E> = BoxFuture<'env, Result<(), E>>;
+
+struct RawScope<'env, E> {
+    /// The tasks spawned but not yet polled by the scope future.
+    spawned: Vec<Task<'env, E>>,
+    waker: Option<Waker>,
+    /// The scope future has completed or been dropped, the later spawned tasks are dropped.
+    closed: bool,
+}
+
+/// The handle to spawn tasks into the scope created by [`scope`], which can be cloned and
+/// moved into the spawned tasks.
+pub struct Scope<'env, E> {
+    raw: Arc<SpinMutex<RawScope<'env, E>>>,
+}
+
+impl<'env, E> Clone for Scope<'env, E> {
+    fn clone(&self) -> Self {
+        Self {
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+impl<'env, E> Scope<'env, E> {
+    /// Spawns a task polled by the scope future, which must complete before the scope.
+    ///
+    /// The task is dropped if the scope has completed, e.g. spawned by a handle moved out of the scope.
+    pub fn spawn<Fut>(&self, fut: Fut)
+    where
+        Fut: Future<Output = Result<(), E>> + Send + 'env,
+    {
+        let mut raw = self.raw.lock();
+
+        if raw.closed {
+            return;
+        }
+
+        raw.spawned.push(Box::pin(fut));
+
+        let waker = raw.waker.take();
+
+        drop(raw);
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Creates a scope for spawning tasks, the body `f` is called with the [`Scope`] handle.
+///
+/// The returned future resolves to the output of the body after all the spawned tasks have completed,
+/// or to the first error of the body and the tasks, see the [module-level documentation](self).
+pub fn scope<'env, F, Fut, T, E>(f: F) -> ScopeFuture<'env, Fut, T, E>
+where
+    F: FnOnce(Scope<'env, E>) -> Fut,
+    Fut: Future<Output = Result<T, E>> + 'env,
+{
+    let scope = Scope {
+        raw: Arc::new(SpinMutex::new(RawScope {
+            spawned: vec![],
+            waker: None,
+            closed: false,
+        })),
+    };
+
+    ScopeFuture {
+        body: Some(Box::pin(f(scope.clone()))),
+        output: None,
+        tasks: FuturesUnordered::new(),
+        scope,
+    }
+}
+
+/// The future created by [`scope`].
+pub struct ScopeFuture<'env, Fut, T, E> {
+    body: Option<Pin<Box<Fut>>>,
+    /// The output of the completed body.
+    output: Option<T>,
+    tasks: FuturesUnordered<Task<'env, E>>,
+    scope: Scope<'env, E>,
+}
+
+// The fields are never pinned, the body is boxed.
+impl<'env, Fut, T, E> Unpin for ScopeFuture<'env, Fut, T, E> {}
+
+impl<'env, Fut, T, E> ScopeFuture<'env, Fut, T, E> {
+    /// Drops the body and all the tasks, the later spawned tasks are dropped too.
+    fn close(&mut self) {
+        let spawned = {
+            let mut raw = self.scope.raw.lock();
+
+            raw.closed = true;
+            raw.waker = None;
+
+            take(&mut raw.spawned)
+        };
+
+        // Dropped outside the lock, the tasks may spawn on drop.
+        drop(spawned);
+
+        self.tasks.clear();
+        self.body = None;
+    }
+
+    /// Polls `f`, closes the scope and resumes the panic if `f` panics.
+    fn catch_panic<R, F: FnOnce(&mut Self) -> R>(&mut self, f: F) -> R {
+        match catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(r) => r,
+            Err(payload) => {
+                self.close();
+                resume_unwind(payload)
+            }
+        }
+    }
+}
+
+impl<'env, Fut, T, E> Future for ScopeFuture<'env, Fut, T, E>
+where
+    Fut: Future<Output = Result<T, E>>,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        this.scope.raw.lock().waker = Some(cx.waker().clone());
+
+        if this.body.is_some() {
+            let poll = this.catch_panic(|this| this.body.as_mut().unwrap().poll_unpin(cx));
+
+            match poll {
+                Poll::Ready(Ok(output)) => {
+                    this.body = None;
+                    this.output = Some(output);
+                }
+                Poll::Ready(Err(err)) => {
+                    this.close();
+                    return Poll::Ready(Err(err));
+                }
+                Poll::Pending => {}
+            }
+        }
+
+        loop {
+            let spawned = take(&mut this.scope.raw.lock().spawned);
+
+            this.tasks.extend(spawned);
+
+            match this.catch_panic(|this| this.tasks.poll_next_unpin(cx)) {
+                Poll::Ready(Some(Ok(()))) => {}
+                Poll::Ready(Some(Err(err))) => {
+                    this.close();
+                    return Poll::Ready(Err(err));
+                }
+                // The body wakes up the scope future.
+                Poll::Ready(None) if this.body.is_some() => return Poll::Pending,
+                Poll::Ready(None) => {
+                    if !this.scope.raw.lock().spawned.is_empty() {
+                        continue;
+                    }
+
+                    this.close();
+
+                    return Poll::Ready(Ok(this
+                        .output
+                        .take()
+                        .expect("scope future polled after completion")));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<'env, Fut, T, E> Drop for ScopeFuture<'env, Fut, T, E> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        panic::catch_unwind,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use futures::{
+        executor::block_on,
+        future::{pending, poll_fn},
+    };
+
+    use super::*;
+
+    /// Returns pending once and wakes up the task immediately.
+    async fn yield_now() {
+        let mut yielded = false;
+
+        poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+
+            yielded = true;
+            cx.waker().wake_by_ref();
+
+            Poll::Pending
+        })
+        .await
+    }
+
+    #[test]
+    fn test_scope_join() {
+        let counter = AtomicUsize::new(0);
+
+        let output = block_on(scope(|s| {
+            let counter = &counter;
+
+            async move {
+                for _ in 0..10 {
+                    let nested = s.clone();
+
+                    s.spawn(async move {
+                        yield_now().await;
+
+                        // The nested task is joined too.
+                        nested.spawn(async move {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            Ok(())
+                        });
+
+                        counter.fetch_add(1, Ordering::SeqCst);
+
+                        Ok::<_, io::Error>(())
+                    });
+                }
+
+                Ok(1)
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(output, 1);
+        assert_eq!(counter.load(Ordering::SeqCst), 20);
+    }
+
+    #[test]
+    fn test_scope_error() {
+        struct Guard<'a>(&'a AtomicUsize);
+
+        impl<'a> Drop for Guard<'a> {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let dropped = AtomicUsize::new(0);
+
+        let err = block_on(scope(|s| {
+            let guard = Guard(&dropped);
+
+            async move {
+                s.spawn(async move {
+                    let _guard = guard;
+                    pending::<()>().await;
+                    Ok(())
+                });
+
+                s.spawn(async { Err(io::Error::other("task failed")) });
+
+                Ok(())
+            }
+        }))
+        .unwrap_err();
+
+        assert_eq!(err.to_string(), "task failed");
+
+        // The pending task is dropped.
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_scope_panic() {
+        let result = catch_unwind(|| {
+            block_on(scope(|s| async move {
+                s.spawn(async {
+                    yield_now().await;
+                    panic!("task panicked");
+                });
+
+                Ok::<_, io::Error>(())
+            }))
+        });
+
+        assert!(result.is_err());
+    }
+}
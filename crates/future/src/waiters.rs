@@ -0,0 +1,246 @@
+use std::fmt::Debug;
+
+/// The key of one waiter in [`WaiterList`], which is invalidated when the waiter is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WaiterKey {
+    index: usize,
+    generation: usize,
+}
+
+enum Node<T> {
+    Occupied {
+        value: T,
+        generation: usize,
+        prev: Option<usize>,
+        next: Option<usize>,
+    },
+    Vacant {
+        generation: usize,
+        next_free: Option<usize>,
+    },
+}
+
+/// A FIFO list of waiters, which is backed by a slab and linked by integer indexes.
+///
+/// All of `push_back` / `pop_front` / `remove` are O(1), and the removed slots are reused
+/// without allocation, so a cancelled waiter can remove itself without scanning the list.
+pub struct WaiterList<T> {
+    nodes: Vec<Node<T>>,
+    free: Option<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    len: usize,
+}
+
+impl<T> Default for WaiterList<T> {
+    fn default() -> Self {
+        Self {
+            nodes: vec![],
+            free: None,
+            head: None,
+            tail: None,
+            len: 0,
+        }
+    }
+}
+
+impl<T: Debug> Debug for WaiterList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> WaiterList<T> {
+    /// Create new empty list.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the number of waiters in this list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if this list contains no waiters.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends one waiter to the back of this list, and returns the key to remove it.
+    pub fn push_back(&mut self, value: T) -> WaiterKey {
+        let prev = self.tail;
+
+        let index = match self.free {
+            Some(index) => {
+                let Node::Vacant {
+                    generation,
+                    next_free,
+                } = self.nodes[index]
+                else {
+                    unreachable!("the free list links occupied node");
+                };
+
+                self.free = next_free;
+
+                self.nodes[index] = Node::Occupied {
+                    value,
+                    generation,
+                    prev,
+                    next: None,
+                };
+
+                index
+            }
+            None => {
+                self.nodes.push(Node::Occupied {
+                    value,
+                    generation: 0,
+                    prev,
+                    next: None,
+                });
+
+                self.nodes.len() - 1
+            }
+        };
+
+        match prev {
+            Some(prev) => self.set_next(prev, Some(index)),
+            None => self.head = Some(index),
+        }
+
+        self.tail = Some(index);
+        self.len += 1;
+
+        WaiterKey {
+            index,
+            generation: self.generation(index),
+        }
+    }
+
+    /// Removes the first waiter of this list.
+    pub fn pop_front(&mut self) -> Option<T> {
+        let index = self.head?;
+
+        Some(self.unlink(index))
+    }
+
+    /// Removes the waiter of `key`, returns `None` if the waiter had been removed.
+    pub fn remove(&mut self, key: WaiterKey) -> Option<T> {
+        match self.nodes.get(key.index) {
+            Some(Node::Occupied { generation, .. }) if *generation == key.generation => {
+                Some(self.unlink(key.index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the waiters in FIFO order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let mut current = self.head;
+
+        std::iter::from_fn(move || {
+            let Node::Occupied { value, next, .. } = &self.nodes[current?] else {
+                unreachable!("the list links vacant node");
+            };
+
+            current = *next;
+
+            Some(value)
+        })
+    }
+
+    fn generation(&self, index: usize) -> usize {
+        match &self.nodes[index] {
+            Node::Occupied { generation, .. } | Node::Vacant { generation, .. } => *generation,
+        }
+    }
+
+    fn set_next(&mut self, index: usize, value: Option<usize>) {
+        if let Node::Occupied { next, .. } = &mut self.nodes[index] {
+            *next = value;
+        }
+    }
+
+    fn set_prev(&mut self, index: usize, value: Option<usize>) {
+        if let Node::Occupied { prev, .. } = &mut self.nodes[index] {
+            *prev = value;
+        }
+    }
+
+    /// Unlinks the occupied node at `index` and pushes the slot into the free list.
+    fn unlink(&mut self, index: usize) -> T {
+        let vacant = Node::Vacant {
+            generation: self.generation(index).wrapping_add(1),
+            next_free: self.free,
+        };
+
+        let Node::Occupied {
+            value, prev, next, ..
+        } = std::mem::replace(&mut self.nodes[index], vacant)
+        else {
+            unreachable!("unlink vacant node");
+        };
+
+        match prev {
+            Some(prev) => self.set_next(prev, next),
+            None => self.head = next,
+        }
+
+        match next {
+            Some(next) => self.set_prev(next, prev),
+            None => self.tail = prev,
+        }
+
+        self.free = Some(index);
+        self.len -= 1;
+
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fifo() {
+        let mut list = WaiterList::new();
+
+        for i in 0..4 {
+            list.push_back(i);
+        }
+
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+
+        for i in 0..4 {
+            assert_eq!(list.pop_front(), Some(i));
+        }
+
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut list = WaiterList::new();
+
+        let keys = (0..4).map(|i| list.push_back(i)).collect::<Vec<_>>();
+
+        assert_eq!(list.remove(keys[1]), Some(1));
+        assert_eq!(list.remove(keys[3]), Some(3));
+        assert_eq!(list.remove(keys[1]), None);
+
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![0, 2]);
+
+        // The removed slot is reused, the stale key doesn't remove the new waiter.
+        let key = list.push_back(4);
+
+        assert_eq!(list.remove(keys[3]), None);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![0, 2, 4]);
+
+        assert_eq!(list.remove(key), Some(4));
+        assert_eq!(list.remove(keys[0]), Some(0));
+        assert_eq!(list.pop_front(), Some(2));
+        assert!(list.is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    future::Future,
+    io,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::task::{waker, ArcWake};
+
+#[cfg(feature = "current")]
+use crate::current::get_driver;
+
+use crate::{Cmd, Description, Driver, Handle, OpenFlags, OwnedHandle, PollerWaker};
+
+/// Wakes up the thread blocking in [`BlockingPoller::block_on`].
+struct BlockingWaker {
+    woken: AtomicBool,
+    /// The blocking thread is polling the poller.
+    parked: AtomicBool,
+    poller_waker: PollerWaker,
+}
+
+impl ArcWake for BlockingWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.woken.store(true, Ordering::SeqCst);
+
+        // The io events are dispatched by the blocking thread itself, so the poller is woken up
+        // only by the wakers called from other threads.
+        if arc_self.parked.load(Ordering::SeqCst) {
+            if let Err(err) = arc_self.poller_waker.wake() {
+                log::error!("wake blocking poller failed, err={}", err);
+            }
+        }
+    }
+}
+
+/// The private poller of the blocking io objects, e.g. the `blocking` modules of hala-tcp / hala-udp.
+///
+/// The future passed to [`block_on`](Self::block_on) is polled on the calling thread, which polls
+/// the io events of this poller while the future is pending, so no event loop thread is needed.
+/// The io objects polled by the future must be registered to [`poller`](Self::poller).
+pub struct BlockingPoller {
+    poller: OwnedHandle,
+}
+
+impl BlockingPoller {
+    /// Creates a poller with global context `driver`.
+    #[cfg(feature = "current")]
+    pub fn new() -> io::Result<Self> {
+        Self::new_with(get_driver()?)
+    }
+
+    /// Creates a poller with providing `driver`.
+    pub fn new_with(driver: Driver) -> io::Result<Self> {
+        let poller = OwnedHandle::open(driver, Description::Poller, OpenFlags::LocalPoller)?;
+
+        Ok(Self { poller })
+    }
+
+    /// Returns the driver of this poller.
+    pub fn driver(&self) -> &Driver {
+        self.poller.driver()
+    }
+
+    /// Returns the poller handle, which is valid while this instance is alive.
+    pub fn poller(&self) -> Handle {
+        *self.poller.as_handle()
+    }
+
+    /// Blocks the calling thread until `fut` is ready.
+    pub fn block_on<Fut: Future>(&self, fut: Fut) -> Fut::Output {
+        let blocking_waker = Arc::new(BlockingWaker {
+            woken: AtomicBool::new(false),
+            parked: AtomicBool::new(false),
+            poller_waker: PollerWaker::new_with(self.driver().clone(), self.poller())
+                .expect("poller handle"),
+        });
+
+        let waker = waker(blocking_waker.clone());
+
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = pin!(fut);
+
+        loop {
+            if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+                return output;
+            }
+
+            // Marks parked before checking the woken flag, so the waker called after the check
+            // wakes up the poller.
+            blocking_waker.parked.store(true, Ordering::SeqCst);
+
+            if !blocking_waker.woken.swap(false, Ordering::SeqCst) {
+                if let Err(err) = self.poller.fd_cntl(Cmd::PollOnce(None)) {
+                    log::error!("poll blocking poller failed, err={}", err);
+                }
+            }
+
+            blocking_waker.parked.store(false, Ordering::SeqCst);
+            blocking_waker.woken.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mio-driver"))]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use futures::channel::oneshot;
+
+    use crate::{mio::mio_driver, sleep_with};
+
+    use super::*;
+
+    #[test]
+    fn test_blocking_poller() {
+        let poller = BlockingPoller::new_with(mio_driver()).unwrap();
+
+        let now = Instant::now();
+
+        poller
+            .block_on(sleep_with(
+                poller.driver().clone(),
+                poller.poller(),
+                Duration::from_millis(20),
+            ))
+            .unwrap();
+
+        assert!(now.elapsed() >= Duration::from_millis(20));
+
+        // Woken up by other thread.
+        let (sender, receiver) = oneshot::channel();
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            sender.send(1).unwrap();
+        });
+
+        assert_eq!(poller.block_on(receiver).unwrap(), 1);
+    }
+}
@@ -0,0 +1,350 @@
+//! Length-limited body readers for protocol parsing.
+//!
+//! The [`BodyReadExt`] adapters take the reader by value or by `&mut`, so the underlying
+//! stream (or one half of [`split`](futures::AsyncReadExt::split)) can be reused once the
+//! body is consumed, e.g. reading the next request on a keep-alive connection:
+//!
+//! ```ignore
+//! let mut body = (&mut stream).take_exact(content_length);
+//!
+//! futures::AsyncReadExt::read_to_end(&mut body, &mut buf).await?;
+//! ```
+//!
+//! The names conflict with [`futures::AsyncReadExt`], so this module is not re-exported by the
+//! crate root, and the two traits should not be imported into the same scope.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{future::poll_fn, ready, AsyncBufRead, AsyncRead};
+
+/// Reader adapter which limits the bytes read from the underlying reader,
+/// created by [`take`](BodyReadExt::take) / [`take_exact`](BodyReadExt::take_exact).
+#[derive(Debug)]
+pub struct Take<R> {
+    inner: R,
+    /// The number of bytes can be read.
+    limit: u64,
+    /// Returns [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error if the underlying reader
+    /// reaches EOF before `limit` bytes are read.
+    exact: bool,
+}
+
+impl<R> Take<R> {
+    /// Returns the number of bytes that can be read before this instance returns EOF.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Sets the number of bytes that can be read before this instance returns EOF.
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Reading directly from the underlying reader doesn't decrease the limit.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this [`Take`], returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Returns the error of truncated body if the `exact` body reaches EOF with `limit` bytes remaining.
+fn check_eof(exact: bool, limit: u64) -> io::Result<()> {
+    if exact && limit > 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("body is truncated, remaining={}", limit),
+        ));
+    }
+
+    Ok(())
+}
+
+impl<R: AsyncRead + Unpin> Take<R> {
+    /// Reads and drops the remaining bytes of the limit, returns the number of dropped bytes.
+    ///
+    /// So the underlying reader is positioned after the body, even if the body isn't read completely.
+    pub async fn discard(&mut self) -> io::Result<u64> {
+        let mut buf = [0; 1024];
+        let mut discarded = 0;
+
+        loop {
+            let read_size = poll_fn(|cx| Pin::new(&mut *self).poll_read(cx, &mut buf)).await?;
+
+            if read_size == 0 {
+                return Ok(discarded);
+            }
+
+            discarded += read_size as u64;
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Take<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.limit == 0 || buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let max = buf.len().min(self.limit.try_into().unwrap_or(usize::MAX));
+
+        let read_size = ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf[..max]))?;
+
+        if read_size == 0 {
+            check_eof(self.exact, self.limit)?;
+
+            return Poll::Ready(Ok(0));
+        }
+
+        self.limit -= read_size as u64;
+
+        Poll::Ready(Ok(read_size))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for Take<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        if this.limit == 0 {
+            return Poll::Ready(Ok(&[]));
+        }
+
+        let available = ready!(Pin::new(&mut this.inner).poll_fill_buf(cx))?;
+
+        if available.is_empty() {
+            check_eof(this.exact, this.limit)?;
+
+            return Poll::Ready(Ok(&[]));
+        }
+
+        let max = available
+            .len()
+            .min(this.limit.try_into().unwrap_or(usize::MAX));
+
+        Poll::Ready(Ok(&available[..max]))
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        let amt = amt.min(self.limit.try_into().unwrap_or(usize::MAX));
+
+        self.limit -= amt as u64;
+
+        Pin::new(&mut self.inner).consume(amt);
+    }
+}
+
+/// Reader adapter which reads from the first reader until EOF, then from the second one,
+/// created by [`chain`](BodyReadExt::chain).
+#[derive(Debug)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+    /// The first reader has reached EOF.
+    done_first: bool,
+}
+
+impl<A, B> Chain<A, B> {
+    /// Returns the references of the underlying readers.
+    pub fn get_ref(&self) -> (&A, &B) {
+        (&self.first, &self.second)
+    }
+
+    /// Returns the mutable references of the underlying readers.
+    pub fn get_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.first, &mut self.second)
+    }
+
+    /// Unwraps this [`Chain`], returning the underlying readers.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: AsyncRead + Unpin, B: AsyncRead + Unpin> AsyncRead for Chain<A, B> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.done_first {
+            match ready!(Pin::new(&mut self.first).poll_read(cx, buf))? {
+                0 if !buf.is_empty() => self.done_first = true,
+                read_size => return Poll::Ready(Ok(read_size)),
+            }
+        }
+
+        Pin::new(&mut self.second).poll_read(cx, buf)
+    }
+}
+
+impl<A: AsyncBufRead + Unpin, B: AsyncBufRead + Unpin> AsyncBufRead for Chain<A, B> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        if !this.done_first {
+            match ready!(Pin::new(&mut this.first).poll_fill_buf(cx))? {
+                [] => this.done_first = true,
+                available => return Poll::Ready(Ok(available)),
+            }
+        }
+
+        Pin::new(&mut this.second).poll_fill_buf(cx)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        if !self.done_first {
+            Pin::new(&mut self.first).consume(amt)
+        } else {
+            Pin::new(&mut self.second).consume(amt)
+        }
+    }
+}
+
+/// Extension trait of [`AsyncRead`] to create the body readers.
+pub trait BodyReadExt: AsyncRead {
+    /// Creates an adapter which reads at most `limit` bytes from this reader.
+    fn take(self, limit: u64) -> Take<Self>
+    where
+        Self: Sized,
+    {
+        Take {
+            inner: self,
+            limit,
+            exact: false,
+        }
+    }
+
+    /// Creates an adapter which reads exactly `len` bytes from this reader, e.g. the body of `Content-Length`.
+    ///
+    /// Returns [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error if this reader reaches EOF
+    /// before `len` bytes are read, so the truncated body is not mistaken for a complete one.
+    fn take_exact(self, len: u64) -> Take<Self>
+    where
+        Self: Sized,
+    {
+        Take {
+            inner: self,
+            limit: len,
+            exact: true,
+        }
+    }
+
+    /// Creates an adapter which chains this reader with `next`.
+    fn chain<R: AsyncRead>(self, next: R) -> Chain<Self, R>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+            done_first: false,
+        }
+    }
+}
+
+impl<R: AsyncRead + ?Sized> BodyReadExt for R {}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use futures::{executor::block_on, io::Cursor, AsyncBufReadExt, AsyncRead};
+
+    use super::BodyReadExt;
+
+    /// Reads until EOF, [`futures::AsyncReadExt`] can't be imported with [`BodyReadExt`].
+    async fn read_to_end<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
+        let mut buf = vec![];
+
+        futures::AsyncReadExt::read_to_end(reader, &mut buf).await?;
+
+        Ok(buf)
+    }
+
+    #[test]
+    fn test_take() {
+        block_on(async {
+            let mut stream = Cursor::new(b"hello world".to_vec());
+
+            let mut body = (&mut stream).take(5);
+
+            assert_eq!(read_to_end(&mut body).await.unwrap(), b"hello");
+
+            // The next read continues after the body.
+            assert_eq!(read_to_end(&mut stream).await.unwrap(), b" world");
+
+            // The non-exact body ends at EOF silently.
+            assert!(read_to_end(&mut stream.take(5)).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_take_exact() {
+        block_on(async {
+            let mut stream = Cursor::new(b"hello world".to_vec());
+
+            let mut body = (&mut stream).take_exact(8);
+
+            let mut line = String::new();
+
+            // The buffered reads are limited too.
+            body.read_line(&mut line).await.unwrap();
+
+            assert_eq!(line, "hello wo");
+            assert_eq!(body.limit(), 0);
+
+            let mut body = stream.take_exact(5);
+
+            let mut buf = vec![];
+
+            let err = futures::AsyncReadExt::read_to_end(&mut body, &mut buf)
+                .await
+                .unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+            assert_eq!(buf, b"rld");
+        });
+    }
+
+    #[test]
+    fn test_discard_and_chain() {
+        block_on(async {
+            let mut stream = Cursor::new(b"header:body:next".to_vec());
+
+            let mut header = (&mut stream).take(7);
+
+            let mut buf = [0; 3];
+
+            futures::AsyncReadExt::read_exact(&mut header, &mut buf)
+                .await
+                .unwrap();
+
+            assert_eq!(header.discard().await.unwrap(), 4);
+
+            let mut body = (&mut stream).take(5).chain(Cursor::new(b"|tail".to_vec()));
+
+            assert_eq!(read_to_end(&mut body).await.unwrap(), b"body:|tail");
+
+            assert_eq!(read_to_end(&mut stream).await.unwrap(), b"next");
+        });
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{ready, AsyncBufRead, AsyncRead, AsyncWrite};
+
+/// The default buffer capacity of [`BufReader`] / [`BufWriter`].
+pub const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// Adds buffering to any reader, so the small reads are served from the memory
+/// instead of crossing the driver `fd_cntl` boundary one by one.
+pub struct BufReader<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    /// The read cursor of buffered data.
+    pos: usize,
+    /// The length of buffered data.
+    cap: usize,
+}
+
+impl<R> BufReader<R> {
+    /// Create new [`BufReader`] with [`DEFAULT_BUF_SIZE`] capacity.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Create new [`BufReader`] with the specified buffer `capacity`.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Reading directly from the underlying reader skips the buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the buffered data, which has not been consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.cap]
+    }
+
+    /// Returns the capacity of the internal buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Unwraps this [`BufReader`], returning the underlying reader.
+    ///
+    /// The buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for BufReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        // Bypass the internal buffer for the large reads.
+        if self.pos == self.cap && buf.len() >= self.buf.len() {
+            return Pin::new(&mut self.inner).poll_read(cx, buf);
+        }
+
+        let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+
+        let read_size = available.len().min(buf.len());
+
+        buf[..read_size].copy_from_slice(&available[..read_size]);
+
+        self.consume(read_size);
+
+        Poll::Ready(Ok(read_size))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncBufRead for BufReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        if this.pos == this.cap {
+            this.cap = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut this.buf))?;
+            this.pos = 0;
+        }
+
+        Poll::Ready(Ok(&this.buf[this.pos..this.cap]))
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        self.pos = (self.pos + amt).min(self.cap);
+    }
+}
+
+impl<R: AsyncWrite + Unpin> AsyncWrite for BufReader<R> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// Adds buffering to any writer, so the small writes are coalesced
+/// into one driver `fd_cntl` call.
+///
+/// The buffered data must be flushed by [`poll_flush`](AsyncWrite::poll_flush)
+/// or [`poll_close`](AsyncWrite::poll_close), it is lost if this writer is dropped.
+pub struct BufWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+    /// The length of buffered data that has been written into `inner`.
+    written: usize,
+}
+
+impl<W> BufWriter<W> {
+    /// Create new [`BufWriter`] with [`DEFAULT_BUF_SIZE`] capacity.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Create new [`BufWriter`] with the specified buffer `capacity`.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner,
+            buf: Vec::with_capacity(capacity),
+            written: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing directly to the underlying writer may reorder the data with the buffered data.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the buffered data, which has not been written into the underlying writer yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.written..]
+    }
+
+    /// Returns the capacity of the internal buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Unwraps this [`BufWriter`], returning the underlying writer.
+    ///
+    /// The buffered data is lost, flush this writer first.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite + Unpin> BufWriter<W> {
+    /// Writes all buffered data into the underlying writer.
+    fn poll_flush_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.buf.len() {
+            let write_size =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.buf[self.written..]))?;
+
+            if write_size == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "write zero byte into writer",
+                )));
+            }
+
+            self.written += write_size;
+        }
+
+        self.buf.clear();
+        self.written = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for BufWriter<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.buf.len() + buf.len() > self.buf.capacity() {
+            ready!(self.poll_flush_buf(cx))?;
+        }
+
+        // Bypass the internal buffer for the large writes.
+        if buf.len() >= self.buf.capacity() {
+            return Pin::new(&mut self.inner).poll_write(cx, buf);
+        }
+
+        self.buf.extend_from_slice(buf);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush_buf(cx))?;
+
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush_buf(cx))?;
+
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+impl<W: AsyncRead + Unpin> AsyncRead for BufWriter<W> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures::{
+        executor::block_on, io::Cursor, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite,
+        AsyncWriteExt,
+    };
+
+    use super::{BufReader, BufWriter};
+
+    /// Counts the calls of the underlying reader / writer.
+    #[derive(Default)]
+    struct MockStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+        calls: usize,
+    }
+
+    impl AsyncRead for MockStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.calls += 1;
+
+            Pin::new(&mut self.input).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for MockStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.calls += 1;
+
+            self.output.extend_from_slice(buf);
+
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_buf_reader() {
+        let stream = MockStream {
+            input: Cursor::new(b"hello\nworld\nhala".to_vec()),
+            ..Default::default()
+        };
+
+        let mut reader = BufReader::with_capacity(64, stream);
+
+        let mut line = String::new();
+
+        block_on(reader.read_line(&mut line)).unwrap();
+        assert_eq!(line, "hello\n");
+
+        let mut buf = vec![];
+
+        block_on(reader.read_until(b'\n', &mut buf)).unwrap();
+        assert_eq!(buf, b"world\n");
+
+        let mut byte = [0; 1];
+
+        block_on(reader.read_exact(&mut byte)).unwrap();
+        assert_eq!(&byte, b"h");
+        assert_eq!(reader.buffer(), b"ala");
+
+        // All data is read by one call.
+        assert_eq!(reader.get_ref().calls, 1);
+    }
+
+    #[test]
+    fn test_buf_writer() {
+        let mut writer = BufWriter::with_capacity(8, MockStream::default());
+
+        for _ in 0..3 {
+            block_on(writer.write_all(b"ab")).unwrap();
+        }
+
+        assert_eq!(writer.get_ref().calls, 0);
+        assert_eq!(writer.buffer(), b"ababab");
+
+        // Flush the buffered data first, then write the large data directly.
+        block_on(writer.write_all(b"0123456789")).unwrap();
+
+        assert_eq!(writer.get_ref().calls, 2);
+
+        block_on(writer.write_all(b"cd")).unwrap();
+        block_on(writer.flush()).unwrap();
+
+        assert_eq!(writer.get_ref().calls, 3);
+        assert_eq!(writer.get_ref().output, b"ababab0123456789cd");
+    }
+}
@@ -0,0 +1,362 @@
+use std::{fmt::Display, io, str::FromStr, sync::Arc, time::Duration};
+
+use crate::{Clock, Driver, DriverCounters, DriverMetrics};
+
+/// The environment variable to force the driver backend, e.g. `HALA_IO_BACKEND=epoll`.
+pub const BACKEND_ENV: &str = "HALA_IO_BACKEND";
+
+/// The io multiplexing backend of driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Linux `epoll(7)`, provided by the mio driver.
+    Epoll,
+    /// BSD / macOS `kqueue(2)`, provided by the mio driver.
+    Kqueue,
+    /// Windows IO completion ports, provided by the mio driver.
+    Iocp,
+    /// Portable `poll(2)`, which is not provided by any driver yet.
+    Poll,
+    /// The deterministic in-memory driver, see `mock` module for more information.
+    Mock,
+}
+
+impl Backend {
+    /// Returns the native backend of the target os, which is selected by mio at compile time.
+    pub fn native() -> Option<Backend> {
+        if cfg!(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "illumos"
+        )) {
+            Some(Backend::Epoll)
+        } else if cfg!(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "dragonfly"
+        )) {
+            Some(Backend::Kqueue)
+        } else if cfg!(windows) {
+            Some(Backend::Iocp)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the backends compiled into this crate, the preferred one comes first.
+    #[allow(unused_mut)]
+    pub fn available() -> Vec<Backend> {
+        let mut backends = vec![];
+
+        #[cfg(feature = "mio-driver")]
+        backends.extend(Self::native());
+
+        #[cfg(feature = "mock-driver")]
+        backends.push(Backend::Mock);
+
+        backends
+    }
+
+    /// Returns the lowercase name of this backend, which is also accepted by [`BACKEND_ENV`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Epoll => "epoll",
+            Backend::Kqueue => "kqueue",
+            Backend::Iocp => "iocp",
+            Backend::Poll => "poll",
+            Backend::Mock => "mock",
+        }
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "epoll" => Ok(Backend::Epoll),
+            "kqueue" => Ok(Backend::Kqueue),
+            "iocp" => Ok(Backend::Iocp),
+            "poll" => Ok(Backend::Poll),
+            "mock" => Ok(Backend::Mock),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown driver backend {:?}", s),
+            )),
+        }
+    }
+}
+
+/// The optional features supported by the driver, returns by [`Cmd::Capabilities`](crate::Cmd::Capabilities) command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriverCapabilities {
+    /// The backend of the driver, `None` if the driver doesn't report it.
+    pub backend: Option<Backend>,
+    /// The poller supports [`OneShot`](crate::PollMode::OneShot) registration.
+    pub supports_oneshot: bool,
+    /// The `RecvBatch` / `SendBatch` commands drain the ready datagrams up to the slot budget in one wake-up,
+    /// otherwise only one datagram is transferred per call.
+    pub supports_fd_budget: bool,
+    /// The `SendToGso` command is sent by one `UDP_SEGMENT` syscall.
+    pub supports_gso: bool,
+}
+
+/// The file descriptor limits of driver, configured by [`DriverBuilder`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct FdLimits {
+    /// The max number of fds opened by the driver, `None` means unlimited.
+    pub(crate) budget: Option<usize>,
+    /// Reserves one fd, which is released to shed the pending connection when `accept` fails with `EMFILE`.
+    pub(crate) reserve_fd: bool,
+    /// The backoff duration carried by [`Emfile`](crate::Emfile) error.
+    pub(crate) backoff: Option<Duration>,
+}
+
+/// The builder of [`Driver`], which selects the backend at runtime.
+///
+/// The backend is selected in the order of [`backend`](Self::backend), the [`BACKEND_ENV`] environment variable
+/// and the first probed backend of [`Backend::available`].
+#[derive(Debug)]
+pub struct DriverBuilder {
+    backend: Option<Backend>,
+    tick_duration: Duration,
+    metrics: Option<DriverCounters>,
+    fd_limits: FdLimits,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl Default for DriverBuilder {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            tick_duration: Duration::from_millis(10),
+            metrics: None,
+            fd_limits: FdLimits::default(),
+            clock: None,
+        }
+    }
+}
+
+impl DriverBuilder {
+    /// Create new builder with default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forces the driver `backend`, which takes precedence over [`BACKEND_ENV`].
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Sets the tick duration of the poller timewheel, the default value is 10ms.
+    ///
+    /// Only used by the mio driver.
+    pub fn tick_duration(mut self, tick_duration: Duration) -> Self {
+        self.tick_duration = tick_duration;
+        self
+    }
+
+    /// Forwards the driver metrics hooks to `subscriber`.
+    ///
+    /// Only used by the mio driver.
+    pub fn metrics<S: DriverMetrics + 'static>(mut self, subscriber: S) -> Self {
+        self.metrics = Some(DriverCounters::with_subscriber(subscriber));
+        self
+    }
+
+    /// Sets the max number of fds opened by the driver, the incoming connections beyond
+    /// the `budget` are shed and the accept fails with [`Emfile`](crate::Emfile) error.
+    ///
+    /// Only used by the mio driver, the timers are not counted.
+    pub fn fd_budget(mut self, budget: usize) -> Self {
+        self.fd_limits.budget = Some(budget);
+        self
+    }
+
+    /// Reserves one emergency fd, which is closed to accept and shed the pending connection when
+    /// the accept fails with `EMFILE` / `ENFILE`, and reopened after that. The default value is false.
+    ///
+    /// Without the emergency fd, the pending connection stays in the backlog until any fd is closed.
+    ///
+    /// Only used by the mio driver on unix platforms.
+    pub fn reserve_fd(mut self, reserve_fd: bool) -> Self {
+        self.fd_limits.reserve_fd = reserve_fd;
+        self
+    }
+
+    /// Sets the backoff duration carried by [`Emfile`](crate::Emfile) error, which the accept
+    /// loop should wait before the next accept.
+    ///
+    /// Only used by the mio driver.
+    pub fn emfile_backoff(mut self, backoff: Duration) -> Self {
+        self.fd_limits.backoff = Some(backoff);
+        self
+    }
+
+    /// Sets the time source of the poller timers, the default is [`SystemClock`](crate::SystemClock).
+    ///
+    /// With a [`MockClock`](crate::MockClock), the timers only expire after the clock is advanced,
+    /// which makes the timer tests deterministic.
+    ///
+    /// Only used by the mio driver, the mock driver always uses its own manual clock.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Returns the backend selected by this builder, without probing it.
+    pub fn selected_backend(&self) -> io::Result<Option<Backend>> {
+        if let Some(backend) = self.backend {
+            return Ok(Some(backend));
+        }
+
+        match std::env::var(BACKEND_ENV) {
+            Ok(value) if !value.trim().is_empty() => value.parse().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Creates the [`Driver`] of the selected backend.
+    ///
+    /// Returns [`Unsupported`](io::ErrorKind::Unsupported) error if the forced backend is not available,
+    /// or no available backend can be created.
+    pub fn build(mut self) -> io::Result<Driver> {
+        if let Some(backend) = self.selected_backend()? {
+            return self.build_backend(backend);
+        }
+
+        let mut last_error = None;
+
+        for backend in Backend::available() {
+            let result = self.probe(backend);
+
+            match result.and_then(|_| self.build_backend(backend)) {
+                Ok(driver) => return Ok(driver),
+                Err(err) => {
+                    log::warn!("driver backend {} is not usable, err={}", backend, err);
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "no driver backend is available, enable `mio-driver` or `mock-driver` feature",
+            )
+        }))
+    }
+
+    /// Checks `backend` works in the current environment, e.g. `epoll_create` may be denied by seccomp.
+    fn probe(&self, backend: Backend) -> io::Result<()> {
+        #[cfg(feature = "mio-driver")]
+        if Some(backend) == Backend::native() {
+            return mio::Poll::new().map(|_| ());
+        }
+
+        _ = backend;
+
+        Ok(())
+    }
+
+    fn build_backend(&mut self, backend: Backend) -> io::Result<Driver> {
+        if !Backend::available().contains(&backend) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "driver backend {} is not available, available={:?}",
+                    backend,
+                    Backend::available()
+                ),
+            ));
+        }
+
+        log::trace!("create driver, backend={}", backend);
+
+        #[cfg(feature = "mock-driver")]
+        if backend == Backend::Mock {
+            return Ok(crate::mock::MockDriver::new().into());
+        }
+
+        #[cfg(feature = "mio-driver")]
+        return crate::mio::mio_driver_with(
+            self.tick_duration,
+            self.metrics.take().unwrap_or_default(),
+            self.fd_limits,
+            self.clock
+                .take()
+                .unwrap_or_else(|| Arc::new(crate::SystemClock)),
+        );
+
+        #[cfg(not(feature = "mio-driver"))]
+        unreachable!("unexpected available backend {}", backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cmd, Description, OpenFlags};
+
+    use super::*;
+
+    #[test]
+    fn test_backend_from_str() {
+        assert_eq!("EPOLL".parse::<Backend>().unwrap(), Backend::Epoll);
+        assert_eq!(" poll ".parse::<Backend>().unwrap(), Backend::Poll);
+        "select".parse::<Backend>().unwrap_err();
+
+        for backend in Backend::available() {
+            assert_eq!(backend.name().parse::<Backend>().unwrap(), backend);
+        }
+    }
+
+    #[cfg(all(feature = "mio-driver", feature = "mock-driver"))]
+    #[test]
+    #[allow(deprecated)]
+    fn test_build() {
+        let capabilities = |driver: &Driver| {
+            let poller = driver
+                .fd_open(Description::Poller, OpenFlags::None)
+                .unwrap();
+
+            let capabilities = driver
+                .fd_cntl(poller, Cmd::Capabilities)
+                .unwrap()
+                .try_into_capabilities()
+                .unwrap();
+
+            driver.fd_close(poller).unwrap();
+
+            capabilities
+        };
+
+        let driver = DriverBuilder::new().backend(Backend::Mock).build().unwrap();
+
+        assert_eq!(capabilities(&driver).backend, Some(Backend::Mock));
+
+        if let Some(native) = Backend::native() {
+            let driver = DriverBuilder::new().backend(native).build().unwrap();
+
+            let caps = capabilities(&driver);
+
+            assert_eq!(caps.backend, Some(native));
+            assert!(caps.supports_oneshot);
+        }
+
+        let err = DriverBuilder::new()
+            .backend(Backend::Poll)
+            .build()
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,80 @@
+use std::{future::Future, io};
+
+use futures::{future::select, FutureExt};
+
+pub use hala_sync::CancellationToken;
+
+/// The error of the operation aborted by [`CancellationToken`], see [`cancellable`].
+///
+/// It's surfaced as [`Other`](io::ErrorKind::Other) kind io error, use [`OperationCancelled::from_io_error`] to extract it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("operation cancelled")]
+pub struct OperationCancelled;
+
+impl OperationCancelled {
+    /// Returns the [`OperationCancelled`] error carried by `err`, or `None` if it's another error.
+    pub fn from_io_error(err: &io::Error) -> Option<&OperationCancelled> {
+        err.get_ref()?.downcast_ref::<OperationCancelled>()
+    }
+}
+
+impl From<OperationCancelled> for io::Error {
+    fn from(value: OperationCancelled) -> Self {
+        io::Error::other(value)
+    }
+}
+
+/// Add cancellation feature for exists `Fut`, `None` means the `fut` is never cancelled.
+///
+/// The `fut` is dropped once `token` is cancelled, which releases the in-flight driver operations,
+/// and [`OperationCancelled`] error is returned.
+pub async fn cancellable<Fut, R>(fut: Fut, token: Option<&CancellationToken>) -> io::Result<R>
+where
+    Fut: Future<Output = io::Result<R>>,
+{
+    let Some(token) = token else {
+        return fut.await;
+    };
+
+    if token.is_cancelled() {
+        return Err(OperationCancelled.into());
+    }
+
+    match select(Box::pin(fut), token.cancelled().fuse()).await {
+        futures::future::Either::Left((r, _)) => r,
+        futures::future::Either::Right(_) => Err(OperationCancelled.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, future::pending};
+
+    use super::*;
+
+    #[test]
+    fn test_cancellable() {
+        let token = CancellationToken::new();
+
+        assert_eq!(
+            block_on(cancellable(async { Ok(1) }, Some(&token))).unwrap(),
+            1
+        );
+
+        let child = token.child_token();
+
+        let canceller = std::thread::spawn(move || token.cancel());
+
+        let err = block_on(cancellable(pending::<io::Result<()>>(), Some(&child))).unwrap_err();
+
+        assert_eq!(
+            OperationCancelled::from_io_error(&err),
+            Some(&OperationCancelled)
+        );
+
+        canceller.join().unwrap();
+
+        // The cancelled token never polls the future.
+        block_on(cancellable(async { Ok(()) }, Some(&child))).unwrap_err();
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    fmt::Debug,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "current")]
+use crate::current::{get_driver, get_poller};
+
+use crate::{Cmd, Driver, Handle};
+
+/// The time source of the driver timers, see [`DriverBuilder::clock`](crate::DriverBuilder::clock).
+///
+/// The timers and deadlines computed by the users should read the time of the driver by [`now_with`],
+/// instead of calling [`Instant::now`] directly, so they're driven by the same clock in the tests.
+pub trait Clock: Send + Sync + Debug {
+    /// Returns the current time of this clock, which never goes backwards.
+    fn now(&self) -> Instant;
+}
+
+/// The [`Clock`] of the system monotonic time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The manual [`Clock`], which only goes forward by calling [`advance`](Self::advance).
+///
+/// The clones share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    start: Instant,
+    /// The elapsed nanoseconds since `start`.
+    elapsed: Arc<AtomicU64>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockClock {
+    /// Creates a clock starting at the current system time.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Default::default(),
+        }
+    }
+
+    /// Advances the clock by `duration`.
+    ///
+    /// The driver timers expired by the advancing are delivered by the next poll.
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed
+            .fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    /// Returns the elapsed time since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed.load(Ordering::SeqCst))
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+}
+
+/// Returns the current time of the clock of `driver`, see [`Cmd::Now`].
+pub fn now_with(driver: &Driver, poller: Handle) -> io::Result<Instant> {
+    driver.fd_cntl(poller, Cmd::Now)?.try_into_instant()
+}
+
+/// Returns the current time of the clock of global context driver.
+#[cfg(feature = "current")]
+pub fn now() -> io::Result<Instant> {
+    now_with(&get_driver()?, get_poller()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new();
+
+        let start = clock.now();
+
+        std::thread::sleep(Duration::from_millis(10));
+
+        assert_eq!(clock.now(), start);
+
+        clock.clone().advance(Duration::from_secs(1));
+
+        assert_eq!(clock.now() - start, Duration::from_secs(1));
+        assert_eq!(clock.elapsed(), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,146 @@
+//! Cooperative scheduling budget for io futures.
+//!
+//! A task wrapped by [`with_budget`] can perform at most `budget` io operations in one poll,
+//! after that the io futures return [`Pending`](Poll::Pending) and wake the task immediately,
+//! so that other tasks on the same executor thread get a chance to run.
+
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::future::poll_fn;
+
+/// The default number of io operations a task can perform in one poll.
+pub const DEFAULT_COOP_BUDGET: usize = 128;
+
+thread_local! {
+    /// The remaining budget of the polling task, `None` means unconstrained.
+    static BUDGET: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Consumes one unit of the current task's budget.
+///
+/// Returns [`Pending`](Poll::Pending) and wakes the task if the budget is exhausted.
+/// Tasks not wrapped by [`with_budget`] are unconstrained.
+pub fn poll_proceed(cx: &mut Context<'_>) -> Poll<()> {
+    BUDGET.with(|budget| match budget.get() {
+        Some(0) => {
+            log::trace!("coop budget exhausted, yield current task");
+
+            cx.waker().wake_by_ref();
+
+            Poll::Pending
+        }
+        Some(remaining) => {
+            budget.set(Some(remaining - 1));
+
+            Poll::Ready(())
+        }
+        None => Poll::Ready(()),
+    })
+}
+
+/// Asynchronously consumes one unit of the current task's budget, see [`poll_proceed`] for more information.
+pub async fn consume_budget() {
+    poll_fn(poll_proceed).await
+}
+
+/// Future returns by [`with_budget`] function.
+pub struct Budgeted<F> {
+    fut: Pin<Box<F>>,
+    budget: usize,
+}
+
+/// Restores the budget of the outer task on drop.
+struct ResetGuard(Option<usize>);
+
+impl Drop for ResetGuard {
+    fn drop(&mut self) {
+        BUDGET.with(|budget| budget.set(self.0));
+    }
+}
+
+impl<F> Future for Budgeted<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _guard = ResetGuard(BUDGET.with(|budget| budget.replace(Some(self.budget))));
+
+        self.fut.as_mut().poll(cx)
+    }
+}
+
+/// Wraps `fut` to perform at most `budget` io operations per poll.
+pub fn with_budget<F>(fut: F, budget: usize) -> Budgeted<F>
+where
+    F: Future,
+{
+    Budgeted {
+        fut: Box::pin(fut),
+        budget,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Poll;
+
+    use futures::{executor::block_on, future::poll_fn, task::noop_waker_ref, FutureExt};
+
+    use super::*;
+
+    #[test]
+    fn test_budget() {
+        let mut polls = 0;
+
+        let mut fut = with_budget(
+            async {
+                for _ in 0..5 {
+                    consume_budget().await;
+                }
+            },
+            2,
+        );
+
+        block_on(poll_fn(|cx| {
+            polls += 1;
+            fut.poll_unpin(cx)
+        }));
+
+        assert_eq!(polls, 3);
+    }
+
+    #[test]
+    fn test_unconstrained() {
+        let mut polls = 0;
+
+        let mut fut = async {
+            for _ in 0..DEFAULT_COOP_BUDGET * 2 {
+                consume_budget().await;
+            }
+        }
+        .boxed();
+
+        block_on(poll_fn(|cx| {
+            polls += 1;
+            fut.poll_unpin(cx)
+        }));
+
+        assert_eq!(polls, 1);
+
+        // the outer budget is restored after polling the wrapped future.
+        block_on(with_budget(consume_budget(), 1));
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        for _ in 0..10 {
+            assert_eq!(poll_proceed(&mut cx), Poll::Ready(()));
+        }
+    }
+}
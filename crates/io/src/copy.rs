@@ -0,0 +1,253 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{future::poll_fn, ready, AsyncRead, AsyncWrite};
+
+/// The default buffer size of one copy direction.
+pub const DEFAULT_COPY_BUF_SIZE: usize = 8 * 1024;
+
+struct CopyBuffer {
+    /// Reader returns EOF.
+    read_done: bool,
+    /// Written data has not been flushed yet.
+    need_flush: bool,
+    /// The read cursor of buffered data.
+    pos: usize,
+    /// The length of buffered data.
+    cap: usize,
+    /// The total number of bytes written.
+    amt: u64,
+    buf: Box<[u8]>,
+}
+
+impl CopyBuffer {
+    fn new(buf_size: usize) -> Self {
+        Self {
+            read_done: false,
+            need_flush: false,
+            pos: 0,
+            cap: 0,
+            amt: 0,
+            buf: vec![0; buf_size].into_boxed_slice(),
+        }
+    }
+
+    fn poll_copy<R, W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut reader: Pin<&mut R>,
+        mut writer: Pin<&mut W>,
+    ) -> Poll<io::Result<u64>>
+    where
+        R: AsyncRead + ?Sized,
+        W: AsyncWrite + ?Sized,
+    {
+        loop {
+            if self.pos == self.cap && !self.read_done {
+                match reader.as_mut().poll_read(cx, &mut self.buf) {
+                    Poll::Ready(Ok(0)) => self.read_done = true,
+                    Poll::Ready(Ok(read_size)) => {
+                        self.pos = 0;
+                        self.cap = read_size;
+                    }
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                    Poll::Pending => {
+                        // Flush the written data before waiting for more data from reader.
+                        if self.need_flush {
+                            ready!(writer.as_mut().poll_flush(cx))?;
+                            self.need_flush = false;
+                        }
+
+                        return Poll::Pending;
+                    }
+                }
+            }
+
+            while self.pos < self.cap {
+                let write_size = ready!(writer
+                    .as_mut()
+                    .poll_write(cx, &self.buf[self.pos..self.cap]))?;
+
+                if write_size == 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "write zero byte into writer",
+                    )));
+                }
+
+                self.pos += write_size;
+                self.amt += write_size as u64;
+                self.need_flush = true;
+            }
+
+            if self.pos == self.cap && self.read_done {
+                ready!(writer.as_mut().poll_flush(cx))?;
+
+                return Poll::Ready(Ok(self.amt));
+            }
+        }
+    }
+}
+
+enum TransferState {
+    Running(CopyBuffer),
+    ShuttingDown(u64),
+    Done(u64),
+}
+
+fn poll_transfer<R, W>(
+    cx: &mut Context<'_>,
+    state: &mut TransferState,
+    reader: &mut R,
+    writer: &mut W,
+) -> Poll<io::Result<u64>>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    loop {
+        match state {
+            TransferState::Running(buf) => {
+                let amt =
+                    ready!(buf.poll_copy(cx, Pin::new(&mut *reader), Pin::new(&mut *writer)))?;
+
+                *state = TransferState::ShuttingDown(amt);
+            }
+            TransferState::ShuttingDown(amt) => {
+                // Propagate the half-close to the peer.
+                ready!(Pin::new(&mut *writer).poll_close(cx))?;
+
+                *state = TransferState::Done(*amt);
+            }
+            TransferState::Done(amt) => return Poll::Ready(Ok(*amt)),
+        }
+    }
+}
+
+/// Copies data in both directions between `a` and `b`, using the default buffer size.
+///
+/// See [`copy_bidirectional_with_sizes`] for more information.
+pub async fn copy_bidirectional<A, B>(a: &mut A, b: &mut B) -> io::Result<(u64, u64)>
+where
+    A: AsyncRead + AsyncWrite + Unpin + ?Sized,
+    B: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    copy_bidirectional_with_sizes(a, b, DEFAULT_COPY_BUF_SIZE, DEFAULT_COPY_BUF_SIZE).await
+}
+
+/// Copies data in both directions between `a` and `b`.
+///
+/// When one side reaches EOF, the write direction of the other side is closed
+/// by [`poll_close`](AsyncWrite::poll_close). This function returns once both directions
+/// are finished, with the tuple (bytes copied from `a` to `b`, bytes copied from `b` to `a`).
+pub async fn copy_bidirectional_with_sizes<A, B>(
+    a: &mut A,
+    b: &mut B,
+    a_to_b_buf_size: usize,
+    b_to_a_buf_size: usize,
+) -> io::Result<(u64, u64)>
+where
+    A: AsyncRead + AsyncWrite + Unpin + ?Sized,
+    B: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    let mut a_to_b = TransferState::Running(CopyBuffer::new(a_to_b_buf_size));
+    let mut b_to_a = TransferState::Running(CopyBuffer::new(b_to_a_buf_size));
+
+    poll_fn(|cx| {
+        let a_to_b = poll_transfer(cx, &mut a_to_b, a, b)?;
+        let b_to_a = poll_transfer(cx, &mut b_to_a, b, a)?;
+
+        let a_to_b = ready!(a_to_b);
+        let b_to_a = ready!(b_to_a);
+
+        Poll::Ready(Ok((a_to_b, b_to_a)))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures::{executor::block_on, io::Cursor, AsyncRead, AsyncWrite};
+
+    use super::copy_bidirectional_with_sizes;
+
+    struct MockStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+        closed: bool,
+    }
+
+    impl MockStream {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: Cursor::new(input.to_vec()),
+                output: vec![],
+                closed: false,
+            }
+        }
+    }
+
+    impl AsyncRead for MockStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.input).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for MockStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            assert!(!self.closed);
+
+            // write at most 3 bytes once.
+            let write_size = buf.len().min(3);
+
+            self.output.extend_from_slice(&buf[..write_size]);
+
+            Poll::Ready(Ok(write_size))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.closed = true;
+
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_copy_bidirectional() {
+        let mut a = MockStream::new(b"hello world");
+        let mut b = MockStream::new(b"hello");
+
+        let (a_to_b, b_to_a) =
+            block_on(copy_bidirectional_with_sizes(&mut a, &mut b, 4, 2)).unwrap();
+
+        assert_eq!(a_to_b, 11);
+        assert_eq!(b_to_a, 5);
+
+        assert_eq!(b.output, b"hello world");
+        assert_eq!(a.output, b"hello");
+
+        assert!(a.closed);
+        assert!(b.closed);
+    }
+}
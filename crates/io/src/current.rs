@@ -1,20 +1,32 @@
 use std::future::Future;
 
-use futures::future::BoxFuture;
+use futures::future::{BoxFuture, LocalBoxFuture};
 
 use super::*;
 
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    cell::{OnceCell, RefCell},
+    io,
+    pin::Pin,
+    sync::{Arc, OnceLock},
+    task::{Context, Poll},
 };
 
-use std::{io, sync::OnceLock};
-
 static DRIVER: OnceLock<Driver> = OnceLock::new();
 
-/// Get the global context registered io driver, or return a NotFound error if it is not registered.
+thread_local! {
+    /// The [`IoContext`] entered by current thread.
+    static CONTEXT: RefCell<Option<IoContext>> = const { RefCell::new(None) };
+}
+
+/// Get the io driver of the [`IoContext`] entered by current thread, or the global context registered io driver,
+/// or return a NotFound error if it is not registered.
 pub fn get_driver() -> io::Result<Driver> {
+    if let Some(driver) = CONTEXT.with_borrow(|context| context.as_ref().map(|c| c.driver.clone()))
+    {
+        return Ok(driver);
+    }
+
     return DRIVER
         .get()
         .map(|driver| driver.clone())
@@ -34,48 +46,171 @@ pub fn register_driver<D: Into<Driver>>(driver: D) -> io::Result<()> {
     })
 }
 
-struct Poller(Handle);
-
-impl Drop for Poller {
-    fn drop(&mut self) {
-        get_driver().unwrap().fd_close(self.0).unwrap();
-    }
-}
-
-/// Get poller [`Handle`] from global context.
+/// Get poller [`Handle`] of the [`IoContext`] entered by current thread, or from global context.
 ///
-/// Based on lazy optimizations, the Poller instance is not created until the first call to the function.
+/// Based on lazy optimizations, the global Poller instance is not created until the first call to the function.
 pub fn get_poller() -> io::Result<Handle> {
-    static POLLER: OnceLock<Poller> = OnceLock::new();
+    if let Some(poller) =
+        CONTEXT.with_borrow(|context| context.as_ref().map(|c| *c.poller.as_handle()))
+    {
+        return Ok(poller);
+    }
+
+    static POLLER: OnceLock<OwnedHandle> = OnceLock::new();
 
     let poller = POLLER.get_or_init(|| {
         let driver = get_driver().expect("call register_driver first");
 
-        let handle = driver
-            .fd_open(Description::Poller, OpenFlags::None)
-            .unwrap();
-
-        Poller(handle)
+        OwnedHandle::open(driver, Description::Poller, OpenFlags::None).unwrap()
     });
 
-    Ok(poller.0)
+    Ok(*poller.as_handle())
+}
+
+/// The io context isolated from the global one, e.g. each test case runs with its own driver / poller.
+///
+/// While entered by [`enter`](Self::enter) or polled by [`scope`](Self::scope), the context overrides
+/// the global driver / poller / spawner returned by [`get_driver`], [`get_poller`] and used by
+/// [`io_spawn`](executor::io_spawn), and the tasks spawned by `io_spawn` run in the context too.
+#[derive(Clone)]
+pub struct IoContext {
+    driver: Driver,
+    /// The poller is closed by the last clone of context.
+    poller: Arc<OwnedHandle>,
+    spawner: Option<Arc<dyn executor::IoSpawner + Send + Sync>>,
+}
+
+impl IoContext {
+    /// Creates a context of `driver` with a new poller, the global spawner is used
+    /// until [`spawner`](Self::spawner) is set.
+    pub fn new<D: Into<Driver>>(driver: D) -> io::Result<Self> {
+        let driver = driver.into();
+
+        let poller = OwnedHandle::open(driver.clone(), Description::Poller, OpenFlags::None)?;
+
+        Ok(Self {
+            poller: Arc::new(poller),
+            driver,
+            spawner: None,
+        })
+    }
+
+    /// Sets the spawner used by [`io_spawn`](executor::io_spawn) in this context.
+    pub fn spawner<S: executor::IoSpawner + Send + Sync + 'static>(mut self, spawner: S) -> Self {
+        self.spawner = Some(Arc::new(spawner));
+        self
+    }
+
+    /// Returns the driver of this context.
+    pub fn driver(&self) -> &Driver {
+        &self.driver
+    }
+
+    /// Returns the poller of this context.
+    pub fn poller(&self) -> Handle {
+        *self.poller.as_handle()
+    }
+
+    /// Enters this context on current thread until the returned guard is dropped.
+    pub fn enter(&self) -> IoContextGuard {
+        IoContextGuard(CONTEXT.replace(Some(self.clone())))
+    }
+
+    /// Wraps `fut` to enter this context while it's polled.
+    pub fn scope<Fut: Future>(&self, fut: Fut) -> Scoped<Fut> {
+        Scoped {
+            context: self.clone(),
+            fut: Box::pin(fut),
+        }
+    }
+
+    /// Returns the context entered by current thread.
+    fn current() -> Option<IoContext> {
+        CONTEXT.with_borrow(|context| context.clone())
+    }
+}
+
+/// The guard returned by [`IoContext::enter`], restores the previous context on drop.
+pub struct IoContextGuard(Option<IoContext>);
+
+impl Drop for IoContextGuard {
+    fn drop(&mut self) {
+        CONTEXT.set(self.0.take());
+    }
+}
+
+/// Future returned by [`IoContext::scope`] function.
+pub struct Scoped<Fut> {
+    context: IoContext,
+    fut: Pin<Box<Fut>>,
+}
+
+impl<Fut: Future> Future for Scoped<Fut> {
+    type Output = Fut::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _guard = self.context.enter();
+
+        self.fut.as_mut().poll(cx)
+    }
 }
 
 pub mod executor {
 
     use super::*;
 
-    use futures::{executor::ThreadPool, task::SpawnExt};
+    use std::{cell::Cell, time::Duration};
+
+    use futures::task::SpawnExt;
+    use hala_future::{
+        local_set,
+        scheduler::{WorkStealing, WorkStealingSpawner},
+        LocalSet,
+    };
+
+    /// The max duration the idle worker of [`block_on`] / [`local_block_on`] blocks in polling io events.
+    pub(crate) const IO_POLLER_TIMEOUT: Duration = Duration::from_millis(10);
 
     /// The `IoSpawner` trait allows for pushing an io futures onto an executor that will
     /// run them to completion.
+    ///
+    /// It's implemented for closures, so the executors (e.g. tokio / async-std) can be registered like:
+    /// `register_spawner(|fut| { tokio::spawn(fut); Ok(()) })`.
     pub trait IoSpawner {
         /// Spawns a io task that polls the given future with output `io::Result<()>` to completion.
         fn spawn(&self, fut: BoxFuture<'static, io::Result<()>>) -> io::Result<()>;
     }
 
+    impl<F> IoSpawner for F
+    where
+        F: Fn(BoxFuture<'static, io::Result<()>>) -> io::Result<()>,
+    {
+        fn spawn(&self, fut: BoxFuture<'static, io::Result<()>>) -> io::Result<()> {
+            self(fut)
+        }
+    }
+
+    /// The `LocalIoSpawner` trait allows for pushing `!Send` io futures onto the executor of current thread.
+    pub trait LocalIoSpawner {
+        /// Spawns a io task on current thread that polls the given future with output `io::Result<()>` to completion.
+        fn spawn_local(&self, fut: LocalBoxFuture<'static, io::Result<()>>) -> io::Result<()>;
+    }
+
+    impl<F> LocalIoSpawner for F
+    where
+        F: Fn(LocalBoxFuture<'static, io::Result<()>>) -> io::Result<()>,
+    {
+        fn spawn_local(&self, fut: LocalBoxFuture<'static, io::Result<()>>) -> io::Result<()> {
+            self(fut)
+        }
+    }
+
     static SPAWNER: OnceLock<Box<dyn IoSpawner + Send + Sync + 'static>> = OnceLock::new();
 
+    thread_local! {
+        static LOCAL_SPAWNER: OnceCell<Box<dyn LocalIoSpawner + 'static>> = const { OnceCell::new() };
+    }
+
     /// Register [`IoSpawner`] for all thread. the `IoSpawner` instance must implement [`Send`] + [`Sync`] traits.
     pub fn register_spawner<S: IoSpawner + Send + Sync + 'static>(spawner: S) -> io::Result<()> {
         if let Err(_) = SPAWNER.set(Box::new(spawner)) {
@@ -88,65 +223,140 @@ pub mod executor {
         Ok(())
     }
 
+    /// Register [`LocalIoSpawner`] for current thread, which is used by [`local_io_spawn`],
+    /// and by [`io_spawn`] if no global spawner is registered.
+    pub fn register_local_spawner<S: LocalIoSpawner + 'static>(spawner: S) -> io::Result<()> {
+        LOCAL_SPAWNER.with(|local| {
+            local.set(Box::new(spawner)).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "Call register_local_spawner more than once",
+                )
+            })
+        })
+    }
+
     /// Spawn an io task that polls the given future with output `io::Result<()>` to completion.
+    ///
+    /// The task is spawned by the spawner of the entered [`IoContext`], or by the global spawner,
+    /// or by the local spawner of current thread if there is no global one.
     pub fn io_spawn<Fut>(fut: Fut) -> io::Result<()>
     where
         Fut: Future<Output = io::Result<()>> + Send + 'static,
     {
+        if let Some(context) = IoContext::current() {
+            let fut = Box::pin(context.scope(coop::with_budget(fut, coop::DEFAULT_COOP_BUDGET)));
+
+            return match &context.spawner {
+                Some(spawner) => spawner.spawn(fut),
+                None => match SPAWNER.get() {
+                    Some(spawner) => spawner.spawn(fut),
+                    None => local_io_spawn(fut),
+                },
+            };
+        }
+
         if let Some(spawner) = SPAWNER.get() {
-            return spawner.spawn(Box::pin(fut));
+            return spawner.spawn(Box::pin(coop::with_budget(fut, coop::DEFAULT_COOP_BUDGET)));
         }
 
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            "Call register_local_spawner / register_spawner first",
-        ));
+        local_io_spawn(fut)
+    }
+
+    /// Spawn an `!Send` io task on current thread that polls the given future with output `io::Result<()>` to completion.
+    pub fn local_io_spawn<Fut>(fut: Fut) -> io::Result<()>
+    where
+        Fut: Future<Output = io::Result<()>> + 'static,
+    {
+        LOCAL_SPAWNER.with(|local| match local.get() {
+            Some(spawner) => {
+                spawner.spawn_local(Box::pin(coop::with_budget(fut, coop::DEFAULT_COOP_BUDGET)))
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Call register_local_spawner / register_spawner first",
+            )),
+        })
     }
 
     /// Start a io future task and block current thread until this future ready.
+    ///
+    /// The task runs on a [`WorkStealing`] runtime with `pool_size` workers, whose idle workers
+    /// poll the io events of the global poller. The runtime and the [`BlockOnIoSpawner`] are
+    /// created by the first call, `pool_size` of the later calls is ignored.
     pub fn block_on<Fut, R>(fut: Fut, pool_size: usize) -> R
     where
         Fut: Future<Output = R> + Send + 'static,
         R: Send + 'static,
     {
-        static POOL: OnceLock<ThreadPool> = OnceLock::new();
-
-        let pool = POOL.get_or_init(|| {
-            let pool = ThreadPool::builder()
-                .pool_size(pool_size)
-                .create()
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+        static RUNTIME: OnceLock<WorkStealing> = OnceLock::new();
+
+        let runtime = RUNTIME.get_or_init(|| {
+            let driver = get_driver().unwrap();
+            let poller = get_poller().unwrap();
+
+            let runtime = WorkStealing::builder()
+                .workers(pool_size)
+                .thread_name("hala-io-worker")
+                .io_poller(move || {
+                    // The worker can't be unparked while polling, so the poll must not block indefinitely.
+                    driver
+                        .fd_cntl(poller, Cmd::PollOnce(Some(IO_POLLER_TIMEOUT)))
+                        .map(|_| ())
+                })
+                .build()
                 .unwrap();
 
-            register_spawner(BlockOnIoSpawner(pool.clone())).unwrap();
+            register_spawner(BlockOnIoSpawner(runtime.spawner())).unwrap();
 
-            pool
+            runtime
         });
 
-        let dropping = Arc::new(AtomicBool::new(false));
+        let handle = runtime
+            .spawn_with_handle(coop::with_budget(fut, coop::DEFAULT_COOP_BUDGET))
+            .unwrap();
 
-        let dropping_cloned = dropping.clone();
+        futures::executor::block_on(handle)
+    }
 
-        let handle = pool
-            .spawn_with_handle(fut)
-            .map_err(|err| {
-                io::Error::new(io::ErrorKind::Other, format!("Spawn local error: {}", err))
-            })
-            .unwrap();
+    thread_local! {
+        /// The local spawner forwarding to the [`LocalSet`] of [`local_block_on`] has been registered.
+        static LOCAL_SET_SPAWNER: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Start a `!Send` io future on current thread and block current thread until this future ready.
+    ///
+    /// The tasks spawned by [`local_io_spawn`] are run on current thread too, the io events of the
+    /// global poller are polled while all tasks are pending, and the uncompleted tasks are dropped
+    /// once `fut` is ready. The local spawner is registered by the first call on current thread,
+    /// unless another local spawner has been registered.
+    pub fn local_block_on<Fut>(fut: Fut) -> Fut::Output
+    where
+        Fut: Future,
+    {
+        if !LOCAL_SET_SPAWNER.replace(true) {
+            _ = register_local_spawner(|fut: LocalBoxFuture<'static, io::Result<()>>| {
+                local_set::spawn_local(async move {
+                    if let Err(err) = fut.await {
+                        log::error!("{}", err);
+                    }
+                })
+            });
+        }
 
         let driver = get_driver().unwrap();
         let poller = get_poller().unwrap();
 
-        std::thread::spawn(move || {
-            while !dropping_cloned.load(Ordering::SeqCst) {
-                driver.fd_cntl(poller, Cmd::PollOnce(None)).unwrap();
-            }
+        let mut local_set = LocalSet::new().io_poller(move || {
+            driver
+                .fd_cntl(poller, Cmd::PollOnce(Some(IO_POLLER_TIMEOUT)))
+                .map(|_| ())
         });
 
-        futures::executor::block_on(handle)
+        local_set.run_until(coop::with_budget(fut, coop::DEFAULT_COOP_BUDGET))
     }
 
-    pub struct BlockOnIoSpawner(pub ThreadPool);
+    pub struct BlockOnIoSpawner(pub WorkStealingSpawner);
 
     impl IoSpawner for BlockOnIoSpawner {
         fn spawn(&self, fut: BoxFuture<'static, io::Result<()>>) -> std::io::Result<()> {
@@ -159,7 +369,123 @@ pub mod executor {
                         }
                     }
                 })
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+                .map_err(io::Error::other)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io, rc::Rc};
+
+    use futures::{
+        executor::LocalPool,
+        future::LocalBoxFuture,
+        task::{LocalSpawnExt, SpawnError},
+    };
+
+    use super::executor::*;
+
+    #[test]
+    fn test_local_spawner() {
+        let mut pool = LocalPool::new();
+
+        let spawner = pool.spawner();
+
+        register_local_spawner(move |fut: LocalBoxFuture<'static, io::Result<()>>| {
+            spawner
+                .spawn_local(async move {
+                    _ = fut.await;
+                })
+                .map_err(|err: SpawnError| io::Error::other(err))
+        })
+        .unwrap();
+
+        // The local spawner is registered once per thread.
+        register_local_spawner(|_: LocalBoxFuture<'static, io::Result<()>>| Ok(())).unwrap_err();
+
+        let value = Rc::new(Cell::new(0));
+
+        let cloned = value.clone();
+
+        local_io_spawn(async move {
+            cloned.set(1);
+
+            Ok(())
+        })
+        .unwrap();
+
+        pool.run();
+
+        assert_eq!(value.get(), 1);
+
+        // Other threads have no local spawner.
+        std::thread::spawn(|| local_io_spawn(async { Ok(()) }).unwrap_err())
+            .join()
+            .unwrap();
+    }
+
+    #[cfg(feature = "mio-driver")]
+    #[hala_test::test(crate::test::local_io_test)]
+    async fn test_local_block_on() {
+        let value = Rc::new(Cell::new(0));
+
+        let cloned = value.clone();
+
+        // The `!Send` io task is run on current thread.
+        local_io_spawn(async move {
+            crate::sleep(std::time::Duration::from_millis(10)).await?;
+
+            cloned.set(1);
+
+            Ok(())
+        })
+        .unwrap();
+
+        crate::sleep(std::time::Duration::from_millis(100))
+            .await
+            .unwrap();
+
+        assert_eq!(value.get(), 1);
+    }
+
+    #[hala_test::test(threads = 4)]
+    async fn test_multi_thread_runtime() {
+        let poller = super::get_poller().unwrap();
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+
+        io_spawn(async move {
+            crate::sleep(std::time::Duration::from_millis(10)).await?;
+
+            // The spawned task runs in the same isolated context.
+            sender.send(super::get_poller()?).unwrap();
+
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(receiver.await.unwrap(), poller);
+    }
+
+    #[hala_test::test(runtime = "local")]
+    async fn test_current_thread_runtime() {
+        let value = Rc::new(Cell::new(0));
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+
+        // The `Send` io task is run on current thread too.
+        io_spawn(async move {
+            crate::sleep(std::time::Duration::from_millis(10)).await?;
+
+            sender.send(1).unwrap();
+
+            Ok(())
+        })
+        .unwrap();
+
+        value.set(receiver.await.unwrap());
+
+        assert_eq!(value.get(), 1);
+    }
+}
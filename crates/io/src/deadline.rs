@@ -0,0 +1,243 @@
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hala_sync::{Lockable, LockableNew, SpinMutex};
+
+use super::{Driver, Handle, Sleep};
+
+/// The timer of one operation guarded by [`IoDeadline`].
+///
+/// The timer is owned by the operation future, so it's dropped with the cancelled operation
+/// and never fires on the next operation.
+#[derive(Default)]
+pub struct IoTimer {
+    /// The timer of the pending operation, created when the operation returns `Pending` first time.
+    sleep: Option<Sleep>,
+    /// The [`IoDeadline`] generation when the timer is created.
+    generation: usize,
+}
+
+#[derive(Default)]
+struct RawIoDeadline {
+    timeout: Option<Duration>,
+    /// Increased by [`IoDeadline::set_timeout`], the timers created before are restarted.
+    generation: usize,
+    /// The timer of the pending operation of [`IoDeadline::poll_io`].
+    timer: IoTimer,
+}
+
+/// The per-operation timeout of io object, e.g. the read timeout of tcp stream.
+///
+/// The timer is started when the operation is pending, and is reset once the operation completes,
+/// so the timeout limits the duration of each operation instead of the lifetime of the io object.
+pub struct IoDeadline {
+    driver: Driver,
+    poller: Handle,
+    raw: SpinMutex<RawIoDeadline>,
+}
+
+impl IoDeadline {
+    /// Create a deadline without timeout, the timer is created by `driver` and registered to `poller`.
+    pub fn new_with(driver: Driver, poller: Handle) -> Self {
+        Self {
+            driver,
+            poller,
+            raw: SpinMutex::new(Default::default()),
+        }
+    }
+
+    /// Sets the timeout of the operations, `None` means the operations never time out.
+    ///
+    /// The timers of the pending operations are restarted with the new timeout.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        let mut raw = self.raw.lock();
+
+        raw.timeout = timeout;
+        raw.generation += 1;
+    }
+
+    /// Returns the timeout of the operations.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.raw.lock().timeout
+    }
+
+    /// Checks the `poll` result of the poll-style operation, e.g. [`AsyncRead::poll_read`](futures::AsyncRead::poll_read),
+    /// returns [`TimedOut`](io::ErrorKind::TimedOut) error if the operation is still pending after the timeout.
+    ///
+    /// The poll-style operations can't be told apart, so they share the timer of this deadline,
+    /// which keeps running until the operation completes. An operation dropped while pending leaves
+    /// its timer to the next one, use [`poll_io_with`](Self::poll_io_with) in the operation futures instead.
+    pub fn poll_io<R>(
+        &self,
+        cx: &mut Context<'_>,
+        poll: Poll<io::Result<R>>,
+    ) -> Poll<io::Result<R>> {
+        let mut raw = self.raw.lock();
+
+        let raw = &mut *raw;
+
+        self.poll_timer(cx, raw.timeout, raw.generation, &mut raw.timer, poll)
+    }
+
+    /// Checks the `poll` result of the operation, whose timer is owned by the operation future.
+    ///
+    /// See [`poll_io`](Self::poll_io) for more information.
+    pub fn poll_io_with<R>(
+        &self,
+        cx: &mut Context<'_>,
+        timer: &mut IoTimer,
+        poll: Poll<io::Result<R>>,
+    ) -> Poll<io::Result<R>> {
+        let (timeout, generation) = {
+            let raw = self.raw.lock();
+
+            (raw.timeout, raw.generation)
+        };
+
+        self.poll_timer(cx, timeout, generation, timer, poll)
+    }
+
+    fn poll_timer<R>(
+        &self,
+        cx: &mut Context<'_>,
+        timeout: Option<Duration>,
+        generation: usize,
+        timer: &mut IoTimer,
+        poll: Poll<io::Result<R>>,
+    ) -> Poll<io::Result<R>> {
+        if poll.is_ready() {
+            timer.sleep = None;
+
+            return poll;
+        }
+
+        let Some(timeout) = timeout else {
+            timer.sleep = None;
+
+            return Poll::Pending;
+        };
+
+        if timer.sleep.is_none() || timer.generation != generation {
+            timer.sleep = Some(Sleep::new_with(self.driver.clone(), self.poller, timeout)?);
+            timer.generation = generation;
+        }
+
+        let result = match Pin::new(timer.sleep.as_mut().unwrap()).poll(cx) {
+            Poll::Ready(Ok(_)) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("io operation timed out, timeout={:?}", timeout),
+            )),
+            Poll::Ready(Err(err)) => Err(err),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        timer.sleep = None;
+
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(all(test, feature = "mio-driver"))]
+mod tests {
+    use std::{future::poll_fn, time::Instant};
+
+    use crate::{
+        current::{get_driver, get_poller},
+        sleep,
+        test::io_test,
+    };
+
+    use super::*;
+
+    #[hala_test::test(io_test)]
+    async fn test_io_deadline() {
+        let deadline = IoDeadline::new_with(get_driver().unwrap(), get_poller().unwrap());
+
+        // Ready operation never times out.
+        deadline.set_timeout(Some(Duration::ZERO));
+
+        poll_fn(|cx| deadline.poll_io(cx, Poll::Ready(Ok(()))))
+            .await
+            .unwrap();
+
+        deadline.set_timeout(Some(Duration::from_millis(50)));
+
+        assert_eq!(deadline.timeout(), Some(Duration::from_millis(50)));
+
+        let now = Instant::now();
+
+        let err = poll_fn(|cx| deadline.poll_io::<()>(cx, Poll::Pending))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(now.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_io_timer_cancelled() {
+        let deadline = IoDeadline::new_with(get_driver().unwrap(), get_poller().unwrap());
+
+        deadline.set_timeout(Some(Duration::from_millis(100)));
+
+        // The operation is cancelled after the first poll, its timer is dropped with it.
+        let mut timer = IoTimer::default();
+
+        poll_fn(|cx| {
+            assert!(deadline
+                .poll_io_with::<()>(cx, &mut timer, Poll::Pending)
+                .is_pending());
+
+            Poll::Ready(())
+        })
+        .await;
+
+        drop(timer);
+
+        sleep(Duration::from_millis(80)).await.unwrap();
+
+        let now = Instant::now();
+
+        let mut timer = IoTimer::default();
+
+        let err = poll_fn(|cx| deadline.poll_io_with::<()>(cx, &mut timer, Poll::Pending))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(now.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_set_timeout_restart() {
+        let deadline = IoDeadline::new_with(get_driver().unwrap(), get_poller().unwrap());
+
+        deadline.set_timeout(Some(Duration::from_millis(50)));
+
+        poll_fn(|cx| {
+            assert!(deadline.poll_io::<()>(cx, Poll::Pending).is_pending());
+
+            Poll::Ready(())
+        })
+        .await;
+
+        sleep(Duration::from_millis(40)).await.unwrap();
+
+        // The timer of the pending operation is restarted.
+        deadline.set_timeout(Some(Duration::from_millis(100)));
+
+        let now = Instant::now();
+
+        let err = poll_fn(|cx| deadline.poll_io::<()>(cx, Poll::Pending))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(now.elapsed() >= Duration::from_millis(90));
+    }
+}
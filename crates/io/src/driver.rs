@@ -1,6 +1,6 @@
 use std::{
     io,
-    net::{Shutdown, SocketAddr},
+    net::{IpAddr, Shutdown, SocketAddr},
     ptr::NonNull,
     task::Waker,
     time::Duration,
@@ -8,7 +8,10 @@ use std::{
 
 use bitmask_enum::bitmask;
 
-use crate::{Description, Handle, Interest};
+use crate::{
+    Description, DriverCapabilities, DriverStats, Handle, HandleInfo, Interest, PollMode,
+    SignalKind,
+};
 
 #[bitmask]
 pub enum FileMode {
@@ -18,6 +21,142 @@ pub enum FileMode {
     Truncate,
 }
 
+/// Socket options applied before binding, used by [`OpenFlags::BindWith`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BindOptions {
+    /// Set `SO_REUSEADDR` option.
+    pub reuse_address: bool,
+    /// Set `SO_REUSEPORT` option, only supported on unix platforms.
+    pub reuse_port: bool,
+    /// Set `UDP_GRO` option of udp socket, only supported on linux.
+    ///
+    /// The coalesced datagrams are received by [`Cmd::RecvMsg`] with [`DatagramInfo::segment_size`],
+    /// so don't receive with other commands after this option is set.
+    pub gro: bool,
+    /// The backlog of listening tcp socket, the driver's default value is used if `None`.
+    pub backlog: Option<u32>,
+    /// Set `IPV6_V6ONLY` option, only applied to ipv6 sockets.
+    pub only_v6: Option<bool>,
+    /// Set `IP_TTL` option of ipv4 sockets, or `IPV6_UNICAST_HOPS` option of ipv6 sockets.
+    pub ttl: Option<u32>,
+    /// Set `SO_BINDTODEVICE` option, only supported on linux.
+    pub bind_device: Option<DeviceName>,
+    /// Set `SO_RCVBUF` option, the os may adjust the value, e.g. linux doubles it and caps it by `rmem_max`.
+    ///
+    /// Setting this option disables the receive buffer auto-tuning of tcp sockets on linux.
+    pub recv_buffer_size: Option<usize>,
+    /// Set `SO_SNDBUF` option, the os may adjust the value, e.g. linux doubles it and caps it by `wmem_max`.
+    pub send_buffer_size: Option<usize>,
+}
+
+/// The protocol of raw socket, used by [`OpenFlags::Protocol`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RawProtocol {
+    /// The `IPPROTO_ICMP` protocol, the received packets include the ipv4 header.
+    Icmpv4,
+    /// The `IPPROTO_ICMPV6` protocol, the checksum of sending packets is filled by the kernel.
+    Icmpv6,
+}
+
+/// The socket buffer used by [`Cmd::SetBufferSize`] and [`Cmd::BufferSize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SocketBuffer {
+    /// The receive buffer, i.e. the `SO_RCVBUF` option.
+    Recv,
+    /// The send buffer, i.e. the `SO_SNDBUF` option.
+    Send,
+}
+
+/// The network interface name used by [`BindOptions::bind_device`].
+///
+/// This type stores the name inline, so [`BindOptions`] stays `Copy`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeviceName {
+    len: u8,
+    buf: [u8; DeviceName::MAX_LEN],
+}
+
+impl DeviceName {
+    /// The max length of interface name, which is `IFNAMSIZ - 1` on linux.
+    pub const MAX_LEN: usize = 15;
+
+    /// Create new interface name, returns error if `name` is empty or longer than [`MAX_LEN`](Self::MAX_LEN).
+    pub fn new(name: &str) -> io::Result<Self> {
+        if name.is_empty() || name.len() > Self::MAX_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid device name: {}", name),
+            ));
+        }
+
+        let mut buf = [0; Self::MAX_LEN];
+
+        buf[..name.len()].copy_from_slice(name.as_bytes());
+
+        Ok(Self {
+            len: name.len() as u8,
+            buf,
+        })
+    }
+
+    /// Returns the interface name.
+    pub fn as_str(&self) -> &str {
+        // Safety: the buf is copied from a valid utf8 string.
+        unsafe { std::str::from_utf8_unchecked(&self.buf[..self.len as usize]) }
+    }
+}
+
+impl std::fmt::Debug for DeviceName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DeviceName({})", self.as_str())
+    }
+}
+
+/// Ancillary data of udp datagram, used by [`Cmd::RecvMsg`] / [`Cmd::SendMsg`] commands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DatagramInfo {
+    /// The destination address of the received datagram, or the source address of the sending datagram.
+    ///
+    /// `None` means unknown or unspecified.
+    pub local_ip: Option<IpAddr>,
+    /// The ECN codepoint, which is the lowest two bits of the IP TOS / traffic class field.
+    pub ecn: u8,
+    /// The IP TTL / hop limit, `None` means unknown or the system default.
+    pub ttl: Option<u8>,
+    /// The size of segments in the buffer, the last segment may be shorter.
+    ///
+    /// Set by [`Cmd::RecvMsg`] if the received datagrams are coalesced by `UDP_GRO`,
+    /// or used by [`Cmd::SendMsg`] to send the buffer as multiple datagrams by `UDP_SEGMENT`.
+    pub segment_size: Option<u16>,
+}
+
+/// One datagram buffer used by [`Cmd::RecvBatch`] / [`Cmd::SendBatch`] commands.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufSlot {
+    /// The buffer to receive datagram, or the data of sending datagram.
+    pub buf: Vec<u8>,
+    /// The length of the received datagram, or the length of data to send in `buf`.
+    pub len: usize,
+    /// The source address of the received datagram, or the destination address of sending datagram.
+    pub raddr: Option<SocketAddr>,
+}
+
+impl BufSlot {
+    /// Create new slot with zeroed buffer of `capacity` bytes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buf: vec![0; capacity],
+            len: 0,
+            raddr: None,
+        }
+    }
+
+    /// Returns the received datagram, or the data to send.
+    pub fn data(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
 /// File description open flags used by `fd_open` method.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum OpenFlags<'a> {
@@ -26,9 +165,27 @@ pub enum OpenFlags<'a> {
     OpenFile(&'a str, FileMode),
     /// The binding addrs of the opening socket
     Bind(&'a [SocketAddr]),
+    /// The binding addrs and the pre-bind socket options of the opening socket
+    BindWith(&'a [SocketAddr], BindOptions),
     /// The address list of the remote peer to which the open socket will connect
     Connect(&'a [SocketAddr]),
+    /// The address of the remote peer to which the open socket will start a non-blocking connection.
+    NonblockingConnect(SocketAddr),
     Duration(Duration),
+    /// The name of the named pipe server instance to create, e.g. `\\.\pipe\hala`.
+    PipeServer(&'a str),
+    /// The name of the named pipe to which the client end will connect.
+    PipeClient(&'a str),
+    /// The kind of os signal to listen.
+    Signal(SignalKind),
+    /// The raw file descriptor whose ownership is transferred to the opening handle.
+    #[cfg(unix)]
+    RawFd(std::os::fd::RawFd),
+    /// The raw socket whose ownership is transferred to the opening handle.
+    #[cfg(windows)]
+    RawSocket(std::os::windows::io::RawSocket),
+    /// The protocol of the opening raw socket.
+    Protocol(RawProtocol),
     UserDefined(&'a [u8]),
     /// Flag to create poller in single thread mode.
     LocalPoller,
@@ -55,6 +212,18 @@ impl<'a> OpenFlags<'a> {
         }
     }
 
+    /// Returns the binding addrs and socket options of `Bind` / `BindWith` flags.
+    pub fn try_into_bind_with(self) -> io::Result<(&'a [SocketAddr], BindOptions)> {
+        match self {
+            Self::Bind(laddrs) => Ok((laddrs, BindOptions::default())),
+            Self::BindWith(laddrs, options) => Ok((laddrs, options)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect Bind / BindWith, but got {:?}", self),
+            )),
+        }
+    }
+
     pub fn try_into_connect(self) -> io::Result<&'a [SocketAddr]> {
         match self {
             Self::Connect(raddrs) => Ok(raddrs),
@@ -100,6 +269,24 @@ pub enum Cmd<'a> {
         buf: &'a [u8],
     },
 
+    /// Write the owned buffer to tcp stream, the caller doesn't need to keep the data alive across `Pending`.
+    ///
+    /// Returns the number of bytes taken by the driver, the driver may queue the unwritten data internally
+    /// and send them once the stream is writable, in this case the whole `buf` is taken.
+    /// Returns WOULD_BLOCK if nothing is taken, e.g. too many data are queued.
+    WriteOwned {
+        waker: Waker,
+        buf: bytes::Bytes,
+    },
+
+    /// Waits until the data queued by [`WriteOwned`](Cmd::WriteOwned) are written to the tcp stream,
+    /// returns WOULD_BLOCK if the queue is not empty.
+    Flush(Waker),
+
+    /// Connect the udp socket to the remote peer, after that the [`Read`](Cmd::Read) / [`Write`](Cmd::Write)
+    /// commands receive from / send to the connected peer.
+    Connect(SocketAddr),
+
     /// Command `Sendto` parameter for udp socket.
     SendTo {
         waker: Waker,
@@ -113,16 +300,71 @@ pub enum Cmd<'a> {
         buf: &'a mut [u8],
     },
 
+    /// Command to send datagram with ancillary data, e.g. source address and ECN codepoint.
+    SendMsg {
+        waker: Waker,
+        buf: &'a [u8],
+        raddr: SocketAddr,
+        info: DatagramInfo,
+    },
+
+    /// Command to send the buffer as multiple datagrams of `segment_size` bytes by one syscall,
+    /// the last datagram may be shorter.
+    SendToGso {
+        waker: Waker,
+        buf: &'a [u8],
+        segment_size: u16,
+        raddr: SocketAddr,
+    },
+
+    /// Command to receive datagram with ancillary data, e.g. destination address and ECN codepoint.
+    RecvMsg {
+        waker: Waker,
+        buf: &'a mut [u8],
+    },
+
+    /// Command to receive up to `slots.len()` datagrams in one wake-up, the datagrams are read
+    /// by repeated non-blocking syscalls until there is no more ready datagram.
+    ///
+    /// Returns the number of filled slots, or WOULD_BLOCK if no datagram is ready.
+    RecvBatch {
+        waker: Waker,
+        slots: &'a mut [BufSlot],
+    },
+
+    /// Command to send the datagrams of `slots` in one wake-up, the datagrams are sent
+    /// by repeated non-blocking syscalls until the socket is not writable.
+    ///
+    /// Returns the number of sent slots, or WOULD_BLOCK if no datagram is sent.
+    SendBatch {
+        waker: Waker,
+        slots: &'a [BufSlot],
+    },
+
+    /// Read data from stream once without registering waker, may returns WOULD_BLOCK.
+    TryRead(&'a mut [u8]),
+    /// Write data to stream once without registering waker, may returns WOULD_BLOCK.
+    TryWrite(&'a [u8]),
+    /// Send datagram once without registering waker, may returns WOULD_BLOCK.
+    TrySendTo {
+        buf: &'a [u8],
+        raddr: SocketAddr,
+    },
+    /// Receive datagram once without registering waker, may returns WOULD_BLOCK.
+    TryRecvFrom(&'a mut [u8]),
+
     /// Register io event interests with `Poll`
     Register {
         source: Handle,
         interests: Interest,
+        mode: PollMode,
     },
 
-    /// Re-register io event interests with `Poll`
+    /// Re-register io event interests with `Poll`, also re-arms the [`OneShot`](PollMode::OneShot) source.
     ReRegister {
         source: Handle,
         interests: Interest,
+        mode: PollMode,
     },
 
     /// Deregister io event interests with `Poll`
@@ -131,16 +373,212 @@ pub enum Cmd<'a> {
     /// Try accept one incoming connection.
     Accept(Waker),
 
-    /// Poll once io readiness events.
+    /// Check the readiness of socket without performing read/write, may returns WOULD_BLOCK.
+    PollReadiness {
+        waker: Waker,
+        /// Either [`Readable`](Interest::Readable) or [`Writable`](Interest::Writable).
+        interest: Interest,
+    },
+
+    /// Take the [`Error`](Interest::Error) / [`Hup`](Interest::Hup) / [`Priority`](Interest::Priority)
+    /// events of socket in `interests` reported since last poll, may returns WOULD_BLOCK.
+    ///
+    /// The response is [`CmdResp::Events`], the taken events are reported again only if they occur again.
+    PollEvents {
+        waker: Waker,
+        interests: Interest,
+    },
+
+    /// Check if the non-blocking connection is established, may returns WOULD_BLOCK.
+    ///
+    /// For the named pipe server, check if a client has connected to this instance.
+    PollConnect(Waker),
+
+    /// Take the signals delivered since last poll, may returns WOULD_BLOCK.
+    PollSignal(Waker),
+
+    /// Poll once io readiness events, blocks until the io event arrives, the earliest timer expires
+    /// or the timeout elapses. `None` blocks indefinitely if there are no timers.
     PollOnce(Option<Duration>),
 
+    /// Wake up the poller blocking in [`PollOnce`](Cmd::PollOnce), can be sent from any thread.
+    WakePoller,
+
+    /// Get the current time of the poller [`Clock`](crate::Clock), which drives the timers
+    /// registered to the poller, the response is [`CmdResp::Instant`].
+    Now,
+
     /// Try to clone the handle.
     TryClone,
     Timeout(Waker),
+    /// Reset the timeout duration of the timer, the new duration takes effect after
+    /// re-registering the timer with the poller.
+    ResetTimeout(Duration),
     LocalAddr,
+    /// Get the peer address of the connected socket.
     RemoteAddr,
 
     Shutdown(Shutdown),
+
+    /// Set the buffer size of the socket, see [`BindOptions::recv_buffer_size`] for the adjustment by os.
+    SetBufferSize(SocketBuffer, usize),
+
+    /// Get the buffer size of the socket, the response is [`CmdResp::DataLen`].
+    BufferSize(SocketBuffer),
+
+    /// Set the `IP_TTL` option of ipv4 udp socket, or the `IPV6_UNICAST_HOPS` option of ipv6 udp socket.
+    SetTtl(u32),
+
+    /// Get the ttl / hop limit of the udp socket, the response is [`CmdResp::DataLen`].
+    Ttl,
+
+    /// Set or clear the DF bit of the datagrams sent by the udp socket, which is required by the path MTU discovery,
+    /// i.e. `IP_MTU_DISCOVER` on linux, `IP_DONTFRAG` on BSD / macOS and `IPV6_DONTFRAG` for ipv6 sockets.
+    ///
+    /// Once set, the oversized datagram fails with `EMSGSIZE` instead of being fragmented.
+    SetDontFragment(bool),
+
+    /// Get whether the DF bit of the datagrams sent by the udp socket is set, the response is [`CmdResp::Bool`].
+    DontFragment,
+
+    /// Get the snapshot of driver metrics counters.
+    Stats,
+
+    /// Get the optional features supported by the driver.
+    Capabilities,
+
+    /// Get the open file handles with their creation backtraces,
+    /// requires the `track-handles` feature.
+    DumpHandles,
+
+    /// Get the raw os file descriptor (raw socket on windows) of the handle,
+    /// the ownership is not transferred.
+    AsRawFd,
+
+    /// Duplicate the raw os file descriptor (raw socket on windows) of the handle,
+    /// the ownership of the duplicated one is transferred to the caller.
+    DupRawFd,
+}
+
+impl<'a> Cmd<'a> {
+    /// Reborrows this command with a shorter lifetime, so the command can be passed to
+    /// [`fd_cntl`](RawDriver::fd_cntl) by value while it's still owned by the caller,
+    /// e.g. by [`fd_cntl_batch`](RawDriver::fd_cntl_batch).
+    pub fn reborrow(&mut self) -> Cmd<'_> {
+        match self {
+            Cmd::Read { waker, buf } => Cmd::Read {
+                waker: waker.clone(),
+                buf,
+            },
+            Cmd::Write { waker, buf } => Cmd::Write {
+                waker: waker.clone(),
+                buf,
+            },
+            Cmd::WriteOwned { waker, buf } => Cmd::WriteOwned {
+                waker: waker.clone(),
+                buf: buf.clone(),
+            },
+            Cmd::Flush(waker) => Cmd::Flush(waker.clone()),
+            Cmd::Connect(raddr) => Cmd::Connect(*raddr),
+            Cmd::SendTo { waker, buf, raddr } => Cmd::SendTo {
+                waker: waker.clone(),
+                buf,
+                raddr: *raddr,
+            },
+            Cmd::RecvFrom { waker, buf } => Cmd::RecvFrom {
+                waker: waker.clone(),
+                buf,
+            },
+            Cmd::SendMsg {
+                waker,
+                buf,
+                raddr,
+                info,
+            } => Cmd::SendMsg {
+                waker: waker.clone(),
+                buf,
+                raddr: *raddr,
+                info: *info,
+            },
+            Cmd::SendToGso {
+                waker,
+                buf,
+                segment_size,
+                raddr,
+            } => Cmd::SendToGso {
+                waker: waker.clone(),
+                buf,
+                segment_size: *segment_size,
+                raddr: *raddr,
+            },
+            Cmd::RecvMsg { waker, buf } => Cmd::RecvMsg {
+                waker: waker.clone(),
+                buf,
+            },
+            Cmd::RecvBatch { waker, slots } => Cmd::RecvBatch {
+                waker: waker.clone(),
+                slots,
+            },
+            Cmd::SendBatch { waker, slots } => Cmd::SendBatch {
+                waker: waker.clone(),
+                slots,
+            },
+            Cmd::TryRead(buf) => Cmd::TryRead(buf),
+            Cmd::TryWrite(buf) => Cmd::TryWrite(buf),
+            Cmd::TrySendTo { buf, raddr } => Cmd::TrySendTo { buf, raddr: *raddr },
+            Cmd::TryRecvFrom(buf) => Cmd::TryRecvFrom(buf),
+            Cmd::Register {
+                source,
+                interests,
+                mode,
+            } => Cmd::Register {
+                source: *source,
+                interests: *interests,
+                mode: *mode,
+            },
+            Cmd::ReRegister {
+                source,
+                interests,
+                mode,
+            } => Cmd::ReRegister {
+                source: *source,
+                interests: *interests,
+                mode: *mode,
+            },
+            Cmd::Deregister(source) => Cmd::Deregister(*source),
+            Cmd::Accept(waker) => Cmd::Accept(waker.clone()),
+            Cmd::PollReadiness { waker, interest } => Cmd::PollReadiness {
+                waker: waker.clone(),
+                interest: *interest,
+            },
+            Cmd::PollEvents { waker, interests } => Cmd::PollEvents {
+                waker: waker.clone(),
+                interests: *interests,
+            },
+            Cmd::PollConnect(waker) => Cmd::PollConnect(waker.clone()),
+            Cmd::PollSignal(waker) => Cmd::PollSignal(waker.clone()),
+            Cmd::PollOnce(timeout) => Cmd::PollOnce(*timeout),
+            Cmd::WakePoller => Cmd::WakePoller,
+            Cmd::Now => Cmd::Now,
+            Cmd::TryClone => Cmd::TryClone,
+            Cmd::Timeout(waker) => Cmd::Timeout(waker.clone()),
+            Cmd::ResetTimeout(duration) => Cmd::ResetTimeout(*duration),
+            Cmd::LocalAddr => Cmd::LocalAddr,
+            Cmd::RemoteAddr => Cmd::RemoteAddr,
+            Cmd::Shutdown(how) => Cmd::Shutdown(*how),
+            Cmd::SetBufferSize(buffer, size) => Cmd::SetBufferSize(*buffer, *size),
+            Cmd::BufferSize(buffer) => Cmd::BufferSize(*buffer),
+            Cmd::SetTtl(ttl) => Cmd::SetTtl(*ttl),
+            Cmd::Ttl => Cmd::Ttl,
+            Cmd::SetDontFragment(enabled) => Cmd::SetDontFragment(*enabled),
+            Cmd::DontFragment => Cmd::DontFragment,
+            Cmd::Stats => Cmd::Stats,
+            Cmd::Capabilities => Cmd::Capabilities,
+            Cmd::DumpHandles => Cmd::DumpHandles,
+            Cmd::AsRawFd => Cmd::AsRawFd,
+            Cmd::DupRawFd => Cmd::DupRawFd,
+        }
+    }
 }
 
 /// The response of `fd_cntl` .
@@ -149,14 +587,36 @@ pub enum CmdResp {
     None,
     /// Command `RecvFrom` response data.
     RecvFrom(usize, SocketAddr),
+    /// Command `RecvMsg` response data.
+    RecvMsg(usize, SocketAddr, DatagramInfo),
     /// Command `Accept` response data.
     Incoming(Handle, SocketAddr),
-    /// Command `Write` / `SendTo` response data
+    /// Command `Write` / `SendTo` / `SendToGso` response data, or the number of slots of `RecvBatch` / `SendBatch`.
     DataLen(usize),
     Timeout(bool),
     /// Command `TryClone` response data.
     Cloned(Handle),
     SockAddr(SocketAddr),
+    /// Command `Stats` response data.
+    Stats(DriverStats),
+    /// Command `Capabilities` response data.
+    Capabilities(DriverCapabilities),
+    /// Command `DumpHandles` response data.
+    Handles(Vec<HandleInfo>),
+    /// Command `PollSignal` response data, the number of delivered signals.
+    Signal(usize),
+    /// Command `PollEvents` response data.
+    Events(Interest),
+    /// Command `Now` response data.
+    Instant(std::time::Instant),
+    /// Command `DontFragment` response data.
+    Bool(bool),
+    /// Command `AsRawFd` / `DupRawFd` response data.
+    #[cfg(unix)]
+    RawFd(std::os::fd::RawFd),
+    /// Command `AsRawFd` / `DupRawFd` response data.
+    #[cfg(windows)]
+    RawSocket(std::os::windows::io::RawSocket),
 }
 
 impl CmdResp {
@@ -180,6 +640,16 @@ impl CmdResp {
         }
     }
 
+    pub fn try_into_recv_msg(self) -> io::Result<(usize, SocketAddr, DatagramInfo)> {
+        match self {
+            Self::RecvMsg(len, raddr, info) => Ok((len, raddr, info)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect RecvMsg, but got {:?}", self),
+            )),
+        }
+    }
+
     pub fn try_into_sockaddr(self) -> io::Result<SocketAddr> {
         match self {
             Self::SockAddr(addr) => Ok(addr),
@@ -209,6 +679,98 @@ impl CmdResp {
             )),
         }
     }
+
+    pub fn try_into_bool(self) -> io::Result<bool> {
+        match self {
+            Self::Bool(value) => Ok(value),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect Bool, but got {:?}", self),
+            )),
+        }
+    }
+
+    pub fn try_into_stats(self) -> io::Result<DriverStats> {
+        match self {
+            Self::Stats(stats) => Ok(stats),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect Stats, but got {:?}", self),
+            )),
+        }
+    }
+
+    pub fn try_into_capabilities(self) -> io::Result<DriverCapabilities> {
+        match self {
+            Self::Capabilities(capabilities) => Ok(capabilities),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect Capabilities, but got {:?}", self),
+            )),
+        }
+    }
+
+    pub fn try_into_signal(self) -> io::Result<usize> {
+        match self {
+            Self::Signal(count) => Ok(count),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect Signal, but got {:?}", self),
+            )),
+        }
+    }
+
+    pub fn try_into_instant(self) -> io::Result<std::time::Instant> {
+        match self {
+            Self::Instant(instant) => Ok(instant),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect Instant, but got {:?}", self),
+            )),
+        }
+    }
+
+    pub fn try_into_events(self) -> io::Result<Interest> {
+        match self {
+            Self::Events(events) => Ok(events),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect Events, but got {:?}", self),
+            )),
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn try_into_raw_fd(self) -> io::Result<std::os::fd::RawFd> {
+        match self {
+            Self::RawFd(fd) => Ok(fd),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect RawFd, but got {:?}", self),
+            )),
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn try_into_raw_socket(self) -> io::Result<std::os::windows::io::RawSocket> {
+        match self {
+            Self::RawSocket(socket) => Ok(socket),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect RawSocket, but got {:?}", self),
+            )),
+        }
+    }
+
+    pub fn try_into_handles(self) -> io::Result<Vec<HandleInfo>> {
+        match self {
+            Self::Handles(handles) => Ok(handles),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect Handles, but got {:?}", self),
+            )),
+        }
+    }
 }
 
 /// io driver must implement this trait.
@@ -219,6 +781,18 @@ pub trait RawDriver {
     /// performs one of file description operation.
     fn fd_cntl(&self, handle: Handle, cmd: Cmd) -> io::Result<CmdResp>;
 
+    /// Performs the file description operations in order, returns the result of each command.
+    ///
+    /// The failure of one command doesn't stop the following commands, e.g. the `WOULD_BLOCK` error
+    /// of [`TryRead`](Cmd::TryRead) followed by [`Write`](Cmd::Write).
+    ///
+    /// The default implementation calls [`fd_cntl`](Self::fd_cntl) for each command.
+    fn fd_cntl_batch(&self, handle: Handle, cmds: &mut [Cmd]) -> Vec<io::Result<CmdResp>> {
+        cmds.iter_mut()
+            .map(|cmd| self.fd_cntl(handle, cmd.reborrow()))
+            .collect()
+    }
+
     /// Close the opened file description.
     ///
     /// #Panic
@@ -227,11 +801,14 @@ pub trait RawDriver {
     fn fd_close(&self, handle: Handle) -> io::Result<()>;
 }
 
+type FdCntlBatch = unsafe fn(NonNull<DriverVTable>, Handle, &mut [Cmd]) -> Vec<io::Result<CmdResp>>;
+
 #[repr(C)]
 #[derive(Clone)]
 struct DriverVTable {
     fd_open: unsafe fn(NonNull<DriverVTable>, Description, OpenFlags) -> io::Result<Handle>,
     fd_cntl: unsafe fn(NonNull<DriverVTable>, Handle, Cmd) -> io::Result<CmdResp>,
+    fd_cntl_batch: FdCntlBatch,
     fd_close: unsafe fn(NonNull<DriverVTable>, Handle) -> io::Result<()>,
     clone: unsafe fn(NonNull<DriverVTable>) -> Driver,
     drop: unsafe fn(NonNull<DriverVTable>),
@@ -263,6 +840,16 @@ impl DriverVTable {
             result
         }
 
+        fn fd_cntl_batch<R: RawDriver + Clone>(
+            ptr: NonNull<DriverVTable>,
+            handle: Handle,
+            cmds: &mut [Cmd],
+        ) -> Vec<io::Result<CmdResp>> {
+            let header = ptr.cast::<DriverHeader<R>>();
+
+            unsafe { header.as_ref().data.fd_cntl_batch(handle, cmds) }
+        }
+
         fn fd_close<R: RawDriver + Clone>(
             ptr: NonNull<DriverVTable>,
             handle: Handle,
@@ -289,6 +876,7 @@ impl DriverVTable {
         Self {
             fd_open: fd_open::<R>,
             fd_cntl: fd_cntl::<R>,
+            fd_cntl_batch: fd_cntl_batch::<R>,
             fd_close: fd_close::<R>,
             clone: clone::<R>,
             drop: drop::<R>,
@@ -346,11 +934,21 @@ impl Driver {
         unsafe { (self.ptr.as_ref().fd_cntl)(self.ptr, handle, cmd) }
     }
 
+    /// Performs the file description operations in order by one call of the driver,
+    /// returns the result of each command, see [`RawDriver::fd_cntl_batch`].
+    ///
+    /// The hot paths can coalesce the commands of one handle, e.g. writing the request and reading
+    /// the response by `Write` + `Read`, to save the dynamic dispatch of each command.
+    pub fn fd_cntl_batch(&self, handle: Handle, cmds: &mut [Cmd]) -> Vec<io::Result<CmdResp>> {
+        unsafe { (self.ptr.as_ref().fd_cntl_batch)(self.ptr, handle, cmds) }
+    }
+
     /// Close the opened file description.
     ///
     /// #Panic
     ///
     /// Closing the `Handle` twice must cause panic
+    #[deprecated(note = "use `OwnedHandle` to close the handle on drop")]
     pub fn fd_close(&self, handle: Handle) -> io::Result<()> {
         unsafe { (self.ptr.as_ref().fd_close)(self.ptr, handle) }
     }
@@ -389,9 +987,17 @@ mod tests {
         fn fd_cntl(
             &self,
             _handle: crate::Handle,
-            _cmd: crate::Cmd,
+            cmd: crate::Cmd,
         ) -> std::io::Result<crate::CmdResp> {
-            Ok(CmdResp::None)
+            match cmd {
+                Cmd::TryRead(buf) => Ok(CmdResp::DataLen(buf.len())),
+                Cmd::TryWrite(buf) => Ok(CmdResp::DataLen(buf.len())),
+                Cmd::WakePoller => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "mock driver can't wake up poller",
+                )),
+                _ => Ok(CmdResp::None),
+            }
         }
 
         fn fd_close(&self, handle: crate::Handle) -> std::io::Result<()> {
@@ -402,6 +1008,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_driver_vtable() {
         let driver = Driver::new(MockDriver {});
 
@@ -413,4 +1020,34 @@ mod tests {
 
         driver.fd_close(handle).unwrap();
     }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_driver_fd_cntl_batch() {
+        let driver = Driver::new(MockDriver {});
+
+        let handle = driver.fd_open(Description::File, OpenFlags::None).unwrap();
+
+        let mut buf = [0; 4];
+
+        let results = driver.fd_cntl_batch(
+            handle,
+            &mut [
+                Cmd::TryRead(&mut buf),
+                Cmd::WakePoller,
+                Cmd::TryWrite(b"hello"),
+            ],
+        );
+
+        // The failed command doesn't stop the following commands.
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[0], Ok(CmdResp::DataLen(4))));
+        assert_eq!(
+            results[1].as_ref().unwrap_err().kind(),
+            io::ErrorKind::Unsupported
+        );
+        assert!(matches!(results[2], Ok(CmdResp::DataLen(5))));
+
+        driver.fd_close(handle).unwrap();
+    }
 }
@@ -5,7 +5,9 @@ use std::{io, net::Shutdown};
 use std::net::SocketAddr;
 
 use crate::{
-    CmdResp, Description, FileMode, Handle, Interest, IntoRawDriver, OpenFlags, RawDriver,
+    BindOptions, BufSlot, CmdResp, DatagramInfo, Description, DriverCapabilities, DriverStats,
+    FileMode, Handle, HandleInfo, Interest, IntoRawDriver, OpenFlags, PollMode, RawDriver,
+    RawProtocol, SignalKind, SocketBuffer,
 };
 
 /// Easier to implement version of `RawDriver` trait
@@ -30,9 +32,39 @@ pub trait RawDriverExt {
 
     fn timeout_close(&self, handle: Handle) -> io::Result<()>;
 
+    /// Reset the timeout `duration` of the timer, which will be restarted by
+    /// [`poller_reregister`](Self::poller_reregister).
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn timeout_reset(&self, _handle: Handle, _duration: Duration) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "reset timeout is not supported",
+        ))
+    }
+
     /// Create new `TcpListener` socket and bound to `laddrs`
     fn tcp_listener_bind(&self, laddrs: &[SocketAddr]) -> io::Result<Handle>;
 
+    /// Create new `TcpListener` socket with pre-bind `options` and bound to `laddrs`
+    ///
+    /// The default implementation falls back to [`tcp_listener_bind`](Self::tcp_listener_bind)
+    /// if no option is set, otherwise returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn tcp_listener_bind_with(
+        &self,
+        laddrs: &[SocketAddr],
+        options: BindOptions,
+    ) -> io::Result<Handle> {
+        if options != BindOptions::default() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "bind options is not supported",
+            ));
+        }
+
+        self.tcp_listener_bind(laddrs)
+    }
+
     /// Accept one incoming `TcpStream` socket, may returns WOULD_BLOCK
     fn tcp_listener_accept(&self, waker: Waker, handle: Handle)
         -> io::Result<(Handle, SocketAddr)>;
@@ -43,6 +75,20 @@ pub trait RawDriverExt {
     /// Create new `TcpStream` socket and try connect to remote peer.
     fn tcp_stream_connect(&self, raddrs: &[SocketAddr]) -> io::Result<Handle>;
 
+    /// Create new `TcpStream` socket and start a non-blocking connection to remote peer.
+    ///
+    /// The default implementation falls back to the blocking [`tcp_stream_connect`](Self::tcp_stream_connect).
+    fn tcp_stream_connect_nonblocking(&self, raddr: SocketAddr) -> io::Result<Handle> {
+        self.tcp_stream_connect(&[raddr])
+    }
+
+    /// Check if the non-blocking connection is established, may returns WOULD_BLOCK
+    ///
+    /// The default implementation always returns `Ok(())`.
+    fn tcp_stream_poll_connect(&self, _waker: Waker, _handle: Handle) -> io::Result<()> {
+        Ok(())
+    }
+
     /// Write data to underly `TcpStream`
     fn tcp_stream_write(&self, waker: Waker, handle: Handle, buf: &[u8]) -> io::Result<usize>;
 
@@ -55,6 +101,25 @@ pub trait RawDriverExt {
     /// Create a new `UdpSocket` and bind to `laddrs`
     fn udp_socket_bind(&self, laddrs: &[SocketAddr]) -> io::Result<Handle>;
 
+    /// Create a new `UdpSocket` with pre-bind `options` and bind to `laddrs`
+    ///
+    /// The default implementation falls back to [`udp_socket_bind`](Self::udp_socket_bind)
+    /// if no option is set, otherwise returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_bind_with(
+        &self,
+        laddrs: &[SocketAddr],
+        options: BindOptions,
+    ) -> io::Result<Handle> {
+        if options != BindOptions::default() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "bind options is not supported",
+            ));
+        }
+
+        self.udp_socket_bind(laddrs)
+    }
+
     /// Send one datagram to `raddr` peer
     fn udp_socket_sendto(
         &self,
@@ -87,6 +152,7 @@ pub trait RawDriverExt {
         poller: Handle,
         source: Handle,
         interests: Interest,
+        mode: PollMode,
     ) -> io::Result<()>;
 
     /// Re-register interests events of one source.
@@ -95,6 +161,7 @@ pub trait RawDriverExt {
         poller: Handle,
         source: Handle,
         interests: Interest,
+        mode: PollMode,
     ) -> io::Result<()>;
 
     /// Deregister interests events of one source.
@@ -102,6 +169,27 @@ pub trait RawDriverExt {
 
     fn poller_poll_once(&self, handle: Handle, duration: Option<Duration>) -> io::Result<()>;
 
+    /// Returns the current time of the clock driving the timers of the poller.
+    ///
+    /// The default implementation returns the system time.
+    fn poller_now(&self, handle: Handle) -> io::Result<std::time::Instant> {
+        handle.expect(Description::Poller)?;
+
+        Ok(std::time::Instant::now())
+    }
+
+    /// Wakes up the poller blocking in [`poller_poll_once`](Self::poller_poll_once).
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn poller_wake(&self, handle: Handle) -> io::Result<()> {
+        handle.expect(Description::Poller)?;
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the driver does not support waking up poller",
+        ))
+    }
+
     /// Close poller
     fn poller_close(&self, handle: Handle) -> io::Result<()>;
 
@@ -114,6 +202,659 @@ pub trait RawDriverExt {
     fn tcp_stream_shutdown(&self, handle: Handle, shutdown: Shutdown) -> io::Result<()>;
 
     fn udp_local_addr(&self, handle: Handle) -> io::Result<SocketAddr>;
+
+    /// Reads data from stream once without registering waker.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn tcp_stream_try_read(&self, _handle: Handle, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "try read/write is not supported",
+        ))
+    }
+
+    /// Writes the owned buffer to stream, see [`Cmd::WriteOwned`](crate::Cmd::WriteOwned).
+    ///
+    /// The default implementation writes the buffer by [`tcp_stream_write`](Self::tcp_stream_write)
+    /// without queuing.
+    fn tcp_stream_write_owned(
+        &self,
+        waker: Waker,
+        handle: Handle,
+        buf: bytes::Bytes,
+    ) -> io::Result<usize> {
+        self.tcp_stream_write(waker, handle, &buf)
+    }
+
+    /// Waits until the queued data of stream are written, see [`Cmd::Flush`](crate::Cmd::Flush).
+    ///
+    /// The default implementation returns `Ok(())`, since nothing is queued by
+    /// the default [`tcp_stream_write_owned`](Self::tcp_stream_write_owned).
+    fn tcp_stream_flush(&self, _waker: Waker, handle: Handle) -> io::Result<()> {
+        handle.expect(Description::TcpStream)
+    }
+
+    /// Writes data to stream once without registering waker.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn tcp_stream_try_write(&self, _handle: Handle, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "try read/write is not supported",
+        ))
+    }
+
+    /// Sends datagram once without registering waker.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_try_sendto(
+        &self,
+        _handle: Handle,
+        _buf: &[u8],
+        _raddr: SocketAddr,
+    ) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "try read/write is not supported",
+        ))
+    }
+
+    /// Receives datagram once without registering waker.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_try_recv_from(
+        &self,
+        _handle: Handle,
+        _buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "try read/write is not supported",
+        ))
+    }
+
+    /// Connects the udp socket to the remote peer `raddr`, the socket only sends to
+    /// and receives from `raddr` after connected.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_connect(&self, _handle: Handle, _raddr: SocketAddr) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "udp connect is not supported",
+        ))
+    }
+
+    /// Sends one datagram to the connected peer.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_send(&self, _waker: Waker, _handle: Handle, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "udp connect is not supported",
+        ))
+    }
+
+    /// Receives one datagram from the connected peer.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_recv(
+        &self,
+        _waker: Waker,
+        _handle: Handle,
+        _buf: &mut [u8],
+    ) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "udp connect is not supported",
+        ))
+    }
+
+    /// Returns the address of the connected peer.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_remote_addr(&self, _handle: Handle) -> io::Result<SocketAddr> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "udp connect is not supported",
+        ))
+    }
+
+    /// Sends datagram to `raddr` with ancillary data `info`.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_send_msg(
+        &self,
+        _waker: Waker,
+        _handle: Handle,
+        _buf: &[u8],
+        _raddr: SocketAddr,
+        _info: DatagramInfo,
+    ) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "send/recv msg is not supported",
+        ))
+    }
+
+    /// Sends `buf` to `raddr` as multiple datagrams of `segment_size` bytes.
+    ///
+    /// The default implementation sends by [`udp_socket_send_msg`](Self::udp_socket_send_msg)
+    /// with [`DatagramInfo::segment_size`].
+    fn udp_socket_send_gso(
+        &self,
+        waker: Waker,
+        handle: Handle,
+        buf: &[u8],
+        segment_size: u16,
+        raddr: SocketAddr,
+    ) -> io::Result<usize> {
+        self.udp_socket_send_msg(
+            waker,
+            handle,
+            buf,
+            raddr,
+            DatagramInfo {
+                segment_size: Some(segment_size),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Receives datagram with ancillary data.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_recv_msg(
+        &self,
+        _waker: Waker,
+        _handle: Handle,
+        _buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr, DatagramInfo)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "send/recv msg is not supported",
+        ))
+    }
+
+    /// Receives up to `slots.len()` datagrams in one wake-up.
+    ///
+    /// The default implementation waits for the first datagram by [`udp_socket_recv_from`](Self::udp_socket_recv_from),
+    /// then receives the ready datagrams by [`udp_socket_try_recv_from`](Self::udp_socket_try_recv_from)
+    /// until WOULD_BLOCK.
+    fn udp_socket_recv_batch(
+        &self,
+        waker: Waker,
+        handle: Handle,
+        slots: &mut [BufSlot],
+    ) -> io::Result<usize> {
+        let Some((first, rest)) = slots.split_first_mut() else {
+            return Ok(0);
+        };
+
+        let (len, raddr) = self.udp_socket_recv_from(waker, handle, &mut first.buf)?;
+
+        first.len = len;
+        first.raddr = Some(raddr);
+
+        let mut count = 1;
+
+        for slot in rest {
+            match self.udp_socket_try_recv_from(handle, &mut slot.buf) {
+                Ok((len, raddr)) => {
+                    slot.len = len;
+                    slot.raddr = Some(raddr);
+                    count += 1;
+                }
+                // The error is reported by the next call, after the received datagrams are consumed.
+                Err(_) => break,
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Sends the datagrams of `slots` in one wake-up.
+    ///
+    /// The default implementation sends the first datagram by [`udp_socket_sendto`](Self::udp_socket_sendto),
+    /// then sends the rest by [`udp_socket_try_sendto`](Self::udp_socket_try_sendto) until WOULD_BLOCK.
+    fn udp_socket_send_batch(
+        &self,
+        waker: Waker,
+        handle: Handle,
+        slots: &[BufSlot],
+    ) -> io::Result<usize> {
+        let Some((first, rest)) = slots.split_first() else {
+            return Ok(0);
+        };
+
+        self.udp_socket_sendto(waker, handle, first.data(), slot_raddr(first)?)?;
+
+        let mut count = 1;
+
+        // The error of rest slots is reported by the next call, after the sent slots are consumed.
+        for slot in rest {
+            let Some(raddr) = slot.raddr else {
+                break;
+            };
+
+            match self.udp_socket_try_sendto(handle, slot.data(), raddr) {
+                Ok(_) => count += 1,
+                Err(_) => break,
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Creates a new instance of the named pipe server `name`.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn named_pipe_create(&self, _name: &str) -> io::Result<Handle> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "named pipe is not supported",
+        ))
+    }
+
+    /// Opens the client end of the named pipe `name`.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn named_pipe_open(&self, _name: &str) -> io::Result<Handle> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "named pipe is not supported",
+        ))
+    }
+
+    /// Checks if a client has connected to the named pipe server instance, returns WOULD_BLOCK error
+    /// and registers the `waker` if not.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn named_pipe_poll_connect(&self, _waker: Waker, _handle: Handle) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "named pipe is not supported",
+        ))
+    }
+
+    /// Writes data to the named pipe.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn named_pipe_write(&self, _waker: Waker, _handle: Handle, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "named pipe is not supported",
+        ))
+    }
+
+    /// Reads data from the named pipe.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn named_pipe_read(
+        &self,
+        _waker: Waker,
+        _handle: Handle,
+        _buf: &mut [u8],
+    ) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "named pipe is not supported",
+        ))
+    }
+
+    /// Closes the named pipe handle.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn named_pipe_close(&self, _handle: Handle) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "named pipe is not supported",
+        ))
+    }
+
+    /// Opens the writing end of anonymous pipe from raw file descriptor `fd`, the ownership of
+    /// `fd` is transferred to this method, which closes `fd` on failure.
+    ///
+    /// The default implementation closes `fd` and returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    #[cfg(unix)]
+    fn pipe_sender_open(&self, fd: std::os::fd::RawFd) -> io::Result<Handle> {
+        use std::os::fd::{FromRawFd, OwnedFd};
+
+        // Safety: the ownership of `fd` is transferred by the caller.
+        drop(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "anonymous pipe is not supported",
+        ))
+    }
+
+    /// Opens the reading end of anonymous pipe from raw file descriptor `fd`, the ownership of
+    /// `fd` is transferred to this method, which closes `fd` on failure.
+    ///
+    /// The default implementation closes `fd` and returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    #[cfg(unix)]
+    fn pipe_receiver_open(&self, fd: std::os::fd::RawFd) -> io::Result<Handle> {
+        use std::os::fd::{FromRawFd, OwnedFd};
+
+        // Safety: the ownership of `fd` is transferred by the caller.
+        drop(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "anonymous pipe is not supported",
+        ))
+    }
+
+    /// Writes data to the writing end of anonymous pipe.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn pipe_write(&self, _waker: Waker, _handle: Handle, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "anonymous pipe is not supported",
+        ))
+    }
+
+    /// Reads data from the reading end of anonymous pipe.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn pipe_read(&self, _waker: Waker, _handle: Handle, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "anonymous pipe is not supported",
+        ))
+    }
+
+    /// Closes either end of anonymous pipe.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn pipe_close(&self, _handle: Handle) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "anonymous pipe is not supported",
+        ))
+    }
+
+    /// Opens a new listener of os signal `kind`.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn signal_open(&self, _kind: SignalKind) -> io::Result<Handle> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "signal handling is not supported",
+        ))
+    }
+
+    /// Takes the number of signals delivered since last poll, returns WOULD_BLOCK error
+    /// and registers the `waker` if there is none.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn signal_poll(&self, _waker: Waker, _handle: Handle) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "signal handling is not supported",
+        ))
+    }
+
+    /// Closes the signal listener.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn signal_close(&self, _handle: Handle) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "signal handling is not supported",
+        ))
+    }
+
+    /// Opens a new raw socket of `protocol`, which usually requires the privilege of
+    /// the process, e.g. `CAP_NET_RAW` on linux.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn raw_socket_open(&self, _protocol: RawProtocol) -> io::Result<Handle> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw socket is not supported",
+        ))
+    }
+
+    /// Send one packet to `raddr` peer by raw socket.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn raw_socket_sendto(
+        &self,
+        _waker: Waker,
+        _handle: Handle,
+        _buf: &[u8],
+        _raddr: SocketAddr,
+    ) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw socket is not supported",
+        ))
+    }
+
+    /// Recv one packet from peer by raw socket.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn raw_socket_recv_from(
+        &self,
+        _waker: Waker,
+        _handle: Handle,
+        _buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw socket is not supported",
+        ))
+    }
+
+    /// Closes the raw socket.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn raw_socket_close(&self, _handle: Handle) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw socket is not supported",
+        ))
+    }
+
+    /// Checks the `interest` readiness of socket `handle` without performing read/write,
+    /// returns WOULD_BLOCK error and registers the `waker` if the socket is not ready.
+    ///
+    /// The readiness of [`External`](Description::External) handle is unknown to the driver,
+    /// the `waker` is registered to be woken by the next readiness event.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn socket_poll_readiness(
+        &self,
+        _waker: Waker,
+        _handle: Handle,
+        _interest: Interest,
+    ) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "poll readiness is not supported",
+        ))
+    }
+
+    /// Takes the error / hangup / priority events in `interests` of socket `handle` reported since
+    /// last poll, returns WOULD_BLOCK error and registers the `waker` if none of them occurred.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn socket_poll_events(
+        &self,
+        _waker: Waker,
+        _handle: Handle,
+        _interests: Interest,
+    ) -> io::Result<Interest> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "poll events is not supported",
+        ))
+    }
+
+    /// Adopts the externally created tcp `listener` into the driver, the listener is
+    /// switched to non-blocking mode.
+    ///
+    /// The default implementation closes `listener` and returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn tcp_listener_from_std(&self, listener: std::net::TcpListener) -> io::Result<Handle> {
+        drop(listener);
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "adopting raw socket is not supported",
+        ))
+    }
+
+    /// Adopts the externally created tcp `stream` into the driver, the stream is
+    /// switched to non-blocking mode.
+    ///
+    /// The default implementation closes `stream` and returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn tcp_stream_from_std(&self, stream: std::net::TcpStream) -> io::Result<Handle> {
+        drop(stream);
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "adopting raw socket is not supported",
+        ))
+    }
+
+    /// Adopts the externally created udp `socket` into the driver, e.g. the socket received
+    /// from another process, the socket is switched to non-blocking mode.
+    ///
+    /// The default implementation closes `socket` and returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_from_std(&self, socket: std::net::UdpSocket) -> io::Result<Handle> {
+        drop(socket);
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "adopting raw socket is not supported",
+        ))
+    }
+
+    /// Sets the `buffer` size of the socket.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn socket_set_buffer_size(
+        &self,
+        _handle: Handle,
+        _buffer: SocketBuffer,
+        _size: usize,
+    ) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "socket buffer size is not supported",
+        ))
+    }
+
+    /// Returns the `buffer` size of the socket.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn socket_buffer_size(&self, _handle: Handle, _buffer: SocketBuffer) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "socket buffer size is not supported",
+        ))
+    }
+
+    /// Sets the ttl / hop limit of udp socket.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_set_ttl(&self, _handle: Handle, _ttl: u32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "udp socket ttl is not supported",
+        ))
+    }
+
+    /// Returns the ttl / hop limit of udp socket.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_ttl(&self, _handle: Handle) -> io::Result<u32> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "udp socket ttl is not supported",
+        ))
+    }
+
+    /// Sets or clears the DF bit of the datagrams sent by udp socket, see [`Cmd::SetDontFragment`](crate::Cmd::SetDontFragment).
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_set_dont_fragment(&self, _handle: Handle, _enabled: bool) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "udp socket DF bit is not supported",
+        ))
+    }
+
+    /// Returns true if the DF bit of the datagrams sent by udp socket is set.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn udp_socket_dont_fragment(&self, _handle: Handle) -> io::Result<bool> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "udp socket DF bit is not supported",
+        ))
+    }
+
+    /// Returns the raw os file descriptor of `handle`, the ownership is not transferred.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    #[cfg(unix)]
+    fn fd_as_raw_fd(&self, _handle: Handle) -> io::Result<std::os::fd::RawFd> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw fd is not supported",
+        ))
+    }
+
+    /// Returns the raw socket of `handle`, the ownership is not transferred.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    #[cfg(windows)]
+    fn fd_as_raw_socket(&self, _handle: Handle) -> io::Result<std::os::windows::io::RawSocket> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw socket is not supported",
+        ))
+    }
+
+    /// Returns the snapshot of driver metrics counters.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn driver_stats(&self) -> io::Result<DriverStats> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "driver metrics is not supported",
+        ))
+    }
+
+    /// Returns the optional features supported by the driver.
+    ///
+    /// The default implementation returns [`DriverCapabilities::default`], no optional feature is supported.
+    fn driver_capabilities(&self) -> io::Result<DriverCapabilities> {
+        Ok(DriverCapabilities::default())
+    }
+
+    /// Returns the open file handles with their creation backtraces.
+    ///
+    /// The default implementation returns [`Unsupported`](io::ErrorKind::Unsupported) error.
+    fn dump_handles(&self) -> io::Result<Vec<HandleInfo>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "handle tracking is not supported",
+        ))
+    }
+}
+
+/// Returns the destination address of sending `slot`.
+fn slot_raddr(slot: &BufSlot) -> io::Result<SocketAddr> {
+    slot.raddr.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the destination address of sending slot is not set",
+        )
+    })
 }
 
 /// Adapter `RawDriverExt` trait to `RawDriver` trait
@@ -129,6 +870,10 @@ impl<T: RawDriverExt + Clone> RawDriverExtProxy<T> {
 }
 
 impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(desc = ?desc))
+    )]
     fn fd_open(
         &self,
         desc: crate::Description,
@@ -140,21 +885,135 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
 
                 self.inner.file_open(path, mode)
             }
-            crate::Description::TcpListener => {
-                let laddrs = open_flags.try_into_bind()?;
+            crate::Description::TcpListener => match open_flags {
+                OpenFlags::BindWith(laddrs, options) => {
+                    self.inner.tcp_listener_bind_with(laddrs, options)
+                }
+                #[cfg(unix)]
+                OpenFlags::RawFd(fd) => {
+                    use std::os::fd::FromRawFd;
 
-                self.inner.tcp_listener_bind(laddrs)
-            }
-            crate::Description::TcpStream => {
-                let laddrs = open_flags.try_into_connect()?;
+                    // Safety: the ownership of `fd` is transferred by the caller.
+                    let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
 
-                self.inner.tcp_stream_connect(laddrs)
-            }
-            crate::Description::UdpSocket => {
-                let laddrs = open_flags.try_into_bind()?;
+                    self.inner.tcp_listener_from_std(listener)
+                }
+                #[cfg(windows)]
+                OpenFlags::RawSocket(socket) => {
+                    use std::os::windows::io::FromRawSocket;
+
+                    // Safety: the ownership of `socket` is transferred by the caller.
+                    let listener = unsafe { std::net::TcpListener::from_raw_socket(socket) };
+
+                    self.inner.tcp_listener_from_std(listener)
+                }
+                _ => {
+                    let laddrs = open_flags.try_into_bind()?;
+
+                    self.inner.tcp_listener_bind(laddrs)
+                }
+            },
+            crate::Description::TcpStream => match open_flags {
+                OpenFlags::NonblockingConnect(raddr) => {
+                    self.inner.tcp_stream_connect_nonblocking(raddr)
+                }
+                #[cfg(unix)]
+                OpenFlags::RawFd(fd) => {
+                    use std::os::fd::FromRawFd;
+
+                    // Safety: the ownership of `fd` is transferred by the caller.
+                    let stream = unsafe { std::net::TcpStream::from_raw_fd(fd) };
+
+                    self.inner.tcp_stream_from_std(stream)
+                }
+                #[cfg(windows)]
+                OpenFlags::RawSocket(socket) => {
+                    use std::os::windows::io::FromRawSocket;
+
+                    // Safety: the ownership of `socket` is transferred by the caller.
+                    let stream = unsafe { std::net::TcpStream::from_raw_socket(socket) };
+
+                    self.inner.tcp_stream_from_std(stream)
+                }
+                _ => {
+                    let raddrs = open_flags.try_into_connect()?;
+
+                    self.inner.tcp_stream_connect(raddrs)
+                }
+            },
+            crate::Description::UdpSocket => match open_flags {
+                OpenFlags::BindWith(laddrs, options) => {
+                    self.inner.udp_socket_bind_with(laddrs, options)
+                }
+                #[cfg(unix)]
+                OpenFlags::RawFd(fd) => {
+                    use std::os::fd::FromRawFd;
+
+                    // Safety: the ownership of `fd` is transferred by the caller.
+                    let socket = unsafe { std::net::UdpSocket::from_raw_fd(fd) };
+
+                    self.inner.udp_socket_from_std(socket)
+                }
+                #[cfg(windows)]
+                OpenFlags::RawSocket(socket) => {
+                    use std::os::windows::io::FromRawSocket;
+
+                    // Safety: the ownership of `socket` is transferred by the caller.
+                    let socket = unsafe { std::net::UdpSocket::from_raw_socket(socket) };
+
+                    self.inner.udp_socket_from_std(socket)
+                }
+                _ => {
+                    let laddrs = open_flags.try_into_bind()?;
 
-                self.inner.udp_socket_bind(laddrs)
+                    self.inner.udp_socket_bind(laddrs)
+                }
+            },
+            crate::Description::RawSocket => match open_flags {
+                OpenFlags::Protocol(protocol) => self.inner.raw_socket_open(protocol),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expect Protocol, but got {:?}", open_flags),
+                )),
+            },
+            crate::Description::NamedPipe => match open_flags {
+                OpenFlags::PipeServer(name) => self.inner.named_pipe_create(name),
+                OpenFlags::PipeClient(name) => self.inner.named_pipe_open(name),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expect PipeServer / PipeClient, but got {:?}", open_flags),
+                )),
+            },
+            #[cfg(unix)]
+            crate::Description::PipeSender => match open_flags {
+                OpenFlags::RawFd(fd) => self.inner.pipe_sender_open(fd),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expect RawFd, but got {:?}", open_flags),
+                )),
+            },
+            #[cfg(unix)]
+            crate::Description::PipeReceiver => match open_flags {
+                OpenFlags::RawFd(fd) => self.inner.pipe_receiver_open(fd),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expect RawFd, but got {:?}", open_flags),
+                )),
+            },
+            #[cfg(not(unix))]
+            crate::Description::PipeSender | crate::Description::PipeReceiver => {
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "anonymous pipe is not supported",
+                ))
             }
+            crate::Description::Signal => match open_flags {
+                OpenFlags::Signal(kind) => self.inner.signal_open(kind),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expect Signal, but got {:?}", open_flags),
+                )),
+            },
             crate::Description::Timeout => {
                 let duration = open_flags.try_into_duration()?;
 
@@ -176,6 +1035,10 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(token = handle.token.0, desc = ?handle.desc))
+    )]
     fn fd_cntl(&self, handle: Handle, cmd: crate::Cmd) -> io::Result<crate::CmdResp> {
         match cmd {
             crate::Cmd::Read { waker, buf } => match handle.desc {
@@ -187,11 +1050,26 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
                     .inner
                     .tcp_stream_read(waker, handle, buf)
                     .map(|len| CmdResp::DataLen(len)),
+                Description::UdpSocket => self
+                    .inner
+                    .udp_socket_recv(waker, handle, buf)
+                    .map(CmdResp::DataLen),
+                Description::NamedPipe => self
+                    .inner
+                    .named_pipe_read(waker, handle, buf)
+                    .map(CmdResp::DataLen),
+                Description::PipeReceiver => self
+                    .inner
+                    .pipe_read(waker, handle, buf)
+                    .map(CmdResp::DataLen),
 
                 _ => {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
-                        format!("Expect File / TcpStream , but got {:?}", handle.desc),
+                        format!(
+                            "Expect File / TcpStream / UdpSocket / NamedPipe / PipeReceiver, but got {:?}",
+                            handle.desc
+                        ),
                     ));
                 }
             },
@@ -204,39 +1082,170 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
                     .inner
                     .tcp_stream_write(waker, handle, buf)
                     .map(|len| CmdResp::DataLen(len)),
+                Description::UdpSocket => self
+                    .inner
+                    .udp_socket_send(waker, handle, buf)
+                    .map(CmdResp::DataLen),
+                Description::NamedPipe => self
+                    .inner
+                    .named_pipe_write(waker, handle, buf)
+                    .map(CmdResp::DataLen),
+                Description::PipeSender => self
+                    .inner
+                    .pipe_write(waker, handle, buf)
+                    .map(CmdResp::DataLen),
                 _ => {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
-                        format!("Expect File / TcpStream , but got {:?}", handle.desc),
+                        format!(
+                            "Expect File / TcpStream / UdpSocket / NamedPipe / PipeSender, but got {:?}",
+                            handle.desc
+                        ),
                     ));
                 }
             },
-            crate::Cmd::SendTo { waker, buf, raddr } => {
+            crate::Cmd::Connect(raddr) => {
                 handle.expect(Description::UdpSocket)?;
 
                 self.inner
+                    .udp_socket_connect(handle, raddr)
+                    .map(|_| CmdResp::None)
+            }
+            crate::Cmd::SendTo { waker, buf, raddr } => match handle.desc {
+                Description::UdpSocket => self
+                    .inner
                     .udp_socket_sendto(waker, handle, buf, raddr)
-                    .map(|len| CmdResp::DataLen(len))
+                    .map(|len| CmdResp::DataLen(len)),
+                Description::RawSocket => self
+                    .inner
+                    .raw_socket_sendto(waker, handle, buf, raddr)
+                    .map(CmdResp::DataLen),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expect UdpSocket / RawSocket, but got {:?}", handle.desc),
+                )),
+            },
+            crate::Cmd::RecvFrom { waker, buf } => match handle.desc {
+                Description::UdpSocket => self
+                    .inner
+                    .udp_socket_recv_from(waker, handle, buf)
+                    .map(|(len, raddr)| CmdResp::RecvFrom(len, raddr)),
+                Description::RawSocket => self
+                    .inner
+                    .raw_socket_recv_from(waker, handle, buf)
+                    .map(|(len, raddr)| CmdResp::RecvFrom(len, raddr)),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expect UdpSocket / RawSocket, but got {:?}", handle.desc),
+                )),
+            },
+            crate::Cmd::SendMsg {
+                waker,
+                buf,
+                raddr,
+                info,
+            } => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_send_msg(waker, handle, buf, raddr, info)
+                    .map(CmdResp::DataLen)
             }
-            crate::Cmd::RecvFrom { waker, buf } => {
+            crate::Cmd::SendToGso {
+                waker,
+                buf,
+                segment_size,
+                raddr,
+            } => {
                 handle.expect(Description::UdpSocket)?;
 
                 self.inner
-                    .udp_socket_recv_from(waker, handle, buf)
+                    .udp_socket_send_gso(waker, handle, buf, segment_size, raddr)
+                    .map(CmdResp::DataLen)
+            }
+            crate::Cmd::RecvMsg { waker, buf } => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_recv_msg(waker, handle, buf)
+                    .map(|(len, raddr, info)| CmdResp::RecvMsg(len, raddr, info))
+            }
+            crate::Cmd::RecvBatch { waker, slots } => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_recv_batch(waker, handle, slots)
+                    .map(CmdResp::DataLen)
+            }
+            crate::Cmd::SendBatch { waker, slots } => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_send_batch(waker, handle, slots)
+                    .map(CmdResp::DataLen)
+            }
+            crate::Cmd::TryRead(buf) => {
+                handle.expect(Description::TcpStream)?;
+
+                self.inner
+                    .tcp_stream_try_read(handle, buf)
+                    .map(CmdResp::DataLen)
+            }
+            crate::Cmd::WriteOwned { waker, buf } => {
+                handle.expect(Description::TcpStream)?;
+
+                self.inner
+                    .tcp_stream_write_owned(waker, handle, buf)
+                    .map(CmdResp::DataLen)
+            }
+            crate::Cmd::Flush(waker) => {
+                handle.expect(Description::TcpStream)?;
+
+                self.inner
+                    .tcp_stream_flush(waker, handle)
+                    .map(|_| CmdResp::None)
+            }
+            crate::Cmd::TryWrite(buf) => {
+                handle.expect(Description::TcpStream)?;
+
+                self.inner
+                    .tcp_stream_try_write(handle, buf)
+                    .map(CmdResp::DataLen)
+            }
+            crate::Cmd::TrySendTo { buf, raddr } => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_try_sendto(handle, buf, raddr)
+                    .map(CmdResp::DataLen)
+            }
+            crate::Cmd::TryRecvFrom(buf) => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_try_recv_from(handle, buf)
                     .map(|(len, raddr)| CmdResp::RecvFrom(len, raddr))
             }
-            crate::Cmd::Register { source, interests } => {
+            crate::Cmd::Register {
+                source,
+                interests,
+                mode,
+            } => {
                 handle.expect(Description::Poller)?;
 
                 self.inner
-                    .poller_register(handle, source, interests)
+                    .poller_register(handle, source, interests, mode)
                     .map(|_| CmdResp::None)
             }
-            crate::Cmd::ReRegister { source, interests } => {
+            crate::Cmd::ReRegister {
+                source,
+                interests,
+                mode,
+            } => {
                 handle.expect(Description::Poller)?;
 
                 self.inner
-                    .poller_reregister(handle, source, interests)
+                    .poller_reregister(handle, source, interests, mode)
                     .map(|_| CmdResp::None)
             }
             crate::Cmd::Deregister(source) => {
@@ -253,6 +1262,54 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
                     .tcp_listener_accept(waker, handle)
                     .map(|(stream, raddr)| CmdResp::Incoming(stream, raddr))
             }
+            crate::Cmd::PollReadiness { waker, interest } => match handle.desc {
+                Description::TcpStream
+                | Description::UdpSocket
+                | Description::RawSocket
+                | Description::External(_) => self
+                    .inner
+                    .socket_poll_readiness(waker, handle, interest)
+                    .map(|_| CmdResp::None),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Expect TcpStream / UdpSocket / RawSocket / External, but got {:?}",
+                        handle.desc
+                    ),
+                )),
+            },
+            crate::Cmd::PollEvents { waker, interests } => match handle.desc {
+                Description::TcpStream | Description::UdpSocket | Description::RawSocket => self
+                    .inner
+                    .socket_poll_events(waker, handle, interests)
+                    .map(CmdResp::Events),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Expect TcpStream / UdpSocket / RawSocket, but got {:?}",
+                        handle.desc
+                    ),
+                )),
+            },
+            crate::Cmd::PollConnect(waker) => match handle.desc {
+                Description::TcpStream => self
+                    .inner
+                    .tcp_stream_poll_connect(waker, handle)
+                    .map(|_| CmdResp::None),
+                Description::NamedPipe => self
+                    .inner
+                    .named_pipe_poll_connect(waker, handle)
+                    .map(|_| CmdResp::None),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expect TcpStream / NamedPipe, but got {:?}", handle.desc),
+                )),
+            },
+            crate::Cmd::PollSignal(waker) => {
+                handle.expect(Description::Signal)?;
+
+                self.inner.signal_poll(waker, handle).map(CmdResp::Signal)
+            }
             crate::Cmd::PollOnce(duration) => {
                 handle.expect(Description::Poller)?;
 
@@ -260,6 +1317,12 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
                     .poller_poll_once(handle, duration)
                     .map(|_| CmdResp::None)
             }
+            crate::Cmd::WakePoller => self.inner.poller_wake(handle).map(|_| CmdResp::None),
+            crate::Cmd::Now => {
+                handle.expect(Description::Poller)?;
+
+                self.inner.poller_now(handle).map(CmdResp::Instant)
+            }
             crate::Cmd::TryClone => match handle.desc {
                 Description::Poller => self
                     .inner
@@ -277,6 +1340,13 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
                     .timeout(waker, handle)
                     .map(|next| CmdResp::Timeout(next))
             }
+            crate::Cmd::ResetTimeout(duration) => {
+                handle.expect(Description::Timeout)?;
+
+                self.inner
+                    .timeout_reset(handle, duration)
+                    .map(|_| CmdResp::None)
+            }
             crate::Cmd::LocalAddr => match handle.desc {
                 Description::TcpListener => self
                     .inner
@@ -302,6 +1372,7 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
                     .inner
                     .tcp_stream_remote_addr(handle)
                     .map(|laddr| CmdResp::SockAddr(laddr)),
+                Description::UdpSocket => self.inner.udp_remote_addr(handle).map(CmdResp::SockAddr),
                 _ => {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
@@ -321,16 +1392,114 @@ impl<T: RawDriverExt + Clone> RawDriver for RawDriverExtProxy<T> {
                     ));
                 }
             },
+            crate::Cmd::SetBufferSize(buffer, size) => match handle.desc {
+                Description::TcpListener | Description::TcpStream | Description::UdpSocket => self
+                    .inner
+                    .socket_set_buffer_size(handle, buffer, size)
+                    .map(|_| CmdResp::None),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Expect TcpListener / TcpStream / UdpSocket, but got {:?}",
+                        handle.desc
+                    ),
+                )),
+            },
+            crate::Cmd::BufferSize(buffer) => match handle.desc {
+                Description::TcpListener | Description::TcpStream | Description::UdpSocket => self
+                    .inner
+                    .socket_buffer_size(handle, buffer)
+                    .map(CmdResp::DataLen),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Expect TcpListener / TcpStream / UdpSocket, but got {:?}",
+                        handle.desc
+                    ),
+                )),
+            },
+            crate::Cmd::SetTtl(ttl) => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_set_ttl(handle, ttl)
+                    .map(|_| CmdResp::None)
+            }
+            crate::Cmd::Ttl => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_ttl(handle)
+                    .map(|ttl| CmdResp::DataLen(ttl as usize))
+            }
+            crate::Cmd::SetDontFragment(enabled) => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_set_dont_fragment(handle, enabled)
+                    .map(|_| CmdResp::None)
+            }
+            crate::Cmd::DontFragment => {
+                handle.expect(Description::UdpSocket)?;
+
+                self.inner
+                    .udp_socket_dont_fragment(handle)
+                    .map(CmdResp::Bool)
+            }
+            crate::Cmd::Stats => self.inner.driver_stats().map(CmdResp::Stats),
+            crate::Cmd::Capabilities => self.inner.driver_capabilities().map(CmdResp::Capabilities),
+            crate::Cmd::DumpHandles => self.inner.dump_handles().map(CmdResp::Handles),
+            #[cfg(unix)]
+            crate::Cmd::AsRawFd => self.inner.fd_as_raw_fd(handle).map(CmdResp::RawFd),
+            #[cfg(unix)]
+            crate::Cmd::DupRawFd => {
+                use std::os::fd::{BorrowedFd, IntoRawFd};
+
+                let fd = self.inner.fd_as_raw_fd(handle)?;
+
+                // Safety: `fd` stays open while the `handle` is open.
+                let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+
+                Ok(CmdResp::RawFd(fd.try_clone_to_owned()?.into_raw_fd()))
+            }
+            #[cfg(windows)]
+            crate::Cmd::AsRawFd => self.inner.fd_as_raw_socket(handle).map(CmdResp::RawSocket),
+            #[cfg(windows)]
+            crate::Cmd::DupRawFd => {
+                use std::os::windows::io::{BorrowedSocket, IntoRawSocket};
+
+                let socket = self.inner.fd_as_raw_socket(handle)?;
+
+                // Safety: `socket` stays open while the `handle` is open.
+                let socket = unsafe { BorrowedSocket::borrow_raw(socket) };
+
+                Ok(CmdResp::RawSocket(
+                    socket.try_clone_to_owned()?.into_raw_socket(),
+                ))
+            }
+            #[cfg(not(any(unix, windows)))]
+            crate::Cmd::AsRawFd | crate::Cmd::DupRawFd => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "raw fd is not supported",
+            )),
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(token = handle.token.0, desc = ?handle.desc))
+    )]
     fn fd_close(&self, handle: Handle) -> io::Result<()> {
         match handle.desc {
             Description::File => self.inner.file_close(handle),
             Description::TcpListener => self.inner.tcp_listener_close(handle),
             Description::TcpStream => self.inner.tcp_stream_close(handle),
             Description::UdpSocket => self.inner.udp_socket_close(handle),
+            Description::RawSocket => self.inner.raw_socket_close(handle),
+            Description::NamedPipe => self.inner.named_pipe_close(handle),
+            Description::PipeSender | Description::PipeReceiver => self.inner.pipe_close(handle),
             Description::Timeout => self.inner.timeout_close(handle),
+            Description::Signal => self.inner.signal_close(handle),
             Description::Poller => self.inner.poller_close(handle),
             Description::External(id) => self.inner.fd_user_define_close(id, handle),
         }
@@ -0,0 +1,113 @@
+use std::{io, time::Duration};
+
+/// The error of accepting incoming connection when the fd limit is reached, either the
+/// [`fd_budget`](crate::DriverBuilder::fd_budget) of driver or the `RLIMIT_NOFILE` of process.
+///
+/// The pending connection is shed (accepted and closed immediately) instead of being left in
+/// the backlog, so the accept loop doesn't spin on the same error. It's surfaced as
+/// [`Other`](io::ErrorKind::Other) kind io error, use [`Emfile::from_io_error`] to extract it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("too many open files, backoff={backoff:?}")]
+pub struct Emfile {
+    /// The duration the accept loop should wait before the next accept, `None` if no backoff is configured.
+    pub backoff: Option<Duration>,
+}
+
+impl Emfile {
+    /// Returns the [`Emfile`] error carried by `err`, or `None` if it's another error.
+    pub fn from_io_error(err: &io::Error) -> Option<&Emfile> {
+        err.get_ref()?.downcast_ref::<Emfile>()
+    }
+}
+
+impl From<Emfile> for io::Error {
+    fn from(value: Emfile) -> Self {
+        io::Error::other(value)
+    }
+}
+
+#[cfg(all(test, feature = "mio-driver", unix))]
+mod tests {
+    use std::{
+        io::Read,
+        net::{SocketAddr, TcpStream},
+    };
+
+    use futures::task::noop_waker;
+
+    use crate::{Backend, Cmd, Description, DriverBuilder, OpenFlags};
+
+    use super::*;
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_fd_budget() {
+        // The poller and the listener take two of the budget.
+        let driver = DriverBuilder::new()
+            .backend(Backend::native().unwrap())
+            .fd_budget(3)
+            .reserve_fd(true)
+            .emfile_backoff(Duration::from_millis(100))
+            .build()
+            .unwrap();
+
+        let poller = driver
+            .fd_open(Description::Poller, OpenFlags::None)
+            .unwrap();
+
+        let laddrs = ["127.0.0.1:0".parse::<SocketAddr>().unwrap()];
+
+        let listener = driver
+            .fd_open(Description::TcpListener, OpenFlags::Bind(&laddrs))
+            .unwrap();
+
+        driver
+            .fd_cntl(
+                poller,
+                Cmd::Register {
+                    source: listener,
+                    interests: crate::Interest::Readable,
+                    mode: crate::PollMode::Edge,
+                },
+            )
+            .unwrap();
+
+        let laddr = driver
+            .fd_cntl(listener, Cmd::LocalAddr)
+            .unwrap()
+            .try_into_sockaddr()
+            .unwrap();
+
+        let _accepted_client = TcpStream::connect(laddr).unwrap();
+        let mut shed_client = TcpStream::connect(laddr).unwrap();
+
+        let (stream, _) = driver
+            .fd_cntl(listener, Cmd::Accept(noop_waker()))
+            .unwrap()
+            .try_into_incoming()
+            .unwrap();
+
+        let err = driver
+            .fd_cntl(listener, Cmd::Accept(noop_waker()))
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+
+        assert_eq!(
+            Emfile::from_io_error(&err),
+            Some(&Emfile {
+                backoff: Some(Duration::from_millis(100))
+            })
+        );
+
+        // The connection beyond the budget is closed by driver.
+        let mut buf = [0; 1];
+
+        assert_eq!(shed_client.read(&mut buf).unwrap(), 0);
+
+        driver.fd_close(stream).unwrap();
+        driver.fd_cntl(poller, Cmd::Deregister(listener)).unwrap();
+        driver.fd_close(listener).unwrap();
+        driver.fd_close(poller).unwrap();
+    }
+}
@@ -20,7 +20,7 @@ pub trait TokenGenerator {
 impl TokenGenerator for Token {}
 
 /// File description variants are used by the `fd_open` function to open file [`Handle`].
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Description {
     /// File description for generating filesystem `File`
     File,
@@ -30,8 +30,18 @@ pub enum Description {
     TcpStream,
     /// File description for generating `UdpSocket`
     UdpSocket,
+    /// File description for generating raw socket, e.g. the icmp socket.
+    RawSocket,
+    /// File description for generating windows named pipe, either the server or the client end.
+    NamedPipe,
+    /// File description for the writing end of anonymous pipe, e.g. the child process stdin.
+    PipeSender,
+    /// File description for the reading end of anonymous pipe, e.g. the child process stdout / stderr.
+    PipeReceiver,
     /// File description for timeout event
     Timeout,
+    /// File description for os signal notifications.
+    Signal,
     /// poller for io readiness events.
     Poller,
     /// Extended file description type defined by the implementation.
@@ -0,0 +1,81 @@
+use std::backtrace::Backtrace;
+
+use dashmap::DashMap;
+
+use crate::{Description, Handle, Token};
+
+/// The open file handle record, returns by [`Cmd::DumpHandles`](crate::Cmd::DumpHandles) command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandleInfo {
+    /// The token of the open handle.
+    pub token: Token,
+    /// The file description type of the open handle.
+    pub desc: Description,
+    /// The backtrace captured when the handle was opened.
+    pub backtrace: String,
+}
+
+/// Open file handles tracker, which is used to find leaked handles.
+///
+/// Capturing backtrace is expensive, driver implementations should only
+/// enable it behind the `track-handles` feature.
+#[derive(Debug, Default)]
+pub struct HandleTracker {
+    handles: DashMap<Token, HandleInfo>,
+}
+
+impl HandleTracker {
+    /// Records the new opened `handle` with the backtrace of the caller.
+    pub fn on_open(&self, handle: &Handle) {
+        self.handles.insert(
+            handle.token,
+            HandleInfo {
+                token: handle.token,
+                desc: handle.desc,
+                backtrace: Backtrace::force_capture().to_string(),
+            },
+        );
+    }
+
+    /// Removes the closed `handle` record.
+    pub fn on_close(&self, handle: &Handle) {
+        self.handles.remove(&handle.token);
+    }
+
+    /// Returns the open handle records, ordered by token.
+    pub fn dump(&self) -> Vec<HandleInfo> {
+        let mut handles = self
+            .handles
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect::<Vec<_>>();
+
+        handles.sort_by_key(|info| info.token);
+
+        handles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker() {
+        let tracker = HandleTracker::default();
+
+        let stream = Handle::new(Description::TcpStream, None, None, std::ptr::null());
+        let timeout = Handle::new(Description::Timeout, None, None, std::ptr::null());
+
+        tracker.on_open(&stream);
+        tracker.on_open(&timeout);
+        tracker.on_close(&stream);
+
+        let handles = tracker.dump();
+
+        assert_eq!(handles.len(), 1);
+        assert_eq!(handles[0].token, timeout.token);
+        assert_eq!(handles[0].desc, Description::Timeout);
+        assert!(handles[0].backtrace.contains("test_tracker"));
+    }
+}
@@ -1,8 +1,33 @@
 use bitmask_enum::bitmask;
 
 /// Interest io event variant used in poll registering.
+///
+/// [`Error`](Interest::Error), [`Hup`](Interest::Hup) and [`Priority`](Interest::Priority) are the
+/// events reported by the poller, which are waited by [`PollEvents`](crate::Cmd::PollEvents).
+/// The error / hangup events are always reported, the priority event is only reported if the source
+/// is registered with `Priority` interest.
 #[bitmask]
 pub enum Interest {
     Writable,
     Readable,
+    /// The error condition of the source, e.g. the pending socket error.
+    Error,
+    /// The peer hung up, e.g. the peer shut down the write direction of the tcp stream.
+    Hup,
+    /// The priority data is available, e.g. the tcp out-of-band data.
+    Priority,
+}
+
+/// Readiness trigger mode used in poll registering.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PollMode {
+    /// Readiness events arrived while no task is waiting are retained,
+    /// and delivered to the next waiting task.
+    Level,
+    /// Readiness events are only delivered to the waiting tasks at the time of arrival.
+    #[default]
+    Edge,
+    /// Only the first readiness event is delivered,
+    /// the source must be re-armed by [`ReRegister`](crate::Cmd::ReRegister) command.
+    OneShot,
 }
@@ -0,0 +1,242 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::{future::poll_fn, Stream};
+
+use crate::current::{get_driver, get_poller};
+
+use super::{
+    now_with, Cmd, Description, Driver, Handle, Interest, OpenFlags, OwnedHandle, PollMode,
+};
+
+/// Defines the behavior of [`Interval`] when it misses ticks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    /// Ticks as fast as possible until caught up.
+    #[default]
+    Burst,
+    /// Schedules the next tick `period` from now.
+    Delay,
+    /// Skips the missed ticks and ticks on the next multiple of `period`.
+    Skip,
+}
+
+/// Stream that yields ticks at a fixed period, created by [`interval`] or [`interval_at`].
+///
+/// The same timeout handle is reused for all ticks, and restarted by re-registering it with the poller.
+pub struct Interval {
+    fd: Option<OwnedHandle>,
+    driver: Driver,
+    poller: Handle,
+    /// The deadline of next tick.
+    next: Instant,
+    period: Duration,
+    /// The timer is armed for the `next` tick.
+    armed: bool,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+impl Interval {
+    /// Create a [`Interval`] instance that first tick completes at `start`.
+    pub fn new_with(driver: Driver, poller: Handle, start: Instant, period: Duration) -> Self {
+        assert!(!period.is_zero(), "`period` must be non-zero.");
+
+        Self {
+            fd: None,
+            driver,
+            poller,
+            next: start,
+            period,
+            armed: false,
+            missed_tick_behavior: Default::default(),
+        }
+    }
+
+    /// Returns the period of this interval.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the [`MissedTickBehavior`] of this interval.
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// Set the [`MissedTickBehavior`] of this interval.
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    /// Completes when the next tick is reached, and returns the scheduled deadline of the tick.
+    pub async fn tick(&mut self) -> io::Result<Instant> {
+        poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Polls for the next tick.
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Instant>> {
+        let now = match now_with(&self.driver, self.poller) {
+            Ok(now) => now,
+            Err(err) => return Poll::Ready(Err(err)),
+        };
+
+        if now < self.next {
+            if !self.armed {
+                if let Err(err) = self.arm(self.next - now) {
+                    return Poll::Ready(Err(err));
+                }
+            }
+
+            let fd = self.fd.as_ref().expect("Call arm first");
+
+            match fd
+                .fd_cntl(Cmd::Timeout(cx.waker().clone()))
+                .and_then(|resp| resp.try_into_timeout())
+            {
+                Ok(true) => {}
+                Ok(false) => return Poll::Pending,
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+
+        let tick = self.next;
+
+        self.armed = false;
+
+        self.next = match self.missed_tick_behavior {
+            MissedTickBehavior::Burst => tick + self.period,
+            MissedTickBehavior::Delay => now + self.period,
+            MissedTickBehavior::Skip => {
+                let missed =
+                    now.saturating_duration_since(tick).as_nanos() / self.period.as_nanos();
+
+                tick + self.period * (missed as u32 + 1)
+            }
+        };
+
+        Poll::Ready(Ok(tick))
+    }
+
+    /// Arms the timer with `duration`, the timeout handle is created on first call,
+    /// and restarted by re-registering with the poller on subsequent calls.
+    fn arm(&mut self, duration: Duration) -> io::Result<()> {
+        match &self.fd {
+            None => {
+                let fd = self.fd.insert(OwnedHandle::open(
+                    self.driver.clone(),
+                    Description::Timeout,
+                    OpenFlags::Duration(duration),
+                )?);
+
+                fd.register(self.poller, Interest::Readable, PollMode::Edge)?;
+            }
+            Some(fd) => {
+                fd.fd_cntl(Cmd::ResetTimeout(duration))?;
+
+                self.driver.fd_cntl(
+                    self.poller,
+                    Cmd::ReRegister {
+                        source: *fd.as_handle(),
+                        interests: Interest::Readable,
+                        mode: PollMode::Edge,
+                    },
+                )?;
+            }
+        }
+
+        self.armed = true;
+
+        Ok(())
+    }
+}
+
+impl Stream for Interval {
+    type Item = io::Result<Instant>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_tick(cx).map(Some)
+    }
+}
+
+impl Drop for Interval {
+    fn drop(&mut self) {
+        if let Some(fd) = self.fd.take() {
+            if let Err(err) = fd.deregister(self.poller) {
+                log::error!("deregister interval timer failed, fd={:?}, err={}", fd, err);
+            }
+        }
+    }
+}
+
+/// Create new [`Interval`] that yields ticks with `period`, the first tick completes immediately.
+pub fn interval(period: Duration) -> io::Result<Interval> {
+    interval_at(crate::now()?, period)
+}
+
+/// Create new [`Interval`] that yields ticks with `period`, the first tick completes at `start`.
+pub fn interval_at(start: Instant, period: Duration) -> io::Result<Interval> {
+    Ok(Interval::new_with(
+        get_driver()?,
+        get_poller()?,
+        start,
+        period,
+    ))
+}
+
+#[cfg(all(test, feature = "mio-driver"))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use futures::StreamExt;
+
+    use crate::{sleep, test::io_test};
+
+    use super::*;
+
+    #[hala_test::test(io_test)]
+    async fn test_interval() {
+        let start = Instant::now();
+
+        let mut interval = interval(Duration::from_millis(20)).unwrap();
+
+        let first = interval.tick().await.unwrap();
+
+        assert!(first <= Instant::now());
+
+        for i in 1..5u32 {
+            let tick = interval.next().await.unwrap().unwrap();
+
+            assert_eq!(tick, first + Duration::from_millis(20) * i);
+            assert!(Instant::now() + Duration::from_millis(10) >= tick);
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(70));
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_missed_tick_skip() {
+        let mut interval = interval(Duration::from_millis(20)).unwrap();
+
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let first = interval.tick().await.unwrap();
+
+        sleep(Duration::from_millis(50)).await.unwrap();
+
+        let now = Instant::now();
+
+        // the late tick completes immediately.
+        let tick = interval.tick().await.unwrap();
+
+        assert_eq!(tick, first + Duration::from_millis(20));
+
+        // the missed ticks are skipped.
+        let tick = interval.tick().await.unwrap();
+
+        assert!(tick > now);
+        assert_eq!((tick - first).as_millis() % 20, 0);
+    }
+}
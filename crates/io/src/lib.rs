@@ -1,3 +1,7 @@
+// The runners selected by `#[hala_test::test(runtime = ..)]` are referred by `::hala_io::test`.
+#[cfg(test)]
+extern crate self as hala_io;
+
 mod interest;
 pub use interest::*;
 
@@ -7,6 +11,9 @@ pub use driver::*;
 mod file;
 pub use file::*;
 
+mod owned;
+pub use owned::*;
+
 mod driver_ext;
 pub use driver_ext::*;
 
@@ -16,12 +23,63 @@ pub use read_buf::*;
 mod wouldblock;
 pub use wouldblock::*;
 
+mod clock;
+pub use clock::*;
+
 mod sleep;
 pub use sleep::*;
 
+mod interval;
+pub use interval::*;
+
 mod timeout;
 pub use timeout::*;
 
+mod cancel;
+pub use cancel::*;
+
+mod deadline;
+pub use deadline::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod handles;
+pub use handles::*;
+
+mod emfile;
+pub use emfile::*;
+
+mod copy;
+pub use copy::*;
+
+mod tracked;
+pub use tracked::*;
+
+mod buffered;
+pub use buffered::*;
+
+mod builder;
+pub use builder::*;
+
+mod rate_limit;
+pub use rate_limit::*;
+
+mod signal;
+pub use signal::*;
+
+mod poller_waker;
+pub use poller_waker::*;
+
+mod blocking;
+pub use blocking::*;
+
+pub mod stdio;
+
+pub mod body;
+
+pub mod coop;
+
 #[cfg(feature = "current")]
 pub mod current;
 
@@ -30,5 +88,8 @@ pub use bytes;
 #[cfg(feature = "mio-driver")]
 pub mod mio;
 
+#[cfg(feature = "mock-driver")]
+pub mod mock;
+
 #[cfg(all(feature = "mio-driver", feature = "current"))]
 pub mod test;
@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use dashmap::DashMap;
+
+use crate::Description;
+
+/// Snapshot of driver counters, returns by [`Cmd::Stats`](crate::Cmd::Stats) command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriverStats {
+    /// The number of opened file handles per [`Description`].
+    pub open_fds: HashMap<Description, usize>,
+    /// The number of `poll_once` invocations.
+    pub poll_once: u64,
+    /// The number of io readiness / timeout events dispatched to wakers.
+    pub events_dispatched: u64,
+    /// The number of timers registered with pollers.
+    pub timer_registrations: u64,
+}
+
+impl DriverStats {
+    /// Returns the number of opened file handles of `desc` type.
+    pub fn open_fds_of(&self, desc: Description) -> usize {
+        self.open_fds.get(&desc).cloned().unwrap_or_default()
+    }
+}
+
+/// Driver observability hooks, driver implementations invoke these functions
+/// when the corresponding things happen.
+///
+/// Implement this trait to export driver metrics to prometheus-style collectors.
+pub trait DriverMetrics: Send + Sync {
+    /// A new file handle of `desc` type is opened.
+    fn on_fd_open(&self, _desc: Description) {}
+
+    /// A file handle of `desc` type is closed.
+    fn on_fd_close(&self, _desc: Description) {}
+
+    /// The poller's `poll_once` function is invoked.
+    fn on_poll_once(&self) {}
+
+    /// `events` io readiness / timeout events are dispatched to wakers.
+    fn on_events_dispatched(&self, _events: usize) {}
+
+    /// A new timer is registered with poller.
+    fn on_timer_register(&self) {}
+}
+
+/// The default [`DriverMetrics`] implementation backed by atomic counters,
+/// which also forwards the hooks to an optional subscriber.
+#[derive(Default)]
+pub struct DriverCounters {
+    open_fds: DashMap<Description, usize>,
+    poll_once: AtomicU64,
+    events_dispatched: AtomicU64,
+    timer_registrations: AtomicU64,
+    subscriber: Option<Arc<dyn DriverMetrics>>,
+}
+
+impl Debug for DriverCounters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DriverCounters({:?})", self.stats())
+    }
+}
+
+impl DriverCounters {
+    /// Create new counters with metrics `subscriber`.
+    pub fn with_subscriber<S: DriverMetrics + 'static>(subscriber: S) -> Self {
+        Self {
+            subscriber: Some(Arc::new(subscriber)),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the snapshot of counters.
+    pub fn stats(&self) -> DriverStats {
+        DriverStats {
+            open_fds: self
+                .open_fds
+                .iter()
+                .map(|entry| (*entry.key(), *entry.value()))
+                .collect(),
+            poll_once: self.poll_once.load(Ordering::Relaxed),
+            events_dispatched: self.events_dispatched.load(Ordering::Relaxed),
+            timer_registrations: self.timer_registrations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl DriverMetrics for DriverCounters {
+    fn on_fd_open(&self, desc: Description) {
+        *self.open_fds.entry(desc).or_default() += 1;
+
+        if let Some(subscriber) = &self.subscriber {
+            subscriber.on_fd_open(desc);
+        }
+    }
+
+    fn on_fd_close(&self, desc: Description) {
+        if let Some(mut count) = self.open_fds.get_mut(&desc) {
+            *count = count.saturating_sub(1);
+        }
+
+        if let Some(subscriber) = &self.subscriber {
+            subscriber.on_fd_close(desc);
+        }
+    }
+
+    fn on_poll_once(&self) {
+        self.poll_once.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(subscriber) = &self.subscriber {
+            subscriber.on_poll_once();
+        }
+    }
+
+    fn on_events_dispatched(&self, events: usize) {
+        self.events_dispatched
+            .fetch_add(events as u64, Ordering::Relaxed);
+
+        if let Some(subscriber) = &self.subscriber {
+            subscriber.on_events_dispatched(events);
+        }
+    }
+
+    fn on_timer_register(&self) {
+        self.timer_registrations.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(subscriber) = &self.subscriber {
+            subscriber.on_timer_register();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    #[derive(Default, Clone)]
+    struct MockSubscriber(Arc<AtomicUsize>);
+
+    impl DriverMetrics for MockSubscriber {
+        fn on_fd_open(&self, _desc: Description) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_counters() {
+        let subscriber = MockSubscriber::default();
+
+        let counters = DriverCounters::with_subscriber(subscriber.clone());
+
+        counters.on_fd_open(Description::TcpStream);
+        counters.on_fd_open(Description::TcpStream);
+        counters.on_fd_open(Description::Poller);
+        counters.on_fd_close(Description::TcpStream);
+        counters.on_poll_once();
+        counters.on_events_dispatched(3);
+
+        let stats = counters.stats();
+
+        assert_eq!(stats.open_fds_of(Description::TcpStream), 1);
+        assert_eq!(stats.open_fds_of(Description::Poller), 1);
+        assert_eq!(stats.open_fds_of(Description::UdpSocket), 0);
+        assert_eq!(stats.poll_once, 1);
+        assert_eq!(stats.events_dispatched, 3);
+        assert_eq!(stats.timer_registrations, 0);
+
+        assert_eq!(subscriber.0.load(Ordering::Relaxed), 3);
+    }
+}
@@ -1,20 +1,364 @@
 use std::{
     io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     task::Waker,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    mio::{timer::MioTimer, with_poller::MioWithPoller},
-    Description, Driver, Handle, Interest, IntoRawDriver, RawDriverExt, Token, TypedHandle,
+    mio::{
+        external::ExternalSource,
+        timer::MioTimer,
+        with_poller::MioWithPoller,
+        write_queue::{self, WRITE_QUEUE_LIMIT},
+    },
+    Backend, BindOptions, Clock, Description, Driver, DriverCapabilities, DriverCounters,
+    DriverMetrics, DriverStats, Emfile, FdLimits, Handle, Interest, IntoRawDriver, RawDriverExt,
+    RawProtocol, SocketBuffer, SystemClock, Token, TypedHandle,
 };
 
 use super::poller::MioPoller;
 
-#[derive(Debug, Default, Clone)]
-struct MioDriver {}
+/// The default backlog of listening tcp socket.
+const DEFAULT_BACKLOG: i32 = 128;
+
+/// Create a new socket of `ty` with `options` and bind to the first available address in `laddrs`.
+fn bind_socket(
+    laddrs: &[std::net::SocketAddr],
+    options: BindOptions,
+    ty: socket2::Type,
+) -> io::Result<socket2::Socket> {
+    let mut last_error = None;
+
+    for laddr in laddrs {
+        let socket = socket2::Socket::new(socket2::Domain::for_address(*laddr), ty, None)?;
+
+        socket.set_reuse_address(options.reuse_address)?;
+
+        if options.reuse_port {
+            #[cfg(unix)]
+            socket.set_reuse_port(true)?;
+
+            #[cfg(not(unix))]
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "SO_REUSEPORT is not supported",
+            ));
+        }
+
+        if laddr.is_ipv6() {
+            if let Some(only_v6) = options.only_v6 {
+                socket.set_only_v6(only_v6)?;
+            }
+
+            if let Some(ttl) = options.ttl {
+                socket.set_unicast_hops_v6(ttl)?;
+            }
+        } else if let Some(ttl) = options.ttl {
+            socket.set_ttl(ttl)?;
+        }
+
+        if let Some(device) = options.bind_device {
+            #[cfg(target_os = "linux")]
+            socket.bind_device(Some(device.as_str().as_bytes()))?;
+
+            #[cfg(not(target_os = "linux"))]
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("SO_BINDTODEVICE is not supported, device={:?}", device),
+            ));
+        }
+
+        if let Some(size) = options.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+
+        if let Some(size) = options.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+
+        match socket.bind(&(*laddr).into()) {
+            Ok(_) => return Ok(socket),
+            Err(err) => last_error = Some(err),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any addresses",
+        )
+    }))
+}
+
+/// Sets or clears the DF bit of the datagrams sent by `socket`, see [`Cmd::SetDontFragment`](crate::Cmd::SetDontFragment).
+#[allow(unused_variables)]
+fn set_dont_fragment(socket: &socket2::Socket, ipv6: bool, enabled: bool) -> io::Result<()> {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd"
+    ))]
+    return super::sockopt::set_dont_fragment(socket, ipv6, enabled);
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd"
+    )))]
+    return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "DF bit is not supported",
+    ));
+}
+
+/// Returns true if the DF bit of the datagrams sent by `socket` is set.
+#[allow(unused_variables)]
+fn dont_fragment(socket: &socket2::Socket, ipv6: bool) -> io::Result<bool> {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd"
+    ))]
+    return super::sockopt::dont_fragment(socket, ipv6);
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd"
+    )))]
+    return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "DF bit is not supported",
+    ));
+}
+
+/// Returns true if the socket is bound to an ipv6 address.
+fn is_ipv6(socket: &socket2::Socket) -> io::Result<bool> {
+    Ok(socket.local_addr()?.is_ipv6())
+}
+
+/// Calls `f` with the [`SockRef`](socket2::SockRef) of the tcp / udp socket `handle`.
+fn with_sock_ref<R>(
+    handle: Handle,
+    f: impl FnOnce(socket2::SockRef<'_>) -> io::Result<R>,
+) -> io::Result<R> {
+    match handle.desc {
+        Description::TcpListener => {
+            TypedHandle::<MioWithPoller<mio::net::TcpListener>>::new(handle)
+                .with(|socket| borrow_socket(&**socket, f))
+        }
+        Description::TcpStream => TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle)
+            .with(|socket| borrow_socket(&**socket, f)),
+        Description::UdpSocket => TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
+            .with(|socket| borrow_socket(&**socket, f)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Expect TcpListener / TcpStream / UdpSocket, but got {:?}",
+                handle.desc
+            ),
+        )),
+    }
+}
+
+#[cfg(unix)]
+fn borrow_socket<S: std::os::fd::AsRawFd, R>(
+    socket: &S,
+    f: impl FnOnce(socket2::SockRef<'_>) -> io::Result<R>,
+) -> io::Result<R> {
+    // Safety: the fd stays open while `socket` is borrowed.
+    let fd = unsafe { std::os::fd::BorrowedFd::borrow_raw(socket.as_raw_fd()) };
+
+    f(socket2::SockRef::from(&fd))
+}
+
+#[cfg(windows)]
+fn borrow_socket<S: std::os::windows::io::AsRawSocket, R>(
+    socket: &S,
+    f: impl FnOnce(socket2::SockRef<'_>) -> io::Result<R>,
+) -> io::Result<R> {
+    // Safety: the socket stays open while `socket` is borrowed.
+    let raw = unsafe { std::os::windows::io::BorrowedSocket::borrow_raw(socket.as_raw_socket()) };
+
+    f(socket2::SockRef::from(&raw))
+}
+
+/// Checks the `interest` readiness of `source` by `poll(2)` with zero timeout.
+///
+/// The error conditions are reported as ready, so the next read/write returns the error.
+#[cfg(unix)]
+fn poll_readiness<S: std::os::fd::AsRawFd>(source: &S, interest: Interest) -> io::Result<()> {
+    let events = if interest.contains(Interest::Readable) {
+        libc::POLLIN
+    } else {
+        libc::POLLOUT
+    };
+
+    let mut fd = libc::pollfd {
+        fd: source.as_raw_fd(),
+        events,
+        revents: 0,
+    };
+
+    // Safety: `fd` is a valid `pollfd` structure during the call.
+    match unsafe { libc::poll(&mut fd, 1, 0) } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "socket is not ready",
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn poll_readiness<S>(_source: &S, _interest: Interest) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "poll readiness is only supported on unix",
+    ))
+}
+
+/// Calls `f` once, retries if the call is interrupted.
+fn try_call<R, F>(mut f: F) -> io::Result<R>
+where
+    F: FnMut() -> io::Result<R>,
+{
+    loop {
+        match f() {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            r => return r,
+        }
+    }
+}
+
+/// The default tick duration of the poller timewheel.
+const DEFAULT_TICK_DURATION: Duration = Duration::from_millis(10);
+
+/// Returns true if `err` is caused by the fd limit of process or system.
+#[cfg(unix)]
+fn is_emfile(err: &io::Error) -> bool {
+    matches!(err.raw_os_error(), Some(libc::EMFILE) | Some(libc::ENFILE))
+}
+
+#[cfg(not(unix))]
+fn is_emfile(_err: &io::Error) -> bool {
+    false
+}
+
+#[derive(Debug, Clone)]
+struct MioDriver {
+    metrics: Arc<DriverCounters>,
+    /// The tick duration of the poller timewheel.
+    tick_duration: Duration,
+    /// The time source of the poller timers.
+    clock: Arc<dyn Clock>,
+    /// The fd budget / emergency fd configuration.
+    fd_limits: FdLimits,
+    /// The number of fds opened by this driver, the timers are not counted.
+    open_fds: Arc<AtomicUsize>,
+    /// The emergency fd reserved by [`FdLimits::reserve_fd`], which is `None` while it's released.
+    emergency_fd: Arc<Mutex<Option<std::fs::File>>>,
+    #[cfg(feature = "track-handles")]
+    handles: Arc<crate::HandleTracker>,
+}
+
+impl Default for MioDriver {
+    fn default() -> Self {
+        Self {
+            metrics: Default::default(),
+            tick_duration: DEFAULT_TICK_DURATION,
+            clock: Arc::new(SystemClock),
+            fd_limits: Default::default(),
+            open_fds: Default::default(),
+            emergency_fd: Default::default(),
+            #[cfg(feature = "track-handles")]
+            handles: Default::default(),
+        }
+    }
+}
 
 impl MioDriver {
+    /// Count the new opened `handle`.
+    fn on_fd_open(&self, handle: Handle) -> Handle {
+        self.metrics.on_fd_open(handle.desc);
+
+        if handle.desc != Description::Timeout {
+            self.open_fds.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "track-handles")]
+        self.handles.on_open(&handle);
+
+        handle
+    }
+
+    /// Count the closed `handle`.
+    fn on_fd_close(&self, handle: Handle) {
+        self.metrics.on_fd_close(handle.desc);
+
+        if handle.desc != Description::Timeout {
+            self.open_fds.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "track-handles")]
+        self.handles.on_close(&handle);
+    }
+
+    /// Opens the emergency fd if it's configured and not opened yet.
+    fn reserve_emergency_fd(&self) -> io::Result<()> {
+        #[cfg(unix)]
+        if self.fd_limits.reserve_fd {
+            let mut emergency_fd = self.emergency_fd.lock().unwrap();
+
+            if emergency_fd.is_none() {
+                *emergency_fd = Some(std::fs::File::open("/dev/null")?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sheds one pending connection of `listener` by the emergency fd, which is released
+    /// before the accept and reopened after that.
+    fn shed_incoming(&self, listener: &mio::net::TcpListener) {
+        if self.emergency_fd.lock().unwrap().take().is_none() {
+            return;
+        }
+
+        match listener.accept() {
+            Ok((_, raddr)) => log::warn!(
+                "fd limit is reached, shed incoming connection from {}",
+                raddr
+            ),
+            Err(err) => log::error!("shed incoming connection failed, err={}", err),
+        }
+
+        if let Err(err) = self.reserve_emergency_fd() {
+            log::error!("reopen emergency fd failed, err={}", err);
+        }
+    }
+
+    /// Creates the [`Emfile`] error with the configured backoff.
+    fn emfile(&self) -> io::Error {
+        Emfile {
+            backoff: self.fd_limits.backoff,
+        }
+        .into()
+    }
+
     fn nonblocking_call<R, F>(
         &self,
         poller: &MioPoller,
@@ -47,19 +391,24 @@ impl MioDriver {
 }
 
 impl RawDriverExt for MioDriver {
-    #[allow(unused)]
-    fn fd_user_define_open(&self, id: usize, buf: &[u8]) -> std::io::Result<crate::Handle> {
-        todo!()
+    fn fd_user_define_open(&self, id: usize, _buf: &[u8]) -> std::io::Result<crate::Handle> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("open external source by `open_external`, id={}", id),
+        ))
     }
 
-    #[allow(unused)]
-    fn fd_user_define_close(&self, id: usize, handle: crate::Handle) -> std::io::Result<()> {
-        todo!()
+    fn fd_user_define_close(&self, _id: usize, handle: crate::Handle) -> std::io::Result<()> {
+        handle.drop_as::<MioWithPoller<ExternalSource>>();
+
+        Ok(())
     }
 
-    #[allow(unused)]
     fn fd_user_define_clone(&self, handle: crate::Handle) -> std::io::Result<crate::Handle> {
-        todo!()
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("external source can't be cloned, {:?}", handle),
+        ))
     }
 
     #[allow(unused)]
@@ -94,11 +443,13 @@ impl RawDriverExt for MioDriver {
     fn timeout_open(&self, duration: std::time::Duration) -> std::io::Result<crate::Handle> {
         assert!(!duration.is_zero(), "create timeout with zero duration");
 
-        Ok((
-            Description::Timeout,
-            MioWithPoller::new(MioTimer::new(duration)),
-        )
-            .into())
+        Ok(self.on_fd_open(
+            (
+                Description::Timeout,
+                MioWithPoller::new(MioTimer::new(duration)),
+            )
+                .into(),
+        ))
     }
 
     fn timeout(&self, waker: std::task::Waker, handle: crate::Handle) -> std::io::Result<bool> {
@@ -107,7 +458,9 @@ impl RawDriverExt for MioDriver {
         let typed_handle = TypedHandle::<MioWithPoller<MioTimer>>::new(handle);
 
         typed_handle.with_mut(|timer| {
-            if timer.is_expired() {
+            let now = timer.poller().now();
+
+            if timer.is_expired(now) {
                 log::trace!("timer, token={:?}, expired", handle.token);
                 return Ok(true);
             }
@@ -120,11 +473,21 @@ impl RawDriverExt for MioDriver {
         })
     }
 
+    fn timeout_reset(&self, handle: crate::Handle, duration: Duration) -> std::io::Result<()> {
+        handle.expect(Description::Timeout)?;
+
+        TypedHandle::<MioWithPoller<MioTimer>>::new(handle).with_mut(|timer| timer.reset(duration));
+
+        Ok(())
+    }
+
     fn timeout_close(&self, handle: crate::Handle) -> std::io::Result<()> {
         handle.expect(Description::Timeout)?;
 
         handle.drop_as::<MioWithPoller<MioTimer>>();
 
+        self.on_fd_close(handle);
+
         Ok(())
     }
 
@@ -135,7 +498,28 @@ impl RawDriverExt for MioDriver {
 
         let tcp_lisener = mio::net::TcpListener::from_std(tcp_listener);
 
-        Ok((Description::TcpListener, MioWithPoller::new(tcp_lisener)).into())
+        Ok(self.on_fd_open((Description::TcpListener, MioWithPoller::new(tcp_lisener)).into()))
+    }
+
+    fn tcp_listener_bind_with(
+        &self,
+        laddrs: &[std::net::SocketAddr],
+        options: BindOptions,
+    ) -> std::io::Result<crate::Handle> {
+        let socket = bind_socket(laddrs, options, socket2::Type::STREAM)?;
+
+        let backlog = options
+            .backlog
+            .map(|backlog| backlog.min(i32::MAX as u32) as i32)
+            .unwrap_or(DEFAULT_BACKLOG);
+
+        socket.listen(backlog)?;
+
+        socket.set_nonblocking(true)?;
+
+        let tcp_lisener = mio::net::TcpListener::from_std(socket.into());
+
+        Ok(self.on_fd_open((Description::TcpListener, MioWithPoller::new(tcp_lisener)).into()))
     }
 
     fn tcp_listener_accept(
@@ -148,19 +532,39 @@ impl RawDriverExt for MioDriver {
         let typed_handle = TypedHandle::<MioWithPoller<mio::net::TcpListener>>::new(handle);
 
         typed_handle.with(|socket| {
-            self.nonblocking_call(
+            let result = self.nonblocking_call(
                 socket.poller(),
                 handle.token,
                 Interest::Readable,
                 waker,
                 || socket.accept(),
-            )
-            .map(|(stream, raddr)| {
-                (
-                    (Description::TcpStream, MioWithPoller::new(stream)).into(),
-                    raddr,
-                )
-            })
+            );
+
+            let (stream, raddr) = match result {
+                Ok(incoming) => incoming,
+                Err(err) if is_emfile(&err) => {
+                    self.shed_incoming(socket);
+                    return Err(self.emfile());
+                }
+                Err(err) => return Err(err),
+            };
+
+            if let Some(budget) = self.fd_limits.budget {
+                if self.open_fds.load(Ordering::Relaxed) >= budget {
+                    log::warn!(
+                        "fd budget is exhausted, shed incoming connection from {}, budget={}",
+                        raddr,
+                        budget
+                    );
+
+                    return Err(self.emfile());
+                }
+            }
+
+            Ok((
+                self.on_fd_open((Description::TcpStream, MioWithPoller::new(stream)).into()),
+                raddr,
+            ))
         })
     }
 
@@ -169,6 +573,8 @@ impl RawDriverExt for MioDriver {
 
         handle.drop_as::<MioWithPoller<mio::net::TcpListener>>();
 
+        self.on_fd_close(handle);
+
         Ok(())
     }
 
@@ -182,7 +588,48 @@ impl RawDriverExt for MioDriver {
 
         let tcp_stream = mio::net::TcpStream::from_std(tcp_stream);
 
-        Ok((Description::TcpStream, MioWithPoller::new(tcp_stream)).into())
+        Ok(self.on_fd_open((Description::TcpStream, MioWithPoller::new(tcp_stream)).into()))
+    }
+
+    fn tcp_stream_connect_nonblocking(
+        &self,
+        raddr: std::net::SocketAddr,
+    ) -> std::io::Result<crate::Handle> {
+        let tcp_stream = mio::net::TcpStream::connect(raddr)?;
+
+        Ok(self.on_fd_open((Description::TcpStream, MioWithPoller::new(tcp_stream)).into()))
+    }
+
+    fn tcp_stream_poll_connect(
+        &self,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+    ) -> std::io::Result<()> {
+        handle.expect(Description::TcpStream)?;
+
+        let typed_handle = TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle);
+
+        typed_handle.with(|socket| {
+            self.nonblocking_call(
+                socket.poller(),
+                handle.token,
+                Interest::Writable,
+                waker,
+                || {
+                    if let Some(err) = socket.take_error()? {
+                        return Err(err);
+                    }
+
+                    match socket.peer_addr() {
+                        Ok(_) => Ok(()),
+                        Err(err) if err.kind() == io::ErrorKind::NotConnected => {
+                            Err(io::Error::new(io::ErrorKind::WouldBlock, err))
+                        }
+                        Err(err) => Err(err),
+                    }
+                },
+            )
+        })
     }
 
     fn tcp_stream_write(
@@ -196,6 +643,19 @@ impl RawDriverExt for MioDriver {
         let typed_handle = TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle);
 
         typed_handle.with_mut(|socket| {
+            // The queued owned buffers are written first.
+            if !write_queue::flush(
+                socket.poller(),
+                handle,
+                socket.write_queue(),
+                Some(waker.clone()),
+            )? {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "write queue is not empty",
+                ));
+            }
+
             self.nonblocking_call(
                 &socket.poller().clone(),
                 handle.token,
@@ -206,6 +666,51 @@ impl RawDriverExt for MioDriver {
         })
     }
 
+    fn tcp_stream_write_owned(
+        &self,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        buf: bytes::Bytes,
+    ) -> std::io::Result<usize> {
+        handle.expect(Description::TcpStream)?;
+
+        TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle).with(|socket| {
+            let queue = socket.write_queue();
+
+            if !write_queue::flush(socket.poller(), handle, queue, Some(waker))?
+                && queue.len() >= WRITE_QUEUE_LIMIT
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "write queue is full",
+                ));
+            }
+
+            let len = buf.len();
+
+            queue.push(buf);
+
+            write_queue::flush(socket.poller(), handle, queue, None)?;
+
+            Ok(len)
+        })
+    }
+
+    fn tcp_stream_flush(&self, waker: std::task::Waker, handle: crate::Handle) -> io::Result<()> {
+        handle.expect(Description::TcpStream)?;
+
+        TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle).with(|socket| {
+            if write_queue::flush(socket.poller(), handle, socket.write_queue(), Some(waker))? {
+                Ok(())
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "write queue is not empty",
+                ))
+            }
+        })
+    }
+
     fn tcp_stream_read(
         &self,
         waker: std::task::Waker,
@@ -230,8 +735,14 @@ impl RawDriverExt for MioDriver {
     fn tcp_stream_close(&self, handle: crate::Handle) -> std::io::Result<()> {
         handle.expect(Description::TcpStream)?;
 
+        // Waits for the queue writing in background, the unwritten data are discarded.
+        TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle)
+            .with(|socket| socket.write_queue().close());
+
         handle.drop_as::<MioWithPoller<mio::net::TcpStream>>();
 
+        self.on_fd_close(handle);
+
         Ok(())
     }
 
@@ -242,7 +753,38 @@ impl RawDriverExt for MioDriver {
 
         let upd_socket = mio::net::UdpSocket::from_std(udp_socket);
 
-        Ok((Description::UdpSocket, MioWithPoller::new(upd_socket)).into())
+        #[cfg(target_os = "linux")]
+        super::msg::enable_recv_msg(&upd_socket, upd_socket.local_addr()?.is_ipv6())?;
+
+        Ok(self.on_fd_open((Description::UdpSocket, MioWithPoller::new(upd_socket)).into()))
+    }
+
+    fn udp_socket_bind_with(
+        &self,
+        laddrs: &[std::net::SocketAddr],
+        options: BindOptions,
+    ) -> std::io::Result<crate::Handle> {
+        let socket = bind_socket(laddrs, options, socket2::Type::DGRAM)?;
+
+        if options.gro {
+            #[cfg(target_os = "linux")]
+            super::msg::enable_gro(&socket)?;
+
+            #[cfg(not(target_os = "linux"))]
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "UDP_GRO is not supported",
+            ));
+        }
+
+        socket.set_nonblocking(true)?;
+
+        let upd_socket = mio::net::UdpSocket::from_std(socket.into());
+
+        #[cfg(target_os = "linux")]
+        super::msg::enable_recv_msg(&upd_socket, upd_socket.local_addr()?.is_ipv6())?;
+
+        Ok(self.on_fd_open((Description::UdpSocket, MioWithPoller::new(upd_socket)).into()))
     }
 
     fn udp_socket_sendto(
@@ -288,54 +830,553 @@ impl RawDriverExt for MioDriver {
         })
     }
 
-    fn udp_socket_close(&self, handle: crate::Handle) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    fn udp_socket_send_msg(
+        &self,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        buf: &[u8],
+        raddr: std::net::SocketAddr,
+        info: crate::DatagramInfo,
+    ) -> io::Result<usize> {
         handle.expect(Description::UdpSocket)?;
 
-        handle.drop_as::<MioWithPoller<mio::net::UdpSocket>>();
-
-        Ok(())
-    }
+        let typed_handle = TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle);
 
-    fn poller_open(&self, _local: bool) -> std::io::Result<crate::Handle> {
-        Ok((
-            Description::Poller,
-            MioPoller::new(Duration::from_millis(10))?,
-        )
-            .into())
+        typed_handle.with_mut(|socket| {
+            self.nonblocking_call(
+                socket.poller(),
+                handle.token,
+                Interest::Writable,
+                waker,
+                || super::msg::send_msg(&**socket, buf, raddr, &info),
+            )
+        })
     }
 
-    fn poller_clone(&self, handle: crate::Handle) -> std::io::Result<crate::Handle> {
-        handle.expect(Description::Poller)?;
+    #[cfg(target_os = "linux")]
+    fn udp_socket_recv_msg(
+        &self,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, std::net::SocketAddr, crate::DatagramInfo)> {
+        handle.expect(Description::UdpSocket)?;
 
-        let cloned = TypedHandle::<MioPoller>::new(handle).with(|poller| poller.clone());
+        let typed_handle = TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle);
 
-        Ok((Description::Poller, cloned).into())
+        typed_handle.with_mut(|socket| {
+            self.nonblocking_call(
+                socket.poller(),
+                handle.token,
+                Interest::Readable,
+                waker,
+                || super::msg::recv_msg(&**socket, buf),
+            )
+        })
     }
 
-    fn poller_register(
+    fn udp_socket_connect(
         &self,
-        poller: crate::Handle,
-        source: crate::Handle,
-        interests: crate::Interest,
-    ) -> std::io::Result<()> {
-        poller.expect(Description::Poller)?;
+        handle: crate::Handle,
+        raddr: std::net::SocketAddr,
+    ) -> io::Result<()> {
+        handle.expect(Description::UdpSocket)?;
 
-        TypedHandle::<MioPoller>::new(poller).with(|poller| poller.register(source, interests))
+        TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
+            .with(|socket| socket.connect(raddr))
     }
 
-    fn poller_reregister(
+    fn udp_socket_send(
         &self,
-        _poller: crate::Handle,
-        _source: crate::Handle,
-        _interests: crate::Interest,
-    ) -> std::io::Result<()> {
-        todo!()
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        buf: &[u8],
+    ) -> io::Result<usize> {
+        handle.expect(Description::UdpSocket)?;
+
+        let typed_handle = TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle);
+
+        typed_handle.with_mut(|socket| {
+            self.nonblocking_call(
+                socket.poller(),
+                handle.token,
+                Interest::Writable,
+                waker,
+                || socket.send(buf),
+            )
+        })
     }
 
-    fn poller_deregister(
+    fn udp_socket_recv(
         &self,
-        poller: crate::Handle,
-        source: crate::Handle,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        handle.expect(Description::UdpSocket)?;
+
+        let typed_handle = TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle);
+
+        typed_handle.with_mut(|socket| {
+            self.nonblocking_call(
+                socket.poller(),
+                handle.token,
+                Interest::Readable,
+                waker,
+                || socket.recv(buf),
+            )
+        })
+    }
+
+    fn udp_remote_addr(&self, handle: crate::Handle) -> io::Result<std::net::SocketAddr> {
+        handle.expect(Description::UdpSocket)?;
+
+        TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
+            .with(|socket| socket.peer_addr())
+    }
+
+    fn tcp_stream_try_read(&self, handle: crate::Handle, buf: &mut [u8]) -> io::Result<usize> {
+        handle.expect(Description::TcpStream)?;
+
+        TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle)
+            .with_mut(|socket| try_call(|| socket.read(buf)))
+    }
+
+    fn tcp_stream_try_write(&self, handle: crate::Handle, buf: &[u8]) -> io::Result<usize> {
+        handle.expect(Description::TcpStream)?;
+
+        TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle).with_mut(|socket| {
+            if !write_queue::flush(socket.poller(), handle, socket.write_queue(), None)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "write queue is not empty",
+                ));
+            }
+
+            try_call(|| socket.write(buf))
+        })
+    }
+
+    fn udp_socket_try_sendto(
+        &self,
+        handle: crate::Handle,
+        buf: &[u8],
+        raddr: std::net::SocketAddr,
+    ) -> io::Result<usize> {
+        handle.expect(Description::UdpSocket)?;
+
+        TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
+            .with_mut(|socket| try_call(|| socket.send_to(buf, raddr)))
+    }
+
+    fn udp_socket_try_recv_from(
+        &self,
+        handle: crate::Handle,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, std::net::SocketAddr)> {
+        handle.expect(Description::UdpSocket)?;
+
+        TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
+            .with_mut(|socket| try_call(|| socket.recv_from(buf)))
+    }
+
+    fn socket_poll_readiness(
+        &self,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        interest: Interest,
+    ) -> std::io::Result<()> {
+        match handle.desc {
+            Description::TcpStream => {
+                TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle).with_mut(|socket| {
+                    self.nonblocking_call(socket.poller(), handle.token, interest, waker, || {
+                        poll_readiness(&**socket, interest)
+                    })
+                })
+            }
+            Description::UdpSocket | Description::RawSocket => {
+                TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle).with_mut(|socket| {
+                    self.nonblocking_call(socket.poller(), handle.token, interest, waker, || {
+                        poll_readiness(&**socket, interest)
+                    })
+                })
+            }
+            Description::External(_) => TypedHandle::<MioWithPoller<ExternalSource>>::new(handle)
+                .with_mut(|source| {
+                    self.nonblocking_call(source.poller(), handle.token, interest, waker, || {
+                        Err::<(), _>(io::Error::new(
+                            io::ErrorKind::WouldBlock,
+                            "wait for the readiness of external source",
+                        ))
+                    })
+                }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect TcpStream / UdpSocket, but got {:?}", handle.desc),
+            )),
+        }
+    }
+
+    fn socket_poll_events(
+        &self,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        interests: Interest,
+    ) -> std::io::Result<Interest> {
+        match handle.desc {
+            Description::TcpStream => {
+                TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle)
+                    .with(|socket| socket.poller().poll_events(handle.token, interests, waker))
+            }
+            Description::UdpSocket | Description::RawSocket => {
+                TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
+                    .with(|socket| socket.poller().poll_events(handle.token, interests, waker))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expect TcpStream / UdpSocket, but got {:?}", handle.desc),
+            )),
+        }
+    }
+
+    fn udp_socket_close(&self, handle: crate::Handle) -> std::io::Result<()> {
+        handle.expect(Description::UdpSocket)?;
+
+        handle.drop_as::<MioWithPoller<mio::net::UdpSocket>>();
+
+        self.on_fd_close(handle);
+
+        Ok(())
+    }
+
+    fn raw_socket_open(&self, protocol: RawProtocol) -> std::io::Result<crate::Handle> {
+        let (domain, protocol) = match protocol {
+            RawProtocol::Icmpv4 => (socket2::Domain::IPV4, socket2::Protocol::ICMPV4),
+            RawProtocol::Icmpv6 => (socket2::Domain::IPV6, socket2::Protocol::ICMPV6),
+        };
+
+        let socket = socket2::Socket::new(domain, socket2::Type::RAW, Some(protocol))?;
+
+        socket.set_nonblocking(true)?;
+
+        // The raw socket shares the datagram semantics of udp socket, e.g. `send_to` / `recv_from`.
+        let raw_socket = mio::net::UdpSocket::from_std(socket.into());
+
+        Ok(self.on_fd_open((Description::RawSocket, MioWithPoller::new(raw_socket)).into()))
+    }
+
+    fn raw_socket_sendto(
+        &self,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        buf: &[u8],
+        raddr: std::net::SocketAddr,
+    ) -> std::io::Result<usize> {
+        handle.expect(Description::RawSocket)?;
+
+        let typed_handle = TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle);
+
+        typed_handle.with_mut(|socket| {
+            self.nonblocking_call(
+                socket.poller(),
+                handle.token,
+                Interest::Writable,
+                waker,
+                || socket.send_to(buf, raddr),
+            )
+        })
+    }
+
+    fn raw_socket_recv_from(
+        &self,
+        waker: std::task::Waker,
+        handle: crate::Handle,
+        buf: &mut [u8],
+    ) -> std::io::Result<(usize, std::net::SocketAddr)> {
+        handle.expect(Description::RawSocket)?;
+
+        let typed_handle = TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle);
+
+        typed_handle.with_mut(|socket| {
+            self.nonblocking_call(
+                socket.poller(),
+                handle.token,
+                Interest::Readable,
+                waker,
+                || socket.recv_from(buf),
+            )
+        })
+    }
+
+    fn raw_socket_close(&self, handle: crate::Handle) -> std::io::Result<()> {
+        handle.expect(Description::RawSocket)?;
+
+        handle.drop_as::<MioWithPoller<mio::net::UdpSocket>>();
+
+        self.on_fd_close(handle);
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn named_pipe_create(&self, name: &str) -> io::Result<Handle> {
+        let pipe = mio::windows::NamedPipe::new(name)?;
+
+        Ok(self.on_fd_open((Description::NamedPipe, MioWithPoller::new(pipe)).into()))
+    }
+
+    #[cfg(windows)]
+    fn named_pipe_open(&self, name: &str) -> io::Result<Handle> {
+        use std::os::windows::{
+            fs::OpenOptionsExt,
+            io::{FromRawHandle, IntoRawHandle},
+        };
+
+        /// Mio requires the pipe handle to be opened for overlapped io.
+        const FILE_FLAG_OVERLAPPED: u32 = 0x40000000;
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(FILE_FLAG_OVERLAPPED)
+            .open(name)?;
+
+        // Safety: the ownership of the pipe handle is transferred from `file`.
+        let pipe = unsafe { mio::windows::NamedPipe::from_raw_handle(file.into_raw_handle()) };
+
+        Ok(self.on_fd_open((Description::NamedPipe, MioWithPoller::new(pipe)).into()))
+    }
+
+    #[cfg(windows)]
+    fn named_pipe_poll_connect(&self, waker: Waker, handle: Handle) -> io::Result<()> {
+        handle.expect(Description::NamedPipe)?;
+
+        TypedHandle::<MioWithPoller<mio::windows::NamedPipe>>::new(handle).with(|pipe| {
+            self.nonblocking_call(
+                pipe.poller(),
+                handle.token,
+                Interest::Writable,
+                waker,
+                || {
+                    if let Some(err) = pipe.take_error()? {
+                        return Err(err);
+                    }
+
+                    // Returns `Ok` immediately once the pending connection is completed.
+                    pipe.connect()
+                },
+            )
+        })
+    }
+
+    #[cfg(windows)]
+    fn named_pipe_write(&self, waker: Waker, handle: Handle, buf: &[u8]) -> io::Result<usize> {
+        handle.expect(Description::NamedPipe)?;
+
+        TypedHandle::<MioWithPoller<mio::windows::NamedPipe>>::new(handle).with_mut(|pipe| {
+            self.nonblocking_call(
+                &pipe.poller().clone(),
+                handle.token,
+                Interest::Writable,
+                waker,
+                || pipe.write(buf),
+            )
+        })
+    }
+
+    #[cfg(windows)]
+    fn named_pipe_read(&self, waker: Waker, handle: Handle, buf: &mut [u8]) -> io::Result<usize> {
+        handle.expect(Description::NamedPipe)?;
+
+        TypedHandle::<MioWithPoller<mio::windows::NamedPipe>>::new(handle).with_mut(|pipe| {
+            self.nonblocking_call(
+                &pipe.poller().clone(),
+                handle.token,
+                Interest::Readable,
+                waker,
+                || pipe.read(buf),
+            )
+        })
+    }
+
+    #[cfg(windows)]
+    fn named_pipe_close(&self, handle: Handle) -> io::Result<()> {
+        handle.expect(Description::NamedPipe)?;
+
+        handle.drop_as::<MioWithPoller<mio::windows::NamedPipe>>();
+
+        self.on_fd_close(handle);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn pipe_sender_open(&self, fd: std::os::fd::RawFd) -> io::Result<Handle> {
+        use std::os::fd::FromRawFd;
+
+        // Safety: the ownership of `fd` is transferred by the caller.
+        let sender = unsafe { mio::unix::pipe::Sender::from_raw_fd(fd) };
+
+        sender.set_nonblocking(true)?;
+
+        Ok(self.on_fd_open((Description::PipeSender, MioWithPoller::new(sender)).into()))
+    }
+
+    #[cfg(unix)]
+    fn pipe_receiver_open(&self, fd: std::os::fd::RawFd) -> io::Result<Handle> {
+        use std::os::fd::FromRawFd;
+
+        // Safety: the ownership of `fd` is transferred by the caller.
+        let receiver = unsafe { mio::unix::pipe::Receiver::from_raw_fd(fd) };
+
+        receiver.set_nonblocking(true)?;
+
+        Ok(self.on_fd_open((Description::PipeReceiver, MioWithPoller::new(receiver)).into()))
+    }
+
+    #[cfg(unix)]
+    fn pipe_write(&self, waker: Waker, handle: Handle, buf: &[u8]) -> io::Result<usize> {
+        handle.expect(Description::PipeSender)?;
+
+        TypedHandle::<MioWithPoller<mio::unix::pipe::Sender>>::new(handle).with_mut(|pipe| {
+            self.nonblocking_call(
+                &pipe.poller().clone(),
+                handle.token,
+                Interest::Writable,
+                waker,
+                || pipe.write(buf),
+            )
+        })
+    }
+
+    #[cfg(unix)]
+    fn pipe_read(&self, waker: Waker, handle: Handle, buf: &mut [u8]) -> io::Result<usize> {
+        handle.expect(Description::PipeReceiver)?;
+
+        TypedHandle::<MioWithPoller<mio::unix::pipe::Receiver>>::new(handle).with_mut(|pipe| {
+            self.nonblocking_call(
+                &pipe.poller().clone(),
+                handle.token,
+                Interest::Readable,
+                waker,
+                || pipe.read(buf),
+            )
+        })
+    }
+
+    #[cfg(unix)]
+    fn pipe_close(&self, handle: Handle) -> io::Result<()> {
+        match handle.desc {
+            Description::PipeSender => handle.drop_as::<MioWithPoller<mio::unix::pipe::Sender>>(),
+            Description::PipeReceiver => {
+                handle.drop_as::<MioWithPoller<mio::unix::pipe::Receiver>>()
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Expect PipeSender / PipeReceiver, but got {:?}",
+                        handle.desc
+                    ),
+                ))
+            }
+        }
+
+        self.on_fd_close(handle);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn signal_open(&self, kind: crate::SignalKind) -> io::Result<Handle> {
+        let receiver = super::signal::SignalReceiver::new(kind)?;
+
+        Ok(self.on_fd_open((Description::Signal, MioWithPoller::new(receiver)).into()))
+    }
+
+    #[cfg(unix)]
+    fn signal_poll(&self, waker: Waker, handle: Handle) -> io::Result<usize> {
+        handle.expect(Description::Signal)?;
+
+        TypedHandle::<MioWithPoller<super::signal::SignalReceiver>>::new(handle).with_mut(
+            |receiver| {
+                self.nonblocking_call(
+                    &receiver.poller().clone(),
+                    handle.token,
+                    Interest::Readable,
+                    waker,
+                    || receiver.take(),
+                )
+            },
+        )
+    }
+
+    #[cfg(unix)]
+    fn signal_close(&self, handle: Handle) -> io::Result<()> {
+        handle.expect(Description::Signal)?;
+
+        handle.drop_as::<MioWithPoller<super::signal::SignalReceiver>>();
+
+        self.on_fd_close(handle);
+
+        Ok(())
+    }
+
+    fn poller_open(&self, _local: bool) -> std::io::Result<crate::Handle> {
+        Ok(self.on_fd_open(
+            (
+                Description::Poller,
+                MioPoller::new(self.tick_duration, self.metrics.clone(), self.clock.clone())?,
+            )
+                .into(),
+        ))
+    }
+
+    fn poller_clone(&self, handle: crate::Handle) -> std::io::Result<crate::Handle> {
+        handle.expect(Description::Poller)?;
+
+        let cloned = TypedHandle::<MioPoller>::new(handle).with(|poller| poller.clone());
+
+        Ok(self.on_fd_open((Description::Poller, cloned).into()))
+    }
+
+    fn poller_now(&self, handle: crate::Handle) -> std::io::Result<Instant> {
+        handle.expect(Description::Poller)?;
+
+        Ok(TypedHandle::<MioPoller>::new(handle).with(|poller| poller.now()))
+    }
+
+    fn poller_register(
+        &self,
+        poller: crate::Handle,
+        source: crate::Handle,
+        interests: crate::Interest,
+        mode: crate::PollMode,
+    ) -> std::io::Result<()> {
+        poller.expect(Description::Poller)?;
+
+        TypedHandle::<MioPoller>::new(poller)
+            .with(|poller| poller.register(source, interests, mode))
+    }
+
+    fn poller_reregister(
+        &self,
+        poller: crate::Handle,
+        source: crate::Handle,
+        interests: crate::Interest,
+        mode: crate::PollMode,
+    ) -> std::io::Result<()> {
+        poller.expect(Description::Poller)?;
+
+        TypedHandle::<MioPoller>::new(poller)
+            .with(|poller| poller.reregister(source, interests, mode))
+    }
+
+    fn poller_deregister(
+        &self,
+        poller: crate::Handle,
+        source: crate::Handle,
     ) -> std::io::Result<()> {
         poller.expect(Description::Poller)?;
 
@@ -352,11 +1393,19 @@ impl RawDriverExt for MioDriver {
         TypedHandle::<MioPoller>::new(poller).with(|poller| poller.poll_once(duration))
     }
 
+    fn poller_wake(&self, poller: crate::Handle) -> std::io::Result<()> {
+        poller.expect(Description::Poller)?;
+
+        TypedHandle::<MioPoller>::new(poller).with(|poller| poller.wake())
+    }
+
     fn poller_close(&self, poller: crate::Handle) -> std::io::Result<()> {
         poller.expect(Description::Poller)?;
 
         poller.drop_as::<MioPoller>();
 
+        self.on_fd_close(poller);
+
         Ok(())
     }
 
@@ -394,8 +1443,191 @@ impl RawDriverExt for MioDriver {
         TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle)
             .with(|socket| socket.shutdown(how))
     }
+
+    fn socket_set_buffer_size(
+        &self,
+        handle: Handle,
+        buffer: SocketBuffer,
+        size: usize,
+    ) -> io::Result<()> {
+        with_sock_ref(handle, |socket| match buffer {
+            SocketBuffer::Recv => socket.set_recv_buffer_size(size),
+            SocketBuffer::Send => socket.set_send_buffer_size(size),
+        })
+    }
+
+    fn socket_buffer_size(&self, handle: Handle, buffer: SocketBuffer) -> io::Result<usize> {
+        with_sock_ref(handle, |socket| match buffer {
+            SocketBuffer::Recv => socket.recv_buffer_size(),
+            SocketBuffer::Send => socket.send_buffer_size(),
+        })
+    }
+
+    fn udp_socket_set_ttl(&self, handle: Handle, ttl: u32) -> io::Result<()> {
+        with_sock_ref(handle, |socket| {
+            if is_ipv6(&socket)? {
+                socket.set_unicast_hops_v6(ttl)
+            } else {
+                socket.set_ttl(ttl)
+            }
+        })
+    }
+
+    fn udp_socket_ttl(&self, handle: Handle) -> io::Result<u32> {
+        with_sock_ref(handle, |socket| {
+            if is_ipv6(&socket)? {
+                socket.unicast_hops_v6()
+            } else {
+                socket.ttl()
+            }
+        })
+    }
+
+    fn udp_socket_set_dont_fragment(&self, handle: Handle, enabled: bool) -> io::Result<()> {
+        with_sock_ref(handle, |socket| {
+            set_dont_fragment(&socket, is_ipv6(&socket)?, enabled)
+        })
+    }
+
+    fn udp_socket_dont_fragment(&self, handle: Handle) -> io::Result<bool> {
+        with_sock_ref(handle, |socket| dont_fragment(&socket, is_ipv6(&socket)?))
+    }
+
+    fn tcp_listener_from_std(&self, listener: std::net::TcpListener) -> io::Result<Handle> {
+        listener.set_nonblocking(true)?;
+
+        let tcp_listener = mio::net::TcpListener::from_std(listener);
+
+        Ok(self.on_fd_open((Description::TcpListener, MioWithPoller::new(tcp_listener)).into()))
+    }
+
+    fn tcp_stream_from_std(&self, stream: std::net::TcpStream) -> io::Result<Handle> {
+        stream.set_nonblocking(true)?;
+
+        let tcp_stream = mio::net::TcpStream::from_std(stream);
+
+        Ok(self.on_fd_open((Description::TcpStream, MioWithPoller::new(tcp_stream)).into()))
+    }
+
+    fn udp_socket_from_std(&self, socket: std::net::UdpSocket) -> io::Result<Handle> {
+        socket.set_nonblocking(true)?;
+
+        let udp_socket = mio::net::UdpSocket::from_std(socket);
+
+        Ok(self.on_fd_open((Description::UdpSocket, MioWithPoller::new(udp_socket)).into()))
+    }
+
+    #[cfg(unix)]
+    fn fd_as_raw_fd(&self, handle: Handle) -> io::Result<std::os::fd::RawFd> {
+        use std::os::fd::AsRawFd;
+
+        match handle.desc {
+            Description::TcpListener => {
+                TypedHandle::<MioWithPoller<mio::net::TcpListener>>::new(handle)
+                    .with(|socket| Ok(socket.as_raw_fd()))
+            }
+            Description::TcpStream => {
+                TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle)
+                    .with(|socket| Ok(socket.as_raw_fd()))
+            }
+            Description::UdpSocket | Description::RawSocket => {
+                TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
+                    .with(|socket| Ok(socket.as_raw_fd()))
+            }
+            Description::PipeSender => {
+                TypedHandle::<MioWithPoller<mio::unix::pipe::Sender>>::new(handle)
+                    .with(|pipe| Ok(pipe.as_raw_fd()))
+            }
+            Description::PipeReceiver => {
+                TypedHandle::<MioWithPoller<mio::unix::pipe::Receiver>>::new(handle)
+                    .with(|pipe| Ok(pipe.as_raw_fd()))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Expect TcpListener / TcpStream / UdpSocket / Pipe, but got {:?}",
+                    handle.desc
+                ),
+            )),
+        }
+    }
+
+    #[cfg(windows)]
+    fn fd_as_raw_socket(&self, handle: Handle) -> io::Result<std::os::windows::io::RawSocket> {
+        use std::os::windows::io::AsRawSocket;
+
+        match handle.desc {
+            Description::TcpListener => {
+                TypedHandle::<MioWithPoller<mio::net::TcpListener>>::new(handle)
+                    .with(|socket| Ok(socket.as_raw_socket()))
+            }
+            Description::TcpStream => {
+                TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle)
+                    .with(|socket| Ok(socket.as_raw_socket()))
+            }
+            Description::UdpSocket | Description::RawSocket => {
+                TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
+                    .with(|socket| Ok(socket.as_raw_socket()))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Expect TcpListener / TcpStream / UdpSocket, but got {:?}",
+                    handle.desc
+                ),
+            )),
+        }
+    }
+
+    fn driver_stats(&self) -> io::Result<DriverStats> {
+        Ok(self.metrics.stats())
+    }
+
+    fn driver_capabilities(&self) -> io::Result<DriverCapabilities> {
+        Ok(DriverCapabilities {
+            backend: Backend::native(),
+            supports_oneshot: true,
+            supports_fd_budget: true,
+            supports_gso: cfg!(target_os = "linux"),
+        })
+    }
+
+    #[cfg(feature = "track-handles")]
+    fn dump_handles(&self) -> io::Result<Vec<crate::HandleInfo>> {
+        Ok(self.handles.dump())
+    }
 }
 
 pub fn mio_driver() -> Driver {
     MioDriver::default().into_raw_driver().into()
 }
+
+/// Create mio driver that forwards the metrics hooks to `subscriber`.
+pub fn mio_driver_with_metrics<S: DriverMetrics + 'static>(subscriber: S) -> Driver {
+    MioDriver {
+        metrics: Arc::new(DriverCounters::with_subscriber(subscriber)),
+        ..Default::default()
+    }
+    .into_raw_driver()
+    .into()
+}
+
+/// Create mio driver with the poller timewheel `tick_duration`, `metrics` counters, `fd_limits` and timer `clock`.
+pub(crate) fn mio_driver_with(
+    tick_duration: Duration,
+    metrics: DriverCounters,
+    fd_limits: FdLimits,
+    clock: Arc<dyn Clock>,
+) -> io::Result<Driver> {
+    let driver = MioDriver {
+        metrics: Arc::new(metrics),
+        tick_duration,
+        clock,
+        fd_limits,
+        ..Default::default()
+    };
+
+    driver.reserve_emergency_fd()?;
+
+    Ok(driver.into_raw_driver().into())
+}
@@ -0,0 +1,188 @@
+use std::{
+    future::poll_fn,
+    io,
+    task::{Context, Poll},
+};
+
+use mio::{event::Source, Interest as MioInterest, Registry, Token};
+
+use crate::{Cmd, Description, Driver, Handle, Interest};
+
+use super::with_poller::MioWithPoller;
+
+/// The io source defined out of this crate, e.g. the tun device.
+pub(super) struct ExternalSource(Box<dyn Source + Send + Sync>);
+
+impl Source for ExternalSource {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: MioInterest,
+    ) -> io::Result<()> {
+        self.0.register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: MioInterest,
+    ) -> io::Result<()> {
+        self.0.reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        self.0.deregister(registry)
+    }
+}
+
+/// Creates the [`External(id)`](Description::External) handle of any mio `source`, which can be
+/// registered to the poller of mio driver by [`Cmd::Register`].
+///
+/// The driver doesn't perform io on the source, the caller keeps the access to the underlying
+/// object (e.g. by a shared reference in `source`) and calls [`external_io`] to wait for the readiness.
+/// The handle is closed by `fd_close` after deregistered (e.g. owned by [`OwnedHandle`](crate::OwnedHandle)),
+/// and is not counted by the driver metrics.
+pub fn open_external<S: Source + Send + Sync + 'static>(id: usize, source: S) -> Handle {
+    (
+        Description::External(id),
+        MioWithPoller::new(ExternalSource(Box::new(source))),
+    )
+        .into()
+}
+
+/// Calls the non-blocking io `f` on the external source `handle`, suspends the current task until
+/// the `interest` readiness event is delivered if `f` returns [`WouldBlock`](io::ErrorKind::WouldBlock).
+pub async fn external_io<R, F>(
+    driver: &Driver,
+    handle: Handle,
+    interest: Interest,
+    mut f: F,
+) -> io::Result<R>
+where
+    F: FnMut() -> io::Result<R> + Unpin,
+{
+    poll_fn(|cx| poll_external_io(driver, handle, cx, interest, &mut f)).await
+}
+
+/// Attempts to call the non-blocking io `f` on the external source `handle`, registers the waker
+/// of `cx` to be woken by the `interest` readiness event if `f` returns [`WouldBlock`](io::ErrorKind::WouldBlock).
+pub fn poll_external_io<R, F>(
+    driver: &Driver,
+    handle: Handle,
+    cx: &mut Context<'_>,
+    interest: Interest,
+    f: F,
+) -> Poll<io::Result<R>>
+where
+    F: FnOnce() -> io::Result<R>,
+{
+    // Registers the waker before the io call, so the readiness event in the meantime is not lost.
+    match driver.fd_cntl(
+        handle,
+        Cmd::PollReadiness {
+            waker: cx.waker().clone(),
+            interest,
+        },
+    ) {
+        Err(err) if err.kind() != io::ErrorKind::WouldBlock => return Poll::Ready(Err(err)),
+        _ => {}
+    }
+
+    match f() {
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+        result => Poll::Ready(result),
+    }
+}
+
+#[cfg(all(test, unix, feature = "current"))]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        os::{fd::AsRawFd, unix::net::UnixStream},
+        sync::Arc,
+    };
+
+    use mio::unix::SourceFd;
+
+    use crate::{
+        current::{get_driver, get_poller},
+        test::io_test,
+        PollMode,
+    };
+
+    use super::*;
+
+    /// The shared unix stream, the reading end is accessed by both the test and the driver.
+    struct SharedStream(Arc<UnixStream>);
+
+    impl Source for SharedStream {
+        fn register(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: MioInterest,
+        ) -> io::Result<()> {
+            SourceFd(&self.0.as_raw_fd()).register(registry, token, interests)
+        }
+
+        fn reregister(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: MioInterest,
+        ) -> io::Result<()> {
+            SourceFd(&self.0.as_raw_fd()).reregister(registry, token, interests)
+        }
+
+        fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+            SourceFd(&self.0.as_raw_fd()).deregister(registry)
+        }
+    }
+
+    #[hala_test::test(io_test)]
+    #[allow(deprecated)]
+    async fn test_external_source() {
+        let driver = get_driver().unwrap();
+        let poller = get_poller().unwrap();
+
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+
+        reader.set_nonblocking(true).unwrap();
+
+        let reader = Arc::new(reader);
+
+        let handle = open_external(1, SharedStream(reader.clone()));
+
+        driver
+            .fd_cntl(
+                poller,
+                Cmd::Register {
+                    source: handle,
+                    interests: Interest::Readable,
+                    mode: PollMode::Edge,
+                },
+            )
+            .unwrap();
+
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            writer.write_all(b"hello").unwrap();
+        });
+
+        let mut buf = [0; 16];
+
+        let read_size = external_io(&driver, handle, Interest::Readable, || {
+            (&*reader).read(&mut buf)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(&buf[..read_size], b"hello");
+
+        driver.fd_cntl(poller, Cmd::Deregister(handle)).unwrap();
+
+        driver.fd_close(handle).unwrap();
+    }
+}
@@ -1,6 +1,25 @@
 mod poller;
 mod timer;
 mod with_poller;
+mod write_queue;
+
+mod external;
+pub use external::*;
+
+#[cfg(target_os = "linux")]
+mod msg;
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+mod sockopt;
+
+#[cfg(unix)]
+mod signal;
 
 mod driver;
 pub use driver::*;
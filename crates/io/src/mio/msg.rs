@@ -0,0 +1,338 @@
+//! `recvmsg(2)` / `sendmsg(2)` with ancillary data, only supported on linux.
+
+use std::{
+    io, mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    os::fd::AsRawFd,
+};
+
+use socket2::SockAddr;
+
+use crate::DatagramInfo;
+
+use super::sockopt::setsockopt;
+
+/// The control message buffer, aligned as `cmsghdr`.
+#[repr(C, align(8))]
+struct CmsgBuf([u8; 128]);
+
+/// Enables receiving the destination address, TOS / traffic class and TTL / hop limit of datagrams.
+pub(super) fn enable_recv_msg<S: AsRawFd>(socket: &S, ipv6: bool) -> io::Result<()> {
+    if ipv6 {
+        setsockopt(socket, libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, 1)?;
+        setsockopt(socket, libc::IPPROTO_IPV6, libc::IPV6_RECVTCLASS, 1)?;
+        setsockopt(socket, libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT, 1)?;
+    }
+
+    for name in [libc::IP_PKTINFO, libc::IP_RECVTOS, libc::IP_RECVTTL] {
+        let result = setsockopt(socket, libc::IPPROTO_IP, name, 1);
+
+        // The ipv4 options only apply to the ipv4-mapped datagrams of ipv6 socket.
+        if !ipv6 {
+            result?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Enables `UDP_GRO`, the coalesced datagrams are reported by [`DatagramInfo::segment_size`].
+pub(super) fn enable_gro<S: AsRawFd>(socket: &S) -> io::Result<()> {
+    setsockopt(socket, libc::SOL_UDP, libc::UDP_GRO, 1)
+}
+
+/// Receives one datagram and parses its ancillary data.
+pub(super) fn recv_msg<S: AsRawFd>(
+    socket: &S,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, DatagramInfo)> {
+    // Safety: all-zero is a valid value of these C structures.
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+
+    let mut cmsg_buf = CmsgBuf([0; 128]);
+
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+
+    msg.msg_name = &mut storage as *mut libc::sockaddr_storage as *mut libc::c_void;
+    msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.0.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = cmsg_buf.0.len() as _;
+
+    // Safety: the buffers referenced by `msg` are valid during the call.
+    let len = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // Safety: `storage` is initialized by `recvmsg`.
+    let raddr = unsafe { SockAddr::new(storage, msg.msg_namelen) }
+        .as_socket()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "recvmsg returns non-ip source address",
+            )
+        })?;
+
+    let mut info = DatagramInfo::default();
+
+    // Safety: the control messages are initialized by `recvmsg`, and the data
+    // are read according to the types documented in ip(7) / ipv6(7).
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+
+        while !cmsg.is_null() {
+            let data = libc::CMSG_DATA(cmsg);
+
+            match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+                (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                    let pktinfo = (data as *const libc::in_pktinfo).read_unaligned();
+
+                    info.local_ip = Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                        pktinfo.ipi_addr.s_addr,
+                    ))));
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                    let pktinfo = (data as *const libc::in6_pktinfo).read_unaligned();
+
+                    info.local_ip = Some(IpAddr::V6(Ipv6Addr::from(pktinfo.ipi6_addr.s6_addr)));
+                }
+                // The received IP_TOS is one byte.
+                (libc::IPPROTO_IP, libc::IP_TOS) => {
+                    info.ecn = *data & 0x03;
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => {
+                    info.ecn = (data as *const libc::c_int).read_unaligned() as u8 & 0x03;
+                }
+                (libc::IPPROTO_IP, libc::IP_TTL) | (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                    info.ttl = Some((data as *const libc::c_int).read_unaligned() as u8);
+                }
+                (libc::SOL_UDP, libc::UDP_GRO) => {
+                    info.segment_size = Some((data as *const libc::c_int).read_unaligned() as u16);
+                }
+                _ => {}
+            }
+
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    // Keeps the same address family as the ipv4-mapped source address of ipv6 socket.
+    if let (SocketAddr::V6(_), Some(IpAddr::V4(ip))) = (raddr, info.local_ip) {
+        info.local_ip = Some(IpAddr::V6(ip.to_ipv6_mapped()));
+    }
+
+    Ok((len as usize, raddr, info))
+}
+
+/// Writes control messages into [`CmsgBuf`].
+struct CmsgWriter {
+    buf: CmsgBuf,
+    len: usize,
+}
+
+impl CmsgWriter {
+    fn push<T: Copy>(&mut self, level: libc::c_int, ty: libc::c_int, value: T) {
+        // Safety: `CMSG_SPACE` / `CMSG_LEN` are pure computations.
+        let (space, len) = unsafe {
+            (
+                libc::CMSG_SPACE(mem::size_of::<T>() as u32) as usize,
+                libc::CMSG_LEN(mem::size_of::<T>() as u32),
+            )
+        };
+
+        assert!(
+            self.len + space <= self.buf.0.len(),
+            "control message buffer overflow"
+        );
+
+        // Safety: the buffer is aligned as `cmsghdr` and has enough space checked above.
+        unsafe {
+            let cmsg = self.buf.0.as_mut_ptr().add(self.len) as *mut libc::cmsghdr;
+
+            (*cmsg).cmsg_level = level;
+            (*cmsg).cmsg_type = ty;
+            (*cmsg).cmsg_len = len as _;
+
+            (libc::CMSG_DATA(cmsg) as *mut T).write_unaligned(value);
+        }
+
+        self.len += space;
+    }
+}
+
+/// Sends one datagram to `raddr` with ancillary data `info`.
+pub(super) fn send_msg<S: AsRawFd>(
+    socket: &S,
+    buf: &[u8],
+    raddr: SocketAddr,
+    info: &DatagramInfo,
+) -> io::Result<usize> {
+    // The ipv4-mapped destination of ipv6 socket is sent by ipv4 stack.
+    let ipv4 = match raddr {
+        SocketAddr::V4(_) => true,
+        SocketAddr::V6(addr) => addr.ip().to_ipv4_mapped().is_some(),
+    };
+
+    let mut cmsgs = CmsgWriter {
+        buf: CmsgBuf([0; 128]),
+        len: 0,
+    };
+
+    let local_ip = info.local_ip.map(|ip| match ip {
+        IpAddr::V6(v6) if ipv4 => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    });
+
+    match (local_ip, ipv4) {
+        (None, _) => {}
+        (Some(IpAddr::V4(ip)), true) => {
+            cmsgs.push(
+                libc::IPPROTO_IP,
+                libc::IP_PKTINFO,
+                libc::in_pktinfo {
+                    ipi_ifindex: 0,
+                    ipi_spec_dst: libc::in_addr {
+                        s_addr: u32::from(ip).to_be(),
+                    },
+                    ipi_addr: libc::in_addr { s_addr: 0 },
+                },
+            );
+        }
+        (Some(IpAddr::V6(ip)), false) => {
+            cmsgs.push(
+                libc::IPPROTO_IPV6,
+                libc::IPV6_PKTINFO,
+                libc::in6_pktinfo {
+                    ipi6_addr: libc::in6_addr {
+                        s6_addr: ip.octets(),
+                    },
+                    ipi6_ifindex: 0,
+                },
+            );
+        }
+        (Some(ip), _) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("source address {} mismatches destination {}", ip, raddr),
+            ));
+        }
+    }
+
+    let (level, tos, ttl) = if ipv4 {
+        (libc::IPPROTO_IP, libc::IP_TOS, libc::IP_TTL)
+    } else {
+        (libc::IPPROTO_IPV6, libc::IPV6_TCLASS, libc::IPV6_HOPLIMIT)
+    };
+
+    if info.ecn != 0 {
+        cmsgs.push(level, tos, (info.ecn & 0x03) as libc::c_int);
+    }
+
+    if let Some(value) = info.ttl {
+        cmsgs.push(level, ttl, value as libc::c_int);
+    }
+
+    if let Some(segment_size) = info.segment_size {
+        cmsgs.push(libc::SOL_UDP, libc::UDP_SEGMENT, segment_size);
+    }
+
+    let raddr = SockAddr::from(raddr);
+
+    // Safety: all-zero is a valid value of `msghdr`.
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+
+    msg.msg_name = raddr.as_ptr() as *mut libc::c_void;
+    msg.msg_namelen = raddr.len();
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+
+    if cmsgs.len != 0 {
+        msg.msg_control = cmsgs.buf.0.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = cmsgs.len as _;
+    }
+
+    // Safety: the buffers referenced by `msg` are valid during the call.
+    let len = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) };
+
+    if len < 0 {
+        let err = io::Error::last_os_error();
+
+        // The old kernel rejects `UDP_SEGMENT` with EINVAL, and the device without
+        // checksum offloading rejects it with EIO.
+        if info.segment_size.is_some()
+            && matches!(err.raw_os_error(), Some(libc::EINVAL) | Some(libc::EIO))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("udp segmentation offload is not supported, err={}", err),
+            ));
+        }
+
+        return Err(err);
+    }
+
+    Ok(len as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+
+    use super::*;
+
+    #[test]
+    fn test_gso_gro() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        enable_gro(&receiver).unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let buf = (0..350).map(|i| i as u8).collect::<Vec<_>>();
+
+        let info = DatagramInfo {
+            segment_size: Some(100),
+            ..Default::default()
+        };
+
+        let raddr = receiver.local_addr().unwrap();
+
+        match send_msg(&sender, &buf, raddr, &info) {
+            Err(err) if err.kind() == io::ErrorKind::Unsupported => return,
+            r => assert_eq!(r.unwrap(), buf.len()),
+        }
+
+        let mut received = vec![];
+        let mut recv_buf = vec![0; 1024];
+
+        while received.len() < buf.len() {
+            let (len, from, info) = recv_msg(&receiver, &mut recv_buf).unwrap();
+
+            assert_eq!(from, sender.local_addr().unwrap());
+
+            // Each datagram is 100 bytes except the last one, whether coalesced or not.
+            if let Some(segment_size) = info.segment_size {
+                assert_eq!(segment_size, 100);
+            } else {
+                assert!(len == 100 || len == 50);
+            }
+
+            received.extend_from_slice(&recv_buf[..len]);
+        }
+
+        assert_eq!(received, buf);
+    }
+}
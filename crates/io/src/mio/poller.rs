@@ -1,21 +1,58 @@
-use std::{io, ops::DerefMut, sync::Arc, task::Waker, time::Duration};
+use std::{
+    io,
+    ops::DerefMut,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::Waker,
+    time::{Duration, Instant},
+};
 
 use dashmap::DashMap;
 use hala_lockfree::timewheel::HashedTimeWheel;
 use hala_sync::{Lockable, LockableNew, SpinMutex};
 use mio::Poll;
 
-use crate::{Handle, Interest, Token, TypedHandle};
+use crate::{Clock, DriverCounters, DriverMetrics, Handle, Interest, PollMode, Token, TypedHandle};
 
-use super::{timer::MioTimer, with_poller::MioWithPoller};
+use super::{external::ExternalSource, timer::MioTimer, with_poller::MioWithPoller};
+
+/// The registration state of one io source.
+struct SourceState {
+    /// Readiness trigger mode of this source.
+    mode: PollMode,
+    /// [`OneShot`](PollMode::OneShot) source is disarmed after the first event is delivered.
+    armed: bool,
+    /// [`Level`](PollMode::Level) source retained readiness, which arrived while no task is waiting.
+    ready: Interest,
+    /// The error / hangup / priority events not yet taken by [`poll_events`](MioPoller::poll_events).
+    events: Interest,
+}
+
+/// The events waited by [`poll_events`](MioPoller::poll_events), instead of the readiness.
+const EVENTS: Interest = Interest::Error.or(Interest::Hup).or(Interest::Priority);
+
+/// The reserved token of the poller waker, which is never allocated to io sources.
+const WAKER_TOKEN: mio::Token = mio::Token(usize::MAX);
 
 struct RawMioPoller {
     mio_poller: SpinMutex<mio::Poll>,
+    /// Interrupts the blocking [`poll_once`](MioPoller::poll_once) from other threads.
+    waker: mio::Waker,
     read_wakers: DashMap<Token, Waker>,
     write_wakers: DashMap<Token, Waker>,
+    /// The wakers waiting for the error / hangup / priority events.
+    event_wakers: DashMap<Token, (Interest, Waker)>,
     registry: mio::Registry,
     hashed_timewheel: HashedTimeWheel<Token>,
     tick_duration: Duration,
+    /// One thread is blocking in [`poll_once`](MioPoller::poll_once), the new timer should wake it up.
+    polling: AtomicBool,
+    metrics: Arc<DriverCounters>,
+    sources: DashMap<Token, SourceState>,
+    /// The time source of the timewheel.
+    clock: Arc<dyn Clock>,
 }
 
 /// [`MioPoller`] io multiplexer poller
@@ -31,45 +68,103 @@ impl Drop for MioPoller {
 }
 
 impl MioPoller {
-    /// Create new [`MioPoller`] with the `tick_duration` of timewheel
-    pub fn new(tick_duration: Duration) -> io::Result<Self> {
+    /// Create new [`MioPoller`] with the `tick_duration` of timewheel, whose timers are driven by `clock`.
+    pub fn new(
+        tick_duration: Duration,
+        metrics: Arc<DriverCounters>,
+        clock: Arc<dyn Clock>,
+    ) -> io::Result<Self> {
         let mio_poller = Poll::new()?;
 
         Ok(Self(Arc::new(RawMioPoller {
+            waker: mio::Waker::new(mio_poller.registry(), WAKER_TOKEN)?,
             registry: mio_poller.registry().try_clone()?,
             read_wakers: Default::default(),
             write_wakers: Default::default(),
+            event_wakers: Default::default(),
             mio_poller: SpinMutex::new(mio_poller),
-            hashed_timewheel: HashedTimeWheel::new(tick_duration),
+            hashed_timewheel: HashedTimeWheel::new_at(tick_duration, clock.now()),
             tick_duration,
+            polling: AtomicBool::new(false),
+            metrics,
+            sources: Default::default(),
+            clock,
         })))
     }
 
+    /// Returns the current time of the poller clock.
+    pub fn now(&self) -> Instant {
+        self.0.clock.now()
+    }
+
     /// Poll io event and notify events waiters once, returns [`io::Error``] if any error happen.
+    ///
+    /// Blocks until the io event arrives, the earliest timer expires or the `timeout` elapses,
+    /// blocks indefinitely if there are no timers and `timeout` is `None`.
     pub fn poll_once(&self, timeout: Option<Duration>) -> io::Result<()> {
-        let timeout = timeout.unwrap_or(self.0.tick_duration);
+        self.0.metrics.on_poll_once();
 
         let mut events = mio::event::Events::with_capacity(1024);
 
+        let mut mio_poller = self.0.mio_poller.lock();
+
+        // Sets the flag before reading the deadline, so the timer started meanwhile either
+        // is seen here or wakes up the poll.
+        self.0.polling.store(true, Ordering::SeqCst);
+
+        let next_deadline = self.0.hashed_timewheel.next_deadline_at(self.now());
+
+        let timeout = match (timeout, next_deadline) {
+            (Some(timeout), Some(deadline)) => Some(timeout.min(deadline)),
+            (timeout, deadline) => timeout.or(deadline),
+        };
+
         // first of all, poll io event.
-        self.0.mio_poller.lock().poll(&mut events, Some(timeout))?;
+        let result = mio_poller.poll(&mut events, timeout);
+
+        self.0.polling.store(false, Ordering::SeqCst);
+
+        drop(mio_poller);
+
+        result?;
 
         let mut hala_events = vec![];
 
         for event in events.iter() {
-            let mut interests = Interest::Readable | Interest::Writable;
+            if event.token() == WAKER_TOKEN {
+                log::trace!("poller woken up by user");
+                continue;
+            }
+
+            let mut interests = Interest::none();
+
+            // The closed / error events also wake up the waiting tasks, which then get EOF or error
+            // from the io call, e.g. the pipe reports only HUP when the writing end is closed.
+            if event.is_readable() || event.is_read_closed() || event.is_error() {
+                interests |= Interest::Readable;
+            }
+
+            if event.is_writable() || event.is_write_closed() || event.is_error() {
+                interests |= Interest::Writable;
+            }
 
-            if !event.is_readable() {
-                interests = interests ^ Interest::Readable;
-            } else if !event.is_writable() {
-                interests = interests ^ Interest::Writable;
+            if event.is_error() {
+                interests |= Interest::Error;
+            }
+
+            if event.is_read_closed() || event.is_write_closed() {
+                interests |= Interest::Hup;
+            }
+
+            if event.is_priority() {
+                interests |= Interest::Priority;
             }
 
             hala_events.push((Token(event.token().0), interests));
         }
 
         // handle timeout timers
-        let timeout_timers = self.0.hashed_timewheel.next_tick();
+        let timeout_timers = self.0.hashed_timewheel.next_tick_at(self.now());
 
         if let Some(timeout_timers) = timeout_timers {
             for token in timeout_timers {
@@ -77,28 +172,115 @@ impl MioPoller {
             }
         }
 
+        let mut dispatched = 0;
+
         for (token, interests) in hala_events {
+            let mut source = self.0.sources.get_mut(&token);
+
+            if let Some(source) = source.as_mut() {
+                if source.mode == PollMode::OneShot {
+                    if !source.armed {
+                        log::trace!("{:?}, oneshot source disarmed, skip event", token);
+                        continue;
+                    }
+
+                    source.armed = false;
+                }
+            }
+
+            let mut pending = Interest::none();
+
+            // Woken after the source lock is released, the waker may register itself again,
+            // e.g. the flush waker of stream write queue.
+            let mut wakers = vec![];
+
             if interests.contains(Interest::Readable) {
                 if let Some((_, waker)) = self.0.read_wakers.remove(&token) {
                     log::trace!("{:?}, wakeup Readable", token);
-                    waker.wake();
+                    wakers.push(waker);
+                } else {
+                    pending |= Interest::Readable;
                 }
             }
 
             if interests.contains(Interest::Writable) {
                 if let Some((_, waker)) = self.0.write_wakers.remove(&token) {
                     log::trace!("{:?}, wakeup Writable", token);
-                    waker.wake();
+                    wakers.push(waker);
+                } else {
+                    pending |= Interest::Writable;
                 }
             }
+
+            if let Some(source) = source.as_mut() {
+                if source.mode == PollMode::Level {
+                    source.ready |= pending;
+                }
+            }
+
+            let events = interests & EVENTS;
+
+            // Retained until taken, the waker registered after the source lock is released
+            // sees the events.
+            if let Some(source) = source.as_mut() {
+                source.events |= events;
+            }
+
+            drop(source);
+
+            dispatched += wakers.len();
+
+            for waker in wakers {
+                waker.wake();
+            }
+
+            if events.is_none() {
+                continue;
+            }
+
+            let waker = self
+                .0
+                .event_wakers
+                .remove_if(&token, |_, (waiting, _)| waiting.intersects(events));
+
+            if let Some((_, (_, waker))) = waker {
+                log::trace!("{:?}, wakeup events {:?}", token, events);
+                waker.wake();
+                dispatched += 1;
+            }
+        }
+
+        if dispatched > 0 {
+            self.0.metrics.on_events_dispatched(dispatched);
         }
 
         Ok(())
     }
 
-    pub fn register(&self, handle: Handle, interests: Interest) -> io::Result<()> {
+    /// Wakes up the blocking [`poll_once`](Self::poll_once) to recompute the timeout with the new timer.
+    fn on_timer_started(&self) {
+        if self.0.polling.load(Ordering::SeqCst) {
+            if let Err(err) = self.0.waker.wake() {
+                log::error!("wake up poller for new timer failed, err={}", err);
+            }
+        }
+    }
+
+    /// Wakes up the blocking [`poll_once`](Self::poll_once), if no thread is polling,
+    /// the next `poll_once` returns immediately.
+    pub fn wake(&self) -> io::Result<()> {
+        self.0.waker.wake()
+    }
+
+    fn mio_interests(interests: Interest) -> mio::Interest {
         let mut mio_interests = mio::Interest::READABLE.add(mio::Interest::WRITABLE);
 
+        // The error and hangup events are always reported by the os poller.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if interests.contains(Interest::Priority) {
+            mio_interests = mio_interests.add(mio::Interest::PRIORITY);
+        }
+
         if !interests.contains(Interest::Writable) {
             mio_interests = mio_interests.remove(mio::Interest::WRITABLE).unwrap();
         }
@@ -107,6 +289,12 @@ impl MioPoller {
             mio_interests = mio_interests.remove(mio::Interest::READABLE).unwrap();
         }
 
+        mio_interests
+    }
+
+    pub fn register(&self, handle: Handle, interests: Interest, mode: PollMode) -> io::Result<()> {
+        let mio_interests = Self::mio_interests(interests);
+
         match handle.desc {
             crate::Description::File => todo!(),
             crate::Description::TcpListener => {
@@ -135,7 +323,7 @@ impl MioPoller {
                     )
                 })?;
             }
-            crate::Description::UdpSocket => {
+            crate::Description::UdpSocket | crate::Description::RawSocket => {
                 let typed_handle = TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle);
 
                 typed_handle.with_mut(|obj| {
@@ -148,6 +336,79 @@ impl MioPoller {
                     )
                 })?;
             }
+            #[cfg(windows)]
+            crate::Description::NamedPipe => {
+                let typed_handle =
+                    TypedHandle::<MioWithPoller<mio::windows::NamedPipe>>::new(handle);
+
+                typed_handle.with_mut(|obj| {
+                    obj.register_poller(self.clone());
+
+                    self.0.registry.register(
+                        obj.deref_mut(),
+                        mio::Token(handle.token.0),
+                        mio_interests,
+                    )
+                })?;
+            }
+            #[cfg(unix)]
+            crate::Description::PipeSender => {
+                let typed_handle =
+                    TypedHandle::<MioWithPoller<mio::unix::pipe::Sender>>::new(handle);
+
+                typed_handle.with_mut(|obj| {
+                    obj.register_poller(self.clone());
+
+                    self.0.registry.register(
+                        obj.deref_mut(),
+                        mio::Token(handle.token.0),
+                        mio_interests,
+                    )
+                })?;
+            }
+            #[cfg(unix)]
+            crate::Description::PipeReceiver => {
+                let typed_handle =
+                    TypedHandle::<MioWithPoller<mio::unix::pipe::Receiver>>::new(handle);
+
+                typed_handle.with_mut(|obj| {
+                    obj.register_poller(self.clone());
+
+                    self.0.registry.register(
+                        obj.deref_mut(),
+                        mio::Token(handle.token.0),
+                        mio_interests,
+                    )
+                })?;
+            }
+            #[cfg(unix)]
+            crate::Description::Signal => {
+                let typed_handle =
+                    TypedHandle::<MioWithPoller<super::signal::SignalReceiver>>::new(handle);
+
+                typed_handle.with_mut(|obj| {
+                    obj.register_poller(self.clone());
+
+                    self.0.registry.register(
+                        obj.deref_mut(),
+                        mio::Token(handle.token.0),
+                        mio_interests,
+                    )
+                })?;
+            }
+            crate::Description::External(_) => {
+                let typed_handle = TypedHandle::<MioWithPoller<ExternalSource>>::new(handle);
+
+                typed_handle.with_mut(|obj| {
+                    obj.register_poller(self.clone());
+
+                    self.0.registry.register(
+                        obj.deref_mut(),
+                        mio::Token(handle.token.0),
+                        mio_interests,
+                    )
+                })?;
+            }
             crate::Description::Timeout => {
                 let typed_handle = TypedHandle::<MioWithPoller<MioTimer>>::new(handle);
 
@@ -156,7 +417,14 @@ impl MioPoller {
 
                     obj.register_poller(self.clone());
 
-                    if !obj.start(handle.token, self.0.tick_duration, &self.0.hashed_timewheel) {
+                    self.0.metrics.on_timer_register();
+
+                    if !obj.start(
+                        handle.token,
+                        self.0.tick_duration,
+                        &self.0.hashed_timewheel,
+                        self.now(),
+                    ) {
                         log::trace!(
                             "timer, token={:?}, timeout={:?}, already timeout.",
                             handle.token,
@@ -168,6 +436,8 @@ impl MioPoller {
                             handle.token,
                             obj.duration
                         );
+
+                        self.on_timer_started();
                     }
                 });
             }
@@ -179,9 +449,152 @@ impl MioPoller {
             }
         }
 
+        self.0.sources.insert(
+            handle.token,
+            SourceState {
+                mode,
+                armed: true,
+                ready: Interest::none(),
+                events: Interest::none(),
+            },
+        );
+
         Ok(())
     }
 
+    /// Re-register the io source with new `interests` and `mode`, and re-arms the [`OneShot`](PollMode::OneShot) source.
+    pub(super) fn reregister(
+        &self,
+        handle: Handle,
+        interests: Interest,
+        mode: PollMode,
+    ) -> io::Result<()> {
+        let mio_interests = Self::mio_interests(interests);
+
+        let token = mio::Token(handle.token.0);
+
+        match handle.desc {
+            crate::Description::TcpListener => {
+                TypedHandle::<MioWithPoller<mio::net::TcpListener>>::new(handle).with_mut(
+                    |source| {
+                        self.0
+                            .registry
+                            .reregister(source.deref_mut(), token, mio_interests)
+                    },
+                )?;
+            }
+            crate::Description::TcpStream => {
+                TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle).with_mut(
+                    |source| {
+                        self.0
+                            .registry
+                            .reregister(source.deref_mut(), token, mio_interests)
+                    },
+                )?;
+            }
+            crate::Description::UdpSocket | crate::Description::RawSocket => {
+                TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle).with_mut(
+                    |source| {
+                        self.0
+                            .registry
+                            .reregister(source.deref_mut(), token, mio_interests)
+                    },
+                )?;
+            }
+            #[cfg(windows)]
+            crate::Description::NamedPipe => {
+                TypedHandle::<MioWithPoller<mio::windows::NamedPipe>>::new(handle).with_mut(
+                    |source| {
+                        self.0
+                            .registry
+                            .reregister(source.deref_mut(), token, mio_interests)
+                    },
+                )?;
+            }
+            #[cfg(unix)]
+            crate::Description::PipeSender => {
+                TypedHandle::<MioWithPoller<mio::unix::pipe::Sender>>::new(handle).with_mut(
+                    |source| {
+                        self.0
+                            .registry
+                            .reregister(source.deref_mut(), token, mio_interests)
+                    },
+                )?;
+            }
+            #[cfg(unix)]
+            crate::Description::PipeReceiver => {
+                TypedHandle::<MioWithPoller<mio::unix::pipe::Receiver>>::new(handle).with_mut(
+                    |source| {
+                        self.0
+                            .registry
+                            .reregister(source.deref_mut(), token, mio_interests)
+                    },
+                )?;
+            }
+            #[cfg(unix)]
+            crate::Description::Signal => {
+                TypedHandle::<MioWithPoller<super::signal::SignalReceiver>>::new(handle).with_mut(
+                    |source| {
+                        self.0
+                            .registry
+                            .reregister(source.deref_mut(), token, mio_interests)
+                    },
+                )?;
+            }
+            crate::Description::External(_) => {
+                TypedHandle::<MioWithPoller<ExternalSource>>::new(handle).with_mut(|source| {
+                    self.0
+                        .registry
+                        .reregister(source.deref_mut(), token, mio_interests)
+                })?;
+            }
+            crate::Description::Timeout => {
+                // Restart the timer with the reset duration.
+                TypedHandle::<MioWithPoller<MioTimer>>::new(handle).with_mut(|obj| {
+                    self.0.metrics.on_timer_register();
+
+                    if !obj.start(
+                        handle.token,
+                        self.0.tick_duration,
+                        &self.0.hashed_timewheel,
+                        self.now(),
+                    ) {
+                        log::trace!(
+                            "timer, token={:?}, timeout={:?}, already timeout.",
+                            handle.token,
+                            obj.duration
+                        );
+                    } else {
+                        self.on_timer_started();
+                    }
+                });
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("[MioDriver] invalid reregister source: {:?}", handle),
+                ))
+            }
+        }
+
+        match self.0.sources.get_mut(&handle.token) {
+            Some(mut source) => {
+                source.mode = mode;
+                source.armed = true;
+                source.ready = Interest::none();
+
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "[MioDriver] reregister source without register: {:?}",
+                    handle
+                ),
+            )),
+        }
+    }
+
     pub(super) fn deregister(&self, handle: Handle) -> io::Result<()> {
         match handle.desc {
             crate::Description::File => todo!(),
@@ -193,10 +606,34 @@ impl MioPoller {
                 TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle)
                     .with_mut(|source| self.0.registry.deregister(source.deref_mut()))?;
             }
-            crate::Description::UdpSocket => {
+            crate::Description::UdpSocket | crate::Description::RawSocket => {
                 TypedHandle::<MioWithPoller<mio::net::UdpSocket>>::new(handle)
                     .with_mut(|source| self.0.registry.deregister(source.deref_mut()))?;
             }
+            #[cfg(windows)]
+            crate::Description::NamedPipe => {
+                TypedHandle::<MioWithPoller<mio::windows::NamedPipe>>::new(handle)
+                    .with_mut(|source| self.0.registry.deregister(source.deref_mut()))?;
+            }
+            #[cfg(unix)]
+            crate::Description::PipeSender => {
+                TypedHandle::<MioWithPoller<mio::unix::pipe::Sender>>::new(handle)
+                    .with_mut(|source| self.0.registry.deregister(source.deref_mut()))?;
+            }
+            #[cfg(unix)]
+            crate::Description::PipeReceiver => {
+                TypedHandle::<MioWithPoller<mio::unix::pipe::Receiver>>::new(handle)
+                    .with_mut(|source| self.0.registry.deregister(source.deref_mut()))?;
+            }
+            #[cfg(unix)]
+            crate::Description::Signal => {
+                TypedHandle::<MioWithPoller<super::signal::SignalReceiver>>::new(handle)
+                    .with_mut(|source| self.0.registry.deregister(source.deref_mut()))?;
+            }
+            crate::Description::External(_) => {
+                TypedHandle::<MioWithPoller<ExternalSource>>::new(handle)
+                    .with_mut(|source| self.0.registry.deregister(source.deref_mut()))?;
+            }
             crate::Description::Timeout => TypedHandle::<MioWithPoller<MioTimer>>::new(handle)
                 .with_mut(|_timer| {
                     log::trace!("timer, token={:?} deregister.", handle.token);
@@ -209,10 +646,69 @@ impl MioPoller {
             }
         }
 
-        self.remove_waker(handle.token, Interest::all()).map(|_| ())
+        self.0.sources.remove(&handle.token);
+
+        self.0.event_wakers.remove(&handle.token);
+
+        self.0.read_wakers.remove(&handle.token);
+
+        // Also drops the flush waker of stream write queue, which refers to this source.
+        self.0.write_wakers.remove(&handle.token);
+
+        Ok(())
+    }
+
+    /// Takes the error / hangup / priority events in `interests` of the source `token`,
+    /// registers the `waker` and returns WOULD_BLOCK error if none of them occurred.
+    pub(super) fn poll_events(
+        &self,
+        token: Token,
+        interests: Interest,
+        waker: Waker,
+    ) -> io::Result<Interest> {
+        let mut source = self.0.sources.get_mut(&token).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "[MioDriver] poll events of unregistered source: {:?}",
+                    token
+                ),
+            )
+        })?;
+
+        let events = source.events & interests & EVENTS;
+
+        if events.is_none() {
+            // Registered while holding the source lock, so the events reported meanwhile wake it up.
+            self.0.event_wakers.insert(token, (interests, waker));
+
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "wait for the error / hangup / priority events",
+            ));
+        }
+
+        source.events &= !events;
+
+        Ok(events)
     }
 
     pub(super) fn add_waker(&self, token: Token, interests: Interest, waker: Waker) {
+        // Delivers the retained readiness of level-triggered source immediately.
+        if let Some(mut source) = self.0.sources.get_mut(&token) {
+            if source.mode == PollMode::Level && source.ready.intersects(interests) {
+                source.ready &= !interests;
+
+                drop(source);
+
+                log::trace!("{:?}, wakeup retained readiness {:?}", token, interests);
+
+                waker.wake();
+
+                return;
+            }
+        }
+
         if interests.contains(Interest::Readable) {
             self.0.read_wakers.insert(token, waker.clone());
         } else if interests.contains(Interest::Writable) {
@@ -234,3 +730,299 @@ impl MioPoller {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use futures::task::{waker, ArcWake};
+
+    use crate::{Description, Handle, Interest, MockClock, PollMode, SystemClock};
+
+    use super::{MioPoller, MioTimer, MioWithPoller};
+
+    #[derive(Default)]
+    struct CountWaker(AtomicUsize);
+
+    impl ArcWake for CountWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn udp_socket(poller: &MioPoller, mode: PollMode) -> (Handle, std::net::SocketAddr) {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        socket.set_nonblocking(true).unwrap();
+
+        let laddr = socket.local_addr().unwrap();
+
+        let handle: Handle = (
+            Description::UdpSocket,
+            MioWithPoller::new(mio::net::UdpSocket::from_std(socket)),
+        )
+            .into();
+
+        poller.register(handle, Interest::Readable, mode).unwrap();
+
+        (handle, laddr)
+    }
+
+    fn close(poller: &MioPoller, handle: Handle) {
+        poller.deregister(handle).unwrap();
+
+        handle.drop_as::<MioWithPoller<mio::net::UdpSocket>>();
+    }
+
+    #[test]
+    fn test_level_mode() {
+        let poller = MioPoller::new(
+            Duration::from_millis(10),
+            Default::default(),
+            Arc::new(SystemClock),
+        )
+        .unwrap();
+
+        let (handle, laddr) = udp_socket(&poller, PollMode::Level);
+
+        let client = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        client.send_to(b"hello", laddr).unwrap();
+
+        // No task is waiting, the readiness is retained.
+        poller.poll_once(Some(Duration::from_secs(1))).unwrap();
+
+        let counter = Arc::new(CountWaker::default());
+
+        poller.add_waker(handle.token, Interest::Readable, waker(counter.clone()));
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        // The retained readiness is consumed.
+        poller.add_waker(handle.token, Interest::Readable, waker(counter.clone()));
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        close(&poller, handle);
+    }
+
+    #[test]
+    fn test_oneshot_mode() {
+        let poller = MioPoller::new(
+            Duration::from_millis(10),
+            Default::default(),
+            Arc::new(SystemClock),
+        )
+        .unwrap();
+
+        let (handle, laddr) = udp_socket(&poller, PollMode::OneShot);
+
+        let client = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let counter = Arc::new(CountWaker::default());
+
+        poller.add_waker(handle.token, Interest::Readable, waker(counter.clone()));
+
+        client.send_to(b"hello", laddr).unwrap();
+
+        poller.poll_once(Some(Duration::from_secs(1))).unwrap();
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        poller.add_waker(handle.token, Interest::Readable, waker(counter.clone()));
+
+        client.send_to(b"hello", laddr).unwrap();
+
+        // The source is disarmed.
+        poller.poll_once(Some(Duration::from_millis(100))).unwrap();
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        poller
+            .reregister(handle, Interest::Readable, PollMode::OneShot)
+            .unwrap();
+
+        poller.poll_once(Some(Duration::from_secs(1))).unwrap();
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+
+        close(&poller, handle);
+    }
+
+    #[test]
+    fn test_poll_timeout_of_timer() {
+        let poller = MioPoller::new(
+            Duration::from_millis(10),
+            Default::default(),
+            Arc::new(SystemClock),
+        )
+        .unwrap();
+
+        let handle: Handle = (
+            Description::Timeout,
+            MioWithPoller::new(MioTimer::new(Duration::from_millis(100))),
+        )
+            .into();
+
+        let counter = Arc::new(CountWaker::default());
+
+        poller.add_waker(handle.token, Interest::Readable, waker(counter.clone()));
+
+        // The timer registered by other thread wakes up the indefinitely blocking poll.
+        let registry = {
+            let poller = poller.clone();
+
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+
+                poller
+                    .register(handle, Interest::Readable, PollMode::Edge)
+                    .unwrap();
+            })
+        };
+
+        let start = Instant::now();
+
+        while counter.0.load(Ordering::SeqCst) == 0 {
+            poller.poll_once(None).unwrap();
+        }
+
+        registry.join().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        poller.deregister(handle).unwrap();
+
+        handle.drop_as::<MioWithPoller<MioTimer>>();
+    }
+
+    #[test]
+    fn test_mock_clock_timer() {
+        let clock = MockClock::new();
+
+        let poller = MioPoller::new(
+            Duration::from_millis(10),
+            Default::default(),
+            Arc::new(clock.clone()),
+        )
+        .unwrap();
+
+        let handle: Handle = (
+            Description::Timeout,
+            MioWithPoller::new(MioTimer::new(Duration::from_secs(60))),
+        )
+            .into();
+
+        poller
+            .register(handle, Interest::Readable, PollMode::Edge)
+            .unwrap();
+
+        let counter = Arc::new(CountWaker::default());
+
+        poller.add_waker(handle.token, Interest::Readable, waker(counter.clone()));
+
+        poller.poll_once(Some(Duration::from_millis(20))).unwrap();
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        clock.advance(Duration::from_secs(30));
+
+        poller.poll_once(Some(Duration::from_millis(20))).unwrap();
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        clock.advance(Duration::from_secs(31));
+
+        // The deadline is reached, the poll returns immediately.
+        let start = Instant::now();
+
+        poller.poll_once(Some(Duration::from_secs(10))).unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        poller.deregister(handle).unwrap();
+
+        handle.drop_as::<MioWithPoller<MioTimer>>();
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn test_poll_events() {
+        let poller = MioPoller::new(
+            Duration::from_millis(10),
+            Default::default(),
+            Arc::new(SystemClock),
+        )
+        .unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (server, _) = listener.accept().unwrap();
+
+        server.set_nonblocking(true).unwrap();
+
+        let handle: Handle = (
+            Description::TcpStream,
+            MioWithPoller::new(mio::net::TcpStream::from_std(server)),
+        )
+            .into();
+
+        poller
+            .register(
+                handle,
+                Interest::Readable | Interest::Priority,
+                PollMode::Edge,
+            )
+            .unwrap();
+
+        let counter = Arc::new(CountWaker::default());
+
+        let events = Interest::Error | Interest::Hup | Interest::Priority;
+
+        poller
+            .poll_events(handle.token, events, waker(counter.clone()))
+            .unwrap_err();
+
+        // The out-of-band data is reported as priority event.
+        socket2::SockRef::from(&client)
+            .send_out_of_band(b"!")
+            .unwrap();
+
+        while counter.0.load(Ordering::SeqCst) == 0 {
+            poller.poll_once(Some(Duration::from_secs(1))).unwrap();
+        }
+
+        assert_eq!(
+            poller
+                .poll_events(handle.token, events, waker(counter.clone()))
+                .unwrap(),
+            Interest::Priority
+        );
+
+        // The hangup event is retained until taken.
+        drop(client);
+
+        poller.poll_once(Some(Duration::from_secs(1))).unwrap();
+
+        assert_eq!(
+            poller
+                .poll_events(handle.token, Interest::Hup, waker(counter.clone()))
+                .unwrap(),
+            Interest::Hup
+        );
+
+        poller.deregister(handle).unwrap();
+
+        handle.drop_as::<MioWithPoller<mio::net::TcpStream>>();
+    }
+}
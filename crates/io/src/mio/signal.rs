@@ -0,0 +1,205 @@
+//! Signal notifications through self-pipe, the signal handler writes one byte to the pipe of each listener.
+
+use std::{
+    io::{self, Read},
+    mem,
+    os::fd::AsRawFd,
+    ptr,
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+use hala_sync::{Lockable, RwLockable, SpinRwLock};
+
+use mio::{
+    event::Source,
+    unix::pipe::{Receiver, Sender},
+    Interest, Registry, Token,
+};
+
+use crate::SignalKind;
+
+/// The upper bound of signal number.
+const MAX_SIGNUM: usize = 65;
+
+/// The maximum number of listeners of one signal.
+const MAX_LISTENERS: usize = 64;
+
+/// The pipe writing ends of listeners, indexed by signal number.
+static LISTENERS: [[AtomicI32; MAX_LISTENERS]; MAX_SIGNUM] =
+    [const { [const { AtomicI32::new(-1) }; MAX_LISTENERS] }; MAX_SIGNUM];
+
+/// The signals whose handler has been installed, which is only written once per signal.
+static INSTALLED: SpinRwLock<[bool; MAX_SIGNUM]> = SpinRwLock::const_new([false; MAX_SIGNUM]);
+
+extern "C" fn on_signal(signum: libc::c_int) {
+    let Some(listeners) = LISTENERS.get(signum as usize) else {
+        return;
+    };
+
+    for fd in listeners {
+        let fd = fd.load(Ordering::Acquire);
+
+        if fd >= 0 {
+            // Safety: `write` is async-signal-safe, the error is ignored if the pipe is full.
+            unsafe {
+                libc::write(fd, [1u8].as_ptr() as *const libc::c_void, 1);
+            }
+        }
+    }
+}
+
+fn signum(kind: SignalKind) -> libc::c_int {
+    match kind {
+        SignalKind::Interrupt => libc::SIGINT,
+        SignalKind::Terminate => libc::SIGTERM,
+        SignalKind::Hangup => libc::SIGHUP,
+        SignalKind::Quit => libc::SIGQUIT,
+        SignalKind::User1 => libc::SIGUSR1,
+        SignalKind::User2 => libc::SIGUSR2,
+        SignalKind::Child => libc::SIGCHLD,
+        SignalKind::WindowChange => libc::SIGWINCH,
+        SignalKind::Pipe => libc::SIGPIPE,
+        SignalKind::Raw(signum) => signum,
+    }
+}
+
+/// Installs the signal handler once for `signum`.
+fn install(signum: libc::c_int) -> io::Result<()> {
+    if INSTALLED.read()[signum as usize] {
+        return Ok(());
+    }
+
+    let mut installed = INSTALLED.lock();
+
+    if installed[signum as usize] {
+        return Ok(());
+    }
+
+    // Safety: all-zero is a valid value of `sigaction`, and `on_signal` is async-signal-safe.
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+
+        action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+
+        libc::sigemptyset(&mut action.sa_mask);
+
+        if libc::sigaction(signum, &action, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    installed[signum as usize] = true;
+
+    Ok(())
+}
+
+/// The listener of one signal.
+pub(super) struct SignalReceiver {
+    signum: libc::c_int,
+    slot: usize,
+    receiver: Receiver,
+    _sender: Sender,
+}
+
+impl SignalReceiver {
+    pub(super) fn new(kind: SignalKind) -> io::Result<Self> {
+        let signum = signum(kind);
+
+        if signum <= 0
+            || signum as usize >= MAX_SIGNUM
+            || [
+                libc::SIGKILL,
+                libc::SIGSTOP,
+                libc::SIGILL,
+                libc::SIGFPE,
+                libc::SIGSEGV,
+                libc::SIGBUS,
+            ]
+            .contains(&signum)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("can't listen signal {:?}", kind),
+            ));
+        }
+
+        let (sender, receiver) = mio::unix::pipe::new()?;
+
+        let slot = LISTENERS[signum as usize]
+            .iter()
+            .position(|fd| {
+                fd.compare_exchange(-1, sender.as_raw_fd(), Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+            })
+            .ok_or_else(|| io::Error::other(format!("too many listeners of signal {:?}", kind)))?;
+
+        let receiver = Self {
+            signum,
+            slot,
+            receiver,
+            _sender: sender,
+        };
+
+        install(signum)?;
+
+        Ok(receiver)
+    }
+
+    /// Takes the number of delivered signals, returns WOULD_BLOCK error if there is none.
+    pub(super) fn take(&mut self) -> io::Result<usize> {
+        let mut buf = [0u8; 64];
+
+        let mut count = 0;
+
+        loop {
+            match self.receiver.read(&mut buf) {
+                Ok(0) => break,
+                Ok(read_size) => count += read_size,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if count == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "no signal delivered",
+            ));
+        }
+
+        Ok(count)
+    }
+}
+
+impl Drop for SignalReceiver {
+    fn drop(&mut self) {
+        // Stops the signal handler writing to the pipe before closing it.
+        LISTENERS[self.signum as usize][self.slot].store(-1, Ordering::Release);
+    }
+}
+
+impl Source for SignalReceiver {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.receiver.register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.receiver.reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        self.receiver.deregister(registry)
+    }
+}
@@ -0,0 +1,113 @@
+//! The socket options not provided by `socket2`.
+
+use std::{io, mem, os::fd::AsRawFd};
+
+pub(super) fn setsockopt<S: AsRawFd>(
+    socket: &S,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> io::Result<()> {
+    // Safety: `value` is a valid `c_int` during the call.
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn getsockopt<S: AsRawFd>(
+    socket: &S,
+    level: libc::c_int,
+    name: libc::c_int,
+) -> io::Result<libc::c_int> {
+    let mut value: libc::c_int = 0;
+    let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+
+    // Safety: `value` and `len` are valid during the call.
+    let ret = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(value)
+}
+
+/// Sets or clears the DF bit of the datagrams sent by `socket`, the oversized datagram fails
+/// with `EMSGSIZE` instead of being fragmented once set.
+///
+/// Uses `IP_MTU_DISCOVER` on linux, `IP_DONTFRAG` on BSD / macOS, and `IPV6_DONTFRAG` for ipv6 sockets.
+pub(super) fn set_dont_fragment<S: AsRawFd>(
+    socket: &S,
+    ipv6: bool,
+    enabled: bool,
+) -> io::Result<()> {
+    if ipv6 {
+        setsockopt(
+            socket,
+            libc::IPPROTO_IPV6,
+            libc::IPV6_DONTFRAG,
+            enabled as libc::c_int,
+        )?;
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let result = setsockopt(
+        socket,
+        libc::IPPROTO_IP,
+        libc::IP_MTU_DISCOVER,
+        if enabled {
+            libc::IP_PMTUDISC_DO
+        } else {
+            libc::IP_PMTUDISC_DONT
+        },
+    );
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let result = setsockopt(
+        socket,
+        libc::IPPROTO_IP,
+        libc::IP_DONTFRAG,
+        enabled as libc::c_int,
+    );
+
+    // The ipv4 option only applies to the ipv4-mapped datagrams of ipv6 socket.
+    if !ipv6 {
+        result?;
+    }
+
+    Ok(())
+}
+
+/// Returns true if the DF bit of the datagrams sent by `socket` is set.
+pub(super) fn dont_fragment<S: AsRawFd>(socket: &S, ipv6: bool) -> io::Result<bool> {
+    if ipv6 {
+        return Ok(getsockopt(socket, libc::IPPROTO_IPV6, libc::IPV6_DONTFRAG)? != 0);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    return getsockopt(socket, libc::IPPROTO_IP, libc::IP_MTU_DISCOVER)
+        .map(|value| value == libc::IP_PMTUDISC_DO || value == libc::IP_PMTUDISC_PROBE);
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    return getsockopt(socket, libc::IPPROTO_IP, libc::IP_DONTFRAG).map(|value| value != 0);
+}
@@ -45,18 +45,27 @@ impl MioTimer {
         token: Token,
         tick_duration: Duration,
         timewheel: &HashedTimeWheel<Token>,
+        now: Instant,
     ) -> bool {
-        self.start_instant = Some(Instant::now());
+        self.start_instant = Some(now);
         self.tick_duration = Some(tick_duration);
 
-        self.timewheel_ticks = timewheel.new_timer(token, self.duration);
+        self.timewheel_ticks = timewheel.new_timer_at(token, self.duration, now);
 
         self.timewheel_ticks.is_some()
     }
 
-    pub(super) fn is_expired(&self) -> bool {
+    /// Reset the timeout `duration`, the timer should be restarted by calling [`start`](Self::start).
+    pub(super) fn reset(&mut self, duration: Duration) {
+        self.duration = duration;
+        self.start_instant = None;
+        self.timewheel_ticks = None;
+    }
+
+    /// Returns true if the timer is expired at `now`, or will be expired within one tick.
+    pub(super) fn is_expired(&self, now: Instant) -> bool {
         if let Some(start_instant) = self.start_instant {
-            let elapsed = start_instant.elapsed();
+            let elapsed = now.saturating_duration_since(start_instant);
 
             if elapsed >= self.duration {
                 return true;
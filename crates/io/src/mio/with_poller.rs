@@ -1,12 +1,14 @@
 use std::{ops, sync::Once};
 
-use super::poller::MioPoller;
+use super::{poller::MioPoller, write_queue::WriteQueue};
 
 /// Mio io fd with poller pair
 pub(super) struct MioWithPoller<T> {
     value: T,
     poller: Option<MioPoller>,
     once: Once,
+    /// The owned buffers queued by [`WriteOwned`](crate::Cmd::WriteOwned), only used by the stream.
+    write_queue: WriteQueue,
 }
 
 impl<T> MioWithPoller<T> {
@@ -15,6 +17,7 @@ impl<T> MioWithPoller<T> {
             value,
             poller: None,
             once: Once::new(),
+            write_queue: Default::default(),
         }
     }
 
@@ -23,6 +26,11 @@ impl<T> MioWithPoller<T> {
         self.poller.as_ref().expect("Call register first")
     }
 
+    /// Get the write queue of this io object.
+    pub(super) fn write_queue(&self) -> &WriteQueue {
+        &self.write_queue
+    }
+
     /// Reigster poller with once guard.
     pub(super) fn register_poller(&mut self, poller: MioPoller) {
         assert!(!self.once.is_completed(), "Call register_poller twice");
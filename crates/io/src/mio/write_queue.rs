@@ -0,0 +1,170 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    sync::Arc,
+    task::Waker,
+};
+
+use bytes::{Buf, Bytes};
+use futures::task::{waker, ArcWake};
+use hala_sync::{Lockable, LockableNew, SpinMutex};
+
+use crate::{Handle, Interest, TypedHandle};
+
+use super::{poller::MioPoller, with_poller::MioWithPoller};
+
+/// The max number of queued bytes of one stream, the [`WriteOwned`](crate::Cmd::WriteOwned) command
+/// returns WOULD_BLOCK until the queue is drained below it.
+pub(super) const WRITE_QUEUE_LIMIT: usize = 256 * 1024;
+
+#[derive(Default)]
+struct RawWriteQueue {
+    bufs: VecDeque<Bytes>,
+    /// The total length of `bufs`.
+    len: usize,
+    /// The error of writing the queue in background, returned by the next write / flush call.
+    error: Option<io::Error>,
+    /// The stream is closed, the queued buffers are discarded.
+    closed: bool,
+}
+
+impl RawWriteQueue {
+    /// Writes the queued buffers to stream `handle` until WOULD_BLOCK, returns true if the queue is drained.
+    fn write(&mut self, handle: Handle) -> io::Result<bool> {
+        if self.closed {
+            return Ok(true);
+        }
+
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+
+        let typed_handle = TypedHandle::<MioWithPoller<mio::net::TcpStream>>::new(handle);
+
+        while let Some(buf) = self.bufs.front_mut() {
+            match typed_handle.with(|socket| (&**socket).write(buf)) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(write_size) => {
+                    self.len -= write_size;
+
+                    if write_size == buf.len() {
+                        self.bufs.pop_front();
+                    } else {
+                        buf.advance(write_size);
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// The owned buffers queued by [`WriteOwned`](crate::Cmd::WriteOwned), which are written
+/// by the [`FlushWaker`] once the stream is writable.
+///
+/// The queue lock is held while writing the stream, so the stream is not closed meanwhile.
+#[derive(Clone)]
+pub(super) struct WriteQueue(Arc<SpinMutex<RawWriteQueue>>);
+
+impl Default for WriteQueue {
+    fn default() -> Self {
+        Self(Arc::new(SpinMutex::new(Default::default())))
+    }
+}
+
+impl WriteQueue {
+    /// Returns the number of queued bytes.
+    pub(super) fn len(&self) -> usize {
+        self.0.lock().len
+    }
+
+    /// Appends `buf` to the queue, the caller should [`flush`] it later.
+    pub(super) fn push(&self, buf: Bytes) {
+        let mut raw = self.0.lock();
+
+        raw.len += buf.len();
+        raw.bufs.push_back(buf);
+    }
+
+    /// Discards the queued buffers, called before the stream is closed.
+    pub(super) fn close(&self) {
+        let mut raw = self.0.lock();
+
+        raw.closed = true;
+        raw.bufs.clear();
+        raw.len = 0;
+    }
+
+    fn write(&self, handle: Handle) -> io::Result<bool> {
+        self.0.lock().write(handle)
+    }
+}
+
+/// The write waker registered while the queue is not empty, which writes the queue once the stream is writable,
+/// and then wakes up the task waiting for the queue drained.
+struct FlushWaker {
+    poller: MioPoller,
+    handle: Handle,
+    queue: WriteQueue,
+    waker: Option<Waker>,
+}
+
+impl ArcWake for FlushWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        match flush(
+            &arc_self.poller,
+            arc_self.handle,
+            &arc_self.queue,
+            arc_self.waker.clone(),
+        ) {
+            Ok(false) => return,
+            Ok(true) => {}
+            Err(err) => {
+                log::trace!(
+                    "{:?}, write queue failed, err={}",
+                    arc_self.handle.token,
+                    err
+                );
+
+                arc_self.queue.0.lock().error = Some(err);
+            }
+        }
+
+        if let Some(waker) = &arc_self.waker {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/// Writes the queue of stream `handle`, returns true if the queue is drained.
+///
+/// Otherwise, the [`FlushWaker`] is registered to write the rest once the stream is writable,
+/// which wakes up `user_waker` after the queue is drained.
+pub(super) fn flush(
+    poller: &MioPoller,
+    handle: Handle,
+    queue: &WriteQueue,
+    user_waker: Option<Waker>,
+) -> io::Result<bool> {
+    if queue.write(handle)? {
+        return Ok(true);
+    }
+
+    poller.add_waker(
+        handle.token,
+        Interest::Writable,
+        waker(Arc::new(FlushWaker {
+            poller: poller.clone(),
+            handle,
+            queue: queue.clone(),
+            waker: user_waker,
+        })),
+    );
+
+    // Retries after registering, so the writable event arrived meanwhile is not missed.
+    queue.write(handle)
+}
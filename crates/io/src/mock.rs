@@ -0,0 +1,1314 @@
+//! Deterministic in-memory driver for unit testing protocols.
+//!
+//! [`MockDriver`] implements tcp/udp endpoints and timeouts without touching the os:
+//!
+//! - The clock is manual, timeouts are only expired by calling [`advance`](MockDriver::advance).
+//! - Udp datagrams can be delayed, dropped or reordered, see [`MockNetworkConfig`].
+//!   The fault injection is driven by a seeded pseudo random generator, so the same seed
+//!   always produces the same packet sequence.
+//! - Tcp streams are reliable and data are delivered immediately.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr},
+    sync::Arc,
+    task::Waker,
+    time::{Duration, Instant},
+};
+
+use hala_sync::{Lockable, LockableNew, SpinMutex};
+
+use crate::{
+    Backend, Description, Driver, DriverCapabilities, FileMode, Handle, Interest, IntoRawDriver,
+    PollMode, RawDriverExt, Token, TokenGenerator,
+};
+
+/// The first port number allocated to sockets bound with port 0.
+const EPHEMERAL_PORT_START: u16 = 40000;
+
+/// The network fault injection config of [`MockDriver`], only affects udp datagrams.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MockNetworkConfig {
+    /// The delivery latency of datagrams.
+    pub latency: Duration,
+    /// The probability of dropping a datagram, in range `[0.0, 1.0]`.
+    pub loss_rate: f64,
+    /// The probability of delivering a datagram before the queued ones, in range `[0.0, 1.0]`.
+    pub reorder_rate: f64,
+}
+
+struct MockTimer {
+    deadline: Duration,
+    waker: Option<Waker>,
+}
+
+struct MockUdpSocket {
+    laddr: SocketAddr,
+    rx: VecDeque<(Vec<u8>, SocketAddr)>,
+    waker: Option<Waker>,
+}
+
+struct InFlight {
+    deliver_at: Duration,
+    from: SocketAddr,
+    to: SocketAddr,
+    data: Vec<u8>,
+}
+
+struct MockTcpListener {
+    laddr: SocketAddr,
+    incoming: VecDeque<(Token, SocketAddr)>,
+    waker: Option<Waker>,
+}
+
+struct MockTcpStream {
+    laddr: SocketAddr,
+    raddr: SocketAddr,
+    /// The other end of this stream, `None` if it had been closed.
+    peer: Option<Token>,
+    rx: VecDeque<u8>,
+    read_closed: bool,
+    write_closed: bool,
+    waker: Option<Waker>,
+}
+
+struct MockState {
+    /// The instant of the manual clock zero, see [`Cmd::Now`](crate::Cmd::Now).
+    start: Instant,
+    now: Duration,
+    rng: u64,
+    config: MockNetworkConfig,
+    next_port: u16,
+    timers: HashMap<Token, MockTimer>,
+    udp_sockets: HashMap<Token, MockUdpSocket>,
+    udp_addrs: HashMap<SocketAddr, Token>,
+    in_flight: Vec<InFlight>,
+    tcp_listeners: HashMap<Token, MockTcpListener>,
+    tcp_listener_addrs: HashMap<SocketAddr, Token>,
+    tcp_streams: HashMap<Token, MockTcpStream>,
+}
+
+/// Returns the address used to lookup sockets bound to unspecified address.
+fn unspecified(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), addr.port()),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), addr.port()),
+    }
+}
+
+/// Returns the address seen by remote peers, the unspecified ip is replaced by loopback ip.
+fn visible(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), addr.port())
+        }
+        IpAddr::V6(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), addr.port())
+        }
+        _ => addr,
+    }
+}
+
+fn lookup(addrs: &HashMap<SocketAddr, Token>, addr: SocketAddr) -> Option<Token> {
+    addrs
+        .get(&addr)
+        .or_else(|| addrs.get(&unspecified(addr)))
+        .cloned()
+}
+
+/// Allocate bind address, the port 0 is replaced by an unused ephemeral port.
+fn bind_addr<F>(next_port: &mut u16, laddr: SocketAddr, in_use: F) -> io::Result<SocketAddr>
+where
+    F: Fn(&SocketAddr) -> bool,
+{
+    if laddr.port() != 0 {
+        if in_use(&laddr) {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("mock driver: address in use, {}", laddr),
+            ));
+        }
+
+        return Ok(laddr);
+    }
+
+    loop {
+        let port = *next_port;
+
+        *next_port = next_port.checked_add(1).unwrap_or(EPHEMERAL_PORT_START);
+
+        let laddr = SocketAddr::new(laddr.ip(), port);
+
+        if !in_use(&laddr) {
+            return Ok(laddr);
+        }
+    }
+}
+
+fn not_found(handle: Handle) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("mock driver: handle not found, {:?}", handle),
+    )
+}
+
+impl MockState {
+    fn new(seed: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            now: Duration::ZERO,
+            // xorshift generator requires non-zero state.
+            rng: seed.max(1),
+            config: Default::default(),
+            next_port: EPHEMERAL_PORT_START,
+            timers: Default::default(),
+            udp_sockets: Default::default(),
+            udp_addrs: Default::default(),
+            in_flight: Default::default(),
+            tcp_listeners: Default::default(),
+            tcp_listener_addrs: Default::default(),
+            tcp_streams: Default::default(),
+        }
+    }
+
+    /// Returns a pseudo random number in range `[0.0, 1.0)`.
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn deliver(&mut self, datagram: InFlight, wakers: &mut Vec<Waker>) {
+        let Some(token) = lookup(&self.udp_addrs, datagram.to) else {
+            log::trace!("mock driver: drop datagram to {}, unreachable", datagram.to);
+            return;
+        };
+
+        let reorder = self.random() < self.config.reorder_rate;
+
+        let socket = self.udp_sockets.get_mut(&token).unwrap();
+
+        if reorder && !socket.rx.is_empty() {
+            socket.rx.push_front((datagram.data, datagram.from));
+        } else {
+            socket.rx.push_back((datagram.data, datagram.from));
+        }
+
+        wakers.extend(socket.waker.take());
+    }
+
+    fn advance(&mut self, duration: Duration, wakers: &mut Vec<Waker>) {
+        self.now += duration;
+
+        let now = self.now;
+
+        // deliver the datagrams in the order of delivery time.
+        self.in_flight.sort_by_key(|datagram| datagram.deliver_at);
+
+        let arrived = self
+            .in_flight
+            .iter()
+            .take_while(|datagram| datagram.deliver_at <= now)
+            .count();
+
+        for datagram in self.in_flight.drain(..arrived).collect::<Vec<_>>() {
+            self.deliver(datagram, wakers);
+        }
+
+        for timer in self.timers.values_mut() {
+            if timer.deadline <= now {
+                wakers.extend(timer.waker.take());
+            }
+        }
+    }
+
+    fn tcp_connect(&mut self, raddr: SocketAddr, wakers: &mut Vec<Waker>) -> io::Result<Token> {
+        let Some(listener_token) = lookup(&self.tcp_listener_addrs, raddr) else {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("mock driver: connection refused, {}", raddr),
+            ));
+        };
+
+        let streams = &self.tcp_streams;
+
+        let laddr = bind_addr(
+            &mut self.next_port,
+            SocketAddr::new(visible(raddr).ip(), 0),
+            |laddr| streams.values().any(|stream| stream.laddr == *laddr),
+        )?;
+
+        let client = Token::next();
+        let server = Token::next();
+
+        let raddr = visible(raddr);
+
+        self.tcp_streams.insert(
+            client,
+            MockTcpStream {
+                laddr,
+                raddr,
+                peer: Some(server),
+                rx: Default::default(),
+                read_closed: false,
+                write_closed: false,
+                waker: None,
+            },
+        );
+
+        self.tcp_streams.insert(
+            server,
+            MockTcpStream {
+                laddr: raddr,
+                raddr: laddr,
+                peer: Some(client),
+                rx: Default::default(),
+                read_closed: false,
+                write_closed: false,
+                waker: None,
+            },
+        );
+
+        let listener = self.tcp_listeners.get_mut(&listener_token).unwrap();
+
+        listener.incoming.push_back((server, laddr));
+
+        wakers.extend(listener.waker.take());
+
+        Ok(client)
+    }
+
+    /// Closes the write direction of stream `token`, returns the waker of peer.
+    fn tcp_shutdown_write(&mut self, token: Token) -> Option<Waker> {
+        let stream = self.tcp_streams.get_mut(&token)?;
+
+        stream.write_closed = true;
+
+        let peer = stream.peer?;
+
+        let peer = self.tcp_streams.get_mut(&peer)?;
+
+        peer.read_closed = true;
+
+        peer.waker.take()
+    }
+}
+
+/// The deterministic in-memory [`Driver`] implementation, see [`module`](self) level document for more information.
+#[derive(Clone)]
+pub struct MockDriver {
+    state: Arc<SpinMutex<MockState>>,
+}
+
+impl Default for MockDriver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<MockDriver> for Driver {
+    fn from(value: MockDriver) -> Self {
+        value.into_raw_driver().into()
+    }
+}
+
+impl MockDriver {
+    /// Create new mock driver with default random seed.
+    pub fn new() -> Self {
+        Self::with_seed(0x2545F4914F6CDD1D)
+    }
+
+    /// Create new mock driver with the `seed` of fault injection generator.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            state: Arc::new(SpinMutex::new(MockState::new(seed))),
+        }
+    }
+
+    /// Set the network fault injection config.
+    pub fn set_network_config(&self, config: MockNetworkConfig) {
+        self.state.lock().config = config;
+    }
+
+    /// Returns the elapsed time of the manual clock.
+    pub fn now(&self) -> Duration {
+        self.state.lock().now
+    }
+
+    /// Advances the manual clock by `duration`, expires timeouts and delivers the delayed datagrams.
+    pub fn advance(&self, duration: Duration) {
+        let mut wakers = vec![];
+
+        self.state.lock().advance(duration, &mut wakers);
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn write_stream(&self, handle: Handle, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock();
+
+        let stream = state
+            .tcp_streams
+            .get(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        let peer = match stream.peer {
+            Some(peer) if !stream.write_closed => peer,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "mock driver: write to closed stream",
+                ))
+            }
+        };
+
+        let peer = state.tcp_streams.get_mut(&peer).unwrap();
+
+        if !peer.read_closed {
+            peer.rx.extend(buf);
+        }
+
+        let waker = peer.waker.take();
+
+        drop(state);
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        Ok(buf.len())
+    }
+
+    fn read_stream(
+        &self,
+        waker: Option<Waker>,
+        handle: Handle,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        let mut state = self.state.lock();
+
+        let stream = state
+            .tcp_streams
+            .get_mut(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        if !stream.rx.is_empty() {
+            let read_size = buf.len().min(stream.rx.len());
+
+            for (to, from) in buf.iter_mut().zip(stream.rx.drain(..read_size)) {
+                *to = from;
+            }
+
+            return Ok(read_size);
+        }
+
+        if stream.read_closed {
+            return Ok(0);
+        }
+
+        // The try operations don't replace the waker of pending task.
+        if waker.is_some() {
+            stream.waker = waker;
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "mock driver: no data to read",
+        ))
+    }
+
+    fn send_datagram(&self, handle: Handle, buf: &[u8], raddr: SocketAddr) -> io::Result<usize> {
+        let mut state = self.state.lock();
+
+        let laddr = state
+            .udp_sockets
+            .get(&handle.token)
+            .ok_or_else(|| not_found(handle))?
+            .laddr;
+
+        if state.random() < state.config.loss_rate {
+            log::trace!("mock driver: drop datagram from {} to {}", laddr, raddr);
+            return Ok(buf.len());
+        }
+
+        let datagram = InFlight {
+            deliver_at: state.now + state.config.latency,
+            from: visible(laddr),
+            to: raddr,
+            data: buf.to_vec(),
+        };
+
+        if state.config.latency.is_zero() {
+            let mut wakers = vec![];
+
+            state.deliver(datagram, &mut wakers);
+
+            drop(state);
+
+            for waker in wakers {
+                waker.wake();
+            }
+        } else {
+            state.in_flight.push(datagram);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn recv_datagram(
+        &self,
+        waker: Option<Waker>,
+        handle: Handle,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr)> {
+        let mut state = self.state.lock();
+
+        let socket = state
+            .udp_sockets
+            .get_mut(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        if let Some((data, from)) = socket.rx.pop_front() {
+            // the excess bytes are discarded like the real udp socket.
+            let read_size = buf.len().min(data.len());
+
+            buf[..read_size].copy_from_slice(&data[..read_size]);
+
+            return Ok((read_size, from));
+        }
+
+        // The try operations don't replace the waker of pending task.
+        if waker.is_some() {
+            socket.waker = waker;
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "mock driver: no datagram to read",
+        ))
+    }
+
+    fn unsupported<R>(&self) -> io::Result<R> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "mock driver: unsupported operation",
+        ))
+    }
+}
+
+impl RawDriverExt for MockDriver {
+    fn fd_user_define_open(&self, _id: usize, _buf: &[u8]) -> io::Result<Handle> {
+        self.unsupported()
+    }
+
+    fn fd_user_define_close(&self, _id: usize, _handle: Handle) -> io::Result<()> {
+        self.unsupported()
+    }
+
+    fn fd_user_define_clone(&self, _handle: Handle) -> io::Result<Handle> {
+        self.unsupported()
+    }
+
+    fn file_open(&self, _path: &str, _mode: FileMode) -> io::Result<Handle> {
+        self.unsupported()
+    }
+
+    fn file_write(&self, _waker: Waker, _handle: Handle, _buf: &[u8]) -> io::Result<usize> {
+        self.unsupported()
+    }
+
+    fn file_read(&self, _waker: Waker, _handle: Handle, _buf: &mut [u8]) -> io::Result<usize> {
+        self.unsupported()
+    }
+
+    fn file_close(&self, _handle: Handle) -> io::Result<()> {
+        self.unsupported()
+    }
+
+    fn timeout_open(&self, duration: Duration) -> io::Result<Handle> {
+        let handle = Handle::new(Description::Timeout, None, None, std::ptr::null());
+
+        let mut state = self.state.lock();
+
+        let deadline = state.now + duration;
+
+        state.timers.insert(
+            handle.token,
+            MockTimer {
+                deadline,
+                waker: None,
+            },
+        );
+
+        Ok(handle)
+    }
+
+    fn timeout(&self, waker: Waker, handle: Handle) -> io::Result<bool> {
+        let mut state = self.state.lock();
+
+        let now = state.now;
+
+        let timer = state
+            .timers
+            .get_mut(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        if timer.deadline <= now {
+            return Ok(true);
+        }
+
+        timer.waker = Some(waker);
+
+        Ok(false)
+    }
+
+    fn timeout_reset(&self, handle: Handle, duration: Duration) -> io::Result<()> {
+        let mut state = self.state.lock();
+
+        let deadline = state.now + duration;
+
+        let timer = state
+            .timers
+            .get_mut(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        timer.deadline = deadline;
+
+        Ok(())
+    }
+
+    fn timeout_close(&self, handle: Handle) -> io::Result<()> {
+        self.state
+            .lock()
+            .timers
+            .remove(&handle.token)
+            .map(|_| ())
+            .ok_or_else(|| not_found(handle))
+    }
+
+    fn tcp_listener_bind(&self, laddrs: &[SocketAddr]) -> io::Result<Handle> {
+        let mut state = self.state.lock();
+
+        let mut last_error = None;
+
+        for laddr in laddrs {
+            let state = &mut *state;
+
+            let listener_addrs = &state.tcp_listener_addrs;
+
+            match bind_addr(&mut state.next_port, *laddr, |laddr| {
+                listener_addrs.contains_key(laddr)
+            }) {
+                Ok(laddr) => {
+                    let handle =
+                        Handle::new(Description::TcpListener, None, None, std::ptr::null());
+
+                    state.tcp_listener_addrs.insert(laddr, handle.token);
+
+                    state.tcp_listeners.insert(
+                        handle.token,
+                        MockTcpListener {
+                            laddr,
+                            incoming: Default::default(),
+                            waker: None,
+                        },
+                    );
+
+                    return Ok(handle);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "laddrs is empty")))
+    }
+
+    fn tcp_listener_accept(
+        &self,
+        waker: Waker,
+        handle: Handle,
+    ) -> io::Result<(Handle, SocketAddr)> {
+        let mut state = self.state.lock();
+
+        let listener = state
+            .tcp_listeners
+            .get_mut(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        if let Some((token, raddr)) = listener.incoming.pop_front() {
+            return Ok((
+                Handle::new(Description::TcpStream, None, Some(token), std::ptr::null()),
+                raddr,
+            ));
+        }
+
+        listener.waker = Some(waker);
+
+        Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "mock driver: no incoming connection",
+        ))
+    }
+
+    fn tcp_listener_close(&self, handle: Handle) -> io::Result<()> {
+        let mut state = self.state.lock();
+
+        let listener = state
+            .tcp_listeners
+            .remove(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        state.tcp_listener_addrs.remove(&listener.laddr);
+
+        Ok(())
+    }
+
+    fn tcp_stream_connect(&self, raddrs: &[SocketAddr]) -> io::Result<Handle> {
+        let mut state = self.state.lock();
+
+        let mut last_error = None;
+
+        for raddr in raddrs {
+            let mut wakers = vec![];
+
+            match state.tcp_connect(*raddr, &mut wakers) {
+                Ok(token) => {
+                    drop(state);
+
+                    for waker in wakers {
+                        waker.wake();
+                    }
+
+                    return Ok(Handle::new(
+                        Description::TcpStream,
+                        None,
+                        Some(token),
+                        std::ptr::null(),
+                    ));
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "raddrs is empty")))
+    }
+
+    fn tcp_stream_write(&self, _waker: Waker, handle: Handle, buf: &[u8]) -> io::Result<usize> {
+        self.write_stream(handle, buf)
+    }
+
+    fn tcp_stream_try_write(&self, handle: Handle, buf: &[u8]) -> io::Result<usize> {
+        self.write_stream(handle, buf)
+    }
+
+    fn tcp_stream_read(&self, waker: Waker, handle: Handle, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_stream(Some(waker), handle, buf)
+    }
+
+    fn tcp_stream_try_read(&self, handle: Handle, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_stream(None, handle, buf)
+    }
+
+    fn tcp_stream_close(&self, handle: Handle) -> io::Result<()> {
+        let mut state = self.state.lock();
+
+        let waker = state.tcp_shutdown_write(handle.token);
+
+        let stream = state
+            .tcp_streams
+            .remove(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        if let Some(peer) = stream
+            .peer
+            .and_then(|peer| state.tcp_streams.get_mut(&peer))
+        {
+            peer.peer = None;
+        }
+
+        drop(state);
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    fn udp_socket_bind(&self, laddrs: &[SocketAddr]) -> io::Result<Handle> {
+        let mut state = self.state.lock();
+
+        let mut last_error = None;
+
+        for laddr in laddrs {
+            let state = &mut *state;
+
+            let udp_addrs = &state.udp_addrs;
+
+            match bind_addr(&mut state.next_port, *laddr, |laddr| {
+                udp_addrs.contains_key(laddr)
+            }) {
+                Ok(laddr) => {
+                    let handle = Handle::new(Description::UdpSocket, None, None, std::ptr::null());
+
+                    state.udp_addrs.insert(laddr, handle.token);
+
+                    state.udp_sockets.insert(
+                        handle.token,
+                        MockUdpSocket {
+                            laddr,
+                            rx: Default::default(),
+                            waker: None,
+                        },
+                    );
+
+                    return Ok(handle);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "laddrs is empty")))
+    }
+
+    fn udp_socket_sendto(
+        &self,
+        _waker: Waker,
+        handle: Handle,
+        buf: &[u8],
+        raddr: SocketAddr,
+    ) -> io::Result<usize> {
+        self.send_datagram(handle, buf, raddr)
+    }
+
+    fn udp_socket_try_sendto(
+        &self,
+        handle: Handle,
+        buf: &[u8],
+        raddr: SocketAddr,
+    ) -> io::Result<usize> {
+        self.send_datagram(handle, buf, raddr)
+    }
+
+    fn udp_socket_recv_from(
+        &self,
+        waker: Waker,
+        handle: Handle,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr)> {
+        self.recv_datagram(Some(waker), handle, buf)
+    }
+
+    fn udp_socket_try_recv_from(
+        &self,
+        handle: Handle,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr)> {
+        self.recv_datagram(None, handle, buf)
+    }
+
+    fn socket_poll_readiness(
+        &self,
+        waker: Waker,
+        handle: Handle,
+        interest: Interest,
+    ) -> io::Result<()> {
+        // The mock sockets have unbounded send buffer, so they are always writable.
+        if !interest.contains(Interest::Readable) {
+            return Ok(());
+        }
+
+        let mut state = self.state.lock();
+
+        let (ready, slot) = match handle.desc {
+            Description::TcpStream => {
+                let stream = state
+                    .tcp_streams
+                    .get_mut(&handle.token)
+                    .ok_or_else(|| not_found(handle))?;
+
+                (
+                    !stream.rx.is_empty() || stream.read_closed,
+                    &mut stream.waker,
+                )
+            }
+            Description::UdpSocket => {
+                let socket = state
+                    .udp_sockets
+                    .get_mut(&handle.token)
+                    .ok_or_else(|| not_found(handle))?;
+
+                (!socket.rx.is_empty(), &mut socket.waker)
+            }
+            _ => return self.unsupported(),
+        };
+
+        if ready {
+            return Ok(());
+        }
+
+        *slot = Some(waker);
+
+        Err(io::Error::new(
+            io::ErrorKind::WouldBlock,
+            "mock driver: socket is not readable",
+        ))
+    }
+
+    fn udp_socket_close(&self, handle: Handle) -> io::Result<()> {
+        let mut state = self.state.lock();
+
+        let socket = state
+            .udp_sockets
+            .remove(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        state.udp_addrs.remove(&socket.laddr);
+
+        Ok(())
+    }
+
+    fn driver_capabilities(&self) -> io::Result<DriverCapabilities> {
+        Ok(DriverCapabilities {
+            backend: Some(Backend::Mock),
+            supports_fd_budget: true,
+            ..Default::default()
+        })
+    }
+
+    fn poller_open(&self, _local: bool) -> io::Result<Handle> {
+        Ok(Handle::new(
+            Description::Poller,
+            None,
+            None,
+            std::ptr::null(),
+        ))
+    }
+
+    fn poller_clone(&self, handle: Handle) -> io::Result<Handle> {
+        handle.expect(Description::Poller)?;
+
+        self.poller_open(false)
+    }
+
+    fn poller_now(&self, handle: Handle) -> io::Result<Instant> {
+        handle.expect(Description::Poller)?;
+
+        let state = self.state.lock();
+
+        Ok(state.start + state.now)
+    }
+
+    fn poller_register(
+        &self,
+        poller: Handle,
+        _source: Handle,
+        _interests: Interest,
+        _mode: PollMode,
+    ) -> io::Result<()> {
+        // The mock driver wakes the waiting tasks directly, the poller is a no-op.
+        poller.expect(Description::Poller)
+    }
+
+    fn poller_reregister(
+        &self,
+        poller: Handle,
+        _source: Handle,
+        _interests: Interest,
+        _mode: PollMode,
+    ) -> io::Result<()> {
+        poller.expect(Description::Poller)
+    }
+
+    fn poller_deregister(&self, poller: Handle, _source: Handle) -> io::Result<()> {
+        poller.expect(Description::Poller)
+    }
+
+    fn poller_poll_once(&self, poller: Handle, duration: Option<Duration>) -> io::Result<()> {
+        poller.expect(Description::Poller)?;
+
+        // Nothing to poll, the manual clock is not affected by this function.
+        // Just don't let the event loop thread spin.
+        std::thread::sleep(
+            duration
+                .unwrap_or(Duration::from_millis(10))
+                .min(Duration::from_millis(10)),
+        );
+
+        Ok(())
+    }
+
+    fn poller_wake(&self, poller: Handle) -> io::Result<()> {
+        // `poller_poll_once` never blocks longer than 10ms.
+        poller.expect(Description::Poller)
+    }
+
+    fn poller_close(&self, poller: Handle) -> io::Result<()> {
+        poller.expect(Description::Poller)
+    }
+
+    fn tcp_listener_local_addr(&self, handle: Handle) -> io::Result<SocketAddr> {
+        self.state
+            .lock()
+            .tcp_listeners
+            .get(&handle.token)
+            .map(|listener| listener.laddr)
+            .ok_or_else(|| not_found(handle))
+    }
+
+    fn tcp_stream_local_addr(&self, handle: Handle) -> io::Result<SocketAddr> {
+        self.state
+            .lock()
+            .tcp_streams
+            .get(&handle.token)
+            .map(|stream| stream.laddr)
+            .ok_or_else(|| not_found(handle))
+    }
+
+    fn tcp_stream_remote_addr(&self, handle: Handle) -> io::Result<SocketAddr> {
+        self.state
+            .lock()
+            .tcp_streams
+            .get(&handle.token)
+            .map(|stream| stream.raddr)
+            .ok_or_else(|| not_found(handle))
+    }
+
+    fn tcp_stream_shutdown(&self, handle: Handle, shutdown: Shutdown) -> io::Result<()> {
+        let mut state = self.state.lock();
+
+        let stream = state
+            .tcp_streams
+            .get_mut(&handle.token)
+            .ok_or_else(|| not_found(handle))?;
+
+        if shutdown != Shutdown::Write {
+            stream.read_closed = true;
+            stream.rx.clear();
+        }
+
+        let waker = if shutdown != Shutdown::Read {
+            state.tcp_shutdown_write(handle.token)
+        } else {
+            None
+        };
+
+        drop(state);
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    fn udp_local_addr(&self, handle: Handle) -> io::Result<SocketAddr> {
+        self.state
+            .lock()
+            .udp_sockets
+            .get(&handle.token)
+            .map(|socket| socket.laddr)
+            .ok_or_else(|| not_found(handle))
+    }
+}
+
+/// Returns the global [`MockDriver`] instance, which is registered to global context on first call.
+#[cfg(feature = "current")]
+pub fn get_mock_driver() -> MockDriver {
+    static MOCK_DRIVER: std::sync::OnceLock<MockDriver> = std::sync::OnceLock::new();
+
+    MOCK_DRIVER
+        .get_or_init(|| {
+            let driver = MockDriver::new();
+
+            crate::current::register_driver(driver.clone()).unwrap();
+
+            driver
+        })
+        .clone()
+}
+
+/// Test runner like [`io_test`](crate::test::io_test), but using the global [`MockDriver`].
+///
+/// Use [`get_mock_driver`] to control the manual clock and network fault injection in the test.
+#[cfg(feature = "current")]
+pub fn mock_test<T, Fut>(label: &'static str, test: T)
+where
+    T: FnOnce() -> Fut + 'static,
+    Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+    get_mock_driver();
+
+    log::trace!("start mock test({})", label);
+
+    crate::current::executor::block_on(test(), 10);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use futures::{executor::block_on, future::poll_fn, task::noop_waker};
+
+    use crate::{now_with, would_block, Cmd, Description, Driver, OpenFlags};
+
+    use super::{MockDriver, MockNetworkConfig};
+
+    fn send_to(driver: &Driver, socket: crate::Handle, buf: &[u8], raddr: SocketAddr) {
+        driver
+            .fd_cntl(
+                socket,
+                Cmd::SendTo {
+                    waker: noop_waker(),
+                    buf,
+                    raddr,
+                },
+            )
+            .unwrap();
+    }
+
+    fn try_recv_from(driver: &Driver, socket: crate::Handle) -> Option<Vec<u8>> {
+        let mut buf = vec![0; 1024];
+
+        match driver.fd_cntl(
+            socket,
+            Cmd::RecvFrom {
+                waker: noop_waker(),
+                buf: &mut buf,
+            },
+        ) {
+            Ok(resp) => {
+                let (read_size, _) = resp.try_into_recv_from().unwrap();
+
+                Some(buf[..read_size].to_vec())
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => None,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn test_udp_latency_and_loss() {
+        let mock = MockDriver::with_seed(1);
+
+        let driver: Driver = mock.clone().into();
+
+        let laddrs = ["127.0.0.1:0".parse().unwrap()];
+
+        let client = driver
+            .fd_open(Description::UdpSocket, OpenFlags::Bind(&laddrs))
+            .unwrap();
+
+        let server = driver
+            .fd_open(Description::UdpSocket, OpenFlags::Bind(&laddrs))
+            .unwrap();
+
+        let raddr = driver
+            .fd_cntl(server, Cmd::LocalAddr)
+            .unwrap()
+            .try_into_sockaddr()
+            .unwrap();
+
+        mock.set_network_config(MockNetworkConfig {
+            latency: Duration::from_millis(100),
+            ..Default::default()
+        });
+
+        send_to(&driver, client, b"hello", raddr);
+
+        assert_eq!(try_recv_from(&driver, server), None);
+
+        mock.advance(Duration::from_millis(100));
+
+        assert_eq!(try_recv_from(&driver, server), Some(b"hello".to_vec()));
+
+        mock.set_network_config(MockNetworkConfig {
+            loss_rate: 1.0,
+            ..Default::default()
+        });
+
+        send_to(&driver, client, b"hello", raddr);
+
+        mock.advance(Duration::from_secs(1));
+
+        assert_eq!(try_recv_from(&driver, server), None);
+    }
+
+    #[test]
+    fn test_deterministic_reorder() {
+        let run = |seed| {
+            let mock = MockDriver::with_seed(seed);
+
+            let driver: Driver = mock.clone().into();
+
+            let laddrs = ["127.0.0.1:0".parse().unwrap()];
+
+            let socket = driver
+                .fd_open(Description::UdpSocket, OpenFlags::Bind(&laddrs))
+                .unwrap();
+
+            let raddr = driver
+                .fd_cntl(socket, Cmd::LocalAddr)
+                .unwrap()
+                .try_into_sockaddr()
+                .unwrap();
+
+            mock.set_network_config(MockNetworkConfig {
+                reorder_rate: 0.5,
+                ..Default::default()
+            });
+
+            for i in 0..10u8 {
+                send_to(&driver, socket, &[i], raddr);
+            }
+
+            std::iter::from_fn(|| try_recv_from(&driver, socket))
+                .flatten()
+                .collect::<Vec<_>>()
+        };
+
+        let packets = run(10);
+
+        assert_eq!(packets.len(), 10);
+        assert_ne!(packets, (0..10u8).collect::<Vec<_>>());
+        assert_eq!(packets, run(10));
+    }
+
+    #[test]
+    fn test_tcp_and_timeout() {
+        let mock = MockDriver::new();
+
+        let driver: Driver = mock.clone().into();
+
+        let laddrs = ["127.0.0.1:0".parse().unwrap()];
+
+        let listener = driver
+            .fd_open(Description::TcpListener, OpenFlags::Bind(&laddrs))
+            .unwrap();
+
+        let raddr = driver
+            .fd_cntl(listener, Cmd::LocalAddr)
+            .unwrap()
+            .try_into_sockaddr()
+            .unwrap();
+
+        let raddrs = [raddr];
+
+        let client = driver
+            .fd_open(Description::TcpStream, OpenFlags::Connect(&raddrs))
+            .unwrap();
+
+        block_on(async {
+            let (server, _) =
+                would_block(|cx| driver.fd_cntl(listener, Cmd::Accept(cx.waker().clone())))
+                    .await
+                    .unwrap()
+                    .try_into_incoming()
+                    .unwrap();
+
+            driver
+                .fd_cntl(
+                    client,
+                    Cmd::Write {
+                        waker: noop_waker(),
+                        buf: b"hello",
+                    },
+                )
+                .unwrap();
+
+            driver
+                .fd_cntl(client, Cmd::Shutdown(std::net::Shutdown::Write))
+                .unwrap();
+
+            let mut buf = vec![0; 1024];
+
+            let read_size = would_block(|cx| {
+                driver.fd_cntl(
+                    server,
+                    Cmd::Read {
+                        waker: cx.waker().clone(),
+                        buf: &mut buf,
+                    },
+                )
+            })
+            .await
+            .unwrap()
+            .try_into_datalen()
+            .unwrap();
+
+            assert_eq!(&buf[..read_size], b"hello");
+
+            // EOF
+            let read_size = would_block(|cx| {
+                driver.fd_cntl(
+                    server,
+                    Cmd::Read {
+                        waker: cx.waker().clone(),
+                        buf: &mut buf,
+                    },
+                )
+            })
+            .await
+            .unwrap()
+            .try_into_datalen()
+            .unwrap();
+
+            assert_eq!(read_size, 0);
+        });
+
+        let timer = driver
+            .fd_open(
+                Description::Timeout,
+                OpenFlags::Duration(Duration::from_secs(10)),
+            )
+            .unwrap();
+
+        let poll_timeout = |cx: &mut Context<'_>| {
+            driver
+                .fd_cntl(timer, Cmd::Timeout(cx.waker().clone()))
+                .unwrap()
+                .try_into_timeout()
+                .unwrap()
+        };
+
+        assert!(!block_on(poll_fn(|cx| Poll::Ready(poll_timeout(cx)))));
+
+        mock.advance(Duration::from_secs(10));
+
+        assert!(block_on(poll_fn(|cx| Poll::Ready(poll_timeout(cx)))));
+
+        assert_eq!(mock.now(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_clock() {
+        let mock = MockDriver::new();
+
+        let driver: Driver = mock.clone().into();
+
+        let poller = driver
+            .fd_open(Description::Poller, OpenFlags::None)
+            .unwrap();
+
+        let start = now_with(&driver, poller).unwrap();
+
+        mock.advance(Duration::from_secs(5));
+
+        assert_eq!(
+            now_with(&driver, poller).unwrap() - start,
+            Duration::from_secs(5)
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fmt::Debug, io, marker::PhantomData, mem::ManuallyDrop, ops::Deref};
+
+use crate::{Cmd, CmdResp, Description, Driver, Handle, Interest, OpenFlags, PollMode};
+
+/// The [`Handle`] owned with its driver, which is closed by the driver on drop.
+///
+/// Unlike the copyable [`Handle`], the owner can't close the handle twice or leak it on early return,
+/// the borrowed [`BorrowedHandle`] is passed to the commands, e.g. [`Cmd::Register`].
+pub struct OwnedHandle {
+    handle: Handle,
+    driver: Driver,
+}
+
+impl Debug for OwnedHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OwnedHandle({:?})", self.handle)
+    }
+}
+
+impl OwnedHandle {
+    /// Opens the file description by `driver`, the handle is owned by the returned instance.
+    pub fn open(driver: Driver, desc: Description, open_flags: OpenFlags) -> io::Result<Self> {
+        let handle = driver.fd_open(desc, open_flags)?;
+
+        Ok(Self { handle, driver })
+    }
+
+    /// Takes the ownership of the `handle` opened by `driver`.
+    ///
+    /// # Safety
+    ///
+    /// The `handle` must be opened by `driver` and not be closed by others, e.g. the handle
+    /// returned by [`Cmd::Accept`], otherwise the handle is closed twice.
+    pub unsafe fn from_raw(driver: Driver, handle: Handle) -> Self {
+        Self { handle, driver }
+    }
+
+    /// Borrows the handle, which is valid while this instance is alive.
+    pub fn as_handle(&self) -> BorrowedHandle<'_> {
+        BorrowedHandle {
+            handle: self.handle,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the driver which owns the handle.
+    pub fn driver(&self) -> &Driver {
+        &self.driver
+    }
+
+    /// Performs one of file description operation of the handle, see [`Driver::fd_cntl`].
+    pub fn fd_cntl(&self, cmd: Cmd) -> io::Result<CmdResp> {
+        self.driver.fd_cntl(self.handle, cmd)
+    }
+
+    /// Registers the handle to `poller` with `interests`, see [`Cmd::Register`].
+    pub fn register(&self, poller: Handle, interests: Interest, mode: PollMode) -> io::Result<()> {
+        self.driver.fd_cntl(
+            poller,
+            Cmd::Register {
+                source: self.handle,
+                interests,
+                mode,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Deregisters the handle from `poller`, see [`Cmd::Deregister`].
+    pub fn deregister(&self, poller: Handle) -> io::Result<()> {
+        self.driver.fd_cntl(poller, Cmd::Deregister(self.handle))?;
+
+        Ok(())
+    }
+
+    /// Releases the ownership, the returned handle is not closed on drop.
+    pub fn into_raw(self) -> Handle {
+        let (_, handle) = self.into_parts();
+
+        handle
+    }
+
+    /// Closes the handle and returns the error, which is logged if the handle is closed on drop.
+    pub fn close(self) -> io::Result<()> {
+        let (driver, handle) = self.into_parts();
+
+        #[allow(deprecated)]
+        driver.fd_close(handle)
+    }
+
+    fn into_parts(self) -> (Driver, Handle) {
+        let this = ManuallyDrop::new(self);
+
+        // Safety: `this` is not dropped, so the driver is moved out once.
+        let driver = unsafe { std::ptr::read(&this.driver) };
+
+        (driver, this.handle)
+    }
+}
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        #[allow(deprecated)]
+        if let Err(err) = self.driver.fd_close(self.handle) {
+            log::error!("close {:?} failed, err={}", self.handle, err);
+        }
+    }
+}
+
+/// The [`Handle`] borrowed from [`OwnedHandle`], which can't outlive the owner.
+#[derive(Debug, Clone, Copy)]
+pub struct BorrowedHandle<'a> {
+    handle: Handle,
+    _marker: PhantomData<&'a OwnedHandle>,
+}
+
+impl<'a> Deref for BorrowedHandle<'a> {
+    type Target = Handle;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
+#[cfg(all(test, feature = "mio-driver"))]
+mod tests {
+    use std::net::SocketAddr;
+
+    use crate::{mio::mio_driver, Cmd, Description, OpenFlags};
+
+    use super::*;
+
+    fn open_udp(driver: &Driver) -> OwnedHandle {
+        let laddrs = ["127.0.0.1:0".parse::<SocketAddr>().unwrap()];
+
+        OwnedHandle::open(
+            driver.clone(),
+            Description::UdpSocket,
+            OpenFlags::Bind(&laddrs),
+        )
+        .unwrap()
+    }
+
+    fn open_udp_sockets(poller: &OwnedHandle) -> usize {
+        poller
+            .fd_cntl(Cmd::Stats)
+            .unwrap()
+            .try_into_stats()
+            .unwrap()
+            .open_fds_of(Description::UdpSocket)
+    }
+
+    #[test]
+    fn test_owned_handle() {
+        let driver = mio_driver();
+
+        let poller =
+            OwnedHandle::open(driver.clone(), Description::Poller, OpenFlags::None).unwrap();
+
+        let socket = open_udp(&driver);
+
+        assert_eq!(open_udp_sockets(&poller), 1);
+
+        // Closed on drop.
+        drop(socket);
+
+        assert_eq!(open_udp_sockets(&poller), 0);
+
+        // The released handle is closed by the new owner.
+        let handle = open_udp(&driver).into_raw();
+
+        assert_eq!(open_udp_sockets(&poller), 1);
+
+        let socket = unsafe { OwnedHandle::from_raw(driver, handle) };
+
+        socket.close().unwrap();
+
+        assert_eq!(open_udp_sockets(&poller), 0);
+    }
+}
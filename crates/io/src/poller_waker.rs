@@ -0,0 +1,98 @@
+use std::io;
+
+#[cfg(feature = "current")]
+use crate::current::{get_driver, get_poller};
+
+use crate::{Cmd, Description, Driver, Handle};
+
+/// The user notification handle of poller, which wakes up the thread blocking in
+/// [`PollOnce`](Cmd::PollOnce) from any thread.
+///
+/// It's used to integrate the non-io events (e.g. GUI event queues, channel notifications)
+/// into the event loop, the event loop checks these events after `PollOnce` returns.
+#[derive(Clone)]
+pub struct PollerWaker {
+    driver: Driver,
+    poller: Handle,
+}
+
+impl PollerWaker {
+    /// Create a waker of the global context poller.
+    #[cfg(feature = "current")]
+    pub fn new() -> io::Result<Self> {
+        Self::new_with(get_driver()?, get_poller()?)
+    }
+
+    /// Create a waker of `poller` with providing `driver`.
+    ///
+    /// The waker doesn't own the `poller`, waking a closed poller is undefined.
+    pub fn new_with(driver: Driver, poller: Handle) -> io::Result<Self> {
+        poller.expect(Description::Poller)?;
+
+        Ok(Self { driver, poller })
+    }
+
+    /// Wakes up the poller, if no thread is polling, the next poll returns immediately.
+    pub fn wake(&self) -> io::Result<()> {
+        self.driver
+            .fd_cntl(self.poller, Cmd::WakePoller)
+            .map(|_| ())
+    }
+}
+
+#[cfg(all(test, feature = "mio-driver"))]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{mio::mio_driver, OpenFlags};
+
+    use super::*;
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_wake_poller() {
+        let driver = mio_driver();
+
+        let poller = driver
+            .fd_open(Description::Poller, OpenFlags::None)
+            .unwrap();
+
+        let waker = PollerWaker::new_with(driver.clone(), poller).unwrap();
+
+        let now = Instant::now();
+
+        let handle = {
+            let driver = driver.clone();
+
+            thread::spawn(move || {
+                driver
+                    .fd_cntl(poller, Cmd::PollOnce(Some(Duration::from_secs(10))))
+                    .unwrap();
+            })
+        };
+
+        thread::sleep(Duration::from_millis(20));
+
+        waker.wake().unwrap();
+
+        handle.join().unwrap();
+
+        assert!(now.elapsed() < Duration::from_secs(5));
+
+        // Wake before polling, the next poll returns immediately.
+        waker.wake().unwrap();
+
+        let now = Instant::now();
+
+        driver
+            .fd_cntl(poller, Cmd::PollOnce(Some(Duration::from_secs(10))))
+            .unwrap();
+
+        assert!(now.elapsed() < Duration::from_secs(5));
+
+        driver.fd_close(poller).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures::{ready, AsyncRead, AsyncWrite};
+use hala_sync::{Lockable, LockableNew, SpinMutex};
+
+use crate::current::{get_driver, get_poller};
+
+use super::{Driver, Handle, Sleep};
+
+struct Bucket {
+    /// The available tokens, negative value means the debt of over-acquired tokens.
+    tokens: f64,
+    /// The last refill time.
+    last: Instant,
+}
+
+/// Token bucket rate limiter, the bucket is refilled at `rate` tokens per second
+/// and holds at most `burst` tokens.
+///
+/// The acquirer that exceeds the available tokens is suspended by the driver's timeout handle
+/// until the debt is repaid, so one acquisition can be larger than the `burst`.
+pub struct RateLimiter {
+    driver: Driver,
+    poller: Handle,
+    rate: f64,
+    burst: f64,
+    bucket: SpinMutex<Bucket>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter with `rate` tokens per second and `burst` capacity, the bucket is initially full.
+    pub fn new(rate: u64, burst: u64) -> io::Result<Self> {
+        Ok(Self::new_with(get_driver()?, get_poller()?, rate, burst))
+    }
+
+    /// Create a rate limiter with providing `driver` / `poller`, see [`new`](Self::new) for more information.
+    pub fn new_with(driver: Driver, poller: Handle, rate: u64, burst: u64) -> Self {
+        assert!(rate > 0, "`rate` must be non-zero.");
+
+        Self {
+            driver,
+            poller,
+            rate: rate as f64,
+            burst: burst as f64,
+            bucket: SpinMutex::new(Bucket {
+                tokens: burst as f64,
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    /// Consumes `n` tokens immediately, and returns the duration the caller should wait
+    /// before the consumed tokens are actually available.
+    pub fn reserve(&self, n: u64) -> Duration {
+        let mut bucket = self.bucket.lock();
+
+        let now = Instant::now();
+
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst) - n as f64;
+        bucket.last = now;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+
+    /// Acquires `n` tokens, waits until they are available.
+    pub async fn acquire(&self, n: u64) -> io::Result<()> {
+        match self.delay(n)? {
+            Some(sleep) => sleep.await,
+            None => Ok(()),
+        }
+    }
+
+    /// Consumes `n` tokens, returns the [`Sleep`] future if the caller should wait.
+    fn delay(&self, n: u64) -> io::Result<Option<Sleep>> {
+        let duration = self.reserve(n);
+
+        if duration.is_zero() {
+            return Ok(None);
+        }
+
+        Sleep::new_with(self.driver.clone(), self.poller, duration).map(Some)
+    }
+}
+
+/// Wrapper of [`AsyncRead`] / [`AsyncWrite`] stream, whose bandwidth is limited by [`RateLimiter`].
+///
+/// The transferred bytes are paid after each read / write, and the next read / write
+/// is suspended until the debt is repaid.
+pub struct RateLimitedStream<S> {
+    stream: S,
+    limiter: Arc<RateLimiter>,
+    read_delay: Option<Sleep>,
+    write_delay: Option<Sleep>,
+}
+
+impl<S> RateLimitedStream<S> {
+    /// Wraps `stream` with `limiter`, the limiter can be shared by multiple streams.
+    pub fn new(stream: S, limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            stream,
+            limiter,
+            read_delay: None,
+            write_delay: None,
+        }
+    }
+
+    /// Returns the reference of the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns the mutable reference of the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this wrapper, returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+fn poll_delay(delay: &mut Option<Sleep>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+    if let Some(sleep) = delay {
+        ready!(Pin::new(sleep).poll(cx))?;
+
+        *delay = None;
+    }
+
+    Poll::Ready(Ok(()))
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for RateLimitedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        ready!(poll_delay(&mut this.read_delay, cx))?;
+
+        let read_size = ready!(Pin::new(&mut this.stream).poll_read(cx, buf))?;
+
+        this.read_delay = this.limiter.delay(read_size as u64)?;
+
+        Poll::Ready(Ok(read_size))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for RateLimitedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        ready!(poll_delay(&mut this.write_delay, cx))?;
+
+        let write_size = ready!(Pin::new(&mut this.stream).poll_write(cx, buf))?;
+
+        this.write_delay = this.limiter.delay(write_size as u64)?;
+
+        Poll::Ready(Ok(write_size))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
+
+#[cfg(all(test, feature = "mio-driver"))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use futures::{io::Cursor, AsyncReadExt, AsyncWriteExt};
+
+    use crate::test::io_test;
+
+    use super::*;
+
+    #[hala_test::test(io_test)]
+    async fn test_acquire() {
+        let limiter = RateLimiter::new(1000, 100).unwrap();
+
+        let start = Instant::now();
+
+        // the burst is available immediately.
+        limiter.acquire(100).await.unwrap();
+
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        limiter.acquire(100).await.unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_rate_limited_stream() {
+        let limiter = Arc::new(RateLimiter::new(10_000, 1000).unwrap());
+
+        let start = Instant::now();
+
+        let mut writer = RateLimitedStream::new(Cursor::new(vec![]), limiter.clone());
+
+        for _ in 0..3 {
+            writer.write_all(&[1; 1000]).await.unwrap();
+        }
+
+        // the first write consumes the burst, the second write is paid after writing,
+        // so only the third write is delayed.
+        assert!(start.elapsed() >= Duration::from_millis(90));
+
+        let mut reader =
+            RateLimitedStream::new(Cursor::new(writer.into_inner().into_inner()), limiter);
+
+        let mut buf = vec![];
+
+        reader.read_to_end(&mut buf).await.unwrap();
+
+        assert_eq!(buf.len(), 3000);
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{future::poll_fn, Stream};
+
+use crate::current::{get_driver, get_poller};
+
+use super::{Cmd, Description, Driver, Handle, Interest, OpenFlags, OwnedHandle, PollMode};
+
+/// The kind of os signal, see [`signal`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SignalKind {
+    /// `SIGINT`, e.g. the user presses `Ctrl-C`.
+    Interrupt,
+    /// `SIGTERM`, the request to terminate the process.
+    Terminate,
+    /// `SIGHUP`, the controlling terminal is closed.
+    Hangup,
+    /// `SIGQUIT`
+    Quit,
+    /// `SIGUSR1`
+    User1,
+    /// `SIGUSR2`
+    User2,
+    /// `SIGCHLD`, a child process has stopped or exited.
+    Child,
+    /// `SIGWINCH`, the terminal window size is changed.
+    WindowChange,
+    /// `SIGPIPE`
+    Pipe,
+    /// The raw signal number of unix platforms.
+    Raw(i32),
+}
+
+/// Stream that yields the delivered signals of one [`SignalKind`], created by [`signal`].
+///
+/// The signals delivered before the first poll are also yielded, but the signals delivered
+/// at the same time may be coalesced by os.
+pub struct Signal {
+    fd: OwnedHandle,
+    poller: Handle,
+    kind: SignalKind,
+    /// The number of taken signals that are not yielded.
+    pending: usize,
+}
+
+impl Signal {
+    /// Create a [`Signal`] stream with providing `driver` / `poller`.
+    pub fn new_with(driver: Driver, poller: Handle, kind: SignalKind) -> io::Result<Self> {
+        let fd = OwnedHandle::open(driver, Description::Signal, OpenFlags::Signal(kind))?;
+
+        fd.register(poller, Interest::Readable, PollMode::Edge)?;
+
+        Ok(Self {
+            fd,
+            poller,
+            kind,
+            pending: 0,
+        })
+    }
+
+    /// Returns the signal kind of this stream.
+    pub fn kind(&self) -> SignalKind {
+        self.kind
+    }
+
+    /// Waits for the next signal.
+    pub async fn recv(&mut self) -> io::Result<()> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls for the next signal.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.pending == 0 {
+            let count = match self.fd.fd_cntl(Cmd::PollSignal(cx.waker().clone())) {
+                Ok(resp) => resp.try_into_signal()?,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(err) => return Poll::Ready(Err(err)),
+            };
+
+            self.pending = count;
+        }
+
+        self.pending = self.pending.saturating_sub(1);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for Signal {
+    type Item = io::Result<SignalKind>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let kind = self.kind;
+
+        self.poll_recv(cx).map(|r| Some(r.map(|_| kind)))
+    }
+}
+
+impl Drop for Signal {
+    fn drop(&mut self) {
+        if let Err(err) = self.fd.deregister(self.poller) {
+            log::error!("deregister signal failed, fd={:?}, err={}", self.fd, err);
+        }
+    }
+}
+
+/// Create new [`Signal`] stream that yields the delivered signals of `kind`.
+///
+/// Once the first listener of `kind` is created, the default action of the signal
+/// (e.g. terminating the process) is replaced for the rest of process lifetime.
+pub fn signal(kind: SignalKind) -> io::Result<Signal> {
+    Signal::new_with(get_driver()?, get_poller()?, kind)
+}
+
+#[cfg(all(test, feature = "mio-driver", unix))]
+mod tests {
+    use futures::StreamExt;
+
+    use crate::test::io_test;
+
+    use super::*;
+
+    #[hala_test::test(io_test)]
+    async fn test_signal() {
+        let mut first = signal(SignalKind::User1).unwrap();
+        let mut second = signal(SignalKind::User1).unwrap();
+
+        // Safety: the handler of SIGUSR1 is installed by `signal`.
+        assert_eq!(unsafe { libc::raise(libc::SIGUSR1) }, 0);
+
+        // all listeners of the same signal are notified.
+        assert_eq!(first.next().await.unwrap().unwrap(), SignalKind::User1);
+
+        second.recv().await.unwrap();
+    }
+}
@@ -2,16 +2,18 @@ use std::{
     future::Future,
     io,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::current::{get_driver, get_poller};
 
-use super::{Cmd, Description, Driver, Handle, Interest, OpenFlags};
+use super::{
+    now_with, Cmd, Description, Driver, Handle, Interest, OpenFlags, OwnedHandle, PollMode,
+};
 
 /// Future type to suspend current task for a while
 pub struct Sleep {
-    fd: Option<Handle>,
+    fd: Option<OwnedHandle>,
     driver: Driver,
     expired: Duration,
     poller: Handle,
@@ -35,25 +37,21 @@ impl Future for Sleep {
     fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         // first time, create timeout fd
         if self.fd.is_none() {
-            let fd = match self
-                .driver
-                .fd_open(Description::Timeout, OpenFlags::Duration(self.expired))
-            {
+            let fd = match OwnedHandle::open(
+                self.driver.clone(),
+                Description::Timeout,
+                OpenFlags::Duration(self.expired),
+            ) {
                 Err(err) => return Poll::Ready(Err(err)),
                 Ok(fd) => fd,
             };
 
-            self.fd = Some(fd);
+            let poller = self.poller;
 
-            match self.driver.fd_cntl(
-                self.poller,
-                Cmd::Register {
-                    source: fd,
-                    interests: Interest::Readable,
-                },
-            ) {
-                Err(err) => return Poll::Ready(Err(err)),
-                _ => {}
+            let fd = self.fd.insert(fd);
+
+            if let Err(err) = fd.register(poller, Interest::Readable, PollMode::Edge) {
+                return Poll::Ready(Err(err));
             }
 
             log::trace!("create timeout {:?}", fd);
@@ -61,8 +59,10 @@ impl Future for Sleep {
 
         // try check status of timeout fd
         match self
-            .driver
-            .fd_cntl(self.fd.unwrap(), Cmd::Timeout(cx.waker().clone()))
+            .fd
+            .as_ref()
+            .unwrap()
+            .fd_cntl(Cmd::Timeout(cx.waker().clone()))
         {
             Ok(resp) => match resp.try_into_timeout() {
                 Ok(status) => {
@@ -85,11 +85,9 @@ impl Future for Sleep {
 impl Drop for Sleep {
     fn drop(&mut self) {
         if let Some(fd) = self.fd.take() {
-            self.driver
-                .fd_cntl(self.poller, Cmd::Deregister(fd))
-                .unwrap();
-
-            self.driver.fd_close(fd).unwrap();
+            if let Err(err) = fd.deregister(self.poller) {
+                log::error!("deregister sleep timer failed, fd={:?}, err={}", fd, err);
+            }
         }
     }
 }
@@ -102,3 +100,16 @@ pub async fn sleep(duration: Duration) -> io::Result<()> {
 pub async fn sleep_with(driver: Driver, poller: Handle, duration: Duration) -> io::Result<()> {
     Sleep::new_with(driver, poller, duration)?.await
 }
+
+/// Sleep until `deadline` is reached.
+///
+/// The `deadline` is measured by the driver clock, see [`now`](crate::now).
+pub async fn sleep_until(deadline: Instant) -> io::Result<()> {
+    sleep_until_with(get_driver()?, get_poller()?, deadline).await
+}
+
+pub async fn sleep_until_with(driver: Driver, poller: Handle, deadline: Instant) -> io::Result<()> {
+    let now = now_with(&driver, poller)?;
+
+    sleep_with(driver, poller, deadline.saturating_duration_since(now)).await
+}
@@ -0,0 +1,682 @@
+//! The asynchronous handles of the standard input / output / error of current process.
+//!
+//! On unix platforms, the stdio is duplicated and registered with the poller, so it's driven by the driver.
+//! Setting the non-blocking mode affects the file description shared with other processes (e.g. the shell),
+//! so the original mode is restored after the last handle of the stdio is dropped. Don't write to the
+//! stdio by the std library (e.g. `println!`) while the handle is alive, which may fail with `WouldBlock`.
+//!
+//! The stdio that can't be registered (e.g. redirected to regular file), or on other platforms,
+//! is served by a dedicated thread instead.
+
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    pin::Pin,
+    task::{ready, Context, Poll},
+    thread,
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    executor::block_on,
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, FutureExt, SinkExt,
+    StreamExt,
+};
+
+#[cfg(feature = "current")]
+use crate::current::{get_driver, get_poller};
+
+use crate::{Driver, Handle};
+
+/// The size of read buffer of [`Stdin`], and the chunk size of the reading thread.
+const STDIN_BUFFER_SIZE: usize = 8 * 1024;
+
+#[cfg(all(unix, feature = "mio-driver"))]
+mod driver {
+    use std::{
+        io,
+        os::fd::RawFd,
+        sync::Mutex,
+        task::{Context, Poll},
+    };
+
+    use crate::{
+        poll_would_block, Cmd, Description, Driver, Handle, Interest, OpenFlags, OwnedHandle,
+        PollMode,
+    };
+
+    /// The original file status flags of the stdio registered with the poller, and the number of alive handles.
+    static ORIGINAL_FLAGS: Mutex<Vec<(RawFd, usize, libc::c_int)>> = Mutex::new(Vec::new());
+
+    fn restore_flags(raw_fd: RawFd, flags: libc::c_int) {
+        // Safety: the `raw_fd` is the stdio of the process, which is never closed.
+        if unsafe { libc::fcntl(raw_fd, libc::F_SETFL, flags) } < 0 {
+            log::error!(
+                "restore stdio flags failed, fd={}, err={}",
+                raw_fd,
+                io::Error::last_os_error()
+            );
+        }
+    }
+
+    /// The duplicated stdio registered with the poller.
+    pub(super) struct DriverStdio {
+        raw_fd: RawFd,
+        fd: OwnedHandle,
+        poller: Handle,
+    }
+
+    impl DriverStdio {
+        /// Duplicates `raw_fd` and registers it with `poller`, the `desc` is either `PipeReceiver` or `PipeSender`.
+        pub(super) fn open(
+            raw_fd: RawFd,
+            desc: Description,
+            driver: Driver,
+            poller: Handle,
+        ) -> io::Result<Self> {
+            let mut original_flags = ORIGINAL_FLAGS.lock().unwrap();
+
+            let index = match original_flags.iter().position(|(fd, _, _)| *fd == raw_fd) {
+                Some(index) => index,
+                None => {
+                    // Safety: `F_GETFL` doesn't change the state of `raw_fd`.
+                    let flags = unsafe { libc::fcntl(raw_fd, libc::F_GETFL) };
+
+                    if flags < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+
+                    original_flags.push((raw_fd, 0, flags));
+
+                    original_flags.len() - 1
+                }
+            };
+
+            let result = Self::register(raw_fd, desc, driver, poller);
+
+            let (_, count, flags) = &mut original_flags[index];
+
+            match result {
+                Ok(fd) => {
+                    *count += 1;
+
+                    Ok(Self { raw_fd, fd, poller })
+                }
+                Err(err) => {
+                    if *count == 0 {
+                        restore_flags(raw_fd, *flags);
+                        original_flags.remove(index);
+                    }
+
+                    Err(err)
+                }
+            }
+        }
+
+        fn register(
+            raw_fd: RawFd,
+            desc: Description,
+            driver: Driver,
+            poller: Handle,
+        ) -> io::Result<OwnedHandle> {
+            // Safety: `F_DUPFD_CLOEXEC` creates a new fd, whose ownership is transferred to driver.
+            let dup_fd = unsafe { libc::fcntl(raw_fd, libc::F_DUPFD_CLOEXEC, 0) };
+
+            if dup_fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let fd = OwnedHandle::open(driver, desc, OpenFlags::RawFd(dup_fd))?;
+
+            let interests = if desc == Description::PipeSender {
+                Interest::Writable
+            } else {
+                Interest::Readable
+            };
+
+            // The handle is closed on error.
+            fd.register(poller, interests, PollMode::Edge)?;
+
+            Ok(fd)
+        }
+
+        pub(super) fn poll_read(
+            &self,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            poll_would_block(|| {
+                self.fd
+                    .fd_cntl(Cmd::Read {
+                        waker: cx.waker().clone(),
+                        buf,
+                    })?
+                    .try_into_datalen()
+            })
+        }
+
+        pub(super) fn poll_write(
+            &self,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            poll_would_block(|| {
+                self.fd
+                    .fd_cntl(Cmd::Write {
+                        waker: cx.waker().clone(),
+                        buf,
+                    })?
+                    .try_into_datalen()
+            })
+        }
+    }
+
+    impl Drop for DriverStdio {
+        fn drop(&mut self) {
+            if let Err(err) = self.fd.deregister(self.poller) {
+                log::error!("deregister stdio failed, fd={:?}, err={}", self.fd, err);
+            }
+
+            let mut original_flags = ORIGINAL_FLAGS.lock().unwrap();
+
+            if let Some(index) = original_flags
+                .iter()
+                .position(|(fd, _, _)| *fd == self.raw_fd)
+            {
+                let (_, count, flags) = &mut original_flags[index];
+
+                *count -= 1;
+
+                if *count == 0 {
+                    restore_flags(self.raw_fd, *flags);
+                    original_flags.remove(index);
+                }
+            }
+        }
+    }
+}
+
+/// Spawns the thread reading `reader` by chunks, the empty chunk means EOF.
+fn spawn_reader<R: Read + Send + 'static>(
+    name: &str,
+    mut reader: R,
+) -> io::Result<mpsc::Receiver<io::Result<Vec<u8>>>> {
+    let (mut sender, receiver) = mpsc::channel(0);
+
+    thread::Builder::new()
+        .name(name.to_owned())
+        .spawn(move || loop {
+            let mut buf = vec![0; STDIN_BUFFER_SIZE];
+
+            let result = match reader.read(&mut buf) {
+                Ok(read_size) => {
+                    buf.truncate(read_size);
+                    Ok(buf)
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => Err(err),
+            };
+
+            let eof = !matches!(&result, Ok(buf) if !buf.is_empty());
+
+            // Exits if the receiver is dropped, the thread may be blocked in the next `read` until then.
+            if block_on(sender.send(result)).is_err() || eof {
+                return;
+            }
+        })?;
+
+    Ok(receiver)
+}
+
+enum WriteMsg {
+    Data(Vec<u8>),
+    Flush(oneshot::Sender<io::Result<()>>),
+}
+
+/// The writer served by a dedicated thread, the write error is returned by the next flush.
+struct ThreadWriter {
+    sender: mpsc::Sender<WriteMsg>,
+    flushing: Option<oneshot::Receiver<io::Result<()>>>,
+}
+
+impl ThreadWriter {
+    fn spawn<W: Write + Send + 'static>(name: &str, mut writer: W) -> io::Result<Self> {
+        let (sender, mut receiver) = mpsc::channel(0);
+
+        thread::Builder::new()
+            .name(name.to_owned())
+            .spawn(move || {
+                let mut last_error = None;
+
+                while let Some(msg) = block_on(receiver.next()) {
+                    match msg {
+                        WriteMsg::Data(data) => {
+                            if let Err(err) = writer.write_all(&data) {
+                                last_error = Some(err);
+                            }
+                        }
+                        WriteMsg::Flush(ack) => {
+                            let result = match last_error.take() {
+                                Some(err) => Err(err),
+                                None => writer.flush(),
+                            };
+
+                            _ = ack.send(result);
+                        }
+                    }
+                }
+
+                _ = writer.flush();
+            })?;
+
+        Ok(Self {
+            sender,
+            flushing: None,
+        })
+    }
+
+    fn broken_pipe() -> io::Error {
+        io::Error::new(io::ErrorKind::BrokenPipe, "stdio thread exited")
+    }
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.sender.poll_ready(cx)).map_err(|_| Self::broken_pipe())?;
+
+        self.sender
+            .start_send(WriteMsg::Data(buf.to_vec()))
+            .map_err(|_| Self::broken_pipe())?;
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.flushing.is_none() {
+            ready!(self.sender.poll_ready(cx)).map_err(|_| Self::broken_pipe())?;
+
+            let (ack, flushing) = oneshot::channel();
+
+            self.sender
+                .start_send(WriteMsg::Flush(ack))
+                .map_err(|_| Self::broken_pipe())?;
+
+            self.flushing = Some(flushing);
+        }
+
+        let result = ready!(self.flushing.as_mut().unwrap().poll_unpin(cx));
+
+        self.flushing = None;
+
+        Poll::Ready(result.unwrap_or_else(|_| Err(Self::broken_pipe())))
+    }
+}
+
+enum Reader {
+    #[cfg(all(unix, feature = "mio-driver"))]
+    Driver(driver::DriverStdio),
+    Thread(mpsc::Receiver<io::Result<Vec<u8>>>),
+}
+
+enum Writer {
+    #[cfg(all(unix, feature = "mio-driver"))]
+    Driver(driver::DriverStdio),
+    Thread(ThreadWriter),
+}
+
+impl Writer {
+    #[allow(unused_variables)]
+    fn open<W: Write + Send + 'static>(
+        name: &str,
+        writer: W,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        #[cfg(all(unix, feature = "mio-driver"))]
+        {
+            use std::os::fd::AsRawFd;
+
+            // The `W` is either `io::Stdout` or `io::Stderr`.
+            let raw_fd = if name == "hala-stdout" {
+                io::stdout().as_raw_fd()
+            } else {
+                io::stderr().as_raw_fd()
+            };
+
+            match driver::DriverStdio::open(raw_fd, crate::Description::PipeSender, driver, poller)
+            {
+                Ok(stdio) => return Ok(Writer::Driver(stdio)),
+                Err(err) => log::trace!("{} fallback to thread, err={}", name, err),
+            }
+        }
+
+        ThreadWriter::spawn(name, writer).map(Writer::Thread)
+    }
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self {
+            #[cfg(all(unix, feature = "mio-driver"))]
+            Writer::Driver(stdio) => stdio.poll_write(cx, buf),
+            Writer::Thread(writer) => writer.poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self {
+            #[cfg(all(unix, feature = "mio-driver"))]
+            Writer::Driver(_) => Poll::Ready(Ok(())),
+            Writer::Thread(writer) => writer.poll_flush(cx),
+        }
+    }
+}
+
+/// The asynchronous handle of the standard input of current process, created by [`stdin`].
+///
+/// The input is buffered, so use one handle to read the whole input, the data buffered by
+/// the dropped handle is lost.
+pub struct Stdin {
+    reader: Reader,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Debug for Stdin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stdin")
+    }
+}
+
+impl Stdin {
+    fn new(reader: Reader) -> Self {
+        Self {
+            reader,
+            buf: vec![],
+            pos: 0,
+        }
+    }
+
+    /// Reads all bytes until a newline (the 0xA byte) is reached, and appends them to `buf`.
+    ///
+    /// Returns the number of bytes read, zero means the input reaches EOF.
+    pub async fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        AsyncBufReadExt::read_line(self, buf).await
+    }
+}
+
+impl AsyncBufRead for Stdin {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        if this.pos >= this.buf.len() {
+            match &mut this.reader {
+                #[cfg(all(unix, feature = "mio-driver"))]
+                Reader::Driver(stdio) => {
+                    this.buf.resize(STDIN_BUFFER_SIZE, 0);
+
+                    let result = stdio.poll_read(cx, &mut this.buf);
+
+                    let read_size = match &result {
+                        Poll::Ready(Ok(read_size)) => *read_size,
+                        _ => 0,
+                    };
+
+                    this.buf.truncate(read_size);
+
+                    ready!(result)?;
+                }
+                Reader::Thread(chunks) => match ready!(chunks.poll_next_unpin(cx)) {
+                    Some(Ok(chunk)) => this.buf = chunk,
+                    Some(Err(err)) => return Poll::Ready(Err(err)),
+                    None => this.buf.clear(),
+                },
+            }
+
+            this.pos = 0;
+        }
+
+        Poll::Ready(Ok(&this.buf[this.pos..]))
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.len());
+    }
+}
+
+impl AsyncRead for Stdin {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let read_size = {
+            let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+
+            let read_size = available.len().min(buf.len());
+
+            buf[..read_size].copy_from_slice(&available[..read_size]);
+
+            read_size
+        };
+
+        self.consume(read_size);
+
+        Poll::Ready(Ok(read_size))
+    }
+}
+
+/// The asynchronous handle of the standard output of current process, created by [`stdout`].
+pub struct Stdout {
+    writer: Writer,
+}
+
+impl Debug for Stdout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stdout")
+    }
+}
+
+impl Stdout {
+    /// Writes `line` and a newline, then flushes the output.
+    pub async fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.write_all(line.as_bytes()).await?;
+        self.write_all(b"\n").await?;
+        self.flush().await
+    }
+}
+
+impl AsyncWrite for Stdout {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.writer.poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.writer.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// The asynchronous handle of the standard error of current process, created by [`stderr`].
+pub struct Stderr {
+    writer: Writer,
+}
+
+impl Debug for Stderr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stderr")
+    }
+}
+
+impl Stderr {
+    /// Writes `line` and a newline, then flushes the output.
+    pub async fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.write_all(line.as_bytes()).await?;
+        self.write_all(b"\n").await?;
+        self.flush().await
+    }
+}
+
+impl AsyncWrite for Stderr {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.writer.poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.writer.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Creates the handle of the standard input with global context `driver` / `poller`.
+#[cfg(feature = "current")]
+pub fn stdin() -> io::Result<Stdin> {
+    stdin_with(get_driver()?, get_poller()?)
+}
+
+/// Creates the handle of the standard input with providing `driver` / `poller`.
+#[allow(unused_variables)]
+pub fn stdin_with(driver: Driver, poller: Handle) -> io::Result<Stdin> {
+    #[cfg(all(unix, feature = "mio-driver"))]
+    {
+        use std::os::fd::AsRawFd;
+
+        match driver::DriverStdio::open(
+            io::stdin().as_raw_fd(),
+            crate::Description::PipeReceiver,
+            driver,
+            poller,
+        ) {
+            Ok(stdio) => return Ok(Stdin::new(Reader::Driver(stdio))),
+            Err(err) => log::trace!("hala-stdin fallback to thread, err={}", err),
+        }
+    }
+
+    spawn_reader("hala-stdin", io::stdin()).map(|chunks| Stdin::new(Reader::Thread(chunks)))
+}
+
+/// Creates the handle of the standard output with global context `driver` / `poller`.
+#[cfg(feature = "current")]
+pub fn stdout() -> io::Result<Stdout> {
+    stdout_with(get_driver()?, get_poller()?)
+}
+
+/// Creates the handle of the standard output with providing `driver` / `poller`.
+pub fn stdout_with(driver: Driver, poller: Handle) -> io::Result<Stdout> {
+    Writer::open("hala-stdout", io::stdout(), driver, poller).map(|writer| Stdout { writer })
+}
+
+/// Creates the handle of the standard error with global context `driver` / `poller`.
+#[cfg(feature = "current")]
+pub fn stderr() -> io::Result<Stderr> {
+    stderr_with(get_driver()?, get_poller()?)
+}
+
+/// Creates the handle of the standard error with providing `driver` / `poller`.
+pub fn stderr_with(driver: Driver, poller: Handle) -> io::Result<Stderr> {
+    Writer::open("hala-stderr", io::stderr(), driver, poller).map(|writer| Stderr { writer })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Cursor,
+        sync::{Arc, Mutex},
+    };
+
+    #[cfg(all(unix, feature = "mio-driver", feature = "current"))]
+    use crate::test::io_test;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Write::write(&mut *self.0.lock().unwrap(), buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_thread_stdio() {
+        let chunks = spawn_reader("test-stdin", Cursor::new(b"hello\nworld".to_vec())).unwrap();
+
+        let mut stdin = Stdin::new(Reader::Thread(chunks));
+
+        let mut line = String::new();
+
+        assert_eq!(block_on(stdin.read_line(&mut line)).unwrap(), 6);
+        assert_eq!(line, "hello\n");
+
+        line.clear();
+
+        assert_eq!(block_on(stdin.read_line(&mut line)).unwrap(), 5);
+        assert_eq!(line, "world");
+
+        assert_eq!(block_on(stdin.read_line(&mut line)).unwrap(), 0);
+
+        let output = SharedBuf::default();
+
+        let mut stdout = Stdout {
+            writer: Writer::Thread(ThreadWriter::spawn("test-stdout", output.clone()).unwrap()),
+        };
+
+        block_on(stdout.write_line("hello")).unwrap();
+        block_on(stdout.write_line("world")).unwrap();
+
+        assert_eq!(output.0.lock().unwrap().as_slice(), b"hello\nworld\n");
+    }
+
+    #[cfg(all(unix, feature = "mio-driver", feature = "current"))]
+    #[hala_test::test(io_test)]
+    async fn test_driver_stdio() {
+        use std::os::fd::AsRawFd;
+
+        let (reader, mut writer) = io::pipe().unwrap();
+
+        let stdio = driver::DriverStdio::open(
+            reader.as_raw_fd(),
+            crate::Description::PipeReceiver,
+            get_driver().unwrap(),
+            get_poller().unwrap(),
+        )
+        .unwrap();
+
+        let mut stdin = Stdin::new(Reader::Driver(stdio));
+
+        thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(20));
+            writer.write_all(b"hello\nworld\n").unwrap();
+        });
+
+        let mut line = String::new();
+
+        stdin.read_line(&mut line).await.unwrap();
+        stdin.read_line(&mut line).await.unwrap();
+
+        assert_eq!(line, "hello\nworld\n");
+
+        drop(stdin);
+
+        // The original blocking mode is restored.
+        // Safety: `F_GETFL` doesn't change the state of the fd.
+        let flags = unsafe { libc::fcntl(reader.as_raw_fd(), libc::F_GETFL) };
+
+        assert_eq!(flags & libc::O_NONBLOCK, 0);
+    }
+}
@@ -1,7 +1,27 @@
 pub use std::future::Future;
 use std::sync::Once;
 
-use crate::{current::register_driver, mio::mio_driver};
+use futures::future::BoxFuture;
+use hala_future::{local_set, scheduler::WorkStealing};
+
+use crate::{
+    coop,
+    current::{
+        executor::{local_block_on, BlockOnIoSpawner, IO_POLLER_TIMEOUT},
+        register_driver, IoContext,
+    },
+    mio::mio_driver,
+    Cmd,
+};
+
+/// Registers the global mio driver once, which is shared by all test runners.
+fn register_mio_driver() {
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        register_driver(mio_driver()).unwrap();
+    });
+}
 
 /// Test runner with multithread spawner and global poll event loop
 pub fn io_test<T, Fut>(label: &'static str, test: T)
@@ -9,11 +29,7 @@ where
     T: FnOnce() -> Fut + 'static,
     Fut: Future<Output = ()> + Send + 'static,
 {
-    static INIT: Once = Once::new();
-
-    INIT.call_once(|| {
-        register_driver(mio_driver()).unwrap();
-    });
+    register_mio_driver();
 
     log::trace!("start io test(st,{})", label);
 
@@ -21,3 +37,88 @@ where
 
     _ = super::current::executor::block_on(fut, 10);
 }
+
+/// Test runner with current thread local set and global poll event loop, the test future needn't be [`Send`].
+pub fn local_io_test<T, Fut>(label: &'static str, test: T)
+where
+    T: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    register_mio_driver();
+
+    log::trace!("start local io test(st,{})", label);
+
+    super::current::executor::local_block_on(test());
+}
+
+/// Test runner with a [`WorkStealing`] runtime of `threads` workers, used by
+/// `#[hala_test::test(threads = N)]` / `#[hala_test::test(runtime = "multi")]`.
+///
+/// Unlike [`io_test`], the runtime, the mio driver and the poller are created for this test only,
+/// and dropped once the test completes, so the test case can't be affected by others.
+pub fn multi_thread_io_test<T, Fut>(threads: usize, label: &'static str, test: T)
+where
+    T: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    log::trace!("start io test(mt={},{})", threads, label);
+
+    let context = IoContext::new(mio_driver()).unwrap();
+
+    let runtime = WorkStealing::builder()
+        .workers(threads)
+        .thread_name("hala-io-test")
+        .io_poller({
+            let driver = context.driver().clone();
+            let poller = context.poller();
+
+            move || {
+                driver
+                    .fd_cntl(poller, Cmd::PollOnce(Some(IO_POLLER_TIMEOUT)))
+                    .map(|_| ())
+            }
+        })
+        .build()
+        .unwrap();
+
+    let context = context.spawner(BlockOnIoSpawner(runtime.spawner()));
+
+    let fut = {
+        let _guard = context.enter();
+
+        context.scope(coop::with_budget(test(), coop::DEFAULT_COOP_BUDGET))
+    };
+
+    let handle = runtime.spawn_with_handle(fut).unwrap();
+
+    futures::executor::block_on(handle);
+
+    // Stops the workers polling the poller before it's closed by the last clone of `context`.
+    drop(runtime);
+}
+
+/// Test runner with current thread local set, used by `#[hala_test::test(runtime = "local")]`.
+///
+/// Unlike [`local_io_test`], the mio driver and the poller are created for this test only,
+/// and the tasks spawned by [`io_spawn`](crate::current::executor::io_spawn) run on current thread too.
+pub fn current_thread_io_test<T, Fut>(label: &'static str, test: T)
+where
+    T: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    log::trace!("start local io test(st,{})", label);
+
+    let context = IoContext::new(mio_driver()).unwrap().spawner(
+        |fut: BoxFuture<'static, std::io::Result<()>>| {
+            local_set::spawn_local(async move {
+                if let Err(err) = fut.await {
+                    log::error!("{}", err);
+                }
+            })
+        },
+    );
+
+    let _guard = context.enter();
+
+    local_block_on(test());
+}
@@ -0,0 +1,235 @@
+use std::{io, pin::Pin};
+
+use futures::{future::poll_fn, AsyncRead, AsyncWrite};
+
+/// The error of [`read_exact_tracked`] / [`write_all_tracked`], carries the number of bytes
+/// transferred before the error occurred.
+#[derive(Debug, thiserror::Error)]
+#[error("{source}, transferred={transferred}")]
+pub struct PartialTransfer {
+    /// The total number of bytes transferred, including the progress of previous calls.
+    pub transferred: usize,
+    /// The error which interrupted the transfer.
+    #[source]
+    pub source: io::Error,
+}
+
+impl PartialTransfer {
+    /// Returns the kind of the underlying io error.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.source.kind()
+    }
+}
+
+impl From<PartialTransfer> for io::Error {
+    fn from(value: PartialTransfer) -> Self {
+        io::Error::new(value.kind(), value)
+    }
+}
+
+/// Reads the exact number of bytes required to fill `buf`, starting at `buf[*progress..]`.
+///
+/// The `progress` is updated as soon as the data is read, so it stays valid when this future
+/// is dropped before completion, and the read can be resumed by calling again with the same
+/// `buf` and `progress`. Returns [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error if
+/// the reader reaches EOF before `buf` is filled.
+pub async fn read_exact_tracked<R>(
+    reader: &mut R,
+    buf: &mut [u8],
+    progress: &mut usize,
+) -> Result<(), PartialTransfer>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    while *progress < buf.len() {
+        let result =
+            poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buf[*progress..])).await;
+
+        match result {
+            Ok(0) => {
+                return Err(PartialTransfer {
+                    transferred: *progress,
+                    source: io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("reader reaches eof, expect={}", buf.len()),
+                    ),
+                })
+            }
+            Ok(read_size) => *progress += read_size,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                return Err(PartialTransfer {
+                    transferred: *progress,
+                    source: err,
+                })
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the entire `buf[*progress..]` into `writer`.
+///
+/// The `progress` is updated as soon as the data is written, so it stays valid when this future
+/// is dropped before completion, and the write can be resumed by calling again with the same
+/// `buf` and `progress`. Returns [`WriteZero`](io::ErrorKind::WriteZero) error if the writer
+/// accepts no more data.
+pub async fn write_all_tracked<W>(
+    writer: &mut W,
+    buf: &[u8],
+    progress: &mut usize,
+) -> Result<(), PartialTransfer>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    while *progress < buf.len() {
+        let result = poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, &buf[*progress..])).await;
+
+        match result {
+            Ok(0) => {
+                return Err(PartialTransfer {
+                    transferred: *progress,
+                    source: io::Error::new(io::ErrorKind::WriteZero, "write zero byte into writer"),
+                })
+            }
+            Ok(write_size) => *progress += write_size,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                return Err(PartialTransfer {
+                    transferred: *progress,
+                    source: err,
+                })
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures::{executor::block_on, io::Cursor, AsyncRead, AsyncWrite, FutureExt};
+
+    use super::*;
+
+    /// Returns the chunks in order, and `Pending` once the chunks are exhausted.
+    struct ChunkReader(Vec<Vec<u8>>);
+
+    impl AsyncRead for ChunkReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            if self.0.is_empty() {
+                return Poll::Pending;
+            }
+
+            let chunk = self.0.remove(0);
+
+            buf[..chunk.len()].copy_from_slice(&chunk);
+
+            Poll::Ready(Ok(chunk.len()))
+        }
+    }
+
+    /// Accepts at most `limit` bytes, then returns `BrokenPipe` error.
+    struct LimitWriter {
+        data: Vec<u8>,
+        limit: usize,
+    }
+
+    impl AsyncWrite for LimitWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let size = buf.len().min(self.limit - self.data.len()).min(2);
+
+            if size == 0 {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+
+            self.data.extend_from_slice(&buf[..size]);
+
+            Poll::Ready(Ok(size))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_read_exact_cancel_and_resume() {
+        let mut reader = ChunkReader(vec![vec![1, 2], vec![3]]);
+
+        let mut buf = [0; 5];
+        let mut progress = 0;
+
+        // Cancelled once the reader is pending.
+        assert!(read_exact_tracked(&mut reader, &mut buf, &mut progress)
+            .now_or_never()
+            .is_none());
+
+        assert_eq!(progress, 3);
+
+        let mut reader = ChunkReader(vec![vec![4, 5]]);
+
+        block_on(read_exact_tracked(&mut reader, &mut buf, &mut progress)).unwrap();
+
+        assert_eq!(progress, 5);
+        assert_eq!(buf, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_read_exact_eof() {
+        let mut reader = Cursor::new(vec![1, 2, 3]);
+
+        let mut buf = [0; 5];
+        let mut progress = 0;
+
+        let err = block_on(read_exact_tracked(&mut reader, &mut buf, &mut progress)).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(err.transferred, 3);
+
+        let err: io::Error = err.into();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_write_all_error() {
+        let mut writer = LimitWriter {
+            data: vec![],
+            limit: 5,
+        };
+
+        let mut progress = 0;
+
+        let err = block_on(write_all_tracked(&mut writer, &[0; 8], &mut progress)).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(err.transferred, 5);
+        assert_eq!(progress, 5);
+
+        writer.limit = 8;
+
+        block_on(write_all_tracked(&mut writer, &[0; 8], &mut progress)).unwrap();
+
+        assert_eq!(writer.data.len(), 8);
+    }
+}
@@ -2,6 +2,8 @@ use std::{future::Future, io, task::Context};
 
 use std::task::Poll;
 
+use crate::coop::poll_proceed;
+
 /// A future object which will suspend current task when `F` returns error [`WouldBlock`](io::ErrorKind::WouldBlock)
 pub struct WouldBlock<F> {
     f: F,
@@ -14,6 +16,8 @@ where
     type Output = io::Result<R>;
 
     fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        futures::ready!(poll_proceed(cx));
+
         match (self.f)(cx) {
             Ok(r) => Poll::Ready(Ok(r)),
             Err(err) if err.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
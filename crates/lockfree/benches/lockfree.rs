@@ -48,3 +48,13 @@ fn multi_thread_push_pop(bencher: Bencher) {
 
     dropping.store(true, Ordering::Release);
 }
+
+#[divan::bench(threads)]
+fn bounded_push_pop(bencher: Bencher) {
+    let queue = hala_lockfree::mpmc::Queue::new(1024);
+
+    bencher.bench(|| {
+        _ = queue.push(1);
+        queue.pop()
+    })
+}
@@ -1,2 +1,4 @@
+pub mod mpmc;
+pub mod pool;
 pub mod queue;
 pub mod timewheel;
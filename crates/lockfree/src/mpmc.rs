@@ -0,0 +1,359 @@
+use std::{
+    cell::UnsafeCell,
+    future::poll_fn,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Poll, Waker},
+};
+
+use crate::queue;
+
+struct Slot<T> {
+    /// The position at which this slot can be written / read.
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A lockfree bounded `MPMC` queue, which is backed by a fixed-size array.
+pub struct Queue<T> {
+    slots: Box<[Slot<T>]>,
+    mask: usize,
+    header: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+impl<T> Queue<T> {
+    /// Create new queue which holds at most `capacity` values,
+    /// the `capacity` is rounded up to the next power of two.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "the capacity of queue must be greater than zero"
+        );
+
+        let capacity = capacity.next_power_of_two();
+
+        let slots = (0..capacity)
+            .map(|i| Slot {
+                sequence: AtomicUsize::new(i),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+
+        Self {
+            slots,
+            mask: capacity - 1,
+            header: Default::default(),
+            tail: Default::default(),
+        }
+    }
+
+    /// Returns the max number of values this queue can hold.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of values in this queue, which may be outdated under contention.
+    pub fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        let header = self.header.load(Ordering::Acquire);
+
+        tail.wrapping_sub(header).min(self.capacity())
+    }
+
+    /// Returns true if this queue contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Push one value into queue tail, returns the value back if this queue is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut tail = self.tail.load(Ordering::Relaxed);
+
+        loop {
+            let slot = &self.slots[tail & self.mask];
+
+            let sequence = slot.sequence.load(Ordering::Acquire);
+
+            match (sequence as isize).wrapping_sub(tail as isize) {
+                0 => match self.tail.compare_exchange_weak(
+                    tail,
+                    tail.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // Safety: the slot is owned by this thread until the sequence is updated.
+                        unsafe {
+                            (*slot.value.get()).write(value);
+                        }
+
+                        slot.sequence.store(tail.wrapping_add(1), Ordering::Release);
+
+                        return Ok(());
+                    }
+                    Err(current) => tail = current,
+                },
+                // The slot is not yet read by the previous round.
+                diff if diff < 0 => return Err(value),
+                _ => tail = self.tail.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Pop one value from the queue's header. returns [`None`] if this queue is empty.
+    pub fn pop(&self) -> Option<T> {
+        let mut header = self.header.load(Ordering::Relaxed);
+
+        loop {
+            let slot = &self.slots[header & self.mask];
+
+            let sequence = slot.sequence.load(Ordering::Acquire);
+
+            match (sequence as isize).wrapping_sub(header.wrapping_add(1) as isize) {
+                0 => match self.header.compare_exchange_weak(
+                    header,
+                    header.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // Safety: the slot is written by `push` and owned by this thread
+                        // until the sequence is updated.
+                        let value = unsafe { (*slot.value.get()).assume_init_read() };
+
+                        slot.sequence
+                            .store(header.wrapping_add(self.capacity()), Ordering::Release);
+
+                        return Some(value);
+                    }
+                    Err(current) => header = current,
+                },
+                // The slot is not yet written.
+                diff if diff < 0 => return None,
+                _ => header = self.header.load(Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+/// The asynchronous adapter of bounded [`Queue`], the sending / receiving task
+/// parks until the queue is not full / not empty.
+pub struct AsyncQueue<T> {
+    queue: Queue<T>,
+    send_wakers: queue::Queue<Waker>,
+    recv_wakers: queue::Queue<Waker>,
+}
+
+impl<T> AsyncQueue<T> {
+    /// Create new asynchronous queue with `capacity`, see [`Queue::new`] for more information.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queue: Queue::new(capacity),
+            send_wakers: Default::default(),
+            recv_wakers: Default::default(),
+        }
+    }
+
+    /// Returns the max number of values this queue can hold.
+    pub fn capacity(&self) -> usize {
+        self.queue.capacity()
+    }
+
+    /// Returns the number of values in this queue, which may be outdated under contention.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if this queue contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Try to send one value without waiting, returns the value back if this queue is full.
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        self.queue.push(value)?;
+
+        wake_all(&self.recv_wakers);
+
+        Ok(())
+    }
+
+    /// Try to receive one value without waiting, returns [`None`] if this queue is empty.
+    pub fn try_recv(&self) -> Option<T> {
+        let value = self.queue.pop()?;
+
+        wake_all(&self.send_wakers);
+
+        Some(value)
+    }
+
+    /// Sends one value, waits until this queue is not full.
+    pub async fn send(&self, value: T) {
+        let mut value = Some(value);
+
+        poll_fn(|cx| {
+            let Err(v) = self.try_send(value.take().unwrap()) else {
+                return Poll::Ready(());
+            };
+
+            self.send_wakers.push(cx.waker().clone());
+
+            // Check again, the slot may be freed before registering the waker.
+            match self.try_send(v) {
+                Ok(_) => Poll::Ready(()),
+                Err(v) => {
+                    value = Some(v);
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Receives one value, waits until this queue is not empty.
+    pub async fn recv(&self) -> T {
+        poll_fn(|cx| {
+            if let Some(value) = self.try_recv() {
+                return Poll::Ready(value);
+            }
+
+            self.recv_wakers.push(cx.waker().clone());
+
+            // Check again, the value may be pushed before registering the waker.
+            match self.try_recv() {
+                Some(value) => Poll::Ready(value),
+                None => Poll::Pending,
+            }
+        })
+        .await
+    }
+}
+
+/// Wakes all parked tasks, the woken task which can't make progress parks again.
+///
+/// Waking only one task may lose the notification if that task has been dropped.
+fn wake_all(wakers: &queue::Queue<Waker>) {
+    while let Some(waker) = wakers.pop() {
+        waker.wake();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Arc, thread::spawn};
+
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn test_push_pop() {
+        let queue = Queue::new(3);
+
+        assert_eq!(queue.capacity(), 4);
+
+        for i in 0..4 {
+            queue.push(i).unwrap();
+        }
+
+        assert_eq!(queue.push(4), Err(4));
+        assert_eq!(queue.len(), 4);
+
+        for i in 0..4 {
+            assert_eq!(queue.pop(), Some(i));
+        }
+
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_drop_values() {
+        let value = Arc::new(());
+
+        let queue = Queue::new(4);
+
+        queue.push(value.clone()).unwrap();
+        queue.push(value.clone()).unwrap();
+
+        drop(queue);
+
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_multi_push_pop() {
+        let queue = Arc::new(Queue::new(16));
+
+        let push_threads = 4;
+        let loops = 10000;
+
+        for i in 0..push_threads {
+            let queue = queue.clone();
+
+            spawn(move || {
+                for j in 0..loops {
+                    let mut value = i * loops + j;
+
+                    while let Err(v) = queue.push(value) {
+                        value = v;
+                        std::thread::yield_now();
+                    }
+                }
+            });
+        }
+
+        let mut set = HashSet::new();
+
+        while set.len() < push_threads * loops {
+            match queue.pop() {
+                Some(value) => assert!(set.insert(value), "duplicated value {value}"),
+                None => std::thread::yield_now(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_queue() {
+        let queue = Arc::new(AsyncQueue::new(2));
+
+        let senders = 4;
+        let loops = 1000;
+
+        for i in 0..senders {
+            let queue = queue.clone();
+
+            spawn(move || {
+                block_on(async {
+                    for j in 0..loops {
+                        queue.send(i * loops + j).await;
+                    }
+                })
+            });
+        }
+
+        let mut set = HashSet::new();
+
+        block_on(async {
+            while set.len() < senders * loops {
+                assert!(set.insert(queue.recv().await));
+            }
+        });
+
+        assert!(queue.try_recv().is_none());
+    }
+}
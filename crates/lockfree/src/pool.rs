@@ -0,0 +1,212 @@
+use std::{
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::queue::Queue;
+
+struct RawBufferPool {
+    slab_size: usize,
+    max_idle: usize,
+    idle: AtomicUsize,
+    slabs: Queue<Vec<u8>>,
+}
+
+/// A lockfree pool of fix-sized buffers, cloning this pool shares the same buffers.
+#[derive(Clone)]
+pub struct BufferPool {
+    raw: Arc<RawBufferPool>,
+}
+
+impl Debug for BufferPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "BufferPool(slab_size={}, idle={})",
+            self.raw.slab_size,
+            self.idle()
+        )
+    }
+}
+
+impl BufferPool {
+    /// Create new pool of buffers with `slab_size` bytes,
+    /// at most `max_idle` returned buffers are kept for reusing.
+    pub fn new(slab_size: usize, max_idle: usize) -> Self {
+        Self {
+            raw: Arc::new(RawBufferPool {
+                slab_size,
+                max_idle,
+                idle: Default::default(),
+                slabs: Queue::new(),
+            }),
+        }
+    }
+
+    /// Returns the size of buffers in this pool.
+    pub fn slab_size(&self) -> usize {
+        self.raw.slab_size
+    }
+
+    /// Returns the number of buffers waiting for reusing.
+    pub fn idle(&self) -> usize {
+        self.raw.idle.load(Ordering::Acquire)
+    }
+
+    /// Get one buffer of `slab_size` bytes, allocates new one if there is no idle buffer.
+    ///
+    /// The reused buffer is not zeroed, it may contain the data written by the previous owner.
+    pub fn get(&self) -> PooledBuf {
+        let buf = match self.raw.slabs.pop() {
+            Some(buf) => {
+                self.raw.idle.fetch_sub(1, Ordering::AcqRel);
+                buf
+            }
+            None => vec![0; self.raw.slab_size],
+        };
+
+        PooledBuf {
+            buf,
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+/// The buffer borrowed from [`BufferPool`], which is returned to the pool on drop.
+pub struct PooledBuf {
+    buf: Vec<u8>,
+    raw: Arc<RawBufferPool>,
+}
+
+impl Debug for PooledBuf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PooledBuf(len={})", self.buf.len())
+    }
+}
+
+impl PooledBuf {
+    /// Shortens this buffer to `len` bytes, has no effect if `len` is greater than the current length.
+    ///
+    /// The full length is restored when this buffer is returned to the pool.
+    pub fn truncate(&mut self, len: usize) {
+        self.buf.truncate(len);
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buf
+    }
+}
+
+impl AsRef<[u8]> for PooledBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl AsMut<[u8]> for PooledBuf {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if self.raw.idle.fetch_add(1, Ordering::AcqRel) >= self.raw.max_idle {
+            self.raw.idle.fetch_sub(1, Ordering::AcqRel);
+            return;
+        }
+
+        let mut buf = std::mem::take(&mut self.buf);
+
+        // `truncate` keeps the capacity, so this doesn't reallocate.
+        buf.resize(self.raw.slab_size, 0);
+
+        self.raw.slabs.push(buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::spawn;
+
+    use super::*;
+
+    #[test]
+    fn test_reuse() {
+        let pool = BufferPool::new(1024, 2);
+
+        let mut buf = pool.get();
+
+        assert_eq!(buf.len(), 1024);
+
+        buf[0] = 1;
+        buf.truncate(10);
+
+        assert_eq!(buf.len(), 10);
+
+        let ptr = buf.as_ptr();
+
+        drop(buf);
+
+        assert_eq!(pool.idle(), 1);
+
+        let buf = pool.get();
+
+        assert_eq!(pool.idle(), 0);
+        assert_eq!(buf.len(), 1024);
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(buf[0], 1);
+    }
+
+    #[test]
+    fn test_max_idle() {
+        let pool = BufferPool::new(16, 2);
+
+        let bufs = (0..4).map(|_| pool.get()).collect::<Vec<_>>();
+
+        drop(bufs);
+
+        assert_eq!(pool.idle(), 2);
+    }
+
+    #[test]
+    fn test_multi_thread() {
+        let pool = BufferPool::new(64, 8);
+
+        let mut handles = vec![];
+
+        for i in 0..8 {
+            let pool = pool.clone();
+
+            handles.push(spawn(move || {
+                for _ in 0..1000 {
+                    let mut buf = pool.get();
+
+                    buf.fill(i);
+
+                    assert!(buf.iter().all(|b| *b == i));
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert!(pool.idle() <= 8);
+    }
+}
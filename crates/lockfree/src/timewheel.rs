@@ -26,10 +26,18 @@ pub struct HashedTimeWheel<T> {
 impl<T> HashedTimeWheel<T> {
     /// Create new default [`HashedTimeWheel`] instance.
     pub fn new(tick_duration: Duration) -> Self {
+        Self::new_at(tick_duration, Instant::now())
+    }
+
+    /// Create new [`HashedTimeWheel`] instance started at `now`.
+    ///
+    /// The `_at` functions take the current time from the caller, e.g. the manual clock in tests,
+    /// which must be the same clock as `now`.
+    pub fn new_at(tick_duration: Duration, now: Instant) -> Self {
         Self {
             timers: Arc::new(DashMap::default()),
             ticks: Default::default(),
-            start_instant: Instant::now(),
+            start_instant: now,
             tick_duration: tick_duration.as_micros(),
             timer_count: Default::default(),
         }
@@ -42,7 +50,12 @@ impl<T> HashedTimeWheel<T> {
 
     /// Creates a new timer and returns the timer expiration ticks.
     pub fn new_timer(&self, timer: T, duration: Duration) -> Option<u64> {
-        let instant_duration = Instant::now() - self.start_instant;
+        self.new_timer_at(timer, duration, Instant::now())
+    }
+
+    /// Creates a new timer expired `duration` after `now`, see [`new_timer`](Self::new_timer).
+    pub fn new_timer_at(&self, timer: T, duration: Duration, now: Instant) -> Option<u64> {
+        let instant_duration = now.saturating_duration_since(self.start_instant);
 
         let ticks = (instant_duration + duration).as_micros() / self.tick_duration;
 
@@ -93,18 +106,38 @@ impl<T> HashedTimeWheel<T> {
         Some(ticks)
     }
 
+    /// Returns the duration until the earliest timer expires, which is returned by the [`next_tick`](Self::next_tick)
+    /// called after it, `None` if there are no timers.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        self.next_deadline_at(Instant::now())
+    }
+
+    /// Returns the duration from `now` until the earliest timer expires, see [`next_deadline`](Self::next_deadline).
+    pub fn next_deadline_at(&self, now: Instant) -> Option<Duration> {
+        let ticks = self.timers.iter().map(|entry| *entry.key()).min()?;
+
+        // The timers of `ticks` are expired once the clock reaches the next tick.
+        let deadline = Duration::from_micros(((ticks + 1) as u128 * self.tick_duration) as u64);
+
+        Some(deadline.saturating_sub(now.saturating_duration_since(self.start_instant)))
+    }
+
     /// Forward to next tick, and returns timeout timers.
     pub fn next_tick(&self) -> Option<Vec<T>> {
+        self.next_tick_at(Instant::now())
+    }
+
+    /// Forward to the tick of `now`, and returns timeout timers, see [`next_tick`](Self::next_tick).
+    pub fn next_tick_at(&self, now: Instant) -> Option<Vec<T>> {
         loop {
             let current = self.ticks.load(Ordering::Acquire);
 
-            let instant_duration = Instant::now() - self.start_instant;
+            let instant_duration = now.saturating_duration_since(self.start_instant);
 
             let ticks = (instant_duration.as_micros() / self.tick_duration) as u64;
 
-            assert!(current <= ticks);
-
-            if current == ticks {
+            // The clock read by other thread may be slightly ahead of `now`.
+            if current >= ticks {
                 return None;
             }
 
@@ -209,4 +242,68 @@ mod tests {
 
         assert_eq!(time_wheel.next_tick(), Some(vec![1]));
     }
+
+    #[test]
+    fn test_next_deadline() {
+        let time_wheel = HashedTimeWheel::<i32>::new(Duration::from_millis(10));
+
+        assert_eq!(time_wheel.next_deadline(), None);
+
+        time_wheel.new_timer(1, Duration::from_millis(200)).unwrap();
+        time_wheel.new_timer(2, Duration::from_millis(50)).unwrap();
+
+        let deadline = time_wheel.next_deadline().unwrap();
+
+        assert!(deadline > Duration::from_millis(40) && deadline <= Duration::from_millis(60));
+
+        sleep(deadline);
+
+        assert_eq!(time_wheel.next_tick(), Some(vec![2]));
+
+        assert!(time_wheel.next_deadline().unwrap() > Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_long_timer_not_wrap() {
+        let time_wheel = HashedTimeWheel::<i32>::new(Duration::from_millis(1));
+
+        // The timers are keyed by the absolute expiration ticks, so the timer beyond
+        // 2048 ticks must not alias to the short one.
+        let short = time_wheel.new_timer(1, Duration::from_millis(10)).unwrap();
+
+        let long = time_wheel
+            .new_timer(2, Duration::from_millis(4096 + 10))
+            .unwrap();
+
+        assert!(long - short >= 4096);
+
+        sleep(Duration::from_millis(20));
+
+        assert_eq!(time_wheel.next_tick(), Some(vec![1]));
+        assert_eq!(time_wheel.timers(), 2);
+    }
+
+    #[test]
+    fn test_manual_time() {
+        let start = Instant::now();
+
+        let time_wheel = HashedTimeWheel::<i32>::new_at(Duration::from_millis(10), start);
+
+        time_wheel
+            .new_timer_at(1, Duration::from_secs(60), start)
+            .unwrap();
+
+        assert_eq!(
+            time_wheel.next_deadline_at(start + Duration::from_secs(30)),
+            Some(Duration::from_millis(30010))
+        );
+
+        // The wall clock doesn't expire the timer.
+        assert_eq!(time_wheel.next_tick_at(start), None);
+
+        assert_eq!(
+            time_wheel.next_tick_at(start + Duration::from_millis(60010)),
+            Some(vec![1])
+        );
+    }
 }
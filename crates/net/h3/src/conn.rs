@@ -0,0 +1,474 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use hala_future::mpsc;
+use hala_io::current::executor::io_spawn;
+use hala_quic::{QuicConn, QuicIncoming, QuicRecvStream, QuicSendStream, QuicStream};
+use hala_sync::{AsyncLockable, AsyncSpinMutex};
+use quiche::h3::{Header, NameValue};
+
+use crate::{
+    decode_headers, encode_headers,
+    frame::*,
+    stream::{H3IncomingPush, H3PushStream, H3Stream, SendStream},
+};
+
+/// The application error code `H3_NO_ERROR`.
+pub const H3_NO_ERROR: u64 = 0x100;
+
+/// The configuration of [`H3Connection`].
+#[derive(Debug, Clone, Copy)]
+pub struct H3Config {
+    /// The max size of header section accepted from the peer, also limits the size of control frames.
+    pub max_field_section_size: u64,
+    /// The max number of server pushes allowed by the client, zero disables server push.
+    ///
+    /// Only used on the client side.
+    pub max_push_streams: u64,
+}
+
+impl Default for H3Config {
+    fn default() -> Self {
+        Self {
+            max_field_section_size: 64 * 1024,
+            max_push_streams: 0,
+        }
+    }
+}
+
+#[derive(Default)]
+struct H3State {
+    /// The settings received from the peer's control stream.
+    peer_settings: Option<Vec<(u64, u64)>>,
+    /// The max push id allowed by the client.
+    max_push_id: Option<u64>,
+    /// The push id of next server push.
+    next_push_id: u64,
+    /// The promised request headers received by the client, indexed by push id.
+    promises: HashMap<u64, Vec<Header>>,
+    /// The id carried by the peer's `GOAWAY` frame.
+    goaway: Option<u64>,
+}
+
+/// The connection-level state shared by [`H3Connection`] and its streams.
+pub(crate) struct H3Shared {
+    config: H3Config,
+    is_server: bool,
+    state: Mutex<H3State>,
+}
+
+impl H3Shared {
+    fn state(&self) -> MutexGuard<'_, H3State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub(crate) fn decode_headers(&self, header_block: &[u8]) -> io::Result<Vec<Header>> {
+        decode_headers(header_block, self.config.max_field_section_size)
+    }
+
+    pub(crate) fn on_push_promise(&self, push_id: u64, header_block: &[u8]) -> io::Result<()> {
+        if self.is_server {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "h3 client sends PUSH_PROMISE",
+            ));
+        }
+
+        let headers = self.decode_headers(header_block)?;
+
+        self.state().promises.insert(push_id, headers);
+
+        Ok(())
+    }
+
+    pub(crate) fn promised_headers(&self, push_id: u64) -> Option<Vec<Header>> {
+        self.state().promises.get(&push_id).cloned()
+    }
+
+    fn on_control_frame(&self, frame: Frame) -> io::Result<()> {
+        let mut state = self.state();
+
+        match frame {
+            Frame::Settings { settings } if state.peer_settings.is_none() => {
+                state.peer_settings = Some(settings);
+            }
+            Frame::MaxPushId { push_id } if self.is_server => {
+                if state.max_push_id.is_some_and(|max| max > push_id) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "h3 client reduces MAX_PUSH_ID",
+                    ));
+                }
+
+                state.max_push_id = Some(push_id);
+            }
+            Frame::GoAway { id } => state.goaway = Some(id),
+            Frame::CancelPush { push_id } => {
+                state.promises.remove(&push_id);
+            }
+            Frame::Unknown { .. } => {}
+            frame => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("h3 control frame unexpected: {:?}", frame),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// HTTP/3 connection over [`QuicConn`].
+///
+/// The incoming quic streams are dispatched by a task spawned by [`io_spawn`],
+/// dropping this connection closes the underlying quic connection.
+pub struct H3Connection {
+    conn: Arc<QuicConn>,
+    shared: Arc<H3Shared>,
+    /// The local control stream, which must be kept open during the connection lifetime.
+    control: QuicSendStream,
+    requests: AsyncSpinMutex<mpsc::Receiver<(Vec<Header>, H3Stream)>>,
+    pushes: AsyncSpinMutex<mpsc::Receiver<H3IncomingPush>>,
+}
+
+impl Debug for H3Connection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "H3Connection(server={}, {:?})",
+            self.shared.is_server, self.conn
+        )
+    }
+}
+
+impl H3Connection {
+    /// Creates the server side connection over the quic connection accepted by [`QuicListener`](hala_quic::QuicListener).
+    pub async fn accept(conn: QuicConn, config: H3Config) -> io::Result<Self> {
+        Self::new(conn, config, true).await
+    }
+
+    /// Creates the client side connection over the quic connection created by [`QuicConn::connect_udp`].
+    pub async fn connect(conn: QuicConn, config: H3Config) -> io::Result<Self> {
+        Self::new(conn, config, false).await
+    }
+
+    async fn new(conn: QuicConn, config: H3Config, is_server: bool) -> io::Result<Self> {
+        let control = conn.open_uni_stream().await?;
+
+        let mut buf = vec![];
+
+        encode_varint(STREAM_CONTROL, &mut buf);
+
+        // Only the static table is used, so the peer's encoder never blocks streams.
+        Frame::Settings {
+            settings: vec![
+                (SETTINGS_QPACK_MAX_TABLE_CAPACITY, 0),
+                (SETTINGS_QPACK_BLOCKED_STREAMS, 0),
+                (
+                    SETTINGS_MAX_FIELD_SECTION_SIZE,
+                    config.max_field_section_size,
+                ),
+            ],
+        }
+        .encode(&mut buf);
+
+        if !is_server && config.max_push_streams > 0 {
+            Frame::MaxPushId {
+                push_id: config.max_push_streams - 1,
+            }
+            .encode(&mut buf);
+        }
+
+        control.send_all(&buf, false).await?;
+
+        let shared = Arc::new(H3Shared {
+            config,
+            is_server,
+            state: Default::default(),
+        });
+
+        let conn = Arc::new(conn);
+
+        let (request_sender, request_receiver) = mpsc::unbounded();
+        let (push_sender, push_receiver) = mpsc::unbounded();
+
+        Self::spawn_dispatcher(conn.clone(), shared.clone(), request_sender, push_sender)?;
+
+        Ok(Self {
+            conn,
+            shared,
+            control,
+            requests: AsyncSpinMutex::new(request_receiver),
+            pushes: AsyncSpinMutex::new(push_receiver),
+        })
+    }
+
+    fn spawn_dispatcher(
+        conn: Arc<QuicConn>,
+        shared: Arc<H3Shared>,
+        request_sender: mpsc::Sender<(Vec<Header>, H3Stream)>,
+        push_sender: mpsc::Sender<H3IncomingPush>,
+    ) -> io::Result<()> {
+        io_spawn(async move {
+            while let Some(incoming) = conn.accept().await {
+                let id = incoming.id();
+                let shared = shared.clone();
+
+                match incoming {
+                    QuicIncoming::Bidi(stream) if shared.is_server => {
+                        let sender = request_sender.clone();
+
+                        io_spawn(async move {
+                            if let Err(err) = Self::recv_request(stream, shared, sender).await {
+                                log::error!(
+                                    "h3 recv request failed, stream_id={}, err={}",
+                                    id,
+                                    err
+                                );
+                            }
+
+                            Ok(())
+                        })?;
+                    }
+                    QuicIncoming::Bidi(_) => {
+                        log::error!("h3 server opens bidirectional stream, stream_id={}", id);
+                    }
+                    QuicIncoming::Uni(stream) => {
+                        let sender = push_sender.clone();
+
+                        io_spawn(async move {
+                            if let Err(err) = Self::recv_uni_stream(stream, shared, sender).await {
+                                log::error!(
+                                    "h3 recv uni stream failed, stream_id={}, err={}",
+                                    id,
+                                    err
+                                );
+                            }
+
+                            Ok(())
+                        })?;
+                    }
+                }
+            }
+
+            log::trace!("{:?} h3 dispatcher stopped", conn);
+
+            Ok(())
+        })
+    }
+
+    /// Reads the request headers, then passes the request stream to [`accept_request`](Self::accept_request).
+    async fn recv_request(
+        stream: QuicStream,
+        shared: Arc<H3Shared>,
+        sender: mpsc::Sender<(Vec<Header>, H3Stream)>,
+    ) -> io::Result<()> {
+        let (read, write) = stream.split();
+
+        let mut frames = FrameReader::new(shared.config.max_field_section_size);
+
+        let headers = loop {
+            match frames.next_frame(&read).await? {
+                Some(Frame::Headers { header_block }) => {
+                    break shared.decode_headers(&header_block)?
+                }
+                Some(Frame::Unknown { .. }) => {}
+                Some(frame) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("h3 request frame unexpected: {:?}", frame),
+                    ))
+                }
+                // The client cancelled the request.
+                None => return Ok(()),
+            }
+        };
+
+        let stream = H3Stream::new(read, write, frames, shared, true);
+
+        // The connection is dropped, ignore the request.
+        _ = sender.send((headers, stream)).await;
+
+        Ok(())
+    }
+
+    async fn recv_uni_stream(
+        stream: QuicRecvStream,
+        shared: Arc<H3Shared>,
+        sender: mpsc::Sender<H3IncomingPush>,
+    ) -> io::Result<()> {
+        let mut frames = FrameReader::new(shared.config.max_field_section_size);
+
+        let Some(ty) = frames.read_varint(&stream).await? else {
+            return Ok(());
+        };
+
+        match ty {
+            STREAM_CONTROL => {
+                while let Some(frame) = frames.next_frame(&stream).await? {
+                    shared.on_control_frame(frame)?;
+                }
+
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "h3 control stream closed",
+                ))
+            }
+            STREAM_PUSH if !shared.is_server => {
+                let Some(push_id) = frames.read_varint(&stream).await? else {
+                    return Ok(());
+                };
+
+                let push = H3IncomingPush::new(push_id, stream, frames, shared);
+
+                _ = sender.send(push).await;
+
+                Ok(())
+            }
+            // The dynamic table is disabled, and the unknown stream types must be ignored,
+            // so just drains them to release the flow control credits.
+            _ => {
+                let mut buf = vec![0; 1024];
+
+                while !stream.recv(&mut buf).await?.1 {}
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the underlying quic connection.
+    pub fn quic_conn(&self) -> &QuicConn {
+        &self.conn
+    }
+
+    /// Returns the settings received from the peer, `None` if not yet received.
+    pub fn peer_settings(&self) -> Option<Vec<(u64, u64)>> {
+        self.shared.state().peer_settings.clone()
+    }
+
+    /// Returns the id carried by the peer's `GOAWAY` frame, `None` if not yet received.
+    pub fn peer_goaway(&self) -> Option<u64> {
+        self.shared.state().goaway
+    }
+
+    /// Sends a request on new request stream, the request body follows if `fin` is false.
+    pub async fn send_request<T: NameValue + Sync>(
+        &self,
+        headers: &[T],
+        fin: bool,
+    ) -> io::Result<H3Stream> {
+        if self.shared.is_server {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "h3 server can't send request",
+            ));
+        }
+
+        if self.shared.state().goaway.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "h3 server is going away",
+            ));
+        }
+
+        let (read, write) = self.conn.open_stream().await?.split();
+
+        let frames = FrameReader::new(self.shared.config.max_field_section_size);
+
+        let stream = H3Stream::new(read, write, frames, self.shared.clone(), false);
+
+        stream.send_headers(headers, fin).await?;
+
+        Ok(stream)
+    }
+
+    /// Accepts one request on the server side, returns tuple (request headers, request stream),
+    /// or `None` if the connection had been closed.
+    pub async fn accept_request(&self) -> Option<(Vec<Header>, H3Stream)> {
+        self.requests.lock().await.recv().await
+    }
+
+    /// Promises a server push associated with the request `stream`, and opens the push stream
+    /// on which the pushed response is sent.
+    ///
+    /// Returns error if the push id exceeds the client's `MAX_PUSH_ID`.
+    pub async fn push_promise<T: NameValue + Sync>(
+        &self,
+        stream: &H3Stream,
+        request_headers: &[T],
+    ) -> io::Result<H3PushStream> {
+        if !self.shared.is_server {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "h3 client can't push",
+            ));
+        }
+
+        let push_id = {
+            let mut state = self.shared.state();
+
+            match state.max_push_id {
+                Some(max_push_id) if state.next_push_id <= max_push_id => {}
+                _ => return Err(io::Error::other("h3 push id exceeds MAX_PUSH_ID")),
+            }
+
+            state.next_push_id += 1;
+
+            state.next_push_id - 1
+        };
+
+        let header_block = encode_headers(request_headers)?;
+
+        stream
+            .writer()
+            .send_frame(
+                &Frame::PushPromise {
+                    push_id,
+                    header_block,
+                },
+                false,
+            )
+            .await?;
+
+        let push_stream = self.conn.open_uni_stream().await?;
+
+        let mut buf = vec![];
+
+        encode_varint(STREAM_PUSH, &mut buf);
+        encode_varint(push_id, &mut buf);
+
+        push_stream.send_all(&buf, false).await?;
+
+        Ok(H3PushStream::new(push_id, push_stream))
+    }
+
+    /// Accepts one server push on the client side, returns `None` if the connection had been closed.
+    pub async fn accept_push(&self) -> Option<H3IncomingPush> {
+        self.pushes.lock().await.recv().await
+    }
+
+    /// Sends `GOAWAY` frame to initiate graceful shutdown.
+    ///
+    /// On the server side `id` is the smallest request stream id that will not be processed,
+    /// on the client side `id` is the smallest push id that will not be accepted.
+    pub async fn goaway(&self, id: u64) -> io::Result<()> {
+        self.control.send_frame(&Frame::GoAway { id }, false).await
+    }
+}
+
+impl Drop for H3Connection {
+    fn drop(&mut self) {
+        // The dispatcher task holds the quic connection, so close it explicitly.
+        let conn = self.conn.clone();
+
+        if let Err(err) = io_spawn(async move { conn.close(H3_NO_ERROR, b"").await }) {
+            log::error!("spawn h3 connection closing task failed, err={}", err);
+        }
+    }
+}
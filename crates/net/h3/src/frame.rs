@@ -0,0 +1,490 @@
+//! HTTP/3 frame codec, the frame codec of quiche is private so it's implemented here.
+
+use std::io;
+
+use hala_quic::{QuicRecvStream, QuicStreamReadHalf};
+
+/// The frame type of `DATA` frame.
+pub const FRAME_DATA: u64 = 0x0;
+/// The frame type of `HEADERS` frame.
+pub const FRAME_HEADERS: u64 = 0x1;
+/// The frame type of `CANCEL_PUSH` frame.
+pub const FRAME_CANCEL_PUSH: u64 = 0x3;
+/// The frame type of `SETTINGS` frame.
+pub const FRAME_SETTINGS: u64 = 0x4;
+/// The frame type of `PUSH_PROMISE` frame.
+pub const FRAME_PUSH_PROMISE: u64 = 0x5;
+/// The frame type of `GOAWAY` frame.
+pub const FRAME_GOAWAY: u64 = 0x7;
+/// The frame type of `MAX_PUSH_ID` frame.
+pub const FRAME_MAX_PUSH_ID: u64 = 0xd;
+
+/// The unidirectional stream type of control stream.
+pub const STREAM_CONTROL: u64 = 0x0;
+/// The unidirectional stream type of push stream.
+pub const STREAM_PUSH: u64 = 0x1;
+/// The unidirectional stream type of qpack encoder stream.
+pub const STREAM_QPACK_ENCODER: u64 = 0x2;
+/// The unidirectional stream type of qpack decoder stream.
+pub const STREAM_QPACK_DECODER: u64 = 0x3;
+
+/// The settings identifier of `SETTINGS_QPACK_MAX_TABLE_CAPACITY`.
+pub const SETTINGS_QPACK_MAX_TABLE_CAPACITY: u64 = 0x1;
+/// The settings identifier of `SETTINGS_MAX_FIELD_SECTION_SIZE`.
+pub const SETTINGS_MAX_FIELD_SECTION_SIZE: u64 = 0x6;
+/// The settings identifier of `SETTINGS_QPACK_BLOCKED_STREAMS`.
+pub const SETTINGS_QPACK_BLOCKED_STREAMS: u64 = 0x7;
+
+/// The max value of quic variable-length integer.
+pub const MAX_VARINT: u64 = (1 << 62) - 1;
+
+/// Appends the quic variable-length integer `value` to `out`.
+///
+/// # Panics
+///
+/// Panics if `value` is greater than [`MAX_VARINT`].
+pub fn encode_varint(value: u64, out: &mut Vec<u8>) {
+    assert!(value <= MAX_VARINT, "varint out of range");
+
+    if value < (1 << 6) {
+        out.push(value as u8);
+    } else if value < (1 << 14) {
+        out.extend_from_slice(&(value as u16 | 0x4000).to_be_bytes());
+    } else if value < (1 << 30) {
+        out.extend_from_slice(&(value as u32 | 0x8000_0000).to_be_bytes());
+    } else {
+        out.extend_from_slice(&(value | 0xc000_0000_0000_0000).to_be_bytes());
+    }
+}
+
+/// Decodes one quic variable-length integer from the front of `buf`,
+/// returns tuple (value, consumed bytes) or `None` if `buf` is too short.
+pub fn decode_varint(buf: &[u8]) -> Option<(u64, usize)> {
+    let first = *buf.first()?;
+
+    let len = 1 << (first >> 6);
+
+    if buf.len() < len {
+        return None;
+    }
+
+    let value = buf[1..len]
+        .iter()
+        .fold((first & 0x3f) as u64, |value, b| (value << 8) | *b as u64);
+
+    Some((value, len))
+}
+
+fn invalid_frame(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("h3 frame error: {}", msg),
+    )
+}
+
+/// HTTP/3 frame, see [RFC 9114](https://www.rfc-editor.org/rfc/rfc9114#section-7.2) for more information.
+///
+/// The payload of `DATA` frame is not buffered, so only its length is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Data {
+        len: u64,
+    },
+    Headers {
+        header_block: Vec<u8>,
+    },
+    CancelPush {
+        push_id: u64,
+    },
+    Settings {
+        settings: Vec<(u64, u64)>,
+    },
+    PushPromise {
+        push_id: u64,
+        header_block: Vec<u8>,
+    },
+    GoAway {
+        id: u64,
+    },
+    MaxPushId {
+        push_id: u64,
+    },
+    /// The frame of unknown or reserved type, which must be ignored.
+    Unknown {
+        ty: u64,
+    },
+}
+
+impl Frame {
+    /// Decodes the frame of type `ty` from the whole frame `payload`.
+    ///
+    /// `DATA` frame is not decoded by this function, use [`FrameReader`] instead.
+    pub fn parse(ty: u64, payload: &[u8]) -> io::Result<Self> {
+        let frame = match ty {
+            FRAME_HEADERS => Frame::Headers {
+                header_block: payload.to_vec(),
+            },
+            FRAME_CANCEL_PUSH => Frame::CancelPush {
+                push_id: Self::parse_id(payload)?,
+            },
+            FRAME_SETTINGS => {
+                let mut settings = vec![];
+                let mut buf = payload;
+
+                while !buf.is_empty() {
+                    let (id, id_len) =
+                        decode_varint(buf).ok_or_else(|| invalid_frame("settings"))?;
+                    let (value, value_len) =
+                        decode_varint(&buf[id_len..]).ok_or_else(|| invalid_frame("settings"))?;
+
+                    settings.push((id, value));
+
+                    buf = &buf[id_len + value_len..];
+                }
+
+                Frame::Settings { settings }
+            }
+            FRAME_PUSH_PROMISE => {
+                let (push_id, len) =
+                    decode_varint(payload).ok_or_else(|| invalid_frame("push promise"))?;
+
+                Frame::PushPromise {
+                    push_id,
+                    header_block: payload[len..].to_vec(),
+                }
+            }
+            FRAME_GOAWAY => Frame::GoAway {
+                id: Self::parse_id(payload)?,
+            },
+            FRAME_MAX_PUSH_ID => Frame::MaxPushId {
+                push_id: Self::parse_id(payload)?,
+            },
+            FRAME_DATA => return Err(invalid_frame("data frame payload is not buffered")),
+            ty => Frame::Unknown { ty },
+        };
+
+        Ok(frame)
+    }
+
+    fn parse_id(payload: &[u8]) -> io::Result<u64> {
+        match decode_varint(payload) {
+            Some((id, len)) if len == payload.len() => Ok(id),
+            _ => Err(invalid_frame("id frame")),
+        }
+    }
+
+    /// Appends the encoded frame to `out`, the `DATA` frame only encodes the frame header.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let mut payload = vec![];
+
+        let ty = match self {
+            Frame::Data { len } => {
+                encode_varint(FRAME_DATA, out);
+                encode_varint(*len, out);
+
+                return;
+            }
+            Frame::Headers { header_block } => {
+                payload.extend_from_slice(header_block);
+                FRAME_HEADERS
+            }
+            Frame::CancelPush { push_id } => {
+                encode_varint(*push_id, &mut payload);
+                FRAME_CANCEL_PUSH
+            }
+            Frame::Settings { settings } => {
+                for (id, value) in settings {
+                    encode_varint(*id, &mut payload);
+                    encode_varint(*value, &mut payload);
+                }
+
+                FRAME_SETTINGS
+            }
+            Frame::PushPromise {
+                push_id,
+                header_block,
+            } => {
+                encode_varint(*push_id, &mut payload);
+                payload.extend_from_slice(header_block);
+                FRAME_PUSH_PROMISE
+            }
+            Frame::GoAway { id } => {
+                encode_varint(*id, &mut payload);
+                FRAME_GOAWAY
+            }
+            Frame::MaxPushId { push_id } => {
+                encode_varint(*push_id, &mut payload);
+                FRAME_MAX_PUSH_ID
+            }
+            Frame::Unknown { ty } => *ty,
+        };
+
+        encode_varint(ty, out);
+        encode_varint(payload.len() as u64, out);
+        out.extend_from_slice(&payload);
+    }
+}
+
+/// The receiving side of quic stream, from which the frames are read.
+pub(crate) trait RecvStream {
+    async fn recv(&self, buf: &mut [u8]) -> io::Result<(usize, bool)>;
+}
+
+impl RecvStream for QuicStreamReadHalf {
+    async fn recv(&self, buf: &mut [u8]) -> io::Result<(usize, bool)> {
+        QuicStreamReadHalf::recv(self, buf).await
+    }
+}
+
+impl RecvStream for QuicRecvStream {
+    async fn recv(&self, buf: &mut [u8]) -> io::Result<(usize, bool)> {
+        QuicRecvStream::recv(self, buf).await
+    }
+}
+
+/// The size of buffer used by one stream read.
+const READ_BUF_SIZE: usize = 4096;
+
+/// Incremental frame parser of one receiving stream.
+#[derive(Debug)]
+pub struct FrameReader {
+    /// The received bytes which are not yet consumed.
+    buf: Vec<u8>,
+    /// True if the stream is finished.
+    fin: bool,
+    /// The unread payload length of current `DATA` frame.
+    data_remaining: u64,
+    /// The max payload length of non-`DATA` frames.
+    max_frame_size: u64,
+}
+
+impl FrameReader {
+    /// Create new reader, the non-`DATA` frames whose payload exceed `max_frame_size` are rejected.
+    pub fn new(max_frame_size: u64) -> Self {
+        Self {
+            buf: vec![],
+            fin: false,
+            data_remaining: 0,
+            max_frame_size,
+        }
+    }
+
+    /// Returns the unread payload length of current `DATA` frame.
+    pub fn data_remaining(&self) -> u64 {
+        self.data_remaining
+    }
+
+    /// Reads more bytes from `stream`, returns false if the stream is finished.
+    async fn fill<S: RecvStream>(&mut self, stream: &S) -> io::Result<bool> {
+        if self.fin {
+            return Ok(false);
+        }
+
+        let offset = self.buf.len();
+
+        self.buf.resize(offset + READ_BUF_SIZE, 0);
+
+        let result = stream.recv(&mut self.buf[offset..]).await;
+
+        let (read_size, fin) = match result {
+            Ok(r) => r,
+            Err(err) => {
+                self.buf.truncate(offset);
+                return Err(err);
+            }
+        };
+
+        self.buf.truncate(offset + read_size);
+        self.fin = fin;
+
+        Ok(read_size > 0 || !fin)
+    }
+
+    /// Reads one quic variable-length integer, returns `None` if the stream finished cleanly before it.
+    pub(crate) async fn read_varint<S: RecvStream>(
+        &mut self,
+        stream: &S,
+    ) -> io::Result<Option<u64>> {
+        loop {
+            if let Some((value, len)) = decode_varint(&self.buf) {
+                self.buf.drain(..len);
+
+                return Ok(Some(value));
+            }
+
+            if !self.fill(stream).await? {
+                return self.eof_or_truncated();
+            }
+        }
+    }
+
+    fn eof_or_truncated<T>(&self) -> io::Result<Option<T>> {
+        if self.buf.is_empty() {
+            Ok(None)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "h3 frame error: truncated frame",
+            ))
+        }
+    }
+
+    /// Reads the next frame, returns `None` if the stream finished cleanly.
+    ///
+    /// The unread payload of previous `DATA` frame is skipped.
+    pub(crate) async fn next_frame<S: RecvStream>(
+        &mut self,
+        stream: &S,
+    ) -> io::Result<Option<Frame>> {
+        while self.data_remaining > 0 {
+            let mut buf = [0; READ_BUF_SIZE];
+
+            if self.read_data(stream, &mut buf).await? == 0 {
+                return self.eof_or_truncated();
+            }
+        }
+
+        let (ty, len) = loop {
+            if let Some((ty, ty_len)) = decode_varint(&self.buf) {
+                if let Some((len, len_len)) = decode_varint(&self.buf[ty_len..]) {
+                    self.buf.drain(..ty_len + len_len);
+
+                    break (ty, len);
+                }
+            }
+
+            if !self.fill(stream).await? {
+                return self.eof_or_truncated();
+            }
+        };
+
+        if ty == FRAME_DATA {
+            self.data_remaining = len;
+
+            return Ok(Some(Frame::Data { len }));
+        }
+
+        if len > self.max_frame_size {
+            return Err(invalid_frame("frame too large"));
+        }
+
+        let len = len as usize;
+
+        while self.buf.len() < len {
+            if !self.fill(stream).await? {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "h3 frame error: truncated frame",
+                ));
+            }
+        }
+
+        let payload = self.buf.drain(..len).collect::<Vec<_>>();
+
+        Frame::parse(ty, &payload).map(Some)
+    }
+
+    /// Reads the payload of current `DATA` frame into `buf`, returns 0 if the payload is exhausted.
+    pub(crate) async fn read_data<S: RecvStream>(
+        &mut self,
+        stream: &S,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        if self.data_remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.buf.is_empty() && !self.fill(stream).await? {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "h3 frame error: truncated data frame",
+            ));
+        }
+
+        let read_size = self
+            .buf
+            .len()
+            .min(buf.len())
+            .min(self.data_remaining as usize);
+
+        buf[..read_size].copy_from_slice(&self.buf[..read_size]);
+
+        self.buf.drain(..read_size);
+        self.data_remaining -= read_size as u64;
+
+        Ok(read_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        for (value, len) in [
+            (0, 1),
+            (63, 1),
+            (64, 2),
+            (16383, 2),
+            (16384, 4),
+            ((1 << 30) - 1, 4),
+            (1 << 30, 8),
+            (MAX_VARINT, 8),
+        ] {
+            let mut buf = vec![];
+
+            encode_varint(value, &mut buf);
+
+            assert_eq!(buf.len(), len);
+            assert_eq!(decode_varint(&buf), Some((value, len)));
+            assert_eq!(decode_varint(&buf[..len - 1]), None);
+        }
+
+        // The example of RFC 9000 appendix A.1
+        assert_eq!(
+            decode_varint(&[0x9d, 0x7f, 0x3e, 0x7d]),
+            Some((494878333, 4))
+        );
+    }
+
+    #[test]
+    fn test_frame() {
+        let frames = [
+            Frame::Headers {
+                header_block: b"hello".to_vec(),
+            },
+            Frame::CancelPush { push_id: 3 },
+            Frame::Settings {
+                settings: vec![(SETTINGS_MAX_FIELD_SECTION_SIZE, 16384), (0x21, 0)],
+            },
+            Frame::PushPromise {
+                push_id: 100,
+                header_block: b"world".to_vec(),
+            },
+            Frame::GoAway { id: 4 },
+            Frame::MaxPushId { push_id: 1 << 20 },
+        ];
+
+        for frame in frames {
+            let mut buf = vec![];
+
+            frame.encode(&mut buf);
+
+            let (ty, ty_len) = decode_varint(&buf).unwrap();
+            let (len, len_len) = decode_varint(&buf[ty_len..]).unwrap();
+
+            assert_eq!(len as usize, buf.len() - ty_len - len_len);
+
+            assert_eq!(Frame::parse(ty, &buf[ty_len + len_len..]).unwrap(), frame);
+        }
+
+        // The reserved frame types are ignored.
+        assert_eq!(
+            Frame::parse(0x21, b"").unwrap(),
+            Frame::Unknown { ty: 0x21 }
+        );
+
+        Frame::parse(FRAME_GOAWAY, &[0x1, 0x2]).unwrap_err();
+    }
+}
@@ -0,0 +1,60 @@
+use std::io;
+
+use quiche::h3::{qpack, Header, NameValue};
+
+fn into_io_error(err: qpack::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("qpack error: {}", err))
+}
+
+/// Compresses `headers` into one QPACK field section.
+///
+/// Only the static table is used, so the peer decodes the field section without the encoder stream.
+pub fn encode_headers<T: NameValue>(headers: &[T]) -> io::Result<Vec<u8>> {
+    // The literal representations are never inflated by huffman encoding,
+    // so the upper bound is the total length plus the integer prefixes.
+    let max_size = headers
+        .iter()
+        .map(|h| h.name().len() + h.value().len() + 20)
+        .sum::<usize>()
+        + 16;
+
+    let mut buf = vec![0; max_size];
+
+    let len = qpack::Encoder::new()
+        .encode(headers, &mut buf)
+        .map_err(into_io_error)?;
+
+    buf.truncate(len);
+
+    Ok(buf)
+}
+
+/// Decompresses one QPACK field section, the field sections larger than `max_size` are rejected.
+pub fn decode_headers(header_block: &[u8], max_size: u64) -> io::Result<Vec<Header>> {
+    qpack::Decoder::new()
+        .decode(header_block, max_size)
+        .map_err(into_io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qpack() {
+        let headers = vec![
+            Header::new(b":method", b"GET"),
+            Header::new(b":scheme", b"https"),
+            Header::new(b":authority", b"quic.tech"),
+            Header::new(b":path", b"/"),
+            Header::new(b"user-agent", b"hala"),
+            Header::new(b"x-custom", b"value"),
+        ];
+
+        let header_block = encode_headers(&headers).unwrap();
+
+        assert_eq!(decode_headers(&header_block, u64::MAX).unwrap(), headers);
+
+        decode_headers(&header_block, 16).unwrap_err();
+    }
+}
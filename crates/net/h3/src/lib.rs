@@ -0,0 +1,30 @@
+//! HTTP/3 protocol implementation over [`hala-quic`](hala_quic).
+//!
+//! The header sections are compressed by the QPACK static table of [`quiche::h3::qpack`].
+//!
+//! # Why not `quiche::h3::Connection`
+//!
+//! This crate frames the HTTP/3 streams itself instead of wrapping [`quiche::h3::Connection`]:
+//!
+//! * `quiche::h3::Connection` reads and writes the quic streams through `&mut quiche::Connection`,
+//!   which is owned by the connection state of hala-quic, whose streams are driven by
+//!   [`QuicConn::accept`](hala_quic::QuicConn::accept) and the [`QuicStream`](hala_quic::QuicStream) APIs.
+//!   Both of them would consume the same stream data.
+//! * quiche 0.20 doesn't implement server push, there is no API to send `PUSH_PROMISE` or open push streams.
+//! * The frame codec of quiche (`quiche::h3::frame`) is private, so it can't be reused separately.
+//!
+//! Only the QPACK codec of quiche is reused, see [`encode_headers`] and [`decode_headers`].
+
+mod frame;
+pub use frame::*;
+
+mod headers;
+pub use headers::*;
+
+mod conn;
+pub use conn::*;
+
+mod stream;
+pub use stream::*;
+
+pub use quiche::h3::{Header, NameValue};
@@ -0,0 +1,361 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use futures::{stream, Stream};
+use hala_quic::{QuicRecvStream, QuicSendStream, QuicStreamReadHalf, QuicStreamWriteHalf};
+use quiche::h3::{Header, NameValue};
+
+use crate::{
+    conn::H3Shared,
+    frame::{Frame, FrameReader, RecvStream},
+};
+
+/// The size of chunks yielded by the body streams.
+const BODY_CHUNK_SIZE: usize = 4096;
+
+fn unexpected_frame(frame: &Frame) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("h3 frame unexpected: {:?}", frame),
+    )
+}
+
+/// The sending side of quic stream, to which the frames are written.
+pub(crate) trait SendStream {
+    async fn send(&self, buf: &[u8], fin: bool) -> io::Result<usize>;
+
+    /// Sends the whole `buf`, the `fin` flag is set with the last piece.
+    async fn send_all(&self, buf: &[u8], fin: bool) -> io::Result<()> {
+        let mut offset = 0;
+
+        loop {
+            offset += self.send(&buf[offset..], fin).await?;
+
+            if offset == buf.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn send_frame(&self, frame: &Frame, fin: bool) -> io::Result<()> {
+        let mut buf = vec![];
+
+        frame.encode(&mut buf);
+
+        self.send_all(&buf, fin).await
+    }
+
+    async fn send_headers<T: NameValue + Sync>(&self, headers: &[T], fin: bool) -> io::Result<()> {
+        let header_block = crate::encode_headers(headers)?;
+
+        self.send_frame(&Frame::Headers { header_block }, fin).await
+    }
+
+    async fn send_body(&self, body: &[u8], fin: bool) -> io::Result<()> {
+        // An empty `DATA` frame is useless, only the `fin` flag is sent.
+        if body.is_empty() {
+            return self.send_all(&[], fin).await;
+        }
+
+        let mut buf = vec![];
+
+        Frame::Data {
+            len: body.len() as u64,
+        }
+        .encode(&mut buf);
+
+        buf.extend_from_slice(body);
+
+        self.send_all(&buf, fin).await
+    }
+}
+
+impl SendStream for QuicStreamWriteHalf {
+    async fn send(&self, buf: &[u8], fin: bool) -> io::Result<usize> {
+        QuicStreamWriteHalf::send(self, buf, fin).await
+    }
+}
+
+impl SendStream for QuicSendStream {
+    async fn send(&self, buf: &[u8], fin: bool) -> io::Result<usize> {
+        QuicSendStream::send(self, buf, fin).await
+    }
+}
+
+/// The receiving state of one HTTP message, shared by request streams and push streams.
+struct MessageReader {
+    frames: FrameReader,
+    shared: Arc<H3Shared>,
+    /// True if the (final or interim) header section has been received.
+    headers_received: bool,
+    trailers: Option<Vec<Header>>,
+}
+
+impl MessageReader {
+    fn new(frames: FrameReader, shared: Arc<H3Shared>, headers_received: bool) -> Self {
+        Self {
+            frames,
+            shared,
+            headers_received,
+            trailers: None,
+        }
+    }
+
+    async fn recv_headers<S: RecvStream>(&mut self, stream: &S) -> io::Result<Vec<Header>> {
+        loop {
+            match self.frames.next_frame(stream).await? {
+                Some(Frame::Headers { header_block }) => {
+                    self.headers_received = true;
+
+                    return self.shared.decode_headers(&header_block);
+                }
+                Some(Frame::PushPromise {
+                    push_id,
+                    header_block,
+                }) => self.shared.on_push_promise(push_id, &header_block)?,
+                Some(Frame::Unknown { .. }) => {}
+                Some(frame) => return Err(unexpected_frame(&frame)),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "h3 stream finished before headers",
+                    ))
+                }
+            }
+        }
+    }
+
+    async fn recv_body<S: RecvStream>(&mut self, stream: &S, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.headers_received {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "h3 stream receives body before headers",
+            ));
+        }
+
+        loop {
+            if self.frames.data_remaining() > 0 {
+                return self.frames.read_data(stream, buf).await;
+            }
+
+            // The trailer section terminates the message.
+            if self.trailers.is_some() {
+                return Ok(0);
+            }
+
+            match self.frames.next_frame(stream).await? {
+                Some(Frame::Data { .. }) | Some(Frame::Unknown { .. }) => {}
+                Some(Frame::Headers { header_block }) => {
+                    self.trailers = Some(self.shared.decode_headers(&header_block)?);
+                }
+                Some(Frame::PushPromise {
+                    push_id,
+                    header_block,
+                }) => self.shared.on_push_promise(push_id, &header_block)?,
+                Some(frame) => return Err(unexpected_frame(&frame)),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// HTTP/3 request stream, which carries one request and its response.
+///
+/// The client creates it by [`send_request`](crate::H3Connection::send_request),
+/// and the server receives it by [`accept_request`](crate::H3Connection::accept_request).
+pub struct H3Stream {
+    read: QuicStreamReadHalf,
+    write: QuicStreamWriteHalf,
+    reader: MessageReader,
+}
+
+impl Debug for H3Stream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "H3Stream(stream_id={})", self.read.id())
+    }
+}
+
+impl H3Stream {
+    pub(crate) fn new(
+        read: QuicStreamReadHalf,
+        write: QuicStreamWriteHalf,
+        frames: FrameReader,
+        shared: Arc<H3Shared>,
+        headers_received: bool,
+    ) -> Self {
+        Self {
+            read,
+            write,
+            reader: MessageReader::new(frames, shared, headers_received),
+        }
+    }
+
+    pub(crate) fn writer(&self) -> &QuicStreamWriteHalf {
+        &self.write
+    }
+
+    /// Returns the quic stream id.
+    pub fn id(&self) -> u64 {
+        self.read.id()
+    }
+
+    /// Sends one header section, which is the response headers on the server side,
+    /// or the trailers after the body.
+    pub async fn send_headers<T: NameValue + Sync>(
+        &self,
+        headers: &[T],
+        fin: bool,
+    ) -> io::Result<()> {
+        self.write.send_headers(headers, fin).await
+    }
+
+    /// Sends `body` in one `DATA` frame, and finishes the message if `fin` is true.
+    pub async fn send_body(&self, body: &[u8], fin: bool) -> io::Result<()> {
+        self.write.send_body(body, fin).await
+    }
+
+    /// Finishes the sending side of this stream.
+    pub async fn finish(&self) -> io::Result<()> {
+        self.write.send_all(&[], true).await
+    }
+
+    /// Receives the next header section on the client side, which is the response headers.
+    ///
+    /// The interim responses (1xx) are returned as well, so call this function again to receive the final response.
+    pub async fn recv_headers(&mut self) -> io::Result<Vec<Header>> {
+        self.reader.recv_headers(&self.read).await
+    }
+
+    /// Reads the message body into `buf`, returns 0 if the body is finished.
+    pub async fn recv_body(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.recv_body(&self.read, buf).await
+    }
+
+    /// Returns the trailers, which are available after the body is finished.
+    pub fn trailers(&self) -> Option<&[Header]> {
+        self.reader.trailers.as_deref()
+    }
+
+    /// Returns the message body as a stream of chunks.
+    pub fn body(&mut self) -> impl Stream<Item = io::Result<Vec<u8>>> + '_ {
+        stream::unfold(self, |stream| async move {
+            let mut buf = vec![0; BODY_CHUNK_SIZE];
+
+            match stream.recv_body(&mut buf).await {
+                Ok(0) => None,
+                Ok(read_size) => {
+                    buf.truncate(read_size);
+                    Some((Ok(buf), stream))
+                }
+                Err(err) => Some((Err(err), stream)),
+            }
+        })
+    }
+}
+
+/// The server side of push stream, created by [`push_promise`](crate::H3Connection::push_promise).
+pub struct H3PushStream {
+    push_id: u64,
+    stream: QuicSendStream,
+}
+
+impl Debug for H3PushStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "H3PushStream(push_id={}, stream_id={})",
+            self.push_id,
+            self.stream.id()
+        )
+    }
+}
+
+impl H3PushStream {
+    pub(crate) fn new(push_id: u64, stream: QuicSendStream) -> Self {
+        Self { push_id, stream }
+    }
+
+    /// Returns the push id.
+    pub fn push_id(&self) -> u64 {
+        self.push_id
+    }
+
+    /// Sends the headers of pushed response, or the trailers after the body.
+    pub async fn send_headers<T: NameValue + Sync>(
+        &self,
+        headers: &[T],
+        fin: bool,
+    ) -> io::Result<()> {
+        self.stream.send_headers(headers, fin).await
+    }
+
+    /// Sends `body` in one `DATA` frame, and finishes the pushed response if `fin` is true.
+    pub async fn send_body(&self, body: &[u8], fin: bool) -> io::Result<()> {
+        self.stream.send_body(body, fin).await
+    }
+
+    /// Finishes this push stream.
+    pub async fn finish(&self) -> io::Result<()> {
+        self.stream.send_all(&[], true).await
+    }
+}
+
+/// The client side of push stream, received by [`accept_push`](crate::H3Connection::accept_push).
+pub struct H3IncomingPush {
+    push_id: u64,
+    stream: QuicRecvStream,
+    reader: MessageReader,
+}
+
+impl Debug for H3IncomingPush {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "H3IncomingPush(push_id={}, stream_id={})",
+            self.push_id,
+            self.stream.id()
+        )
+    }
+}
+
+impl H3IncomingPush {
+    pub(crate) fn new(
+        push_id: u64,
+        stream: QuicRecvStream,
+        frames: FrameReader,
+        shared: Arc<H3Shared>,
+    ) -> Self {
+        Self {
+            push_id,
+            stream,
+            reader: MessageReader::new(frames, shared, false),
+        }
+    }
+
+    /// Returns the push id.
+    pub fn push_id(&self) -> u64 {
+        self.push_id
+    }
+
+    /// Returns the request headers promised by the server.
+    ///
+    /// The `PUSH_PROMISE` frame arrives on the request stream, so returns `None`
+    /// if it has not yet been read by the request stream.
+    pub fn promised_headers(&self) -> Option<Vec<Header>> {
+        self.reader.shared.promised_headers(self.push_id)
+    }
+
+    /// Receives the headers of pushed response.
+    pub async fn recv_headers(&mut self) -> io::Result<Vec<Header>> {
+        self.reader.recv_headers(&self.stream).await
+    }
+
+    /// Reads the pushed response body into `buf`, returns 0 if the body is finished.
+    pub async fn recv_body(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.recv_body(&self.stream, buf).await
+    }
+
+    /// Returns the trailers, which are available after the body is finished.
+    pub fn trailers(&self) -> Option<&[Header]> {
+        self.reader.trailers.as_deref()
+    }
+}
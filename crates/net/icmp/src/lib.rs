@@ -0,0 +1,5 @@
+mod socket;
+pub use socket::*;
+
+mod ping;
+pub use ping::*;
@@ -0,0 +1,161 @@
+use std::{
+    io,
+    net::IpAddr,
+    sync::atomic::{AtomicU16, Ordering},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "current")]
+use hala_io::current::*;
+
+use hala_io::{timeout_with, Driver, Handle, RawProtocol};
+
+use crate::IcmpSocket;
+
+const ICMPV4_ECHO_REQUEST: u8 = 8;
+const ICMPV4_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+/// The payload carried by echo request.
+const ECHO_PAYLOAD: &[u8] = b"hala-icmp echo payload";
+
+/// The sequence number of the next echo request, shared by all [`ping`] calls of this process.
+static SEQUENCE: AtomicU16 = AtomicU16::new(0);
+
+/// Computes the internet checksum (RFC 1071) of `data`.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum = data
+        .chunks(2)
+        .map(|chunk| match chunk {
+            [high, low] => u16::from_be_bytes([*high, *low]) as u32,
+            [high] => u16::from_be_bytes([*high, 0]) as u32,
+            _ => unreachable!(),
+        })
+        .sum::<u32>();
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+/// Creates the echo request message, the checksum of icmpv6 message is left to the kernel.
+fn echo_request(protocol: RawProtocol, identifier: u16, sequence: u16) -> Vec<u8> {
+    let ty = match protocol {
+        RawProtocol::Icmpv4 => ICMPV4_ECHO_REQUEST,
+        RawProtocol::Icmpv6 => ICMPV6_ECHO_REQUEST,
+    };
+
+    let mut message = vec![ty, 0, 0, 0];
+
+    message.extend_from_slice(&identifier.to_be_bytes());
+    message.extend_from_slice(&sequence.to_be_bytes());
+    message.extend_from_slice(ECHO_PAYLOAD);
+
+    if protocol == RawProtocol::Icmpv4 {
+        let checksum = checksum(&message);
+
+        message[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    message
+}
+
+/// Sends one icmp echo request to `addr` and waits for the echo reply, returns the round-trip time.
+///
+/// Returns [`TimedOut`](io::ErrorKind::TimedOut) error if no reply is received within `timeout`.
+#[cfg(feature = "current")]
+pub async fn ping(addr: IpAddr, timeout: Duration) -> io::Result<Duration> {
+    ping_with(addr, timeout, get_driver()?, get_poller()?).await
+}
+
+/// Sends one icmp echo request to `addr` with providing `driver` / `poller`, see [`ping`] for more information.
+pub async fn ping_with(
+    addr: IpAddr,
+    timeout: Duration,
+    driver: Driver,
+    poller: Handle,
+) -> io::Result<Duration> {
+    let (protocol, reply_type) = match addr {
+        IpAddr::V4(_) => (RawProtocol::Icmpv4, ICMPV4_ECHO_REPLY),
+        IpAddr::V6(_) => (RawProtocol::Icmpv6, ICMPV6_ECHO_REPLY),
+    };
+
+    let socket = IcmpSocket::new_with(protocol, driver.clone(), poller)?;
+
+    let identifier = std::process::id() as u16;
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+
+    let request = echo_request(protocol, identifier, sequence);
+
+    let start = Instant::now();
+
+    let recv_reply = async {
+        socket.send_to(&request, addr).await?;
+
+        let mut buf = vec![0; 1500];
+
+        // The raw socket receives all icmp messages of the host, skips those not replying this request.
+        loop {
+            let (len, raddr) = socket.recv_from(&mut buf).await?;
+
+            if raddr == addr
+                && len >= 8
+                && buf[0] == reply_type
+                && buf[4..6] == identifier.to_be_bytes()
+                && buf[6..8] == sequence.to_be_bytes()
+            {
+                return Ok(start.elapsed());
+            }
+
+            log::trace!(
+                "skip icmp message, raddr={}, len={}, type={}",
+                raddr,
+                len,
+                buf[0]
+            );
+        }
+    };
+
+    timeout_with(driver, poller, recv_reply, Some(timeout)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use hala_io::test::io_test;
+
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        let request = echo_request(RawProtocol::Icmpv4, 0x1234, 1);
+
+        // The checksum of message with the filled checksum field is zero.
+        assert_eq!(checksum(&request), 0);
+
+        assert_eq!(checksum(&[0x45, 0x00, 0x00]), !0x4500);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_ping_loopback() {
+        // Creating raw socket requires `CAP_NET_RAW`.
+        let rtt = match ping(Ipv4Addr::LOCALHOST.into(), Duration::from_secs(2)).await {
+            Ok(rtt) => rtt,
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                log::warn!("skip ping test, err={}", err);
+                return;
+            }
+            Err(err) => panic!("ping failed, err={}", err),
+        };
+
+        assert!(rtt < Duration::from_secs(2));
+
+        ping(Ipv6Addr::LOCALHOST.into(), Duration::from_secs(2))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+};
+
+#[cfg(feature = "current")]
+use hala_io::current::*;
+
+use hala_io::*;
+
+/// A raw icmp socket, each send / recv transfers one icmp message.
+///
+/// Creating the raw socket usually requires the privilege of the process, e.g. `CAP_NET_RAW` on linux.
+pub struct IcmpSocket {
+    protocol: RawProtocol,
+    fd: OwnedHandle,
+    poller: Handle,
+}
+
+impl IcmpSocket {
+    /// Create new icmp socket of `protocol` and register it with the global context poller.
+    #[cfg(feature = "current")]
+    pub fn new(protocol: RawProtocol) -> io::Result<Self> {
+        Self::new_with(protocol, get_driver()?, get_poller()?)
+    }
+
+    /// Create new icmp socket of `protocol` with providing `driver` / `poller`.
+    pub fn new_with(protocol: RawProtocol, driver: Driver, poller: Handle) -> io::Result<Self> {
+        let fd = OwnedHandle::open(
+            driver,
+            Description::RawSocket,
+            OpenFlags::Protocol(protocol),
+        )?;
+
+        fd.register(
+            poller,
+            Interest::Readable | Interest::Writable,
+            PollMode::Edge,
+        )?;
+
+        Ok(Self {
+            protocol,
+            fd,
+            poller,
+        })
+    }
+
+    /// Returns the protocol of this socket.
+    pub fn protocol(&self) -> RawProtocol {
+        self.protocol
+    }
+
+    /// Sends the icmp message `buf` to the host `raddr`. On success, returns the number of bytes written.
+    ///
+    /// The checksum of icmpv4 message must be filled by the caller, and the checksum of icmpv6
+    /// message is filled by the kernel.
+    pub async fn send_to(&self, buf: &[u8], raddr: IpAddr) -> io::Result<usize> {
+        // The port of raw socket address must be zero, or the protocol number on some platforms.
+        let raddr = SocketAddr::new(raddr, 0);
+
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::SendTo {
+                    waker: cx.waker().clone(),
+                    buf,
+                    raddr,
+                })?
+                .try_into_datalen()
+        })
+        .await
+    }
+
+    /// Receives one icmp message. On success, returns the number of bytes read and the source host.
+    ///
+    /// The ipv4 header received by icmpv4 socket is stripped, so `buf` only contains the icmp message.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, IpAddr)> {
+        let (len, raddr) = would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::RecvFrom {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_recv_from()
+        })
+        .await?;
+
+        if self.protocol == RawProtocol::Icmpv6 {
+            return Ok((len, raddr.ip()));
+        }
+
+        let header_len = match buf.first() {
+            Some(version_ihl) => (*version_ihl as usize & 0x0f) * 4,
+            None => 0,
+        };
+
+        if header_len < 20 || header_len > len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "invalid ipv4 header, len={}, header_len={}",
+                    len, header_len
+                ),
+            ));
+        }
+
+        buf.copy_within(header_len..len, 0);
+
+        Ok((len - header_len, raddr.ip()))
+    }
+}
+
+impl Drop for IcmpSocket {
+    fn drop(&mut self) {
+        if let Err(err) = self.fd.deregister(self.poller) {
+            log::error!(
+                "deregister icmp socket failed, fd={:?}, err={}",
+                self.fd,
+                err
+            );
+        }
+    }
+}
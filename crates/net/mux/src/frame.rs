@@ -0,0 +1,258 @@
+use std::io;
+
+use bitmask_enum::bitmask;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use hala_codec::{Decoder, Encoder};
+
+/// The protocol version of yamux.
+pub const VERSION: u8 = 0;
+
+/// The length of frame header.
+pub const HEADER_LEN: usize = 12;
+
+/// The initial window size of each stream, which is defined by the yamux specification.
+pub const DEFAULT_WINDOW: u32 = 256 * 1024;
+
+/// The stream id of the session level frames, e.g. ping and go away.
+pub const SESSION_ID: u32 = 0;
+
+/// The type of yamux frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    /// Transmits the stream data, `length` is the payload length.
+    Data,
+    /// Updates the send window of the stream, `length` is the window delta.
+    WindowUpdate,
+    /// Measures the RTT or keeps the session alive, `length` is the opaque value.
+    Ping,
+    /// Terminates the session, `length` is the [`GoAwayCode`].
+    GoAway,
+}
+
+impl TryFrom<u8> for FrameType {
+    type Error = io::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x0 => Ok(FrameType::Data),
+            0x1 => Ok(FrameType::WindowUpdate),
+            0x2 => Ok(FrameType::Ping),
+            0x3 => Ok(FrameType::GoAway),
+            _ => Err(protocol_error("invalid frame type")),
+        }
+    }
+}
+
+impl From<FrameType> for u8 {
+    fn from(value: FrameType) -> Self {
+        match value {
+            FrameType::Data => 0x0,
+            FrameType::WindowUpdate => 0x1,
+            FrameType::Ping => 0x2,
+            FrameType::GoAway => 0x3,
+        }
+    }
+}
+
+/// The flags of yamux frame.
+#[bitmask(u16)]
+pub enum Flags {
+    /// Opens a new stream, or starts a ping.
+    Syn,
+    /// Acknowledges a new stream, or responds a ping.
+    Ack,
+    /// Half-closes the sending side of the stream.
+    Fin,
+    /// Resets the stream immediately.
+    Rst,
+}
+
+/// The error code of [`GoAway`](FrameType::GoAway) frame.
+pub struct GoAwayCode;
+
+impl GoAwayCode {
+    pub const NORMAL: u32 = 0;
+    pub const PROTOCOL_ERROR: u32 = 1;
+    pub const INTERNAL_ERROR: u32 = 2;
+}
+
+pub(crate) fn protocol_error(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("mux protocol error: {}", msg),
+    )
+}
+
+/// Yamux frame, see the [specification](https://github.com/hashicorp/yamux/blob/master/spec.md) for more information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub ty: FrameType,
+    pub flags: Flags,
+    pub stream_id: u32,
+    /// The payload length of data frame, or the type specific value of other frames.
+    pub length: u32,
+    /// The payload of data frame, always empty for other frames.
+    pub payload: Bytes,
+}
+
+impl Frame {
+    /// Create new data frame.
+    pub fn data(stream_id: u32, flags: Flags, payload: Bytes) -> Self {
+        Self {
+            ty: FrameType::Data,
+            flags,
+            stream_id,
+            length: payload.len() as u32,
+            payload,
+        }
+    }
+
+    /// Create new window update frame.
+    pub fn window_update(stream_id: u32, flags: Flags, delta: u32) -> Self {
+        Self::header(FrameType::WindowUpdate, flags, stream_id, delta)
+    }
+
+    /// Create new ping frame.
+    pub fn ping(flags: Flags, opaque: u32) -> Self {
+        Self::header(FrameType::Ping, flags, SESSION_ID, opaque)
+    }
+
+    /// Create new go away frame.
+    pub fn go_away(code: u32) -> Self {
+        Self::header(FrameType::GoAway, Flags::none(), SESSION_ID, code)
+    }
+
+    fn header(ty: FrameType, flags: Flags, stream_id: u32, length: u32) -> Self {
+        Self {
+            ty,
+            flags,
+            stream_id,
+            length,
+            payload: Bytes::new(),
+        }
+    }
+}
+
+/// The codec of yamux frames.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameCodec {
+    max_frame_size: usize,
+}
+
+impl FrameCodec {
+    /// Create new codec, the data frame longer than `max_frame_size` is rejected.
+    pub fn new(max_frame_size: usize) -> Self {
+        Self { max_frame_size }
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        if src[0] != VERSION {
+            return Err(protocol_error("unsupported version"));
+        }
+
+        let ty = FrameType::try_from(src[1])?;
+        let flags = Flags::from(u16::from_be_bytes([src[2], src[3]]));
+        let stream_id = u32::from_be_bytes(src[4..8].try_into().unwrap());
+        let length = u32::from_be_bytes(src[8..12].try_into().unwrap());
+
+        let payload_len = if ty == FrameType::Data {
+            length as usize
+        } else {
+            0
+        };
+
+        if payload_len > self.max_frame_size {
+            return Err(protocol_error("data frame too large"));
+        }
+
+        if src.len() < HEADER_LEN + payload_len {
+            src.reserve(HEADER_LEN + payload_len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(HEADER_LEN);
+
+        Ok(Some(Frame {
+            ty,
+            flags,
+            stream_id,
+            length,
+            payload: src.split_to(payload_len).freeze(),
+        }))
+    }
+}
+
+impl Encoder<Frame> for FrameCodec {
+    fn encode(&mut self, item: Frame, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(HEADER_LEN + item.payload.len());
+
+        dst.put_u8(VERSION);
+        dst.put_u8(item.ty.into());
+        dst.put_u16(item.flags.bits());
+        dst.put_u32(item.stream_id);
+        dst.put_u32(item.length);
+        dst.put_slice(&item.payload);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codec() {
+        let mut codec = FrameCodec::new(1024);
+
+        let mut buf = BytesMut::new();
+
+        codec
+            .encode(
+                Frame::data(1, Flags::Syn, Bytes::from_static(b"hello")),
+                &mut buf,
+            )
+            .unwrap();
+
+        assert_eq!(
+            &buf[..HEADER_LEN],
+            &[0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 5][..]
+        );
+
+        codec.encode(Frame::ping(Flags::Ack, 7), &mut buf).unwrap();
+
+        // Incomplete payload.
+        let mut partial = BytesMut::from(&buf[..HEADER_LEN + 2]);
+
+        assert_eq!(codec.decode(&mut partial).unwrap(), None);
+
+        assert_eq!(
+            codec.decode(&mut buf).unwrap().unwrap(),
+            Frame::data(1, Flags::Syn, Bytes::from_static(b"hello"))
+        );
+
+        assert_eq!(
+            codec.decode(&mut buf).unwrap().unwrap(),
+            Frame::ping(Flags::Ack, 7)
+        );
+
+        assert!(buf.is_empty());
+
+        codec
+            .encode(
+                Frame::data(3, Flags::none(), vec![0; 2048].into()),
+                &mut buf,
+            )
+            .unwrap();
+
+        codec.decode(&mut buf).unwrap_err();
+    }
+}
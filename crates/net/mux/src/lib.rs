@@ -0,0 +1,14 @@
+//! Stream multiplexing over any [`AsyncRead`](futures::AsyncRead) + [`AsyncWrite`](futures::AsyncWrite) io,
+//! which is compatible with the [yamux](https://github.com/hashicorp/yamux/blob/master/spec.md) protocol.
+//!
+//! Create a [`MuxSession`] and spawn its [`MuxDriver`], then use [`open_stream`](MuxSession::open_stream)
+//! and [`accept_stream`](MuxSession::accept_stream) to create the logical streams.
+
+mod frame;
+pub use frame::*;
+
+mod session;
+pub use session::*;
+
+mod stream;
+pub use stream::*;
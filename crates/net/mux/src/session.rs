@@ -0,0 +1,749 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use bytes::BytesMut;
+use futures::{future::poll_fn, ready, AsyncRead, AsyncWrite, Sink, Stream};
+use hala_codec::Framed;
+use hala_io::{interval_at, Interval, MissedTickBehavior};
+use hala_sync::{Lockable, LockableNew, SpinMutex};
+
+use crate::{
+    frame::protocol_error, Flags, Frame, FrameCodec, FrameType, GoAwayCode, MuxStream,
+    DEFAULT_WINDOW, SESSION_ID,
+};
+
+/// The side of the session, the client opens the odd stream ids and the server opens the even ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Client,
+    Server,
+}
+
+/// The configuration of [`MuxSession`].
+#[derive(Debug, Clone)]
+pub struct MuxConfig {
+    /// The receive window of each stream, which is announced to the peer when the stream is opened.
+    /// The value less than the default window 256KB is ignored.
+    pub receive_window: u32,
+    /// The max payload size of one sent data frame.
+    pub max_frame_size: usize,
+    /// The max number of inbound streams waiting to be accepted, the others are reset.
+    pub accept_backlog: usize,
+    /// The max number of active streams, including the streams waiting to be accepted.
+    pub max_streams: usize,
+    /// The interval of keepalive pings, the session is closed if the previous ping is not
+    /// responded when the next ping is sent. `None` disables keepalive.
+    pub keepalive_interval: Option<Duration>,
+}
+
+impl Default for MuxConfig {
+    fn default() -> Self {
+        Self {
+            receive_window: DEFAULT_WINDOW,
+            max_frame_size: 16 * 1024,
+            accept_backlog: 256,
+            max_streams: 8192,
+            keepalive_interval: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+/// The state of one stream.
+#[derive(Debug)]
+pub(crate) struct StreamState {
+    /// The received data, which has not been read.
+    pub(crate) recv_buf: BytesMut,
+    /// The remaining bytes the peer can send.
+    pub(crate) recv_window: u32,
+    /// The bytes read since last window update.
+    pub(crate) consumed: u32,
+    /// The remaining bytes can be sent to the peer.
+    pub(crate) send_window: u32,
+    pub(crate) read_waker: Option<Waker>,
+    pub(crate) write_waker: Option<Waker>,
+    pub(crate) local_fin: bool,
+    pub(crate) remote_fin: bool,
+    pub(crate) reset: bool,
+}
+
+impl StreamState {
+    fn new(receive_window: u32) -> Self {
+        Self {
+            recv_buf: BytesMut::new(),
+            recv_window: receive_window,
+            consumed: 0,
+            send_window: DEFAULT_WINDOW,
+            read_waker: None,
+            write_waker: None,
+            local_fin: false,
+            remote_fin: false,
+            reset: false,
+        }
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The state of session, shared by the session, streams and driver.
+pub(crate) struct SessionState {
+    mode: Mode,
+    pub(crate) config: MuxConfig,
+    next_id: u32,
+    pub(crate) streams: HashMap<u32, StreamState>,
+    incoming: VecDeque<u32>,
+    accept_waker: Option<Waker>,
+    /// The frames waiting to be sent by driver.
+    outbound: VecDeque<Frame>,
+    driver_waker: Option<Waker>,
+    /// The opaque value of the sent ping, which has not been responded.
+    ping: Option<u32>,
+    next_ping: u32,
+    go_away_sent: bool,
+    go_away_received: bool,
+    /// The session is terminated, the error kind is returned by the streams.
+    pub(crate) closed: Option<io::ErrorKind>,
+}
+
+impl SessionState {
+    /// Queues the `frame` and wakes up the driver.
+    pub(crate) fn send(&mut self, frame: Frame) {
+        self.outbound.push_back(frame);
+
+        if let Some(waker) = self.driver_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// The window delta announced when the stream is opened.
+    fn extra_window(&self) -> u32 {
+        self.config.receive_window.saturating_sub(DEFAULT_WINDOW)
+    }
+
+    fn is_remote_id(&self, id: u32) -> bool {
+        match self.mode {
+            Mode::Client => id.is_multiple_of(2),
+            Mode::Server => !id.is_multiple_of(2),
+        }
+    }
+
+    pub(crate) fn closed_error(&self) -> Option<io::Error> {
+        self.closed
+            .map(|kind| io::Error::new(kind, "mux session closed"))
+    }
+
+    /// Terminates the session, and wakes up all streams.
+    fn terminate(&mut self, kind: io::ErrorKind) {
+        if self.closed.is_none() {
+            self.closed = Some(kind);
+        }
+
+        for stream in self.streams.values_mut() {
+            stream.wake();
+        }
+
+        if let Some(waker) = self.accept_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn on_frame(&mut self, frame: Frame) -> io::Result<()> {
+        log::trace!(
+            "mux recv frame, ty={:?}, flags={:?}, stream_id={}, length={}",
+            frame.ty,
+            frame.flags,
+            frame.stream_id,
+            frame.length
+        );
+
+        match frame.ty {
+            FrameType::Data | FrameType::WindowUpdate => self.on_stream_frame(frame),
+            FrameType::Ping => {
+                if frame.flags.contains(Flags::Syn) {
+                    self.send(Frame::ping(Flags::Ack, frame.length));
+                } else if frame.flags.contains(Flags::Ack) && self.ping == Some(frame.length) {
+                    self.ping = None;
+                }
+
+                Ok(())
+            }
+            FrameType::GoAway => {
+                if frame.length != GoAwayCode::NORMAL {
+                    log::warn!("mux session go away, code={}", frame.length);
+                }
+
+                self.go_away_received = true;
+
+                self.terminate(io::ErrorKind::BrokenPipe);
+
+                Ok(())
+            }
+        }
+    }
+
+    fn on_stream_frame(&mut self, frame: Frame) -> io::Result<()> {
+        let id = frame.stream_id;
+
+        if id == SESSION_ID {
+            return Err(protocol_error("stream frame with session id"));
+        }
+
+        if frame.flags.contains(Flags::Syn) {
+            if !self.is_remote_id(id) || self.streams.contains_key(&id) {
+                return Err(protocol_error("invalid stream id"));
+            }
+
+            if self.go_away_sent
+                || self.incoming.len() >= self.config.accept_backlog
+                || self.streams.len() >= self.config.max_streams
+            {
+                log::trace!("mux refuse inbound stream, stream_id={}", id);
+
+                self.send(Frame::window_update(id, Flags::Rst, 0));
+
+                return Ok(());
+            }
+
+            self.streams
+                .insert(id, StreamState::new(self.config.receive_window));
+
+            self.send(Frame::window_update(id, Flags::Ack, self.extra_window()));
+
+            self.incoming.push_back(id);
+
+            if let Some(waker) = self.accept_waker.take() {
+                waker.wake();
+            }
+        }
+
+        let Some(stream) = self.streams.get_mut(&id) else {
+            // The stream has been dropped by this side.
+            log::trace!("mux drop frame of unknown stream, stream_id={}", id);
+            return Ok(());
+        };
+
+        match frame.ty {
+            FrameType::WindowUpdate => {
+                stream.send_window = stream.send_window.saturating_add(frame.length);
+            }
+            _ => {
+                if frame.length > stream.recv_window {
+                    return Err(protocol_error("receive window exceeded"));
+                }
+
+                stream.recv_window -= frame.length;
+                stream.recv_buf.extend_from_slice(&frame.payload);
+            }
+        }
+
+        if frame.flags.contains(Flags::Fin) {
+            stream.remote_fin = true;
+        }
+
+        if frame.flags.contains(Flags::Rst) {
+            stream.reset = true;
+        }
+
+        stream.wake();
+
+        Ok(())
+    }
+
+    fn on_keepalive(&mut self) -> io::Result<()> {
+        if self.ping.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "mux keepalive ping timeout",
+            ));
+        }
+
+        let opaque = self.next_ping;
+
+        self.next_ping = self.next_ping.wrapping_add(1);
+        self.ping = Some(opaque);
+
+        self.send(Frame::ping(Flags::Syn, opaque));
+
+        Ok(())
+    }
+}
+
+/// The handle of multiplexing session, which can be cloned and shared between tasks.
+///
+/// The frames are sent and received by the [`MuxDriver`] returned by [`new`](Self::new),
+/// which must be spawned, e.g. by [`io_spawn`](hala_io::current::executor::io_spawn).
+#[derive(Clone)]
+pub struct MuxSession {
+    state: Arc<SpinMutex<SessionState>>,
+}
+
+impl Drop for MuxSession {
+    fn drop(&mut self) {
+        // The driver completes once the session and all streams are dropped.
+        if let Some(waker) = self.state.lock().driver_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl MuxSession {
+    /// Create new session over `io`, and returns the session handle and its driver.
+    pub fn new<T>(io: T, mode: Mode, config: MuxConfig) -> io::Result<(Self, MuxDriver<T>)> {
+        let codec = FrameCodec::new(config.max_frame_size.max(config.receive_window as usize));
+
+        let keepalive = config
+            .keepalive_interval
+            .map(|period| {
+                interval_at(Instant::now() + period, period).map(|mut interval| {
+                    // The burst ticks would treat the ping in flight as timeout.
+                    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                    interval
+                })
+            })
+            .transpose()?;
+
+        let state = Arc::new(SpinMutex::new(SessionState {
+            mode,
+            config,
+            next_id: match mode {
+                Mode::Client => 1,
+                Mode::Server => 2,
+            },
+            streams: Default::default(),
+            incoming: Default::default(),
+            accept_waker: None,
+            outbound: Default::default(),
+            driver_waker: None,
+            ping: None,
+            next_ping: 0,
+            go_away_sent: false,
+            go_away_received: false,
+            closed: None,
+        }));
+
+        let driver = MuxDriver {
+            framed: Framed::new(io, codec),
+            state: state.clone(),
+            keepalive,
+            flushing: false,
+        };
+
+        Ok((Self { state }, driver))
+    }
+
+    /// Opens a new outbound stream.
+    ///
+    /// The peer is notified by the first frame of the stream, so this function doesn't wait for it.
+    pub fn open_stream(&self) -> io::Result<MuxStream> {
+        let mut state = self.state.lock();
+
+        if let Some(err) = state.closed_error() {
+            return Err(err);
+        }
+
+        if state.go_away_sent || state.go_away_received {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "mux session is going away",
+            ));
+        }
+
+        if state.streams.len() >= state.config.max_streams {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("too many mux streams, max={}", state.config.max_streams),
+            ));
+        }
+
+        let id = state.next_id;
+
+        state.next_id = id.checked_add(2).ok_or(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "mux stream ids exhausted",
+        ))?;
+
+        let stream = StreamState::new(state.config.receive_window);
+
+        state.streams.insert(id, stream);
+
+        let delta = state.extra_window();
+
+        state.send(Frame::window_update(id, Flags::Syn, delta));
+
+        Ok(MuxStream::new(id, self.state.clone()))
+    }
+
+    /// Polls for an inbound stream.
+    pub fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<MuxStream>> {
+        let mut state = self.state.lock();
+
+        if let Some(id) = state.incoming.pop_front() {
+            return Poll::Ready(Ok(MuxStream::new(id, self.state.clone())));
+        }
+
+        if let Some(err) = state.closed_error() {
+            return Poll::Ready(Err(err));
+        }
+
+        state.accept_waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+
+    /// Accepts an inbound stream, returns error if the session is closed.
+    pub async fn accept_stream(&self) -> io::Result<MuxStream> {
+        poll_fn(|cx| self.poll_accept(cx)).await
+    }
+
+    /// Sends the go away frame and closes the session once the queued frames are sent.
+    ///
+    /// The session is also closed when the go away frame is received, the active streams
+    /// are terminated with [`BrokenPipe`](io::ErrorKind::BrokenPipe) error.
+    pub fn close(&self) {
+        let mut state = self.state.lock();
+
+        if !state.go_away_sent && state.closed.is_none() {
+            state.go_away_sent = true;
+
+            state.send(Frame::go_away(GoAwayCode::NORMAL));
+        }
+    }
+
+    /// Returns true if the session is closed.
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().closed.is_some()
+    }
+
+    /// Returns the number of active streams.
+    pub fn streams(&self) -> usize {
+        self.state.lock().streams.len()
+    }
+}
+
+/// The future which sends and receives the frames of [`MuxSession`].
+///
+/// It completes when the session is closed by [`close`](MuxSession::close), by the peer,
+/// or once the session and all its streams are dropped.
+pub struct MuxDriver<T> {
+    framed: Framed<T, FrameCodec>,
+    state: Arc<SpinMutex<SessionState>>,
+    keepalive: Option<Interval>,
+    /// True if the frames are written but not yet flushed.
+    flushing: bool,
+}
+
+impl<T> Drop for MuxDriver<T> {
+    fn drop(&mut self) {
+        self.state.lock().terminate(io::ErrorKind::BrokenPipe);
+    }
+}
+
+impl<T> MuxDriver<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_keepalive(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let Some(interval) = self.keepalive.as_mut() else {
+            return Poll::Pending;
+        };
+
+        ready!(interval.poll_tick(cx))?;
+
+        Poll::Ready(self.state.lock().on_keepalive())
+    }
+
+    /// Writes the queued frames, returns `Ready` if all frames are sent and flushed.
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            ready!(Sink::<Frame>::poll_ready(Pin::new(&mut self.framed), cx))?;
+
+            let frame = {
+                let mut state = self.state.lock();
+
+                let frame = state.outbound.pop_front();
+
+                if frame.is_none() {
+                    state.driver_waker = Some(cx.waker().clone());
+                }
+
+                frame
+            };
+
+            let Some(frame) = frame else {
+                break;
+            };
+
+            Pin::new(&mut self.framed).start_send(frame)?;
+
+            self.flushing = true;
+        }
+
+        if self.flushing {
+            ready!(Sink::<Frame>::poll_flush(Pin::new(&mut self.framed), cx))?;
+
+            self.flushing = false;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_run(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            while let Poll::Ready(result) = self.poll_keepalive(cx) {
+                result?;
+            }
+
+            let sent = self.poll_send(cx)?.is_ready();
+
+            let state = self.state.lock();
+
+            let closing = state.go_away_sent || state.go_away_received;
+
+            // Only the driver holds the state.
+            let dropped = Arc::strong_count(&self.state) == 1;
+
+            // The frames may be queued after `poll_send` returns.
+            let idle = sent && state.outbound.is_empty();
+
+            drop(state);
+
+            if sent && !idle {
+                continue;
+            }
+
+            if idle && (closing || dropped) {
+                // The peer may have closed the io after the go away frame.
+                if let Err(err) = ready!(Pin::new(&mut self.framed).poll_close(cx)) {
+                    log::trace!("mux session close io failed, err={}", err);
+                }
+
+                return Poll::Ready(Ok(()));
+            }
+
+            // Stops reading once the go away frame is sent or received.
+            if closing {
+                return Poll::Pending;
+            }
+
+            match Pin::new(&mut self.framed).poll_next(cx) {
+                Poll::Ready(Some(frame)) => {
+                    let frame = frame?;
+
+                    self.state.lock().on_frame(frame)?;
+                }
+                Poll::Ready(None) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "mux session closed by peer",
+                    )));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> Future for MuxDriver<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let result = ready!(this.poll_run(cx));
+
+        let mut state = this.state.lock();
+
+        match &result {
+            Ok(_) => state.terminate(io::ErrorKind::BrokenPipe),
+            Err(err) => {
+                log::trace!("mux session terminated, err={}", err);
+
+                state.terminate(err.kind());
+            }
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use hala_io::{current::executor::io_spawn, sleep, test::io_test};
+    use hala_tcp::{TcpListener, TcpStream};
+
+    use super::*;
+
+    async fn mock_sessions(config: MuxConfig) -> (MuxSession, MuxSession) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (server, _) = listener.accept().await.unwrap();
+
+        let (client, driver) = MuxSession::new(client, Mode::Client, config.clone()).unwrap();
+
+        io_spawn(driver).unwrap();
+
+        let (server, driver) = MuxSession::new(server, Mode::Server, config).unwrap();
+
+        io_spawn(driver).unwrap();
+
+        (client, server)
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_echo() {
+        let (client, server) = mock_sessions(Default::default()).await;
+
+        io_spawn(async move {
+            loop {
+                let mut stream = server.accept_stream().await?;
+
+                io_spawn(async move {
+                    let mut buf = vec![0; 1024];
+
+                    loop {
+                        let read_size = stream.read(&mut buf).await?;
+
+                        if read_size == 0 {
+                            return stream.close().await;
+                        }
+
+                        stream.write_all(&buf[..read_size]).await?;
+                    }
+                })?;
+            }
+        })
+        .unwrap();
+
+        let mut streams = vec![];
+
+        for _ in 0..10 {
+            streams.push(client.open_stream().unwrap());
+        }
+
+        assert_eq!(
+            streams.iter().map(|s| s.id()).collect::<Vec<_>>(),
+            (0..10).map(|i| i * 2 + 1).collect::<Vec<_>>()
+        );
+
+        for stream in &mut streams {
+            let data = format!("hello {}", stream.id());
+
+            stream.write_all(data.as_bytes()).await.unwrap();
+
+            let mut buf = vec![0; data.len()];
+
+            stream.read_exact(&mut buf).await.unwrap();
+
+            assert_eq!(buf, data.as_bytes());
+        }
+
+        for mut stream in streams {
+            stream.close().await.unwrap();
+
+            let mut buf = vec![];
+
+            stream.read_to_end(&mut buf).await.unwrap();
+
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_flow_control() {
+        let (client, server) = mock_sessions(Default::default()).await;
+
+        const LEN: usize = DEFAULT_WINDOW as usize * 4;
+
+        // Keeps the client session alive until all data is read.
+        let session = client.clone();
+
+        io_spawn(async move {
+            let mut stream = session.open_stream()?;
+
+            stream.write_all(&vec![7; LEN]).await?;
+
+            stream.close().await
+        })
+        .unwrap();
+
+        let mut stream = server.accept_stream().await.unwrap();
+
+        let recv_window = |server: &MuxSession| {
+            let state = server.state.lock();
+
+            let stream = state.streams.get(&stream.id()).unwrap();
+
+            (stream.recv_buf.len(), stream.recv_window)
+        };
+
+        while recv_window(&server).1 > 0 {
+            sleep(Duration::from_millis(10)).await.unwrap();
+        }
+
+        // The writer is blocked by the receive window.
+        sleep(Duration::from_millis(50)).await.unwrap();
+
+        assert_eq!(recv_window(&server), (DEFAULT_WINDOW as usize, 0));
+
+        let mut buf = vec![];
+
+        stream.read_to_end(&mut buf).await.unwrap();
+
+        assert_eq!(buf, vec![7; LEN]);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_keepalive_and_close() {
+        let config = MuxConfig {
+            keepalive_interval: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        let (client, server) = mock_sessions(config).await;
+
+        sleep(Duration::from_millis(200)).await.unwrap();
+
+        assert!(!client.is_closed());
+        assert!(!server.is_closed());
+
+        let mut stream = server.open_stream().unwrap();
+
+        assert_eq!(stream.id(), 2);
+
+        let mut accepted = client.accept_stream().await.unwrap();
+
+        client.close();
+
+        server.accept_stream().await.unwrap_err();
+
+        assert!(server.is_closed());
+
+        let err = stream.write_all(b"hello").await.unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+
+        accepted.read(&mut [0; 1]).await.unwrap_err();
+
+        client.open_stream().unwrap_err();
+    }
+}
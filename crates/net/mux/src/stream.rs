@@ -0,0 +1,194 @@
+use std::{
+    fmt::Debug,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes};
+use futures::{AsyncRead, AsyncWrite};
+use hala_sync::{Lockable, SpinMutex};
+
+use crate::{session::SessionState, Flags, Frame};
+
+/// The logical stream of [`MuxSession`](crate::MuxSession).
+///
+/// Dropping the stream half-closes its sending side if it is not closed yet,
+/// the data received after that is discarded.
+pub struct MuxStream {
+    id: u32,
+    state: Arc<SpinMutex<SessionState>>,
+}
+
+impl Debug for MuxStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MuxStream(id={})", self.id)
+    }
+}
+
+impl MuxStream {
+    pub(crate) fn new(id: u32, state: Arc<SpinMutex<SessionState>>) -> Self {
+        Self { id, state }
+    }
+
+    /// Returns the stream id.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Resets the stream, the pending data of both sides is discarded.
+    pub fn reset(&self) {
+        let mut state = self.state.lock();
+
+        if let Some(stream) = state.streams.get_mut(&self.id) {
+            if stream.reset {
+                return;
+            }
+
+            stream.reset = true;
+            stream.local_fin = true;
+
+            state.send(Frame::window_update(self.id, Flags::Rst, 0));
+        }
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+
+        if let Some(stream) = state.streams.remove(&self.id) {
+            if !stream.local_fin && state.closed.is_none() {
+                state.send(Frame::window_update(self.id, Flags::Fin, 0));
+            }
+        }
+    }
+}
+
+fn reset_error() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionReset, "mux stream reset")
+}
+
+impl AsyncRead for MuxStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.state.lock();
+
+        let window = state.config.receive_window;
+        let closed = state.closed_error();
+
+        let stream = state.streams.get_mut(&self.id).expect("mux stream state");
+
+        if stream.reset {
+            return Poll::Ready(Err(reset_error()));
+        }
+
+        if stream.recv_buf.is_empty() {
+            if stream.remote_fin {
+                return Poll::Ready(Ok(0));
+            }
+
+            if let Some(err) = closed {
+                return Poll::Ready(Err(err));
+            }
+
+            stream.read_waker = Some(cx.waker().clone());
+
+            return Poll::Pending;
+        }
+
+        let read_size = buf.len().min(stream.recv_buf.len());
+
+        buf[..read_size].copy_from_slice(&stream.recv_buf[..read_size]);
+
+        stream.recv_buf.advance(read_size);
+
+        stream.consumed += read_size as u32;
+
+        // Announces the consumed bytes when half of the window is read.
+        if stream.consumed >= window / 2 && !stream.remote_fin && closed.is_none() {
+            let delta = stream.consumed;
+
+            stream.recv_window += delta;
+            stream.consumed = 0;
+
+            state.send(Frame::window_update(self.id, Flags::none(), delta));
+        }
+
+        Poll::Ready(Ok(read_size))
+    }
+}
+
+impl AsyncWrite for MuxStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.state.lock();
+
+        let max_frame_size = state.config.max_frame_size;
+        let closed = state.closed_error();
+
+        let stream = state.streams.get_mut(&self.id).expect("mux stream state");
+
+        if stream.reset {
+            return Poll::Ready(Err(reset_error()));
+        }
+
+        if let Some(err) = closed {
+            return Poll::Ready(Err(err));
+        }
+
+        if stream.local_fin {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "mux stream closed",
+            )));
+        }
+
+        if stream.send_window == 0 {
+            stream.write_waker = Some(cx.waker().clone());
+
+            return Poll::Pending;
+        }
+
+        let write_size = buf
+            .len()
+            .min(stream.send_window as usize)
+            .min(max_frame_size);
+
+        stream.send_window -= write_size as u32;
+
+        state.send(Frame::data(
+            self.id,
+            Flags::none(),
+            Bytes::copy_from_slice(&buf[..write_size]),
+        ));
+
+        Poll::Ready(Ok(write_size))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // The frames are sent by the session driver.
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.state.lock();
+
+        let stream = state.streams.get_mut(&self.id).expect("mux stream state");
+
+        if !stream.local_fin {
+            stream.local_fin = true;
+
+            state.send(Frame::window_update(self.id, Flags::Fin, 0));
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
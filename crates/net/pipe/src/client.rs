@@ -0,0 +1,97 @@
+use std::{
+    fmt::Debug,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite};
+#[cfg(feature = "current")]
+use hala_io::current::*;
+use hala_io::*;
+
+use crate::fd::PipeFd;
+
+/// The client end of named pipe.
+pub struct NamedPipeClient {
+    fd: PipeFd,
+}
+
+impl Debug for NamedPipeClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NamedPipeClient({:?})", self.fd)
+    }
+}
+
+impl NamedPipeClient {
+    /// Connects to the named pipe server `name`, e.g. `\\.\pipe\hala`.
+    ///
+    /// Returns the os error `ERROR_PIPE_BUSY` if there is no pending server instance.
+    #[cfg(feature = "current")]
+    pub fn connect(name: &str) -> io::Result<Self> {
+        Self::connect_with(name, get_driver()?, get_poller()?)
+    }
+
+    /// Connects to the named pipe server `name` with providing `driver` / `poller`.
+    pub fn connect_with(name: &str, driver: Driver, poller: Handle) -> io::Result<Self> {
+        Ok(Self {
+            fd: PipeFd::open(OpenFlags::PipeClient(name), driver, poller)?,
+        })
+    }
+}
+
+impl AsyncRead for NamedPipeClient {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for NamedPipeClient {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for &NamedPipeClient {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for &NamedPipeClient {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
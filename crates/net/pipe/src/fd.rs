@@ -0,0 +1,79 @@
+use std::{
+    fmt::Debug,
+    io,
+    task::{Context, Poll},
+};
+
+use hala_io::*;
+
+/// The named pipe handle registered with poller, shared by the server and the client end.
+pub(crate) struct PipeFd {
+    fd: OwnedHandle,
+    poller: Handle,
+}
+
+impl Debug for PipeFd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.fd)
+    }
+}
+
+impl PipeFd {
+    /// Opens the named pipe with `open_flags` and registers it with `poller`.
+    pub(crate) fn open(open_flags: OpenFlags, driver: Driver, poller: Handle) -> io::Result<Self> {
+        let fd = OwnedHandle::open(driver, Description::NamedPipe, open_flags)?;
+
+        fd.register(
+            poller,
+            Interest::Readable | Interest::Writable,
+            PollMode::Edge,
+        )?;
+
+        Ok(Self { fd, poller })
+    }
+
+    /// Waits for a client to connect to this server instance.
+    pub(crate) async fn connect(&self) -> io::Result<()> {
+        would_block(|cx| self.fd.fd_cntl(Cmd::PollConnect(cx.waker().clone())))
+            .await
+            .map(|_| ())
+    }
+
+    pub(crate) fn poll_read(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_would_block(|| {
+            self.fd
+                .fd_cntl(Cmd::Read {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_datalen()
+        })
+    }
+
+    pub(crate) fn poll_write(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        poll_would_block(|| {
+            self.fd
+                .fd_cntl(Cmd::Write {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_datalen()
+        })
+    }
+}
+
+impl Drop for PipeFd {
+    fn drop(&mut self) {
+        if let Err(err) = self.fd.deregister(self.poller) {
+            log::error!(
+                "deregister named pipe failed, fd={:?}, err={}",
+                self.fd,
+                err
+            );
+        }
+    }
+}
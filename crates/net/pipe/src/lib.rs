@@ -0,0 +1,12 @@
+//! Asynchronous windows named pipe.
+//!
+//! The named pipe driver is only implemented on windows, the other platforms
+//! return [`Unsupported`](std::io::ErrorKind::Unsupported) error.
+
+mod fd;
+
+mod server;
+pub use server::*;
+
+mod client;
+pub use client::*;
@@ -0,0 +1,200 @@
+use std::{
+    fmt::Debug,
+    io, mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{stream, AsyncRead, AsyncWrite, Stream};
+#[cfg(feature = "current")]
+use hala_io::current::*;
+use hala_io::*;
+
+use crate::fd::PipeFd;
+
+/// One instance of the named pipe server, which serves one client at a time.
+pub struct NamedPipeServer {
+    fd: PipeFd,
+}
+
+impl Debug for NamedPipeServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NamedPipeServer({:?})", self.fd)
+    }
+}
+
+impl NamedPipeServer {
+    /// Creates a new instance of the named pipe server `name`, e.g. `\\.\pipe\hala`.
+    #[cfg(feature = "current")]
+    pub fn create(name: &str) -> io::Result<Self> {
+        Self::create_with(name, get_driver()?, get_poller()?)
+    }
+
+    /// Creates a new instance of the named pipe server `name` with providing `driver` / `poller`.
+    pub fn create_with(name: &str, driver: Driver, poller: Handle) -> io::Result<Self> {
+        Ok(Self {
+            fd: PipeFd::open(OpenFlags::PipeServer(name), driver, poller)?,
+        })
+    }
+
+    /// Waits for a client to connect to this instance.
+    pub async fn connect(&self) -> io::Result<()> {
+        self.fd.connect().await
+    }
+}
+
+impl AsyncRead for NamedPipeServer {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for NamedPipeServer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for &NamedPipeServer {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for &NamedPipeServer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The accept loop of named pipe server.
+///
+/// The listener always keeps one pending server instance, so the clients connecting
+/// between two [`accept`](Self::accept) calls don't get the `ERROR_PIPE_BUSY` error.
+pub struct NamedPipeListener {
+    name: String,
+    driver: Driver,
+    poller: Handle,
+    next: NamedPipeServer,
+}
+
+impl Debug for NamedPipeListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NamedPipeListener({})", self.name)
+    }
+}
+
+impl NamedPipeListener {
+    /// Creates the first instance of the named pipe server `name`.
+    #[cfg(feature = "current")]
+    pub fn bind(name: &str) -> io::Result<Self> {
+        Self::bind_with(name, get_driver()?, get_poller()?)
+    }
+
+    /// Creates the first instance of the named pipe server `name` with providing `driver` / `poller`.
+    pub fn bind_with(name: &str, driver: Driver, poller: Handle) -> io::Result<Self> {
+        let next = NamedPipeServer::create_with(name, driver.clone(), poller)?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            driver,
+            poller,
+            next,
+        })
+    }
+
+    /// Returns the name of the named pipe.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Waits for a client to connect, returns the connected server instance.
+    pub async fn accept(&mut self) -> io::Result<NamedPipeServer> {
+        self.next.connect().await?;
+
+        let next = NamedPipeServer::create_with(&self.name, self.driver.clone(), self.poller)?;
+
+        Ok(mem::replace(&mut self.next, next))
+    }
+
+    /// Returns a stream of incoming connections, which yields the result of [`accept`](Self::accept) forever.
+    pub fn incoming(&mut self) -> impl Stream<Item = io::Result<NamedPipeServer>> + '_ {
+        stream::unfold(self, |listener| async move {
+            Some((listener.accept().await, listener))
+        })
+    }
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use hala_io::{current::executor::io_spawn, test::io_test};
+
+    use crate::{NamedPipeClient, NamedPipeListener};
+
+    #[hala_test::test(io_test)]
+    async fn test_echo() {
+        let name = r"\\.\pipe\hala-pipe-test-echo";
+
+        let mut listener = NamedPipeListener::bind(name).unwrap();
+
+        io_spawn(async move {
+            let mut server = listener.accept().await?;
+
+            let mut buf = vec![0; 1024];
+
+            loop {
+                let read_size = server.read(&mut buf).await?;
+
+                if read_size == 0 {
+                    return Ok(());
+                }
+
+                server.write_all(&buf[..read_size]).await?;
+            }
+        })
+        .unwrap();
+
+        let mut client = NamedPipeClient::connect(name).unwrap();
+
+        client.write_all(b"hello world").await.unwrap();
+
+        let mut buf = vec![0; 11];
+
+        client.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(buf, b"hello world");
+    }
+}
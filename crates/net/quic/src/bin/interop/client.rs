@@ -0,0 +1,104 @@
+//! The scenarios of hala-quic client against `quiche-server`.
+
+use std::{io, sync::Arc, time::Duration};
+
+use hala_quic::{Config, MemorySessionStore, QuicConn};
+
+use crate::{
+    endpoint_config, hq,
+    peer::{free_addr, PeerProcess, TempDir},
+    scenario::{Scenario, Spec},
+    Options,
+};
+
+/// The delay before connecting, which waits for `quiche-server` binding the listen address.
+const STARTUP_DELAY: Duration = Duration::from_millis(500);
+
+pub async fn run(options: Arc<Options>, spec: Spec) -> io::Result<()> {
+    let root = TempDir::new("www")?;
+
+    hq::generate(root.path(), &spec.files)?;
+
+    let raddr = free_addr()?;
+
+    let mut args = vec![
+        "--listen".to_owned(),
+        raddr.to_string(),
+        "--root".to_owned(),
+        root.path().display().to_string(),
+        "--cert".to_owned(),
+        options.cert.clone(),
+        "--key".to_owned(),
+        options.key.clone(),
+    ];
+
+    if spec.scenario != Scenario::Retry {
+        args.push("--no-retry".to_owned());
+    }
+
+    args.extend(spec.peer_args.iter().cloned());
+
+    let _server = PeerProcess::spawn(&options.quiche_server, args, options.verbose)?;
+
+    hala_io::sleep(STARTUP_DELAY).await?;
+
+    let config = || -> io::Result<Config> {
+        let version = match spec.scenario {
+            Scenario::VersionNegotiation => spec.version,
+            _ => quiche::PROTOCOL_VERSION,
+        };
+
+        let mut config = endpoint_config(Config::with_version(version)?, &spec)?;
+
+        config.verify_peer(false);
+
+        Ok(config)
+    };
+
+    match spec.scenario {
+        Scenario::Resumption => {
+            let store = Arc::new(MemorySessionStore::default());
+
+            for round in 0..2 {
+                let mut config = config()?;
+
+                config.set_session_store(store.clone());
+
+                let conn = QuicConn::connect_udp(raddr, &mut config).await?;
+
+                fetch_all(&conn, &spec.files).await?;
+
+                let is_resumed = conn.handshake_info().await.is_resumed;
+
+                conn.close(0, b"").await?;
+
+                if round == 1 && !is_resumed {
+                    return Err(io::Error::other("the second connection is not resumed"));
+                }
+            }
+        }
+        _ => {
+            let conn = QuicConn::connect_udp(raddr, &mut config()?).await?;
+
+            fetch_all(&conn, &spec.files).await?;
+
+            if spec.scenario == Scenario::Mtu {
+                crate::check_mtu(conn.current_mtu().await, &spec)?;
+            }
+
+            conn.close(0, b"").await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn fetch_all(conn: &QuicConn, files: &[usize]) -> io::Result<()> {
+    for size in files {
+        let body = hq::fetch(conn, &size.to_string()).await?;
+
+        hq::verify(*size, &body)?;
+    }
+
+    Ok(())
+}
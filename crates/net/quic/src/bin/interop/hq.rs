@@ -0,0 +1,119 @@
+//! The minimal `hq-interop` (HTTP/0.9 over QUIC) protocol spoken by the quiche example endpoints.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use hala_io::bytes::Bytes;
+use hala_quic::{QuicConn, QuicIncoming, QuicStream};
+
+/// The ALPN of `hq-interop` protocol.
+pub const ALPN: &[u8] = b"hq-interop";
+
+/// The response body of the missing file, the same as quiche-server.
+const NOT_FOUND: &[u8] = b"Not Found!";
+
+/// Requests `/path` on a new stream of `conn`, returns the response body.
+pub async fn fetch(conn: &QuicConn, path: &str) -> io::Result<Vec<u8>> {
+    let stream = conn.open_stream().await?;
+
+    stream
+        .send(format!("GET /{}\r\n", path).as_bytes(), true)
+        .await?;
+
+    let mut body = vec![];
+    let mut buf = vec![0; 65535];
+
+    loop {
+        let (read_size, fin) = stream.recv(&mut buf).await?;
+
+        body.extend_from_slice(&buf[..read_size]);
+
+        if fin {
+            return Ok(body);
+        }
+    }
+}
+
+/// Serves the files of `root` to the requests of `conn`, until the connection is closed.
+pub async fn serve(conn: &QuicConn, root: &Path) -> io::Result<()> {
+    while let Some(incoming) = conn.accept().await {
+        let QuicIncoming::Bidi(stream) = incoming else {
+            continue;
+        };
+
+        if let Err(err) = serve_stream(&stream, root).await {
+            log::warn!("stream {} serve failed, err={}", stream.id(), err);
+        }
+    }
+
+    Ok(())
+}
+
+async fn serve_stream(stream: &QuicStream, root: &Path) -> io::Result<()> {
+    let mut request = vec![];
+    let mut buf = vec![0; 1024];
+
+    loop {
+        let (read_size, fin) = stream.recv(&mut buf).await?;
+
+        request.extend_from_slice(&buf[..read_size]);
+
+        if fin || request.ends_with(b"\r\n") {
+            break;
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+
+    let body = match request.trim().strip_prefix("GET ") {
+        Some(path) => fs::read(file_path(root, path)).unwrap_or_else(|_| NOT_FOUND.to_vec()),
+        None => NOT_FOUND.to_vec(),
+    };
+
+    stream.send_bytes(Bytes::from(body), true).await
+}
+
+/// Maps the request `path` to the file of `root`, the directory components are stripped.
+fn file_path(root: &Path, path: &str) -> PathBuf {
+    let name = Path::new(path.trim())
+        .file_name()
+        .map(|name| name.to_owned())
+        .unwrap_or_default();
+
+    root.join(name)
+}
+
+/// Returns the expected content of the generated file with `size`.
+pub fn content(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i % 251) as u8).collect()
+}
+
+/// Generates the files named after their `sizes` in `root`.
+pub fn generate(root: &Path, sizes: &[usize]) -> io::Result<()> {
+    for size in sizes {
+        fs::write(root.join(size.to_string()), content(*size))?;
+    }
+
+    Ok(())
+}
+
+/// Checks the `body` of the file with `size`.
+pub fn verify(size: usize, body: &[u8]) -> io::Result<()> {
+    if body.len() != size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("file {}, received {} bytes", size, body.len()),
+        ));
+    }
+
+    if body != content(size) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("file {}, content mismatched", size),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! Runs the interop scenarios of hala-quic against the quiche example endpoints (`quiche-client` / `quiche-server`)
+//! over the loopback interface.
+//!
+//! ```text
+//! hala-quic-interop [options] <client|server> <scenario> [key=value]... [-- <peer args>...]
+//! hala-quic-interop [options] run <script>
+//! ```
+//!
+//! The role is played by hala-quic, e.g. `client transfer` runs the hala-quic client against `quiche-server`.
+//! The scenarios are `handshake`, `transfer`, `versionnegotiation`, `retry`, `resumption` and `mtu`,
+//! the scenario options are:
+//!
+//! - `files=1024,1048576`, the sizes of files transferred.
+//! - `mtu=1200`, the max udp payload size of hala-quic endpoint, which disables the path MTU discovery.
+//! - `version=babababa`, the version sent by the client of `versionnegotiation` scenario.
+//! - `timeout=30`, the timeout of the scenario in seconds.
+//!
+//! The script contains one scenario per line, the empty lines and the `#` comments are skipped,
+//! see `interop/scenarios.txt`.
+//!
+//! Options:
+//!
+//! - `--quiche-client <path>`, defaults to `$QUICHE_CLIENT` or `quiche-client` in `PATH`.
+//! - `--quiche-server <path>`, defaults to `$QUICHE_SERVER` or `quiche-server` in `PATH`.
+//! - `--cert <path>` / `--key <path>`, the certificate of both endpoints, defaults to the crate's test certificate.
+//! - `-v`, `--verbose`, prints the output of quiche endpoints.
+
+use std::{
+    env, fs, io,
+    path::PathBuf,
+    process::ExitCode,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use hala_quic::Config;
+
+mod client;
+mod hq;
+mod peer;
+mod scenario;
+mod server;
+
+use scenario::{invalid_input, parse_script, Role, Spec};
+
+/// The minimum max udp payload size of QUIC (RFC 9000 section 14).
+const MIN_MTU: usize = 1200;
+
+/// The options shared by all scenarios.
+pub struct Options {
+    pub quiche_client: PathBuf,
+    pub quiche_server: PathBuf,
+    pub cert: String,
+    pub key: String,
+    pub verbose: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        let from_env = |name: &str, default: &str| {
+            env::var_os(name)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(default))
+        };
+
+        Self {
+            quiche_client: from_env("QUICHE_CLIENT", "quiche-client"),
+            quiche_server: from_env("QUICHE_SERVER", "quiche-server"),
+            cert: concat!(env!("CARGO_MANIFEST_DIR"), "/cert/cert.crt").to_owned(),
+            key: concat!(env!("CARGO_MANIFEST_DIR"), "/cert/cert.key").to_owned(),
+            verbose: false,
+        }
+    }
+}
+
+/// Applies the transport parameters shared by the client and server of `spec` to `config`.
+pub fn endpoint_config(mut config: Config, spec: &Spec) -> io::Result<Config> {
+    config
+        .set_application_protos(&[hq::ALPN])
+        .map_err(hala_quic::errors::into_io_error)?;
+
+    config.set_max_idle_timeout(spec.timeout.as_millis() as u64);
+    config.set_initial_max_data(100_000_000);
+    config.set_initial_max_stream_data_bidi_local(10_000_000);
+    config.set_initial_max_stream_data_bidi_remote(10_000_000);
+    config.set_initial_max_stream_data_uni(10_000_000);
+    config.set_initial_max_streams_bidi(100);
+    config.set_initial_max_streams_uni(100);
+
+    if let Some(mtu) = spec.mtu {
+        config.set_max_send_udp_payload_size(mtu);
+        config.enable_pmtud(false);
+    }
+
+    Ok(config)
+}
+
+/// Checks the `mtu` of the finished connection of `spec`.
+pub fn check_mtu(mtu: usize, spec: &Spec) -> io::Result<()> {
+    let max_mtu = spec.mtu.unwrap_or(usize::MAX);
+
+    if mtu < MIN_MTU || mtu > max_mtu {
+        return Err(io::Error::other(format!(
+            "unexpected mtu {}, expect [{}, {}]",
+            mtu, MIN_MTU, max_mtu
+        )));
+    }
+
+    Ok(())
+}
+
+async fn run_spec(options: Arc<Options>, spec: Spec) -> io::Result<()> {
+    let timeout = spec.timeout;
+
+    let fut = async move {
+        match spec.role {
+            Role::Client => client::run(options, spec).await,
+            Role::Server => server::run(options, spec).await,
+        }
+    };
+
+    hala_io::timeout(fut, Some(timeout + Duration::from_secs(1))).await
+}
+
+async fn run_all(options: Arc<Options>, specs: Vec<Spec>) -> usize {
+    let mut failed = 0;
+
+    for spec in specs {
+        let name = spec.to_string();
+        let start = Instant::now();
+
+        match run_spec(options.clone(), spec).await {
+            Ok(_) => println!("PASS {} ({:?})", name, start.elapsed()),
+            Err(err) => {
+                failed += 1;
+                println!("FAIL {} ({:?}): {}", name, start.elapsed(), err);
+            }
+        }
+    }
+
+    failed
+}
+
+/// Parses the command line into the options and the specs to run.
+fn parse_args(args: Vec<String>) -> io::Result<(Options, Vec<Spec>)> {
+    let mut options = Options::default();
+    let mut args = args.into_iter().peekable();
+
+    while let Some(arg) = args.next_if(|arg| arg.starts_with('-')) {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| invalid_input(format!("missing value of {}", arg)))
+        };
+
+        match arg.as_str() {
+            "--quiche-client" => options.quiche_client = value()?.into(),
+            "--quiche-server" => options.quiche_server = value()?.into(),
+            "--cert" => options.cert = value()?,
+            "--key" => options.key = value()?,
+            "-v" | "--verbose" => options.verbose = true,
+            _ => return Err(invalid_input(format!("unknown option {}", arg))),
+        }
+    }
+
+    let specs = match args.peek().map(|arg| arg.as_str()) {
+        Some("run") => {
+            args.next();
+
+            let script = args
+                .next()
+                .ok_or_else(|| invalid_input("missing script path"))?;
+
+            parse_script(&fs::read_to_string(script)?)?
+        }
+        Some(_) => vec![Spec::parse(args)?],
+        None => return Err(invalid_input("missing scenario")),
+    };
+
+    Ok((options, specs))
+}
+
+fn main() -> ExitCode {
+    let (options, specs) = match parse_args(env::args().skip(1).collect()) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("usage: hala-quic-interop [options] <client|server> <scenario> [key=value]... [-- <peer args>...]");
+            eprintln!("       hala-quic-interop [options] run <script>");
+            return ExitCode::from(2);
+        }
+    };
+
+    hala_io::current::register_driver(hala_io::mio::mio_driver()).unwrap();
+
+    let total = specs.len();
+
+    let failed = hala_io::current::executor::block_on(run_all(Arc::new(options), specs), 4);
+
+    println!("{} passed, {} failed", total - failed, failed);
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
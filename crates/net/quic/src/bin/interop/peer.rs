@@ -0,0 +1,120 @@
+//! The quiche example endpoint processes and their working directories.
+
+use std::{
+    env, fs, io,
+    net::{SocketAddr, UdpSocket},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// The temporary directory, which is removed on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> io::Result<Self> {
+        static SEQ: AtomicUsize = AtomicUsize::new(0);
+
+        let path = env::temp_dir().join(format!(
+            "hala-quic-interop-{}-{}-{}",
+            std::process::id(),
+            SEQ.fetch_add(1, Ordering::Relaxed),
+            name
+        ));
+
+        fs::create_dir_all(&path)?;
+
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The spawned quiche example endpoint, which is killed on drop.
+pub struct PeerProcess {
+    name: String,
+    child: Child,
+}
+
+impl PeerProcess {
+    /// Spawns `program` with `args`, the output is inherited if `verbose` is true.
+    pub fn spawn<I, S>(program: &Path, args: I, verbose: bool) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        let mut command = Command::new(program);
+
+        command.args(args);
+
+        if !verbose {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+
+        log::trace!("spawn {:?}", command);
+
+        let child = command.spawn().map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("spawn {}, err={}", program.display(), err),
+            )
+        })?;
+
+        Ok(Self {
+            name: program.display().to_string(),
+            child,
+        })
+    }
+
+    /// Waits for the process exit, returns [`TimedOut`](io::ErrorKind::TimedOut) error after `timeout`.
+    pub async fn wait(&mut self, timeout: Duration) -> io::Result<ExitStatus> {
+        let wait = async {
+            loop {
+                if let Some(status) = self.child.try_wait()? {
+                    return Ok(status);
+                }
+
+                hala_io::sleep(Duration::from_millis(20)).await?;
+            }
+        };
+
+        hala_io::timeout(wait, Some(timeout)).await
+    }
+
+    /// Waits for the process exit, and returns error if the exit status is not success.
+    pub async fn wait_success(&mut self, timeout: Duration) -> io::Result<()> {
+        let status = self.wait(timeout).await?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "{} exit with {}",
+                self.name, status
+            )))
+        }
+    }
+}
+
+impl Drop for PeerProcess {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            _ = self.child.kill();
+            _ = self.child.wait();
+        }
+    }
+}
+
+/// Returns a free udp port of loopback interface.
+pub fn free_addr() -> io::Result<SocketAddr> {
+    UdpSocket::bind("127.0.0.1:0")?.local_addr()
+}
@@ -0,0 +1,203 @@
+use std::{fmt::Display, io, str::FromStr, time::Duration};
+
+/// The side played by hala-quic, the other side is played by the quiche example endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// hala-quic client against `quiche-server`.
+    Client,
+    /// hala-quic server against `quiche-client`.
+    Server,
+}
+
+impl FromStr for Role {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "client" => Ok(Role::Client),
+            "server" => Ok(Role::Server),
+            _ => Err(invalid_input(format!("unknown role {:?}", s))),
+        }
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Client => f.write_str("client"),
+            Role::Server => f.write_str("server"),
+        }
+    }
+}
+
+/// The interop scenarios, named after the test cases of the QUIC interop runner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// Completes the handshake without retry.
+    Handshake,
+    /// Transfers the files over the `hq-interop` streams.
+    Transfer,
+    /// The client starts with an unsupported version, and the server answers version negotiation.
+    VersionNegotiation,
+    /// The server validates the client address by stateless retry.
+    Retry,
+    /// The second connection resumes the TLS session of the first one.
+    Resumption,
+    /// Transfers the files with the path MTU discovery, or the fixed max udp payload size of `mtu` option.
+    Mtu,
+}
+
+impl Scenario {
+    /// Returns the file sizes transferred by default.
+    fn default_files(&self) -> Vec<usize> {
+        match self {
+            Scenario::Transfer | Scenario::Mtu => vec![1024, 1024 * 1024],
+            _ => vec![1024],
+        }
+    }
+}
+
+impl FromStr for Scenario {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "handshake" => Ok(Scenario::Handshake),
+            "transfer" => Ok(Scenario::Transfer),
+            "versionnegotiation" => Ok(Scenario::VersionNegotiation),
+            "retry" => Ok(Scenario::Retry),
+            "resumption" => Ok(Scenario::Resumption),
+            "mtu" => Ok(Scenario::Mtu),
+            _ => Err(invalid_input(format!("unknown scenario {:?}", s))),
+        }
+    }
+}
+
+impl Display for Scenario {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Scenario::Handshake => "handshake",
+            Scenario::Transfer => "transfer",
+            Scenario::VersionNegotiation => "versionnegotiation",
+            Scenario::Retry => "retry",
+            Scenario::Resumption => "resumption",
+            Scenario::Mtu => "mtu",
+        })
+    }
+}
+
+/// The reserved version sent by the client of version negotiation scenario by default.
+pub const GREASE_VERSION: u32 = 0xbabababa;
+
+/// One scenario to run, parsed from `<role> <scenario> [key=value]... [-- <peer args>...]`.
+#[derive(Debug, Clone)]
+pub struct Spec {
+    pub role: Role,
+    pub scenario: Scenario,
+    /// The sizes of files transferred, the option `files=1024,1048576`.
+    pub files: Vec<usize>,
+    /// The max udp payload size of hala-quic endpoint, the option `mtu=1200`.
+    ///
+    /// The path MTU discovery is disabled if set.
+    pub mtu: Option<usize>,
+    /// The version sent by the client of version negotiation scenario, the option `version=babababa`.
+    pub version: u32,
+    /// The timeout of the whole scenario, the option `timeout=30` in seconds.
+    pub timeout: Duration,
+    /// The extra arguments of the quiche endpoint, following `--`.
+    pub peer_args: Vec<String>,
+}
+
+impl Display for Spec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.role, self.scenario)?;
+
+        if let Some(mtu) = self.mtu {
+            write!(f, " mtu={}", mtu)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Spec {
+    /// Parses the spec from the whitespace separated `args`.
+    pub fn parse<I, S>(args: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut args = args.into_iter();
+
+        let mut next = |name: &str| {
+            args.next()
+                .map(|arg| arg.as_ref().to_owned())
+                .ok_or_else(|| invalid_input(format!("missing {}", name)))
+        };
+
+        let role = next("role")?.parse()?;
+        let scenario: Scenario = next("scenario")?.parse()?;
+
+        let mut spec = Spec {
+            role,
+            scenario,
+            files: scenario.default_files(),
+            mtu: None,
+            version: GREASE_VERSION,
+            timeout: Duration::from_secs(30),
+            peer_args: vec![],
+        };
+
+        while let Ok(arg) = next("option") {
+            if arg == "--" {
+                while let Ok(arg) = next("peer argument") {
+                    spec.peer_args.push(arg);
+                }
+
+                break;
+            }
+
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| invalid_input(format!("expect key=value, but got {:?}", arg)))?;
+
+            match key {
+                "files" => {
+                    spec.files = value
+                        .split(',')
+                        .map(|size| size.parse().map_err(invalid_input))
+                        .collect::<io::Result<_>>()?;
+                }
+                "mtu" => spec.mtu = Some(value.parse().map_err(invalid_input)?),
+                "version" => {
+                    spec.version = u32::from_str_radix(value.trim_start_matches("0x"), 16)
+                        .map_err(invalid_input)?
+                }
+                "timeout" => {
+                    spec.timeout = Duration::from_secs(value.parse().map_err(invalid_input)?)
+                }
+                _ => return Err(invalid_input(format!("unknown option {:?}", key))),
+            }
+        }
+
+        Ok(spec)
+    }
+}
+
+/// Parses the scenario script, one spec per line, the empty lines and the `#` comments are skipped.
+pub fn parse_script(script: &str) -> io::Result<Vec<Spec>> {
+    script
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index, line.split('#').next().unwrap_or_default().trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| {
+            Spec::parse(line.split_whitespace())
+                .map_err(|err| invalid_input(format!("script line {}: {}", index + 1, err)))
+        })
+        .collect()
+}
+
+pub fn invalid_input<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
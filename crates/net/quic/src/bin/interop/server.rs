@@ -0,0 +1,159 @@
+//! The scenarios of hala-quic server against `quiche-client`.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hala_io::current::executor::io_spawn;
+use hala_quic::{errors::into_io_error, state::QuicHandshakeInfo, Config, QuicListener};
+
+use crate::{
+    endpoint_config, hq,
+    peer::{PeerProcess, TempDir},
+    scenario::{Scenario, Spec},
+    Options,
+};
+
+/// The delay after `quiche-client` exit, which waits for the server connections to be closed.
+const SHUTDOWN_DELAY: Duration = Duration::from_millis(200);
+
+/// The handshake info and the final mtu of one served connection.
+struct ConnRecord {
+    handshake_info: QuicHandshakeInfo,
+    mtu: Option<usize>,
+}
+
+pub async fn run(options: Arc<Options>, spec: Spec) -> io::Result<()> {
+    let root = TempDir::new("www")?;
+    let dump = TempDir::new("dump")?;
+
+    hq::generate(root.path(), &spec.files)?;
+
+    let mut config = endpoint_config(Config::new()?, &spec)?;
+
+    config
+        .load_cert_chain_from_pem_file(&options.cert)
+        .map_err(into_io_error)?;
+    config
+        .load_priv_key_from_pem_file(&options.key)
+        .map_err(into_io_error)?;
+
+    config.set_stateless_retry(spec.scenario == Scenario::Retry);
+
+    let listener = Arc::new(QuicListener::bind("127.0.0.1:0", config)?);
+
+    let laddr = listener.local_addr();
+
+    let records = Arc::new(Mutex::new(Vec::<Arc<Mutex<ConnRecord>>>::new()));
+
+    spawn_accept_loop(listener.clone(), root.path().to_owned(), records.clone())?;
+
+    let mut args = vec![
+        "--no-verify".to_owned(),
+        "--dump-responses".to_owned(),
+        dump.path().display().to_string(),
+    ];
+
+    if spec.scenario == Scenario::VersionNegotiation {
+        args.push("--wire-version".to_owned());
+        args.push(format!("{:x}", spec.version));
+    }
+
+    let session_file = dump.path().join("session");
+
+    if spec.scenario == Scenario::Resumption {
+        args.push("--session-file".to_owned());
+        args.push(session_file.display().to_string());
+    }
+
+    args.extend(spec.peer_args.iter().cloned());
+
+    args.extend(
+        spec.files
+            .iter()
+            .map(|size| format!("https://{}/{}", laddr, size)),
+    );
+
+    let rounds = if spec.scenario == Scenario::Resumption {
+        2
+    } else {
+        1
+    };
+
+    for _ in 0..rounds {
+        PeerProcess::spawn(&options.quiche_client, &args, options.verbose)?
+            .wait_success(spec.timeout)
+            .await?;
+
+        for size in &spec.files {
+            hq::verify(*size, &fs::read(dump.path().join(size.to_string()))?)?;
+        }
+    }
+
+    hala_io::sleep(SHUTDOWN_DELAY).await?;
+
+    listener.close().await;
+
+    let records = records.lock().unwrap();
+
+    let last = records
+        .last()
+        .ok_or_else(|| io::Error::other("no connection is accepted"))?
+        .lock()
+        .unwrap();
+
+    match spec.scenario {
+        Scenario::Resumption => {
+            if records.len() < 2 {
+                return Err(io::Error::other(format!(
+                    "expect two connections, but accepted {}",
+                    records.len()
+                )));
+            }
+
+            if !last.handshake_info.is_resumed {
+                return Err(io::Error::other("the second connection is not resumed"));
+            }
+        }
+        Scenario::Mtu => {
+            if let Some(mtu) = last.mtu {
+                crate::check_mtu(mtu, &spec)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn spawn_accept_loop(
+    listener: Arc<QuicListener>,
+    root: PathBuf,
+    records: Arc<Mutex<Vec<Arc<Mutex<ConnRecord>>>>>,
+) -> io::Result<()> {
+    io_spawn(async move {
+        while let Some(conn) = listener.accept().await {
+            let record = Arc::new(Mutex::new(ConnRecord {
+                handshake_info: conn.handshake_info().await,
+                mtu: None,
+            }));
+
+            records.lock().unwrap().push(record.clone());
+
+            let root = root.clone();
+
+            io_spawn(async move {
+                let result = hq::serve(&conn, &root).await;
+
+                record.lock().unwrap().mtu = Some(conn.current_mtu().await);
+
+                result
+            })?;
+        }
+
+        Ok(())
+    })
+}
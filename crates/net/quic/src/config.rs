@@ -1,32 +1,442 @@
 use std::{
-    io,
+    env, fs, io,
     ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 
+use rand::{thread_rng, RngCore};
+
+use crate::{
+    errors::into_io_error, sni::initial_server_name, state::DEFAULT_MAX_PLPMTU, PacketFilter,
+    SessionStore,
+};
+
+/// Well-known CA bundle file locations of the OS trust store.
+const NATIVE_CERT_FILES: &[&str] = &[
+    // Debian/Ubuntu/Gentoo etc.
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora/RHEL 6
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    // OpenSUSE
+    "/etc/ssl/ca-bundle.pem",
+    // OpenELEC
+    "/etc/pki/tls/cacert.pem",
+    // CentOS/RHEL 7
+    "/etc/pki/ca-trust/extracted/pem/tls-ca-bundle.pem",
+    // Alpine Linux / macOS
+    "/etc/ssl/cert.pem",
+];
+
+/// Well-known CA certificate directory locations of the OS trust store.
+const NATIVE_CERT_DIRS: &[&str] = &["/etc/ssl/certs", "/etc/pki/tls/certs"];
+
+/// The certificate chain and private key files of one server name, in PEM format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertifiedKey {
+    pub cert_chain_pem_file: PathBuf,
+    pub priv_key_pem_file: PathBuf,
+}
+
+/// Selects the server certificate by the server name indication of the incoming connection.
+pub trait CertResolver: Send + Sync {
+    /// Returns the certificate of `server_name`, or `None` to use the default certificate
+    /// loaded by [`load_cert_chain_from_pem_file`](Config::load_cert_chain_from_pem_file).
+    fn resolve(&self, server_name: &str) -> Option<CertifiedKey>;
+}
+
+/// The default buffer size of the listener's udp sockets, see [`Config::set_socket_buffer_size`].
+pub const DEFAULT_SOCKET_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
 /// Hala quic peer config, Adds hala quic specific configuration options to [`quiche::Config`](quiche::Config)
 pub struct Config {
     #[allow(unused)]
     pub(crate) udp_data_channel_len: usize,
-    #[allow(unused)]
+    /// The high-watermark of the per-stream write buffer.
     pub(crate) stream_buffer: usize,
 
     pub ping_timeout: Duration,
 
+    /// The maximum number of connections (established and handshaking) a listener will hold.
+    pub(crate) max_incoming_conns: usize,
+    /// The maximum length of the listener's pending incoming connection queue.
+    pub(crate) accept_queue_len: usize,
+    /// The duration after which the listener drops connections that have not completed the handshake.
+    pub(crate) handshake_timeout: Duration,
+    /// Validates the client address by stateless retry before creating the connection.
+    pub(crate) stateless_retry: bool,
+    /// The server name used for SNI and certificate verification by client connections.
+    pub(crate) server_name: Option<String>,
+    /// The TLS session storage of client connections.
+    pub(crate) session_store: Option<Arc<dyn SessionStore>>,
+    /// The server certificate resolver.
+    cert_resolver: Option<Arc<dyn CertResolver>>,
+    /// The `SO_RCVBUF` / `SO_SNDBUF` options of the listener's udp sockets.
+    pub(crate) socket_buffer_size: Option<usize>,
+    /// The `IP_TTL` / `IPV6_UNICAST_HOPS` option of the udp sockets.
+    pub(crate) socket_ttl: Option<u32>,
+    /// Enables the path MTU discovery of connections.
+    pub(crate) pmtud: bool,
+    /// The filters of datagrams received by the listener.
+    pub(crate) packet_filters: Vec<Arc<dyn PacketFilter>>,
+    /// The default server certificate chain file.
+    default_cert_chain: Option<PathBuf>,
+    /// The default server private key file.
+    default_priv_key: Option<PathBuf>,
+    /// The certificate currently loaded into `quiche_config`, `None` means the default one.
+    loaded_cert: Option<CertifiedKey>,
+    /// The path of TLS keylog file.
+    keylog: Option<PathBuf>,
+    /// The directory of qlog files.
+    #[cfg(feature = "qlog")]
+    qlog_dir: Option<PathBuf>,
+
     quiche_config: quiche::Config,
 }
 
 impl Config {
     /// Creates a config object with default `PROTOCOL_VERSION`(quiche::PROTOCOL_VERSION).
     pub fn new() -> io::Result<Self> {
+        Self::with_version(quiche::PROTOCOL_VERSION)
+    }
+
+    /// Creates a config object with the QUIC wire `version`.
+    ///
+    /// The client connection with an unsupported version, e.g. the reserved `0x?a?a?a?a` ones,
+    /// triggers the version negotiation of the server.
+    pub fn with_version(version: u32) -> io::Result<Self> {
+        let mut quiche_config = quiche::Config::new(version)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+        // The upper bound of the path MTU discovery.
+        quiche_config.set_max_send_udp_payload_size(DEFAULT_MAX_PLPMTU);
+
         Ok(Self {
             udp_data_channel_len: 1024,
             stream_buffer: 1024,
             ping_timeout: Duration::from_secs(1),
-            quiche_config: quiche::Config::new(quiche::PROTOCOL_VERSION)
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?,
+            max_incoming_conns: usize::MAX,
+            accept_queue_len: 1024,
+            handshake_timeout: Duration::from_secs(10),
+            stateless_retry: true,
+            server_name: None,
+            session_store: None,
+            cert_resolver: None,
+            socket_buffer_size: Some(DEFAULT_SOCKET_BUFFER_SIZE),
+            socket_ttl: None,
+            pmtud: true,
+            packet_filters: vec![],
+            default_cert_chain: None,
+            default_priv_key: None,
+            loaded_cert: None,
+            keylog: None,
+            #[cfg(feature = "qlog")]
+            qlog_dir: None,
+            quiche_config,
         })
     }
+
+    /// Sets the maximum number of connections the listener will hold, including
+    /// connections that have not yet completed the handshake.
+    ///
+    /// Once the limit is reached, initial packets of new connections are dropped.
+    pub fn set_max_incoming_conns(&mut self, n: usize) {
+        self.max_incoming_conns = n;
+    }
+
+    /// Sets the high-watermark of the per-stream write buffer.
+    ///
+    /// Data written by [`write`](crate::QuicStream::write) is coalesced in the buffer
+    /// until it is flushed or the buffer reaches this size.
+    pub fn set_stream_buffer(&mut self, n: usize) {
+        self.stream_buffer = n;
+    }
+
+    /// Sets the maximum length of the incoming connection queue.
+    ///
+    /// Once the queue is full, initial packets of new connections are dropped
+    /// until [`accept`](crate::state::QuicListenerState::accept) drains it.
+    pub fn set_accept_queue_len(&mut self, n: usize) {
+        self.accept_queue_len = n;
+    }
+
+    /// Sets the handshake timeout of incoming connections, the default value is 10 seconds.
+    ///
+    /// The listener drops the connections that have not completed the handshake in `timeout`,
+    /// e.g. the client vanishes after sending the initial packet.
+    pub fn set_handshake_timeout(&mut self, timeout: Duration) {
+        self.handshake_timeout = timeout;
+    }
+
+    /// Enables or disables the stateless retry of incoming connections, which is enabled by default.
+    ///
+    /// Disabling retry saves one round-trip, but the listener can only send three times
+    /// the bytes received from the client until the client address is validated (RFC 9000 section 8).
+    pub fn set_stateless_retry(&mut self, enabled: bool) {
+        self.stateless_retry = enabled;
+    }
+
+    /// Sets the server name of the remote peer, which is sent in the SNI extension
+    /// and used to verify the peer's certificate.
+    pub fn set_server_name(&mut self, server_name: &str) {
+        self.server_name = Some(server_name.to_owned());
+    }
+
+    /// Sets the TLS session storage of client connections.
+    ///
+    /// The session is saved once the session ticket is received from the server, and loaded
+    /// by the next connection to the same server name and address to resume the session,
+    /// so that [early data](quiche::Config::enable_early_data) can be sent.
+    pub fn set_session_store<S: SessionStore + 'static>(&mut self, store: S) {
+        self.session_store = Some(Arc::new(store));
+    }
+
+    /// Sets the resolver used by listeners to select the server certificate by SNI,
+    /// so one listener can serve multiple hostnames.
+    ///
+    /// Connections without SNI, or whose server name is not resolved, use the default certificate.
+    pub fn set_cert_resolver<R: CertResolver + 'static>(&mut self, resolver: R) {
+        self.cert_resolver = Some(Arc::new(resolver));
+    }
+
+    /// Sets the receive and send buffer sizes of the udp sockets bound by [`QuicListener`](crate::QuicListener),
+    /// the default value is [`DEFAULT_SOCKET_BUFFER_SIZE`], `None` keeps the os default value.
+    ///
+    /// The os default buffer is too small for a busy listener, the datagrams are dropped by kernel
+    /// when the recv loops fall behind. The value may be capped by the os, e.g. by `net.core.rmem_max` on linux.
+    ///
+    /// Only the value of the first shard config is used by [`bind_with_workers`](crate::QuicListener::bind_with_workers).
+    pub fn set_socket_buffer_size(&mut self, size: Option<usize>) {
+        self.socket_buffer_size = size;
+    }
+
+    /// Sets the ttl / hop limit of the datagrams sent by the udp sockets of listener and client connections,
+    /// `None` keeps the os default value.
+    ///
+    /// Only applied to the sockets bound by [`QuicListener`](crate::QuicListener) and
+    /// [`QuicConn::connect_udp`](crate::QuicConn::connect_udp) / [`connect_with`](crate::QuicConn::connect_with).
+    pub fn set_socket_ttl(&mut self, ttl: Option<u32>) {
+        self.socket_ttl = ttl;
+    }
+
+    /// Enables or disables the path MTU discovery (RFC 8899) of connections, which is enabled by default.
+    ///
+    /// The connection starts with the datagrams of [`BASE_PLPMTU`](crate::state::BASE_PLPMTU) bytes, and probes
+    /// the larger sizes up to [`set_max_send_udp_payload_size`](quiche::Config::set_max_send_udp_payload_size),
+    /// which is [`DEFAULT_MAX_PLPMTU`] by default, see [`current_mtu`](crate::QuicConn::current_mtu).
+    ///
+    /// Once enabled, the DF bit of the datagrams is set on the platforms supporting it, so the oversized probes
+    /// are dropped instead of being fragmented.
+    ///
+    /// Once disabled, the datagrams are sized by `set_max_send_udp_payload_size` directly,
+    /// which may be dropped by the paths of smaller MTU.
+    pub fn enable_pmtud(&mut self, enabled: bool) {
+        self.pmtud = enabled;
+    }
+
+    /// Adds the filter run by the recv loops of [`QuicListener`](crate::QuicListener), before the datagram
+    /// is processed by quiche, the datagram is dropped if any filter rejects it.
+    ///
+    /// The filters of the shard config only apply to the datagrams routed to the shard,
+    /// see [`bind_with_workers`](crate::QuicListener::bind_with_workers).
+    pub fn add_packet_filter<F: PacketFilter + 'static>(&mut self, filter: F) {
+        self.packet_filters.push(Arc::new(filter));
+    }
+
+    /// Loads the default server certificate chain from a PEM file, see [`quiche::Config::load_cert_chain_from_pem_file`].
+    pub fn load_cert_chain_from_pem_file(&mut self, file: &str) -> quiche::Result<()> {
+        self.quiche_config.load_cert_chain_from_pem_file(file)?;
+
+        self.default_cert_chain = Some(file.into());
+
+        Ok(())
+    }
+
+    /// Loads the default server private key from a PEM file, see [`quiche::Config::load_priv_key_from_pem_file`].
+    pub fn load_priv_key_from_pem_file(&mut self, file: &str) -> quiche::Result<()> {
+        self.quiche_config.load_priv_key_from_pem_file(file)?;
+
+        self.default_priv_key = Some(file.into());
+
+        Ok(())
+    }
+
+    /// Loads the certificate of the server name carried by the client `initial_packet`.
+    ///
+    /// quiche copies the certificate into the new connection on `accept`,
+    /// so reloading it doesn't affect existing connections.
+    pub(crate) fn select_cert(&mut self, initial_packet: &[u8]) -> io::Result<()> {
+        let Some(resolver) = &self.cert_resolver else {
+            return Ok(());
+        };
+
+        let cert = initial_server_name(initial_packet)
+            .and_then(|server_name| resolver.resolve(&server_name));
+
+        if cert == self.loaded_cert {
+            return Ok(());
+        }
+
+        let (cert_chain, priv_key) = match &cert {
+            Some(cert) => (
+                cert.cert_chain_pem_file.clone(),
+                cert.priv_key_pem_file.clone(),
+            ),
+            None => match (&self.default_cert_chain, &self.default_priv_key) {
+                (Some(cert_chain), Some(priv_key)) => (cert_chain.clone(), priv_key.clone()),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "default certificate not loaded",
+                    ))
+                }
+            },
+        };
+
+        let to_str = |path: &Path| {
+            path.to_str().map(str::to_owned).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid certificate path {:?}", path),
+                )
+            })
+        };
+
+        self.quiche_config
+            .load_cert_chain_from_pem_file(&to_str(&cert_chain)?)
+            .map_err(into_io_error)?;
+
+        self.quiche_config
+            .load_priv_key_from_pem_file(&to_str(&priv_key)?)
+            .map_err(into_io_error)?;
+
+        self.loaded_cert = cert;
+
+        Ok(())
+    }
+
+    /// Loads the trusted CA certificates from the OS trust store and enables peer verification.
+    ///
+    /// The `SSL_CERT_FILE` / `SSL_CERT_DIR` environment variables take precedence over
+    /// the well-known locations.
+    pub fn load_native_certs(&mut self) -> io::Result<()> {
+        let files = env::var("SSL_CERT_FILE")
+            .ok()
+            .into_iter()
+            .chain(NATIVE_CERT_FILES.iter().map(|path| path.to_string()));
+
+        let dirs = env::var("SSL_CERT_DIR")
+            .ok()
+            .into_iter()
+            .chain(NATIVE_CERT_DIRS.iter().map(|path| path.to_string()));
+
+        let mut loaded = false;
+
+        for file in files {
+            if Path::new(&file).is_file() {
+                self.load_verify_locations_from_file(&file)
+                    .map_err(into_io_error)?;
+
+                loaded = true;
+
+                break;
+            }
+        }
+
+        for dir in dirs {
+            if Path::new(&dir).is_dir() {
+                self.load_verify_locations_from_directory(&dir)
+                    .map_err(into_io_error)?;
+
+                loaded = true;
+
+                break;
+            }
+        }
+
+        if !loaded {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "native certificates not found",
+            ));
+        }
+
+        self.verify_peer(true);
+
+        Ok(())
+    }
+
+    /// Adds trusted root CA certificates in PEM format and enables peer verification.
+    ///
+    /// `pem` can contain multiple certificates.
+    pub fn add_root_ca_pem(&mut self, pem: &[u8]) -> io::Result<()> {
+        // quiche only loads verify locations from file system.
+        let path = env::temp_dir().join(format!("hala-quic-ca-{:x}.pem", thread_rng().next_u64()));
+
+        fs::write(&path, pem)?;
+
+        let result = self
+            .load_verify_locations_from_file(path.to_str().unwrap())
+            .map_err(into_io_error);
+
+        _ = fs::remove_file(&path);
+
+        result?;
+
+        self.verify_peer(true);
+
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Enables logging of TLS secrets in [keylog](https://developer.mozilla.org/en-US/docs/Mozilla/Projects/NSS/Key_Log_Format) format,
+    /// which can be used by Wireshark to decrypt the traffic.
+    ///
+    /// The secrets of every connection created with this config are appended to the file `path`.
+    pub fn enable_keylog<P: AsRef<Path>>(&mut self, path: P) {
+        self.keylog = Some(path.as_ref().to_path_buf());
+        self.quiche_config.log_keys();
+    }
+
+    /// Enables [qlog](https://datatracker.ietf.org/doc/html/draft-ietf-quic-qlog-main-schema) tracing.
+    ///
+    /// The events of every connection created with this config are written to the file
+    /// `{dir}/{trace_id}-{client|server}.sqlog`.
+    #[cfg(feature = "qlog")]
+    pub fn enable_qlog<P: AsRef<Path>>(&mut self, dir: P) {
+        self.qlog_dir = Some(dir.as_ref().to_path_buf());
+    }
+
+    /// Sets up keylog and qlog writers of the new created connection.
+    pub(crate) fn setup_conn_tracing(&self, conn: &mut quiche::Connection) -> io::Result<()> {
+        if let Some(keylog) = &self.keylog {
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(keylog)?;
+
+            conn.set_keylog(Box::new(file));
+        }
+
+        #[cfg(feature = "qlog")]
+        if let Some(dir) = &self.qlog_dir {
+            let role = if conn.is_server() { "server" } else { "client" };
+
+            fs::create_dir_all(dir)?;
+
+            let file = fs::File::create(dir.join(format!("{}-{}.sqlog", conn.trace_id(), role)))?;
+
+            conn.set_qlog(
+                Box::new(io::BufWriter::new(file)),
+                format!("hala-quic {} qlog", role),
+                format!("hala-quic {} qlog, id={}", role, conn.trace_id()),
+            );
+        }
+
+        Ok(())
+    }
 }
 
 impl Deref for Config {
@@ -0,0 +1,712 @@
+use std::{
+    fmt::Debug,
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use futures::{future::select, stream::FuturesUnordered, FutureExt, StreamExt};
+use hala_future::oneshot;
+use hala_io::{
+    cancellable, current::executor::io_spawn, sleep, timeout, BindOptions, CancellationToken,
+};
+use hala_lockfree::pool::BufferPool;
+use hala_udp::UdpSocket;
+use quiche::RecvInfo;
+
+use crate::{
+    errors::CloseReason,
+    state::{
+        QuicConnState, QuicConnectorState, QuicEventKind, QuicEventStream, QuicHandshakeInfo,
+        QuicStreamFlowControl,
+    },
+    Config, QuicIncoming, QuicSendStream, QuicStream,
+};
+
+/// The max length of quic datagram.
+pub(crate) const MAX_DATAGRAM_SIZE: usize = 65535;
+
+/// The max number of packets sent by one `UDP_SEGMENT` syscall, which is limited by linux kernel.
+const MAX_GSO_SEGMENTS: usize = 64;
+
+/// The delay between two handshake attempts of [`connect_any`](QuicConn::connect_any),
+/// the value recommended by RFC 8305.
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// The max number of idle buffers kept by [`datagram_pool`].
+const MAX_IDLE_DATAGRAM_BUFS: usize = 256;
+
+/// Returns the pool of [`MAX_DATAGRAM_SIZE`] buffers, shared by the send/recv paths of connections and listeners.
+pub(crate) fn datagram_pool() -> &'static BufferPool {
+    static POOL: OnceLock<BufferPool> = OnceLock::new();
+
+    POOL.get_or_init(|| BufferPool::new(MAX_DATAGRAM_SIZE, MAX_IDLE_DATAGRAM_BUFS))
+}
+
+/// Receives datagrams from the connected `socket`, returns tuple (recv_size, segment_size).
+///
+/// The datagrams coalesced by `UDP_GRO` are received at once, and the last one may be shorter.
+async fn recv(socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, usize)> {
+    let (recv_size, segment_size) = match socket.recv_msg(buf).await {
+        Ok((recv_size, _, info)) => (
+            recv_size,
+            info.segment_size.map(usize::from).unwrap_or(recv_size),
+        ),
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+            let recv_size = socket.recv(buf).await?;
+
+            (recv_size, recv_size)
+        }
+        Err(err) => return Err(err),
+    };
+
+    // `chunks` panics on zero chunk size.
+    Ok((recv_size, segment_size.max(1)))
+}
+
+/// Applies the ttl of `config` to the udp `socket`, and sets the DF bit if the path MTU discovery is enabled.
+///
+/// The DF bit is skipped on the platforms without it, the probes may be fragmented there.
+pub(crate) fn setup_socket(socket: &UdpSocket, ttl: Option<u32>, pmtud: bool) -> io::Result<()> {
+    if let Some(ttl) = ttl {
+        socket.set_ttl(ttl)?;
+    }
+
+    if pmtud {
+        if let Err(err) = socket.set_dont_fragment(true) {
+            log::warn!("set DF bit of udp socket failed, err={}", err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Quic client connection, which owns the underlying udp socket.
+///
+/// The udp datagram pump tasks are spawned by [`io_spawn`], so users only deal with stream-level APIs.
+pub struct QuicConn {
+    state: QuicConnState,
+}
+
+impl Debug for QuicConn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "QuicConn({:?})", self.state)
+    }
+}
+
+impl From<QuicConnState> for QuicConn {
+    /// Wraps the server connection accepted by [`QuicListener`](crate::QuicListener),
+    /// whose datagrams are pumped by the listener.
+    fn from(state: QuicConnState) -> Self {
+        Self { state }
+    }
+}
+
+impl QuicConn {
+    /// Binds a new udp socket and connects to the remote peer `raddrs`.
+    ///
+    /// The first address which completes the handshake successfully will be used.
+    pub async fn connect_udp<R: ToSocketAddrs>(raddrs: R, config: &mut Config) -> io::Result<Self> {
+        let mut last_error = None;
+
+        for raddr in raddrs.to_socket_addrs()? {
+            let socket = Self::bind_for(raddr)?;
+
+            match Self::connect_with(socket, raddr, config).await {
+                Ok(conn) => return Ok(conn),
+                Err(err) => {
+                    log::error!("QuicConn connect to {} failed, err={}", raddr, err);
+
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "raddrs is empty")))
+    }
+
+    /// Binds one udp socket per address of `raddrs`, and races the handshakes to them,
+    /// e.g. the IPv6 and IPv4 addresses of a multi-homed server.
+    ///
+    /// The handshakes are started [`CONNECTION_ATTEMPT_DELAY`] apart in the given order, and the
+    /// first established connection is returned, the other handshakes are aborted and their sockets are closed.
+    pub async fn connect_any(raddrs: &[SocketAddr], config: &mut Config) -> io::Result<Self> {
+        let mut attempts = FuturesUnordered::new();
+
+        let mut last_error = None;
+
+        for (index, raddr) in raddrs.iter().enumerate() {
+            // The connector borrows `config` only during creation, so the handshakes can run concurrently.
+            let connector = Self::bind_for(*raddr).and_then(|socket| {
+                let (laddr, connector) = Self::connector(&socket, *raddr, config)?;
+
+                Ok((socket, connector, laddr))
+            });
+
+            let (socket, connector, laddr) = match connector {
+                Ok(connector) => connector,
+                Err(err) => {
+                    log::error!("QuicConn connect to {} failed, err={}", raddr, err);
+
+                    last_error = Some(err);
+
+                    continue;
+                }
+            };
+
+            let delay = CONNECTION_ATTEMPT_DELAY * index as u32;
+
+            attempts.push(async move {
+                let attempt = async {
+                    if !delay.is_zero() {
+                        sleep(delay).await?;
+                    }
+
+                    Self::handshake(socket, connector, laddr, *raddr).await
+                };
+
+                attempt.await.map_err(|err| (*raddr, err))
+            });
+        }
+
+        while let Some(result) = attempts.next().await {
+            match result {
+                Ok(conn) => return Ok(conn),
+                Err((raddr, err)) => {
+                    log::error!("QuicConn connect to {} failed, err={}", raddr, err);
+
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "raddrs is empty")))
+    }
+
+    /// Binds a new udp socket of the same family as `raddr`.
+    fn bind_for(raddr: SocketAddr) -> io::Result<UdpSocket> {
+        let laddr: SocketAddr = if raddr.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap()
+        } else {
+            "[::]:0".parse().unwrap()
+        };
+
+        // The coalesced datagrams are split by the recv loop.
+        let options = BindOptions {
+            gro: cfg!(target_os = "linux"),
+            ..Default::default()
+        };
+
+        UdpSocket::bind_with_options(laddr, options)
+    }
+
+    /// Binds a new udp socket and connects to the remote peer `raddrs`, see [`connect_udp`](Self::connect_udp).
+    ///
+    /// The handshake is aborted once `token` is cancelled, the udp socket is closed,
+    /// and [`OperationCancelled`](hala_io::OperationCancelled) error is returned.
+    pub async fn connect_udp_cancellable<R: ToSocketAddrs>(
+        raddrs: R,
+        config: &mut Config,
+        token: Option<&CancellationToken>,
+    ) -> io::Result<Self> {
+        cancellable(Self::connect_udp(raddrs, config), token).await
+    }
+
+    /// Connects to the remote peer `raddr` using the provided udp `socket`.
+    ///
+    /// The `socket` is connected to `raddr`, so the datagrams from other addresses are dropped.
+    pub async fn connect_with(
+        socket: UdpSocket,
+        raddr: SocketAddr,
+        config: &mut Config,
+    ) -> io::Result<Self> {
+        let (laddr, connector) = Self::connector(&socket, raddr, config)?;
+
+        Self::handshake(socket, connector, laddr, raddr).await
+    }
+
+    /// Connects `socket` to `raddr` and creates the client connection state.
+    fn connector(
+        socket: &UdpSocket,
+        raddr: SocketAddr,
+        config: &mut Config,
+    ) -> io::Result<(SocketAddr, QuicConnectorState)> {
+        socket.connect(raddr)?;
+
+        setup_socket(socket, config.socket_ttl, config.pmtud)?;
+
+        // The local address is determined by the route to `raddr` after connected.
+        let laddr = socket.local_addr()?;
+
+        Ok((laddr, QuicConnectorState::new(config, laddr, raddr)?))
+    }
+
+    /// Drives the handshake of `connector` until the connection is established.
+    async fn handshake(
+        socket: UdpSocket,
+        mut connector: QuicConnectorState,
+        laddr: SocketAddr,
+        raddr: SocketAddr,
+    ) -> io::Result<Self> {
+        let mut buf = datagram_pool().get();
+
+        loop {
+            // The timer is disarmed once closed, so the recv below would never complete.
+            if connector.is_closed() {
+                return Err(connector.close_error());
+            }
+
+            while let Some((send_size, _)) = connector.send(&mut buf)? {
+                socket.send(&buf[..send_size]).await?;
+            }
+
+            if connector.is_established() {
+                break;
+            }
+
+            match timeout(recv(&socket, &mut buf), connector.timeout()).await {
+                Ok((recv_size, segment_size)) => {
+                    for segment in buf[..recv_size].chunks_mut(segment_size) {
+                        connector.recv(
+                            segment,
+                            RecvInfo {
+                                from: raddr,
+                                to: laddr,
+                            },
+                        )?;
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    connector.on_timeout();
+
+                    if connector.is_closed() {
+                        return Err(connector.close_error());
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let state: QuicConnState = connector.into();
+
+        Self::spawn_pump(state.clone(), Arc::new(socket), laddr, raddr)?;
+
+        Ok(Self { state })
+    }
+
+    fn spawn_pump(
+        state: QuicConnState,
+        socket: Arc<UdpSocket>,
+        laddr: SocketAddr,
+        raddr: SocketAddr,
+    ) -> io::Result<()> {
+        // The recv loop exits when the send loop finished.
+        let (closed_sender, closed_receiver) = oneshot::channel::<()>();
+
+        let send_state = state.clone();
+        let send_socket = socket.clone();
+
+        io_spawn(async move {
+            let _closed_sender = closed_sender;
+
+            let mut buf = datagram_pool().get();
+
+            // Falls back to sending packets one by one once `UDP_SEGMENT` is unsupported.
+            let mut max_segments = MAX_GSO_SEGMENTS;
+
+            loop {
+                let (send_size, segment_size, _) =
+                    match send_state.read_gso(&mut buf, max_segments).await {
+                        Ok(r) => r,
+                        Err(err) => {
+                            log::trace!("{:?} send loop stopped, err={}", send_state, err);
+                            return Ok(());
+                        }
+                    };
+
+                if send_size > segment_size {
+                    match send_socket
+                        .send_to_gso(&buf[..send_size], segment_size as u16, raddr)
+                        .await
+                    {
+                        Ok(_) => continue,
+                        Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+                            log::trace!("{:?} disable gso, err={}", send_state, err);
+
+                            max_segments = 1;
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+
+                for segment in buf[..send_size].chunks(segment_size) {
+                    send_socket.send(segment).await?;
+                }
+            }
+        })?;
+
+        io_spawn(async move {
+            let mut buf = datagram_pool().get();
+
+            let mut closed = closed_receiver.fuse();
+
+            loop {
+                let recv = Box::pin(recv(&socket, &mut buf));
+
+                let (recv_size, segment_size) = match select(recv, &mut closed).await {
+                    futures::future::Either::Left((r, _)) => r?,
+                    futures::future::Either::Right(_) => {
+                        log::trace!("{:?} recv loop stopped", state);
+                        return Ok(());
+                    }
+                };
+
+                for segment in buf[..recv_size].chunks_mut(segment_size) {
+                    if let Err(err) = state
+                        .write(
+                            segment,
+                            RecvInfo {
+                                from: raddr,
+                                to: laddr,
+                            },
+                        )
+                        .await
+                    {
+                        log::error!("{:?} write datagram from {}, err={}", state, raddr, err);
+                    }
+                }
+            }
+        })?;
+
+        Ok(())
+    }
+
+    /// Open new outgoing stream.
+    pub async fn open_stream(&self) -> io::Result<QuicStream> {
+        let stream_id = self.state.open_stream().await?;
+
+        Ok(QuicStream::new(self.state.clone(), stream_id))
+    }
+
+    /// Open new outgoing unidirectional stream.
+    pub async fn open_uni_stream(&self) -> io::Result<QuicSendStream> {
+        let stream_id = self.state.open_uni_stream().await?;
+
+        Ok(QuicSendStream::new(self.state.clone(), stream_id))
+    }
+
+    /// Accept one incoming stream, returns `None` if the connection had been closed.
+    pub async fn accept(&self) -> Option<QuicIncoming> {
+        self.accept_detailed().await.ok()
+    }
+
+    /// Accept one incoming stream, returns the error of why the connection was closed.
+    ///
+    /// The error carries the [`CloseReason`] if the `CONNECTION_CLOSE` frame was sent or received,
+    /// use [`CloseReason::from_io_error`] to extract it.
+    pub async fn accept_detailed(&self) -> io::Result<QuicIncoming> {
+        let stream_id = self.state.accept_detailed().await?;
+
+        Ok(QuicIncoming::new(self.state.clone(), stream_id))
+    }
+
+    /// Subscribes the `kinds` events of this connection, see [`QuicConnState::subscribe`].
+    pub fn subscribe(&self, kinds: QuicEventKind) -> QuicEventStream {
+        self.state.subscribe(kinds)
+    }
+
+    /// Returns the max udp payload size of the current path, see [`QuicConnState::current_mtu`].
+    pub async fn current_mtu(&self) -> usize {
+        self.state.current_mtu().await
+    }
+
+    /// Returns the reason the connection was closed, `None` if no `CONNECTION_CLOSE` frame
+    /// was sent or received yet.
+    pub async fn close_reason(&self) -> Option<CloseReason> {
+        self.state.close_reason().await
+    }
+
+    /// Closes the connection with the application error code `err` and `reason`.
+    ///
+    /// The connection enters the draining state, the `CONNECTION_CLOSE` frames are still sent
+    /// until the draining period elapses, use [`closed`](Self::closed) to wait for it.
+    pub async fn close(&self, err: u64, reason: &[u8]) -> io::Result<()> {
+        self.state.close(true, err, reason).await
+    }
+
+    /// Waits until the connection is closed by either side and the draining period elapses.
+    pub async fn closed(&self) {
+        self.state.closed().await
+    }
+
+    /// Sets the keep-alive interval, the ping packet is sent if no packet has been sent
+    /// within `interval`. `None` disables keep-alive.
+    ///
+    /// The initial interval is [`Config::ping_timeout`].
+    pub async fn set_keep_alive(&self, interval: Option<Duration>) {
+        self.state.set_keep_alive(interval).await
+    }
+
+    /// Returns the keep-alive interval, `None` if keep-alive is disabled.
+    pub async fn keep_alive(&self) -> Option<Duration> {
+        self.state.keep_alive().await
+    }
+
+    /// Waits until no packet has been received from the peer for `idle` duration,
+    /// see [`QuicConnState::on_idle_timeout`] for more information.
+    pub async fn on_idle_timeout(&self, idle: Duration) -> io::Result<()> {
+        self.state.on_idle_timeout(idle).await
+    }
+
+    /// Returns true if the connection is closed.
+    pub async fn is_closed(&self) -> bool {
+        self.state.is_closed().await
+    }
+
+    /// Returns the number of bidirectional streams that can be created before the peer's limit is reached,
+    /// see [`QuicConnState::peer_streams_left_bidi`] for more information.
+    pub async fn peer_streams_left_bidi(&self) -> u64 {
+        self.state.peer_streams_left_bidi().await
+    }
+
+    /// Returns the connection-level flow control limit of the data sent to the peer,
+    /// see [`QuicConnState::max_data`] for more information.
+    pub async fn max_data(&self) -> u64 {
+        self.state.max_data().await
+    }
+
+    /// Returns the total bytes read by the application from all streams.
+    pub async fn data_consumed(&self) -> u64 {
+        self.state.data_consumed().await
+    }
+
+    /// Returns the flow control state of stream `stream_id`, see [`QuicStreamFlowControl`] for more information.
+    pub async fn stream_flow_control(&self, stream_id: u64) -> QuicStreamFlowControl {
+        self.state.stream_flow_control(stream_id).await
+    }
+
+    /// Enables or disables the manual flow control of the receiving side,
+    /// see [`QuicConnState::set_manual_flow_control`] for more information.
+    pub async fn set_manual_flow_control(&self, enabled: bool) {
+        self.state.set_manual_flow_control(enabled).await
+    }
+
+    /// Grants `bytes` receive window of stream `stream_id` to the peer,
+    /// see [`QuicConnState::stream_grant`] for more information.
+    pub async fn stream_grant(&self, stream_id: u64, bytes: u64) -> io::Result<()> {
+        self.state.stream_grant(stream_id, bytes).await
+    }
+
+    /// Sets the weight of this connection in the send path of the listener,
+    /// see [`QuicConnState::set_weight`] for more information.
+    pub fn set_weight(&self, weight: u32) {
+        self.state.set_weight(weight)
+    }
+
+    /// Returns the weight of this connection in the send path of the listener.
+    pub fn weight(&self) -> u32 {
+        self.state.weight()
+    }
+
+    /// Returns true if the peer address has been validated,
+    /// see [`QuicConnState::is_address_validated`] for more information.
+    pub async fn is_address_validated(&self) -> bool {
+        self.state.is_address_validated().await
+    }
+
+    /// Returns the DER-encoded peer's leaf certificate, `None` if the peer doesn't provide one.
+    pub async fn peer_cert(&self) -> Option<Vec<u8>> {
+        self.state.peer_cert().await
+    }
+
+    /// Returns the negotiated ALPN protocol, `None` if no protocol was negotiated.
+    pub async fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        self.state.alpn_protocol().await
+    }
+
+    /// Returns the server name requested by the client.
+    pub async fn server_name(&self) -> Option<String> {
+        self.state.server_name().await
+    }
+
+    /// Returns the negotiated handshake parameters, see [`QuicHandshakeInfo`] for more information.
+    pub async fn handshake_info(&self) -> QuicHandshakeInfo {
+        self.state.handshake_info().await
+    }
+}
+
+impl Drop for QuicConn {
+    fn drop(&mut self) {
+        // The pump tasks hold the connection state, so close it explicitly.
+        let state = self.state.clone();
+
+        if let Err(err) = io_spawn(async move {
+            state.close(false, 0, b"raii drop").await?;
+
+            Ok(())
+        }) {
+            log::error!("spawn quic conn closing task failed, err={}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, sync::Arc, time::Duration};
+
+    use hala_io::{current::executor::io_spawn, test::io_test, timeout};
+    use hala_udp::UdpSocket;
+    use quiche::RecvInfo;
+
+    use crate::{
+        mock_config,
+        state::{QuicListenerState, QuicListenerWriteResult},
+        QuicStream,
+    };
+
+    use super::{QuicConn, MAX_DATAGRAM_SIZE};
+
+    fn spawn_mock_server() -> (QuicListenerState, std::net::SocketAddr) {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").unwrap());
+
+        let laddr = socket.local_addr().unwrap();
+
+        let listener = QuicListenerState::new(mock_config(true, 1350)).unwrap();
+
+        let recv_listener = listener.clone();
+        let recv_socket = socket.clone();
+
+        io_spawn(async move {
+            let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+            loop {
+                let (recv_size, from) = recv_socket.recv_from(&mut buf).await?;
+
+                let result = recv_listener
+                    .write(&mut buf, recv_size, RecvInfo { from, to: laddr })
+                    .await?;
+
+                match result {
+                    QuicListenerWriteResult::WriteSize(_) => {}
+                    QuicListenerWriteResult::Internal {
+                        read_size,
+                        send_info,
+                        ..
+                    }
+                    | QuicListenerWriteResult::Incoming {
+                        read_size,
+                        send_info,
+                        ..
+                    } => {
+                        recv_socket.send_to(&buf[..read_size], send_info.to).await?;
+                    }
+                }
+            }
+        })
+        .unwrap();
+
+        let send_listener = listener.clone();
+
+        io_spawn(async move {
+            loop {
+                if let Ok((buf, send_info)) = send_listener.read().await {
+                    socket.send_to(&buf, send_info.to).await?;
+                }
+            }
+        })
+        .unwrap();
+
+        (listener, laddr)
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_connect_udp() {
+        let (listener, raddr) = spawn_mock_server();
+
+        let conn = QuicConn::connect_udp(raddr, &mut mock_config(false, 1350))
+            .await
+            .unwrap();
+
+        let stream = conn.open_stream().await.unwrap();
+
+        stream.send(b"hello", false).await.unwrap();
+
+        let server_conn = listener.accept().await.unwrap();
+
+        let server_stream_id = server_conn.accept().await.unwrap();
+
+        let server_stream = QuicStream::new(server_conn, server_stream_id);
+
+        let mut buf = vec![0; 1024];
+
+        let (read_size, _) = server_stream.recv(&mut buf).await.unwrap();
+
+        server_stream.send(&buf[..read_size], true).await.unwrap();
+
+        let (read_size, fin) = stream.recv(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..read_size], b"hello");
+        assert!(fin);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_connect_unresponsive() {
+        // The bound socket never answers the handshake.
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let mut config = mock_config(false, 1350);
+
+        config.set_max_idle_timeout(200);
+
+        let err = timeout(
+            QuicConn::connect_udp(socket.local_addr().unwrap(), &mut config),
+            Some(Duration::from_secs(5)),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(err.to_string().contains("handshake timed out"));
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_keep_alive() {
+        let (listener, raddr) = spawn_mock_server();
+
+        let conn = QuicConn::connect_udp(raddr, &mut mock_config(false, 1350))
+            .await
+            .unwrap();
+
+        conn.set_keep_alive(Some(Duration::from_millis(50))).await;
+
+        assert_eq!(conn.keep_alive().await, Some(Duration::from_millis(50)));
+
+        let stream = conn.open_stream().await.unwrap();
+
+        stream.send(b"hello", false).await.unwrap();
+
+        let server_conn = listener.accept().await.unwrap();
+
+        server_conn.set_keep_alive(None).await;
+
+        // The ping packets keep the connection alive.
+        let err = timeout(
+            server_conn.on_idle_timeout(Duration::from_millis(200)),
+            Some(Duration::from_millis(500)),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        conn.set_keep_alive(None).await;
+
+        server_conn
+            .on_idle_timeout(Duration::from_millis(200))
+            .await
+            .unwrap();
+    }
+}
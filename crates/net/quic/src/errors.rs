@@ -33,6 +33,9 @@ impl From<HalaIoError> for std::io::Error {
                 quiche::Error::StreamStopped(_) | quiche::Error::InvalidStreamState(_) => {
                     std::io::Error::new(std::io::ErrorKind::BrokenPipe, err)
                 }
+                quiche::Error::StreamReset(_) => {
+                    std::io::Error::new(std::io::ErrorKind::ConnectionReset, err)
+                }
                 quiche::Error::StreamLimit => std::io::Error::new(std::io::ErrorKind::Other, err),
                 _ => std::io::Error::new(std::io::ErrorKind::Other, err),
             },
@@ -55,3 +58,64 @@ pub fn into_io_error<E: Into<HalaIoError>>(error: E) -> io::Error {
 
     err.into()
 }
+
+/// Returns the application error code of the peer-initiated stream error.
+///
+/// The `RESET_STREAM` frame surfaces as [`ConnectionReset`](io::ErrorKind::ConnectionReset) error on reading,
+/// and the `STOP_SENDING` frame surfaces as [`BrokenPipe`](io::ErrorKind::BrokenPipe) error on writing.
+pub fn stream_error_code(error: &io::Error) -> Option<u64> {
+    match error.get_ref()?.downcast_ref::<quiche::Error>()? {
+        quiche::Error::StreamReset(code) | quiche::Error::StreamStopped(code) => Some(*code),
+        _ => None,
+    }
+}
+
+/// The reason the connection was closed, carried by the `CONNECTION_CLOSE` frame.
+///
+/// It's surfaced as [`BrokenPipe`](io::ErrorKind::BrokenPipe) kind io error by the operations
+/// of the closed connection, use [`CloseReason::from_io_error`] to extract it.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "connection closed, local={is_local}, app={is_app}, error_code={error_code}, reason={}",
+    String::from_utf8_lossy(reason)
+)]
+pub struct CloseReason {
+    /// Whether the connection was closed by the local endpoint.
+    pub is_local: bool,
+    /// Whether the error came from the application or the transport layer.
+    pub is_app: bool,
+    /// The error code carried by the `CONNECTION_CLOSE` frame.
+    pub error_code: u64,
+    /// The reason bytes carried by the `CONNECTION_CLOSE` frame.
+    pub reason: Vec<u8>,
+}
+
+impl CloseReason {
+    /// Returns the close reason of `conn`, the error sent by the peer takes precedence.
+    ///
+    /// Returns `None` if no `CONNECTION_CLOSE` frame was sent or received, e.g. the idle timeout elapsed.
+    pub fn from_conn(conn: &quiche::Connection) -> Option<Self> {
+        let (is_local, error) = match conn.peer_error() {
+            Some(error) => (false, error),
+            None => (true, conn.local_error()?),
+        };
+
+        Some(Self {
+            is_local,
+            is_app: error.is_app,
+            error_code: error.error_code,
+            reason: error.reason.clone(),
+        })
+    }
+
+    /// Returns the [`CloseReason`] carried by `err`, or `None` if it's another error.
+    pub fn from_io_error(err: &io::Error) -> Option<&CloseReason> {
+        err.get_ref()?.downcast_ref::<CloseReason>()
+    }
+}
+
+impl From<CloseReason> for io::Error {
+    fn from(value: CloseReason) -> Self {
+        io::Error::new(io::ErrorKind::BrokenPipe, value)
+    }
+}
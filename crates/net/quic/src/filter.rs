@@ -0,0 +1,238 @@
+use std::{
+    collections::HashSet,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use quiche::ConnectionId;
+
+/// The header fields of the datagram received by the listener, which are inspected by [`PacketFilter`].
+#[derive(Debug)]
+pub struct PacketInfo<'a> {
+    /// The source address of the datagram.
+    pub from: SocketAddr,
+    /// The destination connection id of the first packet in the datagram.
+    pub dcid: &'a ConnectionId<'a>,
+    /// The type of the first packet in the datagram.
+    pub ty: quiche::Type,
+}
+
+/// The hook of the listener recv loops, which inspects the datagram before it's processed by quiche,
+/// e.g. dropping the datagrams of a DDoS attack.
+pub trait PacketFilter: Send + Sync {
+    /// Returns false to drop the datagram.
+    fn accept(&self, packet: &PacketInfo<'_>) -> bool;
+}
+
+impl<F> PacketFilter for F
+where
+    F: Fn(&PacketInfo<'_>) -> bool + Send + Sync,
+{
+    fn accept(&self, packet: &PacketInfo<'_>) -> bool {
+        self(packet)
+    }
+}
+
+/// Parses the header of the datagram `buf` and runs `filters`, returns false if any filter drops it.
+///
+/// The datagram with invalid header is dropped too, which would be dropped by quiche anyway.
+pub(crate) fn filter_packet(
+    filters: &[Arc<dyn PacketFilter>],
+    buf: &mut [u8],
+    from: SocketAddr,
+) -> bool {
+    if filters.is_empty() {
+        return true;
+    }
+
+    let header = match quiche::Header::from_slice(buf, quiche::MAX_CONN_ID_LEN) {
+        Ok(header) => header,
+        Err(err) => {
+            log::trace!("filter datagram from {}, invalid header, err={}", from, err);
+            return false;
+        }
+    };
+
+    let packet = PacketInfo {
+        from,
+        dcid: &header.dcid,
+        ty: header.ty,
+    };
+
+    filters.iter().all(|filter| filter.accept(&packet))
+}
+
+/// The static blocklist filter, which drops the datagrams from the listed ip addresses.
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    addrs: HashSet<IpAddr>,
+}
+
+impl Blocklist {
+    /// Creates the blocklist of `addrs`.
+    pub fn new<I: IntoIterator<Item = IpAddr>>(addrs: I) -> Self {
+        Self {
+            addrs: addrs.into_iter().collect(),
+        }
+    }
+}
+
+impl PacketFilter for Blocklist {
+    fn accept(&self, packet: &PacketInfo<'_>) -> bool {
+        !self.addrs.contains(&packet.from.ip())
+    }
+}
+
+/// The token bucket of one source ip address.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// The per-ip rate limiting filter, which drops the datagrams beyond the rate by token bucket.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// The tokens refilled per second.
+    rate: f64,
+    /// The capacity of bucket.
+    burst: f64,
+    /// Only limits the initial packets, i.e. the new connections.
+    initial_only: bool,
+    /// The maximum number of tracked ip addresses.
+    max_tracked: usize,
+    buckets: DashMap<IpAddr, TokenBucket>,
+}
+
+impl RateLimiter {
+    /// Creates the filter that accepts `packets_per_second` datagrams from each ip address on average,
+    /// and up to `burst` datagrams at once.
+    pub fn new(packets_per_second: u32, burst: u32) -> Self {
+        Self {
+            rate: packets_per_second as f64,
+            burst: burst.max(1) as f64,
+            initial_only: false,
+            max_tracked: 65536,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Only limits the initial packets, so the established connections are not affected.
+    pub fn initial_only(mut self) -> Self {
+        self.initial_only = true;
+        self
+    }
+
+    /// Sets the maximum number of tracked ip addresses, the default value is 65536.
+    ///
+    /// Once the limit is reached, the idle buckets are removed, and the datagrams from
+    /// the new ip addresses are dropped if all the buckets are in use.
+    pub fn max_tracked(mut self, n: usize) -> Self {
+        self.max_tracked = n;
+        self
+    }
+
+    /// Returns the duration after which the bucket is full again.
+    fn refill_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.burst / self.rate.max(f64::MIN_POSITIVE))
+    }
+}
+
+impl PacketFilter for RateLimiter {
+    fn accept(&self, packet: &PacketInfo<'_>) -> bool {
+        if self.initial_only && packet.ty != quiche::Type::Initial {
+            return true;
+        }
+
+        let ip = packet.from.ip();
+
+        let now = Instant::now();
+
+        if !self.buckets.contains_key(&ip) && self.buckets.len() >= self.max_tracked {
+            let refill_duration = self.refill_duration();
+
+            self.buckets
+                .retain(|_, bucket| now.duration_since(bucket.updated) < refill_duration);
+
+            if self.buckets.len() >= self.max_tracked {
+                log::trace!(
+                    "rate limiter, too many tracked ip, drop datagram from {}",
+                    ip
+                );
+                return false;
+            }
+        }
+
+        let mut bucket = self.buckets.entry(ip).or_insert(TokenBucket {
+            tokens: self.burst,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+
+    use super::*;
+
+    fn packet<'a>(from: &str, dcid: &'a ConnectionId<'a>, ty: quiche::Type) -> PacketInfo<'a> {
+        PacketInfo {
+            from: from.parse().unwrap(),
+            dcid,
+            ty,
+        }
+    }
+
+    #[test]
+    fn test_blocklist() {
+        let dcid = ConnectionId::from_ref(&[1; 16]);
+
+        let blocklist = Blocklist::new(["10.0.0.1".parse().unwrap()]);
+
+        assert!(!blocklist.accept(&packet("10.0.0.1:443", &dcid, quiche::Type::Initial)));
+        assert!(blocklist.accept(&packet("10.0.0.2:443", &dcid, quiche::Type::Initial)));
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let dcid = ConnectionId::from_ref(&[1; 16]);
+
+        let limiter = RateLimiter::new(10, 2).initial_only().max_tracked(2);
+
+        for _ in 0..2 {
+            assert!(limiter.accept(&packet("10.0.0.1:1", &dcid, quiche::Type::Initial)));
+        }
+
+        assert!(!limiter.accept(&packet("10.0.0.1:1", &dcid, quiche::Type::Initial)));
+
+        // The established connections are not limited.
+        assert!(limiter.accept(&packet("10.0.0.1:1", &dcid, quiche::Type::Short)));
+
+        // The buckets are per ip.
+        assert!(limiter.accept(&packet("10.0.0.2:1", &dcid, quiche::Type::Initial)));
+
+        // No idle bucket can be removed.
+        assert!(!limiter.accept(&packet("10.0.0.3:1", &dcid, quiche::Type::Initial)));
+
+        sleep(Duration::from_millis(250));
+
+        assert!(limiter.accept(&packet("10.0.0.1:1", &dcid, quiche::Type::Initial)));
+        assert!(limiter.accept(&packet("10.0.0.3:1", &dcid, quiche::Type::Initial)));
+    }
+}
@@ -1,7 +1,25 @@
 mod config;
+mod session;
+mod sni;
 
 pub mod state;
 
 pub use config::*;
+pub use session::*;
 
 pub mod errors;
+
+mod filter;
+pub use filter::*;
+
+mod stream;
+pub use stream::*;
+
+mod conn;
+pub use conn::*;
+
+mod listener;
+pub use listener::*;
+
+mod pool;
+pub use pool::*;
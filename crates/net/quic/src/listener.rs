@@ -0,0 +1,1067 @@
+use std::{
+    fmt::Debug,
+    future::Future,
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::Arc,
+};
+
+use futures::{
+    future::{select, select_all},
+    stream, FutureExt, Stream, StreamExt,
+};
+use hala_future::oneshot;
+use hala_io::{
+    cancellable, current::executor::io_spawn, interval, BindOptions, CancellationToken,
+    DatagramInfo,
+};
+use hala_lockfree::{mpmc::AsyncQueue, pool::PooledBuf};
+use hala_udp::UdpSocket;
+use quiche::{RecvInfo, SendInfo};
+
+use crate::{
+    datagram_pool, filter_packet, setup_socket,
+    state::{ConnRouter, QuicListenerState, QuicListenerWriteResult, HANDSHAKE_TIMER_TICK},
+    Config, PacketFilter, QuicConn,
+};
+
+/// The max number of received datagrams queued by one worker shard,
+/// the datagrams are dropped if the queue is full.
+const WORKER_QUEUE_LEN: usize = 256;
+
+/// The datagram received by recv loop and dispatched to the worker shard.
+struct Datagram {
+    buf: PooledBuf,
+    recv_size: usize,
+    recv_info: RecvInfo,
+    /// The index of socket which received this datagram.
+    socket: usize,
+}
+
+type Sockets = Arc<Vec<(Arc<UdpSocket>, SocketAddr)>>;
+
+/// The packet filters of each shard.
+type Filters = Arc<Vec<Vec<Arc<dyn PacketFilter>>>>;
+
+/// Quic server listener, which owns one underlying udp socket per local address.
+///
+/// The received datagrams are dispatched to the worker shards by destination connection id,
+/// each shard owns its own connections, see [`bind_with_workers`](Self::bind_with_workers).
+///
+/// The udp datagram pump tasks are spawned by [`io_spawn`] and stop when the listener is dropped,
+/// after that the accepted connections can no longer send or receive datagrams. If any pump task fails,
+/// the others are stopped and the listener is closed.
+pub struct QuicListener {
+    states: Vec<QuicListenerState>,
+    router: ConnRouter,
+    laddrs: Vec<SocketAddr>,
+    _closed_senders: Vec<oneshot::Sender<()>>,
+}
+
+impl Debug for QuicListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "QuicListener({:?}, {:?})", self.laddrs, self.router)
+    }
+}
+
+impl QuicListener {
+    /// Binds one udp socket to each address of `laddrs` and creates listener with `config`.
+    ///
+    /// The incoming connections from all sockets are accepted by the same listener,
+    /// e.g. binding both `0.0.0.0:443` and `[::]:443`.
+    pub fn bind<L: ToSocketAddrs>(laddrs: L, config: Config) -> io::Result<Self> {
+        let router = ConnRouter::new(1);
+
+        let filters = vec![config.packet_filters.clone()];
+
+        let options = Self::bind_options(&config);
+
+        let pmtud = config.pmtud;
+
+        let state = QuicListenerState::with_router(config, router.clone(), 0)?;
+
+        Self::bind_states(
+            laddrs,
+            options,
+            pmtud,
+            router,
+            vec![state],
+            Arc::new(filters),
+        )
+    }
+
+    /// Binds one udp socket to each address of `laddrs` and creates listener with `workers` shards.
+    ///
+    /// The shard `n` is created with the config returned by `config(n)`. The recv loops dispatch
+    /// datagrams to the shards by [`ConnRouter`], and every shard processes its own connections
+    /// in separate tasks, so the shards can run in parallel on a multi-thread executor.
+    pub fn bind_with_workers<L, F>(laddrs: L, workers: usize, mut config: F) -> io::Result<Self>
+    where
+        L: ToSocketAddrs,
+        F: FnMut(usize) -> io::Result<Config>,
+    {
+        if workers == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "workers must be greater than zero",
+            ));
+        }
+
+        let router = ConnRouter::new(workers);
+
+        let mut filters = vec![];
+
+        let mut options = BindOptions::default();
+
+        let mut pmtud = false;
+
+        let states = (0..workers)
+            .map(|shard| {
+                let config = config(shard)?;
+
+                if shard == 0 {
+                    options = Self::bind_options(&config);
+                    pmtud = config.pmtud;
+                }
+
+                filters.push(config.packet_filters.clone());
+
+                QuicListenerState::with_router(config, router.clone(), shard)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Self::bind_states(laddrs, options, pmtud, router, states, Arc::new(filters))
+    }
+
+    /// Returns the options of the udp sockets bound by listener with `config`.
+    fn bind_options(config: &Config) -> BindOptions {
+        BindOptions {
+            recv_buffer_size: config.socket_buffer_size,
+            send_buffer_size: config.socket_buffer_size,
+            ttl: config.socket_ttl,
+            ..Default::default()
+        }
+    }
+
+    fn bind_states<L: ToSocketAddrs>(
+        laddrs: L,
+        options: BindOptions,
+        pmtud: bool,
+        router: ConnRouter,
+        states: Vec<QuicListenerState>,
+        filters: Filters,
+    ) -> io::Result<Self> {
+        let mut sockets = vec![];
+
+        for laddr in laddrs.to_socket_addrs()? {
+            let socket = UdpSocket::bind_with_options(laddr, options)?;
+
+            // The DF bit is set after binding, so the listener still works on the platforms without it.
+            setup_socket(&socket, None, pmtud)?;
+
+            let laddr = socket.local_addr()?;
+
+            sockets.push((Arc::new(socket), laddr));
+        }
+
+        if sockets.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any addresses",
+            ));
+        }
+
+        let laddrs = sockets.iter().map(|(_, laddr)| *laddr).collect();
+
+        let closed_senders = Self::spawn_pump(&states, router.clone(), Arc::new(sockets), filters)?;
+
+        Ok(Self {
+            states,
+            router,
+            laddrs,
+            _closed_senders: closed_senders,
+        })
+    }
+
+    /// Spawns the recv loop per socket, and the worker / send loop per shard.
+    ///
+    /// Returns the senders to stop the send loops, the worker stops with the send loop of the same shard,
+    /// and the recv loops stop after all send loops have stopped.
+    fn spawn_pump(
+        states: &[QuicListenerState],
+        router: ConnRouter,
+        sockets: Sockets,
+        filters: Filters,
+    ) -> io::Result<Vec<oneshot::Sender<()>>> {
+        let mut recv_closed_senders = vec![];
+        let mut recv_closed_receivers = vec![];
+
+        for _ in sockets.iter() {
+            let (sender, receiver) = oneshot::channel::<()>();
+
+            recv_closed_senders.push(sender);
+            recv_closed_receivers.push(receiver);
+        }
+
+        let recv_closed_senders = Arc::new(recv_closed_senders);
+
+        let queues = states
+            .iter()
+            .map(|_| Arc::new(AsyncQueue::new(WORKER_QUEUE_LEN)))
+            .collect::<Vec<_>>();
+
+        let queues = Arc::new(queues);
+
+        let mut closed_senders = vec![];
+
+        let pump = PumpHandle {
+            token: CancellationToken::new(),
+            states: Arc::new(states.to_vec()),
+        };
+
+        for (state, queue) in states.iter().zip(queues.iter()) {
+            let (closed_sender, closed_receiver) = oneshot::channel::<()>();
+
+            closed_senders.push(closed_sender);
+
+            let (worker_closed_sender, worker_closed_receiver) = oneshot::channel::<()>();
+
+            Self::spawn_worker(
+                &pump,
+                state.clone(),
+                queue.clone(),
+                sockets.clone(),
+                worker_closed_receiver,
+            )?;
+
+            Self::spawn_send_loop(
+                &pump,
+                state.clone(),
+                sockets.clone(),
+                closed_receiver,
+                (worker_closed_sender, recv_closed_senders.clone()),
+            )?;
+        }
+
+        for (index, closed) in recv_closed_receivers.into_iter().enumerate() {
+            Self::spawn_recv_loop(
+                &pump,
+                router.clone(),
+                queues.clone(),
+                sockets.clone(),
+                filters.clone(),
+                index,
+                closed,
+            )?;
+        }
+
+        Ok(closed_senders)
+    }
+
+    fn spawn_send_loop(
+        pump: &PumpHandle,
+        state: QuicListenerState,
+        sockets: Sockets,
+        closed_receiver: oneshot::Receiver<()>,
+        closed_senders: (oneshot::Sender<()>, Arc<Vec<oneshot::Sender<()>>>),
+    ) -> io::Result<()> {
+        pump.spawn(async move {
+            let _closed_senders = closed_senders;
+
+            let mut closed = closed_receiver.fuse();
+
+            loop {
+                let read = Box::pin(state.read());
+
+                let (buf, send_info) = match select(read, &mut closed).await {
+                    futures::future::Either::Left((Ok(r), _)) => r,
+                    // the broken conn is removed by listener.
+                    futures::future::Either::Left((Err(_), _)) => continue,
+                    futures::future::Either::Right(_) => {
+                        log::trace!("QuicListener shard({}) send loop stopped", state.shard());
+                        return Ok(());
+                    }
+                };
+
+                send(route(&sockets, send_info.from), &buf, send_info).await?;
+            }
+        })
+    }
+
+    fn spawn_worker(
+        pump: &PumpHandle,
+        state: QuicListenerState,
+        queue: Arc<AsyncQueue<Datagram>>,
+        sockets: Sockets,
+        closed_receiver: oneshot::Receiver<()>,
+    ) -> io::Result<()> {
+        pump.spawn(async move {
+            let mut closed = closed_receiver.fuse();
+
+            // Collects the half-open handshakes when no datagram is received.
+            let mut handshake_gc = interval(HANDSHAKE_TIMER_TICK)?;
+
+            loop {
+                let recv = select(Box::pin(queue.recv()), handshake_gc.next());
+
+                let Datagram {
+                    mut buf,
+                    recv_size,
+                    recv_info,
+                    socket,
+                } = match select(recv, &mut closed).await {
+                    futures::future::Either::Left((
+                        futures::future::Either::Left((datagram, _)),
+                        _,
+                    )) => datagram,
+                    futures::future::Either::Left((futures::future::Either::Right(_), _)) => {
+                        state.collect_expired_handshakes().await;
+                        continue;
+                    }
+                    futures::future::Either::Right(_) => {
+                        log::trace!("QuicListener shard({}) worker stopped", state.shard());
+                        return Ok(());
+                    }
+                };
+
+                match state.write(&mut buf, recv_size, recv_info).await {
+                    Ok(QuicListenerWriteResult::WriteSize(_)) => {}
+                    Ok(QuicListenerWriteResult::Internal {
+                        read_size,
+                        send_info,
+                        ..
+                    })
+                    | Ok(QuicListenerWriteResult::Incoming {
+                        read_size,
+                        send_info,
+                        ..
+                    }) => {
+                        send(&sockets[socket].0, &buf[..read_size], send_info).await?;
+                    }
+                    Err(err) => {
+                        log::error!(
+                            "QuicListener shard({}) write datagram from {}, err={}",
+                            state.shard(),
+                            recv_info.from,
+                            err
+                        );
+                    }
+                }
+            }
+        })
+    }
+
+    fn spawn_recv_loop(
+        pump: &PumpHandle,
+        router: ConnRouter,
+        queues: Arc<Vec<Arc<AsyncQueue<Datagram>>>>,
+        sockets: Sockets,
+        filters: Filters,
+        index: usize,
+        closed_receiver: oneshot::Receiver<()>,
+    ) -> io::Result<()> {
+        pump.spawn(async move {
+            let (socket, laddr) = &sockets[index];
+
+            let mut closed = closed_receiver.fuse();
+
+            loop {
+                let mut buf = datagram_pool().get();
+
+                let recv = Box::pin(recv(socket, &mut buf, *laddr));
+
+                let (recv_size, recv_info) = match select(recv, &mut closed).await {
+                    futures::future::Either::Left((r, _)) => r?,
+                    futures::future::Either::Right(_) => {
+                        log::trace!("QuicListener({}) recv loop stopped", laddr);
+                        return Ok(());
+                    }
+                };
+
+                let shard = match router.route_packet(&mut buf[..recv_size]) {
+                    Ok(shard) => shard,
+                    Err(err) => {
+                        log::error!(
+                            "QuicListener({}) route datagram from {}, err={}",
+                            laddr,
+                            recv_info.from,
+                            err
+                        );
+
+                        continue;
+                    }
+                };
+
+                if !filter_packet(&filters[shard], &mut buf[..recv_size], recv_info.from) {
+                    log::trace!(
+                        "QuicListener({}) shard({}) filter drop datagram from {}",
+                        laddr,
+                        shard,
+                        recv_info.from
+                    );
+
+                    continue;
+                }
+
+                let datagram = Datagram {
+                    buf,
+                    recv_size,
+                    recv_info,
+                    socket: index,
+                };
+
+                if queues[shard].try_send(datagram).is_err() {
+                    log::trace!(
+                        "QuicListener({}) shard({}) queue is full, drop datagram from {}",
+                        laddr,
+                        shard,
+                        recv_info.from
+                    );
+                }
+            }
+        })
+    }
+
+    /// Returns the first local address that this listener is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.laddrs[0]
+    }
+
+    /// Returns all the local addresses that this listener is bound to.
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.laddrs
+    }
+
+    /// Returns the connection id routing table of this listener.
+    pub fn router(&self) -> &ConnRouter {
+        &self.router
+    }
+
+    /// Accept one incoming connection from any shard, or returns `None` if this listener had been closed.
+    pub async fn accept(&self) -> Option<QuicConn> {
+        let accepts = self.states.iter().map(|state| Box::pin(state.accept()));
+
+        // The incoming connection stays in the queue if the accept future of its shard is dropped.
+        let (conn, _, _) = select_all(accepts).await;
+
+        conn.map(QuicConn::from)
+    }
+
+    /// Accept one incoming connection from any shard until `token` is cancelled, see [`accept`](Self::accept).
+    ///
+    /// Returns [`OperationCancelled`](hala_io::OperationCancelled) error on cancellation,
+    /// the incoming connection stays in the queue.
+    pub async fn accept_cancellable(
+        &self,
+        token: Option<&CancellationToken>,
+    ) -> io::Result<Option<QuicConn>> {
+        cancellable(async { Ok(self.accept().await) }, token).await
+    }
+
+    /// Returns a stream of incoming connections, which ends once this listener had been closed.
+    pub fn incoming(&self) -> impl Stream<Item = QuicConn> + '_ {
+        stream::unfold(self, |listener| async move {
+            listener.accept().await.map(|conn| (conn, listener))
+        })
+    }
+
+    /// Close this listener and drop the incoming queue, the accepted connections are not affected.
+    pub async fn close(&self) {
+        for state in &self.states {
+            state.close().await
+        }
+    }
+}
+
+/// The shared stop handle of the pump tasks of one listener.
+#[derive(Clone)]
+struct PumpHandle {
+    /// Cancelled once any pump task fails.
+    token: CancellationToken,
+    states: Arc<Vec<QuicListenerState>>,
+}
+
+impl PumpHandle {
+    /// Spawns one pump task by [`io_spawn`], so the tasks of different shards run in parallel.
+    ///
+    /// If the task fails, the other pump tasks are dropped and the listener is closed,
+    /// instead of leaving it half working.
+    fn spawn<Fut>(&self, fut: Fut) -> io::Result<()>
+    where
+        Fut: Future<Output = io::Result<()>> + Send + 'static,
+    {
+        let pump = self.clone();
+
+        io_spawn(async move {
+            let Err(err) = cancellable(fut, Some(&pump.token)).await else {
+                return Ok(());
+            };
+
+            // Stopped by the failure of another pump task.
+            if pump.token.is_cancelled() {
+                return Ok(());
+            }
+
+            log::error!("QuicListener pump stopped, err={}", err);
+
+            pump.token.cancel();
+
+            // Closes the incoming queues, so the pending accepts return `None`.
+            for state in pump.states.iter() {
+                state.close().await;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Receives one datagram, the destination address is reported by the driver if supported,
+/// which is required by the wildcard-bound socket.
+async fn recv(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+    laddr: SocketAddr,
+) -> io::Result<(usize, RecvInfo)> {
+    let (recv_size, from, to) = match socket.recv_msg(buf).await {
+        Ok((recv_size, from, info)) => (
+            recv_size,
+            from,
+            info.local_ip
+                .map(|ip| SocketAddr::new(ip, laddr.port()))
+                .unwrap_or(laddr),
+        ),
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+            let (recv_size, from) = socket.recv_from(buf).await?;
+
+            (recv_size, from, laddr)
+        }
+        Err(err) => return Err(err),
+    };
+
+    Ok((recv_size, RecvInfo { from, to }))
+}
+
+/// Selects the socket bound to the source address `from` of the sending datagram.
+///
+/// The wildcard-bound socket matches any source address of the same family and port.
+fn route(sockets: &[(Arc<UdpSocket>, SocketAddr)], from: SocketAddr) -> &UdpSocket {
+    sockets
+        .iter()
+        .find(|(_, laddr)| *laddr == from)
+        .or_else(|| {
+            sockets.iter().find(|(_, laddr)| {
+                laddr.port() == from.port()
+                    && laddr.ip().is_unspecified()
+                    && laddr.is_ipv4() == from.is_ipv4()
+            })
+        })
+        .or_else(|| {
+            sockets
+                .iter()
+                .find(|(_, laddr)| laddr.port() == from.port())
+        })
+        .unwrap_or(&sockets[0])
+        .0
+        .as_ref()
+}
+
+/// Sends one datagram from the source address selected by quiche.
+async fn send(socket: &UdpSocket, buf: &[u8], send_info: SendInfo) -> io::Result<usize> {
+    let info = DatagramInfo {
+        local_ip: Some(send_info.from.ip()).filter(|ip| !ip.is_unspecified()),
+        ..Default::default()
+    };
+
+    match socket.send_msg(buf, send_info.to, info).await {
+        Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+            socket.send_to(buf, send_info.to).await
+        }
+        r => r,
+    }
+}
+
+impl Drop for QuicListener {
+    fn drop(&mut self) {
+        let states = self.states.clone();
+
+        if let Err(err) = io_spawn(async move {
+            for state in states {
+                state.close().await;
+            }
+
+            Ok(())
+        }) {
+            log::error!("spawn quic listener closing task failed, err={}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        path::Path,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Condvar, Mutex,
+        },
+        time::Duration,
+    };
+
+    use futures::{future::join_all, StreamExt};
+    use hala_io::{test::io_test, CancellationToken, OperationCancelled};
+
+    use crate::{
+        mock_config, Blocklist, CertResolver, CertifiedKey, PacketInfo, QuicConn, QuicIncoming,
+    };
+
+    use super::QuicListener;
+
+    #[hala_test::test(io_test)]
+    async fn test_listener() {
+        let listener = QuicListener::bind("0.0.0.0:0", mock_config(true, 1350)).unwrap();
+
+        let raddr = format!("127.0.0.1:{}", listener.local_addr().port());
+
+        let conn = QuicConn::connect_udp(raddr, &mut mock_config(false, 1350))
+            .await
+            .unwrap();
+
+        let stream = conn.open_stream().await.unwrap();
+
+        stream.send(b"hello", false).await.unwrap();
+
+        let server_conn = Box::pin(listener.incoming()).next().await.unwrap();
+
+        let QuicIncoming::Bidi(server_stream) = server_conn.accept().await.unwrap() else {
+            panic!("expect bidirectional stream");
+        };
+
+        let mut buf = vec![0; 1024];
+
+        let (read_size, _) = server_stream.recv(&mut buf).await.unwrap();
+
+        server_stream.send(&buf[..read_size], true).await.unwrap();
+
+        let (read_size, fin) = stream.recv(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..read_size], b"hello");
+        assert!(fin);
+
+        listener.close().await;
+
+        assert!(listener.accept().await.is_none());
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_stream_body_reader() {
+        use hala_io::body::BodyReadExt;
+
+        let listener = QuicListener::bind("127.0.0.1:0", mock_config(true, 1350)).unwrap();
+
+        let conn = QuicConn::connect_udp(listener.local_addr(), &mut mock_config(false, 1350))
+            .await
+            .unwrap();
+
+        let stream = conn.open_stream().await.unwrap();
+
+        stream.send(b"5\nhello", true).await.unwrap();
+
+        let server_conn = listener.accept().await.unwrap();
+
+        let QuicIncoming::Bidi(mut server_stream) = server_conn.accept().await.unwrap() else {
+            panic!("expect bidirectional stream");
+        };
+
+        let mut header = [0; 2];
+
+        futures::AsyncReadExt::read_exact(&mut server_stream, &mut header)
+            .await
+            .unwrap();
+
+        let mut body = vec![];
+
+        futures::AsyncReadExt::read_to_end(&mut (&mut server_stream).take_exact(5), &mut body)
+            .await
+            .unwrap();
+
+        assert_eq!(body, b"hello");
+
+        // The fin flag has been read.
+        let mut buf = [0; 1];
+
+        assert_eq!(
+            futures::AsyncReadExt::read(&mut server_stream, &mut buf)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_conn_subscribe() {
+        use crate::state::{QuicEvent, QuicEventKind};
+
+        let listener = QuicListener::bind("127.0.0.1:0", mock_config(true, 1350)).unwrap();
+
+        let conn = QuicConn::connect_udp(listener.local_addr(), &mut mock_config(false, 1350))
+            .await
+            .unwrap();
+
+        let mut events = conn.subscribe(
+            QuicEventKind::StreamReadable
+                | QuicEventKind::StreamFinished
+                | QuicEventKind::ConnectionClosed,
+        );
+
+        let stream = conn.open_stream().await.unwrap();
+
+        stream.send(b"hello", false).await.unwrap();
+
+        let server_conn = listener.accept().await.unwrap();
+
+        let QuicIncoming::Bidi(server_stream) = server_conn.accept().await.unwrap() else {
+            panic!("expect bidirectional stream");
+        };
+
+        server_stream.send(b"world", true).await.unwrap();
+
+        assert_eq!(
+            events.next().await,
+            Some(QuicEvent::StreamReadable(stream.id()))
+        );
+
+        let mut buf = [0; 1024];
+
+        let (read_size, fin) = stream.recv(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..read_size], b"world");
+        assert!(fin);
+
+        // `StreamReadable` may be published again before the fin flag is read.
+        loop {
+            match events.next().await.unwrap() {
+                QuicEvent::StreamFinished(id) if id == stream.id() => break,
+                event => assert_eq!(event, QuicEvent::StreamReadable(stream.id())),
+            }
+        }
+
+        conn.close(0, b"").await.unwrap();
+
+        assert_eq!(events.next().await, Some(QuicEvent::ConnectionClosed));
+        assert_eq!(events.next().await, None);
+
+        // Subscribing the closed connection yields `ConnectionClosed` only.
+        let events = conn.subscribe(QuicEventKind::all_bits());
+
+        assert_eq!(
+            events.collect::<Vec<_>>().await,
+            vec![QuicEvent::ConnectionClosed]
+        );
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_conn_pmtud() {
+        use crate::state::BASE_PLPMTU;
+
+        let listener = QuicListener::bind("127.0.0.1:0", mock_config(true, 1350)).unwrap();
+
+        let conn = QuicConn::connect_udp(listener.local_addr(), &mut mock_config(false, 1350))
+            .await
+            .unwrap();
+
+        assert_eq!(conn.current_mtu().await, BASE_PLPMTU);
+
+        let stream = conn.open_stream().await.unwrap();
+
+        // The probe carries the stream data.
+        stream.send(&[1; 1350 * 5], false).await.unwrap();
+
+        let server_conn = listener.accept().await.unwrap();
+
+        let QuicIncoming::Bidi(server_stream) = server_conn.accept().await.unwrap() else {
+            panic!("expect bidirectional stream");
+        };
+
+        let mut buf = vec![0; 1350 * 5];
+        let mut read_size = 0;
+
+        while read_size < buf.len() {
+            read_size += server_stream.recv(&mut buf[read_size..]).await.unwrap().0;
+        }
+
+        // Waits for the probe timeout.
+        hala_io::sleep(std::time::Duration::from_millis(500))
+            .await
+            .unwrap();
+
+        assert_eq!(conn.current_mtu().await, 1350);
+
+        let mut config = mock_config(false, 1350);
+
+        config.enable_pmtud(false);
+
+        let conn = QuicConn::connect_udp(listener.local_addr(), &mut config)
+            .await
+            .unwrap();
+
+        assert_eq!(conn.current_mtu().await, 1350);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_listener_packet_filter() {
+        let initial_packets = Arc::new(AtomicUsize::new(0));
+
+        let mut config = mock_config(true, 1350);
+
+        config.add_packet_filter(Blocklist::new(["10.0.0.1".parse().unwrap()]));
+
+        config.add_packet_filter({
+            let initial_packets = initial_packets.clone();
+
+            move |packet: &PacketInfo<'_>| {
+                if packet.ty == quiche::Type::Initial {
+                    initial_packets.fetch_add(1, Ordering::SeqCst);
+                }
+
+                true
+            }
+        });
+
+        let listener = QuicListener::bind("127.0.0.1:0", config).unwrap();
+
+        let conn = QuicConn::connect_udp(listener.local_addr(), &mut mock_config(false, 1350))
+            .await
+            .unwrap();
+
+        conn.open_stream()
+            .await
+            .unwrap()
+            .send(b"hello", false)
+            .await
+            .unwrap();
+
+        listener.accept().await.unwrap();
+
+        assert!(initial_packets.load(Ordering::SeqCst) > 0);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_connect_any() {
+        let listener = QuicListener::bind("127.0.0.1:0", mock_config(true, 1350)).unwrap();
+
+        // No one listens on the closed port.
+        let closed_addr = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let conn = QuicConn::connect_any(
+            &[closed_addr, listener.local_addr()],
+            &mut mock_config(false, 1350),
+        )
+        .await
+        .unwrap();
+
+        conn.open_stream()
+            .await
+            .unwrap()
+            .send(b"hello", false)
+            .await
+            .unwrap();
+
+        listener.accept().await.unwrap();
+
+        QuicConn::connect_any(&[], &mut mock_config(false, 1350))
+            .await
+            .unwrap_err();
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_listener_accept_cancellable() {
+        let listener = QuicListener::bind("127.0.0.1:0", mock_config(true, 1350)).unwrap();
+
+        let token = CancellationToken::new();
+
+        let canceller = {
+            let token = token.clone();
+
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                token.cancel();
+            })
+        };
+
+        let err = listener
+            .accept_cancellable(Some(&token.child_token()))
+            .await
+            .unwrap_err();
+
+        assert!(OperationCancelled::from_io_error(&err).is_some());
+
+        canceller.join().unwrap();
+
+        // The handshake is never started with the cancelled token.
+        let err = QuicConn::connect_udp_cancellable(
+            listener.local_addr(),
+            &mut mock_config(false, 1350),
+            Some(&token),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(OperationCancelled::from_io_error(&err).is_some());
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_listener_multi_addrs() {
+        let laddrs: [SocketAddr; 2] = [
+            "127.0.0.1:0".parse().unwrap(),
+            "127.0.0.1:0".parse().unwrap(),
+        ];
+
+        let listener = QuicListener::bind(&laddrs[..], mock_config(true, 1350)).unwrap();
+
+        assert_eq!(listener.local_addrs().len(), 2);
+
+        let mut incoming = Box::pin(listener.incoming());
+
+        for laddr in listener.local_addrs() {
+            let conn = QuicConn::connect_udp(laddr, &mut mock_config(false, 1350))
+                .await
+                .unwrap();
+
+            let stream = conn.open_stream().await.unwrap();
+
+            stream.send(b"hello", true).await.unwrap();
+
+            let server_conn = incoming.next().await.unwrap();
+
+            let QuicIncoming::Bidi(server_stream) = server_conn.accept().await.unwrap() else {
+                panic!("expect bidirectional stream");
+            };
+
+            let mut buf = vec![0; 1024];
+
+            let (read_size, _) = server_stream.recv(&mut buf).await.unwrap();
+
+            assert_eq!(&buf[..read_size], b"hello");
+
+            // The response is routed out the socket which received the request.
+            server_stream.send(b"world", true).await.unwrap();
+
+            let (read_size, _) = stream.recv(&mut buf).await.unwrap();
+
+            assert_eq!(&buf[..read_size], b"world");
+        }
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_listener_workers() {
+        let listener =
+            QuicListener::bind_with_workers("127.0.0.1:0", 4, |_| Ok(mock_config(true, 1350)))
+                .unwrap();
+
+        let laddr = listener.local_addr();
+
+        let mut incoming = Box::pin(listener.incoming());
+
+        for _ in 0..8 {
+            let conn = QuicConn::connect_udp(laddr, &mut mock_config(false, 1350))
+                .await
+                .unwrap();
+
+            let stream = conn.open_stream().await.unwrap();
+
+            stream.send(b"hello", true).await.unwrap();
+
+            let server_conn = incoming.next().await.unwrap();
+
+            let QuicIncoming::Bidi(server_stream) = server_conn.accept().await.unwrap() else {
+                panic!("expect bidirectional stream");
+            };
+
+            let mut buf = vec![0; 1024];
+
+            let (read_size, _) = server_stream.recv(&mut buf).await.unwrap();
+
+            assert_eq!(&buf[..read_size], b"hello");
+        }
+
+        // Every established connection is registered to the shard which accepted it.
+        assert_eq!(listener.router().len(), 8);
+    }
+
+    /// The shards that entered [`ShardCertResolver::resolve`], and the shards that met the other one there.
+    type Rendezvous = Arc<(Mutex<([bool; 2], [bool; 2])>, Condvar)>;
+
+    /// Blocks the certificate resolving of one shard until the other shard resolves too.
+    struct ShardCertResolver {
+        shard: usize,
+        rendezvous: Rendezvous,
+    }
+
+    impl CertResolver for ShardCertResolver {
+        fn resolve(&self, _server_name: &str) -> Option<CertifiedKey> {
+            let (state, cond) = &*self.rendezvous;
+
+            let mut state = state.lock().unwrap();
+
+            state.0[self.shard] = true;
+
+            cond.notify_all();
+
+            let (mut state, _) = cond
+                .wait_timeout_while(state, Duration::from_secs(2), |(entered, _)| {
+                    !entered.iter().all(|entered| *entered)
+                })
+                .unwrap();
+
+            state.1[self.shard] = state.0.iter().all(|entered| *entered);
+
+            let root_path = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+            Some(CertifiedKey {
+                cert_chain_pem_file: root_path.join("cert/cert.crt"),
+                priv_key_pem_file: root_path.join("cert/cert.key"),
+            })
+        }
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_listener_workers_parallel() {
+        let rendezvous = Rendezvous::default();
+
+        let listener = QuicListener::bind_with_workers("127.0.0.1:0", 2, |shard| {
+            let mut config = mock_config(true, 1350);
+
+            config.set_cert_resolver(ShardCertResolver {
+                shard,
+                rendezvous: rendezvous.clone(),
+            });
+
+            Ok(config)
+        })
+        .unwrap();
+
+        let laddr = listener.local_addr();
+
+        // The initial packets are routed to the shards by the random connection ids.
+        let connects = (0..16).map(|_| async move {
+            let mut config = mock_config(false, 1350);
+
+            config.set_server_name("quic.tech");
+
+            QuicConn::connect_udp(laddr, &mut config).await
+        });
+
+        for conn in join_all(connects).await {
+            conn.unwrap();
+        }
+
+        // Each shard is blocked in the resolver until the other one arrives,
+        // which requires the shards to run at the same time.
+        assert_eq!(rendezvous.0.lock().unwrap().1, [true, true]);
+    }
+}
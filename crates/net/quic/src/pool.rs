@@ -0,0 +1,263 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io,
+    net::{IpAddr, ToSocketAddrs},
+    sync::Arc,
+};
+
+use hala_sync::{AsyncLockable, AsyncSpinMutex, Lockable, SpinMutex};
+
+use crate::{Config, QuicConn, QuicStream};
+
+/// The key of pooled connections, the connections are only shared by the streams to the same
+/// server with the same application protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuicPoolKey {
+    /// The host name or ip address of the server.
+    pub host: String,
+    pub port: u16,
+    /// The ALPN protocol of the connections, e.g. `h3`.
+    pub alpn: Vec<u8>,
+}
+
+impl QuicPoolKey {
+    /// Creates a key of server `host`:`port` with ALPN protocol `alpn`.
+    pub fn new<H: Into<String>, A: Into<Vec<u8>>>(host: H, port: u16, alpn: A) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            alpn: alpn.into(),
+        }
+    }
+}
+
+/// Creates the config of new connections of the key.
+type ConfigFactory = dyn Fn(&QuicPoolKey) -> io::Result<Config> + Send + Sync;
+
+/// The connections of one key, the lock serializes the connecting of the key.
+type PooledConns = Arc<AsyncSpinMutex<Vec<QuicConn>>>;
+
+/// The pool of client connections, which reuses the connections to the same server by opening
+/// multiple streams on them.
+///
+/// The closed connections, e.g. closed by the idle timeout after the keep-alive pings are not
+/// acknowledged, are removed and replaced by new connections on the next
+/// [`open_stream_for`](Self::open_stream_for). The keep-alive interval of new connections is
+/// [`Config::ping_timeout`], which must be shorter than the max idle timeout.
+pub struct QuicConnPool {
+    max_conns_per_key: usize,
+    config: Box<ConfigFactory>,
+    conns: SpinMutex<HashMap<QuicPoolKey, PooledConns>>,
+}
+
+impl Debug for QuicConnPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "QuicConnPool(max_conns_per_key={})",
+            self.max_conns_per_key
+        )
+    }
+}
+
+impl QuicConnPool {
+    /// Creates a pool which keeps at most `max_conns_per_key` connections per key,
+    /// the config of new connections is created by `config`.
+    ///
+    /// The ALPN protocol of the created config is set to the one of the key, and the server name is
+    /// set to the host of the key if it's not an ip address and no server name is set.
+    pub fn new<F>(max_conns_per_key: usize, config: F) -> Self
+    where
+        F: Fn(&QuicPoolKey) -> io::Result<Config> + Send + Sync + 'static,
+    {
+        assert!(
+            max_conns_per_key > 0,
+            "max_conns_per_key must be greater than zero"
+        );
+
+        Self {
+            max_conns_per_key,
+            config: Box::new(config),
+            conns: Default::default(),
+        }
+    }
+
+    /// Opens a bidirectional stream to the server of `key`.
+    ///
+    /// The stream is opened on the connection with the most stream credits left, a new connection is
+    /// created if no connection of the key has credits left. Returns [`ResourceBusy`](io::ErrorKind::ResourceBusy)
+    /// error if there are already `max_conns_per_key` connections and all of them are out of credits.
+    pub async fn open_stream_for(&self, key: &QuicPoolKey) -> io::Result<QuicStream> {
+        let conns = self
+            .conns
+            .lock()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(AsyncSpinMutex::new(vec![])))
+            .clone();
+
+        let mut conns = conns.lock().await;
+
+        let mut live = Vec::with_capacity(conns.len());
+
+        for conn in conns.drain(..) {
+            if is_dead(&conn).await {
+                log::trace!("QuicConnPool({:?}) remove closed {:?}", key, conn);
+            } else {
+                live.push(conn);
+            }
+        }
+
+        *conns = live;
+
+        let mut best: Option<(usize, u64)> = None;
+
+        for (index, conn) in conns.iter().enumerate() {
+            let streams_left = conn.peer_streams_left_bidi().await;
+
+            if streams_left > 0 && best.is_none_or(|(_, best_left)| streams_left > best_left) {
+                best = Some((index, streams_left));
+            }
+        }
+
+        if let Some((index, _)) = best {
+            match conns[index].open_stream().await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => {
+                    // The connection is closed after checking.
+                    log::trace!(
+                        "QuicConnPool({:?}) open stream on {:?}, err={}",
+                        key,
+                        conns[index],
+                        err
+                    );
+
+                    conns.remove(index);
+                }
+            }
+        }
+
+        if conns.len() >= self.max_conns_per_key {
+            return Err(io::Error::new(
+                io::ErrorKind::ResourceBusy,
+                format!(
+                    "QuicConnPool({:?}) connections are out of stream credits",
+                    key
+                ),
+            ));
+        }
+
+        let conn = self.connect(key).await?;
+
+        let stream = conn.open_stream().await?;
+
+        conns.push(conn);
+
+        Ok(stream)
+    }
+
+    /// Returns the number of pooled connections of `key`, including the closed ones
+    /// not yet removed.
+    pub async fn conns_of(&self, key: &QuicPoolKey) -> usize {
+        let conns = self.conns.lock().get(key).cloned();
+
+        match conns {
+            Some(conns) => conns.lock().await.len(),
+            None => 0,
+        }
+    }
+
+    /// Drops all the connections of `key`, which are closed once the opened streams are dropped.
+    pub fn remove(&self, key: &QuicPoolKey) {
+        self.conns.lock().remove(key);
+    }
+
+    async fn connect(&self, key: &QuicPoolKey) -> io::Result<QuicConn> {
+        let mut config = (self.config)(key)?;
+
+        config
+            .set_application_protos(&[&key.alpn])
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        if config.server_name.is_none() && key.host.parse::<IpAddr>().is_err() {
+            config.set_server_name(&key.host);
+        }
+
+        let raddrs = (key.host.as_str(), key.port)
+            .to_socket_addrs()?
+            .collect::<Vec<_>>();
+
+        let conn = QuicConn::connect_udp(raddrs.as_slice(), &mut config).await?;
+
+        log::trace!("QuicConnPool({:?}) new {:?}", key, conn);
+
+        Ok(conn)
+    }
+}
+
+/// Returns true if the connection is closed, or is closing after the `CONNECTION_CLOSE`
+/// frame was sent or received.
+async fn is_dead(conn: &QuicConn) -> bool {
+    conn.is_closed().await || conn.close_reason().await.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use hala_io::test::io_test;
+
+    use crate::{mock_config, QuicIncoming, QuicListener};
+
+    use super::*;
+
+    #[hala_test::test(io_test)]
+    async fn test_pool() {
+        let listener = QuicListener::bind("127.0.0.1:0", mock_config(true, 1350)).unwrap();
+
+        let pool = QuicConnPool::new(1, |_| Ok(mock_config(false, 1350)));
+
+        let key = QuicPoolKey::new("127.0.0.1", listener.local_addr().port(), "hq-interop");
+
+        let stream = pool.open_stream_for(&key).await.unwrap();
+
+        stream.send(b"hello", false).await.unwrap();
+
+        let mut incoming = Box::pin(listener.incoming());
+
+        let server_conn = incoming.next().await.unwrap();
+
+        assert_eq!(server_conn.alpn_protocol().await.unwrap(), b"hq-interop");
+
+        // The connection is reused.
+        let reused = pool.open_stream_for(&key).await.unwrap();
+
+        assert_eq!(pool.conns_of(&key).await, 1);
+
+        reused.send(b"world", false).await.unwrap();
+
+        for _ in 0..2 {
+            let QuicIncoming::Bidi(_) = server_conn.accept().await.unwrap() else {
+                panic!("expect bidirectional stream");
+            };
+        }
+
+        server_conn.close(0, b"").await.unwrap();
+
+        let mut buf = [0; 16];
+
+        while stream.recv(&mut buf).await.is_ok() {}
+
+        // The closed connection is replaced.
+        let stream = pool.open_stream_for(&key).await.unwrap();
+
+        stream.send(b"hello", false).await.unwrap();
+
+        assert_eq!(pool.conns_of(&key).await, 1);
+
+        let server_conn = incoming.next().await.unwrap();
+
+        let QuicIncoming::Bidi(_) = server_conn.accept().await.unwrap() else {
+            panic!("expect bidirectional stream");
+        };
+    }
+}
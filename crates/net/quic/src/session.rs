@@ -0,0 +1,204 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use hala_sync::{Lockable, SpinMutex};
+
+/// The key of the stored TLS session, the session is only resumed by the connection
+/// to the same server name and address.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub server_name: Option<String>,
+    pub raddr: SocketAddr,
+}
+
+/// The storage of TLS sessions, which is used by client connections to resume
+/// the previous sessions and send 0-RTT data.
+pub trait SessionStore: Send + Sync {
+    /// Returns the serialized session of `key`.
+    fn get(&self, key: &SessionKey) -> Option<Vec<u8>>;
+
+    /// Saves the serialized `session` of `key`, replaces the old one if exists.
+    fn put(&self, key: SessionKey, session: Vec<u8>);
+}
+
+impl<S: SessionStore + ?Sized> SessionStore for Arc<S> {
+    fn get(&self, key: &SessionKey) -> Option<Vec<u8>> {
+        self.as_ref().get(key)
+    }
+
+    fn put(&self, key: SessionKey, session: Vec<u8>) {
+        self.as_ref().put(key, session)
+    }
+}
+
+#[derive(Default)]
+struct RawMemorySessionStore {
+    /// The sessions and their last access sequence.
+    sessions: HashMap<SessionKey, (Vec<u8>, u64)>,
+    /// The keys ordered by last access sequence, the least recently used comes first.
+    lru: BTreeMap<u64, SessionKey>,
+    seq: u64,
+}
+
+impl RawMemorySessionStore {
+    fn touch(&mut self, key: &SessionKey) -> Option<&mut Vec<u8>> {
+        let (session, seq) = self.sessions.get_mut(key)?;
+
+        self.lru.remove(seq);
+
+        self.seq += 1;
+        *seq = self.seq;
+
+        self.lru.insert(self.seq, key.clone());
+
+        Some(session)
+    }
+}
+
+/// The in-memory [`SessionStore`], which evicts the least recently used session once
+/// the capacity is reached.
+pub struct MemorySessionStore {
+    capacity: usize,
+    raw: SpinMutex<RawMemorySessionStore>,
+}
+
+impl Default for MemorySessionStore {
+    /// Creates a store with capacity 256.
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl MemorySessionStore {
+    /// Creates a store which keeps at most `capacity` sessions.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            raw: Default::default(),
+        }
+    }
+
+    /// Returns the number of stored sessions.
+    pub fn len(&self) -> usize {
+        self.raw.lock().sessions.len()
+    }
+
+    /// Returns true if no session is stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn get(&self, key: &SessionKey) -> Option<Vec<u8>> {
+        self.raw.lock().touch(key).cloned()
+    }
+
+    fn put(&self, key: SessionKey, session: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut raw = self.raw.lock();
+
+        if let Some(stored) = raw.touch(&key) {
+            *stored = session;
+            return;
+        }
+
+        if raw.sessions.len() == self.capacity {
+            if let Some((_, evicted)) = raw.lru.pop_first() {
+                raw.sessions.remove(&evicted);
+            }
+        }
+
+        raw.seq += 1;
+
+        let seq = raw.seq;
+
+        raw.lru.insert(seq, key.clone());
+        raw.sessions.insert(key, (session, seq));
+    }
+}
+
+/// Saves the session of one client connection once the session ticket is received.
+pub(crate) struct SessionSaver {
+    store: Arc<dyn SessionStore>,
+    key: SessionKey,
+}
+
+impl SessionSaver {
+    pub(crate) fn new(store: Arc<dyn SessionStore>, key: SessionKey) -> Self {
+        Self { store, key }
+    }
+
+    /// Loads the stored session into the new created `conn`.
+    pub(crate) fn load(&self, conn: &mut quiche::Connection) {
+        if let Some(session) = self.store.get(&self.key) {
+            if let Err(err) = conn.set_session(&session) {
+                log::warn!("{:?} load session failed, err={}", self.key, err);
+            }
+        }
+    }
+
+    /// Saves the session of `conn`, returns false if the session ticket is not received yet.
+    ///
+    /// TLS 1.3 servers send the session ticket after the handshake is completed.
+    pub(crate) fn save(&self, conn: &quiche::Connection) -> bool {
+        if !conn.is_established() {
+            return false;
+        }
+
+        match conn.session() {
+            Some(session) => {
+                self.store.put(self.key.clone(), session.to_vec());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Saves the session of `conn` by `saver`, and drops the saver once the session is saved.
+pub(crate) fn save_session(saver: &mut Option<SessionSaver>, conn: &quiche::Connection) {
+    if saver.as_ref().is_some_and(|saver| saver.save(conn)) {
+        *saver = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(port: u16) -> SessionKey {
+        SessionKey {
+            server_name: Some("hala.test".into()),
+            raddr: SocketAddr::from(([127, 0, 0, 1], port)),
+        }
+    }
+
+    #[test]
+    fn test_memory_session_store() {
+        let store = MemorySessionStore::new(2);
+
+        store.put(key(1), vec![1]);
+        store.put(key(2), vec![2]);
+
+        // key(1) becomes the most recently used.
+        assert_eq!(store.get(&key(1)), Some(vec![1]));
+
+        store.put(key(3), vec![3]);
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(&key(2)), None);
+        assert_eq!(store.get(&key(3)), Some(vec![3]));
+
+        store.put(key(1), vec![4]);
+
+        assert_eq!(store.get(&key(1)), Some(vec![4]));
+        assert_eq!(store.len(), 2);
+    }
+}
@@ -0,0 +1,464 @@
+//! Extracts the server name indication from the client's Initial packet.
+//!
+//! The Initial packet is protected by the keys derived from the destination connection id,
+//! see [RFC 9001 section 5.2](https://www.rfc-editor.org/rfc/rfc9001#section-5.2), so the listener
+//! can read the ClientHello before creating the server connection.
+
+use ring::{
+    aead::{self, quic::HeaderProtectionKey, Aad, LessSafeKey, Nonce, UnboundKey},
+    hkdf::{self, Prk, Salt, HKDF_SHA256},
+};
+
+/// The initial salt of QUIC version 1.
+const INITIAL_SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad,
+    0xcc, 0xbb, 0x7f, 0x0a,
+];
+
+/// The length of header protection sample.
+const SAMPLE_LEN: usize = 16;
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.buf.get(self.pos..self.pos.checked_add(len)?)?;
+
+        self.pos += len;
+
+        Some(bytes)
+    }
+
+    fn uint(&mut self, len: usize) -> Option<u64> {
+        Some(
+            self.bytes(len)?
+                .iter()
+                .fold(0, |value, byte| (value << 8) | *byte as u64),
+        )
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.uint(1).map(|value| value as u8)
+    }
+
+    fn u16(&mut self) -> Option<usize> {
+        self.uint(2).map(|value| value as usize)
+    }
+
+    /// Reads QUIC variable-length integer.
+    fn varint(&mut self) -> Option<u64> {
+        let first = *self.buf.get(self.pos)?;
+
+        let len = 1 << (first >> 6);
+
+        self.uint(len)
+            .map(|value| value & (u64::MAX >> (64 - 8 * len + 2)))
+    }
+
+    /// Reads a vector prefixed by `len_size` bytes length.
+    fn vec(&mut self, len_size: usize) -> Option<&'a [u8]> {
+        let len = self.uint(len_size)? as usize;
+
+        self.bytes(len)
+    }
+}
+
+struct Len(usize);
+
+impl hkdf::KeyType for Len {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// HKDF-Expand-Label function defined in TLS 1.3, with empty context.
+fn hkdf_expand_label(prk: &Prk, label: &[u8], len: usize) -> Option<Vec<u8>> {
+    let out_len = (len as u16).to_be_bytes();
+    let label_len = [(b"tls13 ".len() + label.len()) as u8];
+
+    let info = [&out_len[..], &label_len, b"tls13 ", label, &[0]];
+
+    let mut out = vec![0; len];
+
+    prk.expand(&info, Len(len)).ok()?.fill(&mut out).ok()?;
+
+    Some(out)
+}
+
+/// The client Initial packet protection keys.
+struct InitialKeys {
+    key: Vec<u8>,
+    iv: Vec<u8>,
+    hp: Vec<u8>,
+}
+
+impl InitialKeys {
+    fn new(dcid: &[u8]) -> Option<Self> {
+        let initial_secret = Salt::new(HKDF_SHA256, &INITIAL_SALT_V1).extract(dcid);
+
+        let client_secret = hkdf_expand_label(&initial_secret, b"client in", 32)?;
+
+        let client_secret = Prk::new_less_safe(HKDF_SHA256, &client_secret);
+
+        Some(Self {
+            key: hkdf_expand_label(&client_secret, b"quic key", 16)?,
+            iv: hkdf_expand_label(&client_secret, b"quic iv", 12)?,
+            hp: hkdf_expand_label(&client_secret, b"quic hp", 16)?,
+        })
+    }
+}
+
+/// Returns the server name of the ClientHello carried by the client Initial `packet`.
+///
+/// Returns `None` if the packet is not a QUIC v1 Initial packet, or the server name
+/// is not in the first Initial packet.
+pub(crate) fn initial_server_name(packet: &[u8]) -> Option<String> {
+    let mut reader = Reader::new(packet);
+
+    let first = reader.u8()?;
+
+    // Long header with packet type Initial.
+    if first & 0x80 == 0 || (first & 0x30) != 0 {
+        return None;
+    }
+
+    if reader.uint(4)? != 1 {
+        return None;
+    }
+
+    let dcid = reader.vec(1)?;
+
+    let _scid = reader.vec(1)?;
+
+    let token_len = reader.varint()? as usize;
+
+    reader.bytes(token_len)?;
+
+    let len = reader.varint()? as usize;
+
+    let pn_offset = reader.pos;
+
+    let packet = packet.get(..pn_offset.checked_add(len)?)?;
+
+    let keys = InitialKeys::new(dcid)?;
+
+    // Removes header protection.
+    let sample = packet.get(pn_offset + 4..pn_offset + 4 + SAMPLE_LEN)?;
+
+    let mask = HeaderProtectionKey::new(&aead::quic::AES_128, &keys.hp)
+        .ok()?
+        .new_mask(sample)
+        .ok()?;
+
+    let mut header = packet[..pn_offset].to_vec();
+
+    header[0] ^= mask[0] & 0x0f;
+
+    let pn_len = (header[0] & 0x03) as usize + 1;
+
+    let mut pn = 0u64;
+
+    for (i, byte) in packet
+        .get(pn_offset..pn_offset + pn_len)?
+        .iter()
+        .enumerate()
+    {
+        let byte = byte ^ mask[1 + i];
+
+        header.push(byte);
+
+        pn = (pn << 8) | byte as u64;
+    }
+
+    // Decrypts payload.
+    let mut nonce = [0u8; aead::NONCE_LEN];
+
+    nonce.copy_from_slice(&keys.iv);
+
+    for (i, byte) in pn.to_be_bytes().iter().enumerate() {
+        nonce[4 + i] ^= byte;
+    }
+
+    let key = LessSafeKey::new(UnboundKey::new(&aead::AES_128_GCM, &keys.key).ok()?);
+
+    let mut payload = packet[pn_offset + pn_len..].to_vec();
+
+    let payload = key
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(&header),
+            &mut payload,
+        )
+        .ok()?;
+
+    client_hello_server_name(&crypto_data(payload)?)
+}
+
+/// Reassembles the contiguous crypto stream data from offset 0 of the Initial packet `payload`.
+fn crypto_data(payload: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = Reader::new(payload);
+
+    let mut frames = vec![];
+
+    while !reader.is_empty() {
+        match reader.varint()? {
+            // PADDING / PING
+            0x00 | 0x01 => {}
+            // ACK
+            ty @ (0x02 | 0x03) => {
+                // largest acknowledged, ack delay.
+                reader.varint()?;
+                reader.varint()?;
+
+                let ranges = reader.varint()?;
+
+                // first ack range, (gap, ack range length) pairs.
+                for _ in 0..ranges * 2 + 1 {
+                    reader.varint()?;
+                }
+
+                // ECN counts
+                if ty == 0x03 {
+                    for _ in 0..3 {
+                        reader.varint()?;
+                    }
+                }
+            }
+            // CRYPTO
+            0x06 => {
+                let offset = reader.varint()? as usize;
+                let len = reader.varint()? as usize;
+
+                frames.push((offset, reader.bytes(len)?));
+            }
+            // The other frames are not allowed before the ClientHello.
+            _ => break,
+        }
+    }
+
+    frames.sort_by_key(|(offset, _)| *offset);
+
+    let mut data = vec![];
+
+    for (offset, frame) in frames {
+        if offset > data.len() {
+            break;
+        }
+
+        if let Some(frame) = frame.get(data.len() - offset..) {
+            data.extend_from_slice(frame);
+        }
+    }
+
+    Some(data)
+}
+
+/// Parses the server name extension of TLS ClientHello message.
+fn client_hello_server_name(data: &[u8]) -> Option<String> {
+    let mut reader = Reader::new(data);
+
+    // handshake type client_hello.
+    if reader.u8()? != 1 {
+        return None;
+    }
+
+    let _len = reader.uint(3)?;
+
+    // legacy_version, random
+    reader.bytes(2 + 32)?;
+
+    // legacy_session_id, cipher_suites, legacy_compression_methods
+    reader.vec(1)?;
+    reader.vec(2)?;
+    reader.vec(1)?;
+
+    let mut extensions = Reader::new(reader.vec(2)?);
+
+    while !extensions.is_empty() {
+        let ty = extensions.u16()?;
+        let data = extensions.vec(2)?;
+
+        // server_name extension.
+        if ty != 0 {
+            continue;
+        }
+
+        let mut names = Reader::new(Reader::new(data).vec(2)?);
+
+        while !names.is_empty() {
+            let name_type = names.u8()?;
+            let name = names.vec(2)?;
+
+            // host_name
+            if name_type == 0 {
+                return String::from_utf8(name.to_vec()).ok();
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::aead::{self, quic::HeaderProtectionKey};
+
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_initial_keys() {
+        // RFC 9001 Appendix A.1
+        let keys = InitialKeys::new(&hex("8394c8f03e515708")).unwrap();
+
+        assert_eq!(keys.key, hex("1f369613dd76d5467730efcbe3b1a22d"));
+        assert_eq!(keys.iv, hex("fa044b2f42a3fd3b46fb255c"));
+        assert_eq!(keys.hp, hex("9f50449e04a0e810283a1e9933adedd2"));
+
+        // RFC 9001 Appendix A.2
+        let mask = HeaderProtectionKey::new(&aead::quic::AES_128, &keys.hp)
+            .unwrap()
+            .new_mask(&hex("d1b1c98dd7689fb8ec11d242b123dc9b"))
+            .unwrap();
+
+        assert_eq!(mask.to_vec(), hex("437b9aec36"));
+    }
+
+    fn client_hello(server_name: &str) -> Vec<u8> {
+        let mut sni = vec![];
+
+        sni.extend_from_slice(&((server_name.len() + 3) as u16).to_be_bytes());
+        sni.push(0);
+        sni.extend_from_slice(&(server_name.len() as u16).to_be_bytes());
+        sni.extend_from_slice(server_name.as_bytes());
+
+        let mut extensions = vec![];
+
+        // supported_versions
+        extensions.extend_from_slice(&[0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04]);
+
+        extensions.extend_from_slice(&[0x00, 0x00]);
+        extensions.extend_from_slice(&(sni.len() as u16).to_be_bytes());
+        extensions.extend_from_slice(&sni);
+
+        let mut body = vec![0x03, 0x03];
+
+        body.extend_from_slice(&[0; 32]);
+        body.push(0);
+        body.extend_from_slice(&[0x00, 0x02, 0x13, 0x01]);
+        body.extend_from_slice(&[0x01, 0x00]);
+        body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        body.extend_from_slice(&extensions);
+
+        let mut hello = vec![0x01];
+
+        hello.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        hello.extend_from_slice(&body);
+
+        hello
+    }
+
+    /// Protects the client Initial packet by the steps of RFC 9001.
+    fn initial_packet(dcid: &[u8], hello: &[u8]) -> Vec<u8> {
+        let keys = InitialKeys::new(dcid).unwrap();
+
+        // Splits the ClientHello into two out-of-order crypto frames.
+        let (first, second) = hello.split_at(hello.len() / 2);
+
+        let mut frames = vec![0x06];
+
+        frames.extend_from_slice(&[0x40 | (first.len() >> 8) as u8, first.len() as u8]);
+        frames.extend_from_slice(&[0x40, second.len() as u8]);
+        frames.extend_from_slice(second);
+
+        frames.extend_from_slice(&[0x06, 0x00, 0x40, first.len() as u8]);
+        frames.extend_from_slice(first);
+
+        frames.resize(1100, 0);
+
+        let pn: u32 = 2;
+
+        let mut header = vec![0xc3, 0x00, 0x00, 0x00, 0x01, dcid.len() as u8];
+
+        header.extend_from_slice(dcid);
+        header.extend_from_slice(&[0x00, 0x00]);
+
+        let len = 4 + frames.len() + aead::AES_128_GCM.tag_len();
+
+        header.extend_from_slice(&(0x4000 | len as u16).to_be_bytes());
+
+        let pn_offset = header.len();
+
+        header.extend_from_slice(&pn.to_be_bytes());
+
+        let mut nonce = [0u8; aead::NONCE_LEN];
+
+        nonce.copy_from_slice(&keys.iv);
+
+        for (i, byte) in (pn as u64).to_be_bytes().iter().enumerate() {
+            nonce[4 + i] ^= byte;
+        }
+
+        let key = LessSafeKey::new(UnboundKey::new(&aead::AES_128_GCM, &keys.key).unwrap());
+
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(&header),
+            &mut frames,
+        )
+        .unwrap();
+
+        let mut packet = header;
+
+        packet.extend_from_slice(&frames);
+
+        let mask = HeaderProtectionKey::new(&aead::quic::AES_128, &keys.hp)
+            .unwrap()
+            .new_mask(&packet[pn_offset + 4..pn_offset + 4 + SAMPLE_LEN])
+            .unwrap();
+
+        packet[0] ^= mask[0] & 0x0f;
+
+        for i in 0..4 {
+            packet[pn_offset + i] ^= mask[1 + i];
+        }
+
+        packet
+    }
+
+    #[test]
+    fn test_initial_server_name() {
+        let dcid = hex("8394c8f03e515708");
+
+        let packet = initial_packet(&dcid, &client_hello("quic.tech"));
+
+        assert_eq!(initial_server_name(&packet).as_deref(), Some("quic.tech"));
+
+        // Corrupted packet can't be decrypted.
+        let mut corrupted = packet.clone();
+
+        *corrupted.last_mut().unwrap() ^= 0xff;
+
+        assert_eq!(initial_server_name(&corrupted), None);
+
+        // Short header packet.
+        assert_eq!(initial_server_name(&[0x40, 0x00]), None);
+    }
+}
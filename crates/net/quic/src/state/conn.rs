@@ -1,51 +1,171 @@
 //! The quic connection state matchine implementation.
 
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     fmt::Debug,
+    future::{poll_fn, Future},
     io,
     ops::DerefMut,
-    sync::Arc,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
     time::{Duration, Instant},
 };
 
 use hala_future::event_map::{self, EventMap};
-use hala_io::{current::executor::io_spawn, timeout};
+use hala_io::{
+    bytes::{Buf, BytesMut},
+    coop::poll_proceed,
+    current::{executor::io_spawn, get_driver, get_poller},
+    sleep, Sleep,
+};
 use hala_sync::*;
 use quiche::{ConnectionId, RecvInfo, SendInfo};
 
-use crate::errors::into_io_error;
+use crate::{
+    errors::{into_io_error, CloseReason},
+    is_uni_stream,
+    session::{save_session, SessionSaver},
+};
+
+use super::{
+    events::{QuicEvent, QuicEventKind, QuicEventStream, Subscribers},
+    pmtud::{PathInfo, Pmtud},
+    wfq::{DEFAULT_WEIGHT, MAX_WEIGHT},
+};
 
 /// The io event variants for quic connection state mache.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// Events are keyed by the connection serial number instead of the connection id,
+/// so that raising an event neither allocates nor hashes the id bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum QuicConnStateEvent {
     /// This event notify listener that this state machine is now readable.
-    Readable(ConnectionId<'static>),
+    Readable(u64),
 
     /// This event notify listener that one stream of this state machine is now readable.
-    StreamReadable(ConnectionId<'static>, u64),
+    StreamReadable(u64, u64),
 
     /// This event notify listener that one stream of this state machine is now writable.
-    StreamWritable(ConnectionId<'static>, u64),
+    StreamWritable(u64, u64),
 
     /// This event notify listener that one incoming stream is valid.
-    Accept(ConnectionId<'static>),
+    Accept(u64),
+
+    /// This event notify listener that this state machine is closed.
+    Closed(u64),
+}
+
+/// Generates the serial number of new [`QuicConnState`].
+fn next_serial() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The negotiated handshake parameters of one quic connection.
+///
+/// QUIC always runs TLS 1.3 (RFC 9001), so the TLS version is not recorded here.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuicHandshakeInfo {
+    /// The negotiated ALPN protocol, `None` if no protocol was negotiated.
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// The server name requested by the client (SNI).
+    pub server_name: Option<String>,
+    /// The DER-encoded peer certificate chain, the leaf certificate comes first.
+    pub peer_cert_chain: Vec<Vec<u8>>,
+    /// Returns true if the connection is resumed from a previous TLS session.
+    pub is_resumed: bool,
+    /// Returns true if the connection has a pending handshake that has progressed enough to send or receive early data.
+    pub is_in_early_data: bool,
+}
+
+/// The flow control state of one stream, see [`QuicConnState::stream_flow_control`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuicStreamFlowControl {
+    /// The bytes of the stream read by the application, which are released from the receive window.
+    pub consumed: u64,
+    /// The bytes can be sent on the stream before blocked by the flow control or congestion control,
+    /// `None` if the stream is not writable, e.g. the stream is finished or stopped by the peer.
+    pub send_capacity: Option<usize>,
+    /// The receive window granted by [`stream_grant`](QuicConnState::stream_grant) and not yet read,
+    /// `None` if the manual flow control is disabled.
+    pub granted: Option<u64>,
+}
+
+/// The default stream urgency used by quiche.
+const DEFAULT_STREAM_URGENCY: u8 = 127;
+
+/// Returns the current time of the driver clock, which is the reference time of the keep-alive / idle timeouts,
+/// falls back to the system time if no driver is bound to current thread.
+fn now() -> Instant {
+    hala_io::now().unwrap_or_else(|_| Instant::now())
+}
+
+/// Returns the send capacity which the write of `len` bytes to stream `id` waits for.
+///
+/// The low-watermark is at most one datagram, and never exceeds the initial flow control window
+/// granted by the peer, so a stream whose window stays below one datagram still makes progress.
+/// Returns zero before the peer's transport parameters are received, the write is never suspended then.
+fn send_lowat(quiche_conn: &quiche::Connection, id: u64, len: usize) -> usize {
+    let Some(params) = quiche_conn.peer_transport_params() else {
+        return 0;
+    };
+
+    // The stream initiated by this endpoint is the remote stream of the peer.
+    let window = if is_uni_stream(id) {
+        params.initial_max_stream_data_uni
+    } else if (id & 0x1 == 1) == quiche_conn.is_server() {
+        params.initial_max_stream_data_bidi_remote
+    } else {
+        params.initial_max_stream_data_bidi_local
+    };
+
+    let window = window.min(params.initial_max_data) as usize;
+
+    len.min(quiche_conn.max_send_udp_payload_size()).min(window)
 }
 
 struct RawQuicConnState {
     /// quiche connection state machine.
     quiche_conn: quiche::Connection,
-    /// The timestamp of last ping packet sent.
-    send_ack_eliciting_instant: Instant,
-    /// The interval between two ping packets.
-    ping_timeout: Duration,
+    /// The timestamp of last packet sent.
+    send_instant: Instant,
+    /// The timestamp of last packet received from the peer.
+    recv_instant: Instant,
+    /// The keep-alive interval, the ping packet is sent if no packet is sent within it.
+    ping_timeout: Option<Duration>,
+    /// The quiche / keep-alive timer registered by [`poll_send`](QuicConnState::poll_send).
+    send_timer: Option<Sleep>,
     /// When a connection sees a stream ID for the first time,
     /// it is placed into this stream ID set.
     register_incoming_stream_ids: HashSet<u64>,
     /// The latest outgoing stream id on record.
     lastest_outgoing_stream_id: u64,
+    /// The latest outgoing unidirectional stream id on record.
+    lastest_outgoing_uni_stream_id: u64,
     /// Incoming stream id buffer.
     incoming: VecDeque<u64>,
+    /// The (urgency, incremental) priorities set by the application, keyed by stream id.
+    stream_priorities: HashMap<u64, (u8, bool)>,
+    /// The streams whose reading side is shut down by [`stream_shutdown`](QuicConnState::stream_shutdown).
+    stopped_streams: HashSet<u64>,
+    /// The streams whose writing side is shut down by [`stream_shutdown`](QuicConnState::stream_shutdown).
+    reset_streams: HashSet<u64>,
+    /// Saves the TLS session of client connection once the session ticket is received.
+    session_saver: Option<SessionSaver>,
+    /// The path MTU discovery state, `None` if disabled.
+    pmtud: Option<Pmtud>,
+    /// The total bytes read by the application from all streams.
+    data_consumed: u64,
+    /// The bytes read by the application, keyed by stream id, removed once the stream is finished.
+    stream_consumed: HashMap<u64, u64>,
+    /// The receive window granted by the application, keyed by stream id,
+    /// `None` if the manual flow control is disabled.
+    stream_grants: Option<HashMap<u64, u64>>,
 }
 
 impl RawQuicConnState {
@@ -53,14 +173,28 @@ impl RawQuicConnState {
         quiche_conn: quiche::Connection,
         ping_timeout: Duration,
         first_outgoing_stream_id: u64,
+        session_saver: Option<SessionSaver>,
+        pmtud: bool,
     ) -> Self {
         let mut this = Self {
             quiche_conn,
-            ping_timeout,
-            send_ack_eliciting_instant: Instant::now(),
+            ping_timeout: Some(ping_timeout),
+            send_timer: None,
+            send_instant: now(),
+            recv_instant: now(),
             register_incoming_stream_ids: Default::default(),
             lastest_outgoing_stream_id: first_outgoing_stream_id,
+            // The second least significant bit of unidirectional stream id is set to 1.
+            lastest_outgoing_uni_stream_id: first_outgoing_stream_id | 0x2,
             incoming: Default::default(),
+            stream_priorities: Default::default(),
+            stopped_streams: Default::default(),
+            reset_streams: Default::default(),
+            session_saver,
+            pmtud: pmtud.then(Pmtud::default),
+            data_consumed: 0,
+            stream_consumed: Default::default(),
+            stream_grants: None,
         };
 
         // process initial incoming stream.
@@ -74,6 +208,111 @@ impl RawQuicConnState {
 
         this
     }
+
+    /// Returns the duration until the next ping packet, `None` if keep-alive is disabled
+    /// or the handshake is not completed.
+    fn keep_alive_timeout(&self) -> Option<Duration> {
+        if !self.quiche_conn.is_established() {
+            return None;
+        }
+
+        self.ping_timeout.map(|ping_timeout| {
+            ping_timeout.saturating_sub(now().saturating_duration_since(self.send_instant))
+        })
+    }
+
+    /// Returns the max size of the next packet, limited by the path MTU discovery.
+    fn max_send_size(&mut self) -> usize {
+        match self.pmtud.as_mut() {
+            Some(pmtud) => pmtud.send_size(PathInfo::from_conn(&self.quiche_conn), now()),
+            None => self.quiche_conn.max_send_udp_payload_size(),
+        }
+    }
+
+    /// Records the sent packet for the path MTU discovery.
+    fn on_packet_sent(&mut self, size: usize) {
+        if let Some(pmtud) = self.pmtud.as_mut() {
+            pmtud.on_sent(PathInfo::from_conn(&self.quiche_conn), size, now());
+        }
+    }
+
+    /// Returns the max udp payload size of the current path.
+    fn current_mtu(&mut self) -> usize {
+        match self.pmtud.as_mut() {
+            Some(pmtud) => {
+                // Resolves the probe in flight.
+                if let Some(path) = PathInfo::from_conn(&self.quiche_conn) {
+                    pmtud.update(path, now());
+                }
+
+                pmtud.mtu()
+            }
+            None => self.quiche_conn.max_send_udp_payload_size(),
+        }
+    }
+
+    /// Returns the max bytes can be read from stream `id`, limited by the granted window of the manual flow control.
+    fn recv_limit(&self, id: u64, len: usize) -> usize {
+        match &self.stream_grants {
+            Some(grants) => grants
+                .get(&id)
+                .map_or(0, |granted| (*granted).min(len as u64) as usize),
+            None => len,
+        }
+    }
+
+    /// Records the `read_size` bytes read by the application from stream `id`.
+    fn on_stream_consumed(&mut self, id: u64, read_size: usize, fin: bool) {
+        self.data_consumed += read_size as u64;
+
+        if let Some(grants) = self.stream_grants.as_mut() {
+            if let Some(granted) = grants.get_mut(&id) {
+                *granted -= read_size as u64;
+            }
+        }
+
+        if fin {
+            self.remove_flow_control(id);
+        } else {
+            *self.stream_consumed.entry(id).or_default() += read_size as u64;
+        }
+    }
+
+    /// Removes the flow control state of stream `id`, whose reading side is finished or stopped.
+    fn remove_flow_control(&mut self, id: u64) {
+        self.stream_consumed.remove(&id);
+
+        if let Some(grants) = self.stream_grants.as_mut() {
+            grants.remove(&id);
+        }
+    }
+
+    /// Removes the priorities of the streams whose sending side is gone, e.g. stopped by the peer,
+    /// or collected after the stream is completed.
+    fn collect_stream_priorities(&mut self) {
+        let quiche_conn = &self.quiche_conn;
+
+        self.stream_priorities
+            .retain(|id, _| quiche_conn.stream_capacity(*id).is_ok());
+    }
+
+    /// Returns the writable stream ids, ordered by priority. Lower urgency comes first.
+    fn writable_by_priority(&self) -> Vec<u64> {
+        let mut ids = self.quiche_conn.writable().collect::<Vec<_>>();
+
+        ids.sort_by_key(|id| {
+            let (urgency, incremental) = self
+                .stream_priorities
+                .get(id)
+                .cloned()
+                .unwrap_or((DEFAULT_STREAM_URGENCY, true));
+
+            // Non-incremental streams are sent before the incremental streams with the same urgency.
+            (urgency, incremental, *id)
+        });
+
+        ids
+    }
 }
 
 /// The state matchine for quic connection.
@@ -83,10 +322,22 @@ pub struct QuicConnState {
     state: Arc<AsyncSpinMutex<RawQuicConnState>>,
     /// The [`EventMap`] instance.
     mediator: Arc<EventMap<QuicConnStateEvent>>,
+    /// The serial number used as the key of [`QuicConnStateEvent`].
+    serial: u64,
     /// The source id of this connection.
     pub scid: ConnectionId<'static>,
     /// The destination id of this connection.
     pub dcid: ConnectionId<'static>,
+    /// The high-watermark of the per-stream write buffer.
+    stream_buffer: usize,
+    /// The unsent buffered data of the dropped streams, the fin flag is sent after the data is flushed.
+    ///
+    /// Protected by a sync lock, so the stream can be closed in `drop` without blocking.
+    closing_streams: Arc<SpinMutex<HashMap<u64, BytesMut>>>,
+    /// The subscriptions of connection events, see [`subscribe`](Self::subscribe).
+    subscribers: Arc<Subscribers>,
+    /// The share of the listener send path, see [`set_weight`](Self::set_weight).
+    weight: Arc<AtomicU32>,
 }
 
 impl Debug for QuicConnState {
@@ -104,20 +355,66 @@ impl QuicConnState {
     pub fn new(
         quiche_conn: quiche::Connection,
         ping_timeout: Duration,
+        stream_buffer: usize,
         first_outgoing_stream_id: u64,
+    ) -> Self {
+        Self::with_session_saver(
+            quiche_conn,
+            ping_timeout,
+            stream_buffer,
+            first_outgoing_stream_id,
+            None,
+            true,
+        )
+    }
+
+    pub(crate) fn with_session_saver(
+        quiche_conn: quiche::Connection,
+        ping_timeout: Duration,
+        stream_buffer: usize,
+        first_outgoing_stream_id: u64,
+        session_saver: Option<SessionSaver>,
+        pmtud: bool,
     ) -> Self {
         Self {
+            stream_buffer,
             scid: quiche_conn.source_id().into_owned(),
             dcid: quiche_conn.destination_id().into_owned(),
             state: Arc::new(AsyncSpinMutex::new(RawQuicConnState::new(
                 quiche_conn,
                 ping_timeout,
                 first_outgoing_stream_id,
+                session_saver,
+                pmtud,
             ))),
             mediator: Arc::new(EventMap::default()),
+            serial: next_serial(),
+            closing_streams: Default::default(),
+            subscribers: Arc::new(Subscribers::new()),
+            weight: Arc::new(AtomicU32::new(DEFAULT_WEIGHT)),
         }
     }
 
+    /// Returns the high-watermark of the per-stream write buffer.
+    pub fn stream_buffer(&self) -> usize {
+        self.stream_buffer
+    }
+
+    /// Sets the weight of this connection in the send path of the listener, which is clamped to
+    /// `1..=`[`MAX_WEIGHT`].
+    ///
+    /// The connections sharing one listener send their datagrams in proportion to their weights
+    /// when the send path is busy. It has no effect on the client connections.
+    pub fn set_weight(&self, weight: u32) {
+        self.weight
+            .store(weight.clamp(1, MAX_WEIGHT), Ordering::Relaxed);
+    }
+
+    /// Returns the weight of this connection in the send path of the listener, defaults to [`DEFAULT_WEIGHT`].
+    pub fn weight(&self) -> u32 {
+        self.weight.load(Ordering::Relaxed)
+    }
+
     fn handle_quic_conn_status<'a, Guard>(&self, state: &mut Guard) -> io::Result<()>
     where
         Guard: DerefMut<Target = RawQuicConnState>,
@@ -125,6 +422,12 @@ impl QuicConnState {
         if state.quiche_conn.is_closed() {
             self.mediator.notify_any(event_map::Reason::Destroy);
 
+            self.subscribers.close();
+
+            if let Some(reason) = CloseReason::from_conn(&state.quiche_conn) {
+                return Err(reason.into());
+            }
+
             return Err(io::Error::new(
                 io::ErrorKind::BrokenPipe,
                 format!("{:?} closed", state.quiche_conn.source_id()),
@@ -134,6 +437,59 @@ impl QuicConnState {
         Ok(())
     }
 
+    /// Sends the buffered data and fin flag of the closing streams as much as possible,
+    /// returns true if any data is written to the connection.
+    fn flush_closing_streams<Guard>(&self, state: &mut Guard) -> bool
+    where
+        Guard: DerefMut<Target = RawQuicConnState>,
+    {
+        let mut closing_streams = self.closing_streams.lock();
+
+        if closing_streams.is_empty() {
+            return false;
+        }
+
+        let mut written = false;
+
+        closing_streams.retain(|id, buf| {
+            if state.reset_streams.contains(id) {
+                return false;
+            }
+
+            match state.quiche_conn.stream_send(*id, buf, true) {
+                Ok(write_size) => {
+                    written = true;
+
+                    buf.advance(write_size);
+
+                    if !buf.is_empty() {
+                        return true;
+                    }
+
+                    log::trace!("{:?} stream closed, stream_id={}", self, id);
+
+                    state.stream_priorities.remove(id);
+
+                    false
+                }
+                // No capacity, retry on the next call. The collected stream also returns `Done`.
+                Err(quiche::Error::Done) => !buf.is_empty(),
+                Err(err) => {
+                    log::trace!(
+                        "{:?} close stream failed, stream_id={}, err={}",
+                        self,
+                        id,
+                        err
+                    );
+
+                    false
+                }
+            }
+        });
+
+        written
+    }
+
     fn handle_quic_incoming_stream<'a, Guard>(&self, state: &mut Guard, id: u64) -> io::Result<()>
     where
         Guard: DerefMut<Target = RawQuicConnState>,
@@ -145,7 +501,7 @@ impl QuicConnState {
             state.register_incoming_stream_ids.insert(id);
             state.incoming.push_back(id);
             self.mediator.notify_one(
-                QuicConnStateEvent::Accept(self.scid.clone()),
+                QuicConnStateEvent::Accept(self.serial),
                 event_map::Reason::On,
             );
         }
@@ -160,7 +516,7 @@ impl QuicConnState {
         self.handle_quic_conn_status(state)?;
 
         self.mediator.notify_one(
-            QuicConnStateEvent::Readable(self.scid.clone()),
+            QuicConnStateEvent::Readable(self.serial),
             event_map::Reason::On,
         );
 
@@ -174,34 +530,69 @@ impl QuicConnState {
         let mut events = vec![];
 
         for id in state.quiche_conn.readable() {
-            events.push(QuicConnStateEvent::StreamReadable(self.scid.clone(), id));
+            events.push(QuicConnStateEvent::StreamReadable(self.serial, id));
             self.handle_quic_incoming_stream(state, id)?;
         }
 
-        for id in state.quiche_conn.writable() {
-            events.push(QuicConnStateEvent::StreamWritable(self.scid.clone(), id));
+        state.collect_stream_priorities();
+
+        // Wakeup the writers of urgent streams first.
+        for id in state.writable_by_priority() {
+            events.push(QuicConnStateEvent::StreamWritable(self.serial, id));
             self.handle_quic_incoming_stream(state, id)?;
         }
 
+        if !self.subscribers.is_empty() {
+            self.subscribers
+                .publish(events.iter().filter_map(|event| match *event {
+                    QuicConnStateEvent::StreamReadable(_, id) => {
+                        Some(QuicEvent::StreamReadable(id))
+                    }
+                    QuicConnStateEvent::StreamWritable(_, id) => {
+                        Some(QuicEvent::StreamWritable(id))
+                    }
+                    _ => None,
+                }));
+        }
+
         self.mediator.notify_all(&events, event_map::Reason::On);
 
         Ok(())
     }
 
-    /// ASynchronously read a single QUIC packet to be sent to the peer.
+    /// Subscribes the `kinds` events of this connection, so many streams can be driven by one task,
+    /// e.g. reading the stream by [`stream_recv`](Self::stream_recv) once it's readable.
     ///
-    /// if there is nothing to read, this function will `pending` until the state changes to
-    /// [`writable`](QuicConnStateEvent::Writable).
-    pub async fn read(&self, buf: &mut [u8]) -> io::Result<(usize, SendInfo)> {
-        let event = QuicConnStateEvent::Readable(self.scid.clone());
+    /// The events published before subscribing are not delivered, the stream ends with
+    /// [`ConnectionClosed`](QuicEvent::ConnectionClosed) if the connection has been closed.
+    pub fn subscribe(&self, kinds: QuicEventKind) -> QuicEventStream {
+        self.subscribers.subscribe(kinds)
+    }
 
-        loop {
-            // Asynchronously lock the [`QuicConnState`]
-            let mut state = self.state.lock().await;
+    /// Attempts to read a single QUIC packet to be sent to the peer.
+    ///
+    /// If there is nothing to read, registers the waker of `cx` to be woken up when the state changes to
+    /// [`readable`](QuicConnStateEvent::Readable) or the quiche / keep-alive timer expires.
+    ///
+    /// The timer only wakes the task of the most recent call, so this function should be polled by one task.
+    pub fn poll_send(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SendInfo)>> {
+        // Yield the current task if the cooperative budget is exhausted.
+        ready!(poll_proceed(cx));
+
+        let mut state = ready!(self.state.poll_lock(cx));
 
+        self.flush_closing_streams(&mut state);
+
+        loop {
             self.handle_quic_conn_status(&mut state)?;
 
-            match state.quiche_conn.send(buf) {
+            let max_send_size = state.max_send_size().min(buf.len());
+
+            match state.quiche_conn.send(&mut buf[..max_send_size]) {
                 Ok((send_size, send_info)) => {
                     log::trace!(
                         "{:?} read data, len={}, send_info={:?}",
@@ -210,14 +601,38 @@ impl QuicConnState {
                         send_info
                     );
 
+                    state.on_packet_sent(send_size);
+
+                    state.send_instant = now();
+                    state.send_timer = None;
+
                     self.handle_quic_read_write_successful(&mut state)?;
 
-                    return Ok((send_size, send_info));
+                    return Poll::Ready(Ok((send_size, send_info)));
                 }
                 Err(quiche::Error::Done) => {
                     self.handle_quic_conn_status(&mut state)?;
 
-                    let send_timeout = state.quiche_conn.timeout();
+                    // The timer of previous pending call is fired.
+                    if let Some(timer) = state.send_timer.as_mut() {
+                        if Pin::new(timer).poll(cx)?.is_ready() {
+                            state.send_timer = None;
+
+                            self.on_send_timeout(&mut state)?;
+
+                            continue;
+                        }
+                    }
+
+                    let send_timeout =
+                        match (state.quiche_conn.timeout(), state.keep_alive_timeout()) {
+                            (Some(quiche_timeout), Some(keep_alive_timeout)) => {
+                                Some(quiche_timeout.min(keep_alive_timeout))
+                            }
+                            (quiche_timeout, keep_alive_timeout) => {
+                                quiche_timeout.or(keep_alive_timeout)
+                            }
+                        };
 
                     log::trace!(
                         "{:?} read data pending, timeout={:?}, is_established={}",
@@ -226,244 +641,470 @@ impl QuicConnState {
                         state.quiche_conn.is_established()
                     );
 
-                    let wait_fut = async {
-                        self.mediator
-                            .wait(event.clone(), state)
-                            .await
-                            .map_err(into_io_error)
-                    };
-
-                    let wait_fut_with_timeout = timeout(wait_fut, send_timeout);
+                    state.send_timer = match send_timeout {
+                        Some(send_timeout) => {
+                            let mut timer =
+                                Sleep::new_with(get_driver()?, get_poller()?, send_timeout)?;
 
-                    match wait_fut_with_timeout.await {
-                        Ok(_) => {
-                            continue;
-                        }
-                        Err(err) if err.kind() == io::ErrorKind::TimedOut => {
-                            // cancel waiting readable event notify.
-                            self.mediator.wait_cancel(&event);
-                            // relock state.
-                            let mut state = self.state.lock().await;
-
-                            if state.send_ack_eliciting_instant.elapsed() > state.ping_timeout {
-                                state
-                                    .quiche_conn
-                                    .send_ack_eliciting()
-                                    .map_err(into_io_error)?;
-
-                                log::trace!(
-                                    "{:?} ping packet, timout={:?}",
-                                    self,
-                                    state.ping_timeout,
-                                );
+                            // Registers the timer.
+                            if Pin::new(&mut timer).poll(cx)?.is_ready() {
+                                self.on_send_timeout(&mut state)?;
 
                                 continue;
                             }
 
-                            state.quiche_conn.on_timeout();
+                            Some(timer)
+                        }
+                        None => None,
+                    };
 
-                            log::debug!(
-                                "{:?} pending on_timeout, timeout={:?}",
-                                self,
-                                send_timeout
-                            );
+                    self.mediator
+                        .register(QuicConnStateEvent::Readable(self.serial), cx.waker());
 
-                            continue;
-                        }
-                        Err(err) => {
-                            log::error!("{:?} read data pending, err={}", self, err);
-                            return Err(into_io_error(err));
-                        }
-                    }
+                    return Poll::Pending;
                 }
                 Err(err) => {
                     log::error!("{:?} read data, err={}", self, err);
 
                     self.handle_quic_conn_status(&mut state)?;
 
-                    return Err(into_io_error(err));
+                    return Poll::Ready(Err(into_io_error(err)));
                 }
             }
         }
     }
 
-    /// Asynchronous write new data to state machine.
-    pub async fn write(&self, buf: &mut [u8], recv_info: RecvInfo) -> io::Result<usize> {
+    fn on_send_timeout<Guard>(&self, state: &mut Guard) -> io::Result<()>
+    where
+        Guard: DerefMut<Target = RawQuicConnState>,
+    {
+        // The timer may be fired by keep-alive before the quiche timeout expired.
+        if state.quiche_conn.timeout() == Some(Duration::ZERO) {
+            state.quiche_conn.on_timeout();
+
+            log::debug!("{:?} pending on_timeout", self);
+        }
+
+        if state.keep_alive_timeout() == Some(Duration::ZERO) {
+            state
+                .quiche_conn
+                .send_ack_eliciting()
+                .map_err(into_io_error)?;
+
+            log::trace!("{:?} ping packet, timout={:?}", self, state.ping_timeout,);
+        }
+
+        Ok(())
+    }
+
+    /// ASynchronously read a single QUIC packet to be sent to the peer.
+    ///
+    /// if there is nothing to read, this function will `pending` until the state changes to
+    /// [`writable`](QuicConnStateEvent::Writable).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial))
+    )]
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<(usize, SendInfo)> {
+        poll_fn(|cx| self.poll_send(cx, buf)).await
+    }
+
+    /// Asynchronously read a batch of QUIC packets to be sent to the same peer,
+    /// which can be sent by one `UDP_SEGMENT` syscall.
+    ///
+    /// Waits for the first packet like [`read`](Self::read), then appends the following packets
+    /// of the same size until `buf` is full, `max_segments` is reached or there is nothing to send.
+    ///
+    /// Returns tuple (read_size, segment_size, send_info), the last segment may be shorter.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial))
+    )]
+    pub async fn read_gso(
+        &self,
+        buf: &mut [u8],
+        max_segments: usize,
+    ) -> io::Result<(usize, usize, SendInfo)> {
+        let (segment_size, send_info) = self.read(buf).await?;
+
+        let mut read_size = segment_size;
+        let mut segments = 1;
+
         let mut state = self.state.lock().await;
 
+        // The probe of path MTU discovery is sent alone.
+        if segment_size > state.current_mtu() {
+            return Ok((read_size, segment_size, send_info));
+        }
+
+        while segments < max_segments && buf.len() - read_size >= segment_size {
+            match state.quiche_conn.send_on_path(
+                &mut buf[read_size..read_size + segment_size],
+                Some(send_info.from),
+                Some(send_info.to),
+            ) {
+                Ok((send_size, _)) => {
+                    read_size += send_size;
+                    segments += 1;
+
+                    // The shorter packet must be the last segment.
+                    if send_size < segment_size {
+                        break;
+                    }
+                }
+                // The error is reported by the next `read`.
+                Err(_) => break,
+            }
+        }
+
+        if segments > 1 {
+            log::trace!(
+                "{:?} read gso batch, len={}, segments={}",
+                self,
+                read_size,
+                segments
+            );
+
+            self.handle_quic_read_write_successful(&mut state)?;
+        }
+
+        Ok((read_size, segment_size, send_info))
+    }
+
+    /// Returns the max udp payload size of the current path, which is raised by the path MTU discovery
+    /// (RFC 8899) from [`BASE_PLPMTU`](super::BASE_PLPMTU) up to the
+    /// [`max_send_udp_payload_size`](quiche::Connection::max_send_udp_payload_size) of the connection.
+    ///
+    /// Returns the `max_send_udp_payload_size` directly if the discovery is disabled by
+    /// [`Config::enable_pmtud`](crate::Config::enable_pmtud).
+    pub async fn current_mtu(&self) -> usize {
+        self.state.lock().await.current_mtu()
+    }
+
+    /// Sets the keep-alive interval, the ping packet is sent if no packet has been sent
+    /// within `interval`. `None` disables keep-alive.
+    pub async fn set_keep_alive(&self, interval: Option<Duration>) {
+        self.state.lock().await.ping_timeout = interval;
+
+        // Wakeup the send loop to reschedule the ping timer.
+        self.mediator.notify_one(
+            QuicConnStateEvent::Readable(self.serial),
+            event_map::Reason::On,
+        );
+    }
+
+    /// Returns the keep-alive interval, `None` if keep-alive is disabled.
+    pub async fn keep_alive(&self) -> Option<Duration> {
+        self.state.lock().await.ping_timeout
+    }
+
+    /// Waits until no packet has been received from the peer for `idle` duration.
+    ///
+    /// Use the `idle` shorter than `max_idle_timeout` to react before the connection is closed by idle timeout,
+    /// returns [`BrokenPipe`](io::ErrorKind::BrokenPipe) error if the connection has been closed.
+    pub async fn on_idle_timeout(&self, idle: Duration) -> io::Result<()> {
+        loop {
+            let elapsed = {
+                let mut state = self.state.lock().await;
+
+                self.handle_quic_conn_status(&mut state)?;
+
+                now().saturating_duration_since(state.recv_instant)
+            };
+
+            if elapsed >= idle {
+                return Ok(());
+            }
+
+            sleep(idle - elapsed).await?;
+        }
+    }
+
+    /// Attempts to write new data to state machine.
+    pub fn poll_recv(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+        recv_info: RecvInfo,
+    ) -> Poll<io::Result<usize>> {
+        // Declared before the state guard, so the notifications raised by one packet are coalesced
+        // and flushed after the state is unlocked, the woken tasks don't contend for it.
+        let _defer = self.mediator.defer();
+
+        let mut state = ready!(self.state.poll_lock(cx));
+
         match state.quiche_conn.recv(buf, recv_info) {
             Ok(write_size) => {
                 log::trace!("{:?} write data success, len={}", self, write_size);
 
+                state.recv_instant = now();
+
+                let raw = &mut *state;
+
+                save_session(&mut raw.session_saver, &raw.quiche_conn);
+
+                // The acknowledged data releases the capacity of closing streams.
+                if self.flush_closing_streams(&mut state) {
+                    self.notify_readable(&mut state)?;
+                }
+
                 self.handle_quic_read_write_successful(&mut state)?;
 
-                return Ok(write_size);
+                Poll::Ready(Ok(write_size))
             }
             Err(err) => {
                 log::trace!("{:?} write data failed, err={}", self, err);
 
                 self.handle_quic_conn_status(&mut state)?;
 
-                return Err(into_io_error(err));
+                Poll::Ready(Err(into_io_error(err)))
             }
         }
     }
 
-    /// Writes data to stream.
-    pub async fn stream_send(&self, id: u64, buf: &[u8], fin: bool) -> io::Result<usize> {
-        let event = QuicConnStateEvent::StreamWritable(self.scid.clone(), id);
+    /// Asynchronous write new data to state machine.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial))
+    )]
+    pub async fn write(&self, buf: &mut [u8], recv_info: RecvInfo) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_recv(cx, buf, recv_info)).await
+    }
 
-        loop {
-            // Asynchronously lock the [`QuicConnState`]
-            let mut state = self.state.lock().await;
+    /// Attempts to write data to stream.
+    ///
+    /// If the send capacity is below the low-watermark of `buf`, at most one datagram and the peer's stream window,
+    /// registers the waker of `cx` to be woken up when the stream is writable.
+    /// The wakers of concurrent callers are queued, and woken one per notification in the calling order.
+    pub fn poll_stream_send(
+        &self,
+        cx: &mut Context<'_>,
+        id: u64,
+        buf: &[u8],
+        fin: bool,
+    ) -> Poll<io::Result<usize>> {
+        let _defer = self.mediator.defer();
+
+        let mut state = ready!(self.state.poll_lock(cx));
 
-            self.handle_quic_conn_status(&mut state)?;
+        self.handle_quic_conn_status(&mut state)?;
 
-            match state.quiche_conn.stream_send(id, buf, fin) {
-                Ok(write_size) => {
-                    log::trace!(
-                        "{:?} stream write, stream_id={}, len={}",
-                        self,
-                        id,
-                        write_size
-                    );
+        if state.reset_streams.contains(&id) {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                format!("{:?} stream had been reset, stream_id={}", self, id),
+            )));
+        }
 
-                    self.notify_readable(&mut state)?;
+        // Waits until the send capacity reaches the low-watermark, so the data buffered under congestion
+        // is bounded and the writes are not split into tiny frames. `stream_writable` also sets the
+        // low-watermark of quiche, so the stream is not reported writable before then. The unknown
+        // streams fall through to `stream_send`, which reports the error.
+        let lowat = send_lowat(&state.quiche_conn, id, buf.len());
 
-                    return Ok(write_size);
-                }
-                Err(quiche::Error::Done) => {
-                    self.notify_readable(&mut state)?;
+        if let Ok(false) = state.quiche_conn.stream_writable(id, lowat) {
+            self.notify_readable(&mut state)?;
 
-                    log::trace!("{:?} stream no capacity, stream_id={}", self, id,);
+            log::trace!(
+                "{:?} stream capacity below the low-watermark, stream_id={}, lowat={}",
+                self,
+                id,
+                lowat
+            );
 
-                    match self.mediator.wait(event.clone(), state).await {
-                        Ok(_) => {
-                            log::trace!("{:?} wakeup stream to write data, stream_id={}", self, id,);
+            self.mediator.register(
+                QuicConnStateEvent::StreamWritable(self.serial, id),
+                cx.waker(),
+            );
 
-                            // try again.
-                            continue;
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "{:?} wakeup stream to write failed, stream_id={}, err={}",
-                                self,
-                                id,
-                                err
-                            );
-
-                            return Err(into_io_error(err));
-                        }
-                    }
+            return Poll::Pending;
+        }
+
+        // quiche truncates the write to the send capacity, the write is partial if the capacity is
+        // between the low-watermark and the length of `buf`.
+        match state.quiche_conn.stream_send(id, buf, fin) {
+            Ok(write_size) => {
+                log::trace!(
+                    "{:?} stream write, stream_id={}, len={}",
+                    self,
+                    id,
+                    write_size
+                );
+
+                if fin && write_size == buf.len() {
+                    state.stream_priorities.remove(&id);
                 }
-                Err(quiche::Error::StreamStopped(id)) => {
-                    log::error!("{:?} stream sending closed, stream_id={}", self, id,);
 
-                    self.notify_readable(&mut state)?;
+                self.notify_readable(&mut state)?;
 
-                    return Err(into_io_error(quiche::Error::StreamStopped(id)));
-                }
-                Err(err) => {
-                    log::error!(
-                        "{:?} write stream data failed, stream_id={}, err={}",
-                        self,
-                        id,
-                        err
-                    );
+                Poll::Ready(Ok(write_size))
+            }
+            Err(quiche::Error::Done) => {
+                self.notify_readable(&mut state)?;
 
-                    self.notify_readable(&mut state)?;
+                log::trace!("{:?} stream no capacity, stream_id={}", self, id,);
 
-                    return Err(into_io_error(err));
-                }
+                self.mediator.register(
+                    QuicConnStateEvent::StreamWritable(self.serial, id),
+                    cx.waker(),
+                );
+
+                Poll::Pending
+            }
+            Err(quiche::Error::StreamStopped(id)) => {
+                log::error!("{:?} stream sending closed, stream_id={}", self, id,);
+
+                self.notify_readable(&mut state)?;
+
+                Poll::Ready(Err(into_io_error(quiche::Error::StreamStopped(id))))
+            }
+            Err(err) => {
+                log::error!(
+                    "{:?} write stream data failed, stream_id={}, err={}",
+                    self,
+                    id,
+                    err
+                );
+
+                self.notify_readable(&mut state)?;
+
+                Poll::Ready(Err(into_io_error(err)))
             }
         }
     }
 
-    /// Reads data from stream, and returns tuple (read_size,fin)
-    pub async fn stream_recv(&self, id: u64, buf: &mut [u8]) -> io::Result<(usize, bool)> {
-        let event = QuicConnStateEvent::StreamReadable(self.scid.clone(), id);
+    /// Writes data to stream.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial, stream_id = id))
+    )]
+    pub async fn stream_send(&self, id: u64, buf: &[u8], fin: bool) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_stream_send(cx, id, buf, fin)).await
+    }
 
-        loop {
-            // Asynchronously lock the [`QuicConnState`]
-            let mut state = self.state.lock().await;
+    /// Attempts to read data from stream, and returns tuple (read_size,fin)
+    ///
+    /// If there is no data to read, registers the waker of `cx` to be woken up when the stream is readable.
+    /// The wakers of concurrent callers are queued, and woken one per notification in the calling order.
+    pub fn poll_stream_recv(
+        &self,
+        cx: &mut Context<'_>,
+        id: u64,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, bool)>> {
+        let _defer = self.mediator.defer();
+
+        let mut state = ready!(self.state.poll_lock(cx));
 
-            self.handle_quic_conn_status(&mut state)?;
+        self.handle_quic_conn_status(&mut state)?;
 
-            match state.quiche_conn.stream_recv(id, buf) {
-                Ok((read_size, fin)) => {
-                    log::trace!(
-                        "{:?} stream read, stream_id={}, len={}, fin={}",
-                        self,
-                        id,
-                        read_size,
-                        fin,
-                    );
+        if state.stopped_streams.contains(&id) {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                format!("{:?} stream had been stopped, stream_id={}", self, id),
+            )));
+        }
 
-                    self.notify_readable(&mut state)?;
+        let limit = state.recv_limit(id, buf.len());
+
+        match state.quiche_conn.stream_recv(id, &mut buf[..limit]) {
+            // No receive window is granted, the fin flag can still be read without any window.
+            Ok((0, false)) if limit == 0 && !buf.is_empty() => {
+                log::trace!("{:?} stream no granted window, stream_id={}", self, id);
 
-                    return Ok((read_size, fin));
+                self.mediator.register(
+                    QuicConnStateEvent::StreamReadable(self.serial, id),
+                    cx.waker(),
+                );
+
+                Poll::Pending
+            }
+            Ok((read_size, fin)) => {
+                log::trace!(
+                    "{:?} stream read, stream_id={}, len={}, fin={}",
+                    self,
+                    id,
+                    read_size,
+                    fin,
+                );
+
+                state.on_stream_consumed(id, read_size, fin);
+
+                if fin {
+                    self.subscribers.publish([QuicEvent::StreamFinished(id)]);
                 }
-                Err(quiche::Error::Done) => {
-                    self.notify_readable(&mut state)?;
 
-                    log::trace!("{:?} stream no capacity, stream_id={}", self, id,);
+                self.notify_readable(&mut state)?;
 
-                    match self.mediator.wait(event.clone(), state).await {
-                        Ok(_) => {
-                            log::trace!("{:?} wakeup stream to read data, stream_id={}", self, id,);
+                Poll::Ready(Ok((read_size, fin)))
+            }
+            Err(quiche::Error::Done) => {
+                self.notify_readable(&mut state)?;
 
-                            // try again.
-                            continue;
-                        }
-                        Err(err) => {
-                            log::error!(
-                                "{:?} wakeup stream to read failed, stream_id={}, err={}",
-                                self,
-                                id,
-                                err
-                            );
-
-                            return Err(into_io_error(err));
-                        }
-                    }
-                }
-                Err(err) => {
-                    log::error!(
-                        "{:?} write stream data failed, stream_id={}, err={}",
-                        self,
-                        id,
-                        err
-                    );
+                log::trace!("{:?} stream no capacity, stream_id={}", self, id,);
 
-                    self.notify_readable(&mut state)?;
+                self.mediator.register(
+                    QuicConnStateEvent::StreamReadable(self.serial, id),
+                    cx.waker(),
+                );
 
-                    return Err(into_io_error(err));
-                }
+                Poll::Pending
+            }
+            Err(err) => {
+                log::error!(
+                    "{:?} write stream data failed, stream_id={}, err={}",
+                    self,
+                    id,
+                    err
+                );
+
+                self.notify_readable(&mut state)?;
+
+                Poll::Ready(Err(into_io_error(err)))
             }
         }
     }
 
+    /// Reads data from stream, and returns tuple (read_size,fin)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial, stream_id = id))
+    )]
+    pub async fn stream_recv(&self, id: u64, buf: &mut [u8]) -> io::Result<(usize, bool)> {
+        poll_fn(|cx| self.poll_stream_recv(cx, id, buf)).await
+    }
+
     /// Accept one incoming stream.
     ///
     /// If there are no more incoming streams,the function will hang the current task,
+    /// returns `None` if the connection had been closed, see [`accept_detailed`](Self::accept_detailed).
     pub async fn accept(&self) -> Option<u64> {
-        let event = QuicConnStateEvent::Accept(self.scid.clone());
+        self.accept_detailed().await.ok()
+    }
+
+    /// Accept one incoming stream, returns the error of why the connection was closed.
+    ///
+    /// The error carries the [`CloseReason`] if the `CONNECTION_CLOSE` frame was sent or received.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial))
+    )]
+    pub async fn accept_detailed(&self) -> io::Result<u64> {
+        let event = QuicConnStateEvent::Accept(self.serial);
 
         loop {
             // Asynchronously lock the [`QuicConnState`]
             let mut state = self.state.lock().await;
 
-            if self.handle_quic_conn_status(&mut state).is_err() {
-                return None;
-            }
+            self.handle_quic_conn_status(&mut state)?;
 
             if let Some(incoming) = state.incoming.pop_front() {
-                return Some(incoming);
+                return Ok(incoming);
             }
 
             log::trace!("{:?} accept incoming strema pending.", self,);
 
-            match self.mediator.wait(event.clone(), state).await {
+            match self.mediator.wait(event, state).await {
                 Ok(_) => {
                     log::trace!("{:?} wakeup accept task", self,);
 
@@ -475,13 +1116,21 @@ impl QuicConnState {
 
                     // Safety: The event wait function returns an error message only if the event is canceled/destroyed.
                     // then it indicates that the connection is being closed or has been closed.
-                    return None;
+                    let mut state = self.state.lock().await;
+
+                    self.handle_quic_conn_status(&mut state)?;
+
+                    return Err(into_io_error(err));
                 }
             }
         }
     }
 
     /// Open new stream to communicate with remote peer.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial))
+    )]
     pub async fn open_stream(&self) -> io::Result<u64> {
         let mut state = self.state.lock().await;
 
@@ -493,33 +1142,178 @@ impl QuicConnState {
         Ok(stream_id)
     }
 
+    /// Open new unidirectional stream to send data to remote peer.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial))
+    )]
+    pub async fn open_uni_stream(&self) -> io::Result<u64> {
+        let mut state = self.state.lock().await;
+
+        self.handle_quic_conn_status(&mut state)?;
+
+        let stream_id = state.lastest_outgoing_uni_stream_id;
+        state.lastest_outgoing_uni_stream_id += 4;
+
+        Ok(stream_id)
+    }
+
     /// Close stream by stream `id`.
     ///
     /// This function closes stream by sending len(0) data and fin flag.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial, stream_id = id))
+    )]
     pub async fn close_stream(&self, id: u64) -> io::Result<()> {
         self.stream_send(id, b"", true).await.map(|_| ())
     }
 
+    /// Closes stream `id` after the unsent data in `buf` is flushed, without waiting.
+    ///
+    /// The data and fin flag are sent by the connection in the background as the stream capacity
+    /// allows, it's used to close the stream in `drop`. The closing is abandoned if the stream is
+    /// reset or the connection is closed.
+    pub fn close_stream_deferred(&self, id: u64, buf: BytesMut) {
+        self.closing_streams.lock().insert(id, buf);
+
+        // Wakeup the send loop to flush the closing stream.
+        self.mediator.notify_one(
+            QuicConnStateEvent::Readable(self.serial),
+            event_map::Reason::On,
+        );
+    }
+
+    /// Sets the priority of the stream `id`, lower `urgency` is more urgent.
+    ///
+    /// see quiche [`doc`](https://docs.rs/quiche/latest/quiche/struct.Connection.html#method.stream_priority) for more information.
+    pub async fn stream_priority(&self, id: u64, urgency: u8, incremental: bool) -> io::Result<()> {
+        let mut state = self.state.lock().await;
+
+        self.handle_quic_conn_status(&mut state)?;
+
+        state
+            .quiche_conn
+            .stream_priority(id, urgency, incremental)
+            .map_err(into_io_error)?;
+
+        state.stream_priorities.insert(id, (urgency, incremental));
+
+        Ok(())
+    }
+
+    /// Returns the number of streams whose priority is kept.
+    #[cfg(test)]
+    pub(super) async fn stream_priorities_len(&self) -> usize {
+        self.state.lock().await.stream_priorities.len()
+    }
+
     /// Shuts down reading or writing from/to the specified stream.
     ///
+    /// Shutting down the reading side sends `STOP_SENDING` frame, and the writing side sends `RESET_STREAM` frame
+    /// to the peer with the application error code `err`. The pending and later operations on the shut down side
+    /// return [`BrokenPipe`](io::ErrorKind::BrokenPipe) error.
+    ///
     /// see quiche [`doc`](https://docs.rs/quiche/latest/quiche/struct.Connection.html#method.stream_shutdown) for more information.
-    pub async fn stream_shutdown(&self, stream_id: u64, err: u64) -> io::Result<()> {
-        self.state
-            .lock()
-            .await
-            .quiche_conn
-            .stream_shutdown(stream_id, quiche::Shutdown::Read, err)
-            .map_err(into_io_error)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial, stream_id = stream_id))
+    )]
+    pub async fn stream_shutdown(
+        &self,
+        stream_id: u64,
+        direction: quiche::Shutdown,
+        err: u64,
+    ) -> io::Result<()> {
+        let mut state = self.state.lock().await;
+
+        self.handle_quic_conn_status(&mut state)?;
+
+        let read = matches!(direction, quiche::Shutdown::Read);
+
+        match state.quiche_conn.stream_shutdown(stream_id, direction, err) {
+            // The stream is not yet created or had been shut down.
+            Ok(_) | Err(quiche::Error::Done) => {}
+            Err(err) => return Err(into_io_error(err)),
+        }
+
+        let event = if read {
+            log::trace!(
+                "{:?} stream stopped, stream_id={}, err={}",
+                self,
+                stream_id,
+                err
+            );
+
+            state.stopped_streams.insert(stream_id);
+            state.remove_flow_control(stream_id);
+
+            QuicConnStateEvent::StreamReadable(self.serial, stream_id)
+        } else {
+            log::trace!(
+                "{:?} stream reset, stream_id={}, err={}",
+                self,
+                stream_id,
+                err
+            );
+
+            state.reset_streams.insert(stream_id);
+            state.stream_priorities.remove(&stream_id);
+
+            QuicConnStateEvent::StreamWritable(self.serial, stream_id)
+        };
+
+        // Wakeup the pending operations of the shut down side.
+        self.mediator.notify_one(event, event_map::Reason::On);
+
+        self.notify_readable(&mut state)
     }
 
     /// Closes the connection with the given error and reason.
     ///
     /// see quiche [`doc`](https://docs.rs/quiche/latest/quiche/struct.Connection.html#method.close) for more information.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(conn = self.serial))
+    )]
     pub async fn close(&self, app: bool, err: u64, reason: &[u8]) -> io::Result<()> {
-        match self.state.lock().await.quiche_conn.close(app, err, reason) {
-            Ok(_) => Ok(()),
-            Err(quiche::Error::Done) => Ok(()),
-            Err(err) => Err(into_io_error(err)),
+        let mut state = self.state.lock().await;
+
+        match state.quiche_conn.close(app, err, reason) {
+            Ok(_) => {}
+            Err(quiche::Error::Done) => return Ok(()),
+            Err(err) => return Err(into_io_error(err)),
+        }
+
+        // Wakeup the send loop to send the `CONNECTION_CLOSE` frame.
+        self.mediator.notify_one(
+            QuicConnStateEvent::Readable(self.serial),
+            event_map::Reason::On,
+        );
+
+        // Wakeup the `closed` waiters if the connection is closed immediately, e.g. during the handshake.
+        _ = self.handle_quic_conn_status(&mut state);
+
+        Ok(())
+    }
+
+    /// Waits until the connection is closed, e.g. the draining period started by [`close`](Self::close) elapses.
+    ///
+    /// The draining timer is driven by [`poll_send`](Self::poll_send), so the send loop must keep running.
+    pub async fn closed(&self) {
+        let event = QuicConnStateEvent::Closed(self.serial);
+
+        loop {
+            let mut state = self.state.lock().await;
+
+            if self.handle_quic_conn_status(&mut state).is_err() {
+                return;
+            }
+
+            // The waiter is woken up by the destroy notification of `handle_quic_conn_status`.
+            if self.mediator.wait(event, state).await.is_err() {
+                return;
+            }
         }
     }
 
@@ -534,15 +1328,205 @@ impl QuicConnState {
             .stream_finished(stream_id)
     }
 
+    /// Returns the reason the connection was closed, `None` if no `CONNECTION_CLOSE` frame
+    /// was sent or received yet, see [`CloseReason::from_conn`] for more information.
+    pub async fn close_reason(&self) -> Option<CloseReason> {
+        CloseReason::from_conn(&self.state.lock().await.quiche_conn)
+    }
+
     /// Returns true if the connection is closed.
     pub async fn is_closed(&self) -> bool {
         self.state.lock().await.quiche_conn.is_closed()
     }
 
+    /// Returns the number of bidirectional streams that can be created before the peer's limit is reached.
+    ///
+    /// The stream id returned by [`open_stream`](Self::open_stream) is counted once the first frame
+    /// of the stream is sent.
+    pub async fn peer_streams_left_bidi(&self) -> u64 {
+        self.state.lock().await.quiche_conn.peer_streams_left_bidi()
+    }
+
+    /// Returns the connection-level flow control limit of the data sent to the peer, which is
+    /// the `initial_max_data` transport parameter of the peer, 0 before the parameters are received.
+    ///
+    /// quiche doesn't expose the limits raised by the later `MAX_DATA` frames, use the
+    /// [`send_capacity`](QuicStreamFlowControl::send_capacity) of streams to check whether the sending side is blocked.
+    pub async fn max_data(&self) -> u64 {
+        self.state
+            .lock()
+            .await
+            .quiche_conn
+            .peer_transport_params()
+            .map_or(0, |params| params.initial_max_data)
+    }
+
+    /// Returns the total bytes read by the application from all streams, which are released from
+    /// the connection-level receive window.
+    ///
+    /// The stalled receiving side, e.g. a slow application, shows the `data_consumed` not growing.
+    pub async fn data_consumed(&self) -> u64 {
+        self.state.lock().await.data_consumed
+    }
+
+    /// Returns the flow control state of stream `stream_id`, see [`QuicStreamFlowControl`] for more information.
+    ///
+    /// The consumed bytes are reset once the reading side of the stream is finished or stopped.
+    pub async fn stream_flow_control(&self, stream_id: u64) -> QuicStreamFlowControl {
+        let state = self.state.lock().await;
+
+        QuicStreamFlowControl {
+            consumed: state
+                .stream_consumed
+                .get(&stream_id)
+                .cloned()
+                .unwrap_or_default(),
+            send_capacity: state.quiche_conn.stream_capacity(stream_id).ok(),
+            granted: state
+                .stream_grants
+                .as_ref()
+                .map(|grants| grants.get(&stream_id).cloned().unwrap_or_default()),
+        }
+    }
+
+    /// Enables or disables the manual flow control of the receiving side, which is disabled by default.
+    ///
+    /// Once enabled, [`stream_recv`](Self::stream_recv) only reads the window granted by
+    /// [`stream_grant`](Self::stream_grant), the rest data is kept in the quiche buffer, so the
+    /// `MAX_STREAM_DATA` / `MAX_DATA` frames are not sent to the peer until the application grants more window.
+    ///
+    /// Disabling it discards the granted window, and wakes up the pending readers.
+    pub async fn set_manual_flow_control(&self, enabled: bool) {
+        let mut state = self.state.lock().await;
+
+        if enabled == state.stream_grants.is_some() {
+            return;
+        }
+
+        log::trace!("{:?} set manual flow control, enabled={}", self, enabled);
+
+        if enabled {
+            state.stream_grants = Some(Default::default());
+        } else {
+            state.stream_grants = None;
+
+            let events = state
+                .quiche_conn
+                .readable()
+                .map(|id| QuicConnStateEvent::StreamReadable(self.serial, id))
+                .collect::<Vec<_>>();
+
+            self.mediator.notify_all(&events, event_map::Reason::On);
+        }
+    }
+
+    /// Grants `bytes` receive window of stream `stream_id` to the peer, and wakes up the pending reader.
+    ///
+    /// Returns [`InvalidInput`](io::ErrorKind::InvalidInput) error if the manual flow control is disabled,
+    /// see [`set_manual_flow_control`](Self::set_manual_flow_control) for more information.
+    pub async fn stream_grant(&self, stream_id: u64, bytes: u64) -> io::Result<()> {
+        let mut state = self.state.lock().await;
+
+        self.handle_quic_conn_status(&mut state)?;
+
+        let grants = state.stream_grants.as_mut().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} manual flow control is disabled", self),
+            )
+        })?;
+
+        let granted = grants.entry(stream_id).or_default();
+
+        *granted = granted.saturating_add(bytes);
+
+        log::trace!(
+            "{:?} stream grant, stream_id={}, bytes={}, granted={}",
+            self,
+            stream_id,
+            bytes,
+            granted
+        );
+
+        self.mediator.notify_one(
+            QuicConnStateEvent::StreamReadable(self.serial, stream_id),
+            event_map::Reason::On,
+        );
+
+        Ok(())
+    }
+
     /// Returns true if the connection is established.
     pub async fn is_established(&self) -> bool {
         self.state.lock().await.quiche_conn.is_established()
     }
+
+    /// Returns true if the peer address has been validated, see RFC 9000 section 8.
+    ///
+    /// The server connection completes the address validation before establishing,
+    /// the client connection always returns true.
+    pub async fn is_address_validated(&self) -> bool {
+        let state = self.state.lock().await;
+
+        !state.quiche_conn.is_server() || state.quiche_conn.is_established()
+    }
+
+    /// Returns the DER-encoded peer's leaf certificate, `None` if the peer doesn't provide one.
+    pub async fn peer_cert(&self) -> Option<Vec<u8>> {
+        self.state
+            .lock()
+            .await
+            .quiche_conn
+            .peer_cert()
+            .map(|cert| cert.to_vec())
+    }
+
+    /// Returns the negotiated ALPN protocol, `None` if no protocol was negotiated.
+    pub async fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        let state = self.state.lock().await;
+
+        let proto = state.quiche_conn.application_proto();
+
+        if proto.is_empty() {
+            None
+        } else {
+            Some(proto.to_vec())
+        }
+    }
+
+    /// Returns the server name requested by the client.
+    pub async fn server_name(&self) -> Option<String> {
+        self.state
+            .lock()
+            .await
+            .quiche_conn
+            .server_name()
+            .map(|name| name.to_owned())
+    }
+
+    /// Returns the negotiated handshake parameters of this connection.
+    pub async fn handshake_info(&self) -> QuicHandshakeInfo {
+        let state = self.state.lock().await;
+
+        let quiche_conn = &state.quiche_conn;
+
+        let proto = quiche_conn.application_proto();
+
+        QuicHandshakeInfo {
+            alpn_protocol: if proto.is_empty() {
+                None
+            } else {
+                Some(proto.to_vec())
+            },
+            server_name: quiche_conn.server_name().map(|name| name.to_owned()),
+            peer_cert_chain: quiche_conn
+                .peer_cert_chain()
+                .map(|chain| chain.into_iter().map(|cert| cert.to_vec()).collect())
+                .unwrap_or_default(),
+            is_resumed: quiche_conn.is_resumed(),
+            is_in_early_data: quiche_conn.is_in_early_data(),
+        }
+    }
 }
 
 impl Drop for QuicConnState {
@@ -550,12 +1534,13 @@ impl Drop for QuicConnState {
         // The last one instance is dropping.
         if Arc::strong_count(&self.state) == 1 {
             let this = self.clone();
-            io_spawn(async move {
+            if let Err(err) = io_spawn(async move {
                 this.close(false, 0, b"raii drop").await.unwrap();
 
                 Ok(())
-            })
-            .unwrap();
+            }) {
+                log::error!("spawn quic conn state closing task failed, err={}", err);
+            }
         }
     }
 }
@@ -3,7 +3,11 @@ use std::{io, net::SocketAddr, time::Duration};
 use quiche::{RecvInfo, SendInfo};
 use ring::rand::{SecureRandom, SystemRandom};
 
-use crate::{errors::into_io_error, Config};
+use crate::{
+    errors::into_io_error,
+    session::{save_session, SessionSaver},
+    Config, SessionKey,
+};
 
 use super::QuicConnState;
 
@@ -12,6 +16,11 @@ pub struct QuicConnectorState {
     /// source connection id.
     pub(super) quiche_conn: quiche::Connection,
     pub(super) ping_timeout: Duration,
+    pub(super) stream_buffer: usize,
+    /// Enables the path MTU discovery of the connection.
+    pub(super) pmtud: bool,
+    /// Saves the TLS session once the session ticket is received.
+    pub(super) session_saver: Option<SessionSaver>,
 }
 
 impl QuicConnectorState {
@@ -29,12 +38,28 @@ impl QuicConnectorState {
 
         log::trace!("Connector {:?}", scid);
 
-        let quiche_conn = quiche::connect(None, &scid, laddr, raddr, config)
+        let server_name = config.server_name.clone();
+
+        let mut quiche_conn = quiche::connect(server_name.as_deref(), &scid, laddr, raddr, config)
             .map_err(|err| io::Error::new(io::ErrorKind::ConnectionRefused, err))?;
 
+        config.setup_conn_tracing(&mut quiche_conn)?;
+
+        let session_saver = config
+            .session_store
+            .clone()
+            .map(|store| SessionSaver::new(store, SessionKey { server_name, raddr }));
+
+        if let Some(saver) = &session_saver {
+            saver.load(&mut quiche_conn);
+        }
+
         Ok(Self {
             quiche_conn,
             ping_timeout: config.ping_timeout,
+            stream_buffer: config.stream_buffer,
+            pmtud: config.pmtud,
+            session_saver,
         })
     }
 
@@ -82,6 +107,8 @@ impl QuicConnectorState {
             return io::Error::new(io::ErrorKind::ConnectionRefused, err);
         })?;
 
+        save_session(&mut self.session_saver, &self.quiche_conn);
+
         if self.quiche_conn.is_closed() {
             return Err(io::Error::new(
                 io::ErrorKind::ConnectionRefused,
@@ -100,6 +127,38 @@ impl QuicConnectorState {
         self.quiche_conn.is_established()
     }
 
+    /// Returns true if the connection is closed, e.g. the handshake timed out or was rejected by the peer.
+    pub fn is_closed(&self) -> bool {
+        self.quiche_conn.is_closed()
+    }
+
+    /// Returns the reason why the closed connection failed to establish.
+    pub fn close_error(&self) -> io::Error {
+        if self.quiche_conn.is_timed_out() {
+            return io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "handshake timed out, conn_id={:?}",
+                    self.quiche_conn.source_id()
+                ),
+            );
+        }
+
+        let error = self
+            .quiche_conn
+            .peer_error()
+            .or(self.quiche_conn.local_error());
+
+        io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!(
+                "handshake failed, conn_id={:?}, err={:?}",
+                self.quiche_conn.source_id(),
+                error
+            ),
+        )
+    }
+
     /// Returns the amount of time until the next timeout event.
     ///
     /// Once the given duration has elapsed, the [`on_timeout()`] method should
@@ -119,6 +178,13 @@ impl QuicConnectorState {
 
 impl From<QuicConnectorState> for QuicConnState {
     fn from(value: QuicConnectorState) -> Self {
-        QuicConnState::new(value.quiche_conn, value.ping_timeout, 4)
+        QuicConnState::with_session_saver(
+            value.quiche_conn,
+            value.ping_timeout,
+            value.stream_buffer,
+            4,
+            value.session_saver,
+            value.pmtud,
+        )
     }
 }
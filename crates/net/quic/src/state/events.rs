@@ -0,0 +1,183 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use bitmask_enum::bitmask;
+use futures::Stream;
+use hala_sync::*;
+
+/// The kinds of [`QuicEvent`] subscribed by [`subscribe`](crate::QuicConn::subscribe).
+#[bitmask(u8)]
+pub enum QuicEventKind {
+    StreamReadable,
+    StreamWritable,
+    StreamFinished,
+    ConnectionClosed,
+}
+
+/// The event of quic connection, yielded by [`QuicEventStream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuicEvent {
+    /// The stream has data or the fin flag to read.
+    StreamReadable(u64),
+    /// The stream has capacity to write.
+    StreamWritable(u64),
+    /// The fin flag of the stream has been read.
+    StreamFinished(u64),
+    /// The connection is closed, this is the last event of the stream.
+    ConnectionClosed,
+}
+
+impl QuicEvent {
+    /// Returns the kind of this event.
+    pub fn kind(&self) -> QuicEventKind {
+        match self {
+            QuicEvent::StreamReadable(_) => QuicEventKind::StreamReadable,
+            QuicEvent::StreamWritable(_) => QuicEventKind::StreamWritable,
+            QuicEvent::StreamFinished(_) => QuicEventKind::StreamFinished,
+            QuicEvent::ConnectionClosed => QuicEventKind::ConnectionClosed,
+        }
+    }
+}
+
+#[derive(Default)]
+struct RawSubscription {
+    /// The events not yet taken by the subscriber.
+    queue: VecDeque<QuicEvent>,
+    /// The events in `queue`, the same event is queued once until taken.
+    queued: HashSet<QuicEvent>,
+    waker: Option<Waker>,
+    /// No more events will be published.
+    closed: bool,
+}
+
+/// The subscription shared by [`QuicEventStream`] and [`Subscribers`].
+struct Subscription {
+    kinds: QuicEventKind,
+    raw: SpinMutex<RawSubscription>,
+}
+
+/// The subscriptions of one connection, `None` if the connection is closed.
+pub(super) struct Subscribers(SpinMutex<Option<Vec<Arc<Subscription>>>>);
+
+impl Subscribers {
+    pub(super) fn new() -> Self {
+        Self(SpinMutex::new(Some(vec![])))
+    }
+
+    /// Creates a subscription of `kinds` events.
+    pub(super) fn subscribe(&self, kinds: QuicEventKind) -> QuicEventStream {
+        let subscription = Arc::new(Subscription {
+            kinds,
+            raw: Default::default(),
+        });
+
+        match self.0.lock().as_mut() {
+            Some(subscriptions) => {
+                // Removes the dropped subscriptions.
+                subscriptions.retain(|subscription| Arc::strong_count(subscription) > 1);
+                subscriptions.push(subscription.clone());
+            }
+            None => {
+                Self::publish_to(&subscription, QuicEvent::ConnectionClosed);
+                subscription.raw.lock().closed = true;
+            }
+        }
+
+        QuicEventStream { subscription }
+    }
+
+    /// Returns true if there is no subscription, so the caller can skip collecting the events.
+    pub(super) fn is_empty(&self) -> bool {
+        match self.0.lock().as_ref() {
+            Some(subscriptions) => subscriptions.is_empty(),
+            None => true,
+        }
+    }
+
+    /// Publishes `events` to the subscriptions of their kinds.
+    pub(super) fn publish<I: IntoIterator<Item = QuicEvent>>(&self, events: I) {
+        let subscriptions = self.0.lock();
+
+        let Some(subscriptions) = subscriptions.as_ref() else {
+            return;
+        };
+
+        for event in events {
+            for subscription in subscriptions {
+                Self::publish_to(subscription, event);
+            }
+        }
+    }
+
+    /// Publishes [`ConnectionClosed`](QuicEvent::ConnectionClosed) and ends all the subscriptions.
+    pub(super) fn close(&self) {
+        let Some(subscriptions) = self.0.lock().take() else {
+            return;
+        };
+
+        for subscription in subscriptions {
+            Self::publish_to(&subscription, QuicEvent::ConnectionClosed);
+
+            let mut raw = subscription.raw.lock();
+
+            raw.closed = true;
+
+            if let Some(waker) = raw.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    fn publish_to(subscription: &Subscription, event: QuicEvent) {
+        if !subscription.kinds.contains(event.kind()) {
+            return;
+        }
+
+        let mut raw = subscription.raw.lock();
+
+        if raw.closed || !raw.queued.insert(event) {
+            return;
+        }
+
+        raw.queue.push_back(event);
+
+        if let Some(waker) = raw.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The stream of connection events created by [`subscribe`](crate::QuicConn::subscribe),
+/// which ends after [`ConnectionClosed`](QuicEvent::ConnectionClosed).
+///
+/// The events are level-triggered hints, e.g. [`StreamReadable`](QuicEvent::StreamReadable) is
+/// published again while the stream has data to read, and the same event is queued once until taken.
+pub struct QuicEventStream {
+    subscription: Arc<Subscription>,
+}
+
+impl Stream for QuicEventStream {
+    type Item = QuicEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut raw = self.subscription.raw.lock();
+
+        if let Some(event) = raw.queue.pop_front() {
+            raw.queued.remove(&event);
+
+            return Poll::Ready(Some(event));
+        }
+
+        if raw.closed {
+            return Poll::Ready(None);
+        }
+
+        raw.waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
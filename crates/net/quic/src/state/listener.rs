@@ -7,18 +7,29 @@ use std::{
 };
 
 use dashmap::DashMap;
+use futures::{stream, Stream};
 use hala_future::{
     batching::FutureBatcher,
     event_map::{self, EventMap},
 };
-use hala_io::{bytes::BytesMut, *};
-use hala_sync::{AsyncLockable, AsyncSpinMutex};
+use hala_lockfree::{pool::PooledBuf, timewheel::HashedTimeWheel};
+use hala_sync::{AsyncLockable, AsyncSpinMutex, Lockable, SpinMutex};
 use quiche::{ConnectionId, RecvInfo, SendInfo};
 use ring::{hmac::Key, rand::SystemRandom};
 
-use crate::{errors::into_io_error, Config};
+use crate::{datagram_pool, errors::into_io_error, Config};
 
-use super::QuicConnState;
+use super::{wfq::Wfq, ConnRouter, QuicConnState};
+
+/// The tick duration of the handshake timers, which is the precision of the handshake timeout.
+pub const HANDSHAKE_TIMER_TICK: Duration = Duration::from_millis(250);
+
+/// The server can send at most three times the bytes received before the client address is validated,
+/// see RFC 9000 section 8.
+const AMPLIFICATION_FACTOR: usize = 3;
+
+/// The minimum size of the datagram carrying the initial packets, see RFC 9000 section 14.1.
+const MIN_INITIAL_DATAGRAM_SIZE: usize = 1200;
 
 /// [`handshake`](Acceptor::handshake) result.
 pub enum QuicAcceptorHandshake {
@@ -26,6 +37,8 @@ pub enum QuicAcceptorHandshake {
     Incoming {
         conn: quiche::Connection,
         ping_timeout: Duration,
+        stream_buffer: usize,
+        pmtud: bool,
         write_size: usize,
         read_size: usize,
         send_info: SendInfo,
@@ -38,6 +51,84 @@ pub enum QuicAcceptorHandshake {
     },
 }
 
+/// The anti-amplification limit of the connection before address validation.
+#[derive(Debug, Default, Clone, Copy)]
+struct AmplificationBudget {
+    /// The bytes received from the client.
+    received: usize,
+    /// The bytes sent to the client.
+    sent: usize,
+    /// The client address is validated by retry token or by receiving the handshake packet.
+    validated: bool,
+}
+
+impl AmplificationBudget {
+    fn on_recv(&mut self, len: usize, ty: quiche::Type) {
+        self.received += len;
+
+        if ty == quiche::Type::Handshake {
+            self.validated = true;
+        }
+    }
+
+    /// Returns the bytes can be sent to the client, or `None` if the address has been validated.
+    fn remaining(&self) -> Option<usize> {
+        if self.validated {
+            None
+        } else {
+            Some((self.received * AMPLIFICATION_FACTOR).saturating_sub(self.sent))
+        }
+    }
+
+    /// Writes the next datagram of `conn` into `buf`, clamped by the remaining budget.
+    fn send(
+        &mut self,
+        conn: &mut quiche::Connection,
+        buf: &mut [u8],
+        recv_info: RecvInfo,
+    ) -> io::Result<(usize, SendInfo)> {
+        let len = match self.remaining() {
+            // The client would drop the initial datagram smaller than 1200 bytes,
+            // so wait for more bytes from the client.
+            Some(remaining) if remaining < MIN_INITIAL_DATAGRAM_SIZE => 0,
+            Some(remaining) => remaining.min(buf.len()),
+            None => buf.len(),
+        };
+
+        let result = if len == 0 {
+            Err(quiche::Error::Done)
+        } else {
+            conn.send(&mut buf[..len])
+        };
+
+        match result {
+            Ok((read_size, send_info)) => {
+                self.sent += read_size;
+
+                Ok((read_size, send_info))
+            }
+            Err(quiche::Error::Done) => Ok((
+                0,
+                SendInfo {
+                    from: recv_info.to,
+                    to: recv_info.from,
+                    at: Instant::now(),
+                },
+            )),
+            Err(err) => Err(into_io_error(err)),
+        }
+    }
+}
+
+/// The connection before establishing connection.
+struct HandshakingConn {
+    conn: quiche::Connection,
+    /// The instant after which the connection is dropped by [`collect_expired`](QuicAcceptor::collect_expired).
+    deadline: Instant,
+    /// The anti-amplification limit of this connection.
+    budget: AmplificationBudget,
+}
+
 /// Raw incoming connection acceptor for quic server.
 pub struct QuicAcceptor {
     /// Quic connection config
@@ -45,7 +136,9 @@ pub struct QuicAcceptor {
     /// connection id seed.
     conn_id_seed: Key,
     /// connections before establishing connection.
-    pre_established_conns: HashMap<ConnectionId<'static>, quiche::Connection>,
+    pre_established_conns: HashMap<ConnectionId<'static>, HandshakingConn>,
+    /// The handshake timers of `pre_established_conns`.
+    handshake_timers: HashedTimeWheel<ConnectionId<'static>>,
 }
 
 impl QuicAcceptor {
@@ -60,51 +153,135 @@ impl QuicAcceptor {
             config,
             conn_id_seed,
             pre_established_conns: Default::default(),
+            handshake_timers: HashedTimeWheel::new(HANDSHAKE_TIMER_TICK),
         })
     }
 
+    /// Returns the number of connections that have not completed the handshake.
+    pub fn handshaking(&self) -> usize {
+        self.pre_established_conns.len()
+    }
+
+    /// Returns the number of handshaking connections whose client address has not been validated.
+    pub fn unvalidated(&self) -> usize {
+        self.pre_established_conns
+            .values()
+            .filter(|conn| !conn.budget.validated)
+            .count()
+    }
+
+    /// Returns whether the client address of the handshaking connection `scid` has been validated,
+    /// or `None` if the connection is not handshaking.
+    pub fn is_address_validated(&self, scid: &ConnectionId<'_>) -> Option<bool> {
+        self.pre_established_conns
+            .get(scid)
+            .map(|conn| conn.budget.validated)
+    }
+
+    /// Drops one handshaking connection whose client address has not been validated,
+    /// returns false if there is no such connection.
+    fn evict_unvalidated(&mut self) -> bool {
+        let scid = self
+            .pre_established_conns
+            .iter()
+            .find(|(_, conn)| !conn.budget.validated)
+            .map(|(scid, _)| scid.clone());
+
+        if let Some(scid) = scid {
+            log::trace!("evict unvalidated handshaking conn, scid={:?}", scid);
+
+            self.pre_established_conns.remove(&scid);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drops the connections whose handshake timeout has expired, and returns the number of dropped connections.
+    pub fn collect_expired(&mut self) -> usize {
+        let Some(timers) = self.handshake_timers.next_tick() else {
+            return 0;
+        };
+
+        let now = Instant::now();
+
+        let mut expired = 0;
+
+        for scid in timers {
+            // The timer of established connection, or of the previous connection with the same id.
+            match self.pre_established_conns.get(&scid) {
+                Some(conn) if conn.deadline <= now => {}
+                _ => continue,
+            }
+
+            self.pre_established_conns.remove(&scid);
+
+            log::trace!("drop handshake timeout conn, scid={:?}", scid);
+
+            expired += 1;
+        }
+
+        expired
+    }
+
+    /// Returns true if a new connection may be created, given the number of `active_conns`
+    /// held by the listener and the length of its pending incoming queue.
+    pub fn is_accepting(&self, active_conns: usize, pending_conns: usize) -> bool {
+        active_conns + self.pre_established_conns.len() < self.config.max_incoming_conns
+            && pending_conns < self.config.accept_queue_len
+    }
+
     /// Try to process quic init/handshake protocol and returns [`Handshake`] result
+    ///
+    /// If `accepting` is false, initial packets of new connections are dropped
+    /// with a [`ConnectionRefused`](io::ErrorKind::ConnectionRefused) error,
+    /// unless the packet carries a valid retry token and one handshaking connection
+    /// with unvalidated address can be evicted.
     pub fn handshake<'a>(
         &mut self,
         buf: &'a mut [u8],
         write_size: usize,
         recv_info: RecvInfo,
+        accepting: bool,
     ) -> io::Result<QuicAcceptorHandshake> {
         let header = quiche::Header::from_slice(&mut buf[..write_size], quiche::MAX_CONN_ID_LEN)
             .map_err(into_io_error)?;
 
         // this is pre-establishing conn packet
-        if let Some(mut conn) = self.pre_established_conns.remove(&header.dcid) {
+        if let Some(HandshakingConn {
+            mut conn,
+            deadline,
+            mut budget,
+        }) = self.pre_established_conns.remove(&header.dcid)
+        {
+            budget.on_recv(write_size, header.ty);
+
             let write_size = conn
                 .recv(&mut buf[..write_size], recv_info)
                 .map_err(into_io_error)?;
 
-            let (read_size, send_info) = match conn.send(buf) {
-                Ok(r) => r,
-                Err(quiche::Error::Done) => (
-                    0,
-                    SendInfo {
-                        from: recv_info.to,
-                        to: recv_info.from,
-                        at: Instant::now(),
-                    },
-                ),
-                Err(err) => {
-                    return Err(into_io_error(err));
-                }
-            };
+            let (read_size, send_info) = budget.send(&mut conn, buf, recv_info)?;
 
             if conn.is_established() {
                 return Ok(QuicAcceptorHandshake::Incoming {
                     conn,
                     ping_timeout: self.config.ping_timeout,
+                    stream_buffer: self.config.stream_buffer,
+                    pmtud: self.config.pmtud,
                     write_size,
                     read_size,
                     send_info,
                 });
             } else {
-                self.pre_established_conns
-                    .insert(header.dcid.clone().into_owned(), conn);
+                self.pre_established_conns.insert(
+                    header.dcid.clone().into_owned(),
+                    HandshakingConn {
+                        conn,
+                        deadline,
+                        budget,
+                    },
+                );
 
                 return Ok(QuicAcceptorHandshake::Internal {
                     write_size,
@@ -115,6 +292,23 @@ impl QuicAcceptor {
         }
 
         if header.ty == quiche::Type::Initial {
+            let prioritized = || {
+                header
+                    .token
+                    .as_ref()
+                    .is_some_and(|token| Self::validate_token(token, &recv_info.from).is_ok())
+            };
+
+            if !(accepting || (prioritized() && self.evict_unvalidated())) {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!(
+                        "Too many incoming conns, drop initial packet, dcid={:?}",
+                        header.dcid
+                    ),
+                ));
+            }
+
             return self.client_hello(&header, buf, write_size, recv_info);
         } else {
             return Ok(QuicAcceptorHandshake::Unhandled(
@@ -137,12 +331,22 @@ impl QuicAcceptor {
         let token = header.token.as_ref().unwrap();
 
         // generate new token and retry
-        if token.is_empty() {
+        if token.is_empty() && self.config.stateless_retry {
             return self.retry(header, recv_info, buf, write_size);
         }
 
+        let mut budget = AmplificationBudget::default();
+
+        budget.on_recv(write_size, header.ty);
+
         // check token .
-        let odcid = Self::validate_token(token, &recv_info.from)?;
+        let odcid = if token.is_empty() {
+            None
+        } else {
+            budget.validated = true;
+
+            Some(Self::validate_token(token, &recv_info.from)?)
+        };
 
         let scid: quiche::ConnectionId<'_> = header.dcid.clone();
 
@@ -153,15 +357,19 @@ impl QuicAcceptor {
             ));
         }
 
+        self.config.select_cert(&buf[..write_size])?;
+
         let mut conn = quiche::accept(
             &scid,
-            Some(&odcid),
+            odcid.as_ref(),
             recv_info.to,
             recv_info.from,
             &mut self.config,
         )
         .map_err(into_io_error)?;
 
+        self.config.setup_conn_tracing(&mut conn)?;
+
         let write_size = conn
             .recv(&mut buf[..write_size], recv_info)
             .map_err(into_io_error)?;
@@ -173,20 +381,7 @@ impl QuicAcceptor {
             write_size,
         );
 
-        let (read_size, send_info) = match conn.send(buf) {
-            Ok(r) => r,
-            Err(quiche::Error::Done) => (
-                0,
-                SendInfo {
-                    from: recv_info.to,
-                    to: recv_info.from,
-                    at: Instant::now(),
-                },
-            ),
-            Err(err) => {
-                return Err(into_io_error(err));
-            }
-        };
+        let (read_size, send_info) = budget.send(&mut conn, buf, recv_info)?;
 
         if conn.is_established() {
             return Ok(QuicAcceptorHandshake::Incoming {
@@ -194,11 +389,35 @@ impl QuicAcceptor {
                 write_size,
                 read_size,
                 ping_timeout: self.config.ping_timeout,
+                stream_buffer: self.config.stream_buffer,
+                pmtud: self.config.pmtud,
                 send_info,
             });
         } else {
-            self.pre_established_conns
-                .insert(scid.clone().into_owned(), conn);
+            let scid = scid.into_owned();
+
+            let deadline = Instant::now() + self.config.handshake_timeout;
+
+            // Refuse the connection that would never be collected.
+            if self
+                .handshake_timers
+                .new_timer(scid.clone(), self.config.handshake_timeout)
+                .is_none()
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("Add handshake timer failed, scid={:?}", scid),
+                ));
+            }
+
+            self.pre_established_conns.insert(
+                scid,
+                HandshakingConn {
+                    conn,
+                    deadline,
+                    budget,
+                },
+            );
 
             return Ok(QuicAcceptorHandshake::Internal {
                 write_size,
@@ -338,7 +557,7 @@ pub enum QuicListenerWriteResult {
 
 enum QuicListnerConnRead {
     Err(QuicConnState, io::Error),
-    Ok(QuicConnState, BytesMut, SendInfo),
+    Ok(QuicConnState, PooledBuf, SendInfo),
 }
 
 /// The state machine for quic server listener.
@@ -354,21 +573,47 @@ pub struct QuicListenerState {
     mediator: Arc<EventMap<QuicListenerStateEvent>>,
     /// the batch processor for reading data from connections .
     conns_read: Arc<FutureBatcher<QuicListnerConnRead>>,
+    /// The datagrams read from connections, which are sent in the weighted fair order.
+    send_queue: Arc<SpinMutex<Wfq<ConnectionId<'static>, (PooledBuf, SendInfo)>>>,
+    /// The routing table shared by the listener shards.
+    router: ConnRouter,
+    /// The shard index of this listener state in `router`.
+    shard: usize,
 }
 
 impl QuicListenerState {
     /// Use [`config`](Config) to create new [`QuicListenerState`]
     pub fn new(config: Config) -> io::Result<Self> {
+        Self::with_router(config, ConnRouter::new(1), 0)
+    }
+
+    /// Create new [`QuicListenerState`] as the `shard` of `router`,
+    /// the incoming connections are registered to `router` until they are broken.
+    pub fn with_router(config: Config, router: ConnRouter, shard: usize) -> io::Result<Self> {
+        assert!(shard < router.shards(), "shard index out of range");
+
         Ok(Self {
             acceptor: Arc::new(AsyncSpinMutex::new(QuicAcceptor::new(config)?)),
             conns: Default::default(),
             incoming: Arc::new(AsyncSpinMutex::new(Some(Default::default()))),
             mediator: Default::default(),
             conns_read: Default::default(),
+            send_queue: Default::default(),
+            router,
+            shard,
         })
     }
 
+    /// Returns the shard index of this listener state.
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+
     /// Processes QUIC packets received from the peer.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(from = %recv_info.from))
+    )]
     pub async fn write(
         &self,
         buf: &mut [u8],
@@ -378,7 +623,11 @@ impl QuicListenerState {
         let handshake = {
             let mut acceptor = self.acceptor.lock().await;
 
-            acceptor.handshake(buf, write_size, recv_info)?
+            acceptor.collect_expired();
+
+            let accepting = acceptor.is_accepting(self.conns.len(), self.pending().await);
+
+            acceptor.handshake(buf, write_size, recv_info, accepting)?
         };
 
         match handshake {
@@ -401,6 +650,8 @@ impl QuicListenerState {
             QuicAcceptorHandshake::Incoming {
                 conn,
                 ping_timeout,
+                stream_buffer,
+                pmtud,
                 write_size,
                 read_size,
                 send_info,
@@ -413,10 +664,19 @@ impl QuicListenerState {
 
                 let scid = conn.source_id().clone().into_owned();
 
-                let conn = QuicConnState::new(conn, ping_timeout, 5);
+                let conn = QuicConnState::with_session_saver(
+                    conn,
+                    ping_timeout,
+                    stream_buffer,
+                    5,
+                    None,
+                    pmtud,
+                );
 
                 self.conns.insert(scid.clone(), conn.clone());
 
+                self.router.insert(&scid, self.shard);
+
                 self.incoming
                     .lock()
                     .await
@@ -453,15 +713,35 @@ impl QuicListenerState {
         }
     }
 
+    /// Drops the connections whose handshake timeout has expired, and returns the number of dropped connections.
+    ///
+    /// The expired connections are also collected by [`write`](Self::write), call this function periodically
+    /// to collect them when no packet is received.
+    pub async fn collect_expired_handshakes(&self) -> usize {
+        self.acceptor.lock().await.collect_expired()
+    }
+
+    /// Returns the number of connections that have not completed the handshake.
+    pub async fn handshaking(&self) -> usize {
+        self.acceptor.lock().await.handshaking()
+    }
+
+    /// Returns the number of handshaking connections whose client address has not been validated.
+    pub async fn unvalidated_handshaking(&self) -> usize {
+        self.acceptor.lock().await.unvalidated()
+    }
+
     fn batch_read(&self, conn: QuicConnState) {
         // push new task into batch poller.
         self.conns_read.push(async move {
-            let mut buf = ReadBuf::with_capacity(65535);
+            let mut buf = datagram_pool().get();
 
             // TODO: "handle conn closed"
-            match conn.read(buf.as_mut()).await {
+            match conn.read(&mut buf).await {
                 Ok((read_size, send_info)) => {
-                    QuicListnerConnRead::Ok(conn, buf.into_bytes_mut(Some(read_size)), send_info)
+                    buf.truncate(read_size);
+
+                    QuicListnerConnRead::Ok(conn, buf, send_info)
                 }
                 Err(err) => QuicListnerConnRead::Err(conn, err),
             }
@@ -478,6 +758,16 @@ impl QuicListenerState {
             .notify_one(QuicListenerStateEvent::Incoming, event_map::Reason::On);
     }
 
+    /// Returns the number of established connections waiting to be accepted.
+    pub async fn pending(&self) -> usize {
+        self.incoming
+            .lock()
+            .await
+            .as_ref()
+            .map(|incoming| incoming.len())
+            .unwrap_or(0)
+    }
+
     /// Accept one incoming connection, or returns `None` if this listener had been closed.
     pub async fn accept(&self) -> Option<QuicConnState> {
         loop {
@@ -499,8 +789,45 @@ impl QuicListenerState {
         }
     }
 
-    pub async fn read(&self) -> io::Result<(BytesMut, SendInfo)> {
-        match self.conns_read.wait().await {
+    /// Returns a stream of incoming connections, which ends once this listener had been closed.
+    pub fn incoming(&self) -> impl Stream<Item = QuicConnState> + '_ {
+        stream::unfold(self, |listener| async move {
+            listener.accept().await.map(|conn| (conn, listener))
+        })
+    }
+
+    /// Reads the next datagram to send from the connections.
+    ///
+    /// The datagrams are scheduled by deficit round robin with the connection
+    /// [`weight`](QuicConnState::weight)s, so a busy connection can't delay the others' datagrams.
+    pub async fn read(&self) -> io::Result<(PooledBuf, SendInfo)> {
+        loop {
+            // Queues all the read datagrams, so the scheduler chooses among all the ready connections.
+            while let Some(conn_read) = self.conns_read.try_wait() {
+                self.handle_conn_read(conn_read)?;
+            }
+
+            let next = self.send_queue.lock().pop();
+
+            if let Some(((buf, send_info), resumed)) = next {
+                if let Some(scid) = resumed {
+                    // The queue of the connection is no longer full.
+                    if let Some(conn) = self.conns.get(&scid).map(|conn| conn.clone()) {
+                        self.batch_read(conn);
+                    }
+                }
+
+                return Ok((buf, send_info));
+            }
+
+            let conn_read = self.conns_read.wait().await;
+
+            self.handle_conn_read(conn_read)?;
+        }
+    }
+
+    fn handle_conn_read(&self, conn_read: QuicListnerConnRead) -> io::Result<()> {
+        match conn_read {
             QuicListnerConnRead::Err(conn, err) => {
                 log::trace!(
                     "QuicListener, read data from conn error. scid={:?}, dcid={:?}, err={}",
@@ -511,14 +838,26 @@ impl QuicListenerState {
 
                 // remove broken conn
                 self.conns.remove(&conn.scid);
+                self.router.remove(&conn.scid);
 
-                return Err(err);
+                Err(err)
             }
             QuicListnerConnRead::Ok(conn, buf, send_info) => {
-                // push conn into batch poller again.
-                self.batch_read(conn);
+                let len = buf.len();
 
-                return Ok((buf, send_info));
+                let reading = self.send_queue.lock().push(
+                    conn.scid.clone(),
+                    conn.weight(),
+                    (buf, send_info),
+                    len,
+                );
+
+                // push conn into batch poller again, unless its queue is full.
+                if reading {
+                    self.batch_read(conn);
+                }
+
+                Ok(())
             }
         }
     }
@@ -1,10 +1,18 @@
 mod conn;
 mod connector;
+mod events;
 mod listener;
+mod pmtud;
+mod router;
+mod wfq;
 
 pub use conn::*;
 pub use connector::*;
+pub use events::*;
 pub use listener::*;
+pub use pmtud::{BASE_PLPMTU, DEFAULT_MAX_PLPMTU};
+pub use router::*;
+pub use wfq::{DEFAULT_WEIGHT, MAX_WEIGHT};
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,353 @@
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+/// The base PLPMTU, which is the minimum datagram size supported by all QUIC paths (RFC 9000 section 14).
+pub const BASE_PLPMTU: usize = 1200;
+
+/// The default upper bound of the search, the udp payload of ethernet MTU over IPv6.
+pub const DEFAULT_MAX_PLPMTU: usize = 1452;
+
+/// The number of lost probes of one size before the size is considered unsupported (RFC 8899 MAX_PROBES).
+const MAX_PROBES: usize = 3;
+
+/// The search is completed once the gap between the confirmed size and the search upper bound
+/// is less than this value.
+const SEARCH_STEP: usize = 16;
+
+/// The interval of restarting the completed search, to discover a larger PMTU (RFC 8899 PMTU_RAISE_TIMER).
+const PMTU_RAISE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// The max ack delay added to the probe timeout.
+const MAX_ACK_DELAY: Duration = Duration::from_millis(25);
+
+/// The snapshot of the active path, the input of [`Pmtud`].
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PathInfo {
+    pub(super) peer_addr: SocketAddr,
+    /// The number of packets lost on the path.
+    pub(super) lost: usize,
+    pub(super) rtt: Duration,
+    pub(super) rttvar: Duration,
+    /// The max udp payload size allowed by the local config and the peer's transport parameter.
+    pub(super) max_payload_size: usize,
+}
+
+impl PathInfo {
+    /// Returns the active path of `conn`, `None` if the connection is not established.
+    pub(super) fn from_conn(conn: &quiche::Connection) -> Option<Self> {
+        if !conn.is_established() {
+            return None;
+        }
+
+        let max_payload_size = conn.max_send_udp_payload_size();
+
+        conn.path_stats()
+            .find(|stats| stats.active)
+            .map(|stats| PathInfo {
+                peer_addr: stats.peer_addr,
+                lost: stats.lost,
+                rtt: stats.rtt,
+                rttvar: stats.rttvar,
+                max_payload_size,
+            })
+    }
+
+    /// The probe is confirmed if it is not lost in three PTOs.
+    fn probe_timeout(&self) -> Duration {
+        (self.rtt + self.rttvar * 4 + MAX_ACK_DELAY) * 3
+    }
+}
+
+/// The probe packet in flight.
+#[derive(Debug)]
+struct Probe {
+    size: usize,
+    sent: Instant,
+    /// The lost packets of the path when the probe was sent.
+    lost: usize,
+}
+
+/// Datagram packetization layer path MTU discovery of one connection (RFC 8899).
+///
+/// quiche sizes the packet by the length of the output buffer, so the send pump limits the buffer to
+/// [`mtu`](Self::mtu), and periodically lets one packet grow to the probe size. The probe carries the
+/// pending stream data, which is retransmitted in smaller packets by quiche if the probe is lost.
+///
+/// quiche doesn't report the acknowledgement of one packet, so the probe is confirmed if no packet
+/// of the path is lost before the probe timeout, and any loss fails the probe, which only slows down
+/// the search.
+#[derive(Debug)]
+pub(super) struct Pmtud {
+    /// The confirmed PLPMTU.
+    mtu: usize,
+    /// The upper bound of the search.
+    search_high: usize,
+    /// The first probe tries `search_high` directly, the later probes search the middle size.
+    high_probed: bool,
+    /// The current probe size, which is retried until it's confirmed or lost `MAX_PROBES` times.
+    target: Option<usize>,
+    /// The number of lost probes of the current probe size.
+    probe_count: usize,
+    probe: Option<Probe>,
+    /// The time the search was completed.
+    completed: Option<Instant>,
+    /// The peer address of the path the PLPMTU belongs to.
+    peer_addr: Option<SocketAddr>,
+}
+
+impl Default for Pmtud {
+    fn default() -> Self {
+        Self {
+            mtu: BASE_PLPMTU,
+            search_high: BASE_PLPMTU,
+            high_probed: false,
+            target: None,
+            probe_count: 0,
+            probe: None,
+            completed: None,
+            peer_addr: None,
+        }
+    }
+}
+
+impl Pmtud {
+    /// Returns the confirmed PLPMTU.
+    pub(super) fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Returns the max size of the next packet, which is the probe size if a probe is due.
+    pub(super) fn send_size(&mut self, path: Option<PathInfo>, now: Instant) -> usize {
+        let Some(path) = path else {
+            return self.mtu;
+        };
+
+        self.update(path, now);
+
+        if self.probe.is_some() {
+            return self.mtu;
+        }
+
+        match self.completed {
+            Some(completed) if now.duration_since(completed) >= PMTU_RAISE_INTERVAL => {
+                self.restart(path.max_payload_size, now);
+            }
+            Some(_) => return self.mtu,
+            None => {}
+        }
+
+        if let Some(target) = self.target {
+            return target;
+        }
+
+        let target = if self.high_probed {
+            // Rounds up, so the probe is always larger than `mtu`.
+            (self.mtu + self.search_high).div_ceil(2)
+        } else {
+            self.high_probed = true;
+            self.search_high
+        };
+
+        self.target = Some(target);
+
+        target
+    }
+
+    /// Records the sent packet, which is a probe if it is larger than the confirmed PLPMTU.
+    pub(super) fn on_sent(&mut self, path: Option<PathInfo>, size: usize, now: Instant) {
+        let Some(path) = path else {
+            return;
+        };
+
+        if size > self.mtu && self.probe.is_none() {
+            log::trace!("pmtud probe, mtu={}, probe={}", self.mtu, size);
+
+            self.probe = Some(Probe {
+                size,
+                sent: now,
+                lost: path.lost,
+            });
+        }
+    }
+
+    /// Resolves the probe in flight and tracks the path changes.
+    pub(super) fn update(&mut self, path: PathInfo, now: Instant) {
+        if self.peer_addr != Some(path.peer_addr) {
+            // The PLPMTU of the new path is unknown.
+            *self = Self {
+                peer_addr: Some(path.peer_addr),
+                ..Default::default()
+            };
+
+            self.restart(path.max_payload_size, now);
+        }
+
+        // The peer may lower the limit by its transport parameter.
+        if path.max_payload_size < self.search_high {
+            self.search_high = path.max_payload_size.max(BASE_PLPMTU);
+            self.mtu = self.mtu.min(self.search_high);
+
+            if self.target.is_some_and(|target| target > self.search_high) {
+                self.target = None;
+                self.probe_count = 0;
+            }
+        }
+
+        let Some(probe) = &self.probe else {
+            return;
+        };
+
+        if path.lost > probe.lost {
+            log::trace!("pmtud probe lost, mtu={}, probe={}", self.mtu, probe.size);
+
+            self.probe_count += 1;
+
+            if self.probe_count >= MAX_PROBES {
+                self.search_high = self.target.take().unwrap_or(probe.size) - 1;
+                self.probe_count = 0;
+            }
+        } else if now.duration_since(probe.sent) >= path.probe_timeout() {
+            log::trace!("pmtud probe confirmed, probe={}", probe.size);
+
+            self.mtu = self.mtu.max(probe.size);
+
+            // The probe may be smaller than the target, if there was not enough data to send.
+            if self.target.is_some_and(|target| target <= self.mtu) {
+                self.target = None;
+                self.probe_count = 0;
+            }
+        } else {
+            return;
+        }
+
+        self.probe = None;
+
+        if self.search_high < self.mtu + SEARCH_STEP {
+            log::trace!("pmtud search completed, mtu={}", self.mtu);
+
+            self.completed = Some(now);
+        }
+    }
+
+    fn restart(&mut self, max_payload_size: usize, now: Instant) {
+        self.search_high = max_payload_size.max(BASE_PLPMTU);
+        self.high_probed = false;
+        self.target = None;
+        self.probe_count = 0;
+        self.completed = if self.search_high < self.mtu + SEARCH_STEP {
+            Some(now)
+        } else {
+            None
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(lost: usize, max_payload_size: usize) -> Option<PathInfo> {
+        Some(PathInfo {
+            peer_addr: "127.0.0.1:443".parse().unwrap(),
+            lost,
+            rtt: Duration::from_millis(10),
+            rttvar: Duration::from_millis(5),
+            max_payload_size,
+        })
+    }
+
+    #[test]
+    fn test_pmtud_search() {
+        let mut pmtud = Pmtud::default();
+
+        let now = Instant::now();
+
+        // Not established.
+        assert_eq!(pmtud.send_size(None, now), BASE_PLPMTU);
+
+        // Probes the upper bound first.
+        assert_eq!(pmtud.send_size(path(0, 1500), now), 1500);
+
+        // The sent packet is smaller than the probe size, e.g. no enough data.
+        pmtud.on_sent(path(0, 1500), 100, now);
+
+        assert_eq!(pmtud.send_size(path(0, 1500), now), 1500);
+
+        // Lost `MAX_PROBES` times.
+        for lost in 0..MAX_PROBES {
+            pmtud.on_sent(path(lost, 1500), 1500, now);
+
+            assert_eq!(pmtud.send_size(path(lost, 1500), now), BASE_PLPMTU);
+
+            // Retries the same size until lost `MAX_PROBES` times, then searches the middle size.
+            let expected = if lost + 1 < MAX_PROBES { 1500 } else { 1350 };
+
+            assert_eq!(pmtud.send_size(path(lost + 1, 1500), now), expected);
+        }
+
+        // The middle size is confirmed after the probe timeout.
+        pmtud.on_sent(path(3, 1500), 1350, now);
+
+        let later = now + Duration::from_secs(1);
+
+        assert_eq!(pmtud.send_size(path(3, 1500), later), 1425);
+        assert_eq!(pmtud.mtu(), 1350);
+
+        pmtud.on_sent(path(3, 1500), 1425, later);
+
+        let later = later + Duration::from_secs(1);
+
+        assert_eq!(pmtud.send_size(path(3, 1500), later), 1462);
+        assert_eq!(pmtud.mtu(), 1425);
+
+        for lost in 3..3 + MAX_PROBES {
+            pmtud.on_sent(path(lost, 1500), 1462, later);
+            pmtud.send_size(path(lost + 1, 1500), later);
+        }
+
+        assert_eq!(pmtud.send_size(path(6, 1500), later), 1443);
+
+        pmtud.on_sent(path(6, 1500), 1443, later);
+
+        let later = later + Duration::from_secs(1);
+
+        assert_eq!(pmtud.send_size(path(6, 1500), later), 1452);
+
+        pmtud.on_sent(path(6, 1500), 1452, later);
+
+        let later = later + Duration::from_secs(1);
+
+        // The search is completed, 1461 - 1452 < SEARCH_STEP.
+        assert_eq!(pmtud.send_size(path(6, 1500), later), 1452);
+        assert_eq!(pmtud.mtu(), 1452);
+
+        // Restarts after the raise interval.
+        let later = later + PMTU_RAISE_INTERVAL;
+
+        assert_eq!(pmtud.send_size(path(6, 1500), later), 1500);
+    }
+
+    #[test]
+    fn test_pmtud_path_change() {
+        let mut pmtud = Pmtud::default();
+
+        let now = Instant::now() + Duration::from_secs(1);
+
+        assert_eq!(pmtud.send_size(path(0, 1350), now), 1350);
+
+        pmtud.on_sent(path(0, 1350), 1350, now - Duration::from_secs(1));
+
+        assert_eq!(pmtud.send_size(path(0, 1350), now), 1350);
+        assert_eq!(pmtud.mtu(), 1350);
+
+        let mut migrated = path(0, 1350).unwrap();
+
+        migrated.peer_addr = "127.0.0.2:443".parse().unwrap();
+
+        // The PLPMTU of the new path is unknown.
+        assert_eq!(pmtud.send_size(Some(migrated), now), 1350);
+        assert_eq!(pmtud.mtu(), BASE_PLPMTU);
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    io,
+    sync::Arc,
+};
+
+use dashmap::DashMap;
+use quiche::ConnectionId;
+
+use crate::errors::into_io_error;
+
+/// The routing table which dispatches quic packets to the worker shards by destination connection id.
+///
+/// The connection ids registered by [`insert`](Self::insert) are routed to the registered shard,
+/// other connection ids are routed by hashing, so the handshake packets of one connection
+/// (whose DCID is chosen by the client and then by the server's retry) always land on the same shard.
+///
+/// Cloning this router shares the same routing table.
+#[derive(Clone)]
+pub struct ConnRouter {
+    shards: usize,
+    routes: Arc<DashMap<Vec<u8>, usize>>,
+}
+
+impl Debug for ConnRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ConnRouter(shards={}, routes={})",
+            self.shards,
+            self.routes.len()
+        )
+    }
+}
+
+impl ConnRouter {
+    /// Create new router for `shards` workers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn new(shards: usize) -> Self {
+        assert!(shards > 0, "the number of shards must be greater than zero");
+
+        Self {
+            shards,
+            routes: Default::default(),
+        }
+    }
+
+    /// Returns the number of worker shards.
+    pub fn shards(&self) -> usize {
+        self.shards
+    }
+
+    /// Returns the number of registered connection ids.
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Returns true if there is no registered connection id.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Register the connection id `dcid` owned by `shard`.
+    pub fn insert(&self, dcid: &ConnectionId<'_>, shard: usize) {
+        assert!(shard < self.shards, "shard index out of range");
+
+        self.routes.insert(dcid.to_vec(), shard);
+    }
+
+    /// Remove the registered connection id `dcid`.
+    pub fn remove(&self, dcid: &ConnectionId<'_>) {
+        self.routes.remove(dcid.as_ref());
+    }
+
+    /// Returns the shard which owns the connection id `dcid`.
+    pub fn route(&self, dcid: &ConnectionId<'_>) -> usize {
+        if self.shards == 1 {
+            return 0;
+        }
+
+        if let Some(shard) = self.routes.get(dcid.as_ref()) {
+            return *shard;
+        }
+
+        let mut hasher = DefaultHasher::new();
+
+        dcid.as_ref().hash(&mut hasher);
+
+        (hasher.finish() % self.shards as u64) as usize
+    }
+
+    /// Parses the header of quic packet `buf` and returns the shard which owns its destination connection id.
+    pub fn route_packet(&self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.shards == 1 {
+            return Ok(0);
+        }
+
+        let header =
+            quiche::Header::from_slice(buf, quiche::MAX_CONN_ID_LEN).map_err(into_io_error)?;
+
+        Ok(self.route(&header.dcid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        let router = ConnRouter::new(4);
+
+        let dcid = ConnectionId::from_vec(vec![1; quiche::MAX_CONN_ID_LEN]);
+
+        // The unregistered id is routed by hashing, which is stable.
+        let shard = router.route(&dcid);
+
+        assert!(shard < 4);
+        assert_eq!(router.route(&dcid), shard);
+
+        router.insert(&dcid, (shard + 1) % 4);
+
+        assert_eq!(router.route(&dcid), (shard + 1) % 4);
+
+        router.remove(&dcid);
+
+        assert_eq!(router.route(&dcid), shard);
+        assert!(router.is_empty());
+    }
+}
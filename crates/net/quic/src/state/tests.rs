@@ -1,13 +1,26 @@
-use futures::FutureExt;
-use futures_test::task::noop_context;
+use futures::{FutureExt, StreamExt};
+use futures_test::task::{new_count_waker, noop_context};
 use hala_future::poll_once;
-use hala_io::test::io_test;
+use hala_io::{sleep, test::io_test};
 use quiche::RecvInfo;
-use std::{io, task::Poll};
-
-use crate::mock_config;
-
-use super::{QuicConnState, QuicConnectorState, QuicListenerState, QuicListenerWriteResult};
+use std::{
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use crate::{
+    errors::{stream_error_code, CloseReason},
+    is_uni_stream, mock_config, CertResolver, CertifiedKey, Config, MemorySessionStore,
+    QuicIncoming, QuicSendStream, QuicStream, SessionKey, SessionStore,
+};
+
+use super::{
+    QuicConnState, QuicConnectorState, QuicListenerState, QuicListenerWriteResult,
+    HANDSHAKE_TIMER_TICK,
+};
 
 struct MockQuic {
     #[allow(dead_code)]
@@ -22,14 +35,20 @@ const MAX_DATAGRAM_SIZE: usize = 1350;
 
 impl MockQuic {
     async fn new() -> MockQuic {
+        Self::with_config(
+            mock_config(false, MAX_DATAGRAM_SIZE),
+            mock_config(true, MAX_DATAGRAM_SIZE),
+        )
+        .await
+    }
+
+    async fn with_config(mut client_config: Config, server_config: Config) -> MockQuic {
         let laddr = "127.0.0.1:1812".parse().unwrap();
         let raddr = "127.0.0.1:1813".parse().unwrap();
 
-        let mut connector =
-            QuicConnectorState::new(&mut mock_config(false, MAX_DATAGRAM_SIZE), laddr, raddr)
-                .unwrap();
+        let mut connector = QuicConnectorState::new(&mut client_config, laddr, raddr).unwrap();
 
-        let listener = QuicListenerState::new(mock_config(true, MAX_DATAGRAM_SIZE)).unwrap();
+        let listener = QuicListenerState::new(server_config).unwrap();
 
         let mut buf = vec![0; 65535];
 
@@ -262,6 +281,78 @@ async fn test_max_stream_data() {
     assert_eq!(result, Poll::Pending);
 }
 
+#[hala_test::test(io_test)]
+async fn test_stream_send_capacity() {
+    let mock = MockQuic::new().await;
+
+    let stream_id = mock.client.open_stream().await.unwrap();
+
+    // Leaves 100 bytes of the stream window.
+    let send_buf = &[0; MAX_DATAGRAM_SIZE * 10 - 100];
+
+    let result =
+        poll_once!(mock.client.stream_send(stream_id, send_buf, false)).map(|len| len.expect(""));
+
+    assert_eq!(result, Poll::Ready(send_buf.len()));
+
+    // The capacity is below the low-watermark of the requested write.
+    let result =
+        poll_once!(mock.client.stream_send(stream_id, &[0; 1000], false)).map(|len| len.expect(""));
+
+    assert_eq!(result, Poll::Pending);
+
+    let result =
+        poll_once!(mock.client.stream_send(stream_id, &[0; 100], false)).map(|len| len.expect(""));
+
+    assert_eq!(result, Poll::Ready(100));
+}
+
+#[hala_test::test(io_test)]
+async fn test_stream_send_small_window() {
+    let mut server_config = mock_config(true, MAX_DATAGRAM_SIZE);
+
+    // The stream window stays below one datagram.
+    server_config.set_initial_max_stream_data_bidi_remote(500);
+
+    let mut mock =
+        MockQuic::with_config(mock_config(false, MAX_DATAGRAM_SIZE), server_config).await;
+
+    let stream_id = mock.client.open_stream().await.unwrap();
+
+    let send_buf = &[0; MAX_DATAGRAM_SIZE];
+
+    // The low-watermark is clamped to the window, so the write is partial instead of suspended.
+    let result =
+        poll_once!(mock.client.stream_send(stream_id, send_buf, false)).map(|len| len.expect(""));
+
+    assert_eq!(result, Poll::Ready(500));
+
+    let result =
+        poll_once!(mock.client.stream_send(stream_id, send_buf, false)).map(|len| len.expect(""));
+
+    assert_eq!(result, Poll::Pending);
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock.server_conn.as_ref().unwrap();
+
+    assert_eq!(server_conn.accept().await, Some(stream_id));
+
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+    let (read_size, _) = server_conn.stream_recv(stream_id, &mut buf).await.unwrap();
+
+    assert_eq!(read_size, 500);
+
+    // The window is extended once the data is read.
+    mock.send_to_client().await.unwrap();
+
+    let result =
+        poll_once!(mock.client.stream_send(stream_id, send_buf, false)).map(|len| len.expect(""));
+
+    assert_eq!(result, Poll::Ready(500));
+}
+
 #[hala_test::test(io_test)]
 async fn test_server_stream_accept() {
     let send_data = b"hello";
@@ -313,6 +404,116 @@ async fn test_server_stream_accept() {
     assert_eq!(&buf[..read_size], send_data);
 }
 
+#[hala_test::test(io_test)]
+async fn test_poll_stream_recv() {
+    let mut mock = MockQuic::new().await;
+
+    let stream_id = mock.client.open_stream().await.unwrap();
+
+    mock.client
+        .stream_send(stream_id, b"hello", false)
+        .await
+        .unwrap();
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    assert_eq!(server_conn.accept().await, Some(stream_id));
+
+    let (waker, count) = new_count_waker();
+
+    let mut buf = vec![0; 1024];
+
+    assert!(mock
+        .client
+        .poll_stream_recv(&mut Context::from_waker(&waker), stream_id, &mut buf)
+        .is_pending());
+
+    server_conn
+        .stream_send(stream_id, b"world", true)
+        .await
+        .unwrap();
+
+    mock.send_to_client().await.unwrap();
+
+    assert_eq!(count.get(), 1);
+
+    let result = mock
+        .client
+        .poll_stream_recv(&mut Context::from_waker(&waker), stream_id, &mut buf)
+        .map(|r| r.unwrap());
+
+    assert_eq!(result, Poll::Ready((5, true)));
+    assert_eq!(&buf[..5], b"world");
+}
+
+#[hala_test::test(io_test)]
+async fn test_manual_flow_control() {
+    let mut mock = MockQuic::new().await;
+
+    let stream_id = mock.client.open_stream().await.unwrap();
+
+    mock.client
+        .stream_send(stream_id, b"hello world", false)
+        .await
+        .unwrap();
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    assert_eq!(server_conn.accept().await, Some(stream_id));
+
+    assert_eq!(server_conn.max_data().await, 10_000_000);
+
+    server_conn.stream_grant(stream_id, 5).await.unwrap_err();
+
+    server_conn.set_manual_flow_control(true).await;
+
+    let (waker, count) = new_count_waker();
+
+    let mut buf = vec![0; 1024];
+
+    // No window is granted.
+    assert!(server_conn
+        .poll_stream_recv(&mut Context::from_waker(&waker), stream_id, &mut buf)
+        .is_pending());
+
+    server_conn.stream_grant(stream_id, 5).await.unwrap();
+
+    assert_eq!(count.get(), 1);
+
+    assert_eq!(
+        server_conn.stream_recv(stream_id, &mut buf).await.unwrap(),
+        (5, false)
+    );
+
+    assert_eq!(&buf[..5], b"hello");
+
+    let flow_control = server_conn.stream_flow_control(stream_id).await;
+
+    assert_eq!(flow_control.consumed, 5);
+    assert_eq!(flow_control.granted, Some(0));
+    assert_eq!(server_conn.data_consumed().await, 5);
+
+    // Disabling the manual flow control reads the rest data.
+    server_conn.set_manual_flow_control(false).await;
+
+    assert_eq!(
+        server_conn.stream_recv(stream_id, &mut buf).await.unwrap(),
+        (6, false)
+    );
+
+    assert_eq!(&buf[..6], b" world");
+
+    let flow_control = server_conn.stream_flow_control(stream_id).await;
+
+    assert_eq!(flow_control.consumed, 11);
+    assert_eq!(flow_control.granted, None);
+    assert_eq!(server_conn.data_consumed().await, 11);
+}
+
 #[hala_test::test(io_test)]
 async fn test_client_stream_accept() {
     let mut mock = MockQuic::new().await;
@@ -375,7 +576,7 @@ async fn test_stream_stopped() {
 
     let server_conn = mock
         .server_conn
-        .as_ref()
+        .clone()
         .expect("Server connection established");
 
     assert_eq!(
@@ -383,14 +584,74 @@ async fn test_stream_stopped() {
         stream_id
     );
 
-    server_conn.stream_shutdown(stream_id, 1).await.unwrap();
+    server_conn
+        .stream_shutdown(stream_id, quiche::Shutdown::Read, 1)
+        .await
+        .unwrap();
 
     mock.send_to_client().await.unwrap();
 
-    mock.client
+    let err = mock
+        .client
         .stream_send(stream_id, b"hello", true)
         .await
         .expect_err("Server shutdown stream");
+
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    assert_eq!(stream_error_code(&err), Some(1));
+
+    // The local reading side is shut down.
+    let err = server_conn
+        .stream_recv(stream_id, &mut [0; 10])
+        .await
+        .expect_err("Stream stopped");
+
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    assert_eq!(stream_error_code(&err), None);
+}
+
+#[hala_test::test(io_test)]
+async fn test_stream_reset() {
+    let mut mock = MockQuic::new().await;
+
+    let stream_id = mock.client.open_stream().await.unwrap();
+
+    mock.client
+        .stream_send(stream_id, b"hello", false)
+        .await
+        .unwrap();
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock
+        .server_conn
+        .clone()
+        .expect("Server connection established");
+
+    assert_eq!(
+        server_conn.accept().await.expect("New incoming stream"),
+        stream_id
+    );
+
+    mock.client
+        .stream_shutdown(stream_id, quiche::Shutdown::Write, 7)
+        .await
+        .unwrap();
+
+    mock.client
+        .stream_send(stream_id, b"hello", true)
+        .await
+        .expect_err("Stream reset");
+
+    mock.send_to_server().await.unwrap();
+
+    let err = server_conn
+        .stream_recv(stream_id, &mut [0; 10])
+        .await
+        .expect_err("Client reset stream");
+
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    assert_eq!(stream_error_code(&err), Some(7));
 }
 
 #[hala_test::test(io_test)]
@@ -449,3 +710,622 @@ async fn test_server_max_open_streams() {
         .await
         .expect_err("Stream limits");
 }
+
+#[hala_test::test(io_test)]
+async fn test_listener_max_incoming_conns() {
+    let laddr = "127.0.0.1:1812".parse().unwrap();
+    let raddr = "127.0.0.1:1813".parse().unwrap();
+
+    let mut connector =
+        QuicConnectorState::new(&mut mock_config(false, MAX_DATAGRAM_SIZE), laddr, raddr).unwrap();
+
+    let mut config = mock_config(true, MAX_DATAGRAM_SIZE);
+
+    config.set_max_incoming_conns(0);
+
+    let listener = QuicListenerState::new(config).unwrap();
+
+    let mut buf = vec![0; 65535];
+
+    let (send_size, send_info) = connector.send(&mut buf).unwrap().unwrap();
+
+    let err = listener
+        .write(
+            &mut buf,
+            send_size,
+            RecvInfo {
+                from: send_info.from,
+                to: send_info.to,
+            },
+        )
+        .await
+        .err()
+        .expect("Max incoming conns");
+
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+
+    assert_eq!(listener.pending().await, 0);
+}
+
+#[hala_test::test(io_test)]
+async fn test_listener_handshake_timeout() {
+    let laddr = "127.0.0.1:1812".parse().unwrap();
+    let raddr = "127.0.0.1:1813".parse().unwrap();
+
+    let mut connector =
+        QuicConnectorState::new(&mut mock_config(false, MAX_DATAGRAM_SIZE), laddr, raddr).unwrap();
+
+    let mut config = mock_config(true, MAX_DATAGRAM_SIZE);
+
+    config.set_handshake_timeout(Duration::from_millis(100));
+
+    let listener = QuicListenerState::new(config).unwrap();
+
+    let mut buf = vec![0; 65535];
+
+    // The client vanishes once the server has created the handshaking conn.
+    while listener.handshaking().await == 0 {
+        let (send_size, send_info) = connector.send(&mut buf).unwrap().unwrap();
+
+        let QuicListenerWriteResult::Internal {
+            read_size,
+            send_info,
+            ..
+        } = listener
+            .write(
+                &mut buf,
+                send_size,
+                RecvInfo {
+                    from: send_info.from,
+                    to: send_info.to,
+                },
+            )
+            .await
+            .unwrap()
+        else {
+            panic!("not here");
+        };
+
+        connector
+            .recv(
+                &mut buf[..read_size],
+                RecvInfo {
+                    from: send_info.from,
+                    to: send_info.to,
+                },
+            )
+            .unwrap();
+    }
+
+    assert_eq!(listener.collect_expired_handshakes().await, 0);
+
+    sleep(Duration::from_millis(100) + HANDSHAKE_TIMER_TICK * 2)
+        .await
+        .unwrap();
+
+    assert_eq!(listener.collect_expired_handshakes().await, 1);
+
+    assert_eq!(listener.handshaking().await, 0);
+}
+
+#[hala_test::test(io_test)]
+async fn test_listener_anti_amplification() {
+    let laddr = "127.0.0.1:1812".parse().unwrap();
+    let raddr = "127.0.0.1:1813".parse().unwrap();
+
+    let mut connector =
+        QuicConnectorState::new(&mut mock_config(false, MAX_DATAGRAM_SIZE), laddr, raddr).unwrap();
+
+    let mut config = mock_config(true, MAX_DATAGRAM_SIZE);
+
+    config.set_stateless_retry(false);
+
+    let listener = QuicListenerState::new(config).unwrap();
+
+    let mut buf = vec![0; 65535];
+
+    let (send_size, send_info) = connector.send(&mut buf).unwrap().unwrap();
+
+    let QuicListenerWriteResult::Internal { read_size, .. } = listener
+        .write(
+            &mut buf,
+            send_size,
+            RecvInfo {
+                from: send_info.from,
+                to: send_info.to,
+            },
+        )
+        .await
+        .unwrap()
+    else {
+        panic!("not here");
+    };
+
+    assert!(read_size <= send_size * 3);
+    assert_eq!(listener.unvalidated_handshaking().await, 1);
+
+    let mut server_config = mock_config(true, MAX_DATAGRAM_SIZE);
+
+    server_config.set_stateless_retry(false);
+
+    let mock = MockQuic::with_config(mock_config(false, MAX_DATAGRAM_SIZE), server_config).await;
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    assert!(server_conn.is_address_validated().await);
+    assert!(mock.client.is_address_validated().await);
+    assert_eq!(mock.listener.unvalidated_handshaking().await, 0);
+}
+
+#[hala_test::test(io_test)]
+async fn test_stream_split() {
+    let mut mock = MockQuic::new().await;
+
+    let stream_id = mock.client.open_stream().await.unwrap();
+
+    let (read_half, write_half) = QuicStream::new(mock.client.clone(), stream_id).split();
+
+    write_half.send(b"hello", false).await.unwrap();
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    let server_stream_id = server_conn.accept().await.unwrap();
+
+    let (server_read_half, server_write_half) =
+        QuicStream::new(server_conn, server_stream_id).split();
+
+    let mut buf = vec![0; 1024];
+
+    let (read_size, fin) = server_read_half.recv(&mut buf).await.unwrap();
+
+    assert_eq!(&buf[..read_size], b"hello");
+    assert!(!fin);
+
+    server_write_half.send(b"world", true).await.unwrap();
+
+    mock.send_to_client().await.unwrap();
+
+    let (read_size, fin) = read_half.clone().recv(&mut buf).await.unwrap();
+
+    assert_eq!(&buf[..read_size], b"world");
+    assert!(fin);
+
+    let err = read_half.reunite(server_write_half).unwrap_err();
+
+    let stream = err.0.reunite(write_half).unwrap();
+
+    assert_eq!(stream.id(), stream_id);
+}
+
+#[hala_test::test(io_test)]
+async fn test_uni_stream() {
+    let mut mock = MockQuic::new().await;
+
+    let stream_id = mock.client.open_uni_stream().await.unwrap();
+
+    assert!(is_uni_stream(stream_id));
+
+    let stream = QuicSendStream::new(mock.client.clone(), stream_id);
+
+    stream.send(b"hello", true).await.unwrap();
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    let server_stream_id = server_conn.accept().await.unwrap();
+
+    let server_stream = match QuicIncoming::new(server_conn, server_stream_id) {
+        QuicIncoming::Uni(stream) => stream,
+        QuicIncoming::Bidi(stream) => panic!("expect uni stream, but got {:?}", stream),
+    };
+
+    assert_eq!(server_stream.id(), stream_id);
+
+    let mut buf = vec![0; 1024];
+
+    let (read_size, fin) = server_stream.recv(&mut buf).await.unwrap();
+
+    assert_eq!(&buf[..read_size], b"hello");
+    assert!(fin);
+}
+
+#[hala_test::test(io_test)]
+async fn test_stream_write_buffer() {
+    use futures::AsyncWriteExt;
+
+    let mut mock = MockQuic::new().await;
+
+    let mut stream = QuicStream::new(
+        mock.client.clone(),
+        mock.client.open_stream().await.unwrap(),
+    );
+
+    let stream_buffer = mock.client.stream_buffer();
+
+    // The data exceeding the high-watermark is not accepted.
+    assert_eq!(
+        stream.write(&vec![0; stream_buffer * 2]).await.unwrap(),
+        stream_buffer
+    );
+
+    AsyncWriteExt::write_all(&mut stream, b"hello")
+        .await
+        .unwrap();
+    AsyncWriteExt::write_all(&mut stream, b" world")
+        .await
+        .unwrap();
+
+    AsyncWriteExt::close(&mut stream).await.unwrap();
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    let mut buf = vec![0; stream_buffer * 2];
+
+    let mut received = vec![];
+
+    loop {
+        let (read_size, fin) = server_conn
+            .stream_recv(stream.id(), &mut buf)
+            .await
+            .unwrap();
+
+        received.extend_from_slice(&buf[..read_size]);
+
+        if fin {
+            break;
+        }
+    }
+
+    assert_eq!(received.len(), stream_buffer + 11);
+    assert_eq!(&received[stream_buffer..], b"hello world");
+}
+
+#[hala_test::test(io_test)]
+async fn test_stream_drop_flush() {
+    use futures::AsyncWriteExt;
+
+    let mut mock = MockQuic::new().await;
+
+    let stream_id = mock.client.open_stream().await.unwrap();
+
+    let mut stream = QuicStream::new(mock.client.clone(), stream_id);
+
+    // The data is buffered, and flushed by the connection after the stream is dropped.
+    AsyncWriteExt::write_all(&mut stream, b"hello world")
+        .await
+        .unwrap();
+
+    drop(stream);
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    let mut buf = vec![0; 1024];
+
+    let (read_size, fin) = server_conn.stream_recv(stream_id, &mut buf).await.unwrap();
+
+    assert_eq!(&buf[..read_size], b"hello world");
+    assert!(fin);
+}
+
+#[hala_test::test(io_test)]
+async fn test_stream_priority() {
+    let mut mock = MockQuic::new().await;
+
+    let bulk = QuicStream::new(
+        mock.client.clone(),
+        mock.client.open_stream().await.unwrap(),
+    );
+    let urgent = QuicStream::new(
+        mock.client.clone(),
+        mock.client.open_stream().await.unwrap(),
+    );
+
+    bulk.set_priority(200, true).await.unwrap();
+    urgent.set_priority(0, false).await.unwrap();
+
+    // Each stream needs several datagrams, so the sending order can be observed.
+    let bulk_data = vec![1; MAX_DATAGRAM_SIZE * 3];
+    let urgent_data = vec![2; MAX_DATAGRAM_SIZE * 3];
+
+    // The bulk data is written first.
+    assert_eq!(bulk.send(&bulk_data, true).await.unwrap(), bulk_data.len());
+    assert_eq!(
+        urgent.send(&urgent_data, true).await.unwrap(),
+        urgent_data.len()
+    );
+
+    let mut buf = vec![0; 65535];
+
+    let (mut bulk_received, mut urgent_received) = (0, 0);
+
+    while bulk_received < bulk_data.len() {
+        mock.send_to_server().await.unwrap();
+
+        let server_conn = mock.server_conn.clone().unwrap();
+
+        if let Poll::Ready(Ok((read_size, _))) =
+            poll_once!(server_conn.stream_recv(urgent.id(), &mut buf))
+        {
+            urgent_received += read_size;
+        }
+
+        if let Poll::Ready(Ok((read_size, _))) =
+            poll_once!(server_conn.stream_recv(bulk.id(), &mut buf))
+        {
+            // The urgent stream is sent before the bulk stream.
+            assert_eq!(urgent_received, urgent_data.len());
+
+            bulk_received += read_size;
+        }
+    }
+
+    assert_eq!(bulk_received, bulk_data.len());
+}
+
+#[hala_test::test(io_test)]
+async fn test_stream_priority_stopped() {
+    let mut mock = MockQuic::new().await;
+
+    let stream_id = mock.client.open_stream().await.unwrap();
+
+    mock.client
+        .stream_priority(stream_id, 0, false)
+        .await
+        .unwrap();
+
+    mock.client
+        .stream_send(stream_id, b"hello", false)
+        .await
+        .unwrap();
+
+    assert_eq!(mock.client.stream_priorities_len().await, 1);
+
+    mock.send_to_server().await.unwrap();
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    assert_eq!(server_conn.accept().await.unwrap(), stream_id);
+
+    server_conn
+        .stream_shutdown(stream_id, quiche::Shutdown::Read, 1)
+        .await
+        .unwrap();
+
+    mock.send_to_client().await.unwrap();
+
+    // The priority of the stream stopped by the peer is removed.
+    assert_eq!(mock.client.stream_priorities_len().await, 0);
+}
+
+#[hala_test::test(io_test)]
+async fn test_listener_incoming() {
+    let mock = MockQuic::new().await;
+
+    let listener = mock.listener.clone();
+
+    let mut incoming = Box::pin(listener.incoming());
+
+    let conn = incoming.next().await.unwrap();
+
+    assert!(conn.is_established().await);
+
+    mock.listener.close().await;
+
+    assert!(incoming.next().await.is_none());
+}
+
+#[hala_test::test(io_test)]
+async fn test_handshake_info() {
+    let mock = MockQuic::new().await;
+
+    let info = mock.client.handshake_info().await;
+
+    assert_eq!(
+        info.alpn_protocol.as_deref(),
+        Some(b"hq-interop".as_slice())
+    );
+    assert_eq!(mock.client.alpn_protocol().await, info.alpn_protocol);
+    assert!(!info.peer_cert_chain.is_empty());
+    assert_eq!(
+        mock.client.peer_cert().await.as_ref(),
+        info.peer_cert_chain.first()
+    );
+    assert!(!info.is_resumed);
+
+    // the client doesn't provide a certificate.
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    assert_eq!(server_conn.peer_cert().await, None);
+    assert_eq!(server_conn.server_name().await, None);
+}
+
+#[hala_test::test(io_test)]
+async fn test_close_draining() {
+    let mut mock = MockQuic::new().await;
+
+    let client = mock.client.clone();
+
+    let mut closed = Box::pin(client.closed());
+
+    mock.client.close(true, 1, b"bye").await.unwrap();
+
+    // The connection is draining.
+    assert!(!mock.client.is_closed().await);
+    assert!(poll_once!(&mut closed).is_pending());
+
+    // Sends the `CONNECTION_CLOSE` frame.
+    mock.send_to_server().await.unwrap();
+
+    // The send loop returns error once the draining period elapses.
+    let mut buf = vec![0; 65535];
+
+    mock.client.read(&mut buf).await.unwrap_err();
+
+    assert!(mock.client.is_closed().await);
+
+    closed.await;
+}
+
+#[hala_test::test(io_test)]
+async fn test_accept_close_reason() {
+    let mut mock = MockQuic::new().await;
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    let mut accept = Box::pin(server_conn.accept_detailed());
+
+    assert!(poll_once!(&mut accept).is_pending());
+
+    mock.client.close(true, 7, b"bye").await.unwrap();
+
+    assert_eq!(
+        mock.client.close_reason().await,
+        Some(CloseReason {
+            is_local: true,
+            is_app: true,
+            error_code: 7,
+            reason: b"bye".to_vec(),
+        })
+    );
+
+    // Sends the `CONNECTION_CLOSE` frame.
+    mock.send_to_server().await.unwrap();
+
+    // The server connection is closed once the draining period elapses.
+    let mut buf = vec![0; 65535];
+
+    server_conn.read(&mut buf).await.unwrap_err();
+
+    let err = accept.await.unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+
+    assert_eq!(
+        CloseReason::from_io_error(&err),
+        Some(&CloseReason {
+            is_local: false,
+            is_app: true,
+            error_code: 7,
+            reason: b"bye".to_vec(),
+        })
+    );
+}
+
+#[hala_test::test(io_test)]
+async fn test_session_store() {
+    let store = Arc::new(MemorySessionStore::default());
+
+    let mut client_config = mock_config(false, MAX_DATAGRAM_SIZE);
+
+    client_config.set_session_store(store.clone());
+
+    let mock = MockQuic::with_config(client_config, mock_config(true, MAX_DATAGRAM_SIZE)).await;
+
+    assert!(store.is_empty());
+
+    // The session ticket is sent after the handshake is completed.
+    mock.send_to_client().await.unwrap();
+
+    assert_eq!(store.len(), 1);
+
+    let key = SessionKey {
+        server_name: None,
+        raddr: "127.0.0.1:1813".parse().unwrap(),
+    };
+
+    assert!(store.get(&key).is_some());
+}
+
+#[hala_test::test(io_test)]
+async fn test_client_trust_config() {
+    let mut config = mock_config(false, MAX_DATAGRAM_SIZE);
+
+    assert!(config.add_root_ca_pem(b"invalid pem").is_err());
+
+    config
+        .add_root_ca_pem(include_bytes!("../../cert/cert.crt"))
+        .unwrap();
+
+    config.set_server_name("quic.tech");
+
+    let connector = QuicConnectorState::new(
+        &mut config,
+        "127.0.0.1:1812".parse().unwrap(),
+        "127.0.0.1:1813".parse().unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(connector.quiche_conn.server_name(), Some("quic.tech"));
+}
+
+#[hala_test::test(io_test)]
+async fn test_keylog() {
+    let path = std::env::temp_dir().join("hala-quic-test-keylog.txt");
+
+    _ = std::fs::remove_file(&path);
+
+    let mut config = mock_config(false, MAX_DATAGRAM_SIZE);
+
+    config.enable_keylog(&path);
+
+    let _connector = QuicConnectorState::new(
+        &mut config,
+        "127.0.0.1:1812".parse().unwrap(),
+        "127.0.0.1:1813".parse().unwrap(),
+    )
+    .unwrap();
+
+    assert!(path.exists());
+}
+
+#[derive(Default, Clone)]
+struct MockCertResolver(Arc<Mutex<Vec<String>>>);
+
+impl CertResolver for MockCertResolver {
+    fn resolve(&self, server_name: &str) -> Option<CertifiedKey> {
+        self.0.lock().unwrap().push(server_name.to_owned());
+
+        let root_path = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+        Some(CertifiedKey {
+            cert_chain_pem_file: root_path.join("cert/cert.crt"),
+            priv_key_pem_file: root_path.join("cert/cert.key"),
+        })
+    }
+}
+
+#[hala_test::test(io_test)]
+async fn test_cert_resolver() {
+    let resolver = MockCertResolver::default();
+
+    let mut client_config = mock_config(false, MAX_DATAGRAM_SIZE);
+
+    client_config.set_server_name("quic.tech");
+
+    let mut server_config = mock_config(true, MAX_DATAGRAM_SIZE);
+
+    server_config.set_cert_resolver(resolver.clone());
+
+    let mock = MockQuic::with_config(client_config, server_config).await;
+
+    assert!(mock.client.is_established().await);
+
+    assert_eq!(
+        resolver.0.lock().unwrap().as_slice(),
+        ["quic.tech".to_owned()]
+    );
+
+    let server_conn = mock.server_conn.clone().unwrap();
+
+    assert_eq!(
+        server_conn.server_name().await.as_deref(),
+        Some("quic.tech")
+    );
+}
@@ -0,0 +1,182 @@
+//! The deficit round robin scheduler of the listener send path.
+//!
+//! The datagrams read from the connections are queued per connection, and dequeued in the
+//! order of DRR (Shreedhar & Varghese), each round a connection can send up to
+//! `weight * QUANTUM` bytes. A connection stops reading once its queue is full, so one busy
+//! connection can't fill the send path with its own datagrams.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+/// The default weight of connections.
+pub const DEFAULT_WEIGHT: u32 = 1;
+
+/// The max weight of connections.
+pub const MAX_WEIGHT: u32 = 256;
+
+/// The bytes a connection of weight 1 can send each round, about one full-sized datagram.
+const QUANTUM: usize = 1500;
+
+/// The queued datagrams of a connection of weight 1, the queue of heavier connections is longer,
+/// so they have enough datagrams to use up their quantum.
+const QUEUE_DATAGRAMS_PER_WEIGHT: usize = 4;
+
+struct Flow<T> {
+    queue: VecDeque<(T, usize)>,
+    weight: u32,
+    deficit: usize,
+    /// The queue was full, the connection stopped reading.
+    paused: bool,
+}
+
+impl<T> Flow<T> {
+    fn capacity(&self) -> usize {
+        self.weight as usize * QUEUE_DATAGRAMS_PER_WEIGHT
+    }
+}
+
+/// The deficit round robin scheduler of the datagrams of type `T`, keyed by the connection id `K`.
+pub(crate) struct Wfq<K, T> {
+    flows: HashMap<K, Flow<T>>,
+    /// The connections with queued datagrams, in the round robin order.
+    active: VecDeque<K>,
+}
+
+impl<K, T> Default for Wfq<K, T> {
+    fn default() -> Self {
+        Self {
+            flows: HashMap::new(),
+            active: VecDeque::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, T> Wfq<K, T> {
+    /// Queues the datagram `item` of `len` bytes read from the connection `key` of `weight`.
+    ///
+    /// Returns false if the queue of the connection is full, the connection should stop reading
+    /// until it's returned by [`pop`](Self::pop) as resumed.
+    pub(crate) fn push(&mut self, key: K, weight: u32, item: T, len: usize) -> bool {
+        let flow = self.flows.entry(key.clone()).or_insert_with(|| {
+            self.active.push_back(key);
+
+            Flow {
+                queue: VecDeque::new(),
+                weight,
+                deficit: 0,
+                paused: false,
+            }
+        });
+
+        flow.weight = weight.max(1);
+        flow.queue.push_back((item, len));
+
+        if flow.queue.len() >= flow.capacity() {
+            flow.paused = true;
+        }
+
+        !flow.paused
+    }
+
+    /// Dequeues the next datagram, and the connection to resume reading if its queue is no longer full.
+    pub(crate) fn pop(&mut self) -> Option<(T, Option<K>)> {
+        loop {
+            let key = self.active.front()?;
+
+            let flow = self.flows.get_mut(key).expect("active flow");
+
+            let (_, len) = flow.queue.front().expect("active flow is not empty");
+
+            if *len > flow.deficit {
+                // Moves to the next connection, the quantum of this round is used on the next visit.
+                flow.deficit += flow.weight as usize * QUANTUM;
+                self.active.rotate_left(1);
+                continue;
+            }
+
+            let (item, len) = flow.queue.pop_front().unwrap();
+
+            flow.deficit -= len;
+
+            let resumed = if flow.paused && flow.queue.len() < flow.capacity() {
+                flow.paused = false;
+                Some(key.clone())
+            } else {
+                None
+            };
+
+            if flow.queue.is_empty() {
+                // The idle connection doesn't save the credits.
+                let key = self.active.pop_front().unwrap();
+                self.flows.remove(&key);
+            }
+
+            return Some((item, resumed));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wfq_fairness() {
+        let mut wfq = Wfq::default();
+
+        // The busy connection fills its queue first.
+        let mut busy = 0;
+
+        while wfq.push("busy", 1, "busy", 1200) {
+            busy += 1;
+        }
+
+        assert_eq!(busy + 1, QUEUE_DATAGRAMS_PER_WEIGHT);
+
+        wfq.push("idle", 1, "idle", 1200);
+
+        // The idle connection is served in the first round, not after the busy connection drained.
+        let order = std::iter::from_fn(|| wfq.pop())
+            .map(|(item, _)| item)
+            .collect::<Vec<_>>();
+
+        let idle = order.iter().position(|item| *item == "idle").unwrap();
+
+        assert!(idle <= 1, "order: {:?}", order);
+    }
+
+    #[test]
+    fn test_wfq_resume() {
+        let mut wfq = Wfq::default();
+
+        for _ in 0..QUEUE_DATAGRAMS_PER_WEIGHT - 1 {
+            assert!(wfq.push(1, 1, (), 100));
+        }
+
+        assert!(!wfq.push(1, 1, (), 100));
+
+        let (_, resumed) = wfq.pop().unwrap();
+
+        assert_eq!(resumed, Some(1));
+    }
+
+    #[test]
+    fn test_wfq_weight() {
+        // The heavier connection sends twice as many bytes.
+        let mut wfq = Wfq::default();
+
+        for _ in 0..8 {
+            wfq.push("a", 1, "a", 1500);
+            wfq.push("b", 2, "b", 1500);
+        }
+
+        let first_six = std::iter::from_fn(|| wfq.pop())
+            .take(6)
+            .map(|(item, _)| item)
+            .collect::<Vec<_>>();
+
+        assert_eq!(first_six.iter().filter(|item| **item == "b").count(), 4);
+    }
+}
@@ -0,0 +1,660 @@
+use std::{
+    fmt::Debug,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::{future::poll_fn, ready, AsyncRead, AsyncWrite};
+use hala_io::bytes::{Buf, Bytes, BytesMut};
+use hala_sync::{AsyncLockable, AsyncSpinMutex};
+
+use crate::state::{QuicConnState, QuicStreamFlowControl};
+
+struct RawQuicStream {
+    /// The state machine of the connection to which this stream belongs.
+    conn: QuicConnState,
+    /// The quic stream id.
+    stream_id: u64,
+    /// The buffered data not yet sent to the connection.
+    write_buf: AsyncSpinMutex<BytesMut>,
+    /// The fin flag has been read by [`AsyncRead`], the later reads return EOF.
+    read_fin: AtomicBool,
+}
+
+impl RawQuicStream {
+    fn new(conn: QuicConnState, stream_id: u64) -> Self {
+        Self {
+            conn,
+            stream_id,
+            write_buf: AsyncSpinMutex::new(BytesMut::new()),
+            read_fin: AtomicBool::new(false),
+        }
+    }
+
+    fn poll_read(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if self.read_fin.load(Ordering::Acquire) {
+            return Poll::Ready(Ok(0));
+        }
+
+        let (read_size, fin) = ready!(self.conn.poll_stream_recv(cx, self.stream_id, buf))?;
+
+        if fin {
+            self.read_fin.store(true, Ordering::Release);
+        }
+
+        Poll::Ready(Ok(read_size))
+    }
+
+    /// Sends all the buffered data to the stream.
+    fn poll_flush_buf(&self, cx: &mut Context<'_>, buf: &mut BytesMut) -> Poll<io::Result<()>> {
+        while !buf.is_empty() {
+            // Pending here if the stream capacity is exhausted.
+            let write_size = ready!(self.conn.poll_stream_send(cx, self.stream_id, buf, false))?;
+
+            buf.advance(write_size);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Appends `data` to the write buffer, flushes the buffer first if it reaches the high-watermark.
+    fn poll_write(&self, cx: &mut Context<'_>, data: &[u8]) -> Poll<io::Result<usize>> {
+        let mut buf = ready!(self.write_buf.poll_lock(cx));
+
+        let high_watermark = self.conn.stream_buffer().max(1);
+
+        if buf.len() >= high_watermark {
+            ready!(self.poll_flush_buf(cx, &mut buf))?;
+        }
+
+        let write_size = data.len().min(high_watermark - buf.len());
+
+        buf.extend_from_slice(&data[..write_size]);
+
+        Poll::Ready(Ok(write_size))
+    }
+
+    fn poll_flush(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut buf = ready!(self.write_buf.poll_lock(cx));
+
+        self.poll_flush_buf(cx, &mut buf)
+    }
+
+    /// Flushes the buffered data, then sends the fin flag.
+    fn poll_close(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut buf = ready!(self.write_buf.poll_lock(cx));
+
+        ready!(self.poll_flush_buf(cx, &mut buf))?;
+
+        self.conn
+            .poll_stream_send(cx, self.stream_id, b"", true)
+            .map_ok(|_| ())
+    }
+
+    async fn write(&self, data: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_write(cx, data)).await
+    }
+
+    async fn flush(&self) -> io::Result<()> {
+        poll_fn(|cx| self.poll_flush(cx)).await
+    }
+
+    async fn flush_buf(&self, buf: &mut BytesMut) -> io::Result<()> {
+        poll_fn(|cx| self.poll_flush_buf(cx, buf)).await
+    }
+
+    async fn send(&self, data: &[u8], fin: bool) -> io::Result<usize> {
+        let mut buf = self.write_buf.lock().await;
+
+        self.flush_buf(&mut buf).await?;
+
+        self.conn.stream_send(self.stream_id, data, fin).await
+    }
+
+    async fn send_bytes(&self, mut data: Bytes, fin: bool) -> io::Result<()> {
+        let mut buf = self.write_buf.lock().await;
+
+        self.flush_buf(&mut buf).await?;
+
+        loop {
+            let write_size = self.conn.stream_send(self.stream_id, &data, fin).await?;
+
+            data.advance(write_size);
+
+            if data.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn shutdown(&self) -> io::Result<()> {
+        poll_fn(|cx| self.poll_close(cx)).await
+    }
+
+    /// Sends `RESET_STREAM` frame and discards the buffered data.
+    async fn reset(&self, error_code: u64) -> io::Result<()> {
+        // Shutdown first to wakeup the pending flush, which holds the buffer lock.
+        self.conn
+            .stream_shutdown(self.stream_id, quiche::Shutdown::Write, error_code)
+            .await?;
+
+        self.write_buf.lock().await.clear();
+
+        Ok(())
+    }
+
+    async fn stop_sending(&self, error_code: u64) -> io::Result<()> {
+        self.conn
+            .stream_shutdown(self.stream_id, quiche::Shutdown::Read, error_code)
+            .await
+    }
+}
+
+impl Drop for RawQuicStream {
+    fn drop(&mut self) {
+        // The pending operations hold the stream, so the buffer lock is free here.
+        let buf = self
+            .write_buf
+            .try_lock()
+            .map(|mut buf| buf.split())
+            .unwrap_or_default();
+
+        self.conn.close_stream_deferred(self.stream_id, buf);
+    }
+}
+
+/// Returns true if the stream `id` is a unidirectional stream.
+pub fn is_uni_stream(id: u64) -> bool {
+    id & 0x2 != 0
+}
+
+/// Quic stream socket, created by [`open_stream`](QuicConnState::open_stream)
+/// or [`accept`](QuicConnState::accept).
+///
+/// The stream is closed when the last handle(including split halves) is dropped.
+pub struct QuicStream {
+    raw: Arc<RawQuicStream>,
+}
+
+impl Debug for QuicStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "QuicStream, conn={:?}, stream_id={}",
+            self.raw.conn, self.raw.stream_id
+        )
+    }
+}
+
+impl QuicStream {
+    /// Create new `QuicStream` instance with the stream id of `conn`.
+    pub fn new(conn: QuicConnState, stream_id: u64) -> Self {
+        Self {
+            raw: Arc::new(RawQuicStream::new(conn, stream_id)),
+        }
+    }
+
+    /// Returns the stream id.
+    pub fn id(&self) -> u64 {
+        self.raw.stream_id
+    }
+
+    /// Flushes the buffered data, then writes data to the stream directly.
+    ///
+    /// See [`stream_send`](QuicConnState::stream_send) for more information.
+    pub async fn send(&self, buf: &[u8], fin: bool) -> io::Result<usize> {
+        self.raw.send(buf, fin).await
+    }
+
+    /// Flushes the buffered data, then sends the whole owned `buf` to the stream without copying it
+    /// into the write buffer, the `fin` flag is sent with the last piece of data.
+    pub async fn send_bytes(&self, buf: Bytes, fin: bool) -> io::Result<()> {
+        self.raw.send_bytes(buf, fin).await
+    }
+
+    /// Appends data to the write buffer, and returns the number of bytes buffered.
+    ///
+    /// If the buffer reaches the [`high-watermark`](crate::Config::set_stream_buffer),
+    /// this function flushes the buffer first.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.raw.write(buf).await
+    }
+
+    /// Sends all the buffered data to the stream, pending if the stream capacity is exhausted.
+    pub async fn flush(&self) -> io::Result<()> {
+        self.raw.flush().await
+    }
+
+    /// Reads data from this stream, and returns tuple (read_size,fin)
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<(usize, bool)> {
+        self.raw.conn.stream_recv(self.raw.stream_id, buf).await
+    }
+
+    /// Abruptly terminates the sending side of the stream with the application `error_code`,
+    /// the buffered data is discarded.
+    ///
+    /// The peer receives [`ConnectionReset`](io::ErrorKind::ConnectionReset) error on reading,
+    /// see [`stream_error_code`](crate::errors::stream_error_code).
+    pub async fn reset(&self, error_code: u64) -> io::Result<()> {
+        self.raw.reset(error_code).await
+    }
+
+    /// Requests the peer to stop sending data on the stream with the application `error_code`,
+    /// the data received later is discarded.
+    ///
+    /// The peer receives [`BrokenPipe`](io::ErrorKind::BrokenPipe) error on writing,
+    /// see [`stream_error_code`](crate::errors::stream_error_code).
+    pub async fn stop_sending(&self, error_code: u64) -> io::Result<()> {
+        self.raw.stop_sending(error_code).await
+    }
+
+    /// Sets the priority of this stream, see [`stream_priority`](QuicConnState::stream_priority) for more information.
+    pub async fn set_priority(&self, urgency: u8, incremental: bool) -> io::Result<()> {
+        self.raw
+            .conn
+            .stream_priority(self.raw.stream_id, urgency, incremental)
+            .await
+    }
+
+    /// Returns the flow control state of this stream, see [`QuicStreamFlowControl`] for more information.
+    pub async fn flow_control(&self) -> QuicStreamFlowControl {
+        self.raw.conn.stream_flow_control(self.raw.stream_id).await
+    }
+
+    /// Splits this stream into a read half and a write half,
+    /// which can be used to read and write the stream concurrently.
+    pub fn split(self) -> (QuicStreamReadHalf, QuicStreamWriteHalf) {
+        (
+            QuicStreamReadHalf {
+                raw: self.raw.clone(),
+            },
+            QuicStreamWriteHalf { raw: self.raw },
+        )
+    }
+}
+
+/// The read half of [`QuicStream`], created by [`split`](QuicStream::split) function.
+#[derive(Clone)]
+pub struct QuicStreamReadHalf {
+    raw: Arc<RawQuicStream>,
+}
+
+impl Debug for QuicStreamReadHalf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "QuicStreamReadHalf, stream_id={}", self.raw.stream_id)
+    }
+}
+
+impl QuicStreamReadHalf {
+    /// Returns the stream id.
+    pub fn id(&self) -> u64 {
+        self.raw.stream_id
+    }
+
+    /// Reads data from the stream, and returns tuple (read_size,fin)
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<(usize, bool)> {
+        self.raw.conn.stream_recv(self.raw.stream_id, buf).await
+    }
+
+    /// Returns true if all the data has been read from the stream.
+    pub async fn is_finished(&self) -> bool {
+        self.raw.conn.stream_finished(self.raw.stream_id).await
+    }
+
+    /// Returns the flow control state of this stream, see [`QuicStreamFlowControl`] for more information.
+    pub async fn flow_control(&self) -> QuicStreamFlowControl {
+        self.raw.conn.stream_flow_control(self.raw.stream_id).await
+    }
+
+    /// Requests the peer to stop sending data on the stream with the application `error_code`,
+    /// the data received later is discarded.
+    ///
+    /// The peer receives [`BrokenPipe`](io::ErrorKind::BrokenPipe) error on writing,
+    /// see [`stream_error_code`](crate::errors::stream_error_code).
+    pub async fn stop_sending(&self, error_code: u64) -> io::Result<()> {
+        self.raw.stop_sending(error_code).await
+    }
+
+    /// Attempts to put the two halves of a [`QuicStream`] back together.
+    ///
+    /// Returns [`ReuniteError`] if the two halves do not originate from the same stream.
+    pub fn reunite(self, other: QuicStreamWriteHalf) -> Result<QuicStream, ReuniteError> {
+        if Arc::ptr_eq(&self.raw, &other.raw) {
+            Ok(QuicStream { raw: self.raw })
+        } else {
+            Err(ReuniteError(self, other))
+        }
+    }
+}
+
+/// The write half of [`QuicStream`], created by [`split`](QuicStream::split) function.
+#[derive(Clone)]
+pub struct QuicStreamWriteHalf {
+    raw: Arc<RawQuicStream>,
+}
+
+impl Debug for QuicStreamWriteHalf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "QuicStreamWriteHalf, stream_id={}", self.raw.stream_id)
+    }
+}
+
+impl QuicStreamWriteHalf {
+    /// Returns the stream id.
+    pub fn id(&self) -> u64 {
+        self.raw.stream_id
+    }
+
+    /// Flushes the buffered data, then writes data to the stream directly.
+    ///
+    /// See [`stream_send`](QuicConnState::stream_send) for more information.
+    pub async fn send(&self, buf: &[u8], fin: bool) -> io::Result<usize> {
+        self.raw.send(buf, fin).await
+    }
+
+    /// Flushes the buffered data, then sends the whole owned `buf` to the stream without copying it
+    /// into the write buffer, the `fin` flag is sent with the last piece of data.
+    pub async fn send_bytes(&self, buf: Bytes, fin: bool) -> io::Result<()> {
+        self.raw.send_bytes(buf, fin).await
+    }
+
+    /// Appends data to the write buffer, and returns the number of bytes buffered.
+    ///
+    /// If the buffer reaches the [`high-watermark`](crate::Config::set_stream_buffer),
+    /// this function flushes the buffer first.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.raw.write(buf).await
+    }
+
+    /// Sends all the buffered data to the stream, pending if the stream capacity is exhausted.
+    pub async fn flush(&self) -> io::Result<()> {
+        self.raw.flush().await
+    }
+
+    /// Returns the flow control state of this stream, see [`QuicStreamFlowControl`] for more information.
+    pub async fn flow_control(&self) -> QuicStreamFlowControl {
+        self.raw.conn.stream_flow_control(self.raw.stream_id).await
+    }
+
+    /// Shuts down the sending side of the stream by flushing the buffered data and sending len(0) data and fin flag.
+    pub async fn shutdown(&self) -> io::Result<()> {
+        self.raw.shutdown().await
+    }
+
+    /// Abruptly terminates the sending side of the stream with the application `error_code`,
+    /// the buffered data is discarded.
+    ///
+    /// The peer receives [`ConnectionReset`](io::ErrorKind::ConnectionReset) error on reading,
+    /// see [`stream_error_code`](crate::errors::stream_error_code).
+    pub async fn reset(&self, error_code: u64) -> io::Result<()> {
+        self.raw.reset(error_code).await
+    }
+
+    /// Sets the priority of this stream, see [`stream_priority`](QuicConnState::stream_priority) for more information.
+    pub async fn set_priority(&self, urgency: u8, incremental: bool) -> io::Result<()> {
+        self.raw
+            .conn
+            .stream_priority(self.raw.stream_id, urgency, incremental)
+            .await
+    }
+
+    /// Attempts to put the two halves of a [`QuicStream`] back together.
+    ///
+    /// See [`QuicStreamReadHalf::reunite`] for more information.
+    pub fn reunite(self, other: QuicStreamReadHalf) -> Result<QuicStream, ReuniteError> {
+        other.reunite(self)
+    }
+}
+
+/// Error indicating that two halves were not from the same stream, and thus could not be reunited.
+#[derive(Debug, thiserror::Error)]
+#[error("tried to reunite halves that are not from the same stream")]
+pub struct ReuniteError(pub QuicStreamReadHalf, pub QuicStreamWriteHalf);
+
+/// The send-only unidirectional quic stream, created by [`open_uni_stream`](crate::QuicConn::open_uni_stream).
+pub struct QuicSendStream {
+    raw: Arc<RawQuicStream>,
+}
+
+impl Debug for QuicSendStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "QuicSendStream, conn={:?}, stream_id={}",
+            self.raw.conn, self.raw.stream_id
+        )
+    }
+}
+
+impl QuicSendStream {
+    /// Create new `QuicSendStream` instance with the unidirectional stream id of `conn`.
+    pub fn new(conn: QuicConnState, stream_id: u64) -> Self {
+        assert!(is_uni_stream(stream_id), "expect unidirectional stream id");
+
+        Self {
+            raw: Arc::new(RawQuicStream::new(conn, stream_id)),
+        }
+    }
+
+    /// Returns the stream id.
+    pub fn id(&self) -> u64 {
+        self.raw.stream_id
+    }
+
+    /// Flushes the buffered data, then writes data to the stream directly.
+    ///
+    /// See [`stream_send`](QuicConnState::stream_send) for more information.
+    pub async fn send(&self, buf: &[u8], fin: bool) -> io::Result<usize> {
+        self.raw.send(buf, fin).await
+    }
+
+    /// Flushes the buffered data, then sends the whole owned `buf` to the stream without copying it
+    /// into the write buffer, the `fin` flag is sent with the last piece of data.
+    pub async fn send_bytes(&self, buf: Bytes, fin: bool) -> io::Result<()> {
+        self.raw.send_bytes(buf, fin).await
+    }
+
+    /// Appends data to the write buffer, and returns the number of bytes buffered.
+    ///
+    /// If the buffer reaches the [`high-watermark`](crate::Config::set_stream_buffer),
+    /// this function flushes the buffer first.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.raw.write(buf).await
+    }
+
+    /// Sends all the buffered data to the stream, pending if the stream capacity is exhausted.
+    pub async fn flush(&self) -> io::Result<()> {
+        self.raw.flush().await
+    }
+
+    /// Returns the flow control state of this stream, see [`QuicStreamFlowControl`] for more information.
+    pub async fn flow_control(&self) -> QuicStreamFlowControl {
+        self.raw.conn.stream_flow_control(self.raw.stream_id).await
+    }
+
+    /// Shuts down the stream by flushing the buffered data and sending len(0) data and fin flag.
+    pub async fn shutdown(&self) -> io::Result<()> {
+        self.raw.shutdown().await
+    }
+
+    /// Abruptly terminates the sending side of the stream with the application `error_code`,
+    /// the buffered data is discarded.
+    ///
+    /// The peer receives [`ConnectionReset`](io::ErrorKind::ConnectionReset) error on reading,
+    /// see [`stream_error_code`](crate::errors::stream_error_code).
+    pub async fn reset(&self, error_code: u64) -> io::Result<()> {
+        self.raw.reset(error_code).await
+    }
+
+    /// Sets the priority of this stream, see [`stream_priority`](QuicConnState::stream_priority) for more information.
+    pub async fn set_priority(&self, urgency: u8, incremental: bool) -> io::Result<()> {
+        self.raw
+            .conn
+            .stream_priority(self.raw.stream_id, urgency, incremental)
+            .await
+    }
+}
+
+/// Reads the stream data until the fin flag is received, then returns EOF.
+impl AsyncRead for QuicStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.raw.poll_read(cx, buf)
+    }
+}
+
+/// Reads the stream data until the fin flag is received, then returns EOF.
+impl AsyncRead for QuicStreamReadHalf {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.raw.poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for QuicStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.raw.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.raw.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.raw.poll_close(cx)
+    }
+}
+
+impl AsyncWrite for QuicStreamWriteHalf {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.raw.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.raw.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.raw.poll_close(cx)
+    }
+}
+
+impl AsyncWrite for QuicSendStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.raw.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.raw.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.raw.poll_close(cx)
+    }
+}
+
+/// The receive-only unidirectional quic stream, which is opened by remote peer.
+pub struct QuicRecvStream {
+    raw: Arc<RawQuicStream>,
+}
+
+impl Debug for QuicRecvStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "QuicRecvStream, conn={:?}, stream_id={}",
+            self.raw.conn, self.raw.stream_id
+        )
+    }
+}
+
+impl QuicRecvStream {
+    /// Create new `QuicRecvStream` instance with the unidirectional stream id of `conn`.
+    pub fn new(conn: QuicConnState, stream_id: u64) -> Self {
+        assert!(is_uni_stream(stream_id), "expect unidirectional stream id");
+
+        Self {
+            raw: Arc::new(RawQuicStream::new(conn, stream_id)),
+        }
+    }
+
+    /// Returns the stream id.
+    pub fn id(&self) -> u64 {
+        self.raw.stream_id
+    }
+
+    /// Reads data from the stream, and returns tuple (read_size,fin)
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<(usize, bool)> {
+        self.raw.conn.stream_recv(self.raw.stream_id, buf).await
+    }
+
+    /// Returns true if all the data has been read from the stream.
+    pub async fn is_finished(&self) -> bool {
+        self.raw.conn.stream_finished(self.raw.stream_id).await
+    }
+
+    /// Returns the flow control state of this stream, see [`QuicStreamFlowControl`] for more information.
+    pub async fn flow_control(&self) -> QuicStreamFlowControl {
+        self.raw.conn.stream_flow_control(self.raw.stream_id).await
+    }
+
+    /// Requests the peer to stop sending data on the stream with the application `error_code`,
+    /// the data received later is discarded.
+    ///
+    /// The peer receives [`BrokenPipe`](io::ErrorKind::BrokenPipe) error on writing,
+    /// see [`stream_error_code`](crate::errors::stream_error_code).
+    pub async fn stop_sending(&self, error_code: u64) -> io::Result<()> {
+        self.raw.stop_sending(error_code).await
+    }
+}
+
+/// The incoming stream variants returns by [`accept`](crate::QuicConn::accept) function.
+#[derive(Debug)]
+pub enum QuicIncoming {
+    /// The bidirectional stream opened by remote peer.
+    Bidi(QuicStream),
+    /// The unidirectional stream opened by remote peer.
+    Uni(QuicRecvStream),
+}
+
+impl QuicIncoming {
+    /// Create incoming stream with the stream id of `conn`.
+    pub fn new(conn: QuicConnState, stream_id: u64) -> Self {
+        if is_uni_stream(stream_id) {
+            Self::Uni(QuicRecvStream::new(conn, stream_id))
+        } else {
+            Self::Bidi(QuicStream::new(conn, stream_id))
+        }
+    }
+
+    /// Returns the stream id.
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::Bidi(stream) => stream.id(),
+            Self::Uni(stream) => stream.id(),
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! The synchronous tcp types for the codebases without async runtime.
+//!
+//! Each instance runs the async type of the crate on its own [`BlockingPoller`], the operations
+//! block the calling thread until they're completed, so no event loop thread is needed.
+
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
+
+use hala_io::*;
+
+#[cfg(feature = "current")]
+use hala_io::current::get_driver;
+
+/// The blocking version of [`crate::TcpStream`], which implements [`Read`] and [`Write`].
+pub struct TcpStream {
+    // Dropped before the poller it's registered to.
+    inner: crate::TcpStream,
+    poller: BlockingPoller,
+}
+
+impl Debug for TcpStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "blocking::TcpStream({:?})", self.inner.fd)
+    }
+}
+
+impl TcpStream {
+    /// Opens a TCP connection to a remote host with global context `driver`, and blocks until the
+    /// connection is established.
+    #[cfg(feature = "current")]
+    pub fn connect<S: ToSocketAddrs>(raddrs: S) -> io::Result<Self> {
+        Self::connect_with(raddrs, get_driver()?)
+    }
+
+    /// Opens a TCP connection to a remote host with providing `driver`, and blocks until the
+    /// connection is established.
+    pub fn connect_with<S: ToSocketAddrs>(raddrs: S, driver: Driver) -> io::Result<Self> {
+        let poller = BlockingPoller::new_with(driver.clone())?;
+
+        let raddrs = raddrs.to_socket_addrs()?.collect::<Vec<_>>();
+
+        let fd = OwnedHandle::open(driver, Description::TcpStream, OpenFlags::Connect(&raddrs))?;
+
+        let inner = crate::TcpStream::new_with(fd, poller.poller())?;
+
+        poller.block_on(inner.connected())?;
+
+        Ok(Self { inner, poller })
+    }
+
+    /// Adopts the externally created `stream` with global context `driver`.
+    #[cfg(feature = "current")]
+    pub fn from_std(stream: std::net::TcpStream) -> io::Result<Self> {
+        Self::from_std_with(stream, get_driver()?)
+    }
+
+    /// Adopts the externally created `stream` with providing `driver`.
+    ///
+    /// The stream is switched to non-blocking mode, the operations of the returned instance still block.
+    pub fn from_std_with(stream: std::net::TcpStream, driver: Driver) -> io::Result<Self> {
+        let poller = BlockingPoller::new_with(driver.clone())?;
+
+        let inner = crate::TcpStream::from_std_with(stream, driver, poller.poller())?;
+
+        Ok(Self { inner, poller })
+    }
+
+    /// Returns the remote address that this stream is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    /// Returns the local address that this stream is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+
+    /// Sets the timeout of each read operation, see [`crate::TcpStream::set_read_timeout`].
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    /// Returns the read timeout of this stream.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.inner.read_timeout()
+    }
+
+    /// Sets the timeout of each write operation, see [`crate::TcpStream::set_write_timeout`].
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) {
+        self.inner.set_write_timeout(timeout)
+    }
+
+    /// Returns the write timeout of this stream.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.inner.write_timeout()
+    }
+}
+
+impl Read for &TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.poller.block_on(self.inner.read(buf))
+    }
+}
+
+impl Write for &TcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.poller.block_on(self.inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self).read(buf)
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The blocking version of [`crate::TcpListener`].
+pub struct TcpListener {
+    // Dropped before the poller it's registered to.
+    inner: crate::TcpListener,
+    poller: BlockingPoller,
+}
+
+impl Debug for TcpListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "blocking::{:?}", self.inner)
+    }
+}
+
+impl TcpListener {
+    /// Creates a new tcp listener bound to `laddrs` with global context `driver`.
+    #[cfg(feature = "current")]
+    pub fn bind<S: ToSocketAddrs>(laddrs: S) -> io::Result<Self> {
+        Self::bind_with(laddrs, get_driver()?)
+    }
+
+    /// Creates a new tcp listener bound to `laddrs` with providing `driver`.
+    pub fn bind_with<S: ToSocketAddrs>(laddrs: S, driver: Driver) -> io::Result<Self> {
+        let poller = BlockingPoller::new_with(driver.clone())?;
+
+        let inner = crate::TcpListener::bind_with(laddrs, driver, poller.poller())?;
+
+        Ok(Self { inner, poller })
+    }
+
+    /// Blocks until a new incoming connection is accepted.
+    ///
+    /// The accepted stream runs on its own poller, so it can be moved to another thread.
+    pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let poller = BlockingPoller::new_with(self.poller.driver().clone())?;
+
+        let (inner, raddr) = self
+            .poller
+            .block_on(self.inner.accept_with(poller.poller()))?;
+
+        Ok((TcpStream { inner, poller }, raddr))
+    }
+
+    /// Returns an iterator over the connections being received on this listener,
+    /// which never returns `None`.
+    pub fn incoming(&self) -> impl Iterator<Item = io::Result<TcpStream>> + '_ {
+        std::iter::repeat_with(|| self.accept().map(|(stream, _)| stream))
+    }
+
+    /// Sets the timeout of each accept operation, see [`crate::TcpListener::set_accept_timeout`].
+    pub fn set_accept_timeout(&self, timeout: Option<Duration>) {
+        self.inner.set_accept_timeout(timeout)
+    }
+
+    /// Returns the accept timeout of this listener.
+    pub fn accept_timeout(&self) -> Option<Duration> {
+        self.inner.accept_timeout()
+    }
+
+    /// Returns the local address that this listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Read, Write},
+        thread,
+        time::Duration,
+    };
+
+    use hala_io::mio::mio_driver;
+
+    use super::*;
+
+    #[test]
+    fn test_blocking_echo() {
+        let driver = mio_driver();
+
+        let listener = TcpListener::bind_with("127.0.0.1:0", driver.clone()).unwrap();
+
+        let laddr = listener.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect_with(laddr, driver).unwrap();
+
+            stream.write_all(b"hello world").unwrap();
+
+            let mut buf = [0; 11];
+
+            stream.read_exact(&mut buf).unwrap();
+
+            assert_eq!(&buf, b"hello world");
+        });
+
+        let (mut stream, raddr) = listener.accept().unwrap();
+
+        assert_eq!(stream.peer_addr().unwrap(), raddr);
+
+        let mut buf = [0; 11];
+
+        stream.read_exact(&mut buf).unwrap();
+
+        stream.write_all(&buf).unwrap();
+
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn test_blocking_timeout() {
+        let driver = mio_driver();
+
+        let listener = TcpListener::bind_with("127.0.0.1:0", driver.clone()).unwrap();
+
+        listener.set_accept_timeout(Some(Duration::from_millis(20)));
+
+        assert_eq!(
+            listener.accept().unwrap_err().kind(),
+            io::ErrorKind::TimedOut
+        );
+
+        let mut stream = TcpStream::connect_with(listener.local_addr().unwrap(), driver).unwrap();
+
+        stream.set_read_timeout(Some(Duration::from_millis(20)));
+
+        let mut buf = [0; 1];
+
+        assert_eq!(
+            stream.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::TimedOut
+        );
+    }
+}
@@ -3,3 +3,11 @@ pub use listener::*;
 
 mod stream;
 pub use stream::*;
+
+mod split;
+pub use split::*;
+
+mod proxy_protocol;
+pub use proxy_protocol::*;
+
+pub mod blocking;
@@ -1,21 +1,25 @@
 use std::{
     fmt::Debug,
+    future::poll_fn,
     io,
     net::{SocketAddr, ToSocketAddrs},
+    task::ready,
+    time::Duration,
 };
 
+use futures::{stream, Stream};
 use hala_io::*;
 
 #[cfg(feature = "current")]
 use hala_io::current::*;
 
-use super::TcpStream;
+use crate::{stream::raw_open_flags, TcpStream};
 
 /// A structure representing a socket tcp server
 pub struct TcpListener {
-    fd: Handle,
+    fd: OwnedHandle,
     poller: Handle,
-    driver: Driver,
+    accept_deadline: IoDeadline,
 }
 
 impl Debug for TcpListener {
@@ -35,26 +39,76 @@ impl TcpListener {
         laddrs: S,
         driver: Driver,
         poller: Handle,
+    ) -> io::Result<Self> {
+        let laddrs = laddrs.to_socket_addrs()?.collect::<Vec<_>>();
+
+        // The driver binds like `std::net::TcpListener::bind`, which sets `SO_REUSEADDR` on unix.
+        let fd = OwnedHandle::open(driver, Description::TcpListener, OpenFlags::Bind(&laddrs))?;
+
+        Self::new_with(fd, poller)
+    }
+
+    /// Create new tcp listener with pre-bind socket `options`, e.g. `SO_REUSEPORT`.
+    #[cfg(feature = "current")]
+    pub fn bind_with_options<S: ToSocketAddrs>(
+        laddrs: S,
+        options: BindOptions,
+    ) -> io::Result<Self> {
+        Self::bind_with_options_with(laddrs, options, get_driver()?, get_poller()?)
+    }
+
+    /// Create new tcp listener with pre-bind socket `options` and providing `driver` / `poller`.
+    pub fn bind_with_options_with<S: ToSocketAddrs>(
+        laddrs: S,
+        options: BindOptions,
+        driver: Driver,
+        poller: Handle,
     ) -> io::Result<Self> {
         let laddrs = laddrs.to_socket_addrs()?.into_iter().collect::<Vec<_>>();
 
-        let fd = driver.fd_open(Description::TcpListener, OpenFlags::Bind(&laddrs))?;
+        let fd = OwnedHandle::open(
+            driver,
+            Description::TcpListener,
+            OpenFlags::BindWith(&laddrs, options),
+        )?;
+
+        Self::new_with(fd, poller)
+    }
 
-        match driver.fd_cntl(
+    /// Adopts the externally created `listener` with global context `driver` / `poller`,
+    /// e.g. the socket passed by systemd socket activation.
+    ///
+    /// The listener is switched to non-blocking mode.
+    #[cfg(feature = "current")]
+    pub fn from_std(listener: std::net::TcpListener) -> io::Result<Self> {
+        Self::from_std_with(listener, get_driver()?, get_poller()?)
+    }
+
+    /// Adopts the externally created `listener` with providing `driver` / `poller`.
+    pub fn from_std_with(
+        listener: std::net::TcpListener,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        let fd = OwnedHandle::open(driver, Description::TcpListener, raw_open_flags(listener))?;
+
+        Self::new_with(fd, poller)
+    }
+
+    /// Registers the opened listener `fd` to `poller`, the listener is closed if failed.
+    fn new_with(fd: OwnedHandle, poller: Handle) -> io::Result<Self> {
+        fd.register(poller, Interest::Readable, PollMode::Level)?;
+
+        Ok(Self {
+            accept_deadline: IoDeadline::new_with(fd.driver().clone(), poller),
+            fd,
             poller,
-            Cmd::Register {
-                source: fd,
-                interests: Interest::Readable,
-            },
-        ) {
-            Err(err) => {
-                _ = driver.fd_close(fd);
-                return Err(err);
-            }
-            _ => {}
-        }
+        })
+    }
 
-        Ok(Self { fd, driver, poller })
+    /// Creates a [`TcpListenerBuilder`] to configure the listener before binding.
+    pub fn builder() -> TcpListenerBuilder {
+        TcpListenerBuilder::default()
     }
 
     /// Accepts a new incoming connection from this listener.
@@ -64,33 +118,373 @@ impl TcpListener {
 
     /// Accepts a new incoming connection with providing `poller`
     pub async fn accept_with(&self, poller: Handle) -> io::Result<(TcpStream, SocketAddr)> {
-        let (handle, raddr) = would_block(|cx| {
-            self.driver
-                .fd_cntl(self.fd, Cmd::Accept(cx.waker().clone()))
+        let mut timer = IoTimer::default();
+
+        let (handle, raddr) = poll_fn(|cx| {
+            ready!(coop::poll_proceed(cx));
+
+            let poll = poll_would_block(|| self.fd.fd_cntl(Cmd::Accept(cx.waker().clone())));
+
+            self.accept_deadline.poll_io_with(cx, &mut timer, poll)
         })
         .await?
         .try_into_incoming()?;
 
-        let stream = TcpStream::new_with(self.driver.clone(), handle, poller)?;
+        // Safety: the accepted handle is owned by the stream.
+        let fd = unsafe { OwnedHandle::from_raw(self.fd.driver().clone(), handle) };
+
+        let stream = TcpStream::new_with(fd, poller)?;
 
         log::trace!("tcp incoming token={:?}, raddr={}", handle.token, raddr);
 
         Ok((stream, raddr))
     }
 
+    /// Sets the timeout of each accept operation, `None` means the accept operations never time out.
+    ///
+    /// The accept operation that is pending longer than `timeout` fails with [`TimedOut`](io::ErrorKind::TimedOut) error.
+    pub fn set_accept_timeout(&self, timeout: Option<Duration>) {
+        self.accept_deadline.set_timeout(timeout);
+    }
+
+    /// Returns the accept timeout of this listener.
+    pub fn accept_timeout(&self) -> Option<Duration> {
+        self.accept_deadline.timeout()
+    }
+
+    /// Returns a stream of incoming connections, which yields the result of [`accept`](Self::accept) forever.
+    ///
+    /// After yielding the [`Emfile`] error, the stream waits for the [`backoff`](Emfile::backoff)
+    /// before the next accept, so the accept loop doesn't spin when the fd limit is reached.
+    pub fn incoming(&self) -> impl Stream<Item = io::Result<(TcpStream, SocketAddr)>> + '_ {
+        stream::unfold((self, None), |(listener, backoff)| async move {
+            if let Some(backoff) = backoff {
+                if let Err(err) =
+                    sleep_with(listener.fd.driver().clone(), listener.poller, backoff).await
+                {
+                    return Some((Err(err), (listener, None)));
+                }
+            }
+
+            let result = listener.accept().await;
+
+            let backoff = result
+                .as_ref()
+                .err()
+                .and_then(Emfile::from_io_error)
+                .and_then(|emfile| emfile.backoff);
+
+            Some((result, (listener, backoff)))
+        })
+    }
+
     /// Returns the local socket address of this listener.
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        self.driver
-            .fd_cntl(self.fd, Cmd::LocalAddr)?
-            .try_into_sockaddr()
+        self.fd.fd_cntl(Cmd::LocalAddr)?.try_into_sockaddr()
+    }
+
+    /// Sets the `SO_RCVBUF` option of this listener.
+    ///
+    /// The os may adjust the value, see [`BindOptions::recv_buffer_size`].
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.fd
+            .fd_cntl(Cmd::SetBufferSize(SocketBuffer::Recv, size))
+            .map(|_| ())
+    }
+
+    /// Returns the `SO_RCVBUF` option of this listener.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.fd
+            .fd_cntl(Cmd::BufferSize(SocketBuffer::Recv))?
+            .try_into_datalen()
+    }
+
+    /// Sets the `SO_SNDBUF` option of this listener.
+    ///
+    /// The os may adjust the value, see [`BindOptions::send_buffer_size`].
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.fd
+            .fd_cntl(Cmd::SetBufferSize(SocketBuffer::Send, size))
+            .map(|_| ())
+    }
+
+    /// Returns the `SO_SNDBUF` option of this listener.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.fd
+            .fd_cntl(Cmd::BufferSize(SocketBuffer::Send))?
+            .try_into_datalen()
+    }
+}
+
+#[cfg(unix)]
+impl std::os::fd::AsRawFd for TcpListener {
+    /// # Panics
+    ///
+    /// Panics if the driver does not support [`Cmd::AsRawFd`].
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.fd
+            .fd_cntl(Cmd::AsRawFd)
+            .and_then(|resp| resp.try_into_raw_fd())
+            .expect("get raw fd of tcp listener")
+    }
+}
+
+#[cfg(windows)]
+impl std::os::windows::io::AsRawSocket for TcpListener {
+    /// # Panics
+    ///
+    /// Panics if the driver does not support [`Cmd::AsRawFd`].
+    fn as_raw_socket(&self) -> std::os::windows::io::RawSocket {
+        self.fd
+            .fd_cntl(Cmd::AsRawFd)
+            .and_then(|resp| resp.try_into_raw_socket())
+            .expect("get raw socket of tcp listener")
+    }
+}
+
+/// Builder for [`TcpListener`], which applies the socket options before binding.
+///
+/// `SO_REUSEADDR` is set by default on unix like [`TcpListener::bind`], so the server can be
+/// restarted on the same port while the connections of previous one are in `TIME_WAIT` state.
+#[derive(Debug, Clone, Copy)]
+pub struct TcpListenerBuilder {
+    options: BindOptions,
+}
+
+impl Default for TcpListenerBuilder {
+    fn default() -> Self {
+        Self {
+            options: BindOptions {
+                reuse_address: cfg!(unix),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl TcpListenerBuilder {
+    /// Sets the listen backlog, the driver's default value is used if not set.
+    pub fn set_backlog(mut self, backlog: u32) -> Self {
+        self.options.backlog = Some(backlog);
+        self
+    }
+
+    /// Sets `SO_REUSEADDR` option.
+    pub fn reuse_addr(mut self, reuse_addr: bool) -> Self {
+        self.options.reuse_address = reuse_addr;
+        self
+    }
+
+    /// Sets `SO_REUSEPORT` option, only supported on unix platforms.
+    pub fn reuse_port(mut self, reuse_port: bool) -> Self {
+        self.options.reuse_port = reuse_port;
+        self
+    }
+
+    /// Sets `IPV6_V6ONLY` option, only applied when binding to ipv6 addresses.
+    pub fn only_v6(mut self, only_v6: bool) -> Self {
+        self.options.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// Sets the ttl of outgoing packets, which is inherited by the accepted streams.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.options.ttl = Some(ttl);
+        self
+    }
+
+    /// Binds the listener to network interface `device`, only supported on linux.
+    pub fn bind_device(mut self, device: DeviceName) -> Self {
+        self.options.bind_device = Some(device);
+        self
+    }
+
+    /// Sets `SO_RCVBUF` option, which is inherited by the accepted streams.
+    ///
+    /// Setting it disables the receive buffer auto-tuning on linux, so only set it when
+    /// the auto-tuned size is known to be insufficient, e.g. for high bandwidth-delay product links.
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.options.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets `SO_SNDBUF` option, which is inherited by the accepted streams.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.options.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Returns the socket options applied before binding.
+    pub fn options(&self) -> BindOptions {
+        self.options
+    }
+
+    /// Creates the configured listener bound to `laddrs`.
+    #[cfg(feature = "current")]
+    pub fn bind<S: ToSocketAddrs>(self, laddrs: S) -> io::Result<TcpListener> {
+        TcpListener::bind_with_options(laddrs, self.options)
+    }
+
+    /// Creates the configured listener bound to `laddrs` with providing `driver` / `poller`.
+    pub fn bind_with<S: ToSocketAddrs>(
+        self,
+        laddrs: S,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<TcpListener> {
+        TcpListener::bind_with_options_with(laddrs, self.options, driver, poller)
     }
 }
 
 impl Drop for TcpListener {
     fn drop(&mut self) {
-        self.driver
-            .fd_cntl(self.poller, Cmd::Deregister(self.fd))
+        if let Err(err) = self.fd.deregister(self.poller) {
+            log::error!(
+                "deregister tcp listener failed, fd={:?}, err={}",
+                self.fd,
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use futures::StreamExt;
+    use hala_io::{test::io_test, BindOptions, DeviceName};
+
+    use crate::{TcpListener, TcpStream};
+
+    #[hala_test::test(io_test)]
+    async fn test_incoming() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let laddr = listener.local_addr().unwrap();
+
+        let streams = [
+            TcpStream::connect(laddr).unwrap(),
+            TcpStream::connect(laddr).unwrap(),
+        ];
+
+        let incoming = listener
+            .incoming()
+            .take(2)
+            .map(|r| r.unwrap().1)
+            .collect::<Vec<_>>()
+            .await;
+
+        for stream in streams {
+            assert!(incoming.contains(&stream.local_addr().unwrap()));
+        }
+    }
+
+    #[cfg(unix)]
+    #[hala_test::test(io_test)]
+    async fn test_reuse_port() {
+        let options = BindOptions {
+            reuse_address: true,
+            reuse_port: true,
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind_with_options("127.0.0.1:0", options).unwrap();
+
+        let laddr = listener.local_addr().unwrap();
+
+        let listener2 = TcpListener::bind_with_options(laddr, options).unwrap();
+
+        assert_eq!(listener2.local_addr().unwrap(), laddr);
+
+        // Without `SO_REUSEPORT`, binding the same port must fail.
+        TcpListener::bind(laddr).unwrap_err();
+    }
+
+    #[cfg(unix)]
+    #[hala_test::test(io_test)]
+    async fn test_rebind_time_wait() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let laddr = listener.local_addr().unwrap();
+
+        let client = TcpStream::connect(laddr).unwrap();
+
+        let (server, _) = listener.accept().await.unwrap();
+
+        // The server closes first, so its end of the connection enters `TIME_WAIT`.
+        drop(server);
+        drop(listener);
+
+        hala_io::sleep(Duration::from_millis(20)).await.unwrap();
+
+        drop(client);
+
+        hala_io::sleep(Duration::from_millis(20)).await.unwrap();
+
+        drop(TcpListener::bind(laddr).unwrap());
+
+        TcpListener::builder().bind(laddr).unwrap();
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_builder() {
+        let listener = TcpListener::builder()
+            .set_backlog(16)
+            .reuse_addr(true)
+            .only_v6(true)
+            .ttl(32)
+            .bind("[::1]:0")
+            .unwrap();
+
+        let laddr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect(laddr).unwrap();
+
+        let (_, raddr) = listener.accept().await.unwrap();
+
+        assert_eq!(raddr, stream.local_addr().unwrap());
+
+        // Invalid interface names are rejected before binding.
+        DeviceName::new("").unwrap_err();
+        DeviceName::new("a-very-long-interface-name").unwrap_err();
+
+        assert_eq!(DeviceName::new("lo").unwrap().as_str(), "lo");
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_buffer_size() {
+        let listener = TcpListener::builder()
+            .recv_buffer_size(256 * 1024)
+            .send_buffer_size(256 * 1024)
+            .bind("127.0.0.1:0")
             .unwrap();
-        self.driver.fd_close(self.fd).unwrap()
+
+        // Linux doubles the value for bookkeeping overhead.
+        assert!(listener.recv_buffer_size().unwrap() >= 256 * 1024);
+        assert!(listener.send_buffer_size().unwrap() >= 256 * 1024);
+
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        stream.set_recv_buffer_size(64 * 1024).unwrap();
+        stream.set_send_buffer_size(64 * 1024).unwrap();
+
+        assert!(stream.recv_buffer_size().unwrap() >= 64 * 1024);
+        assert!(stream.send_buffer_size().unwrap() >= 64 * 1024);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_accept_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        listener.set_accept_timeout(Some(Duration::from_millis(50)));
+
+        let err = listener.accept().await.unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        listener.accept().await.unwrap();
     }
 }
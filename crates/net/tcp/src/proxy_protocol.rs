@@ -0,0 +1,446 @@
+//! [PROXY protocol](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt) v1/v2 support,
+//! which carries the original client address of connections forwarded by L4 load balancers.
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{AsyncRead, AsyncWrite};
+use hala_io::timeout;
+
+use crate::{TcpListener, TcpStream};
+
+/// The signature of PROXY protocol v2 header.
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The max length of PROXY protocol v1 header, including the trailing CRLF.
+const V1_MAX_LEN: usize = 107;
+
+/// The default timeout of receiving the PROXY protocol header.
+pub const DEFAULT_PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn invalid_header(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid proxy protocol header: {}", msg),
+    )
+}
+
+/// The parsed PROXY protocol header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// The protocol version, `1` or `2`.
+    pub version: u8,
+    /// The original source address, `None` for `LOCAL` command, `UNKNOWN` protocol
+    /// or the unsupported address family (e.g. unix sockets).
+    pub source: Option<SocketAddr>,
+    /// The original destination address, `None` if `source` is `None`.
+    pub destination: Option<SocketAddr>,
+}
+
+impl ProxyHeader {
+    /// Parses the PROXY protocol header at the beginning of `buf`,
+    /// and returns tuple (header, header_len).
+    ///
+    /// Returns `Ok(None)` if `buf` is an incomplete header.
+    pub fn parse(buf: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        if buf.starts_with(V2_SIGNATURE) {
+            return Self::parse_v2(buf);
+        }
+
+        if buf.starts_with(b"PROXY ") {
+            return Self::parse_v1(buf);
+        }
+
+        if V2_SIGNATURE.starts_with(buf) || b"PROXY ".starts_with(buf) {
+            return Ok(None);
+        }
+
+        Err(invalid_header("signature mismatch"))
+    }
+
+    fn parse_v1(buf: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        let Some(end) = buf.windows(2).position(|w| w == b"\r\n") else {
+            if buf.len() >= V1_MAX_LEN {
+                return Err(invalid_header("v1 header too long"));
+            }
+
+            return Ok(None);
+        };
+
+        if end + 2 > V1_MAX_LEN {
+            return Err(invalid_header("v1 header too long"));
+        }
+
+        let line = std::str::from_utf8(&buf[..end]).map_err(|_| invalid_header("v1 not ascii"))?;
+
+        let fields = line.split(' ').collect::<Vec<_>>();
+
+        let (source, destination) = match fields.get(1).cloned() {
+            Some("UNKNOWN") => (None, None),
+            Some(proto @ ("TCP4" | "TCP6")) if fields.len() == 6 => {
+                let parse_addr = |ip: &str, port: &str| -> io::Result<SocketAddr> {
+                    let ip = ip
+                        .parse::<IpAddr>()
+                        .map_err(|_| invalid_header("v1 address"))?;
+
+                    if ip.is_ipv4() != (proto == "TCP4") {
+                        return Err(invalid_header("v1 address family mismatch"));
+                    }
+
+                    let port = port.parse().map_err(|_| invalid_header("v1 port"))?;
+
+                    Ok(SocketAddr::new(ip, port))
+                };
+
+                (
+                    Some(parse_addr(fields[2], fields[4])?),
+                    Some(parse_addr(fields[3], fields[5])?),
+                )
+            }
+            _ => return Err(invalid_header("v1 protocol")),
+        };
+
+        Ok(Some((
+            Self {
+                version: 1,
+                source,
+                destination,
+            },
+            end + 2,
+        )))
+    }
+
+    fn parse_v2(buf: &[u8]) -> io::Result<Option<(Self, usize)>> {
+        if buf.len() < 16 {
+            return Ok(None);
+        }
+
+        let (ver_cmd, family) = (buf[12], buf[13]);
+
+        if ver_cmd >> 4 != 2 {
+            return Err(invalid_header("v2 version"));
+        }
+
+        let len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+
+        if buf.len() < 16 + len {
+            return Ok(None);
+        }
+
+        let addrs = &buf[16..16 + len];
+
+        let (source, destination) = match (ver_cmd & 0x0f, family >> 4) {
+            // LOCAL command, the connection is established by the proxy itself.
+            (0x0, _) => (None, None),
+            // PROXY command with AF_INET.
+            (0x1, 0x1) => {
+                if addrs.len() < 12 {
+                    return Err(invalid_header("v2 ipv4 address block"));
+                }
+
+                let ip = |at: usize| {
+                    IpAddr::V4(Ipv4Addr::from(
+                        <[u8; 4]>::try_from(&addrs[at..at + 4]).unwrap(),
+                    ))
+                };
+
+                let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+
+                (
+                    Some(SocketAddr::new(ip(0), port(8))),
+                    Some(SocketAddr::new(ip(4), port(10))),
+                )
+            }
+            // PROXY command with AF_INET6.
+            (0x1, 0x2) => {
+                if addrs.len() < 36 {
+                    return Err(invalid_header("v2 ipv6 address block"));
+                }
+
+                let ip = |at: usize| {
+                    IpAddr::V6(Ipv6Addr::from(
+                        <[u8; 16]>::try_from(&addrs[at..at + 16]).unwrap(),
+                    ))
+                };
+
+                let port = |at: usize| u16::from_be_bytes([addrs[at], addrs[at + 1]]);
+
+                (
+                    Some(SocketAddr::new(ip(0), port(32))),
+                    Some(SocketAddr::new(ip(16), port(34))),
+                )
+            }
+            // PROXY command with AF_UNSPEC / AF_UNIX, the address block is ignored.
+            (0x1, _) => (None, None),
+            _ => return Err(invalid_header("v2 command")),
+        };
+
+        Ok(Some((
+            Self {
+                version: 2,
+                source,
+                destination,
+            },
+            16 + len,
+        )))
+    }
+}
+
+/// The [`TcpStream`] wrapper whose PROXY protocol header has been received.
+#[derive(Debug)]
+pub struct ProxyProtocolStream {
+    stream: TcpStream,
+    header: ProxyHeader,
+    /// The data received after the header.
+    buffered: Vec<u8>,
+}
+
+impl ProxyProtocolStream {
+    /// Receives the PROXY protocol header of the accepted `stream`.
+    ///
+    /// Returns [`TimedOut`](io::ErrorKind::TimedOut) error if the header is not received within `header_timeout`.
+    pub async fn accept_header(stream: TcpStream, header_timeout: Duration) -> io::Result<Self> {
+        timeout(Self::read_header(stream), Some(header_timeout)).await
+    }
+
+    async fn read_header(stream: TcpStream) -> io::Result<Self> {
+        let mut buf = vec![];
+        let mut chunk = [0; 512];
+
+        loop {
+            if let Some((header, len)) = ProxyHeader::parse(&buf)? {
+                buf.drain(..len);
+
+                return Ok(Self {
+                    stream,
+                    header,
+                    buffered: buf,
+                });
+            }
+
+            let read_size = stream.read(&mut chunk).await?;
+
+            if read_size == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed before receiving proxy protocol header",
+                ));
+            }
+
+            buf.extend_from_slice(&chunk[..read_size]);
+        }
+    }
+
+    /// Returns the parsed PROXY protocol header.
+    pub fn header(&self) -> &ProxyHeader {
+        &self.header
+    }
+
+    /// Returns the original client address carried by the PROXY protocol header,
+    /// falls back to the peer address of this connection if the header has no address.
+    pub fn original_peer_addr(&self) -> io::Result<SocketAddr> {
+        match self.header.source {
+            Some(addr) => Ok(addr),
+            None => self.stream.peer_addr(),
+        }
+    }
+
+    /// Returns the underlying [`TcpStream`], whose peer address is the address of the proxy.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+}
+
+impl AsyncRead for ProxyProtocolStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.buffered.is_empty() {
+            let len = buf.len().min(self.buffered.len());
+
+            buf[..len].copy_from_slice(&self.buffered[..len]);
+
+            self.buffered.drain(..len);
+
+            return Poll::Ready(Ok(len));
+        }
+
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ProxyProtocolStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
+
+/// The opt-in [`TcpListener`] wrapper, which receives the PROXY protocol header of accepted connections.
+///
+/// The header is received in [`accept`](Self::accept), use [`ProxyProtocolStream::accept_header`]
+/// in the spawned connection task instead to avoid slow clients blocking the accept loop.
+#[derive(Debug)]
+pub struct ProxyProtocolAcceptor {
+    listener: TcpListener,
+    header_timeout: Duration,
+}
+
+impl From<TcpListener> for ProxyProtocolAcceptor {
+    fn from(listener: TcpListener) -> Self {
+        Self::new(listener)
+    }
+}
+
+impl ProxyProtocolAcceptor {
+    /// Create new acceptor with [`DEFAULT_PROXY_HEADER_TIMEOUT`].
+    pub fn new(listener: TcpListener) -> Self {
+        Self {
+            listener,
+            header_timeout: DEFAULT_PROXY_HEADER_TIMEOUT,
+        }
+    }
+
+    /// Sets the timeout of receiving the PROXY protocol header.
+    pub fn header_timeout(mut self, header_timeout: Duration) -> Self {
+        self.header_timeout = header_timeout;
+        self
+    }
+
+    /// Accepts a new incoming connection and receives its PROXY protocol header,
+    /// returns the stream and the original client address.
+    pub async fn accept(&self) -> io::Result<(ProxyProtocolStream, SocketAddr)> {
+        let (stream, raddr) = self.listener.accept().await?;
+
+        let stream = ProxyProtocolStream::accept_header(stream, self.header_timeout)
+            .await
+            .map_err(|err| {
+                log::error!(
+                    "receive proxy protocol header failed, raddr={}, err={}",
+                    raddr,
+                    err
+                );
+                err
+            })?;
+
+        let original_peer_addr = stream.header.source.unwrap_or(raddr);
+
+        Ok((stream, original_peer_addr))
+    }
+
+    /// Returns the wrapped [`TcpListener`].
+    pub fn get_ref(&self) -> &TcpListener {
+        &self.listener
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use hala_io::test::io_test;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_v1() {
+        let buf = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET /";
+
+        let (header, len) = ProxyHeader::parse(buf).unwrap().unwrap();
+
+        assert_eq!(len, buf.len() - 5);
+        assert_eq!(header.source, Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(
+            header.destination,
+            Some("192.168.0.11:443".parse().unwrap())
+        );
+
+        let (header, _) = ProxyHeader::parse(b"PROXY TCP6 ::1 ::2 1 2\r\n")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(header.source, Some("[::1]:1".parse().unwrap()));
+
+        let (header, _) = ProxyHeader::parse(b"PROXY UNKNOWN\r\n").unwrap().unwrap();
+
+        assert_eq!(header.source, None);
+
+        assert_eq!(ProxyHeader::parse(b"PROX").unwrap(), None);
+        assert_eq!(ProxyHeader::parse(b"PROXY TCP4 1.1.1.1").unwrap(), None);
+
+        ProxyHeader::parse(b"PROXY TCP4 ::1 ::2 1 2\r\n").unwrap_err();
+        ProxyHeader::parse(b"GET / HTTP/1.1\r\n").unwrap_err();
+        ProxyHeader::parse(&[b'P'; V1_MAX_LEN]).unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let mut buf = V2_SIGNATURE.to_vec();
+
+        // PROXY command, TCP over IPv4.
+        buf.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        buf.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1f, 0x90, 0x01, 0xbb]);
+
+        assert_eq!(ProxyHeader::parse(&buf[..20]).unwrap(), None);
+
+        let (header, len) = ProxyHeader::parse(&buf).unwrap().unwrap();
+
+        assert_eq!(len, 28);
+        assert_eq!(header.version, 2);
+        assert_eq!(header.source, Some("10.0.0.1:8080".parse().unwrap()));
+        assert_eq!(header.destination, Some("10.0.0.2:443".parse().unwrap()));
+
+        // LOCAL command.
+        let mut buf = V2_SIGNATURE.to_vec();
+
+        buf.extend_from_slice(&[0x20, 0x00, 0, 0]);
+
+        let (header, len) = ProxyHeader::parse(&buf).unwrap().unwrap();
+
+        assert_eq!(len, 16);
+        assert_eq!(header.source, None);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_acceptor() {
+        let acceptor = ProxyProtocolAcceptor::new(TcpListener::bind("127.0.0.1:0").unwrap());
+
+        let laddr = acceptor.get_ref().local_addr().unwrap();
+
+        let mut client = TcpStream::connect(laddr).unwrap();
+
+        client
+            .write_all(b"PROXY TCP4 1.2.3.4 5.6.7.8 1234 80\r\nhello")
+            .await
+            .unwrap();
+
+        let (mut stream, raddr) = acceptor.accept().await.unwrap();
+
+        assert_eq!(raddr, "1.2.3.4:1234".parse().unwrap());
+        assert_eq!(stream.original_peer_addr().unwrap(), raddr);
+
+        // The data after the header is kept.
+        let mut buf = [0; 5];
+
+        stream.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(&buf, b"hello");
+    }
+}
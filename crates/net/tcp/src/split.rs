@@ -0,0 +1,252 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite};
+
+use super::TcpStream;
+
+/// Borrowed read half of a [`TcpStream`], created by [`split`](TcpStream::split).
+#[derive(Debug)]
+pub struct ReadHalf<'a>(&'a TcpStream);
+
+/// Borrowed write half of a [`TcpStream`], created by [`split`](TcpStream::split).
+#[derive(Debug)]
+pub struct WriteHalf<'a>(&'a TcpStream);
+
+impl TcpStream {
+    /// Splits a `TcpStream` into a read half and a write half,
+    /// which can be used to read and write the stream concurrently.
+    ///
+    /// The halves borrow the stream, see [`into_split`](Self::into_split) for owned halves
+    /// that can be moved into independent tasks.
+    pub fn split(&self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        (ReadHalf(self), WriteHalf(self))
+    }
+
+    /// Splits a `TcpStream` into a read half and a write half,
+    /// which can be used to read and write the stream concurrently.
+    ///
+    /// The stream is deregistered from the poller and closed when both halves are dropped.
+    pub fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        let stream = Arc::new(self);
+
+        (
+            OwnedReadHalf(stream.clone()),
+            OwnedWriteHalf {
+                stream,
+                shutdown_on_drop: true,
+            },
+        )
+    }
+}
+
+impl AsyncRead for ReadHalf<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for WriteHalf<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(cx)
+    }
+}
+
+/// Owned read half of a [`TcpStream`], created by [`into_split`](TcpStream::into_split).
+#[derive(Debug)]
+pub struct OwnedReadHalf(Arc<TcpStream>);
+
+/// Owned write half of a [`TcpStream`], created by [`into_split`](TcpStream::into_split).
+///
+/// Dropping the write half shuts down the write direction of the stream.
+#[derive(Debug)]
+pub struct OwnedWriteHalf {
+    stream: Arc<TcpStream>,
+    shutdown_on_drop: bool,
+}
+
+/// Error indicating that two halves were not from the same stream, and thus could not be reunited.
+#[derive(Debug, thiserror::Error)]
+#[error("tried to reunite halves that are not from the same stream")]
+pub struct ReuniteError(pub OwnedReadHalf, pub OwnedWriteHalf);
+
+impl OwnedReadHalf {
+    /// Attempts to put the two halves of a [`TcpStream`] back together.
+    ///
+    /// Returns [`ReuniteError`] if the two halves do not originate from the same stream.
+    pub fn reunite(self, other: OwnedWriteHalf) -> Result<TcpStream, ReuniteError> {
+        reunite(self, other)
+    }
+
+    /// Returns the local socket address of the stream.
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.0.local_addr()
+    }
+}
+
+impl OwnedWriteHalf {
+    /// Attempts to put the two halves of a [`TcpStream`] back together.
+    ///
+    /// See [`OwnedReadHalf::reunite`] for more information.
+    pub fn reunite(self, other: OwnedReadHalf) -> Result<TcpStream, ReuniteError> {
+        reunite(other, self)
+    }
+
+    /// Destroys the write half, but don't shut down the write direction of the stream.
+    pub fn forget(mut self) {
+        self.shutdown_on_drop = false;
+    }
+
+    /// Returns the local socket address of the stream.
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.stream.local_addr()
+    }
+}
+
+fn reunite(read: OwnedReadHalf, mut write: OwnedWriteHalf) -> Result<TcpStream, ReuniteError> {
+    if !Arc::ptr_eq(&read.0, &write.stream) {
+        return Err(ReuniteError(read, write));
+    }
+
+    write.shutdown_on_drop = false;
+
+    drop(write);
+
+    // Safety: the write half had been dropped, the read half holds the last reference.
+    Ok(Arc::try_unwrap(read.0).expect("TcpStream: try_unwrap failed in reunite"))
+}
+
+impl Drop for OwnedWriteHalf {
+    fn drop(&mut self) {
+        if self.shutdown_on_drop {
+            _ = self.stream.shutdown(std::net::Shutdown::Write);
+        }
+    }
+}
+
+impl AsyncRead for OwnedReadHalf {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for OwnedWriteHalf {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &*self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &*self.stream).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use hala_io::{current::executor::io_spawn, test::io_test};
+
+    use crate::{TcpListener, TcpStream};
+
+    #[hala_test::test(io_test)]
+    async fn test_into_split() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let raddr = listener.local_addr().unwrap();
+
+        io_spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut buf = vec![0; 1024];
+
+            loop {
+                let read_size = stream.read(&mut buf).await?;
+
+                if read_size == 0 {
+                    break;
+                }
+
+                stream.write_all(&buf[..read_size]).await?;
+            }
+
+            Ok(())
+        })
+        .unwrap();
+
+        let (mut read_half, mut write_half) = TcpStream::connect(raddr).unwrap().into_split();
+
+        io_spawn(async move {
+            for _ in 0..100 {
+                write_half.write_all(b"hello world").await?;
+            }
+
+            Ok(())
+        })
+        .unwrap();
+
+        let mut buf = vec![0; 11 * 100];
+
+        read_half.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..11], b"hello world");
+
+        let mut buf = vec![0; 11];
+
+        // write half had been dropped, the echo server closes connection.
+        assert_eq!(read_half.read(&mut buf).await.unwrap(), 0);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_reunite() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let raddr = listener.local_addr().unwrap();
+
+        let (read_half, write_half) = TcpStream::connect(raddr).unwrap().into_split();
+
+        let (read_half2, write_half2) = TcpStream::connect(raddr).unwrap().into_split();
+
+        let err = read_half.reunite(write_half2).unwrap_err();
+
+        let stream = err.0.reunite(write_half).unwrap();
+
+        let stream2 = read_half2.reunite(err.1).unwrap();
+
+        let (read, write) = stream.split();
+
+        drop((read, write));
+
+        drop(stream2);
+    }
+}
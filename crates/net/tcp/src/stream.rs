@@ -2,20 +2,29 @@ use std::{
     fmt::Debug,
     io,
     net::{Shutdown, SocketAddr, ToSocketAddrs},
-    task::Poll,
+    task::{Context, Poll},
+    time::Duration,
 };
 
 #[cfg(feature = "current")]
 use hala_io::current::*;
-use hala_io::*;
+use hala_io::{
+    bytes::{Buf, Bytes},
+    *,
+};
+
+use futures::{future::poll_fn, ready, stream::FuturesUnordered, AsyncRead, AsyncWrite, StreamExt};
 
-use futures::{AsyncRead, AsyncWrite};
+/// The default delay between two connection attempts of [`connect_happy`](TcpStream::connect_happy),
+/// the value recommended by RFC 8305.
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
 
 /// A TCP stream between a local and a remote socket.
 pub struct TcpStream {
-    pub fd: Handle,
+    pub fd: OwnedHandle,
     poller: Handle,
-    driver: Driver,
+    read_deadline: IoDeadline,
+    write_deadline: IoDeadline,
 }
 
 impl Debug for TcpStream {
@@ -25,22 +34,20 @@ impl Debug for TcpStream {
 }
 
 impl TcpStream {
-    pub(super) fn new_with(driver: Driver, fd: Handle, poller: Handle) -> io::Result<Self> {
-        match driver.fd_cntl(
+    /// Registers the opened stream `fd` to `poller`, the stream is closed if failed.
+    pub(super) fn new_with(fd: OwnedHandle, poller: Handle) -> io::Result<Self> {
+        fd.register(
             poller,
-            Cmd::Register {
-                source: fd,
-                interests: Interest::Readable | Interest::Writable,
-            },
-        ) {
-            Err(err) => {
-                _ = driver.fd_close(fd);
-                return Err(err);
-            }
-            _ => {}
-        }
+            Interest::Readable | Interest::Writable | Interest::Priority,
+            PollMode::Edge,
+        )?;
 
-        Ok(Self { fd, driver, poller })
+        Ok(Self {
+            read_deadline: IoDeadline::new_with(fd.driver().clone(), poller),
+            write_deadline: IoDeadline::new_with(fd.driver().clone(), poller),
+            fd,
+            poller,
+        })
     }
 
     /// Opens a TCP connection to a remote host with global context `poller`
@@ -54,22 +61,436 @@ impl TcpStream {
 
         let raddrs = raddrs.to_socket_addrs()?.into_iter().collect::<Vec<_>>();
 
-        let fd = driver.fd_open(Description::TcpStream, OpenFlags::Connect(&raddrs))?;
+        let fd = OwnedHandle::open(driver, Description::TcpStream, OpenFlags::Connect(&raddrs))?;
+
+        Self::new_with(fd, poller)
+    }
+
+    /// Opens a TCP connection to a remote host with global context `poller`, and waits until
+    /// the connection is established or `token` is cancelled.
+    ///
+    /// The resolved addresses are tried in order, the first established stream is returned.
+    #[cfg(feature = "current")]
+    pub async fn connect_cancellable<S: ToSocketAddrs>(
+        raddrs: S,
+        token: Option<&CancellationToken>,
+    ) -> io::Result<Self> {
+        Self::connect_cancellable_with(raddrs, get_poller()?, token).await
+    }
+
+    /// Opens a TCP connection to a remote host with customer `poller` handle, and waits until
+    /// the connection is established or `token` is cancelled.
+    ///
+    /// The connecting socket is closed on cancellation, and [`OperationCancelled`] error is returned.
+    pub async fn connect_cancellable_with<S: ToSocketAddrs>(
+        raddrs: S,
+        poller: Handle,
+        token: Option<&CancellationToken>,
+    ) -> io::Result<Self> {
+        let mut last_error = None;
+
+        for raddr in raddrs.to_socket_addrs()? {
+            let attempt = async {
+                let stream = Self::connect_nonblocking_with(raddr, poller)?;
+
+                stream.connected().await?;
+
+                Ok(stream)
+            };
+
+            match cancellable(attempt, token).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) if OperationCancelled::from_io_error(&err).is_some() => return Err(err),
+                Err(err) => {
+                    log::trace!("connect to {} failed, err={}", raddr, err);
+
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "raddrs is empty")))
+    }
+
+    /// Opens a TCP connection to a remote host using the happy eyeballs (RFC 8305) algorithm.
+    ///
+    /// Connection attempts to the resolved addresses(IPv6 and IPv4 interleaved, IPv6 first)
+    /// are started [`CONNECTION_ATTEMPT_DELAY`] apart, the first established stream is returned
+    /// and the other attempts are cancelled.
+    #[cfg(feature = "current")]
+    pub async fn connect_happy<S: ToSocketAddrs>(raddrs: S) -> io::Result<Self> {
+        Self::connect_happy_with(raddrs, CONNECTION_ATTEMPT_DELAY, get_poller()?).await
+    }
+
+    /// Opens a TCP connection to a remote host using the happy eyeballs (RFC 8305) algorithm
+    /// with customer connection attempt `delay` and `poller` handle.
+    #[cfg(feature = "current")]
+    pub async fn connect_happy_with<S: ToSocketAddrs>(
+        raddrs: S,
+        delay: Duration,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        let mut raddrs = interleave_addrs(raddrs.to_socket_addrs()?).into_iter();
+
+        let mut attempts = FuturesUnordered::new();
+
+        let mut last_error = None;
+
+        loop {
+            if attempts.is_empty() {
+                match raddrs.next() {
+                    Some(raddr) => attempts.push(Self::connect_attempt(raddr, poller)),
+                    None => {
+                        return Err(last_error.unwrap_or_else(|| {
+                            io::Error::new(io::ErrorKind::InvalidInput, "raddrs is empty")
+                        }))
+                    }
+                }
+            }
+
+            let next_attempt = if raddrs.len() > 0 { Some(delay) } else { None };
+
+            let result = timeout(async { Ok(attempts.next().await) }, next_attempt).await;
+
+            match result {
+                Ok(Some(Ok(stream))) => return Ok(stream),
+                Ok(Some(Err(err))) => {
+                    log::trace!("happy eyeballs connection attempt failed, err={}", err);
+
+                    last_error = Some(err);
+
+                    // start next attempt immediately.
+                    if let Some(raddr) = raddrs.next() {
+                        attempts.push(Self::connect_attempt(raddr, poller));
+                    }
+                }
+                Ok(None) => {}
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    if let Some(raddr) = raddrs.next() {
+                        attempts.push(Self::connect_attempt(raddr, poller));
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Starts a non-blocking connection to `raddr` with global context `poller`,
+    /// returns before the connection is established.
+    ///
+    /// Use [`connected`](Self::connected) to wait for the connection result.
+    #[cfg(feature = "current")]
+    pub fn connect_nonblocking(raddr: SocketAddr) -> io::Result<Self> {
+        Self::connect_nonblocking_with(raddr, get_poller()?)
+    }
+
+    /// Starts a non-blocking connection to `raddr` with customer `poller` handle,
+    /// returns before the connection is established.
+    ///
+    /// Use [`connected`](Self::connected) to wait for the connection result.
+    pub fn connect_nonblocking_with(raddr: SocketAddr, poller: Handle) -> io::Result<Self> {
+        let driver = get_driver()?;
+
+        let fd = OwnedHandle::open(
+            driver,
+            Description::TcpStream,
+            OpenFlags::NonblockingConnect(raddr),
+        )?;
+
+        Self::new_with(fd, poller)
+    }
+
+    /// Adopts the externally created `stream` with global context `driver` / `poller`,
+    /// e.g. the socket inherited from parent process.
+    ///
+    /// The stream is switched to non-blocking mode.
+    #[cfg(feature = "current")]
+    pub fn from_std(stream: std::net::TcpStream) -> io::Result<Self> {
+        Self::from_std_with(stream, get_driver()?, get_poller()?)
+    }
+
+    /// Adopts the externally created `stream` with providing `driver` / `poller`.
+    pub fn from_std_with(
+        stream: std::net::TcpStream,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        let fd = OwnedHandle::open(driver, Description::TcpStream, raw_open_flags(stream))?;
 
-        Self::new_with(driver, fd, poller)
+        Self::new_with(fd, poller)
+    }
+
+    /// Converts this stream into [`std::net::TcpStream`], e.g. to pass it to another process.
+    ///
+    /// The returned stream is a duplicate of the underlying socket and stays in non-blocking mode.
+    pub fn into_std(self) -> io::Result<std::net::TcpStream> {
+        #[cfg(unix)]
+        {
+            use std::os::fd::FromRawFd;
+
+            let fd = self.fd.fd_cntl(Cmd::DupRawFd)?.try_into_raw_fd()?;
+
+            // Safety: the duplicated fd is owned by the caller.
+            Ok(unsafe { std::net::TcpStream::from_raw_fd(fd) })
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::io::FromRawSocket;
+
+            let socket = self.fd.fd_cntl(Cmd::DupRawFd)?.try_into_raw_socket()?;
+
+            // Safety: the duplicated socket is owned by the caller.
+            Ok(unsafe { std::net::TcpStream::from_raw_socket(socket) })
+        }
+    }
+
+    /// Starts a non-blocking connection to `raddr`, and waits until the connection is established.
+    #[cfg(feature = "current")]
+    async fn connect_attempt(raddr: SocketAddr, poller: Handle) -> io::Result<Self> {
+        let stream = Self::connect_nonblocking_with(raddr, poller)?;
+
+        stream.connected().await?;
+
+        Ok(stream)
+    }
+
+    /// Waits until the connection is established, returns the connecting error, e.g. `ECONNREFUSED`.
+    ///
+    /// Returns immediately if the stream is already connected.
+    pub async fn connected(&self) -> io::Result<()> {
+        would_block(|cx| self.fd.fd_cntl(Cmd::PollConnect(cx.waker().clone())))
+            .await
+            .map(|_| ())
+    }
+
+    /// Returns the socket address of the remote peer of this connection.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.fd.fd_cntl(Cmd::RemoteAddr)?.try_into_sockaddr()
     }
 
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        self.driver
-            .fd_cntl(self.fd, Cmd::LocalAddr)?
-            .try_into_sockaddr()
+        self.fd.fd_cntl(Cmd::LocalAddr)?.try_into_sockaddr()
+    }
+
+    /// Sets the `SO_RCVBUF` option of this stream.
+    ///
+    /// The os may adjust the value, see [`BindOptions::recv_buffer_size`].
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.fd
+            .fd_cntl(Cmd::SetBufferSize(SocketBuffer::Recv, size))
+            .map(|_| ())
+    }
+
+    /// Returns the `SO_RCVBUF` option of this stream.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.fd
+            .fd_cntl(Cmd::BufferSize(SocketBuffer::Recv))?
+            .try_into_datalen()
+    }
+
+    /// Sets the `SO_SNDBUF` option of this stream.
+    ///
+    /// The os may adjust the value, see [`BindOptions::send_buffer_size`].
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.fd
+            .fd_cntl(Cmd::SetBufferSize(SocketBuffer::Send, size))
+            .map(|_| ())
+    }
+
+    /// Returns the `SO_SNDBUF` option of this stream.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.fd
+            .fd_cntl(Cmd::BufferSize(SocketBuffer::Send))?
+            .try_into_datalen()
     }
 
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
-        self.driver.fd_cntl(self.fd, Cmd::Shutdown(how))?;
+        self.fd.fd_cntl(Cmd::Shutdown(how))?;
 
         Ok(())
     }
+
+    /// Tries to read data from the stream once, without registering the current task to be woken.
+    ///
+    /// Returns [`WouldBlock`](io::ErrorKind::WouldBlock) error if the stream is not readable.
+    pub fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fd.fd_cntl(Cmd::TryRead(buf))?.try_into_datalen()
+    }
+
+    /// Tries to write data to the stream once, without registering the current task to be woken.
+    ///
+    /// Returns [`WouldBlock`](io::ErrorKind::WouldBlock) error if the stream is not writable.
+    pub fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+        self.fd.fd_cntl(Cmd::TryWrite(buf))?.try_into_datalen()
+    }
+
+    /// Writes the whole owned `buf` to the stream without copying it.
+    ///
+    /// Returns once `buf` is taken by the driver, which may queue the unwritten data and send them
+    /// when the stream is writable. Call [`flush`](futures::AsyncWriteExt::flush) to wait for the
+    /// queued data written, the data still queued are discarded if the stream is dropped.
+    pub async fn write_bytes(&self, mut buf: Bytes) -> io::Result<()> {
+        let mut timer = IoTimer::default();
+
+        while !buf.is_empty() {
+            let write_size = poll_fn(|cx| {
+                let poll = self.poll_write_owned_priv(cx, &buf);
+
+                self.write_deadline.poll_io_with(cx, &mut timer, poll)
+            })
+            .await?;
+
+            buf.advance(write_size);
+        }
+
+        Ok(())
+    }
+
+    /// Reads data into `buf`, returns the number of bytes read.
+    ///
+    /// Unlike [`AsyncRead`], the timer of [`read_timeout`](Self::read_timeout) is owned by
+    /// the returned future, so cancelling this operation never shortens the next one.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut timer = IoTimer::default();
+
+        poll_fn(|cx| {
+            let poll = self.poll_read_priv(cx, buf);
+
+            self.read_deadline.poll_io_with(cx, &mut timer, poll)
+        })
+        .await
+    }
+
+    /// Writes data from `buf`, returns the number of bytes written.
+    ///
+    /// The timer of [`write_timeout`](Self::write_timeout) is owned by the returned future, see [`read`](Self::read).
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut timer = IoTimer::default();
+
+        poll_fn(|cx| {
+            let poll = self.poll_write_priv(cx, buf);
+
+            self.write_deadline.poll_io_with(cx, &mut timer, poll)
+        })
+        .await
+    }
+
+    /// Sets the timeout of each read operation, `None` means the read operations never time out.
+    ///
+    /// The read operation that is pending longer than `timeout` fails with [`TimedOut`](io::ErrorKind::TimedOut) error,
+    /// the stream is still usable after that.
+    ///
+    /// The poll-style [`AsyncRead`] operations share one timer, which keeps running until the operation
+    /// completes, so use [`read`](Self::read) if the operations may be cancelled.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) {
+        self.read_deadline.set_timeout(timeout);
+    }
+
+    /// Returns the read timeout of this stream.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_deadline.timeout()
+    }
+
+    /// Sets the timeout of each write operation, `None` means the write operations never time out.
+    ///
+    /// See [`set_read_timeout`](Self::set_read_timeout) for more information.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) {
+        self.write_deadline.set_timeout(timeout);
+    }
+
+    /// Returns the write timeout of this stream.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_deadline.timeout()
+    }
+
+    /// Waits for the stream to become readable, without consuming any data.
+    pub async fn readable(&self) -> io::Result<()> {
+        self.poll_readiness(Interest::Readable).await
+    }
+
+    /// Waits for the stream to become writable, without writing any data.
+    pub async fn writable(&self) -> io::Result<()> {
+        self.poll_readiness(Interest::Writable).await
+    }
+
+    /// Waits for the [`Error`](Interest::Error) / [`Hup`](Interest::Hup) / [`Priority`](Interest::Priority)
+    /// events in `interests`, returns the occurred ones.
+    ///
+    /// Unlike [`readable`](Self::readable), the peer hangup and out-of-band data are distinguished
+    /// from the normal data, without reading the stream. The returned events are taken,
+    /// the next call waits until they occur again.
+    pub async fn events(&self, interests: Interest) -> io::Result<Interest> {
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::PollEvents {
+                    waker: cx.waker().clone(),
+                    interests,
+                })?
+                .try_into_events()
+        })
+        .await
+    }
+
+    async fn poll_readiness(&self, interest: Interest) -> io::Result<()> {
+        would_block(|cx| {
+            self.fd.fd_cntl(Cmd::PollReadiness {
+                waker: cx.waker().clone(),
+                interest,
+            })
+        })
+        .await
+        .map(|_| ())
+    }
+}
+
+impl TcpStream {
+    fn poll_read_priv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        poll_would_block(|| {
+            self.fd
+                .fd_cntl(Cmd::Read {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_datalen()
+        })
+    }
+
+    fn poll_write_priv(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        poll_would_block(|| {
+            self.fd
+                .fd_cntl(Cmd::Write {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_datalen()
+        })
+    }
+
+    fn poll_write_owned_priv(&self, cx: &mut Context<'_>, buf: &Bytes) -> Poll<io::Result<usize>> {
+        poll_would_block(|| {
+            self.fd
+                .fd_cntl(Cmd::WriteOwned {
+                    waker: cx.waker().clone(),
+                    buf: buf.clone(),
+                })?
+                .try_into_datalen()
+        })
+    }
+
+    fn poll_flush_priv(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let poll = poll_would_block(|| self.fd.fd_cntl(Cmd::Flush(cx.waker().clone())).map(|_| ()));
+
+        self.write_deadline.poll_io(cx, poll)
+    }
+
+    /// Flushes the queued data, then shuts down the write direction of the stream.
+    fn poll_close_priv(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush_priv(cx))?;
+
+        Poll::Ready(self.shutdown(Shutdown::Write))
+    }
 }
 
 impl AsyncWrite for &TcpStream {
@@ -78,31 +499,25 @@ impl AsyncWrite for &TcpStream {
         cx: &mut std::task::Context<'_>,
         buf: &[u8],
     ) -> std::task::Poll<io::Result<usize>> {
-        poll_would_block(|| {
-            self.driver
-                .fd_cntl(
-                    self.fd,
-                    Cmd::Write {
-                        waker: cx.waker().clone(),
-                        buf,
-                    },
-                )?
-                .try_into_datalen()
-        })
+        let poll = self.poll_write_priv(cx, buf);
+
+        self.write_deadline.poll_io(cx, poll)
     }
 
+    /// Waits for the data queued by [`write_bytes`](TcpStream::write_bytes) written.
     fn poll_flush(
         self: std::pin::Pin<&mut Self>,
-        _cx: &mut std::task::Context<'_>,
+        cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
+        self.poll_flush_priv(cx)
     }
 
+    /// Flushes the queued data, then shuts down the write direction of the stream.
     fn poll_close(
         self: std::pin::Pin<&mut Self>,
-        _cx: &mut std::task::Context<'_>,
+        cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
+        self.poll_close_priv(cx)
     }
 }
 
@@ -112,17 +527,9 @@ impl AsyncRead for &TcpStream {
         cx: &mut std::task::Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        poll_would_block(|| {
-            self.driver
-                .fd_cntl(
-                    self.fd,
-                    Cmd::Read {
-                        waker: cx.waker().clone(),
-                        buf,
-                    },
-                )?
-                .try_into_datalen()
-        })
+        let poll = self.poll_read_priv(cx, buf);
+
+        self.read_deadline.poll_io(cx, poll)
     }
 }
 
@@ -132,31 +539,25 @@ impl AsyncWrite for TcpStream {
         cx: &mut std::task::Context<'_>,
         buf: &[u8],
     ) -> std::task::Poll<io::Result<usize>> {
-        poll_would_block(|| {
-            self.driver
-                .fd_cntl(
-                    self.fd,
-                    Cmd::Write {
-                        waker: cx.waker().clone(),
-                        buf,
-                    },
-                )?
-                .try_into_datalen()
-        })
+        let poll = self.poll_write_priv(cx, buf);
+
+        self.write_deadline.poll_io(cx, poll)
     }
 
+    /// Waits for the data queued by [`write_bytes`](TcpStream::write_bytes) written.
     fn poll_flush(
         self: std::pin::Pin<&mut Self>,
-        _cx: &mut std::task::Context<'_>,
+        cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
+        self.poll_flush_priv(cx)
     }
 
+    /// Flushes the queued data, then shuts down the write direction of the stream.
     fn poll_close(
         self: std::pin::Pin<&mut Self>,
-        _cx: &mut std::task::Context<'_>,
+        cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
+        self.poll_close_priv(cx)
     }
 }
 
@@ -166,25 +567,494 @@ impl AsyncRead for TcpStream {
         cx: &mut std::task::Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        poll_would_block(|| {
-            self.driver
-                .fd_cntl(
-                    self.fd,
-                    Cmd::Read {
-                        waker: cx.waker().clone(),
-                        buf,
-                    },
-                )?
-                .try_into_datalen()
-        })
+        let poll = self.poll_read_priv(cx, buf);
+
+        self.read_deadline.poll_io(cx, poll)
     }
 }
 
 impl Drop for TcpStream {
     fn drop(&mut self) {
-        self.driver
-            .fd_cntl(self.poller, Cmd::Deregister(self.fd))
+        if let Err(err) = self.fd.deregister(self.poller) {
+            log::error!(
+                "deregister tcp stream failed, fd={:?}, err={}",
+                self.fd,
+                err
+            );
+        }
+    }
+}
+
+#[cfg(unix)]
+impl std::os::fd::AsRawFd for TcpStream {
+    /// # Panics
+    ///
+    /// Panics if the driver does not support [`Cmd::AsRawFd`].
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.fd
+            .fd_cntl(Cmd::AsRawFd)
+            .and_then(|resp| resp.try_into_raw_fd())
+            .expect("get raw fd of tcp stream")
+    }
+}
+
+#[cfg(windows)]
+impl std::os::windows::io::AsRawSocket for TcpStream {
+    /// # Panics
+    ///
+    /// Panics if the driver does not support [`Cmd::AsRawFd`].
+    fn as_raw_socket(&self) -> std::os::windows::io::RawSocket {
+        self.fd
+            .fd_cntl(Cmd::AsRawFd)
+            .and_then(|resp| resp.try_into_raw_socket())
+            .expect("get raw socket of tcp stream")
+    }
+}
+
+/// Converts the std socket into the open flags that transfer its ownership to the driver.
+#[cfg(unix)]
+pub(crate) fn raw_open_flags<S: std::os::fd::IntoRawFd>(socket: S) -> OpenFlags<'static> {
+    OpenFlags::RawFd(socket.into_raw_fd())
+}
+
+/// Converts the std socket into the open flags that transfer its ownership to the driver.
+#[cfg(windows)]
+pub(crate) fn raw_open_flags<S: std::os::windows::io::IntoRawSocket>(
+    socket: S,
+) -> OpenFlags<'static> {
+    OpenFlags::RawSocket(socket.into_raw_socket())
+}
+
+/// Sorts the addresses by interleaving address families, starting with IPv6.
+fn interleave_addrs<I: IntoIterator<Item = SocketAddr>>(raddrs: I) -> Vec<SocketAddr> {
+    let (mut v6, mut v4): (Vec<_>, Vec<_>) = raddrs.into_iter().partition(|addr| addr.is_ipv6());
+
+    let mut v6 = v6.drain(..);
+    let mut v4 = v4.drain(..);
+
+    let mut addrs = vec![];
+
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return addrs,
+            (v6, v4) => {
+                addrs.extend(v6);
+                addrs.extend(v4);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use hala_io::{current::get_poller, test::io_test};
+
+    use super::*;
+    use crate::TcpListener;
+
+    #[test]
+    fn test_interleave_addrs() {
+        let addrs: Vec<SocketAddr> = vec![
+            "127.0.0.1:1".parse().unwrap(),
+            "127.0.0.2:1".parse().unwrap(),
+            "[::1]:1".parse().unwrap(),
+        ];
+
+        assert_eq!(
+            interleave_addrs(addrs.clone()),
+            vec![addrs[2], addrs[0], addrs[1]]
+        );
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_fd_cntl_batch() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        stream.connected().await.unwrap();
+
+        let (server, _) = listener.accept().await.unwrap();
+
+        let mut buf = [0; 5];
+
+        let results = stream.fd.driver().fd_cntl_batch(
+            *stream.fd.as_handle(),
+            &mut [
+                Cmd::TryRead(&mut buf),
+                Cmd::TryWrite(b"hello"),
+                Cmd::LocalAddr,
+            ],
+        );
+
+        assert_eq!(
+            results[0].as_ref().unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        assert_eq!(
+            results[1]
+                .as_ref()
+                .unwrap()
+                .clone()
+                .try_into_datalen()
+                .unwrap(),
+            5
+        );
+
+        assert_eq!(
+            results[2]
+                .as_ref()
+                .unwrap()
+                .clone()
+                .try_into_sockaddr()
+                .unwrap(),
+            server.peer_addr().unwrap()
+        );
+
+        server.readable().await.unwrap();
+
+        assert_eq!(server.try_read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_write_bytes() {
+        use futures::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        stream.connected().await.unwrap();
+
+        stream.set_send_buffer_size(4096).unwrap();
+
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        let data = Bytes::from((0..200 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>());
+
+        // The data beyond the socket buffer are queued by the driver.
+        stream.write_bytes(data.clone()).await.unwrap();
+
+        // The queued data are sent in background without flushing.
+        let mut buf = vec![0; data.len()];
+
+        server.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(buf, data);
+
+        stream.write_bytes(data.slice(..1024)).await.unwrap();
+        stream.write_all(b"hello").await.unwrap();
+        stream.close().await.unwrap();
+
+        let mut buf = vec![];
+
+        server.read_to_end(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..1024], &data[..1024]);
+        assert_eq!(&buf[1024..], b"hello");
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_connect_cancellable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let laddr = listener.local_addr().unwrap();
+
+        let token = CancellationToken::new();
+
+        let stream = TcpStream::connect_cancellable(laddr, Some(&token))
+            .await
             .unwrap();
-        self.driver.fd_close(self.fd).unwrap()
+
+        let (server, _) = listener.accept().await.unwrap();
+
+        assert_eq!(stream.local_addr().unwrap(), server.peer_addr().unwrap());
+
+        token.cancel();
+
+        let err = TcpStream::connect_cancellable(laddr, Some(&token.child_token()))
+            .await
+            .unwrap_err();
+
+        assert!(OperationCancelled::from_io_error(&err).is_some());
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_readiness() {
+        use futures::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.writable().await.unwrap();
+
+        assert!(futures::poll!(Box::pin(server.readable())).is_pending());
+
+        client.write_all(b"hello").await.unwrap();
+
+        server.readable().await.unwrap();
+
+        // Readiness check doesn't consume data.
+        server.readable().await.unwrap();
+
+        let mut buf = [0; 5];
+
+        server.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_events() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (server, _) = listener.accept().await.unwrap();
+
+        client.connected().await.unwrap();
+
+        assert!(futures::poll!(Box::pin(server.events(Interest::Hup))).is_pending());
+
+        client.shutdown(Shutdown::Write).unwrap();
+
+        assert_eq!(
+            server
+                .events(Interest::Hup | Interest::Error)
+                .await
+                .unwrap(),
+            Interest::Hup
+        );
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_try_read_write() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (server, _) = listener.accept().await.unwrap();
+
+        let mut buf = [0; 5];
+
+        assert_eq!(
+            server.try_read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        client.writable().await.unwrap();
+
+        assert_eq!(client.try_write(b"hello").unwrap(), 5);
+
+        server.readable().await.unwrap();
+
+        assert_eq!(server.try_read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[cfg(unix)]
+    #[hala_test::test(io_test)]
+    async fn test_std_interop() {
+        use std::io::{Read, Write};
+        use std::os::fd::AsRawFd;
+
+        use futures::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let raw_fd = listener.as_raw_fd();
+
+        let listener = TcpListener::from_std(listener).unwrap();
+
+        assert_eq!(listener.as_raw_fd(), raw_fd);
+
+        let mut client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (server, _) = listener.accept().await.unwrap();
+
+        // Pass the accepted socket through std type and adopt it again.
+        let mut server = TcpStream::from_std(server.into_std().unwrap()).unwrap();
+
+        client.write_all(b"hello").unwrap();
+
+        let mut buf = [0; 5];
+
+        server.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(&buf, b"hello");
+
+        server.write_all(b"world").await.unwrap();
+
+        client.read_exact(&mut buf).unwrap();
+
+        assert_eq!(&buf, b"world");
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_connect_happy() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let raddr = listener.local_addr().unwrap();
+
+        // The first address refuses connection.
+        let closed = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+            listener.local_addr().unwrap()
+        };
+
+        let stream = TcpStream::connect_happy_with(
+            [closed, raddr].as_slice(),
+            Duration::from_secs(10),
+            get_poller().unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let (incoming, _) = listener.accept().await.unwrap();
+
+        assert_eq!(stream.local_addr().unwrap(), incoming.peer_addr().unwrap());
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_connected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let raddr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_nonblocking(raddr).unwrap();
+
+        stream.connected().await.unwrap();
+
+        assert_eq!(stream.peer_addr().unwrap(), raddr);
+
+        // Waiting on the established stream returns immediately.
+        stream.connected().await.unwrap();
+
+        drop(listener);
+
+        // The refused connection is reported by `connected`, instead of the first write.
+        let stream = TcpStream::connect_nonblocking(raddr).unwrap();
+
+        assert_eq!(
+            stream.connected().await.unwrap_err().kind(),
+            io::ErrorKind::ConnectionRefused
+        );
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_copy_bidirectional() {
+        use futures::{AsyncReadExt, AsyncWriteExt};
+        use hala_io::current::executor::io_spawn;
+
+        let echo = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let echo_addr = echo.local_addr().unwrap();
+
+        io_spawn(async move {
+            let (mut stream, _) = echo.accept().await?;
+
+            let mut buf = vec![];
+
+            stream.read_to_end(&mut buf).await?;
+
+            stream.write_all(&buf).await?;
+
+            stream.close().await
+        })
+        .unwrap();
+
+        let proxy = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let proxy_addr = proxy.local_addr().unwrap();
+
+        let (sender, receiver) = hala_future::oneshot::channel();
+
+        io_spawn(async move {
+            let (mut stream, _) = proxy.accept().await?;
+
+            let mut upstream = TcpStream::connect(echo_addr)?;
+
+            let counters = copy_bidirectional(&mut stream, &mut upstream).await?;
+
+            _ = sender.send(counters);
+
+            Ok(())
+        })
+        .unwrap();
+
+        let mut stream = TcpStream::connect(proxy_addr).unwrap();
+
+        stream.write_all(b"hello world").await.unwrap();
+
+        stream.close().await.unwrap();
+
+        let mut buf = vec![];
+
+        stream.read_to_end(&mut buf).await.unwrap();
+
+        assert_eq!(buf, b"hello world");
+
+        assert_eq!(receiver.await.unwrap(), (11, 11));
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_read_timeout() {
+        use futures::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.set_read_timeout(Some(Duration::from_millis(50)));
+
+        assert_eq!(client.read_timeout(), Some(Duration::from_millis(50)));
+
+        let mut buf = [0; 5];
+
+        let err = client.read(&mut buf).await.unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        // The stream is still usable after timeout.
+        server.write_all(b"hello").await.unwrap();
+
+        client.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_read_timeout_cancelled() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (_server, _) = listener.accept().await.unwrap();
+
+        client.set_read_timeout(Some(Duration::from_millis(100)));
+
+        let mut buf = [0; 5];
+
+        // The read operation is cancelled before the timeout.
+        timeout(client.read(&mut buf), Some(Duration::from_millis(80)))
+            .await
+            .unwrap_err();
+
+        let now = Instant::now();
+
+        let err = client.read(&mut buf).await.unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(now.elapsed() >= Duration::from_millis(90));
     }
 }
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use futures::{AsyncReadExt, AsyncWriteExt};
+use hala_io::{
+    current::executor::io_spawn,
+    mock::{get_mock_driver, mock_test},
+    sleep,
+};
+use hala_tcp::{TcpListener, TcpStream};
+
+#[hala_test::test(mock_test)]
+async fn test_mock_echo() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+    let raddr = listener.local_addr().unwrap();
+
+    io_spawn(async move {
+        let (mut stream, _) = listener.accept().await?;
+
+        let mut buf = vec![0; 1024];
+
+        loop {
+            let read_size = stream.read(&mut buf).await?;
+
+            if read_size == 0 {
+                return Ok(());
+            }
+
+            stream.write_all(&buf[..read_size]).await?;
+        }
+    })
+    .unwrap();
+
+    let mut stream = TcpStream::connect(raddr).unwrap();
+
+    stream.write_all(b"hello world").await.unwrap();
+
+    let mut buf = vec![0; 11];
+
+    stream.read_exact(&mut buf).await.unwrap();
+
+    assert_eq!(buf, b"hello world");
+}
+
+#[hala_test::test(mock_test)]
+async fn test_mock_clock() {
+    let mut sleep = Box::pin(sleep(Duration::from_secs(3600)));
+
+    // the sleep future never expires by the wall clock.
+    assert!(futures::poll!(&mut sleep).is_pending());
+
+    get_mock_driver().advance(Duration::from_secs(3600));
+
+    sleep.await.unwrap();
+}
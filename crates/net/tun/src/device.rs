@@ -0,0 +1,269 @@
+use std::{
+    fs::File,
+    future::poll_fn,
+    io::{self, Read, Write},
+    net::Ipv4Addr,
+    os::fd::AsRawFd,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite};
+
+#[cfg(feature = "current")]
+use hala_io::current::*;
+
+use ::mio::{event::Source, unix::SourceFd, Registry, Token};
+use hala_io::{
+    mio::{open_external, poll_external_io},
+    *,
+};
+
+use crate::sys;
+
+/// The id of [`External`](Description::External) handle of tun device.
+const TUN_EXTERNAL_ID: usize = 0x74756e;
+
+/// The kind of virtual network device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunKind {
+    /// Layer 3 device, which reads and writes raw IP packets.
+    Tun,
+    /// Layer 2 device, which reads and writes ethernet frames.
+    Tap,
+}
+
+/// The device file shared by [`TunDevice`] and the poller.
+struct TunSource(Arc<File>);
+
+impl Source for TunSource {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: ::mio::Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.0.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: ::mio::Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.0.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.0.as_raw_fd()).deregister(registry)
+    }
+}
+
+/// An asynchronous TUN/TAP device, each read / write transfers one packet.
+///
+/// The packet information header is disabled (`IFF_NO_PI`), so the raw IP packets
+/// (or ethernet frames for tap device) are transferred.
+pub struct TunDevice {
+    file: Arc<File>,
+    name: String,
+    fd: OwnedHandle,
+    poller: Handle,
+}
+
+impl TunDevice {
+    /// Creates the virtual network device `name` and registers it with the global context poller.
+    ///
+    /// The `name` may contain `%d` to let the kernel allocate the number, e.g. `tun%d`.
+    #[cfg(feature = "current")]
+    pub fn open(name: &str, kind: TunKind) -> io::Result<Self> {
+        Self::open_with(name, kind, get_driver()?, get_poller()?)
+    }
+
+    /// Creates the virtual network device `name` with providing `driver` / `poller`,
+    /// the `driver` must be the mio driver.
+    pub fn open_with(
+        name: &str,
+        kind: TunKind,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        let (file, name) = sys::open(name, kind)?;
+
+        let file = Arc::new(file);
+
+        // Safety: the external handle is opened by the mio driver.
+        let fd = unsafe {
+            OwnedHandle::from_raw(
+                driver,
+                open_external(TUN_EXTERNAL_ID, TunSource(file.clone())),
+            )
+        };
+
+        fd.register(
+            poller,
+            Interest::Readable | Interest::Writable,
+            PollMode::Edge,
+        )?;
+
+        Ok(Self {
+            file,
+            name,
+            fd,
+            poller,
+        })
+    }
+
+    /// Returns the interface name of the device.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the MTU of the device.
+    pub fn mtu(&self) -> io::Result<u32> {
+        sys::mtu(&self.name)
+    }
+
+    /// Sets the MTU of the device.
+    pub fn set_mtu(&self, mtu: u32) -> io::Result<()> {
+        sys::set_mtu(&self.name, mtu)
+    }
+
+    /// Assigns the ipv4 address and netmask to the device.
+    pub fn set_ipv4(&self, addr: Ipv4Addr, netmask: Ipv4Addr) -> io::Result<()> {
+        sys::set_ipv4(&self.name, addr, netmask)
+    }
+
+    /// Brings the device up or down.
+    pub fn set_up(&self, up: bool) -> io::Result<()> {
+        sys::set_up(&self.name, up)
+    }
+
+    /// Receives one packet from the device. On success, returns the number of bytes read.
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_recv(cx, buf)).await
+    }
+
+    /// Sends one packet to the device. On success, returns the number of bytes written.
+    pub async fn send(&self, packet: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_send(cx, packet)).await
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        poll_external_io(
+            self.fd.driver(),
+            *self.fd.as_handle(),
+            cx,
+            Interest::Readable,
+            || (&*self.file).read(buf),
+        )
+    }
+
+    fn poll_send(&self, cx: &mut Context<'_>, packet: &[u8]) -> Poll<io::Result<usize>> {
+        poll_external_io(
+            self.fd.driver(),
+            *self.fd.as_handle(),
+            cx,
+            Interest::Writable,
+            || (&*self.file).write(packet),
+        )
+    }
+}
+
+impl AsyncRead for TunDevice {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_recv(cx, buf)
+    }
+}
+
+impl AsyncWrite for TunDevice {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_send(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for TunDevice {
+    fn drop(&mut self) {
+        if let Err(err) = self.fd.deregister(self.poller) {
+            log::error!(
+                "deregister tun device failed, name={}, err={}",
+                self.name,
+                err
+            );
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::net::UdpSocket;
+
+    use hala_io::test::io_test;
+
+    use super::*;
+
+    #[hala_test::test(io_test)]
+    async fn test_tun_device() {
+        // Creating device requires `CAP_NET_ADMIN`.
+        let device = match TunDevice::open("halatun%d", TunKind::Tun) {
+            Ok(device) => device,
+            Err(err) => {
+                log::warn!("skip tun device test, err={}", err);
+                return;
+            }
+        };
+
+        assert!(device.name().starts_with("halatun"));
+
+        device.set_mtu(1400).unwrap();
+
+        assert_eq!(device.mtu().unwrap(), 1400);
+
+        device
+            .set_ipv4(
+                Ipv4Addr::new(10, 254, 0, 1),
+                Ipv4Addr::new(255, 255, 255, 0),
+            )
+            .unwrap();
+
+        device.set_up(true).unwrap();
+
+        // The datagram to the peer in the device subnet is routed to the device.
+        let socket = UdpSocket::bind("10.254.0.1:0").unwrap();
+
+        socket.send_to(b"hello", "10.254.0.2:1812").unwrap();
+
+        let mut buf = vec![0; 1500];
+
+        loop {
+            let len = device.recv(&mut buf).await.unwrap();
+
+            // Skips the packets sent by the kernel, e.g. ipv6 router solicitation.
+            if len < 20 || buf[0] >> 4 != 4 || buf[9] != libc::IPPROTO_UDP as u8 {
+                continue;
+            }
+
+            assert_eq!(&buf[16..20], &[10, 254, 0, 2]);
+            assert_eq!(&buf[len - 5..len], b"hello");
+
+            break;
+        }
+    }
+}
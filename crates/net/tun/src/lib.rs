@@ -0,0 +1,13 @@
+//! Asynchronous TUN/TAP device for userspace networking, e.g. VPN.
+//!
+//! The device is registered with the poller of mio driver as an external source,
+//! it's only implemented on linux, the other platforms return
+//! [`Unsupported`](std::io::ErrorKind::Unsupported) error.
+
+#[cfg(unix)]
+mod sys;
+
+#[cfg(unix)]
+mod device;
+#[cfg(unix)]
+pub use device::*;
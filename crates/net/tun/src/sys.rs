@@ -0,0 +1,187 @@
+//! The platform specific device operations.
+
+use std::{fs::File, io, net::Ipv4Addr};
+
+use crate::TunKind;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        ffi::CStr,
+        fs::OpenOptions,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::fs::OpenOptionsExt,
+        },
+    };
+
+    use super::*;
+
+    /// Creates a zeroed `ifreq` with interface `name`.
+    fn ifreq(name: &str) -> io::Result<libc::ifreq> {
+        if name.len() >= libc::IFNAMSIZ || name.as_bytes().contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid interface name, name={}", name),
+            ));
+        }
+
+        // Safety: `ifreq` is a plain C structure, the all-zero value is valid.
+        let mut req: libc::ifreq = unsafe { std::mem::zeroed() };
+
+        for (dst, src) in req.ifr_name.iter_mut().zip(name.as_bytes()) {
+            *dst = *src as libc::c_char;
+        }
+
+        Ok(req)
+    }
+
+    fn sockaddr(addr: Ipv4Addr) -> libc::sockaddr {
+        let addr = libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: 0,
+            sin_addr: libc::in_addr {
+                s_addr: u32::from_ne_bytes(addr.octets()),
+            },
+            sin_zero: [0; 8],
+        };
+
+        // Safety: `sockaddr_in` has the same size as `sockaddr`.
+        unsafe { std::mem::transmute(addr) }
+    }
+
+    /// Calls the interface configuration `request` by a temporary udp socket.
+    fn ioctl(request: libc::c_ulong, req: &mut libc::ifreq) -> io::Result<()> {
+        // Safety: the returned fd is checked and owned by `socket`.
+        let socket = unsafe {
+            let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            OwnedFd::from_raw_fd(fd)
+        };
+
+        // Safety: `req` is a valid `ifreq` structure during the call.
+        if unsafe { libc::ioctl(socket.as_raw_fd(), request as _, req as *mut libc::ifreq) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn open(name: &str, kind: TunKind) -> io::Result<(File, String)> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+            .open("/dev/net/tun")?;
+
+        let mut req = ifreq(name)?;
+
+        let flags = match kind {
+            TunKind::Tun => libc::IFF_TUN,
+            TunKind::Tap => libc::IFF_TAP,
+        };
+
+        req.ifr_ifru.ifru_flags = (flags | libc::IFF_NO_PI) as libc::c_short;
+
+        // Safety: `req` is a valid `ifreq` structure during the call.
+        if unsafe { libc::ioctl(file.as_raw_fd(), libc::TUNSETIFF as _, &mut req) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // The kernel fills the allocated name, e.g. `tun%d` is expanded to `tun0`.
+        // Safety: the name is nul-terminated by the kernel.
+        let name = unsafe { CStr::from_ptr(req.ifr_name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+
+        Ok((file, name))
+    }
+
+    pub(crate) fn mtu(name: &str) -> io::Result<u32> {
+        let mut req = ifreq(name)?;
+
+        ioctl(libc::SIOCGIFMTU, &mut req)?;
+
+        // Safety: the mtu field is filled by `SIOCGIFMTU`.
+        Ok(unsafe { req.ifr_ifru.ifru_mtu } as u32)
+    }
+
+    pub(crate) fn set_mtu(name: &str, mtu: u32) -> io::Result<()> {
+        let mut req = ifreq(name)?;
+
+        req.ifr_ifru.ifru_mtu = mtu as libc::c_int;
+
+        ioctl(libc::SIOCSIFMTU, &mut req)
+    }
+
+    pub(crate) fn set_ipv4(name: &str, addr: Ipv4Addr, netmask: Ipv4Addr) -> io::Result<()> {
+        let mut req = ifreq(name)?;
+
+        req.ifr_ifru.ifru_addr = sockaddr(addr);
+
+        ioctl(libc::SIOCSIFADDR, &mut req)?;
+
+        req.ifr_ifru.ifru_netmask = sockaddr(netmask);
+
+        ioctl(libc::SIOCSIFNETMASK, &mut req)
+    }
+
+    pub(crate) fn set_up(name: &str, up: bool) -> io::Result<()> {
+        let mut req = ifreq(name)?;
+
+        ioctl(libc::SIOCGIFFLAGS, &mut req)?;
+
+        // Safety: the flags field is filled by `SIOCGIFFLAGS`.
+        let flags = unsafe { req.ifr_ifru.ifru_flags };
+
+        req.ifr_ifru.ifru_flags = if up {
+            flags | libc::IFF_UP as libc::c_short
+        } else {
+            flags & !(libc::IFF_UP as libc::c_short)
+        };
+
+        ioctl(libc::SIOCSIFFLAGS, &mut req)
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::*;
+
+#[cfg(not(target_os = "linux"))]
+mod unsupported {
+    use super::*;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "tun device is only supported on linux",
+        )
+    }
+
+    pub(crate) fn open(_name: &str, _kind: TunKind) -> io::Result<(File, String)> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn mtu(_name: &str) -> io::Result<u32> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn set_mtu(_name: &str, _mtu: u32) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn set_ipv4(_name: &str, _addr: Ipv4Addr, _netmask: Ipv4Addr) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn set_up(_name: &str, _up: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) use unsupported::*;
@@ -0,0 +1,187 @@
+//! The synchronous udp socket for the codebases without async runtime.
+//!
+//! See the `blocking` module of hala-tcp, the [`UdpSocket`] runs [`crate::UdpSocket`] on its own
+//! [`BlockingPoller`].
+
+use std::{
+    fmt::Debug,
+    future::Future,
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
+
+use hala_io::*;
+use hala_sync::{Lockable, LockableNew, SpinMutex};
+
+#[cfg(feature = "current")]
+use hala_io::current::get_driver;
+
+/// The blocking version of [`crate::UdpSocket`].
+pub struct UdpSocket {
+    // Dropped before the poller it's registered to.
+    inner: crate::UdpSocket,
+    poller: BlockingPoller,
+    read_timeout: SpinMutex<Option<Duration>>,
+    write_timeout: SpinMutex<Option<Duration>>,
+}
+
+impl Debug for UdpSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "blocking::UdpSocket({:?})", self.inner.local_addr())
+    }
+}
+
+impl UdpSocket {
+    /// Creates a new udp socket bound to `laddrs` with global context `driver`.
+    #[cfg(feature = "current")]
+    pub fn bind<S: ToSocketAddrs>(laddrs: S) -> io::Result<Self> {
+        Self::bind_with(laddrs, get_driver()?)
+    }
+
+    /// Creates a new udp socket bound to `laddrs` with providing `driver`.
+    pub fn bind_with<S: ToSocketAddrs>(laddrs: S, driver: Driver) -> io::Result<Self> {
+        let poller = BlockingPoller::new_with(driver.clone())?;
+
+        let inner = crate::UdpSocket::bind_with(laddrs, driver, poller.poller())?;
+
+        Ok(Self::new(inner, poller))
+    }
+
+    /// Adopts the externally created `socket` with global context `driver`.
+    #[cfg(feature = "current")]
+    pub fn from_std(socket: std::net::UdpSocket) -> io::Result<Self> {
+        Self::from_std_with(socket, get_driver()?)
+    }
+
+    /// Adopts the externally created `socket` with providing `driver`.
+    ///
+    /// The socket is switched to non-blocking mode, the operations of the returned instance still block.
+    pub fn from_std_with(socket: std::net::UdpSocket, driver: Driver) -> io::Result<Self> {
+        let poller = BlockingPoller::new_with(driver.clone())?;
+
+        let inner = crate::UdpSocket::from_std_with(socket, driver, poller.poller())?;
+
+        Ok(Self::new(inner, poller))
+    }
+
+    fn new(inner: crate::UdpSocket, poller: BlockingPoller) -> Self {
+        Self {
+            inner,
+            poller,
+            read_timeout: SpinMutex::new(None),
+            write_timeout: SpinMutex::new(None),
+        }
+    }
+
+    /// Returns the local address that this socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Connects this socket to the remote peer, see [`crate::UdpSocket::connect`].
+    pub fn connect<S: ToSocketAddrs>(&self, raddrs: S) -> io::Result<()> {
+        self.inner.connect(raddrs)
+    }
+
+    /// Returns the address of the connected peer.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    /// Sets the timeout of each receive operation, `None` means the operations never time out.
+    ///
+    /// The operation that is blocked longer than `timeout` fails with [`TimedOut`](io::ErrorKind::TimedOut) error.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) {
+        *self.read_timeout.lock() = timeout;
+    }
+
+    /// Returns the read timeout of this socket.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        *self.read_timeout.lock()
+    }
+
+    /// Sets the timeout of each send operation, `None` means the operations never time out.
+    ///
+    /// See [`set_read_timeout`](Self::set_read_timeout) for more information.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) {
+        *self.write_timeout.lock() = timeout;
+    }
+
+    /// Returns the write timeout of this socket.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        *self.write_timeout.lock()
+    }
+
+    /// Sends data to the connected peer, blocks until the datagram is sent.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.block_on(self.inner.send(buf), self.write_timeout())
+    }
+
+    /// Receives one datagram from the connected peer, blocks until a datagram is received.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.block_on(self.inner.recv(buf), self.read_timeout())
+    }
+
+    /// Sends data to the given address, blocks until the datagram is sent.
+    pub fn send_to<S: ToSocketAddrs>(&self, buf: &[u8], target: S) -> io::Result<usize> {
+        self.block_on(self.inner.send_to(buf, target), self.write_timeout())
+    }
+
+    /// Receives one datagram, blocks until a datagram is received.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.block_on(self.inner.recv_from(buf), self.read_timeout())
+    }
+
+    fn block_on<Fut, R>(&self, fut: Fut, timeout: Option<Duration>) -> io::Result<R>
+    where
+        Fut: Future<Output = io::Result<R>>,
+    {
+        self.poller.block_on(timeout_with(
+            self.poller.driver().clone(),
+            self.poller.poller(),
+            fut,
+            timeout,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, thread, time::Duration};
+
+    use hala_io::mio::mio_driver;
+
+    use super::*;
+
+    #[test]
+    fn test_blocking_udp() {
+        let driver = mio_driver();
+
+        let receiver = UdpSocket::bind_with("127.0.0.1:0", driver.clone()).unwrap();
+
+        let raddr = receiver.local_addr().unwrap();
+
+        let sender = thread::spawn(move || {
+            let sender = UdpSocket::bind_with("127.0.0.1:0", driver).unwrap();
+
+            sender.send_to(b"hello world", raddr).unwrap();
+
+            sender.local_addr().unwrap()
+        });
+
+        let mut buf = [0; 1024];
+
+        let (read_size, from) = receiver.recv_from(&mut buf).unwrap();
+
+        assert_eq!(&buf[..read_size], b"hello world");
+        assert_eq!(from, sender.join().unwrap());
+
+        receiver.set_read_timeout(Some(Duration::from_millis(20)));
+
+        assert_eq!(
+            receiver.recv_from(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::TimedOut
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::poll_fn,
+    io,
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use hala_sync::{Lockable, SpinMutex};
+
+#[cfg(feature = "current")]
+use hala_io::current::*;
+
+use hala_io::*;
+
+use crate::UdpSocket;
+
+/// The max number of datagrams queued for one connection, the subsequent datagrams are dropped
+/// until the connection receives.
+const MAX_QUEUED_DATAGRAMS: usize = 1024;
+
+#[derive(Default)]
+struct ConnQueue {
+    datagrams: VecDeque<Vec<u8>>,
+    /// The waker of the task waiting on [`UdpGroupConn::recv`].
+    waker: Option<Waker>,
+}
+
+#[derive(Default)]
+struct RawGroupSocket {
+    /// The connections of this socket, indexed by peer address.
+    conns: HashMap<SocketAddr, ConnQueue>,
+}
+
+impl RawGroupSocket {
+    /// Takes the waker of one waiting connection other than `raddr`.
+    ///
+    /// The socket is read by the receiving connection, before returning, it hands over the
+    /// reading to another waiting connection, so the datagrams of other connections are not stalled.
+    fn handover(&mut self, raddr: &SocketAddr) -> Option<Waker> {
+        self.conns
+            .iter_mut()
+            .filter(|(key, _)| *key != raddr)
+            .find_map(|(_, queue)| queue.waker.take())
+    }
+}
+
+struct GroupSocket {
+    socket: UdpSocket,
+    laddr: SocketAddr,
+    raw: SpinMutex<RawGroupSocket>,
+}
+
+/// A pool of udp sockets bound to a port range, which shares the sockets among
+/// massive outbound connections, e.g. the client QUIC connections of a proxy.
+///
+/// Each socket can be connected to one peer address only once, so the connections
+/// are identified by the 4-tuple (local address, peer address).
+pub struct UdpGroup {
+    sockets: Vec<Arc<GroupSocket>>,
+    next: AtomicUsize,
+}
+
+impl UdpGroup {
+    /// Binds `count` sockets to the ports in range `ports` of `ip`, the ports in use are skipped.
+    #[cfg(feature = "current")]
+    pub fn bind(ip: IpAddr, ports: RangeInclusive<u16>, count: usize) -> io::Result<Self> {
+        Self::bind_with(ip, ports, count, get_driver()?, get_poller()?)
+    }
+
+    /// Binds `count` sockets to the ports in range `ports` of `ip` with providing `driver` / `poller`.
+    ///
+    /// Returns [`AddrInUse`](io::ErrorKind::AddrInUse) error if there are not enough free ports.
+    pub fn bind_with(
+        ip: IpAddr,
+        ports: RangeInclusive<u16>,
+        count: usize,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        let mut sockets = vec![];
+
+        for port in ports.clone() {
+            if sockets.len() == count {
+                break;
+            }
+
+            let socket =
+                match UdpSocket::bind_with(SocketAddr::new(ip, port), driver.clone(), poller) {
+                    Ok(socket) => socket,
+                    Err(err) if err.kind() == io::ErrorKind::AddrInUse => continue,
+                    Err(err) => return Err(err),
+                };
+
+            sockets.push(Arc::new(GroupSocket {
+                laddr: socket.local_addr()?,
+                socket,
+                raw: Default::default(),
+            }));
+        }
+
+        if sockets.len() < count || count == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!(
+                    "bind udp group failed, ports={:?}, count={}, bound={}",
+                    ports,
+                    count,
+                    sockets.len()
+                ),
+            ));
+        }
+
+        Ok(Self {
+            sockets,
+            next: Default::default(),
+        })
+    }
+
+    /// Returns the local addresses of the sockets.
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.sockets.iter().map(|socket| socket.laddr).collect()
+    }
+
+    /// Creates a new connection to `raddr`, the sockets are handed out round-robin.
+    ///
+    /// Returns [`AddrInUse`](io::ErrorKind::AddrInUse) error if all sockets are connected to `raddr`.
+    pub fn connect(&self, raddr: SocketAddr) -> io::Result<UdpGroupConn> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+
+        for i in 0..self.sockets.len() {
+            let socket = &self.sockets[(start + i) % self.sockets.len()];
+
+            let mut raw = socket.raw.lock();
+
+            if raw.conns.contains_key(&raddr) {
+                continue;
+            }
+
+            raw.conns.insert(raddr, Default::default());
+
+            return Ok(UdpGroupConn {
+                socket: socket.clone(),
+                raddr,
+            });
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("all sockets of udp group are connected to {}", raddr),
+        ))
+    }
+}
+
+/// A connection created by [`UdpGroup::connect`], which receives the datagrams of the peer
+/// address only.
+pub struct UdpGroupConn {
+    socket: Arc<GroupSocket>,
+    raddr: SocketAddr,
+}
+
+impl UdpGroupConn {
+    /// Returns the local address of the shared socket.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.socket.laddr
+    }
+
+    /// Returns the address of the peer.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.raddr
+    }
+
+    /// Sends data to the peer. On success, returns the number of bytes written.
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.socket.socket.send_to(buf, self.raddr).await
+    }
+
+    /// Receives one datagram from the peer. On success, returns the number of bytes read.
+    ///
+    /// The datagrams of other connections sharing the socket may be read into `buf` before
+    /// dispatching, so `buf` should be large enough to hold the max datagram of all connections.
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_recv(cx, buf)).await
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        loop {
+            let mut raw = self.socket.raw.lock();
+
+            let queue = raw.conns.get_mut(&self.raddr).expect("dropped connection");
+
+            if let Some(datagram) = queue.datagrams.pop_front() {
+                queue.waker = None;
+
+                let waker = raw.handover(&self.raddr);
+
+                drop(raw);
+
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+
+                let len = datagram.len().min(buf.len());
+
+                buf[..len].copy_from_slice(&datagram[..len]);
+
+                return Poll::Ready(Ok(len));
+            }
+
+            // Registers before reading the socket, so the datagram dispatched by other
+            // connections in the meantime is not missed.
+            queue.waker = Some(cx.waker().clone());
+
+            drop(raw);
+
+            // The error of socket is returned to one connection only, others keep reading.
+            let result = match self.socket.socket.poll_recv_from(cx, buf) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok((len, raddr))) if raddr != self.raddr => {
+                    self.dispatch(raddr, &buf[..len]);
+                    continue;
+                }
+                Poll::Ready(result) => result.map(|(len, _)| len),
+            };
+
+            let waker = {
+                let mut raw = self.socket.raw.lock();
+
+                if let Some(queue) = raw.conns.get_mut(&self.raddr) {
+                    queue.waker = None;
+                }
+
+                raw.handover(&self.raddr)
+            };
+
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+
+            return Poll::Ready(result);
+        }
+    }
+
+    /// Dispatches the datagram of other connection to its queue.
+    fn dispatch(&self, raddr: SocketAddr, datagram: &[u8]) {
+        let mut raw = self.socket.raw.lock();
+
+        let Some(queue) = raw.conns.get_mut(&raddr) else {
+            log::trace!(
+                "udp group {} drop datagram from unknown peer {}, len={}",
+                self.socket.laddr,
+                raddr,
+                datagram.len()
+            );
+
+            return;
+        };
+
+        if queue.datagrams.len() == MAX_QUEUED_DATAGRAMS {
+            log::trace!(
+                "udp group {} drop datagram from {}, queue is full",
+                self.socket.laddr,
+                raddr
+            );
+
+            return;
+        }
+
+        queue.datagrams.push_back(datagram.to_vec());
+
+        let waker = queue.waker.take();
+
+        drop(raw);
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for UdpGroupConn {
+    fn drop(&mut self) {
+        let waker = {
+            let mut raw = self.socket.raw.lock();
+
+            raw.conns.remove(&self.raddr);
+
+            raw.handover(&self.raddr)
+        };
+
+        // The dropped connection may be the one waiting on the socket.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hala_io::test::io_test;
+
+    use super::*;
+
+    fn bind_group(count: usize) -> UdpGroup {
+        let base = rand::random::<u16>() % 20000 + 40000;
+
+        UdpGroup::bind("127.0.0.1".parse().unwrap(), base..=base + 100, count).unwrap()
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_group_connect() {
+        let group = bind_group(2);
+
+        let laddrs = group.local_addrs();
+
+        assert_eq!(laddrs.len(), 2);
+
+        let raddr = "127.0.0.1:1812".parse().unwrap();
+
+        let conn1 = group.connect(raddr).unwrap();
+        let conn2 = group.connect(raddr).unwrap();
+
+        assert_ne!(conn1.local_addr(), conn2.local_addr());
+
+        let err = group.connect(raddr).err().unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+        drop(conn1);
+
+        group.connect(raddr).unwrap();
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_group_dispatch() {
+        let group = bind_group(1);
+
+        let server1 = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server2 = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let conn1 = group.connect(server1.local_addr().unwrap()).unwrap();
+        let conn2 = group.connect(server2.local_addr().unwrap()).unwrap();
+
+        conn1.send(b"hello1").await.unwrap();
+        conn2.send(b"hello2").await.unwrap();
+
+        let mut buf = vec![0; 1024];
+
+        let (len, raddr) = server2.recv_from(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..len], b"hello2");
+        assert_eq!(raddr, conn2.local_addr());
+
+        let (len, raddr) = server1.recv_from(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..len], b"hello1");
+        assert_eq!(raddr, conn1.local_addr());
+
+        // The datagram of conn2 arrives first, and is dispatched to conn2's queue.
+        server2.send_to(b"world2", raddr).await.unwrap();
+        server1.send_to(b"world1", raddr).await.unwrap();
+
+        let len = conn1.recv(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..len], b"world1");
+
+        let len = conn2.recv(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..len], b"world2");
+    }
+}
@@ -1,2 +1,7 @@
 mod udp;
 pub use udp::*;
+
+mod group;
+pub use group::*;
+
+pub mod blocking;
@@ -1,6 +1,8 @@
 use std::{
+    future::poll_fn,
     io,
     net::{SocketAddr, ToSocketAddrs},
+    task::{Context, Poll},
 };
 
 #[cfg(feature = "current")]
@@ -10,9 +12,8 @@ use hala_io::*;
 
 /// A Udp socket.
 pub struct UdpSocket {
-    fd: Handle,
+    fd: OwnedHandle,
     poller: Handle,
-    driver: Driver,
 }
 
 impl UdpSocket {
@@ -27,33 +28,232 @@ impl UdpSocket {
         laddrs: S,
         driver: Driver,
         poller: Handle,
+    ) -> io::Result<Self> {
+        Self::bind_with_options_with(laddrs, BindOptions::default(), driver, poller)
+    }
+
+    /// Create new udp socket with pre-bind socket `options`, e.g. `SO_REUSEPORT`.
+    #[cfg(feature = "current")]
+    pub fn bind_with_options<S: ToSocketAddrs>(
+        laddrs: S,
+        options: BindOptions,
+    ) -> io::Result<Self> {
+        Self::bind_with_options_with(laddrs, options, get_driver()?, get_poller()?)
+    }
+
+    /// Create new udp socket with pre-bind socket `options` and providing `driver` / `poller`.
+    pub fn bind_with_options_with<S: ToSocketAddrs>(
+        laddrs: S,
+        options: BindOptions,
+        driver: Driver,
+        poller: Handle,
     ) -> io::Result<Self> {
         let laddrs = laddrs.to_socket_addrs()?.into_iter().collect::<Vec<_>>();
 
-        let fd = driver.fd_open(Description::UdpSocket, OpenFlags::Bind(&laddrs))?;
+        let fd = OwnedHandle::open(
+            driver,
+            Description::UdpSocket,
+            OpenFlags::BindWith(&laddrs, options),
+        )?;
+
+        Self::new_with(fd, poller)
+    }
+
+    /// Adopts the externally created `socket` with global context `driver` / `poller`,
+    /// e.g. the socket received from another process.
+    ///
+    /// The socket is switched to non-blocking mode.
+    #[cfg(feature = "current")]
+    pub fn from_std(socket: std::net::UdpSocket) -> io::Result<Self> {
+        Self::from_std_with(socket, get_driver()?, get_poller()?)
+    }
+
+    /// Adopts the externally created `socket` with providing `driver` / `poller`.
+    pub fn from_std_with(
+        socket: std::net::UdpSocket,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        #[cfg(unix)]
+        let open_flags = OpenFlags::RawFd(std::os::fd::IntoRawFd::into_raw_fd(socket));
+
+        #[cfg(windows)]
+        let open_flags =
+            OpenFlags::RawSocket(std::os::windows::io::IntoRawSocket::into_raw_socket(socket));
+
+        let fd = OwnedHandle::open(driver, Description::UdpSocket, open_flags)?;
 
-        match driver.fd_cntl(
+        Self::new_with(fd, poller)
+    }
+
+    /// Registers the opened socket `fd` to `poller`, the socket is closed if failed.
+    fn new_with(fd: OwnedHandle, poller: Handle) -> io::Result<Self> {
+        fd.register(
             poller,
-            Cmd::Register {
-                source: fd,
-                interests: Interest::Readable | Interest::Writable,
-            },
-        ) {
-            Err(err) => {
-                _ = driver.fd_close(fd);
-                return Err(err);
-            }
-            _ => {}
-        }
+            Interest::Readable | Interest::Writable,
+            PollMode::Edge,
+        )?;
 
-        Ok(Self { fd, driver, poller })
+        Ok(Self { fd, poller })
     }
 
     /// Returns the local address that this socket is bound to.
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        self.driver
-            .fd_cntl(self.fd, Cmd::LocalAddr)?
-            .try_into_sockaddr()
+        self.fd.fd_cntl(Cmd::LocalAddr)?.try_into_sockaddr()
+    }
+
+    /// Sets the `SO_RCVBUF` option of this socket.
+    ///
+    /// The os may adjust the value, see [`BindOptions::recv_buffer_size`].
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.fd
+            .fd_cntl(Cmd::SetBufferSize(SocketBuffer::Recv, size))
+            .map(|_| ())
+    }
+
+    /// Returns the `SO_RCVBUF` option of this socket.
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        self.fd
+            .fd_cntl(Cmd::BufferSize(SocketBuffer::Recv))?
+            .try_into_datalen()
+    }
+
+    /// Sets the `SO_SNDBUF` option of this socket.
+    ///
+    /// The os may adjust the value, see [`BindOptions::send_buffer_size`].
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        self.fd
+            .fd_cntl(Cmd::SetBufferSize(SocketBuffer::Send, size))
+            .map(|_| ())
+    }
+
+    /// Returns the `SO_SNDBUF` option of this socket.
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.fd
+            .fd_cntl(Cmd::BufferSize(SocketBuffer::Send))?
+            .try_into_datalen()
+    }
+
+    /// Sets the `IP_TTL` option of ipv4 socket, or the `IPV6_UNICAST_HOPS` option of ipv6 socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.fd.fd_cntl(Cmd::SetTtl(ttl)).map(|_| ())
+    }
+
+    /// Returns the ttl / hop limit of the datagrams sent by this socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        self.fd
+            .fd_cntl(Cmd::Ttl)?
+            .try_into_datalen()
+            .map(|ttl| ttl as u32)
+    }
+
+    /// Sets or clears the DF bit of the datagrams sent by this socket, see [`Cmd::SetDontFragment`].
+    ///
+    /// The path MTU discovery requires the DF bit set, so the oversized probes are dropped
+    /// instead of being fragmented.
+    pub fn set_dont_fragment(&self, enabled: bool) -> io::Result<()> {
+        self.fd.fd_cntl(Cmd::SetDontFragment(enabled)).map(|_| ())
+    }
+
+    /// Returns true if the DF bit of the datagrams sent by this socket is set.
+    pub fn dont_fragment(&self) -> io::Result<bool> {
+        self.fd.fd_cntl(Cmd::DontFragment)?.try_into_bool()
+    }
+
+    /// Connects this socket to the remote peer, the first address in `raddrs` that succeeds is used.
+    ///
+    /// After connected, [`send`](Self::send) / [`recv`](Self::recv) can be used, and the datagrams
+    /// from other addresses are dropped.
+    pub fn connect<S: ToSocketAddrs>(&self, raddrs: S) -> io::Result<()> {
+        let mut last_error = None;
+
+        for raddr in raddrs.to_socket_addrs()? {
+            match self.fd.fd_cntl(Cmd::Connect(raddr)) {
+                Ok(_) => return Ok(()),
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any addresses",
+            )
+        }))
+    }
+
+    /// Returns the address of the connected peer.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.fd.fd_cntl(Cmd::RemoteAddr)?.try_into_sockaddr()
+    }
+
+    /// Returns the optional features supported by the driver of this socket,
+    /// e.g. whether [`recv_batch`](Self::recv_batch) drains the ready datagrams in one wake-up.
+    pub fn driver_capabilities(&self) -> io::Result<DriverCapabilities> {
+        self.fd.fd_cntl(Cmd::Capabilities)?.try_into_capabilities()
+    }
+
+    /// Sends data to the connected peer. On success, returns the number of bytes written.
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::Write {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_datalen()
+        })
+        .await
+    }
+
+    /// Receives one datagram from the connected peer. On success, returns the number of bytes read.
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::Read {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_datalen()
+        })
+        .await
+    }
+
+    /// Tries to send data to `raddr` once, without registering the current task to be woken.
+    ///
+    /// Returns [`WouldBlock`](io::ErrorKind::WouldBlock) error if the socket is not writable.
+    pub fn try_send_to(&self, buf: &[u8], raddr: SocketAddr) -> io::Result<usize> {
+        self.fd
+            .fd_cntl(Cmd::TrySendTo { buf, raddr })?
+            .try_into_datalen()
+    }
+
+    /// Tries to receive one datagram once, without registering the current task to be woken.
+    ///
+    /// Returns [`WouldBlock`](io::ErrorKind::WouldBlock) error if there is no datagram to receive.
+    pub fn try_recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.fd.fd_cntl(Cmd::TryRecvFrom(buf))?.try_into_recv_from()
+    }
+
+    /// Waits for the socket to become readable, without consuming any data.
+    pub async fn readable(&self) -> io::Result<()> {
+        self.poll_readiness(Interest::Readable).await
+    }
+
+    /// Waits for the socket to become writable, without writing any data.
+    pub async fn writable(&self) -> io::Result<()> {
+        self.poll_readiness(Interest::Writable).await
+    }
+
+    async fn poll_readiness(&self, interest: Interest) -> io::Result<()> {
+        would_block(|cx| {
+            self.fd.fd_cntl(Cmd::PollReadiness {
+                waker: cx.waker().clone(),
+                interest,
+            })
+        })
+        .await
+        .map(|_| ())
     }
 
     /// Sends data on the socket to the given address. On success, returns the
@@ -63,15 +263,12 @@ impl UdpSocket {
 
         for raddr in target.to_socket_addrs()? {
             let result = would_block(|cx| {
-                self.driver
-                    .fd_cntl(
-                        self.fd,
-                        Cmd::SendTo {
-                            waker: cx.waker().clone(),
-                            buf,
-                            raddr,
-                        },
-                    )?
+                self.fd
+                    .fd_cntl(Cmd::SendTo {
+                        waker: cx.waker().clone(),
+                        buf,
+                        raddr,
+                    })?
                     .try_into_datalen()
             })
             .await;
@@ -89,26 +286,260 @@ impl UdpSocket {
     /// Receives data from the socket. On success, returns the number of bytes
     /// read and the address from whence the data came.
     pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        would_block(|cx| {
-            self.driver
-                .fd_cntl(
-                    self.fd,
-                    Cmd::RecvFrom {
-                        waker: cx.waker().clone(),
-                        buf,
-                    },
-                )?
+        poll_fn(|cx| self.poll_recv_from(cx, buf)).await
+    }
+
+    /// Polls to receive one datagram, registers the current task to be woken if no datagram is ready.
+    pub(crate) fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        poll_would_block(|| {
+            self.fd
+                .fd_cntl(Cmd::RecvFrom {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
                 .try_into_recv_from()
         })
+    }
+
+    /// Receives up to `slots.len()` datagrams in one wake-up, returns the number of filled slots.
+    ///
+    /// The datagrams ready at the time of wake-up are read by repeated non-blocking syscalls,
+    /// so the cost of waking is amortized over the batch. The `slots.len()` is the budget of one batch.
+    pub async fn recv_batch(&self, slots: &mut [BufSlot]) -> io::Result<usize> {
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::RecvBatch {
+                    waker: cx.waker().clone(),
+                    slots,
+                })?
+                .try_into_datalen()
+        })
+        .await
+    }
+
+    /// Sends the datagrams of `slots` in one wake-up, returns the number of sent slots.
+    ///
+    /// The slots are sent in order until the socket is not writable, the rest should be sent again.
+    /// Returns [`InvalidInput`](io::ErrorKind::InvalidInput) error if the [`raddr`](BufSlot::raddr) of slot is not set.
+    pub async fn send_batch(&self, slots: &[BufSlot]) -> io::Result<usize> {
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::SendBatch {
+                    waker: cx.waker().clone(),
+                    slots,
+                })?
+                .try_into_datalen()
+        })
+        .await
+    }
+
+    /// Sends data on the socket to `raddr` with ancillary data `info`,
+    /// e.g. the source address of the datagram on a wildcard-bound socket.
+    ///
+    /// Returns [`Unsupported`](io::ErrorKind::Unsupported) error if the driver doesn't support it.
+    pub async fn send_msg(
+        &self,
+        buf: &[u8],
+        raddr: SocketAddr,
+        info: DatagramInfo,
+    ) -> io::Result<usize> {
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::SendMsg {
+                    waker: cx.waker().clone(),
+                    buf,
+                    raddr,
+                    info,
+                })?
+                .try_into_datalen()
+        })
+        .await
+    }
+
+    /// Sends `buf` to `raddr` as multiple datagrams of `segment_size` bytes by one syscall,
+    /// the last datagram may be shorter.
+    ///
+    /// Returns [`Unsupported`](io::ErrorKind::Unsupported) error if the driver or the os doesn't support it.
+    pub async fn send_to_gso(
+        &self,
+        buf: &[u8],
+        segment_size: u16,
+        raddr: SocketAddr,
+    ) -> io::Result<usize> {
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::SendToGso {
+                    waker: cx.waker().clone(),
+                    buf,
+                    segment_size,
+                    raddr,
+                })?
+                .try_into_datalen()
+        })
+        .await
+    }
+
+    /// Receives data from the socket with ancillary data, see [`DatagramInfo`] for more information.
+    ///
+    /// Returns [`Unsupported`](io::ErrorKind::Unsupported) error if the driver doesn't support it.
+    pub async fn recv_msg(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, DatagramInfo)> {
+        would_block(|cx| {
+            self.fd
+                .fd_cntl(Cmd::RecvMsg {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_recv_msg()
+        })
         .await
     }
 }
 
 impl Drop for UdpSocket {
     fn drop(&mut self) {
-        self.driver
-            .fd_cntl(self.poller, Cmd::Deregister(self.fd))
-            .unwrap();
-        self.driver.fd_close(self.fd).unwrap()
+        if let Err(err) = self.fd.deregister(self.poller) {
+            log::error!(
+                "deregister udp socket failed, fd={:?}, err={}",
+                self.fd,
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hala_io::{test::io_test, BindOptions, BufSlot};
+
+    use super::UdpSocket;
+
+    #[hala_test::test(io_test)]
+    async fn test_batch() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let raddr = receiver.local_addr().unwrap();
+
+        assert!(receiver.driver_capabilities().unwrap().supports_fd_budget);
+
+        let slots = (0..8u8)
+            .map(|i| BufSlot {
+                buf: vec![i; 100],
+                len: 10 + i as usize,
+                raddr: Some(raddr),
+            })
+            .collect::<Vec<_>>();
+
+        let mut sent = 0;
+
+        while sent < slots.len() {
+            sent += sender.send_batch(&slots[sent..]).await.unwrap();
+        }
+
+        let mut recv_slots = vec![BufSlot::new(1024); 4];
+
+        let mut received = vec![];
+
+        while received.len() < slots.len() {
+            let count = receiver.recv_batch(&mut recv_slots).await.unwrap();
+
+            // The batch is limited by the number of slots.
+            assert!(count > 0 && count <= 4);
+
+            for slot in &recv_slots[..count] {
+                assert_eq!(slot.raddr, Some(sender.local_addr().unwrap()));
+
+                received.push(slot.data().to_vec());
+            }
+        }
+
+        assert_eq!(
+            received,
+            slots
+                .iter()
+                .map(|slot| slot.data().to_vec())
+                .collect::<Vec<_>>()
+        );
+
+        // The destination address is required.
+        let err = sender.send_batch(&[BufSlot::new(10)]).await.unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_buffer_size() {
+        let options = BindOptions {
+            recv_buffer_size: Some(256 * 1024),
+            send_buffer_size: Some(256 * 1024),
+            ..Default::default()
+        };
+
+        let socket = UdpSocket::bind_with_options("127.0.0.1:0", options).unwrap();
+
+        // The os may adjust the value, e.g. linux doubles it.
+        assert!(socket.recv_buffer_size().unwrap() >= 256 * 1024);
+        assert!(socket.send_buffer_size().unwrap() >= 256 * 1024);
+
+        socket.set_recv_buffer_size(512 * 1024).unwrap();
+
+        assert!(socket.recv_buffer_size().unwrap() >= 512 * 1024);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[hala_test::test(io_test)]
+    async fn test_ttl_dont_fragment() {
+        let options = BindOptions {
+            ttl: Some(32),
+            ..Default::default()
+        };
+
+        let socket = UdpSocket::bind_with_options("127.0.0.1:0", options).unwrap();
+
+        assert_eq!(socket.ttl().unwrap(), 32);
+
+        socket.set_dont_fragment(true).unwrap();
+
+        assert!(socket.dont_fragment().unwrap());
+
+        socket.set_ttl(8).unwrap();
+        socket.set_dont_fragment(false).unwrap();
+
+        assert_eq!(socket.ttl().unwrap(), 8);
+        assert!(!socket.dont_fragment().unwrap());
+
+        let socket = UdpSocket::bind("[::1]:0").unwrap();
+
+        socket.set_ttl(16).unwrap();
+        socket.set_dont_fragment(true).unwrap();
+
+        assert_eq!(socket.ttl().unwrap(), 16);
+        assert!(socket.dont_fragment().unwrap());
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_from_std() {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let laddr = socket.local_addr().unwrap();
+
+        let socket = UdpSocket::from_std(socket).unwrap();
+
+        assert_eq!(socket.local_addr().unwrap(), laddr);
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        sender.send_to(b"hello", laddr).await.unwrap();
+
+        let mut buf = [0; 16];
+
+        let (read_size, raddr) = socket.recv_from(&mut buf).await.unwrap();
+
+        assert_eq!(&buf[..read_size], b"hello");
+        assert_eq!(raddr, sender.local_addr().unwrap());
     }
 }
@@ -0,0 +1,291 @@
+//! The DEFLATE framing of permessage-deflate ([RFC 7692](https://www.rfc-editor.org/rfc/rfc7692)),
+//! the raw DEFLATE streams are compressed / decompressed by [`flate2`].
+//!
+//! The sliding window of the compressor is always 32KB, so the handshake never lets the peer
+//! reduce the window of this endpoint.
+
+use std::io;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+/// The tail of sync flush, which is removed from the compressed messages.
+const SYNC_FLUSH_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// DEFLATE decompressor, which keeps the sliding window across messages unless `no_context_takeover` is set.
+pub struct Inflater {
+    decompress: Decompress,
+    no_context_takeover: bool,
+}
+
+impl std::fmt::Debug for Inflater {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Inflater(no_context_takeover={})",
+            self.no_context_takeover
+        )
+    }
+}
+
+impl Default for Inflater {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Inflater {
+    /// Create new decompressor, the peer doesn't reference previous messages if `no_context_takeover` is true.
+    pub fn new(no_context_takeover: bool) -> Self {
+        Self {
+            decompress: Decompress::new(false),
+            no_context_takeover,
+        }
+    }
+
+    /// Decompresses one message whose sync flush tail was removed,
+    /// returns error if the decompressed size exceeds `max_size`.
+    pub fn decompress(&mut self, input: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+        if self.no_context_takeover {
+            self.decompress.reset(false);
+        }
+
+        // The capacity is limited to `max_size + 1`, which is enough to detect the oversize message.
+        let limit = max_size.saturating_add(1);
+
+        let mut output = Vec::with_capacity(input.len().saturating_mul(2).max(64).min(limit));
+
+        for chunk in [input, &SYNC_FLUSH_TAIL] {
+            let start = self.decompress.total_in();
+
+            loop {
+                if output.len() > max_size {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "permessage-deflate message too large",
+                    ));
+                }
+
+                if output.len() == output.capacity() {
+                    output.reserve_exact(output.len().max(64).min(limit - output.len()));
+                }
+
+                let consumed = (self.decompress.total_in() - start) as usize;
+
+                let status = self
+                    .decompress
+                    .decompress_vec(&chunk[consumed..], &mut output, FlushDecompress::Sync)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+                let consumed = (self.decompress.total_in() - start) as usize;
+
+                // The message ends with a final block, the next message starts a new stream.
+                if status == Status::StreamEnd {
+                    self.decompress.reset(false);
+
+                    return Self::check_size(output, max_size);
+                }
+
+                if consumed == chunk.len() && output.len() < output.capacity() {
+                    break;
+                }
+            }
+        }
+
+        Self::check_size(output, max_size)
+    }
+
+    fn check_size(output: Vec<u8>, max_size: usize) -> io::Result<Vec<u8>> {
+        if output.len() > max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "permessage-deflate message too large",
+            ));
+        }
+
+        Ok(output)
+    }
+}
+
+/// DEFLATE compressor, which keeps the sliding window across messages unless `no_context_takeover` is set.
+pub struct Deflater {
+    compress: Compress,
+    no_context_takeover: bool,
+}
+
+impl std::fmt::Debug for Deflater {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Deflater(no_context_takeover={})",
+            self.no_context_takeover
+        )
+    }
+}
+
+impl Default for Deflater {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Deflater {
+    /// Create new compressor, the messages are compressed independently if `no_context_takeover` is true.
+    pub fn new(no_context_takeover: bool) -> Self {
+        Self {
+            compress: Compress::new(Compression::default(), false),
+            no_context_takeover,
+        }
+    }
+
+    /// Compresses one message, and removes the sync flush tail.
+    pub fn compress(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        if self.no_context_takeover {
+            self.compress.reset();
+        }
+
+        let mut output = Vec::with_capacity(input.len() / 2 + 64);
+
+        let start = self.compress.total_in();
+
+        loop {
+            if output.len() == output.capacity() {
+                output.reserve(output.len());
+            }
+
+            let consumed = (self.compress.total_in() - start) as usize;
+
+            self.compress
+                .compress_vec(&input[consumed..], &mut output, FlushCompress::Sync)
+                .map_err(io::Error::other)?;
+
+            // The sync flush is completed if the output buffer isn't filled.
+            if self.compress.total_in() - start == input.len() as u64
+                && output.len() < output.capacity()
+            {
+                break;
+            }
+        }
+
+        if output.ends_with(&SYNC_FLUSH_TAIL) {
+            output.truncate(output.len() - SYNC_FLUSH_TAIL.len());
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc7692_examples() {
+        let mut inflater = Inflater::new(false);
+
+        // A message compressed using 1 compressed DEFLATE block.
+        assert_eq!(
+            inflater
+                .decompress(&[0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00], 1024)
+                .unwrap(),
+            b"Hello"
+        );
+
+        // The second message references the first one.
+        assert_eq!(
+            inflater
+                .decompress(&[0xf2, 0x00, 0x11, 0x00, 0x00], 1024)
+                .unwrap(),
+            b"Hello"
+        );
+
+        // A message using DEFLATE block with no compression.
+        assert_eq!(
+            Inflater::new(true)
+                .decompress(
+                    &[0x00, 0x05, 0x00, 0xfa, 0xff, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x00],
+                    1024
+                )
+                .unwrap(),
+            b"Hello"
+        );
+
+        // A message with the final bit set, which isn't followed by the sync flush.
+        assert_eq!(
+            Inflater::new(false)
+                .decompress(&[0xf3, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00], 1024)
+                .unwrap(),
+            b"Hello"
+        );
+    }
+
+    #[test]
+    fn test_max_size() {
+        let compressed = Deflater::default().compress(&vec![1; 100_000]).unwrap();
+
+        assert_eq!(
+            Inflater::new(true)
+                .decompress(&compressed, 100_000)
+                .unwrap()
+                .len(),
+            100_000
+        );
+
+        Inflater::new(true)
+            .decompress(&compressed, 99_999)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for no_context_takeover in [false, true] {
+            let mut deflater = Deflater::new(no_context_takeover);
+            let mut inflater = Inflater::new(no_context_takeover);
+
+            let mut messages = vec![
+                vec![],
+                b"Hello".to_vec(),
+                b"abcabcabcabcabcabcabcabcabcabc".to_vec(),
+                vec![0; 100_000],
+            ];
+
+            messages.push((0..50_000u32).map(|i| (i * i % 251) as u8).collect());
+
+            for message in messages {
+                let compressed = deflater.compress(&message).unwrap();
+
+                assert!(!compressed.ends_with(&SYNC_FLUSH_TAIL));
+
+                assert_eq!(
+                    inflater.decompress(&compressed, usize::MAX).unwrap(),
+                    message
+                );
+            }
+        }
+
+        assert!(
+            Deflater::default()
+                .compress(&vec![0; 100_000])
+                .unwrap()
+                .len()
+                < 1000
+        );
+    }
+
+    #[test]
+    fn test_context_takeover() {
+        let mut deflater = Deflater::new(false);
+
+        let message = b"The quick brown fox jumps over the lazy dog.";
+
+        let first = deflater.compress(message).unwrap();
+        let second = deflater.compress(message).unwrap();
+
+        // The second message references the first one.
+        assert!(second.len() < first.len());
+
+        let mut inflater = Inflater::new(false);
+
+        assert_eq!(inflater.decompress(&first, 1024).unwrap(), message);
+        assert_eq!(inflater.decompress(&second, 1024).unwrap(), message);
+    }
+}
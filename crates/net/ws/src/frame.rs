@@ -0,0 +1,350 @@
+use std::io;
+
+use bytes::{Buf, BufMut, BytesMut};
+use hala_codec::{Decoder, Encoder};
+
+/// The operation code of websocket frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    /// Returns true if this is control frame opcode.
+    pub fn is_control(&self) -> bool {
+        matches!(self, OpCode::Close | OpCode::Ping | OpCode::Pong)
+    }
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = io::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x0 => Ok(OpCode::Continuation),
+            0x1 => Ok(OpCode::Text),
+            0x2 => Ok(OpCode::Binary),
+            0x8 => Ok(OpCode::Close),
+            0x9 => Ok(OpCode::Ping),
+            0xa => Ok(OpCode::Pong),
+            _ => Err(protocol_error("reserved opcode")),
+        }
+    }
+}
+
+impl From<OpCode> for u8 {
+    fn from(value: OpCode) -> Self {
+        match value {
+            OpCode::Continuation => 0x0,
+            OpCode::Text => 0x1,
+            OpCode::Binary => 0x2,
+            OpCode::Close => 0x8,
+            OpCode::Ping => 0x9,
+            OpCode::Pong => 0xa,
+        }
+    }
+}
+
+pub(crate) fn protocol_error(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("websocket protocol error: {}", msg),
+    )
+}
+
+/// The max payload length of control frames.
+pub const MAX_CONTROL_PAYLOAD: usize = 125;
+
+/// Websocket frame, see [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455#section-5.2) for more information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    /// The `RSV1` bit, which marks the compressed message of permessage-deflate.
+    pub rsv1: bool,
+    pub opcode: OpCode,
+    /// The unmasked payload data.
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Create new final frame without `RSV1` bit.
+    pub fn new(opcode: OpCode, payload: Vec<u8>) -> Self {
+        Self {
+            fin: true,
+            rsv1: false,
+            opcode,
+            payload,
+        }
+    }
+}
+
+/// The endpoint role, the frames sent by client are masked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// The [`Decoder`] / [`Encoder`] of websocket frames.
+#[derive(Debug, Clone)]
+pub struct FrameCodec {
+    role: Role,
+    max_frame_size: usize,
+    /// True if the `RSV1` bit is allowed by the negotiated extensions.
+    rsv1_allowed: bool,
+}
+
+impl FrameCodec {
+    /// Create new codec for `role`, the frames whose payload exceed `max_frame_size` are rejected.
+    pub fn new(role: Role, max_frame_size: usize) -> Self {
+        Self {
+            role,
+            max_frame_size,
+            rsv1_allowed: false,
+        }
+    }
+
+    /// Allows the `RSV1` bit of incoming frames, which is set when permessage-deflate is negotiated.
+    pub fn set_rsv1_allowed(&mut self, allowed: bool) {
+        self.rsv1_allowed = allowed;
+    }
+
+    /// Returns the endpoint role of this codec.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+}
+
+fn apply_mask(buf: &mut [u8], key: [u8; 4]) {
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b ^= key[i % 4];
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        if src.len() < 2 {
+            return Ok(None);
+        }
+
+        let (first, second) = (src[0], src[1]);
+
+        let fin = first & 0x80 != 0;
+        let rsv1 = first & 0x40 != 0;
+
+        if first & 0x30 != 0 || (rsv1 && !self.rsv1_allowed) {
+            return Err(protocol_error("reserved bits are set"));
+        }
+
+        let opcode = OpCode::try_from(first & 0x0f)?;
+
+        let masked = second & 0x80 != 0;
+
+        // The client must mask all frames, and the server must not.
+        if masked != (self.role == Role::Server) {
+            return Err(protocol_error("unexpected frame masking"));
+        }
+
+        let (len, header_len) = match second & 0x7f {
+            126 => {
+                if src.len() < 4 {
+                    return Ok(None);
+                }
+
+                (u16::from_be_bytes([src[2], src[3]]) as u64, 4)
+            }
+            127 => {
+                if src.len() < 10 {
+                    return Ok(None);
+                }
+
+                let len = u64::from_be_bytes(src[2..10].try_into().unwrap());
+
+                if len >> 63 != 0 {
+                    return Err(protocol_error("invalid payload length"));
+                }
+
+                (len, 10)
+            }
+            len => (len as u64, 2),
+        };
+
+        if opcode.is_control() && (!fin || len > MAX_CONTROL_PAYLOAD as u64) {
+            return Err(protocol_error("invalid control frame"));
+        }
+
+        if len > self.max_frame_size as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "websocket frame too large",
+            ));
+        }
+
+        let mask_len = if masked { 4 } else { 0 };
+
+        let frame_len = header_len + mask_len + len as usize;
+
+        if src.len() < frame_len {
+            src.reserve(frame_len - src.len());
+
+            return Ok(None);
+        }
+
+        let key = masked.then(|| src[header_len..header_len + 4].try_into().unwrap());
+
+        src.advance(header_len + mask_len);
+
+        let mut payload = src.split_to(len as usize).to_vec();
+
+        if let Some(key) = key {
+            apply_mask(&mut payload, key);
+        }
+
+        Ok(Some(Frame {
+            fin,
+            rsv1,
+            opcode,
+            payload,
+        }))
+    }
+}
+
+impl Encoder<Frame> for FrameCodec {
+    fn encode(&mut self, item: Frame, dst: &mut BytesMut) -> io::Result<()> {
+        let mut first = u8::from(item.opcode);
+
+        if item.fin {
+            first |= 0x80;
+        }
+
+        if item.rsv1 {
+            first |= 0x40;
+        }
+
+        dst.put_u8(first);
+
+        let mask_bit = if self.role == Role::Client { 0x80 } else { 0 };
+
+        let len = item.payload.len();
+
+        if len < 126 {
+            dst.put_u8(mask_bit | len as u8);
+        } else if len <= u16::MAX as usize {
+            dst.put_u8(mask_bit | 126);
+            dst.put_u16(len as u16);
+        } else {
+            dst.put_u8(mask_bit | 127);
+            dst.put_u64(len as u64);
+        }
+
+        let mut payload = item.payload;
+
+        if self.role == Role::Client {
+            let key: [u8; 4] = rand::random();
+
+            dst.put_slice(&key);
+
+            apply_mask(&mut payload, key);
+        }
+
+        dst.put_slice(&payload);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc6455_examples() {
+        let mut client = FrameCodec::new(Role::Client, 1024);
+
+        // A single-frame unmasked text message.
+        let mut buf = BytesMut::from(&[0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f][..]);
+
+        assert_eq!(
+            client.decode(&mut buf).unwrap(),
+            Some(Frame::new(OpCode::Text, b"Hello".to_vec()))
+        );
+
+        assert!(buf.is_empty());
+
+        let mut server = FrameCodec::new(Role::Server, 1024);
+
+        // A single-frame masked text message, which is received in two pieces.
+        let bytes = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+
+        let mut buf = BytesMut::from(&bytes[..6]);
+
+        assert_eq!(server.decode(&mut buf).unwrap(), None);
+
+        buf.extend_from_slice(&bytes[6..]);
+
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(Frame::new(OpCode::Text, b"Hello".to_vec()))
+        );
+
+        // The server rejects unmasked frames.
+        server
+            .decode(&mut BytesMut::from(
+                &[0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f][..],
+            ))
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut client = FrameCodec::new(Role::Client, 1 << 20);
+        let mut server = FrameCodec::new(Role::Server, 1 << 20);
+
+        server.set_rsv1_allowed(true);
+
+        let frames = [
+            Frame::new(OpCode::Ping, vec![]),
+            Frame::new(OpCode::Binary, vec![7; 200]),
+            Frame {
+                fin: false,
+                rsv1: true,
+                opcode: OpCode::Text,
+                payload: vec![1; 70000],
+            },
+        ];
+
+        for frame in frames {
+            let mut buf = BytesMut::new();
+
+            client.encode(frame.clone(), &mut buf).unwrap();
+
+            assert_eq!(server.decode(&mut buf).unwrap(), Some(frame));
+        }
+
+        // The control frames must not be fragmented.
+        let mut buf = BytesMut::new();
+
+        client
+            .encode(
+                Frame {
+                    fin: false,
+                    rsv1: false,
+                    opcode: OpCode::Ping,
+                    payload: vec![],
+                },
+                &mut buf,
+            )
+            .unwrap();
+
+        server.decode(&mut buf).unwrap_err();
+    }
+}
@@ -0,0 +1,470 @@
+use std::io;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use ring::digest::{digest, SHA1_FOR_LEGACY_USE_ONLY};
+
+/// The GUID concatenated with `Sec-WebSocket-Key` to compute `Sec-WebSocket-Accept`.
+pub const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The max size of handshake request / response head.
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// The extension token of permessage-deflate.
+const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+pub(crate) fn handshake_error(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("websocket handshake error: {}", msg),
+    )
+}
+
+/// Returns the `Sec-WebSocket-Accept` value of `Sec-WebSocket-Key` header `key`.
+pub fn accept_key(key: &str) -> String {
+    let mut input = key.as_bytes().to_vec();
+
+    input.extend_from_slice(WS_GUID.as_bytes());
+
+    STANDARD.encode(digest(&SHA1_FOR_LEGACY_USE_ONLY, &input))
+}
+
+/// Returns new random `Sec-WebSocket-Key` value.
+pub(crate) fn generate_key() -> String {
+    STANDARD.encode(rand::random::<[u8; 16]>())
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Returns true if the comma-separated header `value` contains `token`, case-insensitively.
+fn contains_token(value: Option<&str>, token: &str) -> bool {
+    value
+        .map(|value| {
+            value
+                .split(',')
+                .any(|t| t.trim().eq_ignore_ascii_case(token))
+        })
+        .unwrap_or(false)
+}
+
+/// The handshake request received by the server.
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    /// The request target, e.g. `/chat?room=1`.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Returns the first header value of `name`, case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// The handshake response received by the client.
+#[derive(Debug, Clone, Default)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Response {
+    /// Returns the first header value of `name`, case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// Reads the http head terminated by an empty line.
+///
+/// The head is read byte by byte, so the frames following the head are left in `io`.
+async fn read_head<T: AsyncRead + Unpin>(
+    io: &mut T,
+) -> io::Result<(String, Vec<(String, String)>)> {
+    let mut buf = vec![];
+    let mut byte = [0u8];
+
+    while !buf.ends_with(b"\r\n\r\n") {
+        if buf.len() >= MAX_HEAD_SIZE {
+            return Err(handshake_error("head too large"));
+        }
+
+        if io.read(&mut byte).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "websocket handshake error: unexpected eof",
+            ));
+        }
+
+        buf.push(byte[0]);
+    }
+
+    let head = String::from_utf8(buf).map_err(|_| handshake_error("invalid utf8 head"))?;
+
+    let mut lines = head.split("\r\n").filter(|line| !line.is_empty());
+
+    let start_line = lines
+        .next()
+        .ok_or_else(|| handshake_error("empty head"))?
+        .to_string();
+
+    let headers = lines
+        .map(|line| {
+            line.split_once(':')
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .ok_or_else(|| handshake_error("invalid header line"))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok((start_line, headers))
+}
+
+/// The negotiated parameters of permessage-deflate, see [RFC 7692](https://www.rfc-editor.org/rfc/rfc7692).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeflateParams {
+    pub server_no_context_takeover: bool,
+    pub client_no_context_takeover: bool,
+    pub server_max_window_bits: Option<u8>,
+    pub client_max_window_bits: Option<u8>,
+}
+
+impl DeflateParams {
+    /// Parses the parameters of one permessage-deflate extension offer / response,
+    /// the `client_max_window_bits` without value is parsed as 15.
+    fn parse<'a, I: Iterator<Item = &'a str>>(params: I) -> io::Result<Self> {
+        let mut result = Self::default();
+
+        for param in params {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param.trim(), None),
+            };
+
+            let window_bits = || -> io::Result<u8> {
+                match value.map(|v| v.parse::<u8>()) {
+                    Some(Ok(bits)) if (8..=15).contains(&bits) => Ok(bits),
+                    None if name == "client_max_window_bits" => Ok(15),
+                    _ => Err(handshake_error("invalid max_window_bits")),
+                }
+            };
+
+            match name {
+                "server_no_context_takeover" if value.is_none() => {
+                    result.server_no_context_takeover = true
+                }
+                "client_no_context_takeover" if value.is_none() => {
+                    result.client_no_context_takeover = true
+                }
+                "server_max_window_bits" => result.server_max_window_bits = Some(window_bits()?),
+                "client_max_window_bits" => result.client_max_window_bits = Some(window_bits()?),
+                _ => return Err(handshake_error("invalid permessage-deflate parameter")),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn to_header_value(self) -> String {
+        let mut value = PERMESSAGE_DEFLATE.to_string();
+
+        if self.server_no_context_takeover {
+            value.push_str("; server_no_context_takeover");
+        }
+
+        if self.client_no_context_takeover {
+            value.push_str("; client_no_context_takeover");
+        }
+
+        if let Some(bits) = self.server_max_window_bits {
+            value.push_str(&format!("; server_max_window_bits={}", bits));
+        }
+
+        if let Some(bits) = self.client_max_window_bits {
+            value.push_str(&format!("; client_max_window_bits={}", bits));
+        }
+
+        value
+    }
+}
+
+/// Returns the permessage-deflate offers of `Sec-WebSocket-Extensions` headers.
+fn deflate_offers(
+    headers: &[(String, String)],
+) -> impl Iterator<Item = io::Result<DeflateParams>> + '_ {
+    headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("sec-websocket-extensions"))
+        .flat_map(|(_, value)| value.split(','))
+        .filter_map(|extension| {
+            let mut params = extension.split(';');
+
+            (params.next()?.trim() == PERMESSAGE_DEFLATE).then(|| DeflateParams::parse(params))
+        })
+}
+
+/// The result of successful handshake.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Negotiated {
+    pub(crate) deflate: Option<DeflateParams>,
+    pub(crate) protocol: Option<String>,
+}
+
+/// Performs the client handshake.
+pub(crate) async fn client_handshake<T: AsyncRead + AsyncWrite + Unpin>(
+    io: &mut T,
+    host: &str,
+    path: &str,
+    protocols: &[String],
+    permessage_deflate: bool,
+) -> io::Result<(Response, Negotiated)> {
+    let key = generate_key();
+
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n",
+        path, host, key
+    );
+
+    if !protocols.is_empty() {
+        request.push_str(&format!(
+            "Sec-WebSocket-Protocol: {}\r\n",
+            protocols.join(", ")
+        ));
+    }
+
+    if permessage_deflate {
+        // The window of the compressor can't be reduced, so `client_max_window_bits` isn't offered.
+        request.push_str("Sec-WebSocket-Extensions: permessage-deflate\r\n");
+    }
+
+    request.push_str("\r\n");
+
+    io.write_all(request.as_bytes()).await?;
+    io.flush().await?;
+
+    let (status_line, headers) = read_head(io).await?;
+
+    let status = status_line
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| handshake_error("invalid status line"))?;
+
+    let response = Response { status, headers };
+
+    if status != 101 {
+        return Err(handshake_error(&format!("unexpected status {}", status)));
+    }
+
+    if !contains_token(response.header("upgrade"), "websocket")
+        || !contains_token(response.header("connection"), "upgrade")
+    {
+        return Err(handshake_error("missing upgrade headers"));
+    }
+
+    if response.header("sec-websocket-accept") != Some(accept_key(&key).as_str()) {
+        return Err(handshake_error("invalid Sec-WebSocket-Accept"));
+    }
+
+    let protocol = response
+        .header("sec-websocket-protocol")
+        .map(str::to_string);
+
+    if let Some(protocol) = &protocol {
+        if !protocols.contains(protocol) {
+            return Err(handshake_error("unexpected subprotocol"));
+        }
+    }
+
+    let deflate = {
+        let mut offers = deflate_offers(&response.headers);
+
+        let deflate = offers.next().transpose()?;
+
+        if offers.next().is_some() || (deflate.is_some() && !permessage_deflate) {
+            return Err(handshake_error("unexpected extensions"));
+        }
+
+        if deflate.is_some_and(|params| params.client_max_window_bits.is_some_and(|b| b < 15)) {
+            return Err(handshake_error("unsupported client_max_window_bits"));
+        }
+
+        deflate
+    };
+
+    Ok((response, Negotiated { deflate, protocol }))
+}
+
+/// Performs the server handshake, the request is rejected with `400 Bad Request` if it's invalid.
+pub(crate) async fn server_handshake<T: AsyncRead + AsyncWrite + Unpin>(
+    io: &mut T,
+    protocols: &[String],
+    permessage_deflate: bool,
+) -> io::Result<(Request, Negotiated)> {
+    let (request_line, headers) = read_head(io).await?;
+
+    let result = (|| {
+        let mut parts = request_line.split(' ');
+
+        let (Some("GET"), Some(path), Some(version)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(handshake_error("invalid request line"));
+        };
+
+        if version != "HTTP/1.1" {
+            return Err(handshake_error("invalid http version"));
+        }
+
+        let request = Request {
+            path: path.to_string(),
+            headers,
+        };
+
+        if !contains_token(request.header("upgrade"), "websocket")
+            || !contains_token(request.header("connection"), "upgrade")
+        {
+            return Err(handshake_error("missing upgrade headers"));
+        }
+
+        if request.header("sec-websocket-version") != Some("13") {
+            return Err(handshake_error("unsupported version"));
+        }
+
+        let key = request
+            .header("sec-websocket-key")
+            .filter(|key| matches!(STANDARD.decode(key), Ok(key) if key.len() == 16))
+            .ok_or_else(|| handshake_error("invalid Sec-WebSocket-Key"))?
+            .to_string();
+
+        // Selects the first offered subprotocol supported by the server.
+        let protocol = request
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("sec-websocket-protocol"))
+            .flat_map(|(_, value)| value.split(','))
+            .map(str::trim)
+            .find(|protocol| protocols.iter().any(|p| p == protocol))
+            .map(str::to_string);
+
+        // Accepts the first valid offer, the invalid ones and the ones reducing the window
+        // of the server's compressor are declined.
+        let deflate = if permessage_deflate {
+            deflate_offers(&request.headers)
+                .filter_map(Result::ok)
+                .find(|offer| offer.server_max_window_bits.unwrap_or(15) == 15)
+        } else {
+            None
+        }
+        .map(|offer| DeflateParams {
+            server_no_context_takeover: offer.server_no_context_takeover,
+            client_no_context_takeover: offer.client_no_context_takeover,
+            server_max_window_bits: offer.server_max_window_bits,
+            client_max_window_bits: None,
+        });
+
+        Ok((request, key, Negotiated { deflate, protocol }))
+    })();
+
+    let (request, key, negotiated) = match result {
+        Ok(r) => r,
+        Err(err) => {
+            _ = io
+                .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")
+                .await;
+
+            _ = io.flush().await;
+
+            return Err(err);
+        }
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n",
+        accept_key(&key)
+    );
+
+    if let Some(protocol) = &negotiated.protocol {
+        response.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", protocol));
+    }
+
+    if let Some(deflate) = negotiated.deflate {
+        response.push_str(&format!(
+            "Sec-WebSocket-Extensions: {}\r\n",
+            deflate.to_header_value()
+        ));
+    }
+
+    response.push_str("\r\n");
+
+    io.write_all(response.as_bytes()).await?;
+    io.flush().await?;
+
+    Ok((request, negotiated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // The example of RFC 6455 section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_deflate_offers() {
+        let headers = vec![(
+            "Sec-WebSocket-Extensions".to_string(),
+            "foo, permessage-deflate; client_max_window_bits; server_max_window_bits=10, permessage-deflate; bar"
+                .to_string(),
+        )];
+
+        let mut offers = deflate_offers(&headers);
+
+        assert_eq!(
+            offers.next().unwrap().unwrap(),
+            DeflateParams {
+                server_max_window_bits: Some(10),
+                client_max_window_bits: Some(15),
+                ..Default::default()
+            }
+        );
+
+        offers.next().unwrap().unwrap_err();
+
+        assert!(offers.next().is_none());
+    }
+
+    #[test]
+    fn test_decline_reduced_window() {
+        let mut io = futures::io::Cursor::new(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+              Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+              Sec-WebSocket-Extensions: permessage-deflate; server_max_window_bits=10, \
+              permessage-deflate; client_no_context_takeover\r\n\r\n"
+                .to_vec(),
+        );
+
+        let (_, negotiated) =
+            futures::executor::block_on(server_handshake(&mut io, &[], true)).unwrap();
+
+        assert_eq!(
+            negotiated.deflate,
+            Some(DeflateParams {
+                client_no_context_takeover: true,
+                ..Default::default()
+            })
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! Websocket protocol ([RFC 6455](https://www.rfc-editor.org/rfc/rfc6455)) over any
+//! [`AsyncRead`](futures::AsyncRead) + [`AsyncWrite`](futures::AsyncWrite) io,
+//! with permessage-deflate extension ([RFC 7692](https://www.rfc-editor.org/rfc/rfc7692)).
+
+mod deflate;
+pub use deflate::*;
+
+mod frame;
+pub use frame::*;
+
+mod handshake;
+pub use handshake::*;
+
+mod websocket;
+pub use websocket::*;
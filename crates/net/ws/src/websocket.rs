@@ -0,0 +1,563 @@
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{ready, AsyncRead, AsyncWrite, Sink, Stream};
+use hala_codec::Framed;
+
+use crate::{
+    client_handshake,
+    frame::{protocol_error, Frame, FrameCodec, OpCode, Role, MAX_CONTROL_PAYLOAD},
+    server_handshake, Deflater, Inflater, Negotiated, Request, Response,
+};
+
+/// The close frame payload, see [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455#section-7.4) for status codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+impl CloseFrame {
+    /// The status code of normal closure.
+    pub const NORMAL: u16 = 1000;
+
+    /// Create new close frame with status `code` and `reason`.
+    pub fn new<R: Into<String>>(code: u16, reason: R) -> Self {
+        Self {
+            code,
+            reason: reason.into(),
+        }
+    }
+
+    fn is_valid_code(code: u16) -> bool {
+        matches!(code, 1000..=1003 | 1007..=1011 | 3000..=4999)
+    }
+}
+
+/// Websocket message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<CloseFrame>),
+}
+
+/// The configuration of [`WebSocket`].
+#[derive(Debug, Clone)]
+pub struct WsConfig {
+    /// The max size of one received message, after decompression.
+    pub max_message_size: usize,
+    /// The max payload size of one received frame.
+    pub max_frame_size: usize,
+    /// Offers / accepts permessage-deflate extension.
+    pub permessage_deflate: bool,
+    /// The subprotocols offered by the client, or supported by the server in order of preference.
+    pub protocols: Vec<String>,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            max_message_size: 64 * 1024 * 1024,
+            max_frame_size: 16 * 1024 * 1024,
+            permessage_deflate: true,
+            protocols: vec![],
+        }
+    }
+}
+
+/// The compression context of negotiated permessage-deflate.
+#[derive(Debug)]
+struct Deflate {
+    deflater: Deflater,
+    inflater: Inflater,
+}
+
+/// Websocket connection over asynchronous io `T`, which is a [`Stream`] / [`Sink`] of [`Message`].
+///
+/// The received pings and close frames are replied automatically, the replies are sent
+/// when the stream is polled or the sink is flushed.
+pub struct WebSocket<T> {
+    framed: Framed<T, FrameCodec>,
+    config: WsConfig,
+    deflate: Option<Deflate>,
+    protocol: Option<String>,
+    /// The fragments of receiving message, tuple (opcode, compressed, payload).
+    fragments: Option<(OpCode, bool, Vec<u8>)>,
+    /// The control frames replied automatically.
+    replies: VecDeque<Frame>,
+    /// True if the replies are written but not yet flushed.
+    flushing: bool,
+    close_sent: bool,
+    close_received: bool,
+}
+
+impl<T> Debug for WebSocket<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "WebSocket(role={:?}, deflate={}, protocol={:?})",
+            self.framed.codec().role(),
+            self.deflate.is_some(),
+            self.protocol
+        )
+    }
+}
+
+impl<T> WebSocket<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Performs the client handshake over `io`, and returns the connection and the handshake response.
+    ///
+    /// `host` is the value of `Host` header, and `path` is the request target.
+    pub async fn connect(
+        mut io: T,
+        host: &str,
+        path: &str,
+        config: WsConfig,
+    ) -> io::Result<(Self, Response)> {
+        let (response, negotiated) = client_handshake(
+            &mut io,
+            host,
+            path,
+            &config.protocols,
+            config.permessage_deflate,
+        )
+        .await?;
+
+        Ok((Self::new(io, Role::Client, config, negotiated), response))
+    }
+
+    /// Performs the server handshake over `io`, and returns the connection and the handshake request.
+    pub async fn accept(mut io: T, config: WsConfig) -> io::Result<(Self, Request)> {
+        let (request, negotiated) =
+            server_handshake(&mut io, &config.protocols, config.permessage_deflate).await?;
+
+        Ok((Self::new(io, Role::Server, config, negotiated), request))
+    }
+
+    /// Wraps the io whose handshake has been performed, without extensions.
+    pub fn from_raw(io: T, role: Role, config: WsConfig) -> Self {
+        Self::new(io, role, config, Default::default())
+    }
+
+    fn new(io: T, role: Role, config: WsConfig, negotiated: Negotiated) -> Self {
+        let mut codec = FrameCodec::new(role, config.max_frame_size);
+
+        let deflate = negotiated.deflate.map(|params| {
+            codec.set_rsv1_allowed(true);
+
+            let (no_context_takeover, peer_no_context_takeover) = match role {
+                Role::Client => (
+                    params.client_no_context_takeover,
+                    params.server_no_context_takeover,
+                ),
+                Role::Server => (
+                    params.server_no_context_takeover,
+                    params.client_no_context_takeover,
+                ),
+            };
+
+            Deflate {
+                deflater: Deflater::new(no_context_takeover),
+                inflater: Inflater::new(peer_no_context_takeover),
+            }
+        });
+
+        Self {
+            framed: Framed::new(io, codec),
+            config,
+            deflate,
+            protocol: negotiated.protocol,
+            fragments: None,
+            replies: Default::default(),
+            flushing: false,
+            close_sent: false,
+            close_received: false,
+        }
+    }
+}
+
+impl<T> WebSocket<T> {
+    /// Returns the negotiated subprotocol.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Returns true if permessage-deflate is negotiated.
+    pub fn is_compressed(&self) -> bool {
+        self.deflate.is_some()
+    }
+
+    /// Returns the reference of the underlying io.
+    pub fn get_ref(&self) -> &T {
+        self.framed.get_ref()
+    }
+
+    /// Consumes this connection and returns the underlying io.
+    pub fn into_inner(self) -> T {
+        self.framed.into_inner()
+    }
+
+    fn encode_message(&mut self, message: Message) -> io::Result<Frame> {
+        if self.close_sent {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "websocket close frame has been sent",
+            ));
+        }
+
+        let (opcode, payload) = match message {
+            Message::Text(text) => (OpCode::Text, text.into_bytes()),
+            Message::Binary(data) => (OpCode::Binary, data),
+            Message::Ping(data) => (OpCode::Ping, data),
+            Message::Pong(data) => (OpCode::Pong, data),
+            Message::Close(frame) => {
+                self.close_sent = true;
+
+                (OpCode::Close, Self::encode_close(frame))
+            }
+        };
+
+        if opcode.is_control() {
+            if payload.len() > MAX_CONTROL_PAYLOAD {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "websocket control frame too large",
+                ));
+            }
+
+            return Ok(Frame::new(opcode, payload));
+        }
+
+        match &mut self.deflate {
+            Some(deflate) => Ok(Frame {
+                fin: true,
+                rsv1: true,
+                opcode,
+                payload: deflate.deflater.compress(&payload)?,
+            }),
+            None => Ok(Frame::new(opcode, payload)),
+        }
+    }
+
+    fn encode_close(frame: Option<CloseFrame>) -> Vec<u8> {
+        let Some(frame) = frame else {
+            return vec![];
+        };
+
+        let mut payload = frame.code.to_be_bytes().to_vec();
+
+        payload.extend_from_slice(frame.reason.as_bytes());
+
+        // The reason is truncated at the char boundary to fit the control frame.
+        let mut len = payload.len().min(MAX_CONTROL_PAYLOAD);
+
+        while std::str::from_utf8(&payload[2..len]).is_err() {
+            len -= 1;
+        }
+
+        payload.truncate(len);
+
+        payload
+    }
+
+    fn decode_close(payload: &[u8]) -> io::Result<Option<CloseFrame>> {
+        match payload.len() {
+            0 => Ok(None),
+            1 => Err(protocol_error("invalid close frame")),
+            _ => {
+                let code = u16::from_be_bytes([payload[0], payload[1]]);
+
+                if !CloseFrame::is_valid_code(code) {
+                    return Err(protocol_error("invalid close code"));
+                }
+
+                let reason = std::str::from_utf8(&payload[2..])
+                    .map_err(|_| protocol_error("invalid utf8 close reason"))?;
+
+                Ok(Some(CloseFrame::new(code, reason)))
+            }
+        }
+    }
+
+    /// Handles one received frame, returns the completed message.
+    fn on_frame(&mut self, frame: Frame) -> io::Result<Option<Message>> {
+        if frame.rsv1 && (frame.opcode.is_control() || frame.opcode == OpCode::Continuation) {
+            return Err(protocol_error(
+                "rsv1 is set on control or continuation frame",
+            ));
+        }
+
+        let (opcode, compressed, payload) = match frame.opcode {
+            OpCode::Ping => {
+                self.replies
+                    .push_back(Frame::new(OpCode::Pong, frame.payload.clone()));
+
+                return Ok(Some(Message::Ping(frame.payload)));
+            }
+            OpCode::Pong => return Ok(Some(Message::Pong(frame.payload))),
+            OpCode::Close => {
+                let close = Self::decode_close(&frame.payload)?;
+
+                self.close_received = true;
+
+                // Echoes the status code.
+                if !self.close_sent {
+                    self.close_sent = true;
+
+                    let reply = close.as_ref().map(|c| CloseFrame::new(c.code, ""));
+
+                    self.replies
+                        .push_back(Frame::new(OpCode::Close, Self::encode_close(reply)));
+                }
+
+                return Ok(Some(Message::Close(close)));
+            }
+            OpCode::Continuation => {
+                let Some((opcode, compressed, mut payload)) = self.fragments.take() else {
+                    return Err(protocol_error("unexpected continuation frame"));
+                };
+
+                if payload.len() + frame.payload.len() > self.config.max_message_size {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "websocket message too large",
+                    ));
+                }
+
+                payload.extend_from_slice(&frame.payload);
+
+                (opcode, compressed, payload)
+            }
+            opcode => {
+                if self.fragments.is_some() {
+                    return Err(protocol_error("expect continuation frame"));
+                }
+
+                if frame.rsv1 && self.deflate.is_none() {
+                    return Err(protocol_error("rsv1 is set without permessage-deflate"));
+                }
+
+                (opcode, frame.rsv1, frame.payload)
+            }
+        };
+
+        if !frame.fin {
+            self.fragments = Some((opcode, compressed, payload));
+
+            return Ok(None);
+        }
+
+        let payload = match &mut self.deflate {
+            Some(deflate) if compressed => deflate
+                .inflater
+                .decompress(&payload, self.config.max_message_size)?,
+            _ => payload,
+        };
+
+        if opcode == OpCode::Text {
+            let text =
+                String::from_utf8(payload).map_err(|_| protocol_error("invalid utf8 text"))?;
+
+            Ok(Some(Message::Text(text)))
+        } else {
+            Ok(Some(Message::Binary(payload)))
+        }
+    }
+}
+
+impl<T> WebSocket<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    /// Writes the automatic replies into the sink buffer, and flushes them.
+    fn poll_replies(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.replies.is_empty() {
+            ready!(Sink::<Frame>::poll_ready(Pin::new(&mut self.framed), cx))?;
+
+            let reply = self.replies.pop_front().unwrap();
+
+            Pin::new(&mut self.framed).start_send(reply)?;
+
+            self.flushing = true;
+        }
+
+        if self.flushing {
+            ready!(Sink::<Frame>::poll_flush(Pin::new(&mut self.framed), cx))?;
+
+            self.flushing = false;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Stream for WebSocket<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    type Item = io::Result<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            // Sending replies doesn't block receiving.
+            if let Poll::Ready(Err(err)) = this.poll_replies(cx) {
+                return Poll::Ready(Some(Err(err)));
+            }
+
+            if this.close_received {
+                return Poll::Ready(None);
+            }
+
+            let frame = match ready!(Pin::new(&mut this.framed).poll_next(cx)) {
+                Some(Ok(frame)) => frame,
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => return Poll::Ready(None),
+            };
+
+            if let Some(message) = this.on_frame(frame)? {
+                return Poll::Ready(Some(Ok(message)));
+            }
+        }
+    }
+}
+
+impl<T> Sink<Message> for WebSocket<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_replies(cx))?;
+
+        Sink::<Frame>::poll_ready(Pin::new(&mut this.framed), cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+
+        let frame = this.encode_message(item)?;
+
+        Pin::new(&mut this.framed).start_send(frame)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_replies(cx))?;
+
+        Sink::<Frame>::poll_flush(Pin::new(&mut this.framed), cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_replies(cx))?;
+
+        Sink::<Frame>::poll_close(Pin::new(&mut this.framed), cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use hala_io::{current::executor::io_spawn, test::io_test};
+    use hala_tcp::{TcpListener, TcpStream};
+
+    use super::*;
+
+    #[hala_test::test(io_test)]
+    async fn test_websocket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let laddr = listener.local_addr().unwrap();
+
+        io_spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+
+            let config = WsConfig {
+                protocols: vec!["chat".into()],
+                ..Default::default()
+            };
+
+            let (mut ws, request) = WebSocket::accept(stream, config).await.unwrap();
+
+            assert_eq!(request.path, "/echo");
+            assert!(ws.is_compressed());
+
+            // Echoes the data messages until the close frame is received.
+            while let Some(message) = ws.next().await {
+                if let message @ (Message::Text(_) | Message::Binary(_)) = message? {
+                    ws.send(message).await?;
+                }
+            }
+
+            Ok(())
+        })
+        .unwrap();
+
+        let stream = TcpStream::connect(laddr).unwrap();
+
+        let config = WsConfig {
+            protocols: vec!["chat".into(), "superchat".into()],
+            ..Default::default()
+        };
+
+        let (mut ws, response) = WebSocket::connect(stream, "localhost", "/echo", config)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, 101);
+        assert_eq!(ws.protocol(), Some("chat"));
+        assert!(ws.is_compressed());
+
+        let messages = [
+            Message::Text("hello".repeat(1000)),
+            Message::Binary(vec![1; 100_000]),
+            Message::Text("hello".repeat(1000)),
+        ];
+
+        for message in messages {
+            ws.send(message.clone()).await.unwrap();
+
+            assert_eq!(ws.next().await.unwrap().unwrap(), message);
+        }
+
+        ws.send(Message::Ping(b"ping".to_vec())).await.unwrap();
+
+        assert_eq!(
+            ws.next().await.unwrap().unwrap(),
+            Message::Pong(b"ping".to_vec())
+        );
+
+        ws.send(Message::Close(Some(CloseFrame::new(
+            CloseFrame::NORMAL,
+            "bye",
+        ))))
+        .await
+        .unwrap();
+
+        assert_eq!(
+            ws.next().await.unwrap().unwrap(),
+            Message::Close(Some(CloseFrame::new(CloseFrame::NORMAL, "")))
+        );
+
+        assert!(ws.next().await.is_none());
+
+        ws.send(Message::Text("closed".into())).await.unwrap_err();
+    }
+}
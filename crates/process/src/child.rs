@@ -0,0 +1,168 @@
+use std::{fmt::Debug, io, process::ExitStatus, time::Duration};
+
+use hala_io::*;
+
+use crate::{ChildStderr, ChildStdin, ChildStdout};
+
+/// The initial interval of polling the exit status of child process.
+const MIN_WAIT_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The maximum interval of polling the exit status of child process.
+const MAX_WAIT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The spawned child process, which is created by [`Command::spawn`](crate::Command::spawn).
+///
+/// Like [`std::process::Child`], the child process is not killed when this value is dropped.
+pub struct Child {
+    inner: std::process::Child,
+    driver: Driver,
+    poller: Handle,
+    /// The handle of child process stdin, if it has been captured.
+    pub stdin: Option<ChildStdin>,
+    /// The handle of child process stdout, if it has been captured.
+    pub stdout: Option<ChildStdout>,
+    /// The handle of child process stderr, if it has been captured.
+    pub stderr: Option<ChildStderr>,
+}
+
+impl Debug for Child {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Child({})", self.inner.id())
+    }
+}
+
+impl Child {
+    pub(crate) fn new(
+        mut inner: std::process::Child,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        match Self::open_stdio(&mut inner, &driver, poller) {
+            Ok((stdin, stdout, stderr)) => Ok(Self {
+                inner,
+                driver,
+                poller,
+                stdin,
+                stdout,
+                stderr,
+            }),
+            Err(err) => {
+                // Don't leave the child process unmanaged.
+                _ = inner.kill();
+                _ = inner.wait();
+
+                Err(err)
+            }
+        }
+    }
+
+    fn open_stdio(
+        inner: &mut std::process::Child,
+        driver: &Driver,
+        poller: Handle,
+    ) -> io::Result<(Option<ChildStdin>, Option<ChildStdout>, Option<ChildStderr>)> {
+        let stdin = inner
+            .stdin
+            .take()
+            .map(|stdin| ChildStdin::new(stdin, driver.clone(), poller))
+            .transpose()?;
+
+        let stdout = inner
+            .stdout
+            .take()
+            .map(|stdout| ChildStdout::new(stdout, driver.clone(), poller))
+            .transpose()?;
+
+        let stderr = inner
+            .stderr
+            .take()
+            .map(|stderr| ChildStderr::new(stderr, driver.clone(), poller))
+            .transpose()?;
+
+        Ok((stdin, stdout, stderr))
+    }
+
+    /// Returns the OS-assigned process identifier of child process.
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Forces the child process to exit, see [`std::process::Child::kill`] for more information.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.inner.kill()
+    }
+
+    /// Returns the exit status if the child process has exited, without blocking.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.inner.try_wait()
+    }
+
+    /// Waits for the child process to exit, and returns its exit status.
+    ///
+    /// The stdin handle is closed before waiting, so the child process waiting for input
+    /// doesn't block forever. The exit status is polled with increasing interval
+    /// up to [`MAX_WAIT_INTERVAL`].
+    pub async fn status(&mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+
+        let mut interval = MIN_WAIT_INTERVAL;
+
+        loop {
+            if let Some(status) = self.inner.try_wait()? {
+                return Ok(status);
+            }
+
+            Sleep::new_with(self.driver.clone(), self.poller, interval)?.await?;
+
+            interval = (interval * 2).min(MAX_WAIT_INTERVAL);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::process::Stdio;
+
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use hala_io::test::io_test;
+
+    use crate::Command;
+
+    #[hala_test::test(io_test)]
+    async fn test_piped_stdio() {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut stdin = child.stdin.take().unwrap();
+
+        stdin.write_all(b"hello world").await.unwrap();
+
+        drop(stdin);
+
+        let mut buf = vec![];
+
+        child
+            .stdout
+            .as_mut()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .await
+            .unwrap();
+
+        assert_eq!(buf, b"hello world");
+
+        assert!(child.status().await.unwrap().success());
+    }
+
+    #[hala_test::test(io_test)]
+    async fn test_kill() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+
+        child.kill().unwrap();
+
+        assert!(!child.status().await.unwrap().success());
+    }
+}
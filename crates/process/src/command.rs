@@ -0,0 +1,128 @@
+use std::{ffi::OsStr, fmt::Debug, io, path::Path, process::Stdio};
+
+#[cfg(feature = "current")]
+use hala_io::current::*;
+use hala_io::*;
+
+use crate::Child;
+
+/// The process builder, which wraps [`std::process::Command`] and spawns [`Child`]
+/// with asynchronous stdio handles.
+pub struct Command {
+    inner: std::process::Command,
+}
+
+impl Debug for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl From<std::process::Command> for Command {
+    fn from(inner: std::process::Command) -> Self {
+        Self { inner }
+    }
+}
+
+impl Command {
+    /// Creates a new process builder for launching the `program`.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Self {
+            inner: std::process::Command::new(program),
+        }
+    }
+
+    /// Adds an argument to pass to the program.
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.inner.arg(arg);
+        self
+    }
+
+    /// Adds multiple arguments to pass to the program.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.inner.args(args);
+        self
+    }
+
+    /// Inserts or updates an environment variable of child process.
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.env(key, val);
+        self
+    }
+
+    /// Inserts or updates multiple environment variables of child process.
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.envs(vars);
+        self
+    }
+
+    /// Removes an environment variable of child process.
+    pub fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Self {
+        self.inner.env_remove(key);
+        self
+    }
+
+    /// Clears all environment variables of child process.
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.inner.env_clear();
+        self
+    }
+
+    /// Sets the working directory of child process.
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.inner.current_dir(dir);
+        self
+    }
+
+    /// Sets the stdin of child process, use [`Stdio::piped`] to capture it as [`ChildStdin`](crate::ChildStdin).
+    pub fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stdin(cfg);
+        self
+    }
+
+    /// Sets the stdout of child process, use [`Stdio::piped`] to capture it as [`ChildStdout`](crate::ChildStdout).
+    pub fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stdout(cfg);
+        self
+    }
+
+    /// Sets the stderr of child process, use [`Stdio::piped`] to capture it as [`ChildStderr`](crate::ChildStderr).
+    pub fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Self {
+        self.inner.stderr(cfg);
+        self
+    }
+
+    /// Returns the reference of the underlying std process builder.
+    pub fn as_std(&self) -> &std::process::Command {
+        &self.inner
+    }
+
+    /// Returns the mutable reference of the underlying std process builder.
+    pub fn as_std_mut(&mut self) -> &mut std::process::Command {
+        &mut self.inner
+    }
+
+    /// Spawns the child process, the captured stdio handles are registered with global context poller.
+    #[cfg(feature = "current")]
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        self.spawn_with(get_driver()?, get_poller()?)
+    }
+
+    /// Spawns the child process with providing `driver` / `poller`.
+    pub fn spawn_with(&mut self, driver: Driver, poller: Handle) -> io::Result<Child> {
+        Child::new(self.inner.spawn()?, driver, poller)
+    }
+}
@@ -0,0 +1,12 @@
+//! Asynchronous child process with piped stdio.
+//!
+//! The piped stdio is only supported on unix platforms.
+
+mod stdio;
+pub use stdio::*;
+
+mod child;
+pub use child::*;
+
+mod command;
+pub use command::*;
@@ -0,0 +1,229 @@
+use std::{
+    fmt::Debug,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{AsyncRead, AsyncWrite};
+use hala_io::*;
+
+/// Opens the pipe handle from std child process stdio, the ownership of `stdio` is transferred to driver.
+#[cfg(unix)]
+fn open_raw<S: std::os::fd::IntoRawFd>(
+    desc: Description,
+    stdio: S,
+    driver: Driver,
+) -> io::Result<OwnedHandle> {
+    OwnedHandle::open(driver, desc, OpenFlags::RawFd(stdio.into_raw_fd()))
+}
+
+#[cfg(not(unix))]
+fn open_raw<S>(_desc: Description, _stdio: S, _driver: Driver) -> io::Result<OwnedHandle> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "piped stdio is only supported on unix platforms",
+    ))
+}
+
+/// The anonymous pipe handle registered with poller.
+struct PipeFd {
+    fd: OwnedHandle,
+    poller: Handle,
+}
+
+impl PipeFd {
+    /// Registers the opened pipe `fd` to `poller`, the pipe is closed if failed.
+    fn new(fd: OwnedHandle, poller: Handle) -> io::Result<Self> {
+        let interests = if fd.as_handle().desc == Description::PipeSender {
+            Interest::Writable
+        } else {
+            Interest::Readable
+        };
+
+        fd.register(poller, interests, PollMode::Edge)?;
+
+        Ok(Self { fd, poller })
+    }
+
+    fn poll_read(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        poll_would_block(|| {
+            self.fd
+                .fd_cntl(Cmd::Read {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_datalen()
+        })
+    }
+
+    fn poll_write(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        poll_would_block(|| {
+            self.fd
+                .fd_cntl(Cmd::Write {
+                    waker: cx.waker().clone(),
+                    buf,
+                })?
+                .try_into_datalen()
+        })
+    }
+}
+
+impl Drop for PipeFd {
+    fn drop(&mut self) {
+        if let Err(err) = self.fd.deregister(self.poller) {
+            log::error!("deregister pipe failed, fd={:?}, err={}", self.fd, err);
+        }
+    }
+}
+
+/// The handle of child process stdin, which is closed when dropped.
+pub struct ChildStdin {
+    fd: PipeFd,
+}
+
+impl Debug for ChildStdin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChildStdin({:?})", self.fd.fd)
+    }
+}
+
+impl ChildStdin {
+    pub(crate) fn new(
+        stdin: std::process::ChildStdin,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        let fd = open_raw(Description::PipeSender, stdin, driver)?;
+
+        Ok(Self {
+            fd: PipeFd::new(fd, poller)?,
+        })
+    }
+}
+
+impl AsyncWrite for ChildStdin {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for &ChildStdin {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The handle of child process stdout.
+pub struct ChildStdout {
+    fd: PipeFd,
+}
+
+impl Debug for ChildStdout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChildStdout({:?})", self.fd.fd)
+    }
+}
+
+impl ChildStdout {
+    pub(crate) fn new(
+        stdout: std::process::ChildStdout,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        let fd = open_raw(Description::PipeReceiver, stdout, driver)?;
+
+        Ok(Self {
+            fd: PipeFd::new(fd, poller)?,
+        })
+    }
+}
+
+impl AsyncRead for ChildStdout {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_read(cx, buf)
+    }
+}
+
+impl AsyncRead for &ChildStdout {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_read(cx, buf)
+    }
+}
+
+/// The handle of child process stderr.
+pub struct ChildStderr {
+    fd: PipeFd,
+}
+
+impl Debug for ChildStderr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChildStderr({:?})", self.fd.fd)
+    }
+}
+
+impl ChildStderr {
+    pub(crate) fn new(
+        stderr: std::process::ChildStderr,
+        driver: Driver,
+        poller: Handle,
+    ) -> io::Result<Self> {
+        let fd = open_raw(Description::PipeReceiver, stderr, driver)?;
+
+        Ok(Self {
+            fd: PipeFd::new(fd, poller)?,
+        })
+    }
+}
+
+impl AsyncRead for ChildStderr {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_read(cx, buf)
+    }
+}
+
+impl AsyncRead for &ChildStderr {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.fd.poll_read(cx, buf)
+    }
+}
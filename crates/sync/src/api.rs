@@ -22,6 +22,23 @@ pub trait Lockable {
     fn unlock(guard: Self::GuardMut<'_>) -> &Self;
 }
 
+/// Any reader-writer lock object should implement this trait,
+/// the [`lock`](Lockable::lock) function acquires the exclusive write access.
+pub trait RwLockable: Lockable {
+    /// RAII scoped shared read access guard type.
+    type GuardRef<'a>
+    where
+        Self: 'a;
+
+    /// Acquires the shared read access, blocks until no writer holds the lock.
+    fn read(&self) -> Self::GuardRef<'_>;
+
+    /// Attempts to acquire the shared read access.
+    ///
+    /// If the read access could not be acquired at this time, then `None` is returned.
+    fn try_read(&self) -> Option<Self::GuardRef<'_>>;
+}
+
 pub trait LockableNew: Lockable {
     type Value;
     fn new(vlaue: Self::Value) -> Self;
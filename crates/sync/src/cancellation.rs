@@ -0,0 +1,197 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Default)]
+struct RawCancellation {
+    cancelled: bool,
+    /// The waiting tasks of [`cancelled`](CancellationToken::cancelled).
+    waiters: HashMap<usize, Waker>,
+    /// The id of next waiting task.
+    next_id: usize,
+    /// The child tokens, which are cancelled with this token.
+    children: Vec<Weak<parking_lot::Mutex<RawCancellation>>>,
+}
+
+/// The token to cancel a chain of asynchronous operations, e.g. resolve → connect → handshake.
+///
+/// The clones share the same cancellation state, and the [`child_token`](Self::child_token)
+/// is cancelled when its parent is cancelled, but not vice versa.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    raw: Arc<parking_lot::Mutex<RawCancellation>>,
+}
+
+impl Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CancellationToken(cancelled={})", self.is_cancelled())
+    }
+}
+
+impl CancellationToken {
+    /// Creates a new uncancelled token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a child token, which is cancelled immediately if this token has been cancelled.
+    pub fn child_token(&self) -> Self {
+        let child = Self::new();
+
+        let mut raw = self.raw.lock();
+
+        if raw.cancelled {
+            drop(raw);
+
+            child.cancel();
+        } else {
+            // Removes the dropped children.
+            raw.children.retain(|child| child.strong_count() > 0);
+            raw.children.push(Arc::downgrade(&child.raw));
+        }
+
+        child
+    }
+
+    /// Cancels this token and all its child tokens, the waiting tasks are woken up.
+    pub fn cancel(&self) {
+        let (waiters, children) = {
+            let mut raw = self.raw.lock();
+
+            if raw.cancelled {
+                return;
+            }
+
+            raw.cancelled = true;
+
+            (
+                std::mem::take(&mut raw.waiters),
+                std::mem::take(&mut raw.children),
+            )
+        };
+
+        for waker in waiters.into_values() {
+            waker.wake();
+        }
+
+        for child in children.iter().filter_map(Weak::upgrade) {
+            Self { raw: child }.cancel();
+        }
+    }
+
+    /// Returns true if this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.raw.lock().cancelled
+    }
+
+    /// Waits until this token is cancelled.
+    pub fn cancelled(&self) -> Cancelled<'_> {
+        Cancelled {
+            token: self,
+            id: None,
+        }
+    }
+}
+
+/// Future created by [`cancelled`](CancellationToken::cancelled) function.
+pub struct Cancelled<'a> {
+    token: &'a CancellationToken,
+    /// The id of waiting task, `None` if not yet waiting.
+    id: Option<usize>,
+}
+
+impl<'a> Future for Cancelled<'a> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut raw = self.token.raw.lock();
+
+        if raw.cancelled {
+            drop(raw);
+
+            self.id = None;
+
+            return Poll::Ready(());
+        }
+
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                let id = raw.next_id;
+
+                raw.next_id = raw.next_id.wrapping_add(1);
+
+                id
+            }
+        };
+
+        raw.waiters.insert(id, cx.waker().clone());
+
+        drop(raw);
+
+        self.id = Some(id);
+
+        Poll::Pending
+    }
+}
+
+impl<'a> Drop for Cancelled<'a> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.token.raw.lock().waiters.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::ThreadPool, task::SpawnExt, FutureExt};
+
+    use super::*;
+
+    #[futures_test::test]
+    async fn test_cancellation_token() {
+        let pool = ThreadPool::builder().pool_size(2).create().unwrap();
+
+        let token = CancellationToken::new();
+
+        let child = token.child_token();
+        let grandchild = child.child_token();
+
+        let mut cancelled = Box::pin(token.cancelled());
+
+        assert!((&mut cancelled).now_or_never().is_none());
+
+        // Cancelling the child doesn't affect the parent.
+        child.cancel();
+
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        assert!(!token.is_cancelled());
+
+        let handle = pool
+            .spawn_with_handle({
+                let token = token.clone();
+
+                async move { token.cancelled().await }
+            })
+            .unwrap();
+
+        let child = token.child_token();
+
+        token.cancel();
+
+        handle.await;
+        cancelled.await;
+
+        assert!(child.is_cancelled());
+
+        // The child of the cancelled token is cancelled immediately.
+        token.child_token().cancelled().await;
+    }
+}
@@ -4,5 +4,26 @@ pub use api::*;
 mod spin;
 pub use spin::*;
 
+mod mutex;
+pub use mutex::*;
+
+mod rwlock;
+pub use rwlock::*;
+
+mod spin_rwlock;
+pub use spin_rwlock::*;
+
+mod local;
+pub use local::*;
+
+mod semaphore;
+pub use semaphore::*;
+
+mod notify;
+pub use notify::*;
+
+mod cancellation;
+pub use cancellation::*;
+
 /// [`AyncLockable`] type maker
 pub mod maker;
@@ -0,0 +1,206 @@
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    ops,
+};
+
+use crate::{Lockable, LockableNew, RwLockable};
+
+/// A mutex type for single thread mode, based on [`RefCell`].
+///
+/// Both the mutex and its guard are `!Send`, use [`SpinMutex`](crate::SpinMutex) to share data between threads.
+#[derive(Debug, Default)]
+pub struct LocalMutex<T> {
+    cell: RefCell<T>,
+}
+
+impl<T> LockableNew for LocalMutex<T> {
+    type Value = T;
+
+    fn new(value: T) -> Self {
+        Self {
+            cell: RefCell::new(value),
+        }
+    }
+}
+
+impl<T> Lockable for LocalMutex<T> {
+    type GuardMut<'a> = LocalMutexGuard<'a, T>
+    where
+        Self: 'a;
+
+    /// Acquires the mutex.
+    ///
+    /// #Panic
+    ///
+    /// Lock the mutex twice in the same scope will cause panic.
+    fn lock(&self) -> Self::GuardMut<'_> {
+        LocalMutexGuard {
+            locker: self,
+            inner: self.cell.borrow_mut(),
+        }
+    }
+
+    fn try_lock(&self) -> Option<Self::GuardMut<'_>> {
+        self.cell
+            .try_borrow_mut()
+            .ok()
+            .map(|inner| LocalMutexGuard {
+                locker: self,
+                inner,
+            })
+    }
+
+    fn unlock(guard: Self::GuardMut<'_>) -> &Self {
+        let locker = guard.locker;
+
+        drop(guard);
+
+        locker
+    }
+}
+
+/// RAII type that handle `scope lock` semantics of [`LocalMutex`]
+pub struct LocalMutexGuard<'a, T> {
+    locker: &'a LocalMutex<T>,
+    inner: RefMut<'a, T>,
+}
+
+impl<'a, T> ops::Deref for LocalMutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<'a, T> ops::DerefMut for LocalMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+/// A reader-writer lock type for single thread mode, based on [`RefCell`].
+///
+/// Acquires the write access while the lock is read (or vice versa) in the same scope will cause panic.
+#[derive(Debug, Default)]
+pub struct LocalRwLock<T> {
+    cell: RefCell<T>,
+}
+
+impl<T> LockableNew for LocalRwLock<T> {
+    type Value = T;
+
+    fn new(value: T) -> Self {
+        Self {
+            cell: RefCell::new(value),
+        }
+    }
+}
+
+impl<T> Lockable for LocalRwLock<T> {
+    type GuardMut<'a> = LocalRwLockWriteGuard<'a, T>
+    where
+        Self: 'a;
+
+    fn lock(&self) -> Self::GuardMut<'_> {
+        LocalRwLockWriteGuard {
+            locker: self,
+            inner: self.cell.borrow_mut(),
+        }
+    }
+
+    fn try_lock(&self) -> Option<Self::GuardMut<'_>> {
+        self.cell
+            .try_borrow_mut()
+            .ok()
+            .map(|inner| LocalRwLockWriteGuard {
+                locker: self,
+                inner,
+            })
+    }
+
+    fn unlock(guard: Self::GuardMut<'_>) -> &Self {
+        let locker = guard.locker;
+
+        drop(guard);
+
+        locker
+    }
+}
+
+impl<T> RwLockable for LocalRwLock<T> {
+    type GuardRef<'a> = Ref<'a, T>
+    where
+        Self: 'a;
+
+    fn read(&self) -> Self::GuardRef<'_> {
+        self.cell.borrow()
+    }
+
+    fn try_read(&self) -> Option<Self::GuardRef<'_>> {
+        self.cell.try_borrow().ok()
+    }
+}
+
+/// RAII type that handle the exclusive write access of [`LocalRwLock`]
+pub struct LocalRwLockWriteGuard<'a, T> {
+    locker: &'a LocalRwLock<T>,
+    inner: RefMut<'a, T>,
+}
+
+impl<'a, T> ops::Deref for LocalRwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<'a, T> ops::DerefMut for LocalRwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LocalMutex, LocalRwLock, Lockable, LockableNew, RwLockable};
+
+    #[test]
+    fn test_local_mutex() {
+        let mutex = LocalMutex::new(1);
+
+        let mut guard = mutex.lock();
+
+        assert!(mutex.try_lock().is_none());
+
+        *guard = 2;
+
+        let mutex = LocalMutex::unlock(guard);
+
+        assert_eq!(*mutex.try_lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_local_rwlock() {
+        let lock = LocalRwLock::new(1);
+
+        let read1 = lock.read();
+        let read2 = lock.try_read().unwrap();
+
+        assert!(lock.try_lock().is_none());
+
+        drop(read1);
+        drop(read2);
+
+        let mut guard = lock.lock();
+
+        assert!(lock.try_read().is_none());
+
+        *guard = 2;
+
+        let lock = LocalRwLock::unlock(guard);
+
+        assert_eq!(*lock.read(), 2);
+    }
+}
@@ -6,6 +6,119 @@ use std::{
 
 use super::*;
 
+/// The waiting tasks of [`AsyncLockableMaker`].
+#[derive(Default)]
+pub struct WaitQueue {
+    /// True if one guard is alive, or the lock is granted to a waiting task but not yet taken.
+    locked: bool,
+    /// FIFO queue of waiting [`lock`](AsyncLockable::lock) futures, tuple (id, waker).
+    waiters: VecDeque<(usize, Waker)>,
+    /// The waiting task whose lock has been granted but not yet taken.
+    granted: Option<usize>,
+    /// The tasks registered by [`poll_lock`](AsyncLockableMaker::poll_lock),
+    /// which are woken when the lock is released and no task is waiting.
+    pollers: Vec<Waker>,
+    /// The id of next waiting task.
+    next_id: usize,
+}
+
+impl WaitQueue {
+    fn try_lock(&mut self) -> bool {
+        // The lock is handed over while tasks are waiting, so nobody can overtake them.
+        if self.locked {
+            return false;
+        }
+
+        self.locked = true;
+
+        true
+    }
+
+    /// Hands the lock over to the front waiting task, or releases it if no task is waiting.
+    ///
+    /// Returns the wakers to be woken after the queue is unlocked.
+    #[must_use]
+    fn unlock(&mut self) -> Vec<Waker> {
+        if let Some((id, waker)) = self.waiters.pop_front() {
+            self.granted = Some(id);
+
+            return vec![waker];
+        }
+
+        self.locked = false;
+
+        std::mem::take(&mut self.pollers)
+    }
+
+    /// Polls the lock of waiting task `id`, or enqueues a new waiting task if `id` is `None`.
+    fn poll_wait(&mut self, id: &mut Option<usize>, waker: &Waker) -> bool {
+        match *id {
+            Some(key) if self.granted == Some(key) => {
+                self.granted = None;
+                *id = None;
+
+                true
+            }
+            Some(key) => {
+                // Update the waker of this task.
+                if let Some(waiter) = self.waiters.iter_mut().find(|(i, _)| *i == key) {
+                    waiter.1 = waker.clone();
+                }
+
+                false
+            }
+            None => {
+                if self.try_lock() {
+                    return true;
+                }
+
+                let key = self.next_id;
+
+                self.next_id = self.next_id.wrapping_add(1);
+                self.waiters.push_back((key, waker.clone()));
+
+                *id = Some(key);
+
+                false
+            }
+        }
+    }
+
+    /// Polls the lock without joining the FIFO queue.
+    fn poll_lock(&mut self, waker: &Waker) -> bool {
+        if self.try_lock() {
+            return true;
+        }
+
+        if !self.pollers.iter().any(|w| w.will_wake(waker)) {
+            self.pollers.push(waker.clone());
+        }
+
+        false
+    }
+
+    /// Removes the waiting task `id`, and hands the lock over to the next one if granted.
+    #[must_use]
+    fn cancel(&mut self, id: usize) -> Vec<Waker> {
+        if self.granted == Some(id) {
+            self.granted = None;
+
+            return self.unlock();
+        }
+
+        self.waiters.retain(|(i, _)| *i != id);
+
+        vec![]
+    }
+}
+
+/// Wakes the tasks outside the queue lock, the woken task may run on another thread at once.
+fn wake_all(wakers: Vec<Waker>) {
+    for waker in wakers {
+        waker.wake();
+    }
+}
+
 /// Type factory for [`AsyncLockable`]
 pub struct AsyncLockableMaker<Locker, Wakers> {
     inner_locker: Locker,
@@ -25,14 +138,57 @@ where
     }
 }
 
+impl<Locker, Wakers> AsyncLockableMaker<Locker, Wakers>
+where
+    Locker: Lockable,
+    Wakers: Lockable,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
+{
+    /// Creates the guard after the lock was acquired from the [`WaitQueue`].
+    fn guard(&self) -> AsyncLockableMakerGuard<'_, Locker, Wakers> {
+        // The previous guard releases `inner_locker` before the queue, so this never blocks.
+        AsyncLockableMakerGuard {
+            locker: self,
+            inner_guard: Some(self.inner_locker.lock()),
+        }
+    }
+
+    /// Attempts to acquire this lock without suspending the current task.
+    ///
+    /// If the lock is held or any task is waiting for it, then `None` is returned.
+    pub fn try_lock(&self) -> Option<AsyncLockableMakerGuard<'_, Locker, Wakers>> {
+        if self.wakers.lock().try_lock() {
+            Some(self.guard())
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to acquire this lock, and registers the waker of `cx` to be woken up when the lock is released
+    /// if the lock could not be acquired at this time.
+    ///
+    /// Unlike [`lock`](AsyncLockable::lock), the caller doesn't join the FIFO queue,
+    /// the lock is acquired only if no task is waiting for it.
+    pub fn poll_lock(
+        &self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<AsyncLockableMakerGuard<'_, Locker, Wakers>> {
+        if self.wakers.lock().poll_lock(cx.waker()) {
+            std::task::Poll::Ready(self.guard())
+        } else {
+            std::task::Poll::Pending
+        }
+    }
+}
+
 impl<Locker, Wakers> AsyncLockable for AsyncLockableMaker<Locker, Wakers>
 where
     Locker: Lockable + Send + Sync,
     for<'a> Locker::GuardMut<'a>: Send + Unpin,
     Wakers: Lockable + Send + Sync,
-    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = VecDeque<Waker>>,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
 {
-    type GuardMut<'a>= AsyncLockableMakerGuard<'a, Locker, Wakers>
+    type GuardMut<'a> = AsyncLockableMakerGuard<'a, Locker, Wakers>
     where
         Self: 'a;
 
@@ -41,7 +197,10 @@ where
         Self: 'a;
 
     fn lock(&self) -> Self::GuardMutFuture<'_> {
-        AsyncLockableMakerFuture { locker: self }
+        AsyncLockableMakerFuture {
+            locker: self,
+            id: None,
+        }
     }
 
     fn unlock<'a>(guard: Self::GuardMut<'a>) -> &'a Self {
@@ -58,7 +217,7 @@ pub struct AsyncLockableMakerGuard<'a, Locker, Wakers>
 where
     Locker: Lockable,
     Wakers: Lockable,
-    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = VecDeque<Waker>>,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
 {
     locker: &'a AsyncLockableMaker<Locker, Wakers>,
     inner_guard: Option<Locker::GuardMut<'a>>,
@@ -69,7 +228,7 @@ where
     Locker: Lockable + Send + Sync,
     for<'b> Locker::GuardMut<'b>: Send + Unpin,
     Wakers: Lockable + Send + Sync,
-    for<'c> Wakers::GuardMut<'c>: DerefMut<Target = VecDeque<Waker>>,
+    for<'c> Wakers::GuardMut<'c>: DerefMut<Target = WaitQueue>,
 {
     type Locker = AsyncLockableMaker<Locker, Wakers>;
 }
@@ -79,7 +238,7 @@ where
     Locker: Lockable,
     for<'c> Locker::GuardMut<'c>: ops::Deref<Target = T>,
     Wakers: Lockable,
-    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = VecDeque<Waker>>,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
 {
     type Target = T;
 
@@ -93,7 +252,7 @@ where
     Locker: Lockable,
     for<'c> Locker::GuardMut<'c>: ops::DerefMut<Target = T>,
     Wakers: Lockable,
-    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = VecDeque<Waker>>,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.inner_guard.as_deref_mut().unwrap()
@@ -104,15 +263,15 @@ impl<'a, Locker, Wakers> Drop for AsyncLockableMakerGuard<'a, Locker, Wakers>
 where
     Locker: Lockable,
     Wakers: Lockable,
-    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = VecDeque<Waker>>,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
 {
     fn drop(&mut self) {
         if let Some(guard) = self.inner_guard.take() {
             drop(guard);
 
-            if let Some(wake) = self.locker.wakers.lock().pop_front() {
-                wake.wake();
-            }
+            let wakers = self.locker.wakers.lock().unlock();
+
+            wake_all(wakers);
         }
     }
 }
@@ -121,34 +280,47 @@ where
 pub struct AsyncLockableMakerFuture<'a, Locker, Wakers>
 where
     Locker: Lockable,
+    Wakers: Lockable,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
 {
     locker: &'a AsyncLockableMaker<Locker, Wakers>,
+    /// The id of waiting task, `None` if not yet waiting.
+    id: Option<usize>,
 }
 
 impl<'a, Locker, Wakers> std::future::Future for AsyncLockableMakerFuture<'a, Locker, Wakers>
 where
     Locker: Lockable,
     Wakers: Lockable,
-    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = VecDeque<Waker>>,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
 {
     type Output = AsyncLockableMakerGuard<'a, Locker, Wakers>;
 
     fn poll(
-        self: std::pin::Pin<&mut Self>,
+        mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        let mut wakers = self.locker.wakers.lock();
+        let locker = self.locker;
 
-        match self.locker.inner_locker.try_lock() {
-            Some(guard) => std::task::Poll::Ready(AsyncLockableMakerGuard {
-                locker: self.locker,
-                inner_guard: Some(guard),
-            }),
-            None => {
-                wakers.push_back(cx.waker().clone());
+        if locker.wakers.lock().poll_wait(&mut self.id, cx.waker()) {
+            return std::task::Poll::Ready(locker.guard());
+        }
 
-                std::task::Poll::Pending
-            }
+        std::task::Poll::Pending
+    }
+}
+
+impl<'a, Locker, Wakers> Drop for AsyncLockableMakerFuture<'a, Locker, Wakers>
+where
+    Locker: Lockable,
+    Wakers: Lockable,
+    for<'b> Wakers::GuardMut<'b>: DerefMut<Target = WaitQueue>,
+{
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            let wakers = self.locker.wakers.lock().cancel(id);
+
+            wake_all(wakers);
         }
     }
 }
@@ -0,0 +1,242 @@
+use crate::{
+    maker::{AsyncLockableMaker, WaitQueue},
+    Lockable, LockableNew,
+};
+
+impl<T> LockableNew for parking_lot::Mutex<T> {
+    type Value = T;
+
+    fn new(value: T) -> Self {
+        parking_lot::Mutex::new(value)
+    }
+}
+
+impl<T> Lockable for parking_lot::Mutex<T> {
+    type GuardMut<'a> = parking_lot::MutexGuard<'a, T>
+    where
+        Self: 'a;
+
+    fn lock(&self) -> Self::GuardMut<'_> {
+        parking_lot::Mutex::lock(self)
+    }
+
+    fn try_lock(&self) -> Option<Self::GuardMut<'_>> {
+        parking_lot::Mutex::try_lock(self)
+    }
+
+    fn unlock(guard: Self::GuardMut<'_>) -> &Self {
+        let locker = parking_lot::MutexGuard::mutex(&guard);
+
+        drop(guard);
+
+        locker
+    }
+}
+
+/// Futures-aware mutex type based on [`parking_lot::Mutex`], the lock is handed over to the waiting tasks in FIFO order.
+pub type AsyncMutex<T> = AsyncLockableMaker<parking_lot::Mutex<T>, parking_lot::Mutex<WaitQueue>>;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    use futures::{
+        executor::ThreadPool,
+        task::{noop_waker_ref, SpawnExt},
+    };
+
+    use crate::{AsyncLockable, AsyncMutex};
+
+    #[futures_test::test]
+    async fn test_async_mutex() {
+        let loops = 1000;
+
+        let pool = ThreadPool::builder().pool_size(10).create().unwrap();
+
+        let shared = Arc::new(AsyncMutex::new(0));
+
+        let mut join_handles = vec![];
+
+        for _ in 0..loops {
+            let shared = shared.clone();
+
+            join_handles.push(
+                pool.spawn_with_handle(async move {
+                    for _ in 0..loops {
+                        let mut data = shared.lock().await;
+
+                        *data += 1;
+
+                        AsyncMutex::unlock(data);
+                    }
+                })
+                .unwrap(),
+            );
+        }
+
+        for join in join_handles {
+            join.await
+        }
+
+        assert_eq!(*shared.lock().await, loops * loops);
+    }
+
+    #[futures_test::test]
+    async fn test_try_lock() {
+        let shared = AsyncMutex::new(0);
+
+        let guard = shared.try_lock().expect("unlocked");
+
+        assert!(shared.try_lock().is_none());
+
+        drop(guard);
+
+        assert!(shared.try_lock().is_some());
+    }
+
+    #[test]
+    fn test_fifo_fairness() {
+        let shared = AsyncMutex::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let guard = shared.try_lock().unwrap();
+
+        let mut first = Box::pin(shared.lock());
+        let mut second = Box::pin(shared.lock());
+
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+
+        drop(guard);
+
+        // Neither `try_lock` nor a fresh `lock` overtakes the waiting tasks.
+        assert!(shared.try_lock().is_none());
+
+        let mut later = Box::pin(shared.lock());
+
+        assert!(later.as_mut().poll(&mut cx).is_pending());
+
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+
+        let Poll::Ready(first_guard) = first.as_mut().poll(&mut cx) else {
+            panic!("the lock is not handed over to the front task");
+        };
+
+        drop(first_guard);
+
+        assert!(later.as_mut().poll(&mut cx).is_pending());
+
+        let Poll::Ready(second_guard) = second.as_mut().poll(&mut cx) else {
+            panic!("the lock is not handed over in FIFO order");
+        };
+
+        drop(second_guard);
+
+        assert!(later.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn test_repoll() {
+        let shared = AsyncMutex::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let guard = shared.try_lock().unwrap();
+
+        let mut lock = Box::pin(shared.lock());
+
+        for _ in 0..3 {
+            assert!(lock.as_mut().poll(&mut cx).is_pending());
+        }
+
+        drop(guard);
+
+        assert!(lock.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn test_cancel_waiting() {
+        let shared = AsyncMutex::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let guard = shared.try_lock().unwrap();
+
+        let mut cancelled = Box::pin(shared.lock());
+
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+
+        let mut lock = Box::pin(shared.lock());
+
+        assert!(lock.as_mut().poll(&mut cx).is_pending());
+
+        drop(cancelled);
+
+        drop(guard);
+
+        // The cancelled task doesn't swallow the wakeup of the task queued behind it.
+        assert!(lock.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn test_cancel_granted() {
+        let shared = AsyncMutex::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let guard = shared.try_lock().unwrap();
+
+        let mut cancelled = Box::pin(shared.lock());
+
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+
+        let mut lock = Box::pin(shared.lock());
+
+        assert!(lock.as_mut().poll(&mut cx).is_pending());
+
+        // Hands the lock over to `cancelled`, which is dropped without taking it.
+        drop(guard);
+        drop(cancelled);
+
+        let Poll::Ready(lock_guard) = lock.as_mut().poll(&mut cx) else {
+            panic!("the granted lock is not forwarded");
+        };
+
+        assert!(shared.try_lock().is_none());
+
+        drop(lock_guard);
+
+        assert!(shared.try_lock().is_some());
+    }
+
+    #[test]
+    fn test_poll_lock() {
+        let shared = AsyncMutex::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let guard = shared.try_lock().unwrap();
+
+        let mut lock = Box::pin(shared.lock());
+
+        assert!(lock.as_mut().poll(&mut cx).is_pending());
+
+        drop(guard);
+
+        // `poll_lock` doesn't overtake the waiting task either.
+        assert!(shared.poll_lock(&mut cx).is_pending());
+
+        let Poll::Ready(lock_guard) = lock.as_mut().poll(&mut cx) else {
+            panic!("the lock is not handed over to the waiting task");
+        };
+
+        drop(lock_guard);
+
+        assert!(shared.poll_lock(&mut cx).is_ready());
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    ops::DerefMut,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{LocalMutex, Lockable, LockableNew};
+
+/// The internal state of [`NotifyMaker`].
+#[derive(Debug, Default)]
+pub struct RawNotify {
+    /// The notification stored by `notify_one` when there is no waiting task.
+    permit: bool,
+    /// The waiting tasks in FIFO order.
+    waiters: VecDeque<(usize, Waker)>,
+    /// The notified tasks, the value is true if notified by `notify_one`.
+    notified: HashMap<usize, bool>,
+    /// The id of next waiting task.
+    next_id: usize,
+}
+
+impl RawNotify {
+    fn notify_one(&mut self) {
+        match self.waiters.pop_front() {
+            Some((id, waker)) => {
+                self.notified.insert(id, true);
+                waker.wake();
+            }
+            None => self.permit = true,
+        }
+    }
+
+    fn notify_waiters(&mut self) {
+        for (id, waker) in self.waiters.drain(..) {
+            self.notified.insert(id, false);
+            waker.wake();
+        }
+    }
+}
+
+/// Type factory of futures-aware notification primitive.
+///
+/// Use [`Notify`] to share between threads, or [`LocalNotify`] in single thread mode.
+pub struct NotifyMaker<Locker> {
+    raw: Locker,
+}
+
+impl<Locker> Default for NotifyMaker<Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawNotify>,
+    for<'a> Locker::GuardMut<'a>: DerefMut<Target = RawNotify>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Locker> NotifyMaker<Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawNotify>,
+    for<'a> Locker::GuardMut<'a>: DerefMut<Target = RawNotify>,
+{
+    /// Create new notify without stored notification.
+    pub fn new() -> Self {
+        Self {
+            raw: Locker::new(Default::default()),
+        }
+    }
+
+    /// Notifies the first waiting task.
+    ///
+    /// If there is no waiting task, the notification is stored and consumed by the next [`notified`](Self::notified) call.
+    /// At most one notification is stored.
+    pub fn notify_one(&self) {
+        self.raw.lock().notify_one();
+    }
+
+    /// Notifies all waiting tasks, the notification is not stored if there is no waiting task.
+    pub fn notify_waiters(&self) {
+        self.raw.lock().notify_waiters();
+    }
+
+    /// Waits for a notification.
+    ///
+    /// The returned future is registered as a waiting task when it's polled for the first time.
+    pub fn notified(&self) -> Notified<'_, Locker> {
+        Notified {
+            notify: self,
+            id: None,
+        }
+    }
+}
+
+/// Future created by [`notified`](NotifyMaker::notified) function.
+pub struct Notified<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawNotify>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawNotify>,
+{
+    notify: &'a NotifyMaker<Locker>,
+    /// The id of waiting task, `None` if not yet waiting.
+    id: Option<usize>,
+}
+
+impl<'a, Locker> Future for Notified<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawNotify>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawNotify>,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let notify = self.notify;
+
+        let mut raw = notify.raw.lock();
+
+        match self.id {
+            Some(id) if raw.notified.remove(&id).is_some() => {
+                drop(raw);
+
+                self.id = None;
+
+                Poll::Ready(())
+            }
+            Some(id) => {
+                // Update the waker of this task.
+                if let Some(waiter) = raw.waiters.iter_mut().find(|(i, _)| *i == id) {
+                    waiter.1 = cx.waker().clone();
+                }
+
+                Poll::Pending
+            }
+            None if raw.permit => {
+                raw.permit = false;
+
+                Poll::Ready(())
+            }
+            None => {
+                let id = raw.next_id;
+
+                raw.next_id = raw.next_id.wrapping_add(1);
+                raw.waiters.push_back((id, cx.waker().clone()));
+
+                drop(raw);
+
+                self.id = Some(id);
+
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<'a, Locker> Drop for Notified<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawNotify>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawNotify>,
+{
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+
+        let mut raw = self.notify.raw.lock();
+
+        match raw.notified.remove(&id) {
+            // Passes the unconsumed `notify_one` notification to the next waiting task.
+            Some(true) => raw.notify_one(),
+            Some(false) => {}
+            None => raw.waiters.retain(|(i, _)| *i != id),
+        }
+    }
+}
+
+/// Futures-aware notification type based on [`parking_lot::Mutex`], which can be shared between threads.
+pub type Notify = NotifyMaker<parking_lot::Mutex<RawNotify>>;
+
+/// Futures-aware notification type for single thread mode, based on [`LocalMutex`].
+pub type LocalNotify = NotifyMaker<LocalMutex<RawNotify>>;
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, task::Poll};
+
+    use futures::{executor::ThreadPool, future::poll_fn, task::SpawnExt, FutureExt};
+
+    use super::*;
+
+    #[futures_test::test]
+    async fn test_notify_one() {
+        let pool = ThreadPool::builder().pool_size(2).create().unwrap();
+
+        let notify = Arc::new(Notify::new());
+
+        // The notification is stored if there is no waiting task.
+        notify.notify_one();
+        notify.notified().await;
+
+        let notify_cloned = notify.clone();
+
+        let join = pool
+            .spawn_with_handle(async move {
+                notify_cloned.notified().await;
+            })
+            .unwrap();
+
+        // The spawned task is notified whether it's waiting or not.
+        notify.notify_one();
+
+        join.await;
+    }
+
+    #[futures_test::test]
+    async fn test_notify_waiters() {
+        let notify = LocalNotify::new();
+
+        let mut first = notify.notified();
+        let mut second = notify.notified();
+
+        assert!(poll_fn(|cx| Poll::Ready(first.poll_unpin(cx).is_pending())).await);
+        assert!(poll_fn(|cx| Poll::Ready(second.poll_unpin(cx).is_pending())).await);
+
+        notify.notify_waiters();
+
+        first.await;
+        second.await;
+
+        // `notify_waiters` doesn't store notification.
+        let mut third = notify.notified();
+
+        assert!(poll_fn(|cx| Poll::Ready(third.poll_unpin(cx).is_pending())).await);
+    }
+
+    #[futures_test::test]
+    async fn test_cancel() {
+        let notify = LocalNotify::new();
+
+        let mut first = notify.notified();
+        let mut second = notify.notified();
+
+        assert!(poll_fn(|cx| Poll::Ready(first.poll_unpin(cx).is_pending())).await);
+        assert!(poll_fn(|cx| Poll::Ready(second.poll_unpin(cx).is_pending())).await);
+
+        notify.notify_one();
+
+        // The notification is passed to the second task.
+        drop(first);
+
+        second.await;
+    }
+}
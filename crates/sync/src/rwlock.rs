@@ -0,0 +1,561 @@
+use std::{
+    cell::UnsafeCell,
+    collections::{HashSet, VecDeque},
+    ops,
+    task::{Poll, Waker},
+};
+
+use crate::{AsyncGuardMut, AsyncLockable, Lockable, LockableNew, SpinMutex};
+
+/// Waiting task kind of [`AsyncRwLock`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Default)]
+struct RawRwLockState {
+    /// The count of alive read guards, including the granted but not yet taken ones.
+    readers: usize,
+    /// True if one write guard is alive, or the write access is granted but not yet taken.
+    writer: bool,
+    /// FIFO queue of waiting tasks, tuple (id, access, waker).
+    waiters: VecDeque<(usize, Access, Waker)>,
+    /// The waiting tasks whose access has been granted but not yet taken.
+    granted: HashSet<usize>,
+    /// The id of next waiting task.
+    next_id: usize,
+}
+
+impl RawRwLockState {
+    fn try_read(&mut self) -> bool {
+        // Nobody is allowed to overtake the waiting tasks.
+        if self.writer || !self.waiters.is_empty() {
+            return false;
+        }
+
+        self.readers += 1;
+
+        true
+    }
+
+    fn try_write(&mut self) -> bool {
+        if self.writer || self.readers > 0 || !self.waiters.is_empty() {
+            return false;
+        }
+
+        self.writer = true;
+
+        true
+    }
+
+    /// Grants the access to the front writer, or all readers queued before the next writer.
+    ///
+    /// Returns the wakers of granted tasks, which should be woken after the state is unlocked.
+    #[must_use]
+    fn grant(&mut self) -> Vec<Waker> {
+        let mut wakers = vec![];
+
+        while let Some((_, access, _)) = self.waiters.front() {
+            match access {
+                Access::Write if self.writer || self.readers > 0 => break,
+                Access::Write => self.writer = true,
+                Access::Read if self.writer => break,
+                Access::Read => self.readers += 1,
+            }
+
+            let (id, _, waker) = self.waiters.pop_front().unwrap();
+
+            self.granted.insert(id);
+
+            wakers.push(waker);
+        }
+
+        wakers
+    }
+
+    /// Polls the access of waiting task `id`, or enqueues a new waiting task if `id` is `None`.
+    fn poll_access(&mut self, id: &mut Option<usize>, access: Access, waker: &Waker) -> bool {
+        match *id {
+            Some(key) if self.granted.remove(&key) => {
+                *id = None;
+
+                true
+            }
+            Some(key) => {
+                // Update the waker of this task.
+                if let Some(waiter) = self.waiters.iter_mut().find(|(i, _, _)| *i == key) {
+                    waiter.2 = waker.clone();
+                }
+
+                false
+            }
+            None => {
+                let acquired = match access {
+                    Access::Read => self.try_read(),
+                    Access::Write => self.try_write(),
+                };
+
+                if !acquired {
+                    let key = self.next_id;
+
+                    self.next_id = self.next_id.wrapping_add(1);
+                    self.waiters.push_back((key, access, waker.clone()));
+
+                    *id = Some(key);
+                }
+
+                acquired
+            }
+        }
+    }
+
+    /// Removes the waiting task `id`, and forwards the access to the next waiting tasks if granted.
+    #[must_use]
+    fn cancel(&mut self, id: usize, access: Access) -> Vec<Waker> {
+        if self.granted.remove(&id) {
+            match access {
+                Access::Read => self.readers -= 1,
+                Access::Write => self.writer = false,
+            }
+        } else {
+            self.waiters.retain(|(i, _, _)| *i != id);
+        }
+
+        // The next waiting tasks may be satisfied after this one is removed.
+        self.grant()
+    }
+}
+
+/// Wakes the granted tasks outside the state lock, the woken task may run on another thread at once.
+fn wake_all(wakers: Vec<Waker>) {
+    for waker in wakers {
+        waker.wake();
+    }
+}
+
+/// A futures-aware reader-writer lock, waiting tasks are woken in FIFO order.
+pub struct AsyncRwLock<T> {
+    state: SpinMutex<RawRwLockState>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for AsyncRwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for AsyncRwLock<T> {}
+
+impl<T: Default> Default for AsyncRwLock<T> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T> AsyncRwLock<T> {
+    /// Creates a new reader-writer lock in an unlocked state ready for use.
+    pub fn new(value: T) -> Self {
+        Self {
+            state: SpinMutex::new(Default::default()),
+            data: value.into(),
+        }
+    }
+
+    /// Locks this rwlock with shared read access, suspending the current task until it can be acquired.
+    pub fn read(&self) -> AsyncRwLockReadFuture<'_, T> {
+        AsyncRwLockReadFuture {
+            locker: self,
+            id: None,
+        }
+    }
+
+    /// Locks this rwlock with exclusive write access, suspending the current task until it can be acquired.
+    pub fn write(&self) -> AsyncRwLockWriteFuture<'_, T> {
+        AsyncRwLockWriteFuture {
+            locker: self,
+            id: None,
+        }
+    }
+
+    /// Attempts to acquire this rwlock with shared read access.
+    ///
+    /// If the access could not be granted at this time, then `None` is returned.
+    pub fn try_read(&self) -> Option<AsyncRwLockReadGuard<'_, T>> {
+        if self.state.lock().try_read() {
+            Some(AsyncRwLockReadGuard { locker: self })
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to acquire this rwlock with exclusive write access.
+    ///
+    /// If the access could not be granted at this time, then `None` is returned.
+    pub fn try_write(&self) -> Option<AsyncRwLockWriteGuard<'_, T>> {
+        if self.state.lock().try_write() {
+            Some(AsyncRwLockWriteGuard { locker: self })
+        } else {
+            None
+        }
+    }
+}
+
+/// RAII structure used to release the shared read access of a [`AsyncRwLock`] when dropped.
+pub struct AsyncRwLockReadGuard<'a, T> {
+    locker: &'a AsyncRwLock<T>,
+}
+
+// Safe to send since we don't track any thread-specific details
+unsafe impl<'a, T: Sync> Send for AsyncRwLockReadGuard<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for AsyncRwLockReadGuard<'a, T> {}
+
+impl<'a, T> ops::Deref for AsyncRwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.locker.data.get() }
+    }
+}
+
+impl<'a, T> Drop for AsyncRwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        let wakers = {
+            let mut state = self.locker.state.lock();
+
+            state.readers -= 1;
+
+            if state.readers == 0 {
+                state.grant()
+            } else {
+                vec![]
+            }
+        };
+
+        wake_all(wakers);
+    }
+}
+
+/// RAII structure used to release the exclusive write access of a [`AsyncRwLock`] when dropped.
+pub struct AsyncRwLockWriteGuard<'a, T> {
+    locker: &'a AsyncRwLock<T>,
+}
+
+// Safe to send since we don't track any thread-specific details
+unsafe impl<'a, T: Send> Send for AsyncRwLockWriteGuard<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for AsyncRwLockWriteGuard<'a, T> {}
+
+impl<'a, T> ops::Deref for AsyncRwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.locker.data.get() }
+    }
+}
+
+impl<'a, T> ops::DerefMut for AsyncRwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.locker.data.get() }
+    }
+}
+
+impl<'a, T> Drop for AsyncRwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        let wakers = {
+            let mut state = self.locker.state.lock();
+
+            state.writer = false;
+
+            state.grant()
+        };
+
+        wake_all(wakers);
+    }
+}
+
+impl<'a, T: Send> AsyncGuardMut<'a> for AsyncRwLockWriteGuard<'a, T> {
+    type Locker = AsyncRwLock<T>;
+}
+
+/// Future created by [`read`](AsyncRwLock::read) function.
+pub struct AsyncRwLockReadFuture<'a, T> {
+    locker: &'a AsyncRwLock<T>,
+    /// The id of waiting task, `None` if not yet waiting.
+    id: Option<usize>,
+}
+
+impl<'a, T> std::future::Future for AsyncRwLockReadFuture<'a, T> {
+    type Output = AsyncRwLockReadGuard<'a, T>;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Self::Output> {
+        let locker = self.locker;
+
+        if locker
+            .state
+            .lock()
+            .poll_access(&mut self.id, Access::Read, cx.waker())
+        {
+            return Poll::Ready(AsyncRwLockReadGuard { locker });
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<'a, T> Drop for AsyncRwLockReadFuture<'a, T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            let wakers = self.locker.state.lock().cancel(id, Access::Read);
+
+            wake_all(wakers);
+        }
+    }
+}
+
+/// Future created by [`write`](AsyncRwLock::write) function.
+pub struct AsyncRwLockWriteFuture<'a, T> {
+    locker: &'a AsyncRwLock<T>,
+    /// The id of waiting task, `None` if not yet waiting.
+    id: Option<usize>,
+}
+
+impl<'a, T> std::future::Future for AsyncRwLockWriteFuture<'a, T> {
+    type Output = AsyncRwLockWriteGuard<'a, T>;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Self::Output> {
+        let locker = self.locker;
+
+        if locker
+            .state
+            .lock()
+            .poll_access(&mut self.id, Access::Write, cx.waker())
+        {
+            return Poll::Ready(AsyncRwLockWriteGuard { locker });
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<'a, T> Drop for AsyncRwLockWriteFuture<'a, T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            let wakers = self.locker.state.lock().cancel(id, Access::Write);
+
+            wake_all(wakers);
+        }
+    }
+}
+
+unsafe impl<'a, T: Send> Send for AsyncRwLockWriteFuture<'a, T> {}
+
+/// The `lock` function of [`AsyncLockable`] acquires the exclusive write access.
+impl<T: Send> AsyncLockable for AsyncRwLock<T> {
+    type GuardMut<'a> = AsyncRwLockWriteGuard<'a, T>
+    where
+        Self: 'a;
+
+    type GuardMutFuture<'a> = AsyncRwLockWriteFuture<'a, T>
+    where
+        Self: 'a;
+
+    fn lock(&self) -> Self::GuardMutFuture<'_> {
+        self.write()
+    }
+
+    fn unlock<'a>(guard: Self::GuardMut<'a>) -> &'a Self {
+        let locker = guard.locker;
+
+        drop(guard);
+
+        locker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    use futures::{
+        executor::ThreadPool,
+        task::{noop_waker_ref, SpawnExt},
+    };
+
+    use crate::{AsyncRwLock, Lockable};
+
+    #[futures_test::test]
+    async fn test_async_rwlock() {
+        let loops = 1000;
+
+        let pool = ThreadPool::builder().pool_size(10).create().unwrap();
+
+        let shared = Arc::new(AsyncRwLock::new(0));
+
+        let mut join_handles = vec![];
+
+        for _ in 0..loops {
+            let shared = shared.clone();
+
+            join_handles.push(
+                pool.spawn_with_handle(async move {
+                    for _ in 0..loops {
+                        let value = *shared.read().await;
+
+                        let mut data = shared.write().await;
+
+                        assert!(*data >= value);
+
+                        *data += 1;
+                    }
+                })
+                .unwrap(),
+            );
+        }
+
+        for join in join_handles {
+            join.await
+        }
+
+        assert_eq!(*shared.read().await, loops * loops);
+    }
+
+    #[futures_test::test]
+    async fn test_try_read_write() {
+        let shared = AsyncRwLock::new(0);
+
+        let read1 = shared.try_read().expect("unlocked");
+        let read2 = shared.try_read().expect("shared read access");
+
+        assert!(shared.try_write().is_none());
+
+        drop(read1);
+        drop(read2);
+
+        let write = shared.try_write().expect("unlocked");
+
+        assert!(shared.try_read().is_none());
+
+        drop(write);
+
+        assert!(shared.try_read().is_some());
+    }
+
+    #[test]
+    fn test_fifo_fairness() {
+        let shared = AsyncRwLock::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let read = shared.try_read().unwrap();
+
+        let mut write = Box::pin(shared.write());
+
+        assert!(write.as_mut().poll(&mut cx).is_pending());
+
+        // Neither readers nor writers overtake the waiting writer.
+        assert!(shared.try_read().is_none());
+        assert!(shared.try_write().is_none());
+
+        let mut later_read = Box::pin(shared.read());
+
+        assert!(later_read.as_mut().poll(&mut cx).is_pending());
+
+        drop(read);
+
+        let Poll::Ready(write_guard) = write.as_mut().poll(&mut cx) else {
+            panic!("write access is not granted");
+        };
+
+        assert!(later_read.as_mut().poll(&mut cx).is_pending());
+
+        drop(write_guard);
+
+        assert!(later_read.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn test_repoll() {
+        let shared = AsyncRwLock::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let write = shared.try_write().unwrap();
+
+        let mut read = Box::pin(shared.read());
+
+        for _ in 0..3 {
+            assert!(read.as_mut().poll(&mut cx).is_pending());
+        }
+
+        assert_eq!(shared.state.lock().waiters.len(), 1);
+
+        drop(write);
+
+        assert!(read.as_mut().poll(&mut cx).is_ready());
+
+        assert!(shared.state.lock().waiters.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_waiting() {
+        let shared = AsyncRwLock::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let write = shared.try_write().unwrap();
+
+        let mut cancelled = Box::pin(shared.write());
+
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+
+        let mut read = Box::pin(shared.read());
+
+        assert!(read.as_mut().poll(&mut cx).is_pending());
+
+        drop(cancelled);
+
+        drop(write);
+
+        // The cancelled writer doesn't block the readers queued behind it.
+        assert!(read.as_mut().poll(&mut cx).is_ready());
+        assert!(shared.try_read().is_some());
+    }
+
+    #[test]
+    fn test_cancel_granted() {
+        let shared = AsyncRwLock::new(0);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let write = shared.try_write().unwrap();
+
+        let mut cancelled = Box::pin(shared.write());
+
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+
+        let mut read = Box::pin(shared.read());
+
+        assert!(read.as_mut().poll(&mut cx).is_pending());
+
+        // Grants the write access to `cancelled`, which is dropped without taking it.
+        drop(write);
+        drop(cancelled);
+
+        let Poll::Ready(read_guard) = read.as_mut().poll(&mut cx) else {
+            panic!("the granted access is not forwarded");
+        };
+
+        assert!(shared.try_write().is_none());
+
+        drop(read_guard);
+
+        assert!(shared.try_write().is_some());
+    }
+}
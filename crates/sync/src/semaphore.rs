@@ -0,0 +1,381 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    future::Future,
+    ops::DerefMut,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{LocalMutex, Lockable, LockableNew};
+
+/// The internal state of [`SemaphoreMaker`].
+#[derive(Debug, Default)]
+pub struct RawSemaphore {
+    /// The number of available permits.
+    permits: usize,
+    /// The waiting tasks in FIFO order, tuple (id, requested permits, waker).
+    waiters: VecDeque<(usize, usize, Waker)>,
+    /// The waiting tasks whose permits have been assigned but not yet taken.
+    assigned: HashSet<usize>,
+    /// The id of next waiting task.
+    next_id: usize,
+}
+
+impl RawSemaphore {
+    fn new(permits: usize) -> Self {
+        Self {
+            permits,
+            ..Default::default()
+        }
+    }
+
+    /// Assigns the available permits to the waiting tasks in FIFO order.
+    fn assign(&mut self) {
+        while let Some((_, n, _)) = self.waiters.front() {
+            if *n > self.permits {
+                break;
+            }
+
+            let (id, n, waker) = self.waiters.pop_front().unwrap();
+
+            self.permits -= n;
+            self.assigned.insert(id);
+
+            waker.wake();
+        }
+    }
+
+    fn release(&mut self, n: usize) {
+        self.permits += n;
+
+        self.assign();
+    }
+}
+
+/// Type factory of futures-aware semaphore, the permits are assigned to the waiting tasks in FIFO order.
+///
+/// Use [`Semaphore`] to share between threads, or [`LocalSemaphore`] in single thread mode.
+pub struct SemaphoreMaker<Locker> {
+    raw: Locker,
+}
+
+impl<Locker> SemaphoreMaker<Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'a> Locker::GuardMut<'a>: DerefMut<Target = RawSemaphore>,
+{
+    /// Create new semaphore with the initial number of `permits`.
+    pub fn new(permits: usize) -> Self {
+        Self {
+            raw: Locker::new(RawSemaphore::new(permits)),
+        }
+    }
+
+    /// Returns the number of available permits.
+    pub fn available_permits(&self) -> usize {
+        self.raw.lock().permits
+    }
+
+    /// Adds `n` new permits to this semaphore.
+    pub fn add_permits(&self, n: usize) {
+        self.raw.lock().release(n);
+    }
+
+    /// Attempts to acquire `n` permits without suspending the current task.
+    ///
+    /// Returns `None` if there are not enough permits, or other tasks are waiting for permits.
+    pub fn try_acquire(&self, n: usize) -> Option<SemaphorePermit<'_, Locker>> {
+        let mut raw = self.raw.lock();
+
+        if !raw.waiters.is_empty() || raw.permits < n {
+            return None;
+        }
+
+        raw.permits -= n;
+
+        Some(SemaphorePermit {
+            semaphore: self,
+            permits: n,
+        })
+    }
+
+    /// Acquires `n` permits asynchronously, the permits are released when the returned guard is dropped.
+    pub fn acquire(&self, n: usize) -> SemaphoreAcquire<'_, Locker> {
+        SemaphoreAcquire {
+            semaphore: self,
+            permits: n,
+            id: None,
+        }
+    }
+
+    /// Acquires `n` permits asynchronously, and returns the guard which owns the semaphore.
+    pub async fn acquire_owned(self: Arc<Self>, n: usize) -> OwnedSemaphorePermit<Locker> {
+        self.acquire(n).await.forget();
+
+        OwnedSemaphorePermit {
+            semaphore: self,
+            permits: n,
+        }
+    }
+}
+
+/// Future created by [`acquire`](SemaphoreMaker::acquire) function.
+pub struct SemaphoreAcquire<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    semaphore: &'a SemaphoreMaker<Locker>,
+    permits: usize,
+    /// The id of waiting task, `None` if not yet waiting.
+    id: Option<usize>,
+}
+
+impl<'a, Locker> Future for SemaphoreAcquire<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    type Output = SemaphorePermit<'a, Locker>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let semaphore = self.semaphore;
+        let permits = self.permits;
+
+        let mut raw = semaphore.raw.lock();
+
+        let ready = match self.id {
+            Some(id) if raw.assigned.remove(&id) => true,
+            Some(id) => {
+                // Update the waker of this task.
+                if let Some(waiter) = raw.waiters.iter_mut().find(|(i, _, _)| *i == id) {
+                    waiter.2 = cx.waker().clone();
+                }
+
+                false
+            }
+            None if raw.waiters.is_empty() && raw.permits >= permits => {
+                raw.permits -= permits;
+                true
+            }
+            None => {
+                let id = raw.next_id;
+
+                raw.next_id = raw.next_id.wrapping_add(1);
+                raw.waiters.push_back((id, permits, cx.waker().clone()));
+
+                drop(raw);
+
+                self.id = Some(id);
+
+                return Poll::Pending;
+            }
+        };
+
+        if ready {
+            self.id = None;
+
+            return Poll::Ready(SemaphorePermit { semaphore, permits });
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<'a, Locker> Drop for SemaphoreAcquire<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+
+        let mut raw = self.semaphore.raw.lock();
+
+        if raw.assigned.remove(&id) {
+            // Returns the assigned permits which are never taken.
+            raw.release(self.permits);
+        } else {
+            raw.waiters.retain(|(i, _, _)| *i != id);
+
+            // The next waiting task may be satisfied after this one is removed.
+            raw.assign();
+        }
+    }
+}
+
+/// RAII guard of the permits acquired from [`SemaphoreMaker`], the permits are released on drop.
+pub struct SemaphorePermit<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    semaphore: &'a SemaphoreMaker<Locker>,
+    permits: usize,
+}
+
+impl<'a, Locker> SemaphorePermit<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    /// Returns the number of permits held by this guard.
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Forgets the permits without releasing them back to the semaphore.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl<'a, Locker> Drop for SemaphorePermit<'a, Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.raw.lock().release(self.permits);
+        }
+    }
+}
+
+/// RAII guard of the permits acquired by [`acquire_owned`](SemaphoreMaker::acquire_owned),
+/// which owns the semaphore and releases the permits on drop.
+pub struct OwnedSemaphorePermit<Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    semaphore: Arc<SemaphoreMaker<Locker>>,
+    permits: usize,
+}
+
+impl<Locker> OwnedSemaphorePermit<Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    /// Returns the number of permits held by this guard.
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Returns the semaphore from which the permits are acquired.
+    pub fn semaphore(&self) -> &Arc<SemaphoreMaker<Locker>> {
+        &self.semaphore
+    }
+}
+
+impl<Locker> Drop for OwnedSemaphorePermit<Locker>
+where
+    Locker: Lockable + LockableNew<Value = RawSemaphore>,
+    for<'b> Locker::GuardMut<'b>: DerefMut<Target = RawSemaphore>,
+{
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.semaphore.raw.lock().release(self.permits);
+        }
+    }
+}
+
+/// Futures-aware semaphore type based on [`parking_lot::Mutex`], which can be shared between threads.
+pub type Semaphore = SemaphoreMaker<parking_lot::Mutex<RawSemaphore>>;
+
+/// Futures-aware semaphore type for single thread mode, based on [`LocalMutex`].
+pub type LocalSemaphore = SemaphoreMaker<LocalMutex<RawSemaphore>>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use futures::{executor::ThreadPool, future::poll_fn, task::SpawnExt, FutureExt};
+
+    use super::*;
+
+    #[futures_test::test]
+    async fn test_semaphore() {
+        let pool = ThreadPool::builder().pool_size(10).create().unwrap();
+
+        let semaphore = Arc::new(Semaphore::new(3));
+
+        let running = Arc::new(AtomicUsize::new(0));
+
+        let mut join_handles = vec![];
+
+        for _ in 0..100 {
+            let semaphore = semaphore.clone();
+            let running = running.clone();
+
+            join_handles.push(
+                pool.spawn_with_handle(async move {
+                    let _permit = semaphore.acquire_owned(1).await;
+
+                    assert!(running.fetch_add(1, Ordering::SeqCst) < 3);
+
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+                .unwrap(),
+            );
+        }
+
+        for join in join_handles {
+            join.await
+        }
+
+        assert_eq!(semaphore.available_permits(), 3);
+    }
+
+    #[futures_test::test]
+    async fn test_fifo() {
+        let semaphore = LocalSemaphore::new(2);
+
+        let permit = semaphore.acquire(2).await;
+
+        let mut first = semaphore.acquire(2);
+        let mut second = semaphore.acquire(1);
+
+        assert!(poll_fn(|cx| Poll::Ready(first.poll_unpin(cx).is_pending())).await);
+        assert!(poll_fn(|cx| Poll::Ready(second.poll_unpin(cx).is_pending())).await);
+
+        drop(permit);
+
+        // The first waiting task takes all permits, even though the second one requests less.
+        assert!(semaphore.try_acquire(1).is_none());
+
+        let first = first.await;
+
+        assert!(poll_fn(|cx| Poll::Ready(second.poll_unpin(cx).is_pending())).await);
+
+        drop(first);
+
+        assert_eq!(second.await.permits(), 1);
+        assert_eq!(semaphore.available_permits(), 2);
+    }
+
+    #[futures_test::test]
+    async fn test_cancel() {
+        let semaphore = LocalSemaphore::new(1);
+
+        let permit = semaphore.try_acquire(1).unwrap();
+
+        let mut first = semaphore.acquire(1);
+
+        assert!(poll_fn(|cx| Poll::Ready(first.poll_unpin(cx).is_pending())).await);
+
+        drop(permit);
+
+        // The assigned permits are released when the waiting task is cancelled.
+        drop(first);
+
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+}
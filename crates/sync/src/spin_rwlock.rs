@@ -0,0 +1,255 @@
+use std::{
+    cell::UnsafeCell,
+    hint, ops,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{Lockable, LockableNew, RwLockable};
+
+/// The state bit of alive write guard.
+const WRITER: usize = 1 << (usize::BITS - 1);
+/// The state bit of waiting writer, which prevents new readers from acquiring the lock.
+const WRITER_WAITING: usize = 1 << (usize::BITS - 2);
+/// The state bits of alive read guards count.
+const READERS: usize = !(WRITER | WRITER_WAITING);
+
+/// A spin style reader-writer lock, the waiting writer takes precedence over new readers.
+///
+/// The [`lock`](Lockable::lock) function acquires the exclusive write access.
+pub struct SpinRwLock<T> {
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for SpinRwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for SpinRwLock<T> {}
+
+impl<T: Default> Default for SpinRwLock<T> {
+    fn default() -> Self {
+        Self::const_new(Default::default())
+    }
+}
+
+impl<T> LockableNew for SpinRwLock<T> {
+    type Value = T;
+
+    fn new(value: T) -> Self {
+        Self::const_new(value)
+    }
+}
+
+impl<T> SpinRwLock<T> {
+    /// Creates a new lock in an unlocked state, which can be used to initialize static variables.
+    pub const fn const_new(value: T) -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    fn try_acquire_read(&self, state: usize) -> Result<(), usize> {
+        if state & (WRITER | WRITER_WAITING) != 0 {
+            return Err(state);
+        }
+
+        assert!(state & READERS != READERS, "too many readers");
+
+        self.state
+            .compare_exchange_weak(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
+            .map(|_| ())
+    }
+
+    fn try_acquire_write(&self, state: usize) -> Result<(), usize> {
+        if state & !WRITER_WAITING != 0 {
+            return Err(state);
+        }
+
+        self.state
+            .compare_exchange_weak(state, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .map(|_| ())
+    }
+}
+
+impl<T> Lockable for SpinRwLock<T> {
+    type GuardMut<'a> = SpinRwLockWriteGuard<'a, T>
+    where
+        Self: 'a;
+
+    fn lock(&self) -> Self::GuardMut<'_> {
+        let mut state = self.state.load(Ordering::Relaxed);
+
+        while let Err(current) = self.try_acquire_write(state) {
+            if current & WRITER_WAITING == 0 {
+                self.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+            }
+
+            hint::spin_loop();
+
+            state = self.state.load(Ordering::Relaxed);
+        }
+
+        SpinRwLockWriteGuard { locker: self }
+    }
+
+    fn try_lock(&self) -> Option<Self::GuardMut<'_>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+
+        loop {
+            match self.try_acquire_write(state) {
+                Ok(_) => return Some(SpinRwLockWriteGuard { locker: self }),
+                // Spurious failure of `compare_exchange_weak`.
+                Err(current) if current & !WRITER_WAITING == 0 => state = current,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn unlock(guard: Self::GuardMut<'_>) -> &Self {
+        let locker = guard.locker;
+
+        drop(guard);
+
+        locker
+    }
+}
+
+impl<T> RwLockable for SpinRwLock<T> {
+    type GuardRef<'a> = SpinRwLockReadGuard<'a, T>
+    where
+        Self: 'a;
+
+    fn read(&self) -> Self::GuardRef<'_> {
+        let mut state = self.state.load(Ordering::Relaxed);
+
+        while self.try_acquire_read(state).is_err() {
+            hint::spin_loop();
+
+            state = self.state.load(Ordering::Relaxed);
+        }
+
+        SpinRwLockReadGuard { locker: self }
+    }
+
+    fn try_read(&self) -> Option<Self::GuardRef<'_>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+
+        loop {
+            match self.try_acquire_read(state) {
+                Ok(_) => return Some(SpinRwLockReadGuard { locker: self }),
+                Err(current) if current & (WRITER | WRITER_WAITING) == 0 => state = current,
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+/// RAII type that handle the shared read access of [`SpinRwLock`]
+pub struct SpinRwLockReadGuard<'a, T> {
+    locker: &'a SpinRwLock<T>,
+}
+
+unsafe impl<'a, T: Sync> Send for SpinRwLockReadGuard<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for SpinRwLockReadGuard<'a, T> {}
+
+impl<'a, T> ops::Deref for SpinRwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.locker.data.get() }
+    }
+}
+
+impl<'a, T> Drop for SpinRwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.locker.state.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// RAII type that handle the exclusive write access of [`SpinRwLock`]
+pub struct SpinRwLockWriteGuard<'a, T> {
+    locker: &'a SpinRwLock<T>,
+}
+
+unsafe impl<'a, T: Send> Send for SpinRwLockWriteGuard<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for SpinRwLockWriteGuard<'a, T> {}
+
+impl<'a, T> ops::Deref for SpinRwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.locker.data.get() }
+    }
+}
+
+impl<'a, T> ops::DerefMut for SpinRwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.locker.data.get() }
+    }
+}
+
+impl<'a, T> Drop for SpinRwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.locker.state.fetch_and(!WRITER, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use crate::{Lockable, LockableNew, RwLockable, SpinRwLock};
+
+    #[test]
+    fn test_spin_rwlock() {
+        let loops = 1000;
+
+        let shared = Arc::new(SpinRwLock::new(0));
+
+        let threads = (0..10)
+            .map(|_| {
+                let shared = shared.clone();
+
+                thread::spawn(move || {
+                    for _ in 0..loops {
+                        let value = *shared.read();
+
+                        let mut data = shared.lock();
+
+                        assert!(*data >= value);
+
+                        *data += 1;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(*shared.read(), loops * 10);
+    }
+
+    #[test]
+    fn test_try_read_write() {
+        let shared = SpinRwLock::new(0);
+
+        let read1 = shared.try_read().expect("unlocked");
+        let read2 = shared.try_read().expect("shared read access");
+
+        assert!(shared.try_lock().is_none());
+
+        drop(read1);
+        drop(read2);
+
+        let mut write = shared.try_lock().expect("unlocked");
+
+        assert!(shared.try_read().is_none());
+
+        *write = 1;
+
+        let shared = SpinRwLock::unlock(write);
+
+        assert_eq!(*shared.try_read().unwrap(), 1);
+    }
+}
@@ -1,12 +1,108 @@
 use proc_macro::TokenStream;
 use quote::{quote, quote_spanned};
-use syn::{parse_macro_input, spanned::Spanned, ItemFn, TypePath};
+use syn::{
+    parse::Parser, punctuated::Punctuated, spanned::Spanned, Expr, ExprLit, ItemFn, Lit, Meta,
+    Path, Token,
+};
 
+/// The default number of worker threads of `runtime = "multi"`.
+const DEFAULT_THREADS: usize = 2;
+
+/// The runtime selected by the attribute arguments.
+enum Runtime {
+    /// Calls the runner function, e.g. `#[hala_test::test(io_test)]`.
+    Runner(Path),
+    /// The isolated multi-thread runtime, e.g. `#[hala_test::test(threads = 4)]`.
+    Multi(usize),
+    /// The isolated current thread runtime, i.e. `#[hala_test::test(runtime = "local")]`.
+    Local,
+}
+
+fn parse_runtime(attr: TokenStream) -> syn::Result<Runtime> {
+    let args = Punctuated::<Meta, Token![,]>::parse_terminated.parse(attr)?;
+
+    let mut runner = None;
+    let mut local = None;
+    let mut threads = None;
+
+    for arg in &args {
+        match arg {
+            Meta::Path(path) if runner.is_none() && args.len() == 1 => {
+                runner = Some(path.clone());
+            }
+            Meta::NameValue(kv) if kv.path.is_ident("runtime") => match &kv.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) if s.value() == "local" || s.value() == "multi" => {
+                    local = Some(s.value() == "local");
+                }
+                value => {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "expect runtime = \"local\" or runtime = \"multi\"",
+                    ))
+                }
+            },
+            Meta::NameValue(kv) if kv.path.is_ident("threads") => match &kv.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(n), ..
+                }) => {
+                    let n = n.base10_parse::<usize>()?;
+
+                    if n == 0 {
+                        return Err(syn::Error::new(
+                            n.span(),
+                            "threads must be greater than zero",
+                        ));
+                    }
+
+                    threads = Some(n);
+                }
+                value => return Err(syn::Error::new(value.span(), "expect threads = <integer>")),
+            },
+            _ => {
+                return Err(syn::Error::new(
+                    arg.span(),
+                    "expect a runner path, or `runtime = \"local\" | \"multi\"` / `threads = N` options",
+                ))
+            }
+        }
+    }
+
+    if let Some(runner) = runner {
+        return Ok(Runtime::Runner(runner));
+    }
+
+    match (local, threads) {
+        (Some(true), Some(_)) => Err(syn::Error::new(
+            args.span(),
+            "`threads` can't be used with runtime = \"local\"",
+        )),
+        (Some(true), None) => Ok(Runtime::Local),
+        _ => Ok(Runtime::Multi(threads.unwrap_or(DEFAULT_THREADS))),
+    }
+}
+
+/// Runs the async test function with the runtime selected by the attribute arguments:
+///
+/// - `#[hala_test::test(io_test)]`, calls the runner function `io_test(label, test_fn)`.
+/// - `#[hala_test::test(threads = 4)]` / `#[hala_test::test(runtime = "multi")]`, runs with
+///   `hala_io::test::multi_thread_io_test`.
+/// - `#[hala_test::test(runtime = "local")]`, runs with `hala_io::test::current_thread_io_test`,
+///   the test future needn't be `Send`.
+///
+/// The runtimes selected by options create their own driver and poller for each test case.
 #[proc_macro_attribute]
 pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let runner_path = parse_macro_input!(attr as TypePath);
+    let runtime = match parse_runtime(attr) {
+        Ok(runtime) => runtime,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
-    let item_fn = parse_macro_input!(item as ItemFn);
+    let item_fn = match syn::parse::<ItemFn>(item) {
+        Ok(item_fn) => item_fn,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
     if item_fn.sig.asyncness.is_none() {
         return TokenStream::from(quote_spanned! { item_fn.span() =>
@@ -18,12 +114,24 @@ pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     let test_name = item_fn.sig.ident.to_string();
 
+    let run = match runtime {
+        Runtime::Runner(runner_path) => quote! {
+            #runner_path(#test_name,#fn_name);
+        },
+        Runtime::Multi(threads) => quote! {
+            ::hala_io::test::multi_thread_io_test(#threads,#test_name,#fn_name);
+        },
+        Runtime::Local => quote! {
+            ::hala_io::test::current_thread_io_test(#test_name,#fn_name);
+        },
+    };
+
     quote! {
         #[::core::prelude::v1::test]
         fn #fn_name() {
             #item_fn
 
-            #runner_path(#test_name,#fn_name);
+            #run
         }
     }
     .into()
@@ -1,11 +1,15 @@
+pub use hala_codec as codec;
 pub use hala_future as future;
 pub use hala_io as io;
 pub use hala_lockfree as lockfree;
 
 pub mod net {
+    pub use hala_h3 as h3;
+    pub use hala_mux as mux;
     pub use hala_quic as quic;
     pub use hala_tcp as tcp;
     pub use hala_udp as udp;
+    pub use hala_ws as ws;
 }
 
 pub use hala_sync as sync;